@@ -2,11 +2,13 @@ use clap::{App, Arg, ArgMatches};
 use anyhow::{Result, anyhow};
 use console::Style;
 use env_logger::{Builder, Env, Target};
-use romst::{RomsetMode, Romst, sysout::{DatImporterReporterSysOut, ReportReporterSysOut}};
+use romst::{ChecksumFormat, DeletePolicy, i18n::{Language, tr}, GameEdit, GameFilter, MatchPolicy, NameRules, PatternKind, RebuildMode, RegionPriorities, ReleaseFilter, ReportScope, ReportView, RomEdit, RomFilter, RomPolicies, RomPolicy, ScanLevel, ScanOptions, RomsetMode, Romst, SetLayout, SetListAction, SetListSource, Verbosity, WriteOptions, ZipFormat, sysout::{ColorMode, DatImporterReporterSysOut, ProgressFormat, ReportReporterSysOut, paged::{Page, PagedDisplay}}};
 use serde::Serialize;
-use std::{fmt::Display, path::Path, str::FromStr};
+use std::{fmt::Display, fs, io::{self, BufWriter, Write}, path::Path, str::FromStr, time::Duration};
 
 const DB_EXTENSION: &str = "rst";
+/// The lines of a page when only the page number is given
+const DEFAULT_PAGE_SIZE: usize = 100;
 
 enum OutputFormat {
     Json,
@@ -56,10 +58,155 @@ fn create_matches() -> ArgMatches {
         .default_value("json")
         .takes_value(true)
         .required(false);
+    let arg_games = Arg::new("games")
+        .about("A list of games")
+        .long("games")
+        .short('g')
+        .takes_value(true)
+        .multiple(true)
+        .required(true);
+
+    let arg_region = Arg::new("region")
+        .about("Only the games released in this region, from the release elements of No-Intro DATs (e.g. `USA`)")
+        .long("region")
+        .takes_value(true)
+        .required(false);
+    let arg_language = Arg::new("language")
+        .about("Only the games released in this language, from the release elements of No-Intro DATs (e.g. `En`)")
+        .long("language")
+        .takes_value(true)
+        .required(false);
+    let arg_prefer = Arg::new("prefer")
+        .about("Only one game of each parent/clone family (1G1R): the one of the first of these regions, comma separated (e.g. `USA,World,Europe`). The parent when none of them is found")
+        .long("prefer")
+        .takes_value(true)
+        .required(false);
+    let arg_prefer_language = Arg::new("prefer-language")
+        .about("The languages preferred when choosing the game of each family with --prefer, comma separated (e.g. `En,Fr`)")
+        .long("prefer-language")
+        .takes_value(true)
+        .requires("prefer")
+        .required(false);
+    let arg_tag = Arg::new("tag")
+        .about("The tag name")
+        .long("tag")
+        .short('t')
+        .takes_value(true)
+        .required(true);
+    let arg_page = Arg::new("page")
+        .about("Shows only this page of the result, in plain format, the first one is 1")
+        .long("page")
+        .takes_value(true)
+        .required(false);
+    let arg_page_size = Arg::new("page-size")
+        .about("The lines of each page")
+        .long("page-size")
+        .takes_value(true)
+        .requires("page")
+        .required(false);
+
+    let arg_edit_game = Arg::new("game")
+        .about("The name of the game")
+        .index(1)
+        .takes_value(true)
+        .required(true);
+    let arg_edit_rom = Arg::new("rom")
+        .about("The name of the rom")
+        .index(2)
+        .takes_value(true)
+        .required(true);
+    let arg_new_name = Arg::new("name")
+        .about("The new name")
+        .long("name")
+        .takes_value(true)
+        .required(false);
+    let args_game_edit = [
+        Arg::new("description").about("The description of the game").long("description").takes_value(true).required(false),
+        Arg::new("year").about("The year of the game").long("year").takes_value(true).required(false),
+        Arg::new("manufacturer").about("The manufacturer of the game").long("manufacturer").takes_value(true).required(false),
+        Arg::new("clone-of").about("The parent of the game").long("clone-of").takes_value(true).required(false),
+    ];
+    let args_rom_edit = [
+        Arg::new("size").about("The size of the rom in bytes").long("size").takes_value(true).required(false),
+        Arg::new("crc").about("The crc of the rom").long("crc").takes_value(true).required(false),
+        Arg::new("md5").about("The md5 of the rom").long("md5").takes_value(true).required(false),
+        Arg::new("sha1").about("The sha1 of the rom").long("sha1").takes_value(true).required(false),
+    ];
 
     let matches = App::new("romst")
         .version("0.1b")
         .author("Nico H. <mail@nico2sh.com>")
+        .arg(Arg::new("wait")
+            .about("If another process is writing the database, waits for it to finish instead of failing")
+            .long("wait")
+            .takes_value(false)
+            .global(true))
+        .arg(Arg::new("passwords")
+            .about("A file with passwords to try with encrypted archives, one per line")
+            .long("passwords")
+            .takes_value(true)
+            .global(true))
+        .arg(Arg::new("progress")
+            .about("How the progress is shown: a progress bar, or JSON events in stderr, one per line")
+            .long("progress")
+            .possible_values(&["bar", "json"])
+            .takes_value(true)
+            .global(true))
+        .arg(Arg::new("salvage")
+            .about("Reads the files that are not damaged in corrupt archives, the damaged ones are still reported")
+            .long("salvage")
+            .takes_value(false)
+            .global(true))
+        .arg(Arg::new("skip-headers")
+            .about("Also matches the files without their header, like the iNES one, for the DATs of headerless dumps")
+            .long("skip-headers")
+            .takes_value(false)
+            .global(true))
+        .arg(Arg::new("skippers")
+            .about("A directory with more header skippers, in the XML format of ClrMamePro. Implies --skip-headers")
+            .long("skippers")
+            .takes_value(true)
+            .global(true))
+        .arg(Arg::new("threads")
+            .about("How many files are hashed at the same time, one for each core by default")
+            .long("threads")
+            .takes_value(true)
+            .global(true))
+        .arg(Arg::new("color")
+            .about("When to color the output: only in a terminal and without NO_COLOR set, always or never")
+            .long("color")
+            .possible_values(&["auto", "always", "never"])
+            .takes_value(true)
+            .global(true))
+        .arg(Arg::new("lang")
+            .about("Language of the labels and messages, taken from ROMST_LANG or the locale if not set")
+            .long("lang")
+            .possible_values(&["en", "es"])
+            .takes_value(true)
+            .global(true))
+        .arg(Arg::new("match-policy")
+            .about("Which checksums have to agree for a file to match a rom: the sha1 or md5 (or the crc if it's the only one known), the sha1, two of sha1, md5 and crc, or the crc and the size")
+            .long("match-policy")
+            .possible_values(&["any", "sha1", "two-of-three", "crc-size"])
+            .takes_value(true)
+            .global(true))
+        .arg(Arg::new("scan-level")
+            .about("How the files are checked: hashing them all, or trusting the names and sizes of the files in archives named after their sets, for quick passes over verified collections")
+            .long("scan-level")
+            .possible_values(&["full", "trust"])
+            .takes_value(true)
+            .global(true))
+        .arg(Arg::new("name-rules")
+            .about("The differences ignored when comparing the names of the files with the names of the sets, comma separated: diacritics, separators (underscores, dots and spaces), articles (`Legend of Zelda, The`), case, all or none. All by default")
+            .long("name-rules")
+            .takes_value(true)
+            .global(true))
+        .arg(Arg::new("layout")
+            .about("How the sets are kept: an archive per set, or a folder per set with the roms unzipped. Checks read the folders named like a set as that set, rebuilds write a folder per set")
+            .long("layout")
+            .possible_values(&["archive", "folder"])
+            .takes_value(true)
+            .global(true))
         .subcommand(App::new("import")
             .about("Import a DAT file into the database")
             .arg(Arg::new("file")
@@ -79,7 +226,25 @@ fn create_matches() -> ArgMatches {
                 .short('w')
                 .about("Overwrites the destination file if exists")
                 .takes_value(false)
-                .required(false)))
+                .required(false))
+            .arg(Arg::new("duplicate")
+                .about("Imports the DAT even if another database of the destination directory has the same DAT or an older version of it")
+                .long("allow-duplicate")
+                .takes_value(false)
+                .required(false))
+            .arg(Arg::new("log")
+                .about("Writes a log of everything imported to this file: games and roms written, roms reused and rows that failed")
+                .long("log")
+                .takes_value(true)
+                .required(false))
+            .arg(Arg::new("update")
+                .about("Updates the database with a new version of its DAT, writing only the games added, changed or removed, and reports them")
+                .long("update")
+                .short('u')
+                .takes_value(false)
+                .conflicts_with("overwrite")
+                .required(false))
+            .arg(arg_format.clone()))
         .subcommand(App::new("info")
             .about("Gets information from roms and sets from the database")
             .subcommand(App::new("data")
@@ -98,6 +263,16 @@ fn create_matches() -> ArgMatches {
                 .arg(arg_db.clone())
                 .arg(arg_set_mode.clone())
                 .arg(arg_format.clone()))
+            .subcommand(App::new("game")
+                .about("Gets all the information from the database for a game")
+                .arg(Arg::new("game")
+                    .about("The game to get the information from")
+                    .index(1)
+                    .takes_value(true)
+                    .required(true))
+                .arg(arg_db.clone())
+                .arg(arg_set_mode.clone())
+                .arg(arg_format.clone()))
             .subcommand(App::new("romusage")
                 .about("Shows which sets a Rom is used")
                 .arg(Arg::new("game")
@@ -113,102 +288,1146 @@ fn create_matches() -> ArgMatches {
                     .takes_value(true)
                     .required(false))
                 .arg(arg_db.clone())
-                .arg(arg_set_mode.clone()))
+                .arg(arg_set_mode.clone())
+                .arg(arg_format.clone())
+                .arg(arg_page.clone())
+                .arg(arg_page_size.clone()))
+            .subcommand(App::new("deviceusage")
+                .about("Shows which sets need a device")
+                .arg(Arg::new("device")
+                    .about("The device set")
+                    .index(1)
+                    .takes_value(true)
+                    .required(true))
+                .arg(arg_db.clone())
+                .arg(arg_format.clone()))
+            .subcommand(App::new("samples")
+                .about("Shows which sets use a sample set, or all the sample sets if none is given")
+                .arg(Arg::new("sample_set")
+                    .about("The sample set")
+                    .index(1)
+                    .takes_value(true)
+                    .required(false))
+                .arg(arg_db.clone())
+                .arg(arg_format.clone()))
+                .arg(arg_format.clone()))
+        .subcommand(App::new("exclude")
+            .about("Manages the sets to ignore in the reports")
+            .subcommand(App::new("add")
+                .about("Adds sets to the exclusion list")
+                .arg(arg_games.clone())
+                .arg(arg_db.clone()))
+            .subcommand(App::new("remove")
+                .about("Removes sets from the exclusion list")
+                .arg(arg_games.clone())
+                .arg(arg_db.clone()))
+            .subcommand(App::new("list")
+                .about("Lists the excluded sets")
+                .arg(arg_db.clone())
+                .arg(arg_format.clone())))
+        .subcommand(App::new("tag")
+            .about("Manages user tags on games")
+            .subcommand(App::new("add")
+                .about("Adds a tag to games")
+                .arg(arg_games.clone())
+                .arg(arg_tag.clone())
+                .arg(arg_db.clone()))
+            .subcommand(App::new("remove")
+                .about("Removes a tag from games")
+                .arg(arg_games.clone())
+                .arg(arg_tag.clone())
+                .arg(arg_db.clone()))
+            .subcommand(App::new("list")
+                .about("Lists the tagged games")
+                .arg(arg_tag.clone()
+                    .about("Only list the games with this tag")
+                    .required(false))
+                .arg(arg_db.clone())
+                .arg(arg_format.clone())))
+        .subcommand(App::new("setlist")
+            .about("Imports and exports lists of set names, a set per line, like the ones shared for collections")
+            .subcommand(App::new("import")
+                .about("Tags, excludes or keeps the sets of a list. The names can be old names, have the archive extension or differ in spelling")
+                .arg(Arg::new("file")
+                    .about("The list of sets")
+                    .index(1)
+                    .takes_value(true)
+                    .required(true))
+                .arg(arg_tag.clone()
+                    .about("Tags the sets with this tag")
+                    .required(false)
+                    .required_unless_present_any(&["exclude", "keep"]))
+                .arg(Arg::new("exclude")
+                    .about("Adds the sets to the exclusion list")
+                    .long("exclude")
+                    .takes_value(false)
+                    .conflicts_with_all(&["tag", "keep"]))
+                .arg(Arg::new("keep")
+                    .about("A 1G1R list, excludes the other games of the families of the sets")
+                    .long("keep")
+                    .takes_value(false)
+                    .conflicts_with_all(&["tag", "exclude"]))
+                .arg(arg_db.clone())
+                .arg(arg_format.clone()))
+            .subcommand(App::new("export")
+                .about("Writes a list of the sets with a tag, the excluded ones, or the game kept of each family")
+                .arg(Arg::new("file")
+                    .about("The file to write the list to")
+                    .index(1)
+                    .takes_value(true)
+                    .required(true))
+                .arg(arg_tag.clone()
+                    .about("The sets with this tag")
+                    .required(false)
+                    .required_unless_present_any(&["excluded", "prefer"]))
+                .arg(Arg::new("excluded")
+                    .about("The excluded sets")
+                    .long("excluded")
+                    .takes_value(false)
+                    .conflicts_with_all(&["tag", "prefer"]))
+                .arg(arg_prefer.clone()
+                    .conflicts_with_all(&["tag", "excluded"]))
+                .arg(arg_prefer_language.clone())
+                .arg(arg_db.clone())))
+        .subcommand(App::new("search")
+            .about("Searches games by name or description")
+            .arg(Arg::new("pattern")
+                .about("The pattern to search for, a glob by default (e.g. `pac*`)")
+                .index(1)
+                .takes_value(true)
+                .required(true))
+            .arg(Arg::new("regex")
+                .about("Treats the pattern as a regular expression")
+                .long("regex")
+                .short('r')
+                .takes_value(false)
+                .required(false))
+            .arg(Arg::new("manufacturer")
+                .about("Only games whose manufacturer contains this text")
+                .long("manufacturer")
+                .takes_value(true)
+                .required(false))
+            .arg(Arg::new("year")
+                .about("Only games from this year")
+                .long("year")
+                .takes_value(true)
+                .required(false))
+            .arg(Arg::new("parents")
+                .about("Only parent games")
+                .long("parents")
+                .takes_value(false)
+                .required(false))
+            .arg(Arg::new("clones")
+                .about("Only clones")
+                .long("clones")
+                .takes_value(false)
+                .required(false)
+                .conflicts_with("parents"))
+            .arg(Arg::new("bios")
+                .about("Only BIOS sets")
+                .long("bios")
+                .takes_value(false)
+                .required(false))
+            .arg(Arg::new("no-bios")
+                .about("Leaves out the BIOS sets")
+                .long("no-bios")
+                .takes_value(false)
+                .required(false)
+                .conflicts_with("bios"))
+            .arg(Arg::new("devices")
+                .about("Only devices")
+                .long("devices")
+                .takes_value(false)
+                .required(false))
+            .arg(Arg::new("no-devices")
+                .about("Leaves out the devices")
+                .long("no-devices")
+                .takes_value(false)
+                .required(false)
+                .conflicts_with("devices"))
+            .arg(Arg::new("mechanical")
+                .about("Only mechanical machines")
+                .long("mechanical")
+                .takes_value(false)
+                .required(false))
+            .arg(Arg::new("no-mechanical")
+                .about("Leaves out the mechanical machines")
+                .long("no-mechanical")
+                .takes_value(false)
+                .required(false)
+                .conflicts_with("mechanical"))
+            .arg(Arg::new("runnable")
+                .about("Only the machines that can be run, leaving out devices and BIOS that can't")
+                .long("runnable")
+                .takes_value(false)
+                .required(false))
+            .arg(Arg::new("driver-status")
+                .about("Only games whose driver has this status (good, imperfect or preliminary)")
+                .long("driver-status")
+                .takes_value(true)
+                .required(false))
+            .arg(arg_region.clone())
+            .arg(arg_language.clone())
+            .arg(arg_prefer.clone())
+            .arg(arg_prefer_language.clone())
+            .arg(arg_db.clone())
+            .arg(arg_format.clone()))
+        .subcommand(App::new("verify-set")
+            .about("Checks a single archive or directory against a set")
+            .arg(Arg::new("game")
+                .about("The set to verify")
+                .index(1)
+                .takes_value(true)
+                .required(true))
+            .arg(Arg::new("source")
+                .about("The archive or directory with the set")
+                .index(2)
+                .takes_value(true)
+                .required(true))
+            .arg(arg_db.clone())
+            .arg(arg_set_mode.clone())
+            .arg(arg_format.clone()))
+        .subcommand(App::new("verify-disks")
+            .about("Checks the CHD files of a set, including the parent CHDs needed by delta CHDs")
+            .arg(Arg::new("game")
+                .about("The set to verify")
+                .index(1)
+                .takes_value(true)
+                .required(true))
+            .arg(Arg::new("source")
+                .about("The directory with the sets, each set with its CHDs in a directory named like the set")
+                .index(2)
+                .takes_value(true)
+                .required(true))
+            .arg(arg_db.clone())
+            .arg(arg_format.clone()))
+        .subcommand(App::new("verify-all")
+            .about("Checks a directory tree, each subtree with the database and settings of its profile, with a summary of each database")
+            .arg(Arg::new("source")
+                .about("The root of the directory tree, if not specified all the directories of the profiles are checked")
+                .index(1)
+                .takes_value(true)
+                .required(false))
+            .arg(Arg::new("parallel")
+                .about("Checks several directories at the same time")
+                .long("parallel")
+                .takes_value(false)
+                .required(false))
+            .arg(Arg::new("profiles")
+                .about("A JSON file with the profiles, and the directories each one is used for")
+                .long("profiles")
+                .short('p')
+                .takes_value(true)
+                .required(true))
+            .arg(arg_format.clone()))
+        .subcommand(App::new("search-roms")
+            .about("Searches roms by file name in all the sets, listing their checksums and the sets using them")
+            .arg(Arg::new("pattern")
+                .about("The pattern to search for, a glob by default (e.g. `*.6e`)")
+                .index(1)
+                .takes_value(true)
+                .required(true))
+            .arg(Arg::new("regex")
+                .about("Treats the pattern as a regular expression")
+                .long("regex")
+                .short('r')
+                .takes_value(false)
+                .required(false))
+            .arg(arg_db.clone())
+            .arg(arg_format.clone()))
+        .subcommand(App::new("where")
+            .about("Finds which sets use a rom, and under which names")
+            .arg(Arg::new("rom")
+                .about("The crc, md5 or sha1 of the rom, or a file to get the checksums from")
+                .index(1)
+                .takes_value(true)
+                .required(true))
+            .arg(arg_db.clone())
+            .arg(arg_format.clone()))
+        .subcommand(App::new("note")
+            .about("Shows or edits the note of a game")
+            .arg(Arg::new("game")
+                .about("The game to get or set the note")
+                .index(1)
+                .takes_value(true)
+                .required(true))
+            .arg(Arg::new("set")
+                .about("The new text for the note")
+                .long("set")
+                .short('s')
+                .takes_value(true)
+                .required(false))
+            .arg(Arg::new("clear")
+                .about("Removes the note")
+                .long("clear")
+                .takes_value(false)
+                .required(false)
+                .conflicts_with("set"))
+            .arg(arg_db.clone()))
+        .subcommand(App::new("edit")
+            .about("Adds, changes or removes games and roms, to maintain a DAT in the database")
+            .subcommand(App::new("add-game")
+                .about("Adds a game without roms")
+                .arg(arg_edit_game.clone())
+                .args(&args_game_edit)
+                .arg(Arg::new("dat")
+                    .about("The id of the DAT to add the game to, as shown by the `db dats` command")
+                    .long("dat")
+                    .takes_value(true)
+                    .required(false))
+                .arg(arg_db.clone()))
+            .subcommand(App::new("game")
+                .about("Changes a game, an empty value removes it. A renamed game keeps its old name as an alias")
+                .arg(arg_edit_game.clone())
+                .arg(arg_new_name.clone())
+                .args(&args_game_edit)
+                .arg(arg_db.clone()))
+            .subcommand(App::new("remove-game")
+                .about("Removes a game and its roms, if no other game depends on it")
+                .arg(arg_edit_game.clone())
+                .arg(arg_db.clone()))
+            .subcommand(App::new("add-rom")
+                .about("Adds a rom to a game, it needs at least a checksum")
+                .arg(arg_edit_game.clone())
+                .arg(arg_edit_rom.clone())
+                .args(&args_rom_edit)
+                .arg(arg_db.clone()))
+            .subcommand(App::new("rom")
+                .about("Changes the name, size or checksums of a rom")
+                .arg(arg_edit_game.clone())
+                .arg(arg_edit_rom.clone())
+                .arg(arg_new_name.clone())
+                .args(&args_rom_edit)
+                .arg(arg_db.clone()))
+            .subcommand(App::new("remove-rom")
+                .about("Removes a rom from a game")
+                .arg(arg_edit_game.clone())
+                .arg(arg_edit_rom.clone())
+                .arg(arg_db.clone())))
+        .subcommand(App::new("db")
+            .about("Database operations")
+            .subcommand(App::new("subset")
+                .about("Creates a new database or DAT file with the selected sets and the sets they depend on")
+                .arg(arg_games.clone()
+                    .about("The sets to export")
+                    .long("sets")
+                    .short('s')
+                    .required(false))
+                .arg(Arg::new("sets-file")
+                    .about("A text file with the sets to export, one per line")
+                    .long("sets-file")
+                    .takes_value(true)
+                    .required(false))
+                .arg(arg_tag.clone()
+                    .about("Export the sets with this tag")
+                    .required(false))
+                .arg(Arg::new("output")
+                    .about("Destination file, if the extension is `dat` or `xml` a DAT file is generated, otherwise a database")
+                    .long("output")
+                    .short('o')
+                    .takes_value(true)
+                    .required(true))
+                .arg(Arg::new("overwrite")
+                    .short('w')
+                    .about("Overwrites the destination file if exists")
+                    .takes_value(false)
+                    .required(false))
+                .arg(arg_db.clone()))
+            .subcommand(App::new("export")
+                .about("Writes every game in the database as a DAT file")
+                .arg(Arg::new("output")
+                    .about("Destination DAT file")
+                    .long("output")
+                    .short('o')
+                    .takes_value(true)
+                    .required(true))
+                .arg(Arg::new("set-mode")
+                    .about("Writes the roms of the sets in this romset mode, the merged clones are in their parents. The roms are written as imported otherwise")
+                    .long("set-mode")
+                    .short('m')
+                    .possible_values(&["merged", "non-merged", "split"])
+                    .takes_value(true)
+                    .required(false))
+                .arg(Arg::new("overwrite")
+                    .short('w')
+                    .about("Overwrites the destination file if exists")
+                    .takes_value(false)
+                    .required(false))
+                .arg(arg_db.clone()))
+            .subcommand(App::new("merge")
+                .about("Combines several databases into a new one")
+                .arg(Arg::new("dbs")
+                    .about("The databases to merge")
+                    .index(1)
+                    .takes_value(true)
+                    .multiple(true)
+                    .required(true))
+                .arg(Arg::new("output")
+                    .about("Destination database")
+                    .long("output")
+                    .short('o')
+                    .takes_value(true)
+                    .required(true))
+                .arg(Arg::new("overwrite")
+                    .short('w')
+                    .about("Overwrites the destination file if exists")
+                    .takes_value(false)
+                    .required(false))
+                .arg(arg_format.clone()))
+            .subcommand(App::new("dats")
+                .about("Lists the DATs in the database")
+                .arg(arg_db.clone())
+                .arg(arg_format.clone()))
+            .subcommand(App::new("purge")
+                .about("Removes a DAT and all its games from the database")
+                .arg(Arg::new("dat")
+                    .about("The id of the DAT to remove, as shown by the `dats` command")
+                    .long("dat")
+                    .takes_value(true)
+                    .required(true))
+                .arg(arg_db.clone()))
+            .subcommand(App::new("unreferenced")
+                .about("Lists the roms not used by any game, left behind by older purges or merges")
+                .arg(Arg::new("prune")
+                    .about("Removes them from the database")
+                    .long("prune")
+                    .takes_value(false)
+                    .required(false))
+                .arg(arg_db.clone())
+                .arg(arg_format.clone()))
+            .subcommand(App::new("migrate")
+                .about("Updates a database created by an older version of romst")
+                .arg(arg_db.clone()))
+            .subcommand(App::new("query")
+                .about("Runs a SELECT statement on the database, the database is opened read only")
+                .arg(Arg::new("sql")
+                    .about("The SELECT statement, with `?` for the parameters")
+                    .index(1)
+                    .takes_value(true)
+                    .required(true))
+                .arg(Arg::new("params")
+                    .about("The values of the parameters, in order")
+                    .index(2)
+                    .takes_value(true)
+                    .multiple(true)
+                    .required(false))
+                .arg(arg_db.clone())
+                .arg(arg_format.clone()))
+            .subcommand(App::new("changes")
+                .about("Lists the games removed or renamed by the DATs imported over the database")
+                .arg(Arg::new("since")
+                    .about("Only the games removed after this DAT version")
+                    .long("since")
+                    .takes_value(true)
+                    .required(false))
+                .arg(arg_db.clone())
+                .arg(arg_format.clone()))
+            .subcommand(App::new("aliases")
+                .about("Lists the old names of the games, the archives and searches using them still find the games")
+                .arg(Arg::new("import")
+                    .about("Adds the renames of a list, a line per game with the old and the new name, e.g. `puckman pacman`")
+                    .long("import")
+                    .takes_value(true)
+                    .required(false))
+                .arg(arg_db.clone())
                 .arg(arg_format.clone()))
+            .subcommand(App::new("history")
+                .about("Lists the reports stored by the daemon")
+                .arg(Arg::new("source")
+                    .about("Only list the reports of this directory")
+                    .long("source")
+                    .short('s')
+                    .takes_value(true)
+                    .required(false))
+                .arg(arg_db.clone())
+                .arg(arg_format.clone())))
+        .subcommand(App::new("rebuild")
+            .about("Builds the sets of the database from loose files and archives, as zip archives named after the sets")
+            .arg(Arg::new("source")
+                .about("The directory with the files to take the roms from, or a remote one to copy them from, like `https://host/roms`, `s3://bucket/roms` or `ssh://host/roms`")
+                .long("source")
+                .short('s')
+                .takes_value(true)
+                .required(true))
+            .arg(Arg::new("output")
+                .about("The directory to write the sets to, the archives already there keep the files not replaced")
+                .long("output")
+                .short('o')
+                .takes_value(true)
+                .required(true))
+            .arg(Arg::new("mode")
+                .about("Copy the roms, or move them removing them from the source")
+                .long("mode")
+                .possible_values(&["copy", "move"])
+                .default_value("copy")
+                .takes_value(true))
+            .arg(Arg::new("torrentzip")
+                .about("Writes the archives in the TorrentZip format, with the files sorted and compressed again with fixed settings, so the sets match the ones of the torrents")
+                .long("torrentzip")
+                .takes_value(false)
+                .required(false))
+            .arg(Arg::new("dry-run")
+                .about("Only lists the sets that would be built")
+                .long("dry-run"))
+            .arg(Arg::new("optional-roms")
+                .about("Whether the roms flagged as optional are written: `always`, missing when not found, `if-available`, only when found, or `never`")
+                .long("optional-roms")
+                .possible_values(&["always", "if-available", "never"])
+                .default_value("always")
+                .takes_value(true))
+            .arg(Arg::new("bios-roms")
+                .about("Whether the roms of the BIOS options are written: `always`, missing when not found, `if-available`, only when found, or `never`")
+                .long("bios-roms")
+                .possible_values(&["always", "if-available", "never"])
+                .default_value("always")
+                .takes_value(true))
+            .arg(arg_tag.clone()
+                .about("Only build the sets with this tag")
+                .required(false))
+            .arg(Arg::new("trash")
+                .about("Moving the roms, sends the files left empty, and the archives as they were before removing the roms moved, to this directory, or to the trash of the system with `system`, instead of deleting them")
+                .long("trash")
+                .takes_value(true)
+                .required(false))
+            .arg(Arg::new("verify")
+                .about("Reads back and hashes the roms of each set once written, a set not matching the database fails and the roms are not removed from the source")
+                .long("verify")
+                .takes_value(false)
+                .required(false))
+            .arg(Arg::new("report")
+                .about("Writes the report of the rebuild to this file as JSON: the sets built with where each rom was taken from, the bytes written and the sets skipped with the reason")
+                .long("report")
+                .short('r')
+                .takes_value(true)
+                .required(false))
+            .arg(arg_db.clone())
+            .arg(arg_set_mode.clone())
+            .arg(arg_format.clone()))
+        .subcommand(App::new("progress")
+            .about("Shows how the completeness of the collection changed over time, from the reports in the history of the databases")
+            .arg(arg_db.clone()
+                .about("The ROMST databases, a table for each one")
+                .multiple(true))
+            .arg(Arg::new("source")
+                .about("Only show the reports of this directory")
+                .long("source")
+                .short('s')
+                .takes_value(true)
+                .required(false))
+            .arg(Arg::new("last")
+                .about("The number of reports to show for each directory, 0 for all of them")
+                .long("last")
+                .short('n')
+                .takes_value(true)
+                .default_value("10"))
+            .arg(arg_format.clone()))
+        .subcommand(App::new("doctor")
+            .about("Checks the database and the environment, suggesting how to fix the problems found")
+            .arg(Arg::new("profiles")
+                .about("Also validates this profiles file")
+                .long("profiles")
+                .takes_value(true)
+                .required(false))
+            .arg(arg_db.clone())
+            .arg(arg_format.clone()))
+        .subcommand(App::new("daemon")
+            .about("Checks directories periodically, storing the results in the report history of the database")
+            .arg(Arg::new("source")
+                .about("The directories to check")
+                .long("source")
+                .short('s')
+                .takes_value(true)
+                .multiple(true)
+                .required(true))
+            .arg(Arg::new("interval")
+                .about("Minutes to wait between checks")
+                .long("interval")
+                .takes_value(true)
+                .default_value("60")
+                .required(false))
+            .arg(Arg::new("exec")
+                .about("Command to run when the completeness of a directory regresses, the counts are passed as ROMST_* environment variables")
+                .long("exec")
+                .takes_value(true)
+                .required(false))
+            .arg(Arg::new("once")
+                .about("Checks the directories a single time and exits")
+                .long("once")
+                .takes_value(false)
+                .required(false))
+            .arg(arg_db.clone())
+            .arg(arg_set_mode.clone()))
+        .subcommand(App::new("scan")
+            .about("Reads the checksums of several files or a directory into a manifest, that can be checked later")
+            .arg(Arg::new("source")
+                .about("A directory or list of files to scan, or a remote directory as ssh://user@host/path, https://host/path or s3://bucket/path")
+                .long("source")
+                .short('s')
+                .takes_value(true)
+                .multiple(true)
+                .required(true))
+            .arg(Arg::new("output")
+                .about("The manifest file, saved as JSON if it has the json extension, in binary format otherwise")
+                .long("output")
+                .short('o')
+                .takes_value(true)
+                .required(true)))
+        .subcommand(App::new("sfv")
+            .about("Writes a checksum file describing the contents of each set archive, or a single one for all of them")
+            .arg(Arg::new("source")
+                .about("A directory or list of set archives")
+                .long("source")
+                .short('s')
+                .takes_value(true)
+                .multiple(true)
+                .required(true))
+            .arg(Arg::new("type")
+                .about("The type of checksum file, written next to each archive")
+                .long("type")
+                .short('t')
+                .possible_values(&["sfv", "md5", "sha1"])
+                .default_value("sfv")
+                .takes_value(true)
+                .required(false))
+            .arg(Arg::new("output")
+                .about("Writes a single checksum file with all the archives instead, its type is given by the extension")
+                .long("output")
+                .short('o')
+                .takes_value(true)
+                .required(false)))
         .subcommand(App::new("check")
             .about("Checks several files or a directory")
             .arg(Arg::new("source")
-                .about("A directory or list of files to check")
+                .about("A directory or list of files to check, or a remote directory as ssh://user@host/path, https://host/path or s3://bucket/path")
                 .long("source")
                 .short('s')
                 .takes_value(true)
                 .multiple(true)
-                .required(true))
+                .required_unless_present_any(&["manifest", "checksums"]))
+            .arg(Arg::new("manifest")
+                .about("Checks the files from a manifest created with the scan command instead of reading them")
+                .long("manifest")
+                .takes_value(true)
+                .required(false)
+                .conflicts_with_all(&["source", "checksums"]))
+            .arg(Arg::new("checksums")
+                .about("Checks the files listed in sfv, md5 or sha1 files, using their checksums instead of reading them")
+                .long("checksums")
+                .takes_value(true)
+                .multiple(true)
+                .required(false)
+                .conflicts_with("source"))
+            .arg(Arg::new("verify-sample")
+                .about("With --checksums, the number of files of each checksum file to read and verify")
+                .long("verify-sample")
+                .takes_value(true)
+                .default_value("0")
+                .required(false))
             .arg(arg_db.clone())
             .arg(arg_set_mode.clone())
             .arg(arg_format.clone())
-            .arg(Arg::new("report")
-                .about("Destination file for the report (if not specified, prints in text format on screen)")
-                .long("report")
-                .short('r')
+            .arg(arg_tag.clone()
+                .about("Only include in the report the sets with this tag")
+                .required(false))
+            .arg(Arg::new("sets")
+                .about("Only check these sets: names, glob patterns like `pac*`, comma separated lists or files with a set per line")
+                .long("sets")
                 .takes_value(true)
-                .required(false)
-                .conflicts_with("format")))
-        .get_matches();
-
-        matches
-}
-
-fn main() {
-    let mut builder = Builder::from_env(Env::default().default_filter_or("warn"));
-    builder.target(Target::Stdout);
-    builder.init();
-
-    let matches = create_matches();
-
-    match matches.subcommand() {
-        Some(("import", import_matches)) => import(import_matches),
+                .multiple(true)
+                .required(false))
+            .arg(arg_region.clone()
+                .about("Only include in the report the sets released in this region, from the release elements of No-Intro DATs (e.g. `USA`)"))
+            .arg(arg_language.clone()
+                .about("Only include in the report the sets released in this language, from the release elements of No-Intro DATs (e.g. `En`)"))
+            .arg(arg_prefer.clone())
+            .arg(arg_prefer_language.clone())
+            .arg(Arg::new("scope")
+                .about("Which sets to report: only the ones with files found, or all the sets in the database")
+                .long("scope")
+                .possible_values(&["present", "all"])
+                .default_value("present")
+                .takes_value(true)
+                .required(false))
+            .arg(Arg::new("group")
+                .about("Groups the sets in the report by status: complete, fixable, incomplete and missing")
+                .long("group")
+                .takes_value(false)
+                .required(false))
+            .arg(Arg::new("families")
+                .about("Adds a summary of the parents with clones: how many sets of each family are complete, and the families with only clones")
+                .long("families")
+                .takes_value(false)
+                .required(false))
+            .arg(Arg::new("verbosity")
+                .about("How much of the report is printed in plain format: only the totals, a line with the counters of each set, or every rom")
+                .long("verbosity")
+                .short('v')
+                .possible_values(&["quiet", "normal", "verbose"])
+                .default_value("verbose")
+                .takes_value(true)
+                .required(false))
+            .arg(Arg::new("plan")
+                .about("Adds a plan of the roms to move, copy or rename between the archives scanned to complete the fixable sets")
+                .long("plan")
+                .takes_value(false)
+                .required(false))
+            .arg(Arg::new("fix-script")
+                .about("Writes the fix plan as a script to review and run: PowerShell for .ps1 files, sh for .sh ones, otherwise the shell of the platform")
+                .long("fix-script")
+                .takes_value(true)
+                .required(false))
+            .arg(Arg::new("verify-writes")
+                .about("The fix script hashes every rom it writes, stopping before removing its source if it doesn't match the database. The checks are logged to romst_fix.log")
+                .long("verify-writes")
+                .requires("fix-script")
+                .takes_value(false)
+                .required(false))
+            .arg(Arg::new("have-list")
+                .about("Writes the complete sets to this file as a clrmamepro have list. Checks all the sets of the database, as with `--scope all`")
+                .long("have-list")
+                .takes_value(true)
+                .required(false))
+            .arg(Arg::new("miss-list")
+                .about("Writes the sets not complete to this file as a clrmamepro miss list. Checks all the sets of the database, as with `--scope all`")
+                .long("miss-list")
+                .takes_value(true)
+                .required(false))
+            .arg(Arg::new("fixdat")
+                .about("Writes the roms missing in each set to this file as a DAT. Checks all the sets of the database, as with `--scope all`")
+                .long("fixdat")
+                .takes_value(true)
+                .required(false))
+            .arg(Arg::new("csv")
+                .about("Writes the report to this file as CSV, a row per rom of each set with its status")
+                .long("csv")
+                .takes_value(true)
+                .required(false))
+            .arg(Arg::new("suspects")
+                .about("Looks for near matches of the unknown files: same crc and size but different sha1, or the name of a known rom with different checksums")
+                .long("suspects")
+                .takes_value(false)
+                .required(false))
+            .arg(Arg::new("identify")
+                .about("Tells the DAT the unknown and ignored files probably belong to, looking for them in the other databases of the directory of the database, or by their header or extension")
+                .long("identify")
+                .takes_value(false)
+                .required(false))
+            .arg(Arg::new("report")
+                .about("Destination file for the report (if not specified, prints in text format on screen)")
+                .long("report")
+                .short('r')
+                .takes_value(true)
+                .required(false)
+                .conflicts_with("format")))
+        .subcommand(App::new("what-if")
+            .about("Shows which sets not complete in the collection would be completed by roms that could be acquired, and how many missing roms they have")
+            .arg(Arg::new("source")
+                .about("The directory or files of the collection")
+                .long("source")
+                .short('s')
+                .takes_value(true)
+                .multiple(true)
+                .required(true))
+            .arg(Arg::new("candidates")
+                .about("The roms that could be acquired: a DAT, a manifest created with the scan command or a directory")
+                .long("candidates")
+                .short('c')
+                .takes_value(true)
+                .required(true))
+            .arg(Arg::new("scope")
+                .about("Which sets to compare: only the ones with files in the collection, or all the sets in the database")
+                .long("scope")
+                .possible_values(&["present", "all"])
+                .default_value("present")
+                .takes_value(true)
+                .required(false))
+            .arg(arg_db.clone())
+            .arg(arg_set_mode.clone())
+            .arg(arg_format.clone()))
+        .subcommand(App::new("diff")
+            .about("Compares two databases or two DATs: the sets added, removed and renamed in the new one, and the roms that changed")
+            .arg(Arg::new("old")
+                .about("The database or DAT of the old version")
+                .index(1)
+                .takes_value(true)
+                .required(true))
+            .arg(Arg::new("new")
+                .about("The database or DAT of the new version")
+                .index(2)
+                .takes_value(true)
+                .required(true))
+            .arg(arg_format.clone()))
+        .get_matches();
+
+        matches
+}
+
+fn main() {
+    let mut builder = Builder::from_env(Env::default().default_filter_or("warn"));
+    builder.target(Target::Stdout);
+    builder.init();
+
+    let matches = create_matches();
+    value_of_in_subcommands(&matches, "color")
+        .map(|mode| str::parse::<ColorMode>(mode).unwrap_or_default())
+        .unwrap_or_default()
+        .apply();
+    Language::set_current(value_of_in_subcommands(&matches, "lang")
+        .map(|lang| str::parse::<Language>(lang).unwrap_or_default())
+        .unwrap_or_else(Language::from_env));
+    let mut write_options = WriteOptions::default();
+    write_options.set_wait_for_lock(is_present_in_subcommands(&matches, "wait"));
+    let mut options = ScanOptions::default();
+    options.set_salvage_archives(is_present_in_subcommands(&matches, "salvage"));
+    if let Some(passwords_file) = value_of_in_subcommands(&matches, "passwords") {
+        match read_list_file(passwords_file) {
+            Ok(passwords) => options.set_archive_passwords(passwords),
+            Err(e) => {
+                println!("{} reading the passwords file {}.\n{}",
+                    Style::new().red().apply_to(tr("ERROR")),
+                    passwords_file,
+                    e);
+                return;
+            }
+        }
+    }
+
+    let skippers_dir = value_of_in_subcommands(&matches, "skippers");
+    if is_present_in_subcommands(&matches, "skip-headers") || skippers_dir.is_some() {
+        if let Err(e) = options.set_header_skippers(skippers_dir.map(str::to_string)) {
+            println!("{} reading the header skippers.\n{}",
+                Style::new().red().apply_to(tr("ERROR")),
+                e);
+            return;
+        }
+    }
+
+    if let Some(threads) = value_of_in_subcommands(&matches, "threads") {
+        match threads.parse::<usize>() {
+            Ok(threads) => Romst::set_hashing_threads(threads),
+            Err(e) => {
+                println!("{} not a valid number of threads.\n{}",
+                    Style::new().red().apply_to(tr("ERROR")),
+                    e);
+                return;
+            }
+        }
+    }
+
+    if let Some(policy) = value_of_in_subcommands(&matches, "match-policy") {
+        options.set_match_policy(str::parse::<MatchPolicy>(policy).unwrap_or_default());
+    }
+    if let Some(level) = value_of_in_subcommands(&matches, "scan-level") {
+        options.set_scan_level(str::parse::<ScanLevel>(level).unwrap_or_default());
+    }
+    if let Some(layout) = value_of_in_subcommands(&matches, "layout") {
+        options.set_layout(str::parse::<SetLayout>(layout).unwrap_or_default());
+    }
+    if let Some(rules) = value_of_in_subcommands(&matches, "name-rules") {
+        match str::parse::<NameRules>(rules) {
+            Ok(rules) => options.set_name_rules(rules),
+            Err(e) => {
+                println!("{} {}", Style::new().red().apply_to(tr("ERROR")), e);
+                return;
+            }
+        }
+    }
+
+    let progress = value_of_in_subcommands(&matches, "progress")
+        .map(|format| str::parse::<ProgressFormat>(format).unwrap_or_default())
+        .unwrap_or_default();
+
+    match matches.subcommand() {
+        Some(("import", import_matches)) => import(import_matches, progress, &write_options),
         Some(("info", info_matches)) => info(info_matches),
-        Some(("check", check_matches)) => check(check_matches),
+        Some(("scan", scan_matches)) => scan(scan_matches, progress, &options),
+        Some(("sfv", sfv_matches)) => sfv(sfv_matches, &options),
+        Some(("check", check_matches)) => check(check_matches, progress, &options),
+        Some(("what-if", what_if_matches)) => what_if(what_if_matches, progress, &options),
+        Some(("diff", diff_matches)) => diff(diff_matches),
+        Some(("exclude", exclude_matches)) => exclude(exclude_matches, &write_options),
+        Some(("tag", tag_matches)) => tag(tag_matches, &write_options),
+        Some(("setlist", setlist_matches)) => setlist(setlist_matches, &options, &write_options),
+        Some(("search", search_matches)) => search(search_matches),
+        Some(("verify-set", verify_matches)) => verify_set(verify_matches, &options),
+        Some(("verify-disks", verify_matches)) => verify_disks(verify_matches),
+        Some(("verify-all", verify_matches)) => verify_all(verify_matches, &options),
+        Some(("search-roms", search_matches)) => search_roms(search_matches),
+        Some(("where", where_matches)) => rom_where(where_matches),
+        Some(("note", note_matches)) => note(note_matches, &write_options),
+        Some(("edit", edit_matches)) => edit(edit_matches, &write_options),
+        Some(("db", db_matches)) => db(db_matches, &write_options),
+        Some(("rebuild", rebuild_matches)) => rebuild(rebuild_matches, &options),
+        Some(("progress", progress_matches)) => show_progress(progress_matches),
+        Some(("doctor", doctor_matches)) => doctor(doctor_matches),
+        Some(("daemon", daemon_matches)) => daemon(daemon_matches, &options, &write_options),
         Some(_) => {}
         None => {}
     }
 }
 
+// Global args are only visible in the subcommand they were set
+fn is_present_in_subcommands(matches: &ArgMatches, arg: &str) -> bool {
+    if matches.is_present(arg) {
+        return true;
+    }
+    match matches.subcommand() {
+        Some((_, sub_matches)) => is_present_in_subcommands(sub_matches, arg),
+        None => false
+    }
+}
+
+fn value_of_in_subcommands<'a>(matches: &'a ArgMatches, arg: &str) -> Option<&'a str> {
+    match (matches.value_of(arg), matches.subcommand()) {
+        (Some(value), _) => Some(value),
+        (None, Some((_, sub_matches))) => value_of_in_subcommands(sub_matches, arg),
+        (None, None) => None
+    }
+}
+
 fn print_from_format<T: Serialize + Display>(matches: &ArgMatches, obj: T) {
     let format = match matches.value_of("format") {
         Some(f) => str::parse::<OutputFormat>(f).unwrap_or_default(),
         None => OutputFormat::default() 
     };
 
-    match format {
-        OutputFormat::Json => {
-            let serialized = serde_json::to_string(&obj).unwrap();
-            println!("{}", serialized)
-        }
-        OutputFormat::JsonPretty => {
-            let serialized = serde_json::to_string_pretty(&obj).unwrap();
-            println!("{}", serialized)
-        }
-        OutputFormat::Plain => println!("{}", obj)
+    // Written as it's formatted, big results are never held in a string
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let written = match format {
+        OutputFormat::Json => serde_json::to_writer(&mut out, &obj).map_err(io::Error::from),
+        OutputFormat::JsonPretty => serde_json::to_writer_pretty(&mut out, &obj).map_err(io::Error::from),
+        OutputFormat::Plain => write!(out, "{}", obj)
     };
+    if let Err(e) = written.and_then(|_| writeln!(out)).and_then(|_| out.flush()) {
+        drop(out);
+        println!("{} writing the output.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e);
+    }
 }
 
-fn check(matches: &ArgMatches) {
+/// Only the lines of a page of the result in plain text, the whole result otherwise
+fn print_page<T: Serialize + Display + PagedDisplay>(matches: &ArgMatches, obj: T) {
+    let page = match matches.value_of("page") {
+        Some(page) if matches.value_of("format") == Some("plain") => page.parse::<usize>().ok(),
+        _ => None
+    };
+    let page = match page {
+        Some(page) => Page::new(page, matches.value_of("page-size").and_then(|size| size.parse().ok()).unwrap_or(DEFAULT_PAGE_SIZE)),
+        None => return print_from_format(matches, obj)
+    };
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let written = obj.write_page(&mut out, page)
+        .and_then(|_| writeln!(out, "{} {}/{}", tr("Page"), page.number, page.count(obj.line_count())))
+        .and_then(|_| out.flush());
+    if let Err(e) = written {
+        drop(out);
+        println!("{} writing the output.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e);
+    }
+}
+
+fn check(matches: &ArgMatches, progress: ProgressFormat, options: &ScanOptions) {
     let db = matches.value_of("db").unwrap();
-    let files = matches.values_of("source").unwrap().collect::<Vec<_>>();
     let set_mode = match matches.value_of("set-mode") {
         Some(mode) => str::parse::<RomsetMode>(mode).unwrap_or_default(),
         None => RomsetMode::default() 
     };
 
-    let reporter = Some(ReportReporterSysOut::new());
-    match Romst::get_report(db, files, set_mode, reporter) {
-        Ok(report) => {
+    let tag = matches.value_of("tag");
+    // The miss list and the fixdat have to include the sets without any file
+    let scope = match matches.value_of("scope") {
+        _ if matches.is_present("have-list") || matches.is_present("miss-list") || matches.is_present("fixdat") => ReportScope::All,
+        Some("all") => ReportScope::All,
+        _ => ReportScope::Present
+    };
+
+    let sets = match matches.values_of("sets").map(read_sets) {
+        Some(Ok(sets)) => Some(sets),
+        Some(Err(e)) => {
+            println!("{} reading the sets.\n{}",
+                Style::new().red().apply_to(tr("ERROR")),
+                e);
+            return;
+        }
+        None => None
+    };
+
+    let releases = get_release_filter(matches);
+    let reporter = Some(ReportReporterSysOut::with_format(progress));
+    let report = match (matches.value_of("manifest"), matches.values_of("checksums")) {
+        (Some(manifest), _) => Romst::get_report_from_manifest(db, manifest, set_mode, scope, tag, sets, releases, options, reporter),
+        (None, Some(checksum_files)) => {
+            let verify_sample = matches.value_of("verify-sample").unwrap_or_default().parse::<usize>().unwrap_or_default();
+            Romst::get_report_from_checksum_files(db, checksum_files.collect(), verify_sample, set_mode, scope, tag, sets, releases, options, reporter)
+        }
+        (None, None) => {
+            let files = matches.values_of("source").unwrap().collect::<Vec<_>>();
+            Romst::get_report(db, files, set_mode, scope, tag, sets, releases, options, reporter)
+        }
+    };
+    match report {
+        Ok(mut report) => {
+            if matches.is_present("suspects") {
+                if let Err(e) = Romst::add_suspects(db, &mut report) {
+                    println!("{} looking for suspect files.\n{}", Style::new().red().apply_to(tr("ERROR")), e);
+                }
+            }
+            if matches.is_present("identify") {
+                if let Err(e) = Romst::identify_unknowns(db, &mut report, options) {
+                    println!("{} identifying the unknown files.\n{}", Style::new().red().apply_to(tr("ERROR")), e);
+                }
+            }
+            if let Some(script_file) = matches.value_of("fix-script") {
+                match Romst::write_fix_script(&report, script_file, matches.is_present("verify-writes")) {
+                    Ok(plan) => {
+                        println!("{} {} {} {}", Style::new().green().apply_to(tr("SUCCESS")), plan.actions.len(), tr("actions written to"), script_file);
+                        for unrepresentable in &plan.unrepresentable {
+                            println!("{} {} left out, it can't be written on Windows: {}", Style::new().yellow().apply_to(tr("WARNING")), unrepresentable.set_name, unrepresentable.issue);
+                        }
+                    }
+                    Err(e) => println!("{} writing the fix script.\n{}", Style::new().red().apply_to(tr("ERROR")), e),
+                }
+            }
+            let have_file = matches.value_of("have-list");
+            let miss_file = matches.value_of("miss-list");
+            if have_file.is_some() || miss_file.is_some() {
+                match Romst::write_have_miss_lists(db, &report, have_file, miss_file) {
+                    Ok(lists) => println!("{} {} {}, {} {} {}", Style::new().green().apply_to(tr("SUCCESS")), lists.have.len(), tr("sets have"), lists.miss.len(), tr("missing of"), lists.known()),
+                    Err(e) => println!("{} writing the have and miss lists.\n{}", Style::new().red().apply_to(tr("ERROR")), e),
+                }
+            }
+            if let Some(fixdat_file) = matches.value_of("fixdat") {
+                match Romst::write_fixdat(db, &report, fixdat_file) {
+                    Ok(sets) => println!("{} {} sets with missing roms written to {}", Style::new().green().apply_to(tr("SUCCESS")), sets, fixdat_file),
+                    Err(e) => println!("{} writing the fixdat.\n{}", Style::new().red().apply_to(tr("ERROR")), e),
+                }
+            }
+            if let Some(csv_file) = matches.value_of("csv") {
+                match Romst::write_report_csv(&report, csv_file) {
+                    Ok(rows) => println!("{} {} rows written to {}", Style::new().green().apply_to(tr("SUCCESS")), rows, csv_file),
+                    Err(e) => println!("{} writing the CSV report.\n{}", Style::new().red().apply_to(tr("ERROR")), e),
+                }
+            }
             if let Some(dest_file) = matches.value_of("report") {
                 match Romst::save_report(dest_file, report) {
                     Ok(_) => {
-                        println!("{} report saved",
-                            Style::new().green().apply_to("SUCCESS"));
+                        println!("{} {}",
+                            Style::new().green().apply_to(tr("SUCCESS")), tr("report saved"));
                     }
                     Err(e) => {
                         println!("{} saving a report.\n{}",
-                            Style::new().red().apply_to("ERROR"), e);
+                            Style::new().red().apply_to(tr("ERROR")), e);
                     }
                 }
             } else {
-                print_from_format(matches, report);
+                let families = if matches.is_present("families") { Some(Romst::get_family_report(db, &report)) } else { None };
+                let plan = if matches.is_present("plan") { Some(Romst::get_fix_plan(&report)) } else { None };
+                let verbosity = matches.value_of("verbosity").map(|verbosity| str::parse::<Verbosity>(verbosity).unwrap_or_default()).unwrap_or_default();
+                if matches.is_present("group") {
+                    print_from_format(matches, ReportView::new(report.into_grouped(), verbosity));
+                } else {
+                    print_from_format(matches, ReportView::new(report, verbosity));
+                }
+                match families {
+                    Some(Ok(families)) => print_from_format(matches, families),
+                    Some(Err(e)) => println!("{} getting the families.\n{}", Style::new().red().apply_to(tr("ERROR")), e),
+                    None => {}
+                }
+                if let Some(plan) = plan {
+                    print_from_format(matches, plan);
+                }
             }
         }
         Err(e) => {
             println!("{} generating a report.\n{}",
-                Style::new().red().apply_to("ERROR"), e);
+                Style::new().red().apply_to(tr("ERROR")), e);
+        }
+    }
+}
+
+fn what_if(matches: &ArgMatches, progress: ProgressFormat, options: &ScanOptions) {
+    let db = matches.value_of("db").unwrap();
+    let files = matches.values_of("source").unwrap().collect::<Vec<_>>();
+    let candidates = matches.value_of("candidates").unwrap();
+    let set_mode = match matches.value_of("set-mode") {
+        Some(mode) => str::parse::<RomsetMode>(mode).unwrap_or_default(),
+        None => RomsetMode::default()
+    };
+    let scope = match matches.value_of("scope") {
+        Some("all") => ReportScope::All,
+        _ => ReportScope::Present
+    };
+
+    let reporter = Some(ReportReporterSysOut::with_format(progress));
+    match Romst::what_if(db, files, candidates, set_mode, scope, options, reporter) {
+        Ok(report) => print_from_format(matches, report),
+        Err(e) => {
+            println!("{} comparing the candidates.\n{}",
+                Style::new().red().apply_to(tr("ERROR")), e);
+        }
+    }
+}
+
+fn diff(matches: &ArgMatches) {
+    let old = matches.value_of("old").unwrap();
+    let new = matches.value_of("new").unwrap();
+
+    match Romst::diff(old, new) {
+        Ok(diff) => print_from_format(matches, diff),
+        Err(e) => {
+            println!("{} comparing {} with {}.\n{}",
+                Style::new().red().apply_to(tr("ERROR")), old, new, e);
         }
     }
 }
 
-fn import(matches: &ArgMatches) {
-    let file = matches.value_of("source").unwrap();
+fn scan(matches: &ArgMatches, progress: ProgressFormat, options: &ScanOptions) {
+    let files = matches.values_of("source").unwrap().collect::<Vec<_>>();
+    let output = matches.value_of("output").unwrap();
+
+    let reporter = Some(ReportReporterSysOut::with_format(progress));
+    match Romst::scan_files(files, output, options, reporter) {
+        Ok(scanned) => {
+            println!("{} {} {} {}",
+                Style::new().green().apply_to(tr("SUCCESS")),
+                scanned, tr("files saved in the manifest"),
+                output);
+        }
+        Err(e) => {
+            println!("{} scanning files.\n{}",
+                Style::new().red().apply_to(tr("ERROR")), e);
+        }
+    }
+}
+
+fn sfv(matches: &ArgMatches, options: &ScanOptions) {
+    let files = matches.values_of("source").unwrap().collect::<Vec<_>>();
+    let output = matches.value_of("output");
+    let format = match output {
+        Some(output) => ChecksumFormat::from_file(&output),
+        None => str::parse::<ChecksumFormat>(matches.value_of("type").unwrap_or_default())
+    };
+
+    match format.and_then(|format| Romst::create_checksum_files(files, format, output, options)) {
+        Ok(written) => {
+            println!("{} {} {}",
+                Style::new().green().apply_to(tr("SUCCESS")),
+                written, tr("checksum files written"));
+        }
+        Err(e) => {
+            println!("{} writing checksum files.\n{}",
+                Style::new().red().apply_to(tr("ERROR")), e);
+        }
+    }
+}
+
+fn import(matches: &ArgMatches, progress: ProgressFormat, write_options: &WriteOptions) {
+    let file = matches.value_of("file").unwrap();
     let output = match matches.value_of("dest") {
         Some(o) => {
             o.to_string()
@@ -219,13 +1438,46 @@ fn import(matches: &ArgMatches) {
         }
     };
     let overwrite = matches.is_present("overwrite");
+    let reporter = DatImporterReporterSysOut::with_format(progress);
+    let log_file = matches.value_of("log");
+
+    if matches.is_present("update") {
+        match Romst::update_dat(file, output.as_str(), log_file, write_options, Some(reporter)) {
+            Ok(update) => print_from_format(matches, update),
+            Err(e) => {
+                println!("{} updating the database with the file {}.\n{}",
+                    Style::new().red().apply_to(tr("ERROR")),
+                    Style::new().green().apply_to(file),
+                    e);
+            }
+        }
+        return;
+    }
+
+    if !matches.is_present("duplicate") {
+        match Romst::find_duplicate_dats(file, output.as_str()) {
+            Ok(duplicates) if !duplicates.is_empty() => {
+                for duplicate in &duplicates {
+                    println!("{} {}", Style::new().yellow().apply_to(tr("WARNING")), duplicate);
+                }
+                println!("Import it over that database to update it, with `--db {} --update`, or use `--allow-duplicate` to create another one.", duplicates[0].db);
+                return;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                println!("{} looking for the DAT in other databases.\n{}",
+                    Style::new().red().apply_to(tr("ERROR")),
+                    e);
+                return;
+            }
+        }
+    }
 
-    let reporter = DatImporterReporterSysOut::new();
-    match Romst::import_dat(file, &output, overwrite, Some(reporter)) {
+    match Romst::import_dat(file, output.as_str(), overwrite, log_file, write_options, Some(reporter)) {
         Ok(_) => {}
         Err(e) => { 
             println!("{} parsing the file {}.\n{}",
-            Style::new().red().apply_to("ERROR"),
+            Style::new().red().apply_to(tr("ERROR")),
             Style::new().green().apply_to(file),
             e); 
         }
@@ -236,7 +1488,10 @@ fn info(matches: &ArgMatches) {
     match matches.subcommand() {
         Some(("data", data_matches)) => info_data(data_matches),
         Some(("set", set_matches)) => info_set(set_matches),
+        Some(("game", game_matches)) => info_game(game_matches),
         Some(("romusage", rom_usage_matches)) => rom_usage(rom_usage_matches),
+        Some(("deviceusage", device_usage_matches)) => device_usage(device_usage_matches),
+        Some(("samples", samples_matches)) => samples_usage(samples_matches),
         Some(_) | None => {}
     }
 }
@@ -249,7 +1504,7 @@ fn info_data(matches: &ArgMatches) {
         }
         Err(e) => {
             println!("{} getting roms info.\n{}",
-                Style::new().red().apply_to("ERROR"),
+                Style::new().red().apply_to(tr("ERROR")),
                 e);
         }
     }
@@ -268,7 +1523,25 @@ fn info_set(matches: &ArgMatches) {
             print_from_format(matches, romsets);
         }
         Err(e) => { println!("{} getting game info.\n{}",
-            Style::new().red().apply_to("ERROR"),
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+
+fn info_game(matches: &ArgMatches) {
+    let db = matches.value_of("db").unwrap();
+    let game = matches.value_of("game").unwrap();
+    let set_mode = match matches.value_of("set-mode") {
+        Some(mode) => str::parse::<RomsetMode>(mode).unwrap_or_default(),
+        None => RomsetMode::default() 
+    };
+
+    match Romst::get_game_info(db, game, set_mode) {
+        Ok(game_info) => {
+            print_from_format(matches, game_info);
+        }
+        Err(e) => { println!("{} getting game info.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
             e); }
     }
 }
@@ -293,10 +1566,741 @@ fn rom_usage(matches: &ArgMatches) {
 
     match execution {
         Ok(result) => {
-            print_from_format(matches, result);
+            print_page(matches, result);
         }
         Err(e) => { println!("{} getting roms info.\n{}",
-            Style::new().red().apply_to("ERROR"),
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+fn device_usage(matches: &ArgMatches) {
+    let db = matches.value_of("db").unwrap();
+    let device = matches.value_of("device").unwrap();
+
+    match Romst::get_games_using_device(db, device) {
+        Ok(games) => {
+            print_from_format(matches, games);
+        }
+        Err(e) => { println!("{} getting the device usage.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+
+fn samples_usage(matches: &ArgMatches) {
+    let db = matches.value_of("db").unwrap();
+
+    let result = match matches.value_of("sample_set") {
+        Some(sample_set) => Romst::get_games_using_samples(db, sample_set).map(|games| print_from_format(matches, games)),
+        None => Romst::get_sample_sets(db).map(|sample_sets| print_from_format(matches, sample_sets)),
+    };
+    if let Err(e) = result {
+        println!("{} getting the samples usage.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e);
+    }
+}
+
+fn exclude(matches: &ArgMatches, write_options: &WriteOptions) {
+    match matches.subcommand() {
+        Some(("add", add_matches)) => {
+            let db = add_matches.value_of("db").unwrap();
+            let games = add_matches.values_of("games").unwrap().collect::<Vec<_>>();
+            match Romst::add_exclusions(db, games, write_options) {
+                Ok(_) => {
+                    println!("{} {}",
+                        Style::new().green().apply_to(tr("SUCCESS")), tr("sets excluded"));
+                }
+                Err(e) => { println!("{} excluding sets.\n{}",
+                    Style::new().red().apply_to(tr("ERROR")),
+                    e); }
+            }
+        }
+        Some(("remove", remove_matches)) => {
+            let db = remove_matches.value_of("db").unwrap();
+            let games = remove_matches.values_of("games").unwrap().collect::<Vec<_>>();
+            match Romst::remove_exclusions(db, games, write_options) {
+                Ok(_) => {
+                    println!("{} {}",
+                        Style::new().green().apply_to(tr("SUCCESS")), tr("sets removed from the exclusions"));
+                }
+                Err(e) => { println!("{} removing exclusions.\n{}",
+                    Style::new().red().apply_to(tr("ERROR")),
+                    e); }
+            }
+        }
+        Some(("list", list_matches)) => {
+            let db = list_matches.value_of("db").unwrap();
+            match Romst::get_exclusions(db) {
+                Ok(exclusions) => {
+                    print_from_format(list_matches, exclusions);
+                }
+                Err(e) => { println!("{} getting the exclusions.\n{}",
+                    Style::new().red().apply_to(tr("ERROR")),
+                    e); }
+            }
+        }
+        Some(_) | None => {}
+    }
+}
+
+fn tag(matches: &ArgMatches, write_options: &WriteOptions) {
+    match matches.subcommand() {
+        Some(("add", add_matches)) => {
+            let db = add_matches.value_of("db").unwrap();
+            let games = add_matches.values_of("games").unwrap().collect::<Vec<_>>();
+            let tag = add_matches.value_of("tag").unwrap();
+            match Romst::add_tag(db, games, tag, write_options) {
+                Ok(_) => {
+                    println!("{} {} {}",
+                        Style::new().green().apply_to(tr("SUCCESS")), tr("games tagged as"),
+                        Style::new().bold().apply_to(tag));
+                }
+                Err(e) => { println!("{} tagging games.\n{}",
+                    Style::new().red().apply_to(tr("ERROR")),
+                    e); }
+            }
+        }
+        Some(("remove", remove_matches)) => {
+            let db = remove_matches.value_of("db").unwrap();
+            let games = remove_matches.values_of("games").unwrap().collect::<Vec<_>>();
+            let tag = remove_matches.value_of("tag").unwrap();
+            match Romst::remove_tag(db, games, tag, write_options) {
+                Ok(_) => {
+                    println!("{} {} {} {}",
+                        Style::new().green().apply_to(tr("SUCCESS")), tr("tag"),
+                        Style::new().bold().apply_to(tag), tr("removed"));
+                }
+                Err(e) => { println!("{} removing tag.\n{}",
+                    Style::new().red().apply_to(tr("ERROR")),
+                    e); }
+            }
+        }
+        Some(("list", list_matches)) => {
+            let db = list_matches.value_of("db").unwrap();
+            let tag = list_matches.value_of("tag");
+            match Romst::get_tags(db, tag) {
+                Ok(tags) => {
+                    print_from_format(list_matches, tags);
+                }
+                Err(e) => { println!("{} getting the tags.\n{}",
+                    Style::new().red().apply_to(tr("ERROR")),
+                    e); }
+            }
+        }
+        Some(_) | None => {}
+    }
+}
+
+fn setlist(matches: &ArgMatches, options: &ScanOptions, write_options: &WriteOptions) {
+    match matches.subcommand() {
+        Some(("import", import_matches)) => {
+            let db = import_matches.value_of("db").unwrap();
+            let file = import_matches.value_of("file").unwrap();
+            let action = match import_matches.value_of("tag") {
+                Some(tag) => SetListAction::Tag(tag.to_string()),
+                None if import_matches.is_present("keep") => SetListAction::Keep,
+                None => SetListAction::Exclude,
+            };
+            match Romst::import_set_list(db, file, &action, options, write_options) {
+                Ok(import) => {
+                    print_from_format(import_matches, import);
+                }
+                Err(e) => { println!("{} importing the list {}.\n{}",
+                    Style::new().red().apply_to(tr("ERROR")),
+                    file,
+                    e); }
+            }
+        }
+        Some(("export", export_matches)) => {
+            let db = export_matches.value_of("db").unwrap();
+            let file = export_matches.value_of("file").unwrap();
+            let source = match (export_matches.value_of("tag"), export_matches.value_of("prefer")) {
+                (Some(tag), _) => SetListSource::Tag(tag.to_string()),
+                (None, Some(regions)) => SetListSource::Prefer(RegionPriorities::parse(regions, export_matches.value_of("prefer-language"))),
+                (None, None) => SetListSource::Exclusions,
+            };
+            match Romst::export_set_list(db, file, &source) {
+                Ok(list) => {
+                    println!("{} {} {} {}",
+                        Style::new().green().apply_to(tr("SUCCESS")),
+                        list.names.len(), tr("sets written to"),
+                        Style::new().bold().apply_to(file));
+                }
+                Err(e) => { println!("{} exporting the list {}.\n{}",
+                    Style::new().red().apply_to(tr("ERROR")),
+                    file,
+                    e); }
+            }
+        }
+        Some(_) | None => {}
+    }
+}
+
+fn get_release_filter(matches: &ArgMatches) -> ReleaseFilter {
+    ReleaseFilter {
+        region: matches.value_of("region").map(|region| region.to_string()),
+        language: matches.value_of("language").map(|language| language.to_string()),
+        prefer: matches.value_of("prefer").map(|regions| RegionPriorities::parse(regions, matches.value_of("prefer-language"))),
+    }
+}
+
+/// Some(true) with the flag, Some(false) with its negation, None with neither
+fn get_flag_filter(matches: &ArgMatches, flag: &str, negated: &str) -> Option<bool> {
+    if matches.is_present(flag) {
+        Some(true)
+    } else if matches.is_present(negated) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+fn search(matches: &ArgMatches) {
+    let db = matches.value_of("db").unwrap();
+    let pattern = matches.value_of("pattern").unwrap();
+    let kind = if matches.is_present("regex") { PatternKind::Regex } else { PatternKind::Glob };
+
+    let games = GameFilter::new(pattern, kind).and_then(|mut filter| {
+        filter.manufacturer = matches.value_of("manufacturer").map(|m| m.to_string());
+        filter.year = matches.value_of("year").map(|y| y.to_string());
+        if matches.is_present("parents") {
+            filter.clones = Some(false);
+        } else if matches.is_present("clones") {
+            filter.clones = Some(true);
+        }
+        filter.bios = get_flag_filter(matches, "bios", "no-bios");
+        filter.devices = get_flag_filter(matches, "devices", "no-devices");
+        filter.mechanical = get_flag_filter(matches, "mechanical", "no-mechanical");
+        if matches.is_present("runnable") {
+            filter.runnable = Some(true);
+        }
+        filter.driver_status = matches.value_of("driver-status").map(|status| status.to_string());
+        filter.releases = get_release_filter(matches);
+        Romst::search_games(db, &filter)
+    });
+
+    match games {
+        Ok(games) => {
+            print_from_format(matches, games);
+        }
+        Err(e) => { println!("{} searching games.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+
+fn search_roms(matches: &ArgMatches) {
+    let db = matches.value_of("db").unwrap();
+    let pattern = matches.value_of("pattern").unwrap();
+    let kind = if matches.is_present("regex") { PatternKind::Regex } else { PatternKind::Glob };
+
+    match RomFilter::new(pattern, kind).and_then(|filter| Romst::search_roms(db, &filter)) {
+        Ok(roms) => {
+            print_from_format(matches, roms);
+        }
+        Err(e) => { println!("{} searching roms.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+
+fn verify_set(matches: &ArgMatches, options: &ScanOptions) {
+    let db = matches.value_of("db").unwrap();
+    let game = matches.value_of("game").unwrap();
+    let source = matches.value_of("source").unwrap();
+    let set_mode = match matches.value_of("set-mode") {
+        Some(mode) => str::parse::<RomsetMode>(mode).unwrap_or_default(),
+        None => RomsetMode::default() 
+    };
+
+    match Romst::verify_set(db, game, source, set_mode, options) {
+        Ok(set_report) => {
+            print_from_format(matches, set_report);
+        }
+        Err(e) => { println!("{} verifying the set.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+
+fn verify_disks(matches: &ArgMatches) {
+    let db = matches.value_of("db").unwrap();
+    let game = matches.value_of("game").unwrap();
+    let source = matches.value_of("source").unwrap();
+
+    match Romst::verify_disks(db, game, source) {
+        Ok(disk_report) => {
+            print_from_format(matches, disk_report);
+        }
+        Err(e) => { println!("{} verifying the disks.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+
+fn verify_all(matches: &ArgMatches, options: &ScanOptions) {
+    let profiles = matches.value_of("profiles").unwrap();
+    let source = matches.value_of("source");
+
+    match Romst::verify_all(profiles, source, matches.is_present("parallel"), options) {
+        Ok(reports) => {
+            print_from_format(matches, reports);
+        }
+        Err(e) => { println!("{} verifying the directories.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+
+fn rom_where(matches: &ArgMatches) {
+    let db = matches.value_of("db").unwrap();
+    let rom = matches.value_of("rom").unwrap();
+
+    match Romst::find_rom_locations(db, rom) {
+        Ok(locations) => {
+            print_from_format(matches, locations);
+        }
+        Err(e) => { println!("{} finding the rom.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+
+fn note(matches: &ArgMatches, write_options: &WriteOptions) {
+    let db = matches.value_of("db").unwrap();
+    let game = matches.value_of("game").unwrap();
+
+    let execution = if matches.is_present("clear") {
+        Romst::set_note(db, game, None, write_options).map(|_| {
+            println!("{} {}", Style::new().green().apply_to(tr("SUCCESS")), tr("note removed"));
+        })
+    } else if let Some(text) = matches.value_of("set") {
+        Romst::set_note(db, game, Some(text), write_options).map(|_| {
+            println!("{} {}", Style::new().green().apply_to(tr("SUCCESS")), tr("note saved"));
+        })
+    } else {
+        Romst::get_note(db, game).map(|note| {
+            match note {
+                Some(note) => println!("{}", note),
+                None => println!("{} {}", tr("No note for"), Style::new().bold().apply_to(game)),
+            }
+        })
+    };
+
+    if let Err(e) = execution {
+        println!("{} with the note.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e);
+    }
+}
+
+fn edit(matches: &ArgMatches, write_options: &WriteOptions) {
+    let (edit_matches, execution) = match matches.subcommand() {
+        Some(("add-game", add_matches)) => (add_matches, add_matches.value_of("dat").map(|dat| dat.parse::<u32>()).transpose()
+            .map_err(anyhow::Error::from)
+            .and_then(|dat_id| Romst::add_game(add_matches.value_of("db").unwrap(), add_matches.value_of("game").unwrap(), &get_game_edit(add_matches), dat_id, write_options))
+            .map(|game| format!("game {} added", game.name))),
+        Some(("game", game_matches)) => (game_matches,
+            Romst::edit_game(game_matches.value_of("db").unwrap(), game_matches.value_of("game").unwrap(), &get_game_edit(game_matches), write_options)
+                .map(|game| format!("game {} saved", game.name))),
+        Some(("remove-game", remove_matches)) => (remove_matches,
+            Romst::remove_game(remove_matches.value_of("db").unwrap(), remove_matches.value_of("game").unwrap(), write_options)
+                .map(|_| "game removed".to_string())),
+        Some(("add-rom", add_matches)) => (add_matches, get_rom_edit(add_matches).and_then(|edit| {
+            let edit = RomEdit { name: add_matches.value_of("rom").map(String::from), ..edit };
+            Romst::add_rom(add_matches.value_of("db").unwrap(), add_matches.value_of("game").unwrap(), &edit, write_options)
+                .map(|rom| format!("rom {} added", rom))
+        })),
+        Some(("rom", rom_matches)) => (rom_matches, get_rom_edit(rom_matches).and_then(|edit| {
+            Romst::edit_rom(rom_matches.value_of("db").unwrap(), rom_matches.value_of("game").unwrap(), rom_matches.value_of("rom").unwrap(), &edit, write_options)
+                .map(|rom| format!("rom {} saved", rom))
+        })),
+        Some(("remove-rom", remove_matches)) => (remove_matches,
+            Romst::remove_rom(remove_matches.value_of("db").unwrap(), remove_matches.value_of("game").unwrap(), remove_matches.value_of("rom").unwrap(), write_options)
+                .map(|_| "rom removed".to_string())),
+        Some(_) | None => return,
+    };
+
+    match execution {
+        Ok(message) => println!("{} {}", Style::new().green().apply_to(tr("SUCCESS")), message),
+        Err(e) => println!("{} editing {}.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            edit_matches.value_of("game").unwrap_or_default(),
+            e),
+    }
+}
+
+fn get_game_edit(matches: &ArgMatches) -> GameEdit {
+    GameEdit {
+        name: matches.value_of("name").map(String::from),
+        description: matches.value_of("description").map(String::from),
+        year: matches.value_of("year").map(String::from),
+        manufacturer: matches.value_of("manufacturer").map(String::from),
+        clone_of: matches.value_of("clone-of").map(String::from),
+    }
+}
+
+fn get_rom_edit(matches: &ArgMatches) -> Result<RomEdit> {
+    Ok(RomEdit {
+        name: matches.value_of("name").map(String::from),
+        size: matches.value_of("size").map(|size| size.parse::<u32>()).transpose()?,
+        crc: matches.value_of("crc").map(String::from),
+        md5: matches.value_of("md5").map(String::from),
+        sha1: matches.value_of("sha1").map(String::from),
+    })
+}
+
+fn doctor(matches: &ArgMatches) {
+    let db = matches.value_of("db").unwrap();
+    print_from_format(matches, Romst::doctor(db, matches.value_of("profiles")));
+}
+
+fn db(matches: &ArgMatches, write_options: &WriteOptions) {
+    match matches.subcommand() {
+        Some(("subset", subset_matches)) => db_subset(subset_matches, write_options),
+        Some(("export", export_matches)) => db_export(export_matches),
+        Some(("merge", merge_matches)) => db_merge(merge_matches, write_options),
+        Some(("dats", dats_matches)) => db_dats(dats_matches),
+        Some(("purge", purge_matches)) => db_purge(purge_matches, write_options),
+        Some(("migrate", migrate_matches)) => db_migrate(migrate_matches, write_options),
+        Some(("unreferenced", unreferenced_matches)) => db_unreferenced(unreferenced_matches, write_options),
+        Some(("query", query_matches)) => db_query(query_matches),
+        Some(("history", history_matches)) => db_history(history_matches),
+        Some(("changes", changes_matches)) => db_changes(changes_matches),
+        Some(("aliases", aliases_matches)) => db_aliases(aliases_matches, write_options),
+        Some(_) | None => {}
+    }
+}
+
+fn daemon(matches: &ArgMatches, options: &ScanOptions, write_options: &WriteOptions) {
+    let db = matches.value_of("db").unwrap();
+    let directories = matches.values_of("source").unwrap().collect::<Vec<_>>();
+    let set_mode = match matches.value_of("set-mode") {
+        Some(mode) => str::parse::<RomsetMode>(mode).unwrap_or_default(),
+        None => RomsetMode::default()
+    };
+    let interval = match matches.value_of("interval").unwrap().parse::<u64>() {
+        Ok(minutes) => Duration::from_secs(minutes * 60),
+        Err(e) => {
+            println!("{} not a valid interval.\n{}",
+                Style::new().red().apply_to(tr("ERROR")),
+                e);
+            return;
+        }
+    };
+    let command = matches.value_of("exec");
+
+    loop {
+        for directory in &directories {
+            match Romst::record_report(db, directory, set_mode, options, write_options, None::<ReportReporterSysOut>) {
+                Ok(record) => {
+                    print!("{}", record);
+                    if let (true, Some(command)) = (record.is_regression(), command) {
+                        if let Err(e) = Romst::run_command_for_record(command, &record) {
+                            println!("{} running the command.\n{}",
+                                Style::new().red().apply_to(tr("ERROR")),
+                                e);
+                        }
+                    }
+                }
+                Err(e) => {
+                    println!("{} checking {}.\n{}",
+                        Style::new().red().apply_to(tr("ERROR")),
+                        directory,
+                        e);
+                }
+            }
+        }
+
+        if matches.is_present("once") {
+            break;
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+fn db_unreferenced(matches: &ArgMatches, write_options: &WriteOptions) {
+    let db = matches.value_of("db").unwrap();
+
+    match Romst::get_unreferenced_roms(db, matches.is_present("prune"), write_options) {
+        Ok(roms) => {
+            print_from_format(matches, roms);
+        }
+        Err(e) => { println!("{} getting the unreferenced roms.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+
+fn db_migrate(matches: &ArgMatches, write_options: &WriteOptions) {
+    let db = matches.value_of("db").unwrap();
+
+    match Romst::migrate_db(db, write_options) {
+        Ok(true) => println!("{}", tr("Database updated")),
+        Ok(false) => println!("{}", tr("The database is up to date")),
+        Err(e) => { println!("{} updating the database.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+
+fn db_query(matches: &ArgMatches) {
+    let db = matches.value_of("db").unwrap();
+    let sql = matches.value_of("sql").unwrap();
+    let params = matches.values_of("params").map(|values| values.map(String::from).collect::<Vec<_>>()).unwrap_or_default();
+
+    match Romst::query(db, sql, &params) {
+        Ok(rows) => {
+            print_from_format(matches, rows);
+        }
+        Err(e) => { println!("{} running the query.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+
+fn db_history(matches: &ArgMatches) {
+    let db = matches.value_of("db").unwrap();
+    let source = matches.value_of("source");
+
+    match Romst::get_report_history(db, source) {
+        Ok(history) => {
+            print_from_format(matches, history);
+        }
+        Err(e) => { println!("{} getting the report history.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+
+fn rebuild(matches: &ArgMatches, options: &ScanOptions) {
+    let db = matches.value_of("db").unwrap();
+    let source = matches.value_of("source").unwrap();
+    let output = matches.value_of("output").unwrap();
+    let set_mode = match matches.value_of("set-mode") {
+        Some(mode) => str::parse::<RomsetMode>(mode).unwrap_or_default(),
+        None => RomsetMode::default()
+    };
+    let mode = match matches.value_of("mode") {
+        Some(mode) => str::parse::<RebuildMode>(mode).unwrap_or_default(),
+        None => RebuildMode::default()
+    };
+
+    let zip_format = if matches.is_present("torrentzip") { ZipFormat::TorrentZip } else { ZipFormat::Standard };
+    let get_policy = |name: &str| matches.value_of(name).map(|policy| str::parse::<RomPolicy>(policy).unwrap_or_default()).unwrap_or_default();
+    let rom_policies = RomPolicies { optional: get_policy("optional-roms"), bios: get_policy("bios-roms") };
+    let delete_policy = match matches.value_of("trash").map(str::parse::<DeletePolicy>) {
+        Some(Ok(delete_policy)) => delete_policy,
+        Some(Err(e)) => {
+            println!("{} {}", Style::new().red().apply_to(tr("ERROR")), e);
+            return;
+        }
+        None => DeletePolicy::Delete,
+    };
+
+    match Romst::rebuild(db, source, output, set_mode, matches.value_of("tag"), mode, zip_format, matches.is_present("dry-run"), matches.is_present("verify"), rom_policies, delete_policy, options) {
+        Ok(report) => {
+            if let Some(report_file) = matches.value_of("report") {
+                if let Err(e) = Romst::save_rebuild_report(report_file, &report) {
+                    println!("{} writing the rebuild report.\n{}",
+                        Style::new().red().apply_to(tr("ERROR")),
+                        e);
+                }
+            }
+            print_from_format(matches, report);
+        }
+        Err(e) => { println!("{} rebuilding the sets.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+
+fn show_progress(matches: &ArgMatches) {
+    let dbs = matches.values_of("db").unwrap().collect::<Vec<_>>();
+    let source = matches.value_of("source");
+    let last = match matches.value_of("last").unwrap().parse::<usize>() {
+        Ok(last) => last,
+        Err(e) => {
+            println!("{} not a valid number of reports.\n{}",
+                Style::new().red().apply_to(tr("ERROR")),
+                e);
+            return;
+        }
+    };
+
+    match Romst::get_progress(&dbs, source, last) {
+        Ok(progress) => {
+            print_from_format(matches, progress);
+        }
+        Err(e) => { println!("{} getting the progress of the collection.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+
+fn db_changes(matches: &ArgMatches) {
+    let db = matches.value_of("db").unwrap();
+    let since = matches.value_of("since");
+
+    match Romst::get_game_changes(db, since) {
+        Ok(changes) => {
+            print_from_format(matches, changes);
+        }
+        Err(e) => { println!("{} getting the game changes.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+
+fn db_aliases(matches: &ArgMatches, write_options: &WriteOptions) {
+    let db = matches.value_of("db").unwrap();
+
+    if let Some(list_file) = matches.value_of("import") {
+        match Romst::import_game_aliases(db, list_file, write_options) {
+            Ok(added) => println!("{} {} {}", Style::new().green().apply_to(tr("SUCCESS")), added, tr("aliases added")),
+            Err(e) => println!("{} importing the aliases.\n{}", Style::new().red().apply_to(tr("ERROR")), e),
+        }
+        return;
+    }
+
+    match Romst::get_game_aliases(db) {
+        Ok(aliases) => {
+            print_from_format(matches, aliases);
+        }
+        Err(e) => { println!("{} getting the game aliases.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+
+/// Each value is a file with a set per line, or a comma separated list of sets
+fn read_sets<'a>(values: impl Iterator<Item = &'a str>) -> Result<Vec<String>> {
+    let mut sets = vec![];
+    for value in values {
+        if Path::new(value).is_file() {
+            sets.extend(read_list_file(value)?);
+        } else {
+            sets.extend(value.split(',')
+                .map(|set| set.trim())
+                .filter(|set| !set.is_empty())
+                .map(|set| set.to_string()));
+        }
+    }
+    Ok(sets)
+}
+
+fn read_list_file(file: &str) -> Result<Vec<String>> {
+    let content = fs::read_to_string(file)?;
+    Ok(content.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_string())
+        .collect())
+}
+
+fn db_subset(matches: &ArgMatches, write_options: &WriteOptions) {
+    let db = matches.value_of("db").unwrap();
+    let output = matches.value_of("output").unwrap();
+    let overwrite = matches.is_present("overwrite");
+    let tag = matches.value_of("tag");
+    let mut games = match matches.values_of("games") {
+        Some(games) => games.map(|game| game.to_string()).collect::<Vec<_>>(),
+        None => vec![]
+    };
+    if let Some(sets_file) = matches.value_of("sets-file") {
+        match read_list_file(sets_file) {
+            Ok(sets) => games.extend(sets),
+            Err(e) => {
+                println!("{} reading the file {}.\n{}",
+                    Style::new().red().apply_to(tr("ERROR")),
+                    sets_file,
+                    e);
+                return;
+            }
+        }
+    }
+
+    match Romst::export_subset(db, output, games, tag, overwrite, write_options) {
+        Ok(total) => {
+            println!("{} {} {} {}",
+                Style::new().green().apply_to(tr("SUCCESS")),
+                total, tr("sets exported to"),
+                Style::new().bold().apply_to(output));
+        }
+        Err(e) => { println!("{} exporting the sets.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+
+fn db_export(matches: &ArgMatches) {
+    let db = matches.value_of("db").unwrap();
+    let output = matches.value_of("output").unwrap();
+
+    let set_mode = matches.value_of("set-mode").and_then(|mode| str::parse::<RomsetMode>(mode).ok());
+
+    match Romst::export_dat(db, output, set_mode, matches.is_present("overwrite")) {
+        Ok(total) => {
+            println!("{} {} {} {}",
+                Style::new().green().apply_to(tr("SUCCESS")),
+                total, tr("sets exported to"),
+                Style::new().bold().apply_to(output));
+        }
+        Err(e) => { println!("{} exporting the DAT.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
             e); }
     }
-}
\ No newline at end of file
+}
+
+fn db_merge(matches: &ArgMatches, write_options: &WriteOptions) {
+    let dbs = matches.values_of("dbs").unwrap().collect::<Vec<_>>();
+    let output = matches.value_of("output").unwrap();
+    let overwrite = matches.is_present("overwrite");
+
+    match Romst::merge_dbs(dbs, output, overwrite, write_options) {
+        Ok(info) => {
+            print_from_format(matches, info);
+        }
+        Err(e) => { println!("{} merging the databases.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+
+fn db_dats(matches: &ArgMatches) {
+    let db = matches.value_of("db").unwrap();
+
+    match Romst::get_dats(db) {
+        Ok(dats) => {
+            print_from_format(matches, dats);
+        }
+        Err(e) => { println!("{} getting the DATs.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
+
+fn db_purge(matches: &ArgMatches, write_options: &WriteOptions) {
+    let db = matches.value_of("db").unwrap();
+    let dat_id = match matches.value_of("dat").unwrap().parse::<u32>() {
+        Ok(dat_id) => dat_id,
+        Err(e) => {
+            println!("{} not a valid DAT id.\n{}",
+                Style::new().red().apply_to(tr("ERROR")),
+                e);
+            return;
+        }
+    };
+
+    match Romst::purge_dat(db, dat_id, write_options) {
+        Ok(games) => {
+            println!("{} {} {}",
+                Style::new().green().apply_to(tr("SUCCESS")),
+                games, tr("games removed"));
+        }
+        Err(e) => { println!("{} removing the DAT.\n{}",
+            Style::new().red().apply_to(tr("ERROR")),
+            e); }
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::{HashSet, VecDeque};
+
+use anyhow::Result;
+use log::warn;
+
+use crate::RomsetMode;
+use super::{models::{entry::GameEntry, file::DataFile}, reader::DataReader, writer::DataWriter};
+
+/// Writes the games as they are in the reader, without their dependencies. Returns the number of games written
+pub fn write_games<R, W, S>(reader: &R, writer: &mut W, game_names: &[S]) -> Result<usize> where R: DataReader, W: DataWriter, S: AsRef<str> {
+    let mut total = 0;
+    for game_name in game_names {
+        match reader.get_game_entry(game_name.as_ref())? {
+            Some(entry) => {
+                writer.on_new_entry(entry.game, entry.roms, entry.disks, entry.samples, entry.device_refs)?;
+                total += 1;
+            }
+            None => {
+                warn!("Game `{}` not found, it won't be written", game_name.as_ref());
+            }
+        }
+    }
+
+    Ok(total)
+}
+
+/// Writes the games with the roms of their sets in the romset mode, instead of the roms as they are in the DAT. When
+/// merged the clones are in the sets of their parents, so only the clones without their parent in the reader are
+/// written. Returns the number of games written
+pub fn write_games_in_mode<R, W, S>(reader: &R, writer: &mut W, game_names: &[S], rom_mode: RomsetMode) -> Result<usize> where R: DataReader, W: DataWriter, S: AsRef<str> {
+    let mut total = 0;
+    for game_name in game_names {
+        let entry = match reader.get_game_entry(game_name.as_ref())? {
+            Some(entry) => entry,
+            None => {
+                warn!("Game `{}` not found, it won't be written", game_name.as_ref());
+                continue;
+            }
+        };
+        if matches!(rom_mode, RomsetMode::Merged) && entry.game.clone_of.as_ref().is_some_and(|parent| reader.get_game(parent).is_some()) {
+            continue;
+        }
+
+        // The roms of the clones merged with the same ones of the parent are only written once
+        let mut roms = vec![];
+        if !entry.roms.is_empty() {
+            roms = reader.get_romset_roms(game_name.as_ref(), rom_mode)?.1.into_iter()
+                .map(|rom| rom.file)
+                .collect::<Vec<DataFile>>();
+            roms.sort_by(|a, b| a.name.cmp(&b.name));
+            roms.dedup();
+        }
+        writer.on_new_entry(entry.game, roms, entry.disks, entry.samples, entry.device_refs)?;
+        total += 1;
+    }
+
+    Ok(total)
+}
+
+/// Copies games from a reader to a writer, including the sets they depend on
+pub struct Exporter<R: DataReader, W: DataWriter> {
+    reader: R,
+    writer: W,
+}
+
+impl<R: DataReader, W: DataWriter> Exporter<R, W> {
+    pub fn new(reader: R, writer: W) -> Self { Self { reader, writer } }
+
+    /// Returns the entries for the games, plus their parents, bios and devices
+    pub fn get_entries_with_dependencies<S>(&self, game_names: &[S]) -> Result<Vec<GameEntry>> where S: AsRef<str> {
+        let mut entries = vec![];
+        let mut visited = HashSet::new();
+        let mut pending = game_names.iter().map(|game_name| game_name.as_ref().to_string()).collect::<VecDeque<_>>();
+
+        while let Some(game_name) = pending.pop_front() {
+            if !visited.insert(game_name.clone()) {
+                continue;
+            }
+
+            match self.reader.get_game_entry(&game_name)? {
+                Some(entry) => {
+                    if let Some(clone_of) = &entry.game.clone_of {
+                        pending.push_back(clone_of.to_owned());
+                    }
+                    if let Some(rom_of) = &entry.game.rom_of {
+                        pending.push_back(rom_of.to_owned());
+                    }
+                    pending.extend(entry.device_refs.iter().cloned());
+                    entries.push(entry);
+                }
+                None => {
+                    warn!("Game `{}` not found, it won't be exported", game_name);
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Writes the games and their dependencies, returns the number of games written
+    pub fn export<S>(&mut self, game_names: &[S]) -> Result<usize> where S: AsRef<str> {
+        let entries = self.get_entries_with_dependencies(game_names)?;
+        let total = entries.len();
+
+        self.writer.init()?;
+        for entry in entries {
+            self.writer.on_new_entry(entry.game, entry.roms, entry.disks, entry.samples, entry.device_refs)?;
+        }
+        self.writer.finish()?;
+
+        Ok(total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use rusqlite::{Connection, OpenFlags};
+    use crate::data::{importer::DatImporter, reader::sqlite::DBReader, writer::{dat::DatWriter, sqlite::DBWriter}};
+    use super::*;
+
+    fn get_db_connection(dat_path: &impl AsRef<Path>) -> Result<Connection> {
+        let mut conn = Connection::open_in_memory_with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+        let writer = DBWriter::from_connection(&mut conn, 100);
+        let mut importer = DatImporter::from_path(dat_path, writer)?;
+        importer.load_dat()?;
+
+        Ok(conn)
+    }
+
+    #[test]
+    fn exports_subset_with_dependencies() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let source_conn = get_db_connection(&path)?;
+        let mut dest_conn = Connection::open_in_memory_with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+
+        let mut exporter = Exporter::new(DBReader::from_connection(&source_conn), DBWriter::from_connection(&mut dest_conn, 100));
+        let total = exporter.export(&["game1a"])?;
+        drop(exporter);
+
+        // game1a, its parent game1 and the devices device1, deviceref and screen
+        assert_eq!(5, total);
+        let reader = DBReader::from_connection(&dest_conn);
+        assert!(reader.get_game("game1a").is_some());
+        assert!(reader.get_game("game1").is_some());
+        assert!(reader.get_game("device1").is_some());
+        assert!(reader.get_game("game2").is_none());
+        assert_eq!(6, reader.get_romset_roms("game1a", RomsetMode::NonMerged)?.1.len());
+        assert_eq!(2, reader.get_romset_roms("game1a", RomsetMode::Split)?.1.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn writes_the_games_in_the_romset_mode() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let reader = DBReader::from_connection(&conn);
+        let write_dat = |rom_mode: RomsetMode| -> Result<(usize, String)> {
+            let mut output = vec![];
+            let mut writer = DatWriter::new(&mut output, "Export");
+            writer.init()?;
+            let total = write_games_in_mode(&reader, &mut writer, &["game1", "game1a"], rom_mode)?;
+            writer.finish()?;
+            drop(writer);
+            Ok((total, String::from_utf8(output)?))
+        };
+
+        let (total, split) = write_dat(RomsetMode::Split)?;
+        assert_eq!(2, total);
+        assert!(split.contains("<machine name=\"game1a\""));
+        assert_eq!(4 + 2, split.matches("<rom ").count());
+
+        let (total, non_merged) = write_dat(RomsetMode::NonMerged)?;
+        assert_eq!(2, total);
+        assert_eq!(4 + 6, non_merged.matches("<rom ").count());
+
+        let (total, merged) = write_dat(RomsetMode::Merged)?;
+        assert_eq!(1, total);
+        assert!(!merged.contains("<machine name=\"game1a\""));
+        assert!(merged.contains("<rom name=\"rom5.trom\""));
+        assert_eq!(6, merged.matches("<rom ").count());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,454 @@
+use std::{collections::BTreeMap, fs::{self, File}, io::{BufRead, BufReader}, path::Path};
+use log::{debug, error, info};
+use anyhow::Result;
+use crate::{data::writer::*, err, error::RomstError};
+
+use super::{DatImporterReporter, super::models::{dat::DatInfo, disk::GameDisk, file::{DataFile, DataFileInfo, FileType}, game::Game, release::Release}};
+
+/// The blocks at the top of a ClrMamePro DAT, any other one is skipped
+const HEADER_BLOCKS: [&str; 2] = ["clrmamepro", "emulator"];
+const GAME_BLOCKS: [&str; 3] = ["game", "machine", "resource"];
+
+/// Imports the ClrMamePro text DATs, made of blocks like `game ( name "pacman" rom ( name pacman.6e size 4096 crc c1e6ab10 ) )`,
+/// into the same writers than the XML ones
+pub struct ClrMameProImporter<R: BufRead, W: DataWriter> {
+    tokenizer: Tokenizer<R>,
+    writer: W,
+    reporter: Option<Box<dyn DatImporterReporter>>,
+    total_bytes: u64,
+}
+
+impl<W: DataWriter> ClrMameProImporter<BufReader<File>, W> {
+    pub fn from_path(path: &impl AsRef<Path>, writer: W) -> Result<Self> {
+        let total_bytes = fs::metadata(path)?.len();
+        let mut importer = ClrMameProImporter::new(BufReader::new(File::open(path)?), writer);
+        importer.total_bytes = total_bytes;
+        Ok(importer)
+    }
+}
+
+impl<R: BufRead, W: DataWriter> ClrMameProImporter<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self { tokenizer: Tokenizer::new(reader), writer, reporter: None, total_bytes: 0 }
+    }
+
+    pub fn set_reporter<P>(&mut self, mut reporter: P) where P: DatImporterReporter + 'static {
+        reporter.set_total_bytes(self.total_bytes);
+        self.reporter = Some(Box::new(reporter));
+    }
+
+    /// The writer, once the DAT is loaded
+    pub fn into_writer(self) -> W {
+        self.writer
+    }
+
+    pub fn load_dat(&mut self) -> Result<()> {
+        self.writer.init()?;
+
+        while let Some(token) = self.tokenizer.next_token()? {
+            let name = match token {
+                Token::Word(name) => name.to_lowercase(),
+                _ => return err!(RomstError::ParsingError { message: format!("Expected the name of a block at position {}", self.tokenizer.position) }),
+            };
+            match self.tokenizer.next_token()? {
+                Some(Token::Open) => {}
+                _ => return err!(RomstError::ParsingError { message: format!("Expected `(` after `{}` at position {}", name, self.tokenizer.position) }),
+            }
+            let entries = self.tokenizer.read_block()?;
+            if HEADER_BLOCKS.contains(&name.as_str()) {
+                self.writer.on_dat_info(dat_info_from_entries(entries))?;
+            } else if GAME_BLOCKS.contains(&name.as_str()) {
+                self.read_game_entry(&name, entries)?;
+            } else {
+                debug!("Skipping block {}", name);
+            }
+        }
+
+        if let Some(ref reporter) = self.reporter {
+            reporter.start_finish();
+        }
+        self.writer.finish()?;
+        if let Some(ref reporter) = self.reporter {
+            reporter.finish();
+        }
+
+        Ok(())
+    }
+
+    fn read_game_entry(&mut self, block: &str, entries: Vec<(String, Value)>) -> Result<()> {
+        let mut game = Game::new(String::new());
+        // The BIOS sets of the old MAME DATs are resources
+        game.is_bios = block == "resource";
+
+        let mut roms = vec![];
+        let mut samples = vec![];
+        let mut disks = vec![];
+        let mut devices = vec![];
+        let mut releases = vec![];
+
+        for (key, value) in entries {
+            match (key.as_str(), value) {
+                ("name", Value::Text(name)) => game.name = name,
+                ("description", Value::Text(description)) => game.info_description = Some(description),
+                ("year", Value::Text(year)) => game.info_year = Some(year),
+                ("manufacturer", Value::Text(manufacturer)) => game.info_manufacturer = Some(manufacturer),
+                ("cloneof", Value::Text(clone_of)) => game.clone_of = Some(clone_of),
+                ("romof", Value::Text(rom_of)) => game.rom_of = Some(rom_of),
+                ("sampleof", Value::Text(sample_of)) => game.sample_of = Some(sample_of),
+                ("sourcefile", Value::Text(source_file)) => game.source_file = Some(source_file),
+                ("isbios", Value::Text(value)) => game.is_bios = value == "yes",
+                ("isdevice", Value::Text(value)) => game.is_device = value == "yes",
+                ("ismechanical", Value::Text(value)) => game.is_mechanical = value == "yes",
+                ("runnable", Value::Text(value)) => game.runnable = value != "no",
+                ("sample", Value::Text(sample)) => samples.push(sample),
+                ("device_ref", Value::Text(device)) => devices.push(device),
+                ("rom", Value::Block(rom)) => roms.push(file_from_entries(rom)?),
+                ("disk", Value::Block(disk)) => disks.push(disk_from_entries(disk)?),
+                ("release", Value::Block(release)) => {
+                    if let Some(release) = release_from_entries(release) {
+                        releases.push(release);
+                    }
+                },
+                ("driver", Value::Block(driver)) => {
+                    game.driver_status = driver.into_iter().find(|(key, _)| key == "status").and_then(|(_, value)| value.into_text());
+                },
+                (k, _) => debug!("Unknown entry parsing: {}", k),
+            }
+        }
+
+        if game.name.is_empty() {
+            return err!(RomstError::ParsingError { message: String::from("Missing name for Game") });
+        }
+
+        let game_name = game.name.clone();
+        self.writer.on_new_entry(game, roms, disks, samples, devices)?;
+        if !releases.is_empty() {
+            self.writer.on_releases(&game_name, releases)?;
+        }
+        let position = self.tokenizer.position;
+        if let Some(reporter) = self.reporter.as_mut() {
+            reporter.update_position(position, 1);
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether the file is a ClrMamePro DAT, by the first block of it. Anything else is read as XML
+pub fn is_clrmamepro_dat(path: &impl AsRef<Path>) -> Result<bool> {
+    let mut tokenizer = Tokenizer::new(BufReader::new(File::open(path)?));
+    tokenizer.skip_whitespace()?;
+    if tokenizer.peek_byte()? == Some(b'<') {
+        return Ok(false);
+    }
+    match (tokenizer.next_token(), tokenizer.next_token()) {
+        (Ok(Some(Token::Word(name))), Ok(Some(Token::Open))) => {
+            let name = name.to_lowercase();
+            Ok(HEADER_BLOCKS.contains(&name.as_str()) || GAME_BLOCKS.contains(&name.as_str()))
+        }
+        _ => Ok(false)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Word(String),
+}
+
+#[derive(Debug, PartialEq)]
+enum Value {
+    Text(String),
+    Block(Vec<(String, Value)>),
+}
+
+impl Value {
+    fn into_text(self) -> Option<String> {
+        match self {
+            Value::Text(text) => Some(text),
+            Value::Block(_) => None,
+        }
+    }
+}
+
+/// Splits the DAT in parenthesis, quoted strings and words, keeping the position to report the progress
+struct Tokenizer<R: BufRead> {
+    reader: R,
+    position: u64,
+}
+
+impl<R: BufRead> Tokenizer<R> {
+    fn new(reader: R) -> Self {
+        Self { reader, position: 0 }
+    }
+
+    fn peek_byte(&mut self) -> Result<Option<u8>> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    fn consume_byte(&mut self) {
+        self.reader.consume(1);
+        self.position += 1;
+    }
+
+    /// Also skips the byte order mark at the start of the file
+    fn skip_whitespace(&mut self) -> Result<()> {
+        while let Some(byte) = self.peek_byte()? {
+            let byte_order_mark = self.position < 3 && [0xef, 0xbb, 0xbf].contains(&byte);
+            if !byte.is_ascii_whitespace() && !byte_order_mark {
+                break;
+            }
+            self.consume_byte();
+        }
+        Ok(())
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>> {
+        self.skip_whitespace()?;
+        let first = match self.peek_byte()? {
+            Some(byte) => byte,
+            None => return Ok(None),
+        };
+        match first {
+            b'(' => {
+                self.consume_byte();
+                Ok(Some(Token::Open))
+            }
+            b')' => {
+                self.consume_byte();
+                Ok(Some(Token::Close))
+            }
+            b'"' => {
+                self.consume_byte();
+                let mut text = vec![];
+                loop {
+                    match self.peek_byte()? {
+                        Some(b'"') => {
+                            self.consume_byte();
+                            break;
+                        }
+                        Some(b'\\') => {
+                            self.consume_byte();
+                            if let Some(escaped) = self.peek_byte()? {
+                                text.push(escaped);
+                                self.consume_byte();
+                            }
+                        }
+                        Some(byte) => {
+                            text.push(byte);
+                            self.consume_byte();
+                        }
+                        None => return err!(RomstError::UnexpectedEOF),
+                    }
+                }
+                Ok(Some(Token::Word(String::from_utf8_lossy(&text).to_string())))
+            }
+            _ => {
+                let mut word = vec![];
+                while let Some(byte) = self.peek_byte()? {
+                    if byte.is_ascii_whitespace() || byte == b'(' || byte == b')' {
+                        break;
+                    }
+                    word.push(byte);
+                    self.consume_byte();
+                }
+                Ok(Some(Token::Word(String::from_utf8_lossy(&word).to_string())))
+            }
+        }
+    }
+
+    /// The keys and values of a block, once its `(` is read, until it closes
+    fn read_block(&mut self) -> Result<Vec<(String, Value)>> {
+        let mut entries = vec![];
+        loop {
+            let key = match self.next_token()? {
+                Some(Token::Close) => return Ok(entries),
+                Some(Token::Word(key)) => key.to_lowercase(),
+                Some(Token::Open) => return err!(RomstError::ParsingError { message: format!("Unexpected `(` at position {}", self.position) }),
+                None => return err!(RomstError::UnexpectedEOF),
+            };
+            let value = match self.next_token()? {
+                Some(Token::Open) => Value::Block(self.read_block()?),
+                Some(Token::Word(text)) => Value::Text(text),
+                Some(Token::Close) => return err!(RomstError::ParsingError { message: format!("Missing the value of `{}` at position {}", key, self.position) }),
+                None => return err!(RomstError::UnexpectedEOF),
+            };
+            entries.push((key, value));
+        }
+    }
+}
+
+fn dat_info_from_entries(entries: Vec<(String, Value)>) -> DatInfo {
+    let header = entries.into_iter()
+        .filter_map(|(key, value)| value.into_text().map(|text| (key, text)))
+        .collect::<BTreeMap<_, _>>();
+    for (key, value) in &header {
+        info!("{}: {}", key, value);
+    }
+
+    let mut dat_info = DatInfo::new(header.get("name").cloned().unwrap_or_default());
+    dat_info.description = header.get("description").cloned();
+    dat_info.version = header.get("version").cloned();
+    dat_info.header = header;
+    dat_info
+}
+
+/// The checksums are lowercase, as the ones of the XML DATs
+fn file_from_entries(entries: Vec<(String, Value)>) -> Result<DataFile> {
+    let mut data_file_info = DataFileInfo::new(FileType::Rom);
+    let mut file_name = None;
+    let mut status = None;
+    let mut bios = None;
+
+    for (key, value) in entries {
+        let value = match value.into_text() {
+            Some(value) => value,
+            None => continue,
+        };
+        match key.as_str() {
+            "name" => file_name = Some(value),
+            "bios" => bios = Some(value),
+            "sha1" => data_file_info.sha1 = Some(value.to_lowercase()),
+            "md5" => data_file_info.md5 = Some(value.to_lowercase()),
+            "sha256" => data_file_info.sha256 = Some(value.to_lowercase()),
+            "xxh3" => data_file_info.xxh3 = Some(value.to_lowercase()),
+            "crc" => data_file_info.crc = Some(value.to_lowercase()),
+            "size" => data_file_info.size = value.parse::<u32>().ok(),
+            // The old DATs have the status as flags
+            "status" | "flags" => status = Some(value),
+            k => debug!("Unknown entry parsing: {}", k),
+        }
+    }
+
+    if let Some(name) = file_name {
+        let mut data_file = DataFile::new_with_status(name, data_file_info, status);
+        data_file.bios = bios;
+        Ok(data_file)
+    } else {
+        error!("Found file without name, not adding");
+        err!(RomstError::ParsingError { message: "File without name".to_string() })
+    }
+}
+
+fn disk_from_entries(entries: Vec<(String, Value)>) -> Result<GameDisk> {
+    let mut disk = GameDisk::new(String::new());
+
+    for (key, value) in entries {
+        let value = match value.into_text() {
+            Some(value) => value,
+            None => continue,
+        };
+        match key.as_str() {
+            "name" => disk.name = value,
+            "sha1" => disk.info.sha1 = Some(value.to_lowercase()),
+            "region" => disk.info.region = Some(value),
+            "status" | "flags" => disk.info.status = Some(value.to_lowercase()),
+            k => debug!("Unknown entry parsing: {}", k),
+        }
+    }
+
+    if disk.name.is_empty() {
+        error!("Found disk without name, not adding");
+        return err!(RomstError::ParsingError { message: "Disk without name".to_string() });
+    }
+    Ok(disk)
+}
+
+fn release_from_entries(entries: Vec<(String, Value)>) -> Option<Release> {
+    let mut release = Release::new(String::new(), String::new());
+    for (key, value) in entries {
+        let value = match value.into_text() {
+            Some(value) => value,
+            None => continue,
+        };
+        match key.as_str() {
+            "name" => release.name = value,
+            "region" => release.region = value,
+            "language" => release.language = Some(value),
+            "date" => release.date = Some(value),
+            "default" => release.default = value.eq_ignore_ascii_case("yes"),
+            k => debug!("Unknown entry parsing: {}", k),
+        }
+    }
+
+    if release.name.is_empty() || release.region.is_empty() {
+        error!("Found release without name or region, not adding");
+        return None;
+    }
+    Some(release)
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::{Connection, OpenFlags};
+
+    use crate::data::{importer::DatImporter, reader::{DataReader, sqlite::DBReader}, writer::sqlite::DBWriter};
+
+    use super::*;
+
+    #[test]
+    fn reads_blocks_and_quoted_strings() -> Result<()> {
+        let mut tokenizer = Tokenizer::new("game ( name \"Pac-Man (Midway)\" rom ( name pacman.6e size 4096 ) )".as_bytes());
+        assert_eq!(Some(Token::Word("game".to_string())), tokenizer.next_token()?);
+        assert_eq!(Some(Token::Open), tokenizer.next_token()?);
+        assert_eq!(tokenizer.read_block()?, vec![
+            ("name".to_string(), Value::Text("Pac-Man (Midway)".to_string())),
+            ("rom".to_string(), Value::Block(vec![
+                ("name".to_string(), Value::Text("pacman.6e".to_string())),
+                ("size".to_string(), Value::Text("4096".to_string())),
+            ])),
+        ]);
+        assert_eq!(None, tokenizer.next_token()?);
+
+        let mut tokenizer = Tokenizer::new("game ( name pacman".as_bytes());
+        tokenizer.next_token()?;
+        tokenizer.next_token()?;
+        assert!(tokenizer.read_block().is_err());
+
+        Ok(())
+    }
+
+    fn import<F>(load: F) -> Result<Connection> where F: FnOnce(DBWriter) -> Result<()> {
+        let mut conn = Connection::open_in_memory_with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+        load(DBWriter::from_connection(&mut conn, 5))?;
+        Ok(conn)
+    }
+
+    #[test]
+    fn imports_the_same_as_the_xml_dat() -> Result<()> {
+        let cmpro_path = Path::new("testdata").join("test_cmpro.dat");
+        let xml_path = Path::new("testdata").join("test.dat");
+        assert!(is_clrmamepro_dat(&cmpro_path)?);
+        assert!(!is_clrmamepro_dat(&xml_path)?);
+
+        let cmpro = import(|writer| ClrMameProImporter::from_path(&cmpro_path, writer)?.load_dat())?;
+        let xml = import(|writer| DatImporter::from_path(&xml_path, writer)?.load_dat())?;
+        let cmpro_reader = DBReader::from_connection(&cmpro);
+        let xml_reader = DBReader::from_connection(&xml);
+
+        let dats = cmpro_reader.get_dats()?;
+        assert_eq!("MAME", dats[0].name);
+        assert_eq!(Some("0.1"), dats[0].version.as_deref());
+        assert_eq!(Some("Nico"), dats[0].header.get("author").map(String::as_str));
+
+        for game_name in ["screen", "deviceref", "device1", "game0", "game1", "game1a", "game2", "game4", "game5"] {
+            let mut cmpro_entry = cmpro_reader.get_game_entry(game_name)?.unwrap();
+            let mut xml_entry = xml_reader.get_game_entry(game_name)?.unwrap();
+            for entry in [&mut cmpro_entry, &mut xml_entry] {
+                entry.roms.sort_by(|a, b| a.name.cmp(&b.name));
+                entry.samples.sort();
+                entry.device_refs.sort();
+            }
+            assert_eq!(xml_entry.game, cmpro_entry.game);
+            assert_eq!(xml_entry.roms, cmpro_entry.roms);
+            assert_eq!(xml_entry.disks, cmpro_entry.disks);
+            assert_eq!(xml_entry.samples, cmpro_entry.samples);
+            assert_eq!(xml_entry.device_refs, cmpro_entry.device_refs);
+        }
+        assert_eq!(xml_reader.get_releases("game1")?, cmpro_reader.get_releases("game1")?);
+        assert!(cmpro_reader.get_game("neogeo").is_some_and(|game| game.is_bios));
+
+        Ok(())
+    }
+}
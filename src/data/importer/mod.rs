@@ -1,11 +1,14 @@
 
-use std::{fs::{self, File}, io::{BufRead, BufReader}, path::Path, str};
+pub mod clrmamepro;
+
+use std::{collections::BTreeMap, fs::{self, File}, io::{BufRead, BufReader}, path::Path, str};
 use log::{debug, error, info};
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use quick_xml::{Reader, events::{attributes::Attributes, Event}};
 use crate::{data::writer::*, err, error::RomstError};
 
-use super::models::{disk::{GameDisk, GameDiskInfo}, file::DataFile, file::{DataFileInfo, FileType}, game::Game};
+use self::clrmamepro::ClrMameProImporter;
+use super::models::{dat::DatInfo, disk::{GameDisk, GameDiskInfo}, file::DataFile, file::{DataFileInfo, FileType}, game::Game, release::Release};
 
 pub struct DatImporter<R: BufRead, W: DataWriter> {
     reader: Reader<R>,
@@ -39,6 +42,11 @@ impl<R: BufRead, W: DataWriter> DatImporter<R, W> {
         self.reporter = Some(Box::new(reporter));
     }
 
+    /// The writer, once the DAT is loaded
+    pub fn into_writer(self) -> W {
+        self.writer
+    }
+
     fn report_new_entry(&mut self, new_entries: u32) {
         let buf_pos = self.buf_pos() as u64;
         if let Some(reporter) = self.reporter.as_mut() {
@@ -65,9 +73,14 @@ impl<R: BufRead, W: DataWriter> DatImporter<R, W> {
                                 self.read_datafile()?;
                             },
                             "mame" => {
-                                self.read_mame_header(e.attributes());
+                                self.read_mame_header(e.attributes())?;
                                 self.read_datafile()?;
                             },
+                            // Alone or inside `softwarelists`, each list is a DAT
+                            "softwarelist" => {
+                                let list_name = self.read_softwarelist_header(e.attributes())?;
+                                self.read_softwarelist(&list_name)?;
+                            },
                             _ => {} 
                         }
                     }
@@ -176,68 +189,194 @@ impl<R: BufRead, W: DataWriter> DatImporter<R, W> {
         }
     }
 
-    fn read_mame_header(&mut self, attributes: Attributes) {
+    fn read_mame_header(&mut self, attributes: Attributes) -> Result<()> {
+        let mut dat_info = DatInfo::new("MAME");
+        process_attributes(attributes, |key, value| {
+            let key = key.to_lowercase();
+            if key == "build" {
+                info!("Build: {}", value);
+                dat_info.version = Some(value.to_string());
+            }
+            dat_info.header.insert(key, value.to_string());
+        });
+        self.writer.on_dat_info(dat_info)
+    }
+
+    /// Returns the name of the list
+    fn read_softwarelist_header(&mut self, attributes: Attributes) -> Result<String> {
+        let mut header = BTreeMap::new();
         process_attributes(attributes, |key, value| {
-            let val = value.trim();
-            match key.to_lowercase().trim() {
-                "build" => {
-                    let build = String::from(val);
-                    info!("Build: {}", build);
+            header.insert(key.to_lowercase(), value.to_string());
+        });
+        let list_name = header.get("name").cloned().unwrap_or_default();
+        info!("Software list: {}", list_name);
+
+        let mut dat_info = DatInfo::new(list_name.clone());
+        dat_info.description = header.get("description").cloned();
+        dat_info.header = header;
+        self.writer.on_dat_info(dat_info)?;
+        Ok(list_name)
+    }
+
+    fn read_softwarelist(&mut self, list_name: &str) -> Result<()> {
+        let mut buf = Vec::new();
+        loop {
+            match self.reader.read_event(&mut buf)? {
+                Event::Start(ref e) => {
+                    let name = str::from_utf8(e.name())?.trim().to_lowercase();
+                    match name.as_str() {
+                        "software" => self.read_software_entry(list_name, e.attributes())?,
+                        tag_name => self.consume_tag(tag_name.to_string())?,
+                    }
+                },
+                Event::End(e) => {
+                    if str::from_utf8(e.name())?.trim().to_lowercase() == "softwarelist" {
+                        return Ok(());
+                    }
+                    return err!(RomstError::UnexpectedTagClose {
+                        expected: String::from("softwarelist"),
+                        found: String::from_utf8(e.name().to_vec())?,
+                        position: self.buf_pos() });
+                },
+                Event::Eof => return err!(RomstError::UnexpectedEOF),
+                _ => (),
+            }
+            buf.clear();
+        }
+    }
+
+    /// A software is a set with the roms of the data areas and the disks of the disk areas of all its parts.
+    /// The clones share the roms of the parent, as in the MAME sets
+    fn read_software_entry(&mut self, list_name: &str, attributes: Attributes) -> Result<()> {
+        let mut game = game_from_attributes(attributes)?;
+        game.software_list = Some(list_name.to_string());
+        if game.rom_of.is_none() {
+            game.rom_of = game.clone_of.clone();
+        }
+
+        let mut roms = vec![];
+        let mut disks = vec![];
+        let mut buf = Vec::new();
+        loop {
+            match self.reader.read_event(&mut buf)? {
+                Event::Start(ref e) => {
+                    let name = str::from_utf8(e.name())?.trim().to_lowercase();
+                    match name.as_str() {
+                        "description" => game.info_description = Some(self.get_text()?),
+                        "year" => game.info_year = Some(self.get_text()?),
+                        "publisher" => game.info_manufacturer = Some(self.get_text()?),
+                        // Only the areas inside the parts have files
+                        "part" => {},
+                        "dataarea" | "diskarea" => self.read_software_area(&name, &mut roms, &mut disks)?,
+                        tag_name => self.consume_tag(tag_name.to_string())?,
+                    }
+                },
+                Event::End(e) => {
+                    match str::from_utf8(e.name())?.trim().to_lowercase().as_str() {
+                        "software" => break,
+                        "part" => {},
+                        _ => return err!(RomstError::UnexpectedTagClose {
+                            expected: String::from("software"),
+                            found: String::from_utf8(e.name().to_vec())?,
+                            position: self.buf_pos() }),
+                    }
                 },
-                "debug" => {
-                    let debug = String::from(val);
-                    info!("Debug: {}", debug);
+                Event::Eof => return err!(RomstError::UnexpectedEOF),
+                _ => (),
+            }
+            buf.clear();
+        }
+
+        self.writer.on_new_entry(game, roms, disks, vec![], vec![])?;
+        self.report_new_entry(1);
+
+        Ok(())
+    }
+
+    /// The entries without name, like the ones continuing or filling a rom, only say how to load the roms
+    fn read_software_area(&mut self, area: &str, roms: &mut Vec<DataFile>, disks: &mut Vec<GameDisk>) -> Result<()> {
+        let mut buf = Vec::new();
+        loop {
+            match self.reader.read_event(&mut buf)? {
+                Event::Empty(ref e) => {
+                    let name = str::from_utf8(e.name())?.trim().to_lowercase();
+                    let has_name = e.attributes().filter_map(|attribute| attribute.ok()).any(|attribute| attribute.key.eq_ignore_ascii_case(b"name"));
+                    match name.as_str() {
+                        "rom" if has_name => roms.push(file_from_attributes(FileType::Rom, e.attributes())?),
+                        "disk" if has_name => disks.push(disk_from_attributes(e.attributes())?),
+                        _ => (),
+                    }
                 },
-                "mameconfig" => {
-                    let mameconfig = String::from(val);
-                    info!("Mameconfig: {}", mameconfig);
+                Event::Start(ref e) => self.consume_tag(str::from_utf8(e.name())?.trim().to_string())?,
+                Event::End(e) => {
+                    if str::from_utf8(e.name())?.trim().to_lowercase() == area {
+                        return Ok(());
+                    }
+                    return err!(RomstError::UnexpectedTagClose {
+                        expected: area.to_string(),
+                        found: String::from_utf8(e.name().to_vec())?,
+                        position: self.buf_pos() });
                 },
-                k => debug!("Unknown atribute parsing: {}", k),
+                Event::Eof => return err!(RomstError::UnexpectedEOF),
+                _ => (),
             }
-        });
+            buf.clear();
+        }
     }
 
     fn read_dat_header(&mut self) -> Result<()> {
+        let mut header = BTreeMap::new();
         let mut buf = Vec::new();
         loop {
             match self.reader.read_event(&mut buf)? {
                 Event::Start(ref e) => {
-                    if let Ok(name) = str::from_utf8(e.name()) {
-                        match name.to_lowercase().trim() {
-                            "name" => {
-                                let name = self.get_text()?;
-                                info!("Name: {}", name);
-                            },
-                            "description" => {
-                                let desc = self.get_text()?;
-                                info!("Description: {}", desc);
-                            },
-                            "category" => {
-                                let cat = self.get_text()?;
-                                info!("Category: {}", cat);
-                            },
-                            "version" => {
-                                let ver = self.get_text()?;
-                                info!("Version: {}", ver);
-                            },
-                            "comment" => {
-                                let comment = self.get_text()?;
-                                info!("Comment: {}", comment);
-                            },
-                            tag_name => {
-                                // we consume the tag
-                                self.consume_tag(tag_name.trim().to_string())?;
-                            },
-                        }
-                    }
+                    let key = str::from_utf8(e.name())?.trim().to_lowercase();
+                    let value = self.get_header_value(&key)?;
+                    info!("{}: {}", key, value);
+                    header.insert(key, value);
+                },
+                Event::Empty(ref e) => {
+                    let element = str::from_utf8(e.name())?.trim().to_lowercase();
+                    process_attributes(e.attributes(), |key, value| {
+                        header.insert(format!("{}.{}", element, key.to_lowercase()), value.to_string());
+                    });
                 },
                 Event::End(_) => break,
-                Event::Eof => panic!("Unexpected end of file"),
+                Event::Eof => return err!(RomstError::UnexpectedEOF),
                 _ => (),
             }
+            buf.clear();
+        }
+
+        let mut dat_info = DatInfo::new(header.get("name").cloned().unwrap_or_default());
+        dat_info.description = header.get("description").cloned();
+        dat_info.version = header.get("version").cloned();
+        dat_info.header = header;
+        self.writer.on_dat_info(dat_info)
+    }
+
+    /// The text of a header element, elements nested in it are skipped
+    fn get_header_value(&mut self, tag_name: &str) -> Result<String> {
+        let mut buf = Vec::new();
+        let mut text = String::new();
+        loop {
+            match self.reader.read_event(&mut buf)? {
+                Event::Text(t) => text.push_str(t.unescape_and_decode(&self.reader)?.trim()),
+                Event::Start(ref e) => self.consume_tag(str::from_utf8(e.name())?.trim().to_string())?,
+                Event::End(e) => {
+                    if str::from_utf8(e.name())?.trim().to_lowercase() == tag_name {
+                        return Ok(text);
+                    }
+                    return err!(RomstError::UnexpectedTagClose {
+                        expected: tag_name.to_string(),
+                        found: String::from_utf8(e.name().to_vec())?,
+                        position: self.buf_pos() });
+                },
+                Event::Eof => return err!(RomstError::UnexpectedEOF),
+                _ => (),
+            }
+            buf.clear();
         }
-        buf.clear();
-        Ok(())
     }
 
     fn read_game_entry(&mut self, entry_type: String, attributes: Attributes) -> Result<()> {
@@ -247,6 +386,7 @@ impl<R: BufRead, W: DataWriter> DatImporter<R, W> {
         let mut samples = vec![];
         let mut disks = vec![];
         let mut devices = vec![];
+        let mut releases = vec![];
 
         let mut buf = Vec::new();
         loop {
@@ -293,6 +433,18 @@ impl<R: BufRead, W: DataWriter> DatImporter<R, W> {
                                     devices.push(device_name);
                                 }
                             },
+                            "release" => {
+                                if let Some(release) = release_from_attributes(e.attributes()) {
+                                    releases.push(release);
+                                }
+                            },
+                            "driver" => {
+                                process_attributes(e.attributes(), |key, value| {
+                                    if key.to_lowercase() == "status" {
+                                        game.driver_status = Some(value.to_string());
+                                    }
+                                });
+                            },
                             _ => ()
                         }
                     }
@@ -312,13 +464,91 @@ impl<R: BufRead, W: DataWriter> DatImporter<R, W> {
             buf.clear();
         }
 
+        let game_name = game.name.clone();
         self.writer.on_new_entry(game, roms, disks, samples, devices)?;
+        if !releases.is_empty() {
+            self.writer.on_releases(&game_name, releases)?;
+        }
         self.report_new_entry(1);
 
         Ok(())
     }
 }
 
+/// Imports a DAT in any of the formats supported, XML or ClrMamePro, found by the start of the file
+pub enum AnyDatImporter<W: DataWriter> {
+    Xml(DatImporter<BufReader<File>, W>),
+    ClrMamePro(ClrMameProImporter<BufReader<File>, W>),
+}
+
+impl<W: DataWriter> AnyDatImporter<W> {
+    pub fn from_path(path: &impl AsRef<Path>, writer: W) -> Result<Self> {
+        if clrmamepro::is_clrmamepro_dat(path)? {
+            info!("Reading a ClrMamePro DAT");
+            Ok(AnyDatImporter::ClrMamePro(ClrMameProImporter::from_path(path, writer)?))
+        } else {
+            Ok(AnyDatImporter::Xml(DatImporter::from_path(path, writer)?))
+        }
+    }
+
+    pub fn set_reporter<P>(&mut self, reporter: P) where P: DatImporterReporter + 'static {
+        match self {
+            AnyDatImporter::Xml(importer) => importer.set_reporter(reporter),
+            AnyDatImporter::ClrMamePro(importer) => importer.set_reporter(reporter),
+        }
+    }
+
+    pub fn load_dat(&mut self) -> Result<()> {
+        match self {
+            AnyDatImporter::Xml(importer) => importer.load_dat(),
+            AnyDatImporter::ClrMamePro(importer) => importer.load_dat(),
+        }
+    }
+
+    /// The writer, once the DAT is loaded
+    pub fn into_writer(self) -> W {
+        match self {
+            AnyDatImporter::Xml(importer) => importer.into_writer(),
+            AnyDatImporter::ClrMamePro(importer) => importer.into_writer(),
+        }
+    }
+}
+
+/// Keeps the header of the DAT and stops the import at the first game
+struct HeaderWriter {
+    header: Option<DatInfo>,
+}
+
+impl DataWriter for HeaderWriter {
+    fn init(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_dat_info(&mut self, header: DatInfo) -> Result<()> {
+        self.header = Some(header);
+        Ok(())
+    }
+
+    fn on_new_entry(&mut self, _game: Game, _roms: Vec<DataFile>, _disks: Vec<GameDisk>, _samples: Vec<String>, _device_refs: Vec<String>) -> Result<()> {
+        Err(anyhow!("The header is read"))
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads only the header of a DAT file, without the games. None if the DAT has no header
+pub fn read_dat_info(path: &impl AsRef<Path>) -> Result<Option<DatInfo>> {
+    let mut importer = AnyDatImporter::from_path(path, HeaderWriter { header: None })?;
+    let result = importer.load_dat();
+    match (importer.into_writer().header, result) {
+        (Some(header), _) => Ok(Some(header)),
+        (None, Err(e)) => Err(e),
+        (None, Ok(())) => Ok(None),
+    }
+}
+
 // Helper functions
 fn process_attributes<F>(attributes: Attributes, mut f: F) where F: FnMut(&str, &str) {
     attributes.for_each(|a| {
@@ -352,16 +582,42 @@ fn device_ref(attributes: Attributes) -> Option<String> {
     device_name
 }
 
+fn release_from_attributes(attributes: Attributes) -> Option<Release> {
+    let mut release = Release::new(String::new(), String::new());
+    process_attributes(attributes, |key, value| {
+        match key.to_lowercase().as_str() {
+            "name" => release.name = value.to_string(),
+            "region" => release.region = value.to_string(),
+            "language" => release.language = Some(value.to_string()),
+            "date" => release.date = Some(value.to_string()),
+            "default" => release.default = value.eq_ignore_ascii_case("yes"),
+            k => debug!("Unknown atribute parsing: {}", k),
+        }
+    });
+
+    if release.name.is_empty() || release.region.is_empty() {
+        error!("Found release without name or region, not adding");
+        return None;
+    }
+    Some(release)
+}
+
 fn file_from_attributes(file_type: FileType, attributes: Attributes) -> Result<DataFile> {
     let mut data_file_info = DataFileInfo::new(file_type);
     let mut file_name = None;
     let mut status = None;
+    let mut bios = None;
+    let mut optional = false;
 
     process_attributes(attributes, |key, value| {
         match key.to_lowercase().as_str() {
             "name" => file_name = Some(value.to_string()),
+            "bios" => bios = Some(value.to_string()),
+            "optional" => optional = value.eq_ignore_ascii_case("yes"),
             "sha1" => data_file_info.sha1 = Some(String::from(value)),
             "md5" => data_file_info.md5 = Some(String::from(value)),
+            "sha256" => data_file_info.sha256 = Some(value.to_lowercase()),
+            "xxh3" => data_file_info.xxh3 = Some(value.to_lowercase()),
             "crc" => data_file_info.crc = Some(String::from(value).to_lowercase()),
             "size" => data_file_info.size = value.parse::<u32>().ok(),
             "serial" => debug!("Ignoring serial attribute from file"),
@@ -371,7 +627,10 @@ fn file_from_attributes(file_type: FileType, attributes: Attributes) -> Result<D
     });
 
     if let Some(name) = file_name {
-        Ok(DataFile::new_with_status(name, data_file_info, status))
+        let mut data_file = DataFile::new_with_status(name, data_file_info, status);
+        data_file.bios = bios;
+        data_file.optional = optional;
+        Ok(data_file)
     } else {
         error!("Found file without name, not adding");
         err!(RomstError::ParsingError { message: "File without name".to_string() })
@@ -412,6 +671,10 @@ fn game_from_attributes(attributes: Attributes) -> Result<Game> {
             "romof" => game.rom_of = Some(String::from(value)),
             "sourcefile" => game.source_file = Some(String::from(value)),
             "sampleof" => game.sample_of = Some(String::from(value)),
+            "isbios" => game.is_bios = value == "yes",
+            "isdevice" => game.is_device = value == "yes",
+            "ismechanical" => game.is_mechanical = value == "yes",
+            "runnable" => game.runnable = value != "no",
             k => debug!("Unknown atribute parsing: {}", k),
         }
     });
@@ -426,11 +689,15 @@ fn game_from_attributes(attributes: Attributes) -> Result<Game> {
 #[cfg(test)]
 mod tests {
     use std::{rc::Rc, cell::RefCell};
+    use rusqlite::{Connection, OpenFlags};
+
+    use crate::data::{reader::{DataReader, sqlite::DBReader}, writer::sqlite::DBWriter};
 
     use super::*;
 
     pub struct MemoryWriter {
         pub initialized: Rc<RefCell<bool>>,
+        pub dats: Rc<RefCell<Vec<DatInfo>>>,
         pub games: Rc<RefCell<Vec<String>>>,
     }
 
@@ -438,6 +705,7 @@ mod tests {
         pub fn new() -> Self {
             MemoryWriter {
                 initialized: Rc::new(RefCell::new(false)),
+                dats: Rc::new(RefCell::new(vec![])),
                 games: Rc::new(RefCell::new(vec![])),
             }
         }
@@ -449,6 +717,11 @@ mod tests {
             Ok(())
         }
 
+        fn on_dat_info(&mut self, header: DatInfo) -> Result<()> {
+            self.dats.borrow_mut().push(header);
+            Ok(())
+        }
+
         fn finish(&mut self) -> Result<()> {
             Ok(())
         }
@@ -465,6 +738,7 @@ mod tests {
         let writer = MemoryWriter::new();
         let games = Rc::clone(&writer.games);
         let initialized = Rc::clone(&writer.initialized);
+        let dats = Rc::clone(&writer.dats);
 
         let path = Path::new("testdata").join("test.dat");
         let mut importer =
@@ -472,9 +746,55 @@ mod tests {
         importer.load_dat()?;
         
         assert!(*initialized.borrow());
+        let dats = dats.borrow();
+        assert_eq!(dats.len(), 1);
+        assert_eq!(dats[0].name, "MAME");
+        assert_eq!(dats[0].version.as_deref(), Some("0.1"));
+        assert_eq!(dats[0].header.get("author").map(String::as_str), Some("Nico"));
+        assert_eq!(dats[0].header.get("homepage").map(String::as_str), Some("http://www.example.com/"));
 
         println!("{:?}", *games.borrow());
         
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn reads_only_the_header() -> Result<()> {
+        for dat in ["test.dat", "test_cmpro.dat"] {
+            let dat_info = read_dat_info(&Path::new("testdata").join(dat))?.unwrap();
+            assert_eq!("MAME", dat_info.name);
+            assert_eq!(Some("0.1"), dat_info.version.as_deref());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn reads_the_software_lists() -> Result<()> {
+        let mut conn = Connection::open_in_memory_with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+        DatImporter::from_path(&Path::new("testdata").join("softlist.xml"), DBWriter::from_connection(&mut conn, 5))?.load_dat()?;
+        let reader = DBReader::from_connection(&conn);
+
+        let dats = reader.get_dats()?;
+        assert_eq!("mockcart", dats[0].name);
+        assert_eq!(Some("Mock cartridges"), dats[0].description.as_deref());
+
+        let game2 = reader.get_game_entry("game2")?.unwrap();
+        assert_eq!(Some("mockcart"), game2.game.software_list.as_deref());
+        assert_eq!(Some("Acme"), game2.game.info_manufacturer.as_deref());
+        let mut rom_names = game2.roms.iter().map(|rom| rom.name.as_str()).collect::<Vec<_>>();
+        rom_names.sort();
+        assert_eq!(vec!["binary1.bin", "binary2.bin", "binary3.bin"], rom_names);
+
+        let game2j = reader.get_game_entry("game2j")?.unwrap();
+        assert_eq!(Some("game2"), game2j.game.clone_of.as_deref());
+        assert_eq!(Some("game2"), game2j.game.rom_of.as_deref());
+        assert_eq!(2, game2j.roms.len());
+
+        let game5cd = reader.get_game_entry("game5cd")?.unwrap();
+        assert!(game5cd.roms.is_empty());
+        assert_eq!("game5", game5cd.disks[0].name);
+
+        Ok(())
+    }
+}
@@ -3,4 +3,6 @@ pub mod models;
 pub mod importer;
 pub mod writer;
 pub mod reader;
-pub mod reporter;
\ No newline at end of file
+pub mod reporter;
+pub mod exporter;
+pub mod rebuilder;
\ No newline at end of file
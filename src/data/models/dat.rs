@@ -0,0 +1,137 @@
+use std::{cmp::Ordering, collections::BTreeMap, fmt::{self, Display}};
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::tr;
+use super::history::compare_versions;
+
+/// Information from the header of an imported DAT file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatInfo {
+    pub id: Option<u32>,
+    pub name: String,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    /// All the values of the header by key, including the name, description and version.
+    /// Attributes of empty elements, like `<clrmamepro forcenodump="ignore"/>`, are keyed as `clrmamepro.forcenodump`
+    #[serde(default)]
+    pub header: BTreeMap<String, String>,
+    /// Where the DAT was imported from, unknown for databases created before it was recorded
+    #[serde(default)]
+    pub source: Option<ImportSource>,
+}
+
+impl DatInfo {
+    pub fn new<S>(name: S) -> Self where S: Into<String> {
+        Self { id: None, name: name.into(), description: None, version: None, header: BTreeMap::new(), source: None }
+    }
+}
+
+/// The file a DAT was imported from, and when
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportSource {
+    pub path: String,
+    pub sha1: Option<String>,
+    pub imported_at: String,
+    /// Version of romst that imported it
+    pub romst_version: String,
+}
+
+impl ImportSource {
+    pub fn new<S>(path: S, sha1: Option<String>) -> Self where S: Into<String> {
+        Self {
+            path: path.into(),
+            sha1,
+            imported_at: chrono::Utc::now().to_rfc3339(),
+            romst_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+/// How far an import went, recorded with every write to resume it if it's interrupted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportProgress {
+    /// The DAT being imported, the import is only resumed with the same file
+    pub source_sha1: Option<String>,
+    pub dat_id: Option<u32>,
+    /// The entries of the DAT already written
+    pub entries: u32,
+}
+
+impl Display for ImportSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if let Some(sha1) = &self.sha1 {
+            write!(f, " (sha1: {})", sha1)?;
+        }
+        write!(f, ", {} {} {} {}", tr("imported"), self.imported_at, tr("with romst"), self.romst_version)
+    }
+}
+
+impl Display for DatInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(id) = self.id {
+            write!(f, "[{}] ", id)?;
+        }
+        write!(f, "{}", self.name)?;
+        if let Some(version) = &self.version {
+            write!(f, " ({})", version)?;
+        }
+        if let Some(description) = &self.description {
+            write!(f, ": {}", description)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How a DAT about to be imported matches one already in a database
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DuplicateKind {
+    /// The same file, by its sha1
+    Identical,
+    /// Same name and version
+    SameVersion,
+    /// Same name, the one in the database has an older version
+    OlderVersion,
+}
+
+/// A database with the same DAT, or an older version of it, than the one about to be imported
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateDat {
+    pub db: String,
+    pub dat: DatInfo,
+    pub kind: DuplicateKind,
+}
+
+impl DuplicateDat {
+    /// How the DAT of a database matches the new one, None if it's another DAT or a newer version
+    pub fn find_kind(existing: &DatInfo, new: &DatInfo, new_sha1: Option<&str>) -> Option<DuplicateKind> {
+        let existing_sha1 = existing.source.as_ref().and_then(|source| source.sha1.as_deref());
+        if existing_sha1.is_some() && existing_sha1 == new_sha1 {
+            return Some(DuplicateKind::Identical);
+        }
+        if existing.name != new.name {
+            return None;
+        }
+        match (&existing.version, &new.version) {
+            (None, None) => Some(DuplicateKind::SameVersion),
+            (Some(existing_version), Some(new_version)) => match compare_versions(existing_version, new_version) {
+                Ordering::Equal => Some(DuplicateKind::SameVersion),
+                Ordering::Less => Some(DuplicateKind::OlderVersion),
+                Ordering::Greater => None,
+            },
+            _ => None
+        }
+    }
+}
+
+impl Display for DuplicateDat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            DuplicateKind::Identical => tr("the same DAT"),
+            DuplicateKind::SameVersion => tr("the same version"),
+            DuplicateKind::OlderVersion => tr("an older version"),
+        };
+        write!(f, "{} {} {}: {}", self.db, tr("has"), kind, self.dat)
+    }
+}
@@ -1,6 +1,7 @@
 use std::fmt::{self, Display};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub struct GameDiskInfo {
     pub sha1: Option<String>,
     pub region: Option<String>,
@@ -35,7 +36,7 @@ impl Display for GameDiskInfo {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub struct GameDisk {
     pub name: String,
     pub info: GameDiskInfo,
@@ -0,0 +1,141 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use crate::filesystem::checksum::{ChecksumEngine, Crc32Engine, Md5Engine, Sha1Engine};
+use super::{file::{DataFile, DataFileInfo, FileType}, game::Game};
+
+/// The changes to a game, the fields left as `None` are kept and the empty ones are removed
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct GameEdit {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub year: Option<String>,
+    pub manufacturer: Option<String>,
+    /// The parent, as both `cloneof` and `romof`
+    pub clone_of: Option<String>,
+}
+
+impl GameEdit {
+    pub fn apply(&self, game: &mut Game) -> Result<()> {
+        if let Some(name) = &self.name {
+            validate_name("game", name)?;
+            game.name = name.to_owned();
+        }
+        if let Some(description) = &self.description {
+            game.info_description = non_empty(description);
+        }
+        if let Some(year) = &self.year {
+            game.info_year = non_empty(year);
+        }
+        if let Some(manufacturer) = &self.manufacturer {
+            game.info_manufacturer = non_empty(manufacturer);
+        }
+        if let Some(clone_of) = &self.clone_of {
+            if clone_of == &game.name {
+                return Err(anyhow!("`{}` can't be a clone of itself", game.name));
+            }
+            game.clone_of = non_empty(clone_of);
+            game.rom_of = non_empty(clone_of);
+        }
+
+        Ok(())
+    }
+}
+
+/// A rom to add to a game, or the changes to one. The fields left as `None` are kept
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RomEdit {
+    pub name: Option<String>,
+    pub size: Option<u32>,
+    pub crc: Option<String>,
+    pub md5: Option<String>,
+    pub sha1: Option<String>,
+}
+
+impl RomEdit {
+    pub fn has_checksums(&self) -> bool {
+        self.crc.is_some() || self.md5.is_some() || self.sha1.is_some()
+    }
+
+    /// The new rom, a rom needs a name and at least a checksum to be found in the collection
+    pub fn to_rom(&self) -> Result<DataFile> {
+        if !self.has_checksums() {
+            return Err(anyhow!("A rom needs at least a crc, md5 or sha1"));
+        }
+        let name = self.name.as_ref().ok_or_else(|| anyhow!("A rom needs a name"))?;
+        let mut rom = DataFile::new(name.to_owned(), DataFileInfo::new(FileType::Rom));
+        self.apply(&mut rom)?;
+
+        Ok(rom)
+    }
+
+    pub fn apply(&self, rom: &mut DataFile) -> Result<()> {
+        if let Some(name) = &self.name {
+            validate_name("rom", name)?;
+            rom.name = name.to_owned();
+        }
+        if let Some(size) = self.size {
+            rom.info.size = Some(size);
+        }
+        let checksums: [(&dyn ChecksumEngine, &Option<String>); 3] = [(&Crc32Engine, &self.crc), (&Md5Engine, &self.md5), (&Sha1Engine, &self.sha1)];
+        for (engine, checksum) in checksums.iter() {
+            if let Some(checksum) = checksum {
+                let checksum = checksum.trim().to_lowercase();
+                if checksum.len() != engine.hex_len() || !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(anyhow!("`{}` is not a {} checksum", checksum, engine.name()));
+                }
+                engine.set(&mut rom.info, checksum);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Names end up as file names, in the archives or as the archives themselves
+fn validate_name(kind: &str, name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        return Err(anyhow!("The {} name can't be empty", kind));
+    }
+    if name.trim() != name {
+        return Err(anyhow!("The {} name `{}` can't start or end with spaces", kind, name));
+    }
+    if name.chars().any(|c| c.is_control()) {
+        return Err(anyhow!("The {} name `{}` has control characters", kind, name.escape_debug()));
+    }
+    // Roms in subdirectories of the set have a slash, but not the sets
+    if kind == "game" && name.contains(['/', '\\']) {
+        return Err(anyhow!("The game name `{}` can't have slashes", name));
+    }
+
+    Ok(())
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    if value.is_empty() { None } else { Some(value.to_owned()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_the_edits() {
+        let mut game = Game::new("pacman".to_string());
+        game.info_year = Some("1980".to_string());
+        let edit = GameEdit { description: Some("Pac-Man".to_string()), year: Some("".to_string()), clone_of: Some("puckman".to_string()), ..Default::default() };
+        edit.apply(&mut game).unwrap();
+        assert_eq!(game.info_description.as_deref(), Some("Pac-Man"));
+        assert_eq!(game.info_year, None);
+        assert_eq!(game.rom_of.as_deref(), Some("puckman"));
+        assert!(GameEdit { clone_of: Some("pacman".to_string()), ..Default::default() }.apply(&mut game).is_err());
+        assert!(GameEdit { name: Some("pac/man".to_string()), ..Default::default() }.apply(&mut game).is_err());
+
+        let edit = RomEdit { name: Some("pacman.6e".to_string()), size: Some(4096), crc: Some("C1E6AB10".to_string()), ..Default::default() };
+        let rom = edit.to_rom().unwrap();
+        assert_eq!(rom.info.crc.as_deref(), Some("c1e6ab10"));
+        assert!(RomEdit { name: Some("pacman.6e".to_string()), ..Default::default() }.to_rom().is_err());
+        assert!(RomEdit { name: Some("pacman.6e".to_string()), crc: Some("c1e6ab1".to_string()), ..Default::default() }.to_rom().is_err());
+        assert!(RomEdit { name: Some("pacman.6e".to_string()), sha1: Some("c1e6ab10".to_string()), ..Default::default() }.to_rom().is_err());
+    }
+}
@@ -0,0 +1,15 @@
+use super::{disk::GameDisk, file::DataFile, game::Game};
+
+/// A game with all its data as it is found in a DAT file
+#[derive(Debug, Clone)]
+pub struct GameEntry {
+    pub game: Game,
+    pub roms: Vec<DataFile>,
+    pub disks: Vec<GameDisk>,
+    pub samples: Vec<String>,
+    pub device_refs: Vec<String>,
+}
+
+impl GameEntry {
+    pub fn new(game: Game, roms: Vec<DataFile>, disks: Vec<GameDisk>, samples: Vec<String>, device_refs: Vec<String>) -> Self { Self { game, roms, disks, samples, device_refs } }
+}
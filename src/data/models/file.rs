@@ -2,10 +2,10 @@ use std::{cmp::Ordering, fmt::{self, Display}, hash::Hash};
 use std::cmp::Ord;
 use serde::{Deserialize, Serialize};
 
-use filesystem::FileChecks;
+use filesystem::{FileChecks, checksum};
 use anyhow::Result;
 
-use crate::{error::RomstError, err, filesystem};
+use crate::{error::RomstError, err, filesystem, i18n::tr};
 
 #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum FileType {
@@ -27,6 +27,15 @@ pub struct DataFile {
     pub name: String,
     pub info: DataFileInfo,
     pub status: Option<String>,
+    /// The info of the file without its header, only for the files read with a skipper that found one
+    #[serde(default)]
+    pub headerless: Option<DataFileInfo>,
+    /// The BIOS of the rom, for the sets with a variant of the rom for each region
+    #[serde(default)]
+    pub bios: Option<String>,
+    /// The set works without it, like the roms of the optional add-ons in MAME
+    #[serde(default)]
+    pub optional: bool,
 }
 
 impl Ord for DataFile {
@@ -54,6 +63,12 @@ impl Display for DataFile {
         if let Some(status) = &self.status {
             write!(f, " ({})", status)?;
         };
+        if let Some(bios) = &self.bios {
+            write!(f, " [{}: {}]", tr("bios"), bios)?;
+        };
+        if self.optional {
+            write!(f, " [{}]", tr("optional"))?;
+        };
 
         Ok(())
     }
@@ -64,7 +79,10 @@ impl DataFile {
         Self {
             name: name.into(),
             info: file_info,
-            status: None
+            status: None,
+            headerless: None,
+            bios: None,
+            optional: false,
         }
     }
 
@@ -72,7 +90,18 @@ impl DataFile {
         Self {
             name: name.into(),
             info: file_info,
-            status
+            status,
+            headerless: None,
+            bios: None,
+            optional: false,
+        }
+    }
+
+    /// The name with the BIOS of the rom, to tell apart the variants of a rom for each region
+    pub fn get_name_with_bios(&self) -> String {
+        match &self.bios {
+            Some(bios) => format!("{} [bios: {}]", self.name, bios),
+            None => self.name.to_owned(),
         }
     }
 
@@ -93,6 +122,11 @@ pub struct DataFileInfo {
     pub md5: Option<String>,
     pub crc: Option<String>,
     pub size: Option<u32>,
+    /// Only in the newer DATs, or hashed when the database has it
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub xxh3: Option<String>,
 }
 
 impl DataFileInfo {
@@ -103,43 +137,46 @@ impl DataFileInfo {
             md5: None,
             crc: None,
             size: None,
+            sha256: None,
+            xxh3: None,
         }
     }
 
+    /// Builds the info from a single checksum, the type is guessed from its length
+    pub fn from_checksum(checksum: &str) -> Result<Self> {
+        let checksum = checksum.trim().to_lowercase();
+        if !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+            return err!(RomstError::ParsingError {
+                message: format!("`{}` is not a valid checksum", checksum)
+            });
+        }
+
+        let engine = match checksum::ENGINES.iter().find(|engine| engine.hex_len() == checksum.len()) {
+            Some(engine) => engine,
+            None => return err!(RomstError::ParsingError {
+                message: format!("`{}` is not a {} checksum", checksum, checksum::get_engine_names())
+            })
+        };
+        let mut info = DataFileInfo::new(FileType::Rom);
+        engine.set(&mut info, checksum);
+
+        Ok(info)
+    }
+
     pub fn deep_compare(&self, other: &Self, file_checks: FileChecks) -> Result<bool> {
         let mut compared = false;
         let mut result = true;
         
-        if file_checks.contains(FileChecks::SHA1) {
-            result = result && match (self.sha1.as_ref(), other.sha1.as_ref()) {
-                (Some(self_sha1), Some(other_sha1)) => {
+        for engine in checksum::get_engines(file_checks) {
+            result = result && match (engine.get(self), engine.get(other)) {
+                (Some(self_checksum), Some(other_checksum)) => {
                     compared = true;
-                    self_sha1.eq(other_sha1)
+                    self_checksum.eq(other_checksum)
                 },
                 _ => { true }
             };
         }
 
-        if file_checks.contains(FileChecks::MD5) {
-            result = result && match (self.md5.as_ref(), other.md5.as_ref()) {
-                (Some(self_md5), Some(other_md5)) => {
-                    compared = true;
-                    self_md5.eq(other_md5)
-                },
-                _ => { true }
-            }
-        }
-
-        if file_checks.contains(FileChecks::CRC) {
-            result = result && match (self.crc.as_ref(), other.crc.as_ref()) {
-                (Some(self_crc), Some(other_crc)) => {
-                    compared = true;
-                    self_crc.eq(other_crc)
-                },
-                _ => { true }
-            }
-        }
-
         if file_checks.contains(FileChecks::SIZE) {
             result = result && match (self.size.as_ref(), other.size.as_ref()) {
                 (Some(self_size), Some(other_size)) => {
@@ -217,6 +254,9 @@ impl Display for DataFileInfo {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut rom_data = vec![];
 
+        if let Some(sha256) = &self.sha256 {
+            rom_data.push(format!("sha256: {}", sha256))
+        }
         if let Some(sha1) = &self.sha1 {
             rom_data.push(format!("sha1: {}", sha1))
         }
@@ -226,6 +266,9 @@ impl Display for DataFileInfo {
         if let Some(crc) = &self.crc {
             rom_data.push(format!("crc: {}", crc))
         }
+        if let Some(xxh3) = &self.xxh3 {
+            rom_data.push(format!("xxh3: {}", xxh3))
+        }
         if let Some(size) = &self.size {
             rom_data.push(format!("size: {}", size))
         }
@@ -1,5 +1,6 @@
 use std::fmt;
 use serde::{Deserialize, Serialize};
+use crate::i18n::tr;
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub struct Game {
@@ -11,6 +12,25 @@ pub struct Game {
     pub info_description: Option<String>,
     pub info_year: Option<String>,
     pub info_manufacturer: Option<String>,
+    /// From the MAME -listxml attributes, other DATs only have games
+    #[serde(default)]
+    pub is_bios: bool,
+    #[serde(default)]
+    pub is_device: bool,
+    #[serde(default)]
+    pub is_mechanical: bool,
+    #[serde(default = "default_runnable")]
+    pub runnable: bool,
+    /// The status of the driver emulating the machine: `good`, `imperfect` or `preliminary`
+    #[serde(default)]
+    pub driver_status: Option<String>,
+    /// The MAME software list of a software set, like `nes`, None for the machines
+    #[serde(default)]
+    pub software_list: Option<String>,
+}
+
+fn default_runnable() -> bool {
+    true
 }
 
 impl Game {
@@ -23,7 +43,13 @@ impl Game {
             sample_of: None,
             info_description: None,
             info_year: None,
-            info_manufacturer: None
+            info_manufacturer: None,
+            is_bios: false,
+            is_device: false,
+            is_mechanical: false,
+            runnable: true,
+            driver_status: None,
+            software_list: None,
         }
     }
 }
@@ -33,16 +59,34 @@ impl fmt::Display for Game {
         let mut game_data = vec![];
 
         if let Some(clone_of) = &self.clone_of {
-            game_data.push(format!("Clone of: {}", clone_of));
+            game_data.push(format!("{}: {}", tr("Clone of"), clone_of));
         }
         if let Some(rom_of) = &self.rom_of {
-            game_data.push(format!("ROM of: {}", rom_of));
+            game_data.push(format!("{}: {}", tr("ROM of"), rom_of));
         }
         if let Some(source_file) = &self.source_file {
-            game_data.push(format!("Source File: {}", source_file));
+            game_data.push(format!("{}: {}", tr("Source File"), source_file));
         }
         if let Some(sample_of) = &self.sample_of {
-            game_data.push(format!("Sample of: {}", sample_of))
+            game_data.push(format!("{}: {}", tr("Sample of"), sample_of))
+        }
+        if self.is_bios {
+            game_data.push("BIOS".to_string());
+        }
+        if self.is_device {
+            game_data.push(tr("Device").to_string());
+        }
+        if self.is_mechanical {
+            game_data.push(tr("Mechanical").to_string());
+        }
+        if !self.runnable {
+            game_data.push(tr("Not runnable").to_string());
+        }
+        if let Some(driver_status) = &self.driver_status {
+            game_data.push(format!("{}: {}", tr("Driver"), driver_status));
+        }
+        if let Some(software_list) = &self.software_list {
+            game_data.push(format!("{}: {}", tr("Software list"), software_list));
         }
 
         let name_and_desc = match self.info_description {
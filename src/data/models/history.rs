@@ -0,0 +1,385 @@
+use std::{cmp::Ordering, collections::{HashMap, HashSet}, fmt::{self, Display}};
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use crate::i18n::tr;
+
+/// The set counts of a report, stored so the completeness of a source can be followed over time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportHistoryEntry {
+    pub id: Option<u32>,
+    pub source: String,
+    pub date_time: String,
+    pub rom_mode: String,
+    pub complete: u32,
+    pub fixable: u32,
+    pub incomplete: u32,
+    pub missing: u32,
+}
+
+impl ReportHistoryEntry {
+    /// There is a regression when sets stop being complete, or there are more incomplete or missing sets than before
+    pub fn is_regression_from(&self, previous: &ReportHistoryEntry) -> bool {
+        self.complete < previous.complete ||
+            self.incomplete > previous.incomplete ||
+            self.missing > previous.missing
+    }
+}
+
+impl ReportHistoryEntry {
+    /// The sets with some roms but not complete, fixable or not
+    pub fn partial(&self) -> u32 {
+        self.fixable + self.incomplete
+    }
+
+    /// The percentage of the sets checked that are complete
+    pub fn completeness(&self) -> f64 {
+        let total = self.complete + self.partial() + self.missing;
+        if total == 0 {
+            return 0.0;
+        }
+        self.complete as f64 * 100.0 / total as f64
+    }
+}
+
+impl Display for ReportHistoryEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(id) = self.id {
+            write!(f, "[{}] ", id)?;
+        }
+        write!(f, "{} {} ({}) - {}: {}, {}: {}, {}: {}, {}: {}",
+            self.date_time, self.source, self.rom_mode, tr("Complete"), self.complete, tr("Fixable"), self.fixable,
+            tr("Incomplete"), self.incomplete, tr("Missing"), self.missing)
+    }
+}
+
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// The change in the set counts between two reports of a source
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProgressDelta {
+    /// The date of the previous report
+    pub since: String,
+    pub complete: i64,
+    pub partial: i64,
+    pub missing: i64,
+    /// In percentage points
+    pub completeness: f64,
+}
+
+impl ProgressDelta {
+    pub fn between(previous: &ReportHistoryEntry, current: &ReportHistoryEntry) -> Self {
+        ProgressDelta {
+            since: previous.date_time.to_owned(),
+            complete: current.complete as i64 - previous.complete as i64,
+            partial: current.partial() as i64 - previous.partial() as i64,
+            missing: current.missing as i64 - previous.missing as i64,
+            completeness: current.completeness() - previous.completeness(),
+        }
+    }
+}
+
+impl Display for ProgressDelta {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:+} {}, {:+} {}, {:+} {} ({:+.2}%) {} {}",
+            self.complete, tr("complete"), self.partial, tr("partial"), self.missing, tr("missing"), self.completeness, tr("since"), self.since)
+    }
+}
+
+/// The reports of a source checked with the same rom mode, oldest first, to follow its completeness over time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceProgress {
+    pub source: String,
+    pub rom_mode: String,
+    pub entries: Vec<ReportHistoryEntry>,
+    /// The change from the previous report to the last one
+    pub delta: Option<ProgressDelta>,
+}
+
+impl SourceProgress {
+    /// Groups the history by source and rom mode, as the counts of different modes can't be compared.
+    /// Only the last reports of each group are kept, all of them if `last` is 0
+    pub fn from_history(entries: Vec<ReportHistoryEntry>, last: usize) -> Vec<SourceProgress> {
+        let mut progress: Vec<SourceProgress> = vec![];
+        for entry in entries {
+            match progress.iter_mut().find(|group| group.source == entry.source && group.rom_mode == entry.rom_mode) {
+                Some(group) => group.entries.push(entry),
+                None => progress.push(SourceProgress {
+                    source: entry.source.to_owned(),
+                    rom_mode: entry.rom_mode.to_owned(),
+                    entries: vec![entry],
+                    delta: None,
+                }),
+            }
+        }
+        for group in progress.iter_mut() {
+            if last > 0 && group.entries.len() > last {
+                group.entries.drain(..group.entries.len() - last);
+            }
+            group.delta = match group.entries.as_slice() {
+                [.., previous, current] => Some(ProgressDelta::between(previous, current)),
+                _ => None,
+            };
+        }
+        progress
+    }
+
+    pub fn latest(&self) -> Option<&ReportHistoryEntry> {
+        self.entries.last()
+    }
+
+    /// A bar per report with the height of its completeness, scaled between the lowest and the highest ones
+    /// so small changes in big collections can be seen
+    pub fn sparkline(&self) -> String {
+        let values = self.entries.iter().map(|entry| entry.completeness()).collect::<Vec<_>>();
+        let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        values.iter().map(|value| {
+            if (max - min).abs() < f64::EPSILON {
+                return SPARK_LEVELS[SPARK_LEVELS.len() / 2];
+            }
+            let level = ((value - min) / (max - min) * (SPARK_LEVELS.len() - 1) as f64).round() as usize;
+            SPARK_LEVELS[level]
+        }).collect()
+    }
+}
+
+impl Display for SourceProgress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({}) {}", self.source, self.rom_mode, self.sparkline())?;
+        if let Some(latest) = self.latest() {
+            write!(f, " {:.2}% {}", latest.completeness(), tr("complete"))?;
+        }
+        writeln!(f)?;
+        if let Some(delta) = &self.delta {
+            writeln!(f, "  {}", delta)?;
+        }
+        writeln!(f, "  {:<26} {:>9} {:>9} {:>9} {:>8}", tr("Date"), tr("Complete"), tr("Partial"), tr("Missing"), "%")?;
+        for entry in &self.entries {
+            writeln!(f, "  {:<26} {:>9} {:>9} {:>9} {:>7.2}%",
+                entry.date_time, entry.complete, entry.partial(), entry.missing, entry.completeness())?;
+        }
+        Ok(())
+    }
+}
+
+/// A game that was in the database and is not in the DAT imported over it, kept so the archives named after it can be explained
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameChange {
+    pub game_name: String,
+    /// The version of the last DAT with the game
+    pub last_version: Option<String>,
+    /// The version of the DAT without it
+    pub removed_in: Option<String>,
+    /// The game of the new DAT with the same roms, if it was renamed
+    pub successor: Option<String>,
+    pub date_time: String,
+}
+
+impl Display for GameChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.successor {
+            Some(successor) => write!(f, "{}: {} {}", self.game_name, tr("renamed to"), successor)?,
+            None => write!(f, "{}: {}", self.game_name, tr("removed"))?,
+        }
+        if let Some(removed_in) = &self.removed_in {
+            write!(f, " {} {}", tr("in"), removed_in)?;
+        }
+        if let Some(last_version) = &self.last_version {
+            write!(f, " ({} {})", tr("last in"), last_version)?;
+        }
+        write!(f, " {} {}", tr("on"), self.date_time)
+    }
+}
+
+/// An old name of a game, so the archives and searches using it still find the game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameAlias {
+    pub alias: String,
+    pub game_name: String,
+}
+
+impl GameAlias {
+    /// The alias for the name a renamed game had
+    pub fn from_change(change: &GameChange) -> Option<Self> {
+        change.successor.as_ref().map(|successor| GameAlias { alias: change.game_name.to_owned(), game_name: successor.to_owned() })
+    }
+
+    /// Reads a list of renames, a line per game with the old and the new name separated by spaces, a comma or `=`.
+    /// The lines starting with `#` or `;` are comments
+    pub fn parse_list(content: &str) -> Result<Vec<Self>> {
+        let mut aliases = vec![];
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            let names = line.split(|c: char| c.is_whitespace() || c == ',' || c == '=').filter(|name| !name.is_empty()).collect::<Vec<_>>();
+            match names.as_slice() {
+                [alias, game_name] => aliases.push(GameAlias { alias: alias.to_string(), game_name: game_name.to_string() }),
+                _ => return Err(anyhow!("Line {} is not an old and a new name: `{}`", number + 1, line)),
+            }
+        }
+        Ok(aliases)
+    }
+}
+
+impl Display for GameAlias {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} -> {}", self.alias, self.game_name)
+    }
+}
+
+/// The games of a database with the checksums of their roms, to compare them with the ones of a DAT imported over it
+#[derive(Debug, Default)]
+pub struct GamesSnapshot {
+    pub version: Option<String>,
+    pub games: HashMap<String, HashSet<String>>,
+}
+
+impl GamesSnapshot {
+    /// The games not in the current snapshot. A removed game is renamed if a new game has at least half of its roms,
+    /// the one sharing the most if there are several
+    pub fn find_changes(&self, current: &GamesSnapshot, date_time: &str) -> Vec<GameChange> {
+        let mut new_games = current.games.iter().filter(|(game_name, _)| !self.games.contains_key(*game_name)).collect::<Vec<_>>();
+        new_games.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut removed_games = self.games.iter().filter(|(game_name, _)| !current.games.contains_key(*game_name)).collect::<Vec<_>>();
+        removed_games.sort_by(|a, b| a.0.cmp(b.0));
+
+        removed_games.into_iter().map(|(game_name, roms)| {
+            let successor = new_games.iter()
+                .map(|(new_name, new_roms)| (*new_name, roms.intersection(new_roms).count()))
+                .filter(|(_, shared)| *shared > 0 && shared * 2 >= roms.len())
+                .fold(None, |best: Option<(&String, usize)>, candidate| match best {
+                    Some(best) if best.1 >= candidate.1 => Some(best),
+                    _ => Some(candidate),
+                })
+                .map(|(new_name, _)| new_name.to_string());
+            GameChange {
+                game_name: game_name.to_string(),
+                last_version: self.version.clone(),
+                removed_in: current.version.clone(),
+                successor,
+                date_time: date_time.to_string(),
+            }
+        }).collect()
+    }
+}
+
+/// The roms of each game of a database or a DAT, by name with their sha1, or their crc if they don't have it
+#[derive(Debug, Default)]
+pub struct DatContents {
+    pub version: Option<String>,
+    pub games: HashMap<String, HashMap<String, String>>,
+}
+
+impl DatContents {
+    pub fn get_snapshot(&self) -> GamesSnapshot {
+        GamesSnapshot {
+            version: self.version.clone(),
+            games: self.games.iter().map(|(game_name, roms)| (game_name.to_owned(), roms.values().cloned().collect())).collect(),
+        }
+    }
+}
+
+/// Compares the numbers in the versions, so `0.99` comes before `0.250` and No-Intro dates like `20240101-123456` work too
+pub fn compare_versions(version: &str, other: &str) -> Ordering {
+    let numbers = |version: &str| -> Vec<u64> {
+        version.split(|c: char| !c.is_ascii_digit()).filter_map(|number| number.parse().ok()).collect()
+    };
+    numbers(version).cmp(&numbers(other))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(complete: u32, fixable: u32, incomplete: u32, missing: u32) -> ReportHistoryEntry {
+        ReportHistoryEntry {
+            id: None,
+            source: "/roms".to_string(),
+            date_time: "2021-01-01T00:00:00+00:00".to_string(),
+            rom_mode: "Split".to_string(),
+            complete, fixable, incomplete, missing
+        }
+    }
+
+    #[test]
+    fn detects_regressions() {
+        let previous = entry(10, 2, 3, 1);
+        assert!(!entry(10, 2, 3, 1).is_regression_from(&previous));
+        assert!(!entry(12, 0, 3, 1).is_regression_from(&previous));
+        assert!(entry(9, 2, 3, 1).is_regression_from(&previous));
+        assert!(entry(10, 1, 4, 1).is_regression_from(&previous));
+        assert!(entry(10, 2, 2, 2).is_regression_from(&previous));
+    }
+
+    #[test]
+    fn computes_the_completeness() {
+        let entry = entry(6, 1, 1, 2);
+        assert_eq!(entry.partial(), 2);
+        assert!((entry.completeness() - 60.0).abs() < f64::EPSILON);
+        assert!(ReportHistoryEntry { complete: 0, fixable: 0, incomplete: 0, missing: 0, ..entry }.completeness().abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn finds_removed_and_renamed_games() {
+        let snapshot = |version: &str, games: Vec<(&str, Vec<&str>)>| GamesSnapshot {
+            version: Some(version.to_string()),
+            games: games.into_iter().map(|(name, roms)| (name.to_string(), roms.into_iter().map(|rom| rom.to_string()).collect())).collect(),
+        };
+        let previous = snapshot("0.250", vec![("pacman", vec!["a", "b"]), ("galaga", vec!["c", "d"]), ("gone", vec!["e"])]);
+        let current = snapshot("0.251", vec![("pacman", vec!["a", "b"]), ("galagao", vec!["c", "d", "f"]), ("other", vec!["d"])]);
+
+        let changes = previous.find_changes(&current, "2021-01-01T00:00:00+00:00");
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].game_name, "galaga");
+        assert_eq!(changes[0].successor, Some("galagao".to_string()));
+        assert_eq!(changes[0].removed_in, Some("0.251".to_string()));
+        assert_eq!(changes[1].game_name, "gone");
+        assert_eq!(changes[1].successor, None);
+        assert_eq!(changes[1].last_version, Some("0.250".to_string()));
+    }
+
+    #[test]
+    fn reads_a_list_of_renames() -> Result<()> {
+        let aliases = GameAlias::parse_list("# MAME renames\ngalaga galagao\n\nmspac,mspacman\n; old = new\npuckman = pacman\n")?;
+        assert_eq!(aliases, vec![
+            GameAlias { alias: "galaga".to_string(), game_name: "galagao".to_string() },
+            GameAlias { alias: "mspac".to_string(), game_name: "mspacman".to_string() },
+            GameAlias { alias: "puckman".to_string(), game_name: "pacman".to_string() },
+        ]);
+        assert!(GameAlias::parse_list("galaga").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn compares_versions() {
+        assert_eq!(compare_versions("0.99", "0.250"), Ordering::Less);
+        assert_eq!(compare_versions("0.250", "0.250"), Ordering::Equal);
+        assert_eq!(compare_versions("20240102-000000", "20240101-123456"), Ordering::Greater);
+    }
+
+    #[test]
+    fn follows_the_progress_of_each_source() {
+        let at = |source: &str, day: u32, complete: u32, missing: u32| ReportHistoryEntry {
+            source: source.to_string(),
+            date_time: format!("2021-01-{:02}T00:00:00+00:00", day),
+            ..entry(complete, 0, 1, missing)
+        };
+        let history = vec![at("/roms", 1, 2, 7), at("/other", 1, 5, 5), at("/roms", 2, 4, 5), at("/roms", 3, 9, 0), at("/roms", 4, 6, 3)];
+
+        let progress = SourceProgress::from_history(history.clone(), 3);
+        assert_eq!(progress.len(), 2);
+        assert_eq!(progress[0].source, "/roms");
+        assert_eq!(progress[0].entries.len(), 3);
+        assert_eq!(progress[0].sparkline(), "▁█▄");
+        assert_eq!(progress[0].delta, Some(ProgressDelta {
+            since: "2021-01-03T00:00:00+00:00".to_string(), complete: -3, partial: 0, missing: 3, completeness: -30.0
+        }));
+        assert_eq!(progress[1].sparkline(), "▅");
+        assert_eq!(progress[1].delta, None);
+        assert_eq!(SourceProgress::from_history(history, 0)[0].entries.len(), 4);
+    }
+}
@@ -4,8 +4,27 @@ pub mod set;
 pub mod game;
 pub mod file;
 pub mod disk;
+pub mod dat;
+pub mod entry;
+pub mod search;
+pub mod history;
+pub mod profile;
+pub mod release;
+pub mod edit;
+pub mod set_list;
+
+/// The folders of the sets are named with a trailing slash in the reports, as they have no extension to tell them apart
+pub const FOLDER_SUFFIX: char = '/';
+
+/// The name a folder of a set has in the reports
+pub fn get_folder_file_name(folder_name: &str) -> String {
+    format!("{}{}", folder_name, FOLDER_SUFFIX)
+}
 
 pub fn get_set_from_file(file: &str) -> String {
+    if let Some(folder_name) = file.strip_suffix(FOLDER_SUFFIX) {
+        return folder_name.to_string();
+    }
     let file_path = Path::new(file);
     if let Some(set_name) = file_path.file_stem() {
         set_name.to_string_lossy().to_string()
@@ -15,6 +34,9 @@ pub fn get_set_from_file(file: &str) -> String {
 }
 
 pub fn does_file_belong_to_set(file: &str, set: &str) -> bool {
+    if let Some(folder_name) = file.strip_suffix(FOLDER_SUFFIX) {
+        return folder_name == set;
+    }
     let file_path = Path::new(file);
     if is_extension_for_file_set(&file_path) {
         if let Some(set_name) = file_path.file_stem() {
@@ -29,7 +51,7 @@ pub fn does_file_belong_to_set(file: &str, set: &str) -> bool {
 
 fn is_extension_for_file_set(file: &impl AsRef<Path>) -> bool {
     if let Some(extension) = file.as_ref().extension() {
-        return extension.eq("zip");
+        return extension.eq("zip") || extension.eq("7z") || extension.eq("rar");
     }
 
     false
@@ -37,11 +59,21 @@ fn is_extension_for_file_set(file: &impl AsRef<Path>) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use super::does_file_belong_to_set;
+    use super::{does_file_belong_to_set, get_folder_file_name, get_set_from_file};
 
     #[test]
     pub fn should_identify_a_set() {
-        assert!(does_file_belong_to_set("set.zip", "set"))
+        assert!(does_file_belong_to_set("set.zip", "set"));
+        assert!(does_file_belong_to_set("set.7z", "set"));
+        assert!(does_file_belong_to_set("set.rar", "set"))
+    }
+
+    #[test]
+    pub fn should_identify_a_set_folder() {
+        let folder = get_folder_file_name("set.v2");
+        assert!(does_file_belong_to_set(&folder, "set.v2"));
+        assert_eq!("set.v2", get_set_from_file(&folder));
+        assert!(!does_file_belong_to_set(&folder, "set"));
     }
 
     #[test]
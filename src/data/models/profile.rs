@@ -0,0 +1,262 @@
+use std::{collections::BTreeMap, fs::File, io::BufReader, path::{Path, PathBuf}, str::FromStr};
+use anyhow::{Result, anyhow};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{RomsetMode, data::{reader::MatchPolicy, reporter::ReportScope}};
+use super::{release::RegionPriorities, search::{ReleaseFilter, glob_to_regex}};
+
+const DB_EXTENSION: &str = "rst";
+/// The `skippers` of a profile with only the header skippers shipped with Romst
+const BUILTIN_SKIPPERS: &str = "builtin";
+
+/// The database files in a directory, sorted by name
+pub fn find_databases(directory: &impl AsRef<Path>) -> Result<Vec<String>> {
+    let mut databases = vec![];
+    for entry in directory.as_ref().read_dir()? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|extension| extension == DB_EXTENSION) {
+            databases.push(path.to_string_lossy().to_string());
+        }
+    }
+    databases.sort();
+    Ok(databases)
+}
+
+/// How the files of a directory are checked: against which database, in which mode and which files are skipped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanProfile {
+    pub db: String,
+    /// `merged`, `split` or `non-merged`, non merged by default
+    #[serde(default)]
+    pub mode: Option<String>,
+    /// `all` to report the sets without any file found as missing
+    #[serde(default)]
+    pub scope: Option<String>,
+    /// Only the sets with this tag are reported
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// Only these sets are reported, the names can be glob patterns
+    #[serde(default)]
+    pub sets: Option<Vec<String>>,
+    /// Only the sets released in this region are reported, like `USA` in No-Intro DATs
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Only the sets released in this language are reported, like `En`
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Only one set of each parent/clone family is reported (1G1R), the one of the first of these regions, comma separated
+    #[serde(default)]
+    pub prefer: Option<String>,
+    /// The languages preferred when choosing the set of each family, comma separated
+    #[serde(default)]
+    pub prefer_language: Option<String>,
+    /// Glob patterns of the names of the files and directories that are not checked
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// The checksums that have to agree for a file to match a rom: `any`, `sha1`, `two-of-three` or `crc-size`.
+    /// The one of the command line when not set
+    #[serde(default)]
+    pub checks: Option<String>,
+    /// The header skippers: `builtin` for the ones shipped with Romst, or a directory with more of them. The ones of
+    /// the command line when not set
+    #[serde(default)]
+    pub skippers: Option<String>,
+}
+
+impl ScanProfile {
+    pub fn get_rom_mode(&self) -> Result<RomsetMode> {
+        match &self.mode {
+            Some(mode) => RomsetMode::from_str(mode),
+            None => Ok(RomsetMode::default())
+        }
+    }
+
+    pub fn get_match_policy(&self) -> Result<Option<MatchPolicy>> {
+        self.checks.as_deref().map(MatchPolicy::from_str).transpose()
+    }
+
+    /// The directory with the header skippers of the profile, if they are not only the builtin ones
+    pub fn get_skippers_dir(&self) -> Option<&str> {
+        self.skippers.as_deref().filter(|skippers| *skippers != BUILTIN_SKIPPERS)
+    }
+
+    pub fn get_scope(&self) -> ReportScope {
+        match self.scope.as_deref() {
+            Some("all") => ReportScope::All,
+            _ => ReportScope::Present
+        }
+    }
+
+    pub fn get_releases(&self) -> ReleaseFilter {
+        ReleaseFilter {
+            region: self.region.clone(),
+            language: self.language.clone(),
+            prefer: self.prefer.as_deref().map(|regions| RegionPriorities::parse(regions, self.prefer_language.as_deref())),
+        }
+    }
+
+    pub fn is_excluded(&self, path: &impl AsRef<Path>) -> bool {
+        let file_name = path.as_ref().file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        self.exclude.iter()
+            .filter_map(|glob| Regex::new(&glob_to_regex(glob)).ok())
+            .any(|pattern| pattern.is_match(&file_name))
+    }
+}
+
+/// Profiles by name, and the directories they are used for. A profile is used for the subdirectories of
+/// its directory too, unless they have a profile of their own.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ScanProfiles {
+    /// The directory with the databases, the databases of the profiles are relative to it
+    #[serde(default)]
+    pub db_directory: Option<String>,
+    pub profiles: BTreeMap<String, ScanProfile>,
+    pub directories: BTreeMap<String, String>,
+}
+
+impl ScanProfiles {
+    /// Relative paths of the directories and databases are relative to the profiles file
+    pub fn load(profiles_file: &impl AsRef<Path>) -> Result<Self> {
+        let profiles_file = profiles_file.as_ref();
+        let mut profiles: ScanProfiles = serde_json::from_reader(BufReader::new(File::open(profiles_file)?))?;
+        let base_dir = profiles_file.parent().unwrap_or_else(|| Path::new(""));
+        profiles.db_directory = profiles.db_directory.map(|db_directory| base_dir.join(db_directory).to_string_lossy().to_string());
+        let db_dir = profiles.db_directory.as_ref().map(Path::new).unwrap_or(base_dir);
+
+        for (name, profile) in profiles.profiles.iter_mut() {
+            profile.get_rom_mode().map_err(|e| anyhow!("Profile {}: {}", name, e))?;
+            profile.get_match_policy().map_err(|e| anyhow!("Profile {}: {}", name, e))?;
+            profile.db = db_dir.join(&profile.db).to_string_lossy().to_string();
+            if let Some(skippers_dir) = profile.get_skippers_dir() {
+                profile.skippers = Some(base_dir.join(skippers_dir).to_string_lossy().to_string());
+            }
+        }
+        let mut directories = BTreeMap::new();
+        for (directory, name) in profiles.directories {
+            if !profiles.profiles.contains_key(&name) {
+                return Err(anyhow!("The directory {} uses the profile {}, but there is no profile with that name", directory, name));
+            }
+            directories.insert(base_dir.join(&directory).to_string_lossy().to_string(), name);
+        }
+        profiles.directories = directories;
+
+        Ok(profiles)
+    }
+
+    /// The profile of the closest directory containing this one
+    pub fn get_profile(&self, directory: &impl AsRef<Path>) -> Option<(&str, &ScanProfile)> {
+        let directory = std::fs::canonicalize(directory).ok()?;
+        self.directories.iter()
+            .filter_map(|(profile_dir, name)| std::fs::canonicalize(profile_dir).ok().map(|profile_dir| (profile_dir, name)))
+            .filter(|(profile_dir, _)| directory.starts_with(profile_dir))
+            .max_by_key(|(profile_dir, _)| profile_dir.components().count())
+            .and_then(|(_, name)| self.profiles.get_key_value(name))
+            .map(|(name, profile)| (name.as_str(), profile))
+    }
+
+    /// The databases in the database directory, and the ones of the profiles
+    pub fn get_databases(&self) -> Result<Vec<String>> {
+        let mut databases = self.profiles.values().map(|profile| profile.db.clone()).collect::<Vec<_>>();
+        if let Some(db_directory) = &self.db_directory {
+            databases.extend(find_databases(db_directory)?);
+        }
+        databases.sort();
+        databases.dedup();
+        Ok(databases)
+    }
+
+    /// The configured directories and their subdirectories, skipping the excluded ones
+    pub fn get_all_directories(&self) -> Result<Vec<PathBuf>> {
+        let mut directories = vec![];
+        for directory in self.directories.keys() {
+            directories.extend(self.get_directories(directory)?);
+        }
+        directories.sort();
+        directories.dedup();
+        Ok(directories)
+    }
+
+    /// The directory and its subdirectories with a profile, skipping the excluded ones
+    pub fn get_directories(&self, root: &impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let mut directories = vec![];
+        let mut pending = vec![root.as_ref().to_path_buf()];
+        while let Some(directory) = pending.pop() {
+            let profile = self.get_profile(&directory).map(|(_, profile)| profile);
+            if profile.is_some_and(|profile| profile.is_excluded(&directory) && directory != root.as_ref()) {
+                continue;
+            }
+            if profile.is_some() {
+                directories.push(directory.clone());
+            }
+            for entry in directory.read_dir()? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    pending.push(path);
+                }
+            }
+        }
+        directories.sort();
+        Ok(directories)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_profile_of_each_directory() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("romst_profiles_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("roms").join("arcade").join("neogeo"))?;
+        std::fs::create_dir_all(dir.join("roms").join("arcade").join("extras"))?;
+        std::fs::create_dir_all(dir.join("roms").join("other"))?;
+        std::fs::write(dir.join("profiles.json"), r#"{
+            "profiles": {
+                "arcade": { "db": "mame.rst", "mode": "split", "exclude": ["extras", "*.txt"], "checks": "sha1", "skippers": "builtin" },
+                "neogeo": { "db": "dbs/neogeo.rst", "scope": "all", "skippers": "skippers" }
+            },
+            "directories": { "roms/arcade": "arcade", "roms/arcade/neogeo": "neogeo" }
+        }"#)?;
+
+        let profiles = ScanProfiles::load(&dir.join("profiles.json"))?;
+        let (name, profile) = profiles.get_profile(&dir.join("roms").join("arcade").join("neogeo")).unwrap();
+        assert_eq!(name, "neogeo");
+        assert_eq!(profile.db, dir.join("dbs/neogeo.rst").to_string_lossy());
+        assert_eq!(profile.get_scope(), ReportScope::All);
+        assert_eq!(profiles.get_profile(&dir.join("roms").join("arcade")).map(|(name, _)| name), Some("arcade"));
+        assert!(profiles.get_profile(&dir.join("roms").join("other")).is_none());
+        assert!(profiles.profiles["arcade"].is_excluded(&"roms/readme.txt"));
+        assert!(!profiles.profiles["arcade"].is_excluded(&"roms/pacman.zip"));
+        assert_eq!(profiles.profiles["arcade"].get_match_policy()?, Some(MatchPolicy::Sha1));
+        assert_eq!(profiles.profiles["arcade"].get_skippers_dir(), None);
+        assert_eq!(profile.get_match_policy()?, None);
+        assert_eq!(profile.get_skippers_dir().map(str::to_string), Some(dir.join("skippers").to_string_lossy().to_string()));
+
+        let directories = profiles.get_directories(&dir.join("roms"))?;
+        assert_eq!(directories, vec![dir.join("roms").join("arcade"), dir.join("roms").join("arcade").join("neogeo")]);
+        assert_eq!(profiles.get_all_directories()?, directories);
+
+        std::fs::create_dir_all(dir.join("dbs"))?;
+        std::fs::write(dir.join("dbs").join("nes.rst"), "")?;
+        std::fs::write(dir.join("dbs.json"), r#"{
+            "db_directory": "dbs",
+            "profiles": { "arcade": { "db": "mame.rst" } },
+            "directories": { "roms/arcade": "arcade" }
+        }"#)?;
+        let profiles = ScanProfiles::load(&dir.join("dbs.json"))?;
+        assert_eq!(profiles.profiles["arcade"].db, dir.join("dbs").join("mame.rst").to_string_lossy());
+        assert_eq!(profiles.get_databases()?, vec![
+            dir.join("dbs").join("mame.rst").to_string_lossy().to_string(),
+            dir.join("dbs").join("nes.rst").to_string_lossy().to_string(),
+        ]);
+
+        std::fs::write(dir.join("wrong.json"), r#"{ "profiles": {}, "directories": { "roms": "arcade" } }"#)?;
+        assert!(ScanProfiles::load(&dir.join("wrong.json")).is_err());
+        std::fs::write(dir.join("wrong.json"), r#"{ "profiles": { "arcade": { "db": "mame.rst", "checks": "md4" } }, "directories": {} }"#)?;
+        assert!(ScanProfiles::load(&dir.join("wrong.json")).is_err());
+        std::fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
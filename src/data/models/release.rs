@@ -0,0 +1,162 @@
+use std::{collections::{HashMap, HashSet}, fmt};
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::tr;
+use super::game::Game;
+
+/// A release of a game in a region, as listed in No-Intro and other Logiqx DATs
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Release {
+    pub name: String,
+    pub region: String,
+    /// The language codes, comma separated (e.g. `En,Fr`)
+    pub language: Option<String>,
+    pub date: Option<String>,
+    /// The release picked for the game when there are several
+    pub default: bool,
+}
+
+impl Release {
+    pub fn new(name: String, region: String) -> Self { Self { name, region, language: None, date: None, default: false } }
+
+    pub fn has_language(&self, language: &str) -> bool {
+        self.language.as_ref().is_some_and(|languages| languages.split(',').any(|code| code.trim().eq_ignore_ascii_case(language)))
+    }
+}
+
+impl fmt::Display for Release {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut release_data = vec![];
+        if let Some(language) = &self.language {
+            release_data.push(format!("{}: {}", tr("language"), language));
+        }
+        if let Some(date) = &self.date {
+            release_data.push(format!("{}: {}", tr("date"), date));
+        }
+        if self.default {
+            release_data.push("default".to_string());
+        }
+
+        write!(f, "{} [{}]", self.region, self.name)?;
+        if !release_data.is_empty() {
+            write!(f, " ({})", release_data.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// How preferred a game is, the lower the better. The fields are compared in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Score {
+    region_rank: usize,
+    language_rank: usize,
+    /// Not from a default release
+    not_default: bool,
+}
+
+/// The game kept of a family so far
+struct FamilyChoice<'a> {
+    game: &'a Game,
+    score: Score,
+    is_clone: bool,
+}
+
+/// The regions and languages wanted, the first ones preferred, to keep only one game of each parent/clone family (1G1R)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegionPriorities {
+    pub regions: Vec<String>,
+    #[serde(default)]
+    pub languages: Vec<String>,
+}
+
+impl RegionPriorities {
+    /// From comma separated lists, like `USA,World,Europe` and `En,Fr`
+    pub fn parse(regions: &str, languages: Option<&str>) -> Self {
+        let split = |list: &str| list.split(',').map(|value| value.trim().to_string()).filter(|value| !value.is_empty()).collect();
+        Self { regions: split(regions), languages: languages.map(split).unwrap_or_default() }
+    }
+
+    /// The position of the first of the values in the priorities, after the last one if none is there
+    fn rank<'a>(priorities: &[String], values: impl Iterator<Item = &'a str>) -> usize {
+        values.filter_map(|value| priorities.iter().position(|priority| priority.eq_ignore_ascii_case(value)))
+            .min()
+            .unwrap_or(priorities.len())
+    }
+
+    /// How preferred a game is, the lower the better, by its most preferred release. Without releases, the regions and
+    /// languages in parentheses in its name are used, as in `Game (USA, Europe) (En,Fr)`
+    fn score(&self, game: &Game, releases: &[Release]) -> Score {
+        let release_scores = releases.iter().map(|release| {
+            let languages = release.language.as_deref().unwrap_or_default().split(',').map(|language| language.trim());
+            Score {
+                region_rank: RegionPriorities::rank(&self.regions, std::iter::once(release.region.as_str())),
+                language_rank: RegionPriorities::rank(&self.languages, languages),
+                not_default: !release.default,
+            }
+        });
+        let tags = game.name.split('(').skip(1).filter_map(|tag| tag.split(')').next())
+            .flat_map(|tag| tag.split(',').map(|value| value.trim())).collect::<Vec<_>>();
+        let name_score = Score {
+            region_rank: RegionPriorities::rank(&self.regions, tags.iter().copied()),
+            language_rank: RegionPriorities::rank(&self.languages, tags.iter().copied()),
+            not_default: true,
+        };
+        release_scores.min().unwrap_or(name_score)
+    }
+
+    /// The game kept of each family: the one with the most preferred region, then language. When tied, the one with a
+    /// default release and then the parent are kept, so a family without any of the regions keeps its parent. The games
+    /// without a parent in the list are the parents of their families
+    pub fn select<'a>(&self, games: impl IntoIterator<Item = &'a Game>, releases: &HashMap<String, Vec<Release>>) -> HashSet<String> {
+        let mut families: HashMap<&str, FamilyChoice> = HashMap::new();
+        for game in games {
+            let family = game.clone_of.as_deref().unwrap_or(&game.name);
+            let score = self.score(game, releases.get(&game.name).map(|releases| releases.as_slice()).unwrap_or_default());
+            let is_clone = game.clone_of.is_some();
+            let preferred = match families.get(family) {
+                Some(kept) => (score, is_clone, &game.name) < (kept.score, kept.is_clone, &kept.game.name),
+                None => true
+            };
+            if preferred {
+                families.insert(family, FamilyChoice { game, score, is_clone });
+            }
+        }
+
+        families.into_values().map(|kept| kept.game.name.to_owned()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(name: &str, clone_of: Option<&str>) -> Game {
+        let mut game = Game::new(name.to_string());
+        game.clone_of = clone_of.map(|parent| parent.to_string());
+        game
+    }
+
+    #[test]
+    fn selects_one_game_of_each_family() {
+        let games = vec![
+            game("Game (Japan)", None),
+            game("Game (USA)", Some("Game (Japan)")),
+            game("Game (Europe) (En,Fr,De)", Some("Game (Japan)")),
+            game("Other (Japan)", None),
+            game("Other (Japan) (Rev 1)", Some("Other (Japan)")),
+            game("Third", None),
+            game("Third (Alt)", Some("Third")),
+        ];
+        let mut releases = HashMap::new();
+        let mut release = Release::new("Third (Alt)".to_string(), "EUR".to_string());
+        release.language = Some("Fr".to_string());
+        releases.insert("Third (Alt)".to_string(), vec![release]);
+
+        let selected = RegionPriorities::parse("USA, Europe", None).select(&games, &releases);
+        assert_eq!(selected, HashSet::from(["Game (USA)".to_string(), "Other (Japan)".to_string(), "Third".to_string()]));
+
+        let priorities = RegionPriorities::parse("Europe,EUR,USA", Some("Fr"));
+        let selected = priorities.select(&games, &releases);
+        assert_eq!(selected, HashSet::from(["Game (Europe) (En,Fr,De)".to_string(), "Other (Japan)".to_string(), "Third (Alt)".to_string()]));
+    }
+}
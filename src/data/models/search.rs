@@ -0,0 +1,240 @@
+use anyhow::Result;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use super::{game::Game, release::{RegionPriorities, Release}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternKind {
+    Glob,
+    Regex,
+}
+
+/// Filter used to search games by name or description, plus some optional metadata
+#[derive(Debug)]
+pub struct GameFilter {
+    pattern: Regex,
+    pub manufacturer: Option<String>,
+    pub year: Option<String>,
+    pub clones: Option<bool>,
+    pub bios: Option<bool>,
+    pub devices: Option<bool>,
+    pub mechanical: Option<bool>,
+    pub runnable: Option<bool>,
+    /// The status of the MAME driver, e.g. `good`, `imperfect` or `preliminary`
+    pub driver_status: Option<String>,
+    /// Checked by the reader, as the releases are not part of the game
+    pub releases: ReleaseFilter,
+}
+
+/// Filter used to search roms by their file name, in all the sets
+#[derive(Debug)]
+pub struct RomFilter {
+    pattern: Regex,
+}
+
+/// Filter of the games by their releases, a game matches if any of its releases is in the region and the language
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseFilter {
+    pub region: Option<String>,
+    pub language: Option<String>,
+    /// Only the preferred game of each parent/clone family (1G1R), among the ones in the region and the language
+    #[serde(default)]
+    pub prefer: Option<RegionPriorities>,
+}
+
+impl GameFilter {
+    pub fn new<S>(pattern: S, kind: PatternKind) -> Result<Self> where S: AsRef<str> {
+        let pattern = build_pattern(pattern.as_ref(), kind)?;
+
+        Ok(Self { pattern, manufacturer: None, year: None, clones: None,
+            bios: None, devices: None, mechanical: None, runnable: None, driver_status: None, releases: ReleaseFilter::default() })
+    }
+
+    pub fn matches(&self, game: &Game) -> bool {
+        self.matches_with_aliases(game, &[])
+    }
+
+    /// The pattern is also matched with the old names of the game
+    pub fn matches_with_aliases(&self, game: &Game, aliases: &[String]) -> bool {
+        let pattern_match = self.pattern.is_match(&game.name) ||
+            game.info_description.as_ref().is_some_and(|description| self.pattern.is_match(description)) ||
+            aliases.iter().any(|alias| self.pattern.is_match(alias));
+        if !pattern_match {
+            return false;
+        }
+
+        if let Some(manufacturer) = &self.manufacturer {
+            let manufacturer = manufacturer.to_lowercase();
+            if !game.info_manufacturer.as_ref().is_some_and(|m| m.to_lowercase().contains(&manufacturer)) {
+                return false;
+            }
+        }
+
+        if let Some(year) = &self.year {
+            if game.info_year.as_ref() != Some(year) {
+                return false;
+            }
+        }
+
+        if let Some(driver_status) = &self.driver_status {
+            if !game.driver_status.as_ref().is_some_and(|status| status.eq_ignore_ascii_case(driver_status)) {
+                return false;
+            }
+        }
+
+        let flags = [
+            (self.clones, game.clone_of.is_some()),
+            (self.bios, game.is_bios),
+            (self.devices, game.is_device),
+            (self.mechanical, game.is_mechanical),
+            (self.runnable, game.runnable),
+        ];
+        flags.iter().all(|(wanted, value)| wanted.is_none_or(|wanted| wanted == *value))
+    }
+}
+
+impl RomFilter {
+    pub fn new<S>(pattern: S, kind: PatternKind) -> Result<Self> where S: AsRef<str> {
+        Ok(Self { pattern: build_pattern(pattern.as_ref(), kind)? })
+    }
+
+    pub fn matches<S>(&self, rom_name: S) -> bool where S: AsRef<str> {
+        self.pattern.is_match(rom_name.as_ref())
+    }
+}
+
+impl ReleaseFilter {
+    pub fn is_empty(&self) -> bool {
+        !self.filters_releases() && self.prefer.is_none()
+    }
+
+    /// Only the games with a release in the region and the language are kept
+    pub fn filters_releases(&self) -> bool {
+        self.region.is_some() || self.language.is_some()
+    }
+
+    pub fn matches(&self, release: &Release) -> bool {
+        self.region.as_ref().is_none_or(|region| release.region.eq_ignore_ascii_case(region)) &&
+            self.language.as_ref().is_none_or(|language| release.has_language(language))
+    }
+}
+
+fn build_pattern(pattern: &str, kind: PatternKind) -> Result<Regex> {
+    let pattern = match kind {
+        PatternKind::Glob => glob_to_regex(pattern),
+        PatternKind::Regex => pattern.to_string(),
+    };
+    Ok(RegexBuilder::new(&pattern).case_insensitive(true).build()?)
+}
+
+/// Glob patterns match the whole text, with `*` and `?` as wildcards
+pub(crate) fn glob_to_regex(glob: &str) -> String {
+    let mut pattern = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            _ => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(name: &str, description: &str, clone_of: Option<&str>) -> Game {
+        let mut game = Game::new(name.to_string());
+        game.info_description = Some(description.to_string());
+        game.info_manufacturer = Some("Acme".to_string());
+        game.info_year = Some("1999".to_string());
+        game.clone_of = clone_of.map(|c| c.to_string());
+        game
+    }
+
+    #[test]
+    fn matches_glob_and_regex() -> Result<()> {
+        let parent = game("pacman", "Pac-Man (Midway)", None);
+        let clone = game("pacmanf", "Pac-Man (fast)", Some("pacman"));
+
+        let filter = GameFilter::new("pac*", PatternKind::Glob)?;
+        assert!(filter.matches(&parent));
+        assert!(filter.matches(&clone));
+
+        let filter = GameFilter::new("pacman?", PatternKind::Glob)?;
+        assert!(!filter.matches(&parent));
+        assert!(filter.matches(&clone));
+
+        let filter = GameFilter::new(r"\(midway\)", PatternKind::Regex)?;
+        assert!(filter.matches(&parent));
+        assert!(!filter.matches(&clone));
+
+        let mut filter = GameFilter::new("pac*", PatternKind::Glob)?;
+        filter.clones = Some(false);
+        filter.manufacturer = Some("acme".to_string());
+        assert!(filter.matches(&parent));
+        assert!(!filter.matches(&clone));
+        filter.year = Some("1980".to_string());
+        assert!(!filter.matches(&parent));
+
+        Ok(())
+    }
+
+    #[test]
+    fn matches_machine_info() -> Result<()> {
+        let mut bios = game("neogeo", "Neo-Geo", None);
+        bios.is_bios = true;
+        let mut device = game("z80", "Zilog Z80", None);
+        device.is_device = true;
+        device.runnable = false;
+        let mut parent = game("pacman", "Pac-Man (Midway)", None);
+        parent.driver_status = Some("good".to_string());
+
+        let mut filter = GameFilter::new("*", PatternKind::Glob)?;
+        filter.bios = Some(false);
+        filter.devices = Some(false);
+        assert!(!filter.matches(&bios));
+        assert!(!filter.matches(&device));
+        assert!(filter.matches(&parent));
+
+        let mut filter = GameFilter::new("*", PatternKind::Glob)?;
+        filter.runnable = Some(false);
+        assert!(filter.matches(&device));
+        assert!(!filter.matches(&parent));
+
+        let mut filter = GameFilter::new("*", PatternKind::Glob)?;
+        filter.driver_status = Some("Good".to_string());
+        assert!(filter.matches(&parent));
+        assert!(!filter.matches(&bios));
+
+        Ok(())
+    }
+
+    #[test]
+    fn matches_rom_names() -> Result<()> {
+        let filter = RomFilter::new("*.6e", PatternKind::Glob)?;
+        assert!(filter.matches("pacman.6e"));
+        assert!(filter.matches("PACMAN.6E"));
+        assert!(!filter.matches("pacman.6f"));
+
+        let filter = RomFilter::new(r"^pacman\.6[ef]$", PatternKind::Regex)?;
+        assert!(filter.matches("pacman.6f"));
+        assert!(!filter.matches("mspacman.6f"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn matches_releases() {
+        let mut release = Release::new("Pac-Man (USA, Europe)".to_string(), "USA".to_string());
+        release.language = Some("En,Fr".to_string());
+
+        assert!(ReleaseFilter::default().matches(&release));
+        assert!(ReleaseFilter { region: Some("usa".to_string()), ..ReleaseFilter::default() }.matches(&release));
+        assert!(ReleaseFilter { region: Some("USA".to_string()), language: Some("fr".to_string()), prefer: None }.matches(&release));
+        assert!(!ReleaseFilter { region: Some("USA".to_string()), language: Some("De".to_string()), prefer: None }.matches(&release));
+        assert!(!ReleaseFilter { region: Some("EUR".to_string()), ..ReleaseFilter::default() }.matches(&release));
+    }
+}
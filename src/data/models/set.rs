@@ -1,8 +1,40 @@
-use std::{fmt::Display, writeln};
+use std::{fmt::Display, str::FromStr, writeln};
 
+use anyhow::{Result, anyhow};
+use crate::i18n::tr;
 use super::{file::DataFile, game::Game};
 use serde::{Deserialize, Serialize};
 
+/// How the sets are kept in a directory: an archive per set, or a folder per set with the roms unzipped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SetLayout {
+    #[default]
+    Archive,
+    /// The directories named like a set are that set, the roms are the files inside, in subdirectories too
+    Folder,
+}
+
+impl Display for SetLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SetLayout::Archive => write!(f, "{}", tr("Archive per set")),
+            SetLayout::Folder => write!(f, "{}", tr("Folder per set")),
+        }
+    }
+}
+
+impl FromStr for SetLayout {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "archive" => Ok(SetLayout::Archive),
+            "folder" => Ok(SetLayout::Folder),
+            _ => Err(anyhow!("Non valid layout, can be either `archive` or `folder`"))
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GameSet {
     pub game: Game,
@@ -10,35 +42,46 @@ pub struct GameSet {
     pub samples: Vec<DataFile>,
     pub disks: Vec<DataFile>,
     pub device_refs: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub note: Option<String>,
+    /// Encrypted files in the archive that couldn't be read with any of the passwords
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub locked: Vec<String>,
+    /// Files of a corrupt archive that failed when reading them, only when the rest of the archive is salvaged
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub corrupt: Vec<String>,
 }
 
 impl GameSet {
-    pub fn new(game: Game, roms: Vec<DataFile>, samples: Vec<DataFile>, disks: Vec<DataFile>, device_refs: Vec<String>) -> Self { Self { game, roms, samples, disks, device_refs } }
+    pub fn new(game: Game, roms: Vec<DataFile>, samples: Vec<DataFile>, disks: Vec<DataFile>, device_refs: Vec<String>) -> Self { Self { game, roms, samples, disks, device_refs, note: None, locked: vec![], corrupt: vec![] } }
 }
 
 impl Display for GameSet {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "{}", self.game)?;
+        if let Some(note) = &self.note {
+            writeln!(f, "{}: {}", tr("Note"), note)?;
+        }
         if !self.roms.is_empty() {
-            writeln!(f, "Roms:")?;
+            writeln!(f, "{}:", tr("Roms"))?;
             for rom in self.roms.as_slice() {
                 writeln!(f, "    - {}", rom)?;
             }
         }
         if !self.samples.is_empty() {
-            writeln!(f, "Samples:")?;
+            writeln!(f, "{}:", tr("Samples"))?;
             for sample in self.samples.as_slice() {
                 writeln!(f, "    - {}", sample)?;
             }
         }
         if !self.disks.is_empty() {
-            writeln!(f, "Disks:")?;
+            writeln!(f, "{}:", tr("Disks"))?;
             for disk in self.disks.as_slice() {
                 writeln!(f, "    - {}", disk)?;
             }
         }
         if !self.device_refs.is_empty() {
-            writeln!(f, "Depends on:")?;
+            writeln!(f, "{}:", tr("Depends on"))?;
             for device_ref in self.device_refs.as_slice() {
                 writeln!(f, "    - {}", device_ref)?;
             }
@@ -0,0 +1,179 @@
+use std::{collections::{BTreeMap, HashMap, HashSet}, fmt::{self, Display}, path::Path};
+use console::Style;
+use serde::{Deserialize, Serialize};
+use crate::{filesystem::names::NameRules, i18n::tr};
+use super::{FOLDER_SUFFIX, is_extension_for_file_set, release::RegionPriorities};
+
+/// What is done with the sets of a list imported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetListAction {
+    Tag(String),
+    Exclude,
+    /// A 1G1R list, the other games of the families of the sets are excluded
+    Keep,
+}
+
+/// The sets written to a list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetListSource {
+    Tag(String),
+    Exclusions,
+    /// The game kept of each family
+    Prefer(RegionPriorities),
+}
+
+/// A list of set names, a line per set, like the ones shared for a collection. The lines starting with `#` or `;` are
+/// comments, and the names can end with the extension of the archive, so the listing of a folder is a list too
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SetList {
+    pub names: Vec<String>,
+}
+
+impl SetList {
+    pub fn new(names: Vec<String>) -> Self {
+        Self { names }
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let mut seen = HashSet::new();
+        let names = content.trim_start_matches('\u{feff}').lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with(';'))
+            .map(SetList::get_set_name)
+            .filter(|name| seen.insert(name.to_owned()))
+            .collect();
+        Self { names }
+    }
+
+    fn get_set_name(line: &str) -> String {
+        if let Some(folder_name) = line.strip_suffix(FOLDER_SUFFIX) {
+            return folder_name.to_string();
+        }
+        // Names like `Super Mario Bros. (World)` have dots, only the extensions of the archives are removed
+        let path = Path::new(line);
+        match path.file_stem() {
+            Some(set_name) if is_extension_for_file_set(&path) => set_name.to_string_lossy().to_string(),
+            _ => line.to_string(),
+        }
+    }
+
+    /// The sets of the database in the list, by their name, an old name of them, or a name that only differs in
+    /// spelling under the name rules, if only one set has it
+    pub fn resolve(&self, game_names: &[String], aliases: &HashMap<String, String>, rules: NameRules) -> SetListMatch {
+        let exact = game_names.iter().map(|name| name.as_str()).collect::<HashSet<_>>();
+        let mut normalized: HashMap<String, Vec<&str>> = HashMap::new();
+        for name in game_names {
+            normalized.entry(rules.normalize(name)).or_default().push(name.as_str());
+        }
+
+        let mut found = SetListMatch::default();
+        let mut seen = HashSet::new();
+        for name in &self.names {
+            let set_name = if exact.contains(name.as_str()) {
+                Some(name.to_owned())
+            } else if let Some(game_name) = aliases.get(name).filter(|game_name| exact.contains(game_name.as_str())) {
+                Some(game_name.to_owned())
+            } else {
+                match normalized.get(&rules.normalize(name)).map(|names| names.as_slice()) {
+                    Some([game_name]) => Some(game_name.to_string()),
+                    _ => None,
+                }
+            };
+            match set_name {
+                Some(set_name) if seen.insert(set_name.to_owned()) => found.sets.push(set_name),
+                Some(_) => {}
+                None => found.unknown.push(name.to_owned()),
+            }
+        }
+
+        found
+    }
+
+    /// The games to exclude to keep only the sets of the list of their families. The families without any of the
+    /// sets are left as they are
+    pub fn get_others_in_families(sets: &[String], families: &BTreeMap<String, Vec<String>>) -> Vec<String> {
+        let kept = sets.iter().map(|set| set.as_str()).collect::<HashSet<_>>();
+        let mut others = families.iter()
+            .map(|(parent, clones)| std::iter::once(parent).chain(clones.iter()).collect::<Vec<_>>())
+            .filter(|members| members.iter().any(|member| kept.contains(member.as_str())))
+            .flatten()
+            .filter(|member| !kept.contains(member.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+        others.sort();
+        others
+    }
+}
+
+impl Display for SetList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for name in &self.names {
+            writeln!(f, "{}", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// The names of a list found in a database
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SetListMatch {
+    pub sets: Vec<String>,
+    pub unknown: Vec<String>,
+}
+
+/// What importing a list changed
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SetListImport {
+    pub sets: Vec<String>,
+    /// The other games of the families, with a 1G1R list
+    pub excluded: Vec<String>,
+    /// The names not in the database
+    pub unknown: Vec<String>,
+}
+
+impl Display for SetListImport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}: {}", tr("Sets found"), self.sets.len())?;
+        if !self.excluded.is_empty() {
+            writeln!(f, "{}: {}", tr("Excluded from their families"), self.excluded.len())?;
+        }
+        if !self.unknown.is_empty() {
+            writeln!(f, "{}", Style::new().bold().yellow().apply_to(format!("{} ({}):", tr("Not in the database"), self.unknown.len())))?;
+            for name in &self.unknown {
+                writeln!(f, "   - {}", name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn resolves_the_names_of_a_list() {
+        let list = SetList::parse("# Best of\npacman.zip\n; old name\npuckman\n\nSuper Mario Bros. (World)\nlegend of zelda, the (usa).7z\ngalaxian/\nunknown\npacman\n");
+        assert_eq!(list.names, names(&["pacman", "puckman", "Super Mario Bros. (World)", "legend of zelda, the (usa)", "galaxian", "unknown"]));
+
+        let game_names = names(&["pacman", "Super Mario Bros. (World)", "The Legend of Zelda (USA)", "galaxian"]);
+        let aliases = vec![("puckman".to_string(), "pacman".to_string())].into_iter().collect();
+        let found = list.resolve(&game_names, &aliases, NameRules::ALL);
+        assert_eq!(found.sets, names(&["pacman", "Super Mario Bros. (World)", "The Legend of Zelda (USA)", "galaxian"]));
+        assert_eq!(found.unknown, names(&["unknown"]));
+    }
+
+    #[test]
+    fn excludes_the_rest_of_the_families() {
+        let families = vec![
+            ("pacman".to_string(), names(&["puckman", "pacmanf"])),
+            ("galaxian".to_string(), names(&["galaxiana"])),
+        ].into_iter().collect();
+        let others = SetList::get_others_in_families(&names(&["puckman", "dkong"]), &families);
+        assert_eq!(others, names(&["pacman", "pacmanf"]));
+    }
+}
@@ -1,10 +1,10 @@
 pub mod sqlite;
 
-use std::{collections::{HashMap, HashSet}, fmt::Display, ops::Deref, rc::Rc};
+use std::{collections::{BTreeMap, HashMap, HashSet}, fmt::Display, io, ops::Deref, rc::Rc, str::FromStr};
 
-use crate::{RomsetMode, err, error::RomstError, filesystem::FileChecks};
-use super::models::{file::DataFile, game::Game, set::GameSet};
-use anyhow::Result;
+use crate::{RomsetMode, err, i18n::tr, error::RomstError, filesystem::FileChecks, sysout::paged::PagedDisplay};
+use super::models::{dat::DatInfo, entry::GameEntry, history::{GameAlias, GameChange, ReportHistoryEntry}, file::{DataFile, DataFileInfo}, game::Game, release::Release, search::{GameFilter, ReleaseFilter, RomFilter}, set::GameSet};
+use anyhow::{Result, anyhow};
 use serde::{Serialize, Deserialize};
 use console::Style;
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -39,16 +39,161 @@ impl SetDependencies {
     }
 }
 
+/// The checksums present in both the file and the rom of the database, that agreed when matching them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchedChecks {
+    pub sha1: bool,
+    pub md5: bool,
+    pub crc: bool,
+    pub size: bool,
+    #[serde(default)]
+    pub sha256: bool,
+    /// Not cryptographic, as good as the crc to tell the roms apart
+    #[serde(default)]
+    pub xxh3: bool,
+    /// Matched by name, only when the files are not hashed
+    #[serde(default)]
+    pub name: bool,
+}
+
+impl MatchedChecks {
+    pub fn between(file: &DataFileInfo, db_rom: &DataFileInfo) -> Self {
+        Self {
+            sha1: file.sha1.is_some() && db_rom.sha1.is_some(),
+            md5: file.md5.is_some() && db_rom.md5.is_some(),
+            crc: file.crc.is_some() && db_rom.crc.is_some(),
+            size: file.size.is_some() && db_rom.size.is_some(),
+            sha256: file.sha256.is_some() && db_rom.sha256.is_some(),
+            xxh3: file.xxh3.is_some() && db_rom.xxh3.is_some(),
+            name: false,
+        }
+    }
+
+    /// Without sha1, md5 or sha256 the match is only as good as the crc
+    pub fn is_weak(&self) -> bool { !self.sha1 && !self.md5 && !self.sha256 }
+
+    /// Matched without any checksum, by the name and the size
+    pub fn is_unhashed(&self) -> bool { self.is_weak() && !self.crc && !self.xxh3 }
+}
+
+impl Display for MatchedChecks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let checks = [(self.sha256, "sha256"), (self.sha1, "sha1"), (self.md5, "md5"), (self.crc, "crc"), (self.xxh3, "xxh3"), (self.size, "size"), (self.name, "name")].iter()
+            .filter(|(matched, _)| *matched)
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>();
+        write!(f, "{}", checks.join(", "))
+    }
+}
+
+/// Which checksums have to agree with the database for a file to match a rom
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchPolicy {
+    /// The sha1 or the md5, or the crc when it's the only one known
+    #[default]
+    Any,
+    /// The sha1 is required
+    Sha1,
+    /// At least two of sha1, md5 and crc
+    TwoOfThree,
+    /// At least the crc and the size
+    CrcSize,
+}
+
+impl Display for MatchPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchPolicy::Any => write!(f, "{}", tr("Any checksum")),
+            MatchPolicy::Sha1 => write!(f, "SHA1"),
+            MatchPolicy::TwoOfThree => write!(f, "{}", tr("Two of SHA1, MD5 and CRC")),
+            MatchPolicy::CrcSize => write!(f, "{}", tr("CRC and size")),
+        }
+    }
+}
+
+impl FromStr for MatchPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "any" => Ok(MatchPolicy::Any),
+            "sha1" => Ok(MatchPolicy::Sha1),
+            "two-of-three" => Ok(MatchPolicy::TwoOfThree),
+            "crc-size" => Ok(MatchPolicy::CrcSize),
+            _ => Err(anyhow!("Non valid match policy, can be either `any`, `sha1`, `two-of-three` or `crc-size`"))
+        }
+    }
+}
+
+impl MatchPolicy {
+    pub fn accepts(&self, checks: &MatchedChecks) -> bool {
+        match self {
+            MatchPolicy::Any => checks.sha1 || checks.md5 || checks.crc || checks.sha256 || checks.xxh3,
+            MatchPolicy::Sha1 => checks.sha1,
+            MatchPolicy::TwoOfThree => [checks.sha1, checks.md5, checks.crc].iter().filter(|matched| **matched).count() >= 2,
+            MatchPolicy::CrcSize => checks.crc && checks.size,
+        }
+    }
+}
+
+/// Why an unknown file looks like a rom of the database
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NearMatchReason {
+    /// Same size and crc, but the sha1 or md5 differ
+    SameCrcAndSize,
+    /// Same name, but different checksums
+    SameName,
+}
+
+impl Display for NearMatchReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NearMatchReason::SameCrcAndSize => write!(f, "{}", tr("same crc and size, different sha1")),
+            NearMatchReason::SameName => write!(f, "{}", tr("same name, different checksums")),
+        }
+    }
+}
+
+/// A rom of the database found by its name, with the sets using it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RomNameMatch {
+    pub rom: DataFile,
+    pub sets: Vec<String>,
+}
+
+impl Display for RomNameMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.rom, self.sets.join(", "))
+    }
+}
+
+/// A rom of the database that almost matches a file, a hint of a bad dump or an overdump
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NearMatch {
+    pub game_name: String,
+    pub rom: DataFile,
+    pub reason: NearMatchReason,
+}
+
+impl Display for NearMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} in {} ({})", self.rom.name, self.game_name, self.reason)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RomSearch {
     searched_roms: HashSet<Rc<DbDataEntry<DataFile>>>,
     pub set_results: HashMap<String, SetContent>,
-    pub unknowns: Vec<DataFile>
+    pub unknowns: Vec<DataFile>,
+    /// The checks that matched for each rom found, by rom id
+    #[serde(default)]
+    pub matched_checks: HashMap<u32, MatchedChecks>,
 }
 
 impl RomSearch {
     pub fn new() -> Self {
-        Self { searched_roms: HashSet::new(), set_results: HashMap::new(), unknowns: vec![] }
+        Self { searched_roms: HashSet::new(), set_results: HashMap::new(), unknowns: vec![], matched_checks: HashMap::new() }
     }
     pub fn add_file_for_set(&mut self, set_name: String, file: DbDataEntry<DataFile>) {
         let set_results = &mut self.set_results;
@@ -117,26 +262,66 @@ impl SetContent {
     }
 }
 
-impl  Display for RomSearch {
+/// A line of the result of a search, only formatted when it's shown
+enum RomSearchLine<'a> {
+    Set(&'a str),
+    Roms,
+    Rom(&'a DbDataEntry<DataFile>),
+    Unknowns,
+    Unknown(&'a DataFile),
+}
+
+impl Display for RomSearchLine<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if !self.set_results.is_empty() {
-            for game_roms in &self.set_results {
-                writeln!(f, "Set: {}", Style::new().green().bold().apply_to(game_roms.0))?;
-                let set_content = game_roms.1;
-                if !set_content.roms_included.is_empty() {
-                    writeln!(f, "  {}:", Style::new().cyan().apply_to("Roms"))?;
-                    for rom in &set_content.roms_included {
-                        writeln!(f, "   - {}", rom)?;
-                    }
-                }
-            }
+        match self {
+            RomSearchLine::Set(set_name) => write!(f, "{}: {}", tr("Set"), Style::new().green().bold().apply_to(set_name)),
+            RomSearchLine::Roms => write!(f, "  {}:", Style::new().cyan().apply_to(tr("Roms"))),
+            RomSearchLine::Rom(rom) => write!(f, "   - {}", rom),
+            RomSearchLine::Unknowns => write!(f, "  {}:", Style::new().red().apply_to(tr("Unknown files"))),
+            RomSearchLine::Unknown(unknown) => write!(f, "   - {}", unknown),
         }
+    }
+}
 
-        if !self.unknowns.is_empty() {
-            writeln!(f, "  {}:", Style::new().red().apply_to("Unkown files"))?;
-            for unknown in &self.unknowns {
-                writeln!(f, "   - {}", unknown)?;
-            }
+impl RomSearch {
+    /// The lines shown, the sets by name with their roms by name, so the pages are always the same
+    fn lines(&self) -> impl Iterator<Item = RomSearchLine<'_>> {
+        let mut sets = self.set_results.iter().collect::<Vec<_>>();
+        sets.sort_by(|a, b| a.0.cmp(b.0));
+        let set_lines = sets.into_iter().flat_map(|(set_name, set_content)| {
+            let mut roms = set_content.get_roms_included();
+            roms.sort_by(|a, b| a.file.name.cmp(&b.file.name).then(a.id.cmp(&b.id)));
+            let header = if roms.is_empty() { None } else { Some(RomSearchLine::Roms) };
+            std::iter::once(RomSearchLine::Set(set_name)).chain(header).chain(roms.into_iter().map(RomSearchLine::Rom))
+        });
+        let header = if self.unknowns.is_empty() { None } else { Some(RomSearchLine::Unknowns) };
+        set_lines.chain(header).chain(self.unknowns.iter().map(RomSearchLine::Unknown))
+    }
+}
+
+impl PagedDisplay for RomSearch {
+    fn line_count(&self) -> usize {
+        let set_lines = self.set_results.values()
+            .map(|set_content| if set_content.roms_included.is_empty() { 1 } else { 2 + set_content.roms_included.len() })
+            .sum::<usize>();
+        let unknown_lines = if self.unknowns.is_empty() { 0 } else { 1 + self.unknowns.len() };
+        set_lines + unknown_lines
+    }
+
+    fn write_lines(&self, out: &mut dyn io::Write, start: usize, count: usize) -> io::Result<usize> {
+        let mut written = 0;
+        for line in self.lines().skip(start).take(count) {
+            writeln!(out, "{}", line)?;
+            written += 1;
+        }
+        Ok(written)
+    }
+}
+
+impl Display for RomSearch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in self.lines() {
+            writeln!(f, "{}", line)?;
         }
 
         Ok(())
@@ -146,7 +331,9 @@ impl  Display for RomSearch {
 pub struct FileCheckSearch {
     pub sha1: u32,
     pub md5: u32,
-    pub crc: u32
+    pub crc: u32,
+    pub sha256: u32,
+    pub xxh3: u32,
 }
 
 impl FileCheckSearch {
@@ -161,6 +348,13 @@ impl FileCheckSearch {
         if self.crc == 0 {
             use_checks &= !FileChecks::CRC;
         }
+        // The optional ones are only worth hashing when the database has them
+        if self.sha256 > 0 {
+            use_checks |= FileChecks::SHA256;
+        }
+        if self.xxh3 > 0 {
+            use_checks |= FileChecks::XXH3;
+        }
 
         use_checks
     }
@@ -183,31 +377,100 @@ pub trait DataReader {
             None => err!(RomstError::GenericError{ message: format!("Game {} not found", game_name.as_ref()) }),
         }
     }
+    /// Returns the game with all the data found in the DAT, regardless of the romset mode
+    fn get_game_entry<S>(&self, game_name: S) -> Result<Option<GameEntry>> where S: AsRef<str> + rusqlite::ToSql;
+    /// Returns the games matching the filter, sorted by name
+    fn find_games(&self, filter: &GameFilter) -> Result<Vec<Game>>;
+    /// Like `find_games`, but the games are read as they are iterated, for traversing the whole database
+    fn iter_games<'a>(&'a self, filter: &'a GameFilter) -> impl Iterator<Item = Result<Game>> + 'a;
+    /// Returns the names of the clones of a game
+    fn get_clones<S>(&self, game_name: S) -> Result<Vec<String>> where S: AsRef<str> + rusqlite::ToSql;
     /// Finds where this rom is included, in other games. Returns the games and the name used for that rom
     fn find_rom_usage<S>(&self, game_name: S, rom_name: S, rom_mode: RomsetMode) -> Result<RomSearch> where S: AsRef<str> + rusqlite::ToSql;
     /// Gets all romsets that include roms in the searched game
     /// This is useful to know what new (incomplete though) sets can be generated from the current one
     fn get_romset_shared_roms<S>(&self, game_name: S, rom_mode: RomsetMode) -> Result<RomSearch> where S: AsRef<str> + rusqlite::ToSql;
 
+    /// The checksums that have to agree with the database for a file to match a rom
+    fn get_match_policy(&self) -> MatchPolicy;
     /// Finds all romsets associated with the roms sent
     fn get_romsets_from_roms(&self, roms: Vec<DataFile>, rom_mode: RomsetMode) -> Result<RomSearch>;
+    /// Finds the romsets containing any rom matching the checksums, keeping the rom names used in each set
+    fn find_rom_locations(&self, rom_info: &DataFileInfo, rom_mode: RomsetMode) -> Result<RomSearch>;
 
     fn get_devices_for_game<S>(&self, game_name: S) -> Result<SetDependencies> where S: AsRef<str> + rusqlite::ToSql;
+    /// Returns the names of the games that need this device, sorted by name
+    fn get_games_using_device<S>(&self, device_name: S) -> Result<Vec<String>> where S: AsRef<str> + rusqlite::ToSql;
+    /// Returns the names of the games using this sample set, sorted by name
+    fn get_games_using_samples<S>(&self, sample_set: S) -> Result<Vec<String>> where S: AsRef<str> + rusqlite::ToSql;
+    /// Returns the sample sets with the number of games using each one, the most used first
+    fn get_sample_sets(&self) -> Result<Vec<(String, u32)>>;
+    /// Returns the roms not used by any game, left behind by older purges or merges
+    fn get_unreferenced_roms(&self) -> Result<Vec<DbDataEntry<DataFileInfo>>>;
+    /// Returns the parents with clones, with their clones sorted by name
+    fn get_families(&self) -> Result<BTreeMap<String, Vec<String>>>;
+    /// Returns the roms with the same size and crc, or the same name, as a file that didn't match anything
+    fn get_near_matches(&self, file: &DataFile) -> Result<Vec<NearMatch>>;
+
+    /// The roms with a name matching the filter, sorted by name, each one with the sets using it
+    fn search_roms(&self, filter: &RomFilter) -> Result<Vec<RomNameMatch>>;
+
+    /// Returns the sets marked by the user to be ignored
+    fn get_exclusions(&self) -> Result<HashSet<String>>;
+
+    /// Returns the releases of a game, the default one first
+    fn get_releases<S>(&self, game_name: S) -> Result<Vec<Release>> where S: AsRef<str> + rusqlite::ToSql;
+
+    /// Returns the names of the games with any release matching the filter
+    fn get_games_with_release(&self, filter: &ReleaseFilter) -> Result<HashSet<String>>;
+
+    /// Returns how many sets use the rom, none if the database has no counts or doesn't have the rom
+    fn get_rom_shares(&self, rom: &DataFileInfo) -> Result<Option<u32>>;
+
+    /// Returns the names of the games with any disk
+    fn get_games_with_disks(&self) -> Result<HashSet<String>>;
+
+    /// Returns the user tags, grouped by game
+    fn get_tags(&self) -> Result<HashMap<String, HashSet<String>>>;
+
+    /// Returns the user note for a game, if any
+    fn get_note<S>(&self, game_name: S) -> Result<Option<String>> where S: AsRef<str> + rusqlite::ToSql;
+
+    /// Returns the DATs imported in the database
+    fn get_dats(&self) -> Result<Vec<DatInfo>>;
+
+    /// Returns the stored report counts, oldest first, only the ones from a source if specified
+    fn get_report_history(&self, source: Option<&str>) -> Result<Vec<ReportHistoryEntry>>;
+
+    /// Returns the games removed or renamed by the DATs imported over the database, the oldest first
+    fn get_game_changes(&self) -> Result<Vec<GameChange>>;
+
+    /// Returns the old names of the games, sorted by alias
+    fn get_game_aliases(&self) -> Result<Vec<GameAlias>>;
+
+    /// Returns the current name of a game from an old one, if the game is in the database
+    fn get_aliased_game(&self, alias: &str) -> Result<Option<String>>;
+
+    /// Returns the names of all the games, or only the ones from a DAT if specified
+    fn get_game_names(&self, dat_id: Option<u32>) -> Result<Vec<String>>;
 
     fn get_file_checks(&self) -> Result<FileCheckSearch>;
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{DbDataEntry, FileCheckSearch, RomSearch};
-    use crate::{data::models::file::{DataFile, DataFileInfo, FileType}, filesystem::FileChecks};
+    use anyhow::Result;
+    use super::{DbDataEntry, FileCheckSearch, MatchPolicy, MatchedChecks, RomSearch};
+    use crate::{data::models::file::{DataFile, DataFileInfo, FileType}, filesystem::FileChecks, sysout::paged::{Page, PagedDisplay}};
 
     #[test]
     fn should_check_with_all() {
         let file_check_search = FileCheckSearch {
             sha1: 1,
             md5: 1,
-            crc: 1
+            crc: 1,
+            sha256: 0,
+            xxh3: 0,
         };
         let file_checks = file_check_search.get_file_checks();
 
@@ -221,7 +484,9 @@ mod tests {
         let file_check_search = FileCheckSearch {
             sha1: 1,
             md5: 0,
-            crc: 1
+            crc: 1,
+            sha256: 0,
+            xxh3: 0,
         };
         let file_checks = file_check_search.get_file_checks();
 
@@ -235,7 +500,9 @@ mod tests {
         let file_check_search = FileCheckSearch {
             sha1: 0,
             md5: 1,
-            crc: 1
+            crc: 1,
+            sha256: 0,
+            xxh3: 0,
         };
         let file_checks = file_check_search.get_file_checks();
 
@@ -249,7 +516,9 @@ mod tests {
         let file_check_search = FileCheckSearch {
             sha1: 0,
             md5: 0,
-            crc: 1
+            crc: 1,
+            sha256: 0,
+            xxh3: 0,
         };
         let file_checks = file_check_search.get_file_checks();
 
@@ -263,7 +532,9 @@ mod tests {
         let file_check_search = FileCheckSearch {
             sha1: 1,
             md5: 1,
-            crc: 0
+            crc: 0,
+            sha256: 0,
+            xxh3: 0,
         };
         let file_checks = file_check_search.get_file_checks();
 
@@ -272,6 +543,40 @@ mod tests {
         assert!(!file_checks.contains(FileChecks::CRC));
     }
 
+    #[test]
+    fn should_check_the_optional_ones_only_when_stored() {
+        let file_check_search = FileCheckSearch {
+            sha1: 1,
+            md5: 0,
+            crc: 1,
+            sha256: 1,
+            xxh3: 0,
+        };
+        let file_checks = file_check_search.get_file_checks();
+
+        assert!(file_checks.contains(FileChecks::SHA256));
+        assert!(!file_checks.contains(FileChecks::XXH3));
+        assert!(!FileChecks::ALL.contains(FileChecks::SHA256));
+    }
+
+    #[test]
+    fn match_policies_require_their_checks() {
+        let only_crc = MatchedChecks { crc: true, size: true, ..Default::default() };
+        let sha1_and_crc = MatchedChecks { sha1: true, crc: true, ..Default::default() };
+        let only_sha1 = MatchedChecks { sha1: true, ..Default::default() };
+        let only_sha256 = MatchedChecks { sha256: true, ..Default::default() };
+
+        assert!(MatchPolicy::Any.accepts(&only_crc));
+        assert!(!MatchPolicy::Sha1.accepts(&only_crc));
+        assert!(MatchPolicy::Sha1.accepts(&only_sha1));
+        assert!(MatchPolicy::TwoOfThree.accepts(&sha1_and_crc));
+        assert!(!MatchPolicy::TwoOfThree.accepts(&only_sha1));
+        assert!(MatchPolicy::CrcSize.accepts(&only_crc));
+        assert!(!MatchPolicy::CrcSize.accepts(&sha1_and_crc));
+        assert!(MatchPolicy::Any.accepts(&only_sha256));
+        assert!(!only_sha256.is_weak());
+    }
+
     #[test]
     fn should_correctly_add_rom_for_set() {
         let mut rom1 = DataFile::new("rom1", DataFileInfo::new(FileType::Rom));
@@ -305,4 +610,29 @@ mod tests {
         assert!(available_2.iter().find(|f| { if let Some(crc) = &f.file.info.crc { crc.eq(&"dc20b010".to_string()) } else { false } }).is_some());
         assert!(available_2.iter().find(|f| { if let Some(crc) = &f.file.info.crc { crc.eq(&"fbe0d501".to_string()) } else { false } }).is_some());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn should_write_the_search_by_pages() -> Result<()> {
+        let mut rom_search = RomSearch::new();
+        for (id, set_name, rom_name) in [(1, "set2", "rom2"), (2, "set1", "rom1b"), (3, "set1", "rom1a")] {
+            rom_search.add_file_for_set(set_name.to_string(), DbDataEntry::new(id, DataFile::new(rom_name, DataFileInfo::new(FileType::Rom))));
+        }
+        rom_search.unknowns.push(DataFile::new("unknown", DataFileInfo::new(FileType::Rom)));
+
+        let output = rom_search.to_string();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(9, rom_search.line_count());
+        assert_eq!(rom_search.line_count(), lines.len());
+        assert!(console::strip_ansi_codes(lines[0]).ends_with("set1"));
+        assert!(lines[2].contains("rom1a"));
+        assert!(lines[3].contains("rom1b"));
+        assert!(lines[6].contains("rom2"));
+
+        let mut page = vec![];
+        assert_eq!(3, rom_search.write_page(&mut page, Page::new(3, 3))?);
+        assert_eq!(String::from_utf8(page)?, format!("{}\n", lines[6..].join("\n")));
+        assert_eq!(0, rom_search.write_page(&mut std::io::sink(), Page::new(4, 3))?);
+
+        Ok(())
+    }
+}
@@ -1,25 +1,27 @@
-use std::{collections::HashSet, fmt::Display, iter::FromIterator};
+use std::{collections::{BTreeMap, HashMap, HashSet, VecDeque}, fmt::Display, iter::FromIterator};
 
 use anyhow::Result;
 use console::Style;
 use log::{debug, error, warn};
-use rusqlite::{Connection, Row, ToSql, params};
+use rusqlite::{Connection, OptionalExtension, Row, ToSql, params, types::ValueRef};
 use serde::{Deserialize, Serialize};
 
-use crate::{err, error::RomstError};
-use crate::{RomsetMode, data::models::{disk::GameDisk, file::{DataFile, DataFileInfo, FileType}, game::Game}};
+use crate::{err, error::RomstError, i18n::tr};
+use crate::{RomsetMode, data::models::{dat::{DatInfo, ImportProgress, ImportSource}, disk::GameDisk, entry::GameEntry, file::{DataFile, DataFileInfo, FileType}, game::Game, history::{DatContents, GameAlias, GameChange, GamesSnapshot, ReportHistoryEntry}, release::Release, search::{GameFilter, PatternKind, ReleaseFilter, RomFilter}}};
 
-use super::{DataReader, DbDataEntry, FileCheckSearch, RomSearch, SetDependencies};
+use super::{DataReader, DbDataEntry, FileCheckSearch, MatchPolicy, MatchedChecks, NearMatch, NearMatchReason, RomNameMatch, RomSearch, SetDependencies};
 
 #[derive(Debug)]
 pub struct SearchEntryIds<T> {
     pub found: Vec<DbDataEntry<T>>,
     pub not_found: Vec<T>,
-    pub ignored: Vec<T>
+    pub ignored: Vec<T>,
+    /// The checks that matched for each id found
+    pub matched_checks: HashMap<u32, MatchedChecks>,
 }
 
 impl <T> SearchEntryIds<T> {
-    fn new() -> Self { Self { found: vec![], not_found: vec![], ignored: vec![] } }
+    fn new() -> Self { Self { found: vec![], not_found: vec![], ignored: vec![], matched_checks: HashMap::new() } }
 
     fn add_found(&mut self, id: u32, file: T) {
         self.found.push(DbDataEntry::new(id, file));
@@ -32,15 +34,22 @@ impl <T> SearchEntryIds<T> {
 
 #[derive(Serialize, Deserialize)]
 pub struct DBReport {
+    pub dats: u32,
+    /// Where each DAT was imported from
+    #[serde(default)]
+    pub sources: Vec<DatInfo>,
     pub games: u32,
     pub roms: u32,
     pub roms_in_games: u32,
     pub samples: u32,
     pub device_refs: u32,
+    /// The counts of the last report stored in the history, to show the completeness without checking again
+    #[serde(default)]
+    pub last_report: Option<ReportHistoryEntry>,
 }
 
 impl DBReport {
-    pub fn new() -> Self { Self { games: 0, roms: 0, roms_in_games: 0, samples: 0, device_refs: 0 } }
+    pub fn new() -> Self { Self { dats: 0, sources: vec![], games: 0, roms: 0, roms_in_games: 0, samples: 0, device_refs: 0, last_report: None } }
 }
 
 impl Default for DBReport {
@@ -51,12 +60,47 @@ impl Default for DBReport {
 
 impl Display for DBReport {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "{}", Style::new().bold().yellow().apply_to("Database info"))?;
-        writeln!(f, "- Games: {}", self.games)?;
-        writeln!(f, "- Roms: {}", self.roms)?;
-        writeln!(f, "- Roms in Games: {}", self.roms_in_games)?;
-        writeln!(f, "- Samples: {}", self.samples)?;
-        writeln!(f, "- Device References: {}", self.device_refs)
+        writeln!(f, "{}", Style::new().bold().yellow().apply_to(tr("Database info")))?;
+        writeln!(f, "- {}: {}", tr("Dats"), self.dats)?;
+        writeln!(f, "- {}: {}", tr("Games"), self.games)?;
+        writeln!(f, "- {}: {}", tr("Roms"), self.roms)?;
+        writeln!(f, "- {}: {}", tr("Roms in Games"), self.roms_in_games)?;
+        writeln!(f, "- {}: {}", tr("Samples"), self.samples)?;
+        writeln!(f, "- {}: {}", tr("Device References"), self.device_refs)?;
+        for dat in &self.sources {
+            if let Some(source) = &dat.source {
+                writeln!(f, "- {}: {}", dat.name, source)?;
+            }
+        }
+        if let Some(last_report) = &self.last_report {
+            writeln!(f, "{}", Style::new().bold().yellow().apply_to(tr("Last verification")))?;
+            writeln!(f, "- {} {}", last_report.date_time, last_report.source)?;
+            writeln!(f, "- {}: {}, {}: {}, {}: {} ({:.1}% {})", tr("Complete"), last_report.complete, tr("Partial"), last_report.partial(),
+                tr("Missing"), last_report.missing, last_report.completeness(), tr("complete"))?;
+        }
+        Ok(())
+    }
+}
+
+/// The rows of a raw query, each one as a JSON object by column name
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QueryRows {
+    pub columns: Vec<String>,
+    pub rows: Vec<serde_json::Value>,
+}
+
+impl Display for QueryRows {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", Style::new().bold().apply_to(self.columns.join("\t")))?;
+        for row in &self.rows {
+            let values = self.columns.iter().map(|column| match &row[column] {
+                serde_json::Value::String(value) => value.to_owned(),
+                serde_json::Value::Null => "NULL".to_string(),
+                value => value.to_string(),
+            }).collect::<Vec<_>>();
+            writeln!(f, "{}", values.join("\t"))?;
+        }
+        writeln!(f, "{} {}", self.rows.len(), tr("rows"))
     }
 }
 
@@ -77,8 +121,12 @@ Game info_desc = row.get(12)?;
 Game info_year = row.get(13)?;
 Game info_manuf = row.get(14)?;
 Rom id = row.get(15)?;
+Rom bios = row.get(16)?;
+Rom sha256 = row.get(17)?;
+Rom xxh3 = row.get(18)?;
+Rom optional = row.get(19)?;
 */
-const GAMES_ROMS_QUERY: &str = "SELECT DISTINCT game_roms.game_name, game_roms.name as rom_name, roms.sha1, roms.md5, roms.crc, roms.size, game_roms.status, game_roms.parent, games.clone_of, games.rom_of, games.source_file, games.sample_of, games.info_desc, games.info_year, games.info_manuf, roms.id
+const GAMES_ROMS_QUERY: &str = "SELECT DISTINCT game_roms.game_name, game_roms.name as rom_name, roms.sha1, roms.md5, roms.crc, roms.size, game_roms.status, game_roms.parent, games.clone_of, games.rom_of, games.source_file, games.sample_of, games.info_desc, games.info_year, games.info_manuf, roms.id, game_roms.bios, roms.sha256, roms.xxh3, game_roms.optional
                 FROM game_roms JOIN roms ON game_roms.rom_id = roms.id JOIN games ON game_roms.game_name = games.name";
 fn process_games_rom_row(row: &Row) -> Result<(Game, DbDataEntry<DataFile>, Option<String>), rusqlite::Error> {
     let mut game = Game::new(row.get(0)?);
@@ -95,10 +143,14 @@ fn process_games_rom_row(row: &Row) -> Result<(Game, DbDataEntry<DataFile>, Opti
     data_file_info.md5 = row.get(3)?;
     data_file_info.crc = row.get(4)?;
     data_file_info.size = row.get(5)?;
+    data_file_info.sha256 = row.get(17)?;
+    data_file_info.xxh3 = row.get(18)?;
 
     let rom_name: String = row.get(1)?;
     let mut data_file = DataFile::new(rom_name, data_file_info);
     data_file.status = row.get(6)?;
+    data_file.bios = row.get(16)?;
+    data_file.optional = row.get(19)?;
 
     let rom_id = row.get(15)?;
     let db_entry = DbDataEntry::new(rom_id, data_file);
@@ -108,6 +160,35 @@ fn process_games_rom_row(row: &Row) -> Result<(Game, DbDataEntry<DataFile>, Opti
     Ok((game, db_entry, rom_parent))
 }
 
+fn group_sets_by_mode(roms_rows: Vec<(Game, DbDataEntry<DataFile>, Option<String>)>, rom_mode: RomsetMode) -> RomSearch {
+    let mut result = RomSearch::new();
+    for item in roms_rows {
+        let game = item.0;
+        let rom = item.1;
+        let game_parent = item.2;
+
+        match rom_mode {
+            RomsetMode::Merged => {
+                if let Some(game_parent_name) = game.rom_of {
+                    result.add_file_for_set(game_parent_name, rom);
+                } else {
+                    result.add_file_for_set(game.name, rom);
+                }
+            }
+            RomsetMode::NonMerged => {
+                result.add_file_for_set(game.name, rom);
+            }
+            RomsetMode::Split => {
+                if game_parent.is_none() {
+                    result.add_file_for_set(game.name, rom);
+                }
+            }
+        }
+    }
+
+    result
+}
+
 /*
 Game name = row.get(0)?;
 Rom name = row.get(1)?;
@@ -121,6 +202,75 @@ Rom id = row.get(8)?;
 */
 const _ROMS_QUERY: &str = "SELECT DISTINCT game_roms.game_name, game_roms.name as rom_name, roms.sha1, roms.md5, roms.crc, roms.size, game_roms.status, game_roms.parent, roms.id
                 FROM game_roms JOIN roms ON game_roms.rom_id = roms.id";
+/// The roms of the database that may be the one with the info, none if the info has no checksum to look for it
+fn find_roms_by_info(conn: &Connection, rom: &DataFileInfo) -> Result<Option<Vec<(u32, DataFileInfo)>>> {
+    let mut params: Vec<(&str, &dyn ToSql)> = vec![];
+    let mut statement_where = vec![];
+    let mut has_hash = false;
+
+    if let Some(ref sha1) = rom.sha1 {
+        has_hash = true;
+        params.push((":sha1", sha1));
+        statement_where.push("(sha1 = :sha1 OR sha1 IS NULL)");
+    }
+    if let Some(ref md5) = rom.md5 {
+        has_hash = true;
+        params.push((":md5", md5));
+        statement_where.push("(md5 = :md5 OR md5 IS NULL)");
+    }
+    // The optional checksums are only hashed when the database has them, so the columns exist
+    let mut hash_columns = vec!["sha1", "md5"];
+    if let Some(ref sha256) = rom.sha256 {
+        has_hash = true;
+        params.push((":sha256", sha256));
+        statement_where.push("(sha256 = :sha256 OR sha256 IS NULL)");
+        hash_columns.push("sha256");
+    }
+    if let Some(ref xxh3) = rom.xxh3 {
+        params.push((":xxh3", xxh3));
+        statement_where.push("(xxh3 = :xxh3 OR xxh3 IS NULL)");
+    }
+
+    // Checksum files like sfv only have the crc, that with the size is good enough
+    if !has_hash && rom.crc.is_none() {
+        return Ok(None);
+    }
+    if let Some(ref crc) = rom.crc {
+        params.push((":crc", crc));
+        statement_where.push("(crc = :crc OR crc IS NULL)");
+    }
+    if let Some(ref size) = rom.size {
+        params.push((":size", size));
+        statement_where.push("(size = :size OR size IS NULL)");
+    }
+
+    // Minimum fields to find, has to have at least md5 or sha1, or the crc if that's all we have
+    let has_any_hash = hash_columns.iter().map(|column| format!("{} IS NOT NULL", column)).collect::<Vec<_>>().join(" OR ");
+    if has_hash {
+        statement_where.push(&has_any_hash);
+    } else {
+        statement_where.push("crc IS NOT NULL");
+    }
+
+    let statement = format!("SELECT id, sha1, md5, crc, size, {}, {} FROM roms WHERE {};",
+        if rom.sha256.is_some() { "sha256" } else { "NULL" }, if rom.xxh3.is_some() { "xxh3" } else { "NULL" },
+        statement_where.join(" AND "));
+
+    let mut rom_stmt = conn.prepare_cached(&statement)?;
+    let query_rom_result = rom_stmt.query_map_named(params.as_slice(), |row| {
+        let mut db_rom = DataFileInfo::new(FileType::Rom);
+        db_rom.sha1 = row.get(1)?;
+        db_rom.md5 = row.get(2)?;
+        db_rom.crc = row.get(3)?;
+        db_rom.size = row.get(4)?;
+        db_rom.sha256 = row.get(5)?;
+        db_rom.xxh3 = row.get(6)?;
+        Ok((row.get(0)?, db_rom))
+    })?.filter_map(|row| row.ok() ).collect();
+
+    Ok(Some(query_rom_result))
+}
+
 fn _process_rom_row(row: &Row) -> Result<(String, DbDataEntry<DataFile>, Option<String>), rusqlite::Error> {
     let game: String = row.get(0)?;
 
@@ -142,19 +292,155 @@ fn _process_rom_row(row: &Row) -> Result<(String, DbDataEntry<DataFile>, Option<
     Ok((game, db_entry, rom_parent))
 }
 
+/// Games read from the database at once when iterating them
+const GAMES_BATCH_SIZE: u32 = 1000;
+
 #[derive(Debug)]
 pub struct DBReader<'d> {
     conn: &'d Connection,
+    match_policy: MatchPolicy,
+}
+
+/// Iterates the games sorted by name, reading them in batches so they are never all in memory
+pub struct GameIterator<'a> {
+    conn: &'a Connection,
+    filter: &'a GameFilter,
+    batch_size: u32,
+    last_name: String,
+    batch: VecDeque<Game>,
+    finished: bool,
+    /// The games with a release matching the filter, read with the first batch
+    release_games: Option<HashSet<String>>,
+    /// The old names of the games, also matched with the pattern, read with the first batch
+    aliases: Option<HashMap<String, Vec<String>>>,
+}
+
+impl<'a> GameIterator<'a> {
+    fn new(conn: &'a Connection, filter: &'a GameFilter, batch_size: u32) -> Self {
+        Self { conn, filter, batch_size, last_name: String::new(), batch: VecDeque::new(), finished: false, release_games: None, aliases: None }
+    }
+
+    /// Each batch starts after the last name of the previous one
+    fn read_batch(&mut self) -> Result<()> {
+        if self.release_games.is_none() && !self.filter.releases.is_empty() {
+            self.release_games = Some(DBReader::from_connection(self.conn).get_games_with_release(&self.filter.releases)?);
+        }
+        if self.aliases.is_none() {
+            let mut aliases: HashMap<String, Vec<String>> = HashMap::new();
+            for alias in DBReader::from_connection(self.conn).get_game_aliases()? {
+                aliases.entry(alias.game_name).or_default().push(alias.alias);
+            }
+            self.aliases = Some(aliases);
+        }
+        let columns = DBReader::from_connection(self.conn).get_game_columns()?;
+        let mut stmt = self.conn.prepare(&format!("SELECT {} FROM games WHERE name > ?1 ORDER BY name LIMIT ?2;", columns))?;
+        let games = stmt.query_map(params![ self.last_name, self.batch_size ], DBReader::game_from_row)?.collect::<rusqlite::Result<VecDeque<_>>>()?;
+
+        self.finished = games.len() < self.batch_size as usize;
+        if let Some(game) = games.back() {
+            self.last_name = game.name.to_owned();
+        }
+        self.batch = games;
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for GameIterator<'a> {
+    type Item = Result<Game>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(game) = self.batch.pop_front() {
+                let has_release = self.release_games.as_ref().is_none_or(|games| games.contains(&game.name));
+                let aliases = self.aliases.as_ref().and_then(|aliases| aliases.get(&game.name)).map(|aliases| aliases.as_slice()).unwrap_or_default();
+                if has_release && self.filter.matches_with_aliases(&game, aliases) {
+                    return Some(Ok(game));
+                }
+                continue;
+            }
+            if self.finished {
+                return None;
+            }
+            if let Err(e) = self.read_batch() {
+                self.finished = true;
+                return Some(Err(e));
+            }
+        }
+    }
 }
 
 impl <'d> DBReader <'d>{
     pub fn from_connection(conn: &'d Connection) -> Self {
-        Self { conn }
+        Self { conn, match_policy: MatchPolicy::default() }
+    }
+
+    /// Which checksums have to agree with the database for a file to match a rom
+    pub fn set_match_policy(&mut self, match_policy: MatchPolicy) {
+        self.match_policy = match_policy;
+    }
+
+    /// The progress of an import that was interrupted, none if the database was fully written
+    pub fn get_import_progress(&self) -> Result<Option<ImportProgress>> {
+        if !self.table_exists("import_progress")? {
+            return Ok(None);
+        }
+
+        let progress = self.conn.query_row("SELECT source_sha1, dat_id, entries FROM import_progress;", params![], |row| {
+            Ok(ImportProgress { source_sha1: row.get(0)?, dat_id: row.get(1)?, entries: row.get(2)? })
+        }).optional()?;
+
+        Ok(progress)
+    }
+
+    /// The games with the checksums of their roms, and the version of the last DAT, before importing another DAT over them
+    pub fn get_games_snapshot(&self) -> Result<GamesSnapshot> {
+        let mut snapshot = GamesSnapshot { version: self.get_dats()?.into_iter().filter_map(|dat| dat.version).next_back(), ..GamesSnapshot::default() };
+        if !self.table_exists("games")? {
+            return Ok(snapshot);
+        }
+
+        let mut stmt = self.conn.prepare("SELECT games.name, COALESCE(roms.sha1, roms.crc) FROM games
+            LEFT JOIN game_roms ON game_roms.game_name = games.name
+            LEFT JOIN roms ON roms.id = game_roms.rom_id;")?;
+        let mut rows = stmt.query(params![])?;
+        while let Some(row) = rows.next()? {
+            let roms = snapshot.games.entry(row.get(0)?).or_default();
+            if let Some(checksum) = row.get::<_, Option<String>>(1)? {
+                roms.insert(checksum);
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    /// The roms of every game by name, to compare the database with another one
+    pub fn get_dat_contents(&self) -> Result<DatContents> {
+        let mut contents = DatContents { version: self.get_dats()?.into_iter().filter_map(|dat| dat.version).next_back(), ..DatContents::default() };
+        if !self.table_exists("games")? {
+            return Ok(contents);
+        }
+
+        let mut stmt = self.conn.prepare("SELECT games.name, game_roms.name, COALESCE(roms.sha1, roms.crc) FROM games
+            LEFT JOIN game_roms ON game_roms.game_name = games.name
+            LEFT JOIN roms ON roms.id = game_roms.rom_id;")?;
+        let mut rows = stmt.query(params![])?;
+        while let Some(row) = rows.next()? {
+            let roms = contents.games.entry(row.get(0)?).or_default();
+            if let (Some(rom_name), Some(checksum)) = (row.get::<_, Option<String>>(1)?, row.get::<_, Option<String>>(2)?) {
+                roms.insert(rom_name, checksum);
+            }
+        }
+
+        Ok(contents)
     }
 
     pub fn get_stats(&self) -> Result<DBReport> {
         let mut db_report = DBReport::new();
 
+        let dats = self.get_dats()?;
+        db_report.dats = dats.len() as u32;
+        db_report.sources = dats.into_iter().filter(|dat| dat.source.is_some()).collect();
+
         let mut stmt = self.conn.prepare("SELECT COUNT(*) FROM games;")?;
         let games: u32 = stmt.query_row(params![], |row| {
             Ok(row.get(0)?)
@@ -184,10 +470,164 @@ impl <'d> DBReader <'d>{
             Ok(row.get(0)?)
         })?;
         db_report.device_refs = device_refs;
+        db_report.last_report = self.get_report_history(None)?.pop();
 
         Ok(db_report)
     }
 
+    /// Runs a single SELECT statement, binding the parameters in order. Blobs are returned as hex strings.
+    /// The connection is query only while it runs, so SQLite rejects any statement writing to the database
+    pub fn query_rows(&self, sql: &str, query_params: &[String]) -> Result<QueryRows> {
+        if count_statements(sql) != 1 {
+            return err!(RomstError::GenericError { message: "Only a single SELECT statement can be run".to_string() });
+        }
+
+        let query_only = self.conn.query_row("PRAGMA query_only", params![], |row| row.get::<_, bool>(0))?;
+        self.conn.pragma_update(None, "query_only", &true)?;
+        let result = self.run_query(sql, query_params);
+        self.conn.pragma_update(None, "query_only", &query_only)?;
+        result
+    }
+
+    fn run_query(&self, sql: &str, query_params: &[String]) -> Result<QueryRows> {
+        let mut stmt = self.conn.prepare(sql)?;
+        if stmt.column_count() == 0 {
+            return err!(RomstError::GenericError { message: "Only a single SELECT statement can be run".to_string() });
+        }
+        let columns = stmt.column_names().into_iter().map(String::from).collect::<Vec<_>>();
+        let mut rows = stmt.query(query_params.iter())?;
+        let mut result = vec![];
+        while let Some(row) = rows.next()? {
+            let mut values = serde_json::Map::new();
+            for (index, column) in columns.iter().enumerate() {
+                let value = match row.get_raw(index) {
+                    ValueRef::Null => serde_json::Value::Null,
+                    ValueRef::Integer(value) => value.into(),
+                    ValueRef::Real(value) => value.into(),
+                    ValueRef::Text(value) => String::from_utf8_lossy(value).into(),
+                    ValueRef::Blob(value) => value.iter().map(|byte| format!("{:02x}", byte)).collect::<String>().into(),
+                };
+                values.insert(column.to_owned(), value);
+            }
+            result.push(serde_json::Value::Object(values));
+        }
+
+        Ok(QueryRows { columns, rows: result })
+    }
+
+    fn column_exists(&self, table_name: &str, column_name: &str) -> Result<bool> {
+        Ok(self.get_column_names(table_name)?.contains(column_name))
+    }
+
+    fn get_column_names(&self, table_name: &str) -> Result<HashSet<String>> {
+        let mut stmt = self.conn.prepare(&format!("PRAGMA table_info({});", table_name))?;
+        let names = stmt.query_map(params![], |row| {
+            row.get::<_, String>(1)
+        })?.filter_map(|row| row.ok()).collect();
+
+        Ok(names)
+    }
+
+    /// The query reading the BIOS of the roms, the databases imported before it was stored have none
+    fn with_rom_bios(&self, query: &str) -> Result<String> {
+        if self.column_exists("game_roms", "bios")? {
+            Ok(query.to_string())
+        } else {
+            Ok(query.replace("game_roms.bios", "NULL"))
+        }
+    }
+
+    /// The query reading the optional flag of the roms, no rom is optional in the databases imported before it was stored
+    fn with_rom_optional(&self, query: &str) -> Result<String> {
+        if self.column_exists("game_roms", "optional")? {
+            Ok(query.to_string())
+        } else {
+            Ok(query.replace("game_roms.optional", "0"))
+        }
+    }
+
+    /// The query reading the optional checksums of the roms, the databases imported before they were stored have none
+    fn with_rom_checksums(&self, query: &str) -> Result<String> {
+        let existing = self.get_column_names("roms")?;
+        let mut query = query.to_string();
+        for column in ["sha256", "xxh3"] {
+            if !existing.contains(column) {
+                query = query.replace(&format!("roms.{}", column), "NULL");
+            }
+        }
+        Ok(query)
+    }
+
+    /// The query of `process_games_rom_row`
+    fn get_games_roms_query(&self) -> Result<String> {
+        self.with_rom_checksums(&self.with_rom_optional(&self.with_rom_bios(GAMES_ROMS_QUERY)?)?)
+    }
+
+    /// The columns read by `game_from_row`, the databases imported before the machine info was stored get the values
+    /// of a plain runnable game
+    fn get_game_columns(&self) -> Result<String> {
+        let existing = self.get_column_names("games")?;
+        let mut columns = vec!["name", "clone_of", "rom_of", "source_file", "sample_of", "info_desc", "info_year", "info_manuf"];
+        for (column, default) in [("is_bios", "0"), ("is_device", "0"), ("is_mechanical", "0"), ("runnable", "1"), ("driver_status", "NULL"), ("software_list", "NULL")] {
+            columns.push(if existing.contains(column) { column } else { default });
+        }
+        Ok(columns.join(", "))
+    }
+
+    fn game_from_row(row: &rusqlite::Row) -> rusqlite::Result<Game> {
+        Ok(Game {
+            name: row.get(0)?,
+            clone_of: row.get(1)?,
+            rom_of: row.get(2)?,
+            source_file: row.get(3)?,
+            sample_of: row.get(4)?,
+            info_description: row.get(5)?,
+            info_year: row.get(6)?,
+            info_manufacturer: row.get(7)?,
+            is_bios: row.get(8)?,
+            is_device: row.get(9)?,
+            is_mechanical: row.get(10)?,
+            runnable: row.get(11)?,
+            driver_status: row.get(12)?,
+            software_list: row.get(13)?,
+        })
+    }
+
+    fn release_from_row(row: &rusqlite::Row) -> rusqlite::Result<Release> {
+        Ok(Release {
+            name: row.get(0)?,
+            region: row.get(1)?,
+            language: row.get(2)?,
+            date: row.get(3)?,
+            default: row.get::<_, Option<bool>>(4)?.unwrap_or_default(),
+        })
+    }
+
+    /// The releases of every game, by game
+    fn get_all_releases(&self) -> Result<HashMap<String, Vec<Release>>> {
+        let mut releases: HashMap<String, Vec<Release>> = HashMap::new();
+        if !self.table_exists("releases")? {
+            return Ok(releases);
+        }
+
+        let mut stmt = self.conn.prepare("SELECT name, region, language, date, is_default, game_name FROM releases;")?;
+        let mut rows = stmt.query(params![])?;
+        while let Some(row) = rows.next()? {
+            releases.entry(row.get(5)?).or_default().push(DBReader::release_from_row(row)?);
+        }
+
+        Ok(releases)
+    }
+
+    fn table_exists(&self, table_name: &str) -> Result<bool> {
+        let mut stmt = self.conn.prepare("SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name = ?1;")?;
+        let count: u32 = stmt.query_row(params![ table_name ], |row| {
+            row.get(0)
+        })?;
+
+        Ok(count > 0)
+    }
+
     fn find_sets_for_roms(&self, db_roms: Vec<DbDataEntry<DataFile>>, rom_mode: RomsetMode) -> Result<RomSearch> {
         let mut params: Vec<&dyn ToSql> = vec![];
         let mut ids_cond = String::new();
@@ -203,7 +643,7 @@ impl <'d> DBReader <'d>{
         });
 
         // We do a query with all the roms we received, the result will return all sets associated with these roms
-        let query = GAMES_ROMS_QUERY.to_string() + " WHERE game_roms.rom_id IN (" + &ids_cond + ") ORDER BY game_roms.game_name;";
+        let query = self.get_games_roms_query()? + " WHERE game_roms.rom_id IN (" + &ids_cond + ") ORDER BY game_roms.game_name;";
 
         type QueryResult = (Game, DbDataEntry<DataFile>, Option<String>);
         let mut roms_stmt = self.conn.prepare(&query)?;
@@ -233,35 +673,10 @@ impl <'d> DBReader <'d>{
             }).collect::<Vec<_>>()
         }).collect::<Vec<_>>();
 
-        let mut result = RomSearch::new();
-        for item in roms_rows {
-            let game = item.0;
-            let rom = item.1;
-            let game_parent = item.2;
-
-            match rom_mode {
-                RomsetMode::Merged => {
-                    if let Some(game_parent_name) = game.rom_of {
-                        result.add_file_for_set(game_parent_name, rom);
-                    } else {
-                        result.add_file_for_set(game.name, rom);
-                    }
-                }
-                RomsetMode::NonMerged => {
-                    result.add_file_for_set(game.name, rom);
-                }
-                RomsetMode::Split => {
-                    if game_parent == None {
-                        result.add_file_for_set(game.name, rom);
-                    }
-                }
-            }
-        }
-
-        Ok(result)
+        Ok(group_sets_by_mode(roms_rows, rom_mode))
     }
 
-    pub fn get_ids_from_files(conn: &Connection, files: Vec<DataFile>) -> Result<SearchEntryIds<DataFile>> {
+    pub fn get_ids_from_files(conn: &Connection, files: Vec<DataFile>, match_policy: MatchPolicy) -> Result<SearchEntryIds<DataFile>> {
         let mut result = SearchEntryIds::new();
         for rom_file in files {
             let rom = &rom_file.info;
@@ -272,59 +687,45 @@ impl <'d> DBReader <'d>{
                     result.ignored.push(rom_file);
                 },
                 _ => {
-                    let mut params: Vec<(&str, &dyn ToSql)> = vec![];
-                    let mut statement_where = vec![];
-                    let mut has_hash = false;
-
-                    if let Some(ref sha1) = rom.sha1 {
-                        has_hash = true;
-                        params.push((":sha1", sha1));
-                        statement_where.push("(sha1 = :sha1 OR sha1 IS NULL)");
-                    }
-                    if let Some(ref md5) = rom.md5 {
-                        has_hash = true;
-                        params.push((":md5", md5));
-                        statement_where.push("(md5 = :md5 OR md5 IS NULL)");
-                    }
-
-                    if !has_hash {
-                        warn!("Rom `{}` has no hash value, it could match any other rom, should be ignored", rom_file);
-                        result.not_found.push(rom_file);
-                    } else {
-                        if let Some(ref crc) = rom.crc {
-                            params.push((":crc", crc));
-                            statement_where.push("(crc = :crc OR crc IS NULL)");
+                    let mut matched_info = rom;
+                    let mut query_rom_result = match find_roms_by_info(conn, rom)? {
+                        Some(query_rom_result) => query_rom_result,
+                        None => {
+                            warn!("Rom `{}` has no hash value, it could match any other rom, should be ignored", rom_file);
+                            result.not_found.push(rom_file);
+                            continue;
                         }
-                        if let Some(ref size) = rom.size {
-                            params.push((":size", size));
-                            statement_where.push("(size = :size OR size IS NULL)");
+                    };
+                    // A headered dump is found by its data without the header, for the DATs of headerless dumps
+                    if let (true, Some(headerless)) = (query_rom_result.is_empty(), &rom_file.headerless) {
+                        if let Some(headerless_result) = find_roms_by_info(conn, headerless)? {
+                            debug!("Looking for the ROM without its header: {}", headerless);
+                            query_rom_result = headerless_result;
+                            matched_info = headerless;
                         }
+                    }
 
-                        // Minimum fields to find, has to have at least md5 or sha1
-                        statement_where.push("(sha1 IS NOT NULL OR md5 IS NOT NULL)");
-
-                        let statement = "SELECT id FROM roms WHERE ".to_string() +
-                            &statement_where.join(" AND ") + ";";
-                        
-                        let mut rom_stmt = conn.prepare_cached(&statement)?;
-                        let query_rom_result: Vec<u32> = rom_stmt.query_map_named(params.as_slice(), |row| {
-                            Ok(row.get(0)?)
-                        })?.filter_map(|row| row.ok() ).collect();
-
-                        match query_rom_result.len() {
-                            0 => {
-                                debug!("No ROM found in DB: {}", rom);
+                    match query_rom_result.len() {
+                        0 => {
+                            debug!("No ROM found in DB: {}", rom);
+                            result.add_not_found(rom_file);
+                        },
+                        1 => {
+                            debug!("Found ROM in DB: {}", rom);
+                            let (rom_id, db_rom) = &query_rom_result[0];
+                            let checks = MatchedChecks::between(matched_info, db_rom);
+                            if match_policy.accepts(&checks) {
+                                result.matched_checks.insert(*rom_id, checks);
+                                result.add_found(*rom_id, rom_file);
+                            } else {
+                                debug!("ROM found in DB only with {}, not enough for the policy {}: {}", checks, match_policy, rom);
                                 result.add_not_found(rom_file);
-                            },
-                            1 => {
-                                debug!("Found ROM in DB: {}", rom);
-                                result.add_found(query_rom_result[0], rom_file);
-                            },
-                            n => {
-                                // TODO: There is a corner case which is, if the search has a sha1, and the DB has a md5 it may match as both with match against the null value
-                                warn!("Found more than one rom ({}) on the query, ROM: {}", n, rom_file);
-                                result.ignored.push(rom_file);
                             }
+                        },
+                        n => {
+                            // TODO: There is a corner case which is, if the search has a sha1, and the DB has a md5 it may match as both with match against the null value
+                            warn!("Found more than one rom ({}) on the query, ROM: {}", n, rom_file);
+                            result.ignored.push(rom_file);
                         }
                     }
                 }
@@ -394,29 +795,24 @@ impl <'d> DBReader <'d>{
 
 impl <'d> DataReader for DBReader<'d> {
     fn get_game<S>(&self, game_name: S) -> Option<Game> where S: AsRef<str> + rusqlite::ToSql {
-        let mut game_stmt = self.conn.prepare("SELECT name, clone_of, rom_of, source_file, sample_of, info_desc, info_year, info_manuf
-            FROM games WHERE name = ?1;").ok()?;
-        let game_result= game_stmt.query_row(params![ game_name ], |row| {
-            Ok(
-                Game {
-                    name: row.get(0)?,
-                    clone_of: row.get(1)?,
-                    rom_of: row.get(2)?,
-                    source_file: row.get(3)?,
-                    sample_of: row.get(4)?,
-                    info_description: row.get(5)?,
-                    info_year: row.get(6)?,
-                    info_manufacturer: row.get(7)?
-                }
-            )
-        });
+        let columns = self.get_game_columns().ok()?;
+        let mut game_stmt = self.conn.prepare(&format!("SELECT {} FROM games WHERE name = ?1;", columns)).ok()?;
+        let game_result= game_stmt.query_row(params![ game_name ], DBReader::game_from_row);
 
         match game_result {
             Ok(game) => {
                 Some(game)
             },
             Err(rusqlite::Error::QueryReturnedNoRows) => {
-                None
+                // It may be an old name of the game
+                match self.get_aliased_game(game_name.as_ref()) {
+                    Ok(Some(current_name)) => self.get_game(current_name),
+                    Ok(None) => None,
+                    Err(e) => {
+                        error!("Unexpected error reading the roms database: {}", e);
+                        None
+                    }
+                }
             },
             Err(e) => {
                 error!("Unexpected error reading the roms database: {}", e);
@@ -426,7 +822,7 @@ impl <'d> DataReader for DBReader<'d> {
     }
 
     fn get_romset_roms<S>(&self, game_name: S, rom_mode: RomsetMode) -> Result<(Game, Vec<DbDataEntry<DataFile>>)> where S: AsRef<str> + rusqlite::ToSql {
-        let mut query = GAMES_ROMS_QUERY.to_string();
+        let mut query = self.get_games_roms_query()?;
         match rom_mode {
             RomsetMode::Merged => {
                 query.push_str(" WHERE (game_roms.game_name = ?1 OR games.rom_of = ?1);");
@@ -457,6 +853,77 @@ impl <'d> DataReader for DBReader<'d> {
         }
     }
 
+    fn get_game_entry<S>(&self, game_name: S) -> Result<Option<GameEntry>> where S: AsRef<str> + rusqlite::ToSql {
+        let game = match self.get_game(&game_name) {
+            Some(game) => game,
+            None => return Ok(None)
+        };
+
+        let query = self.with_rom_optional(&self.with_rom_bios("SELECT game_roms.name, roms.sha1, roms.md5, roms.crc, roms.size, game_roms.status, game_roms.bios, roms.sha256, roms.xxh3, game_roms.optional
+            FROM game_roms JOIN roms ON game_roms.rom_id = roms.id WHERE game_roms.game_name = ?1;")?)?;
+        let mut roms_stmt = self.conn.prepare(&self.with_rom_checksums(&query)?)?;
+        let roms = roms_stmt.query_map(params![ game_name ], |row| {
+            let mut data_file_info = DataFileInfo::new(FileType::Rom);
+            data_file_info.sha1 = row.get(1)?;
+            data_file_info.md5 = row.get(2)?;
+            data_file_info.crc = row.get(3)?;
+            data_file_info.size = row.get(4)?;
+            data_file_info.sha256 = row.get(7)?;
+            data_file_info.xxh3 = row.get(8)?;
+            let name: String = row.get(0)?;
+            let mut data_file = DataFile::new_with_status(name, data_file_info, row.get(5)?);
+            data_file.bios = row.get(6)?;
+            data_file.optional = row.get(9)?;
+            Ok(data_file)
+        })?.filter_map(|row| row.ok()).collect();
+
+        let mut disks_stmt = self.conn.prepare("SELECT game_disks.name, disks.sha1, disks.region, disks.status
+            FROM game_disks JOIN disks ON game_disks.disk_id = disks.id WHERE game_disks.game_name = ?1;")?;
+        let disks = disks_stmt.query_map(params![ game_name ], |row| {
+            let name: String = row.get(0)?;
+            let mut disk = GameDisk::new(name);
+            disk.info.sha1 = row.get(1)?;
+            disk.info.region = row.get(2)?;
+            disk.info.status = row.get(3)?;
+            Ok(disk)
+        })?.filter_map(|row| row.ok()).collect();
+
+        let samples = match &game.sample_of {
+            Some(sample_set) => {
+                let mut samples_stmt = self.conn.prepare("SELECT sample FROM samples WHERE sample_set = ?1;")?;
+                let samples = samples_stmt.query_map(params![ sample_set ], |row| {
+                    row.get(0)
+                })?.filter_map(|row| row.ok()).collect();
+                samples
+            }
+            None => vec![]
+        };
+
+        let mut devices_stmt = self.conn.prepare("SELECT device_ref FROM devices WHERE game_name = ?1;")?;
+        let device_refs = devices_stmt.query_map(params![ game_name ], |row| {
+            row.get(0)
+        })?.filter_map(|row| row.ok()).collect();
+
+        Ok(Some(GameEntry::new(game, roms, disks, samples, device_refs)))
+    }
+
+    fn find_games(&self, filter: &GameFilter) -> Result<Vec<Game>> {
+        self.iter_games(filter).collect()
+    }
+
+    fn iter_games<'a>(&'a self, filter: &'a GameFilter) -> impl Iterator<Item = Result<Game>> + 'a {
+        GameIterator::new(self.conn, filter, GAMES_BATCH_SIZE)
+    }
+
+    fn get_clones<S>(&self, game_name: S) -> Result<Vec<String>> where S: AsRef<str> + rusqlite::ToSql {
+        let mut stmt = self.conn.prepare("SELECT name FROM games WHERE clone_of = ?1 ORDER BY name;")?;
+        let clones = stmt.query_map(params![ game_name ], |row| {
+            row.get(0)
+        })?.filter_map(|row| row.ok()).collect();
+
+        Ok(clones)
+    }
+
     fn find_rom_usage<S>(&self, game_name: S, rom_name: S, rom_mode: RomsetMode) -> Result<RomSearch> where S: AsRef<str> + rusqlite::ToSql {
         let game_roms = self.get_romset_roms(game_name, rom_mode)?;
         
@@ -468,7 +935,7 @@ impl <'d> DataReader for DBReader<'d> {
             }
         }).collect();
 
-        let rom_ids = DBReader::get_ids_from_files(self.conn, roms)?.found;
+        let rom_ids = DBReader::get_ids_from_files(self.conn, roms, self.match_policy)?.found;
 
         self.find_sets_for_roms(rom_ids, rom_mode)
     }
@@ -481,14 +948,73 @@ impl <'d> DataReader for DBReader<'d> {
         self.find_sets_for_roms(game_roms, rom_mode)
     }
 
+    fn get_match_policy(&self) -> MatchPolicy {
+        self.match_policy
+    }
+
     fn get_romsets_from_roms(&self, roms: Vec<DataFile>, rom_mode: RomsetMode) -> Result<RomSearch> {
-        let mut search_rom_ids_result = DBReader::get_ids_from_files(self.conn, roms)?;
+        let mut search_rom_ids_result = DBReader::get_ids_from_files(self.conn, roms, self.match_policy)?;
 
         let mut rom_search = self.find_sets_for_roms(search_rom_ids_result.found, rom_mode)?;
         rom_search.unknowns.append(search_rom_ids_result.not_found.as_mut());
+        rom_search.matched_checks = search_rom_ids_result.matched_checks;
         Ok(rom_search)
     }
 
+    fn find_rom_locations(&self, rom_info: &DataFileInfo, rom_mode: RomsetMode) -> Result<RomSearch> {
+        let mut params: Vec<(&str, &dyn ToSql)> = vec![];
+        let mut statement_where = vec![];
+        let mut any_match = vec![];
+
+        if let Some(ref sha1) = rom_info.sha1 {
+            params.push((":sha1", sha1));
+            statement_where.push("(roms.sha1 = :sha1 OR roms.sha1 IS NULL)");
+            any_match.push("roms.sha1 = :sha1");
+        }
+        if let Some(ref md5) = rom_info.md5 {
+            params.push((":md5", md5));
+            statement_where.push("(roms.md5 = :md5 OR roms.md5 IS NULL)");
+            any_match.push("roms.md5 = :md5");
+        }
+        if let Some(ref crc) = rom_info.crc {
+            params.push((":crc", crc));
+            statement_where.push("(roms.crc = :crc OR roms.crc IS NULL)");
+            any_match.push("roms.crc = :crc");
+        }
+        if let Some(ref sha256) = rom_info.sha256 {
+            params.push((":sha256", sha256));
+            statement_where.push("(roms.sha256 = :sha256 OR roms.sha256 IS NULL)");
+            any_match.push("roms.sha256 = :sha256");
+        }
+        if let Some(ref xxh3) = rom_info.xxh3 {
+            params.push((":xxh3", xxh3));
+            statement_where.push("(roms.xxh3 = :xxh3 OR roms.xxh3 IS NULL)");
+            any_match.push("roms.xxh3 = :xxh3");
+        }
+        if any_match.is_empty() {
+            return Ok(RomSearch::new());
+        }
+        if let Some(ref size) = rom_info.size {
+            params.push((":size", size));
+            statement_where.push("(roms.size = :size OR roms.size IS NULL)");
+        }
+
+        // At least one of the checksums has to be present in the DB, the other ones may be missing
+        let any_match = format!("({})", any_match.join(" OR "));
+        statement_where.push(&any_match);
+
+        let match_policy = self.match_policy;
+        let query = self.get_games_roms_query()? + " WHERE " + &statement_where.join(" AND ") + " ORDER BY game_roms.game_name;";
+        let mut roms_stmt = self.conn.prepare(&query)?;
+        let roms_rows = roms_stmt.query_map_named(params.as_slice(), |row| {
+            process_games_rom_row(row)
+        })?.filter_map(|result| result.ok())
+            .filter(|(_, rom, _)| match_policy.accepts(&MatchedChecks::between(rom_info, &rom.file.info)))
+            .collect::<Vec<_>>();
+
+        Ok(group_sets_by_mode(roms_rows, rom_mode))
+    }
+
     fn get_devices_for_game<S>(&self, game_name: S) -> Result<SetDependencies> where S: AsRef<str> + rusqlite::ToSql {
         let mut search_stmt = self.conn.prepare("SELECT devices.device_ref FROM devices
             JOIN game_roms ON devices.device_ref = game_roms.game_name
@@ -504,13 +1030,386 @@ impl <'d> DataReader for DBReader<'d> {
         Ok(set_dependencies)
     }
 
+    fn get_games_using_device<S>(&self, device_name: S) -> Result<Vec<String>> where S: AsRef<str> + rusqlite::ToSql {
+        let mut stmt = self.conn.prepare("SELECT DISTINCT game_name FROM devices WHERE device_ref = ?1 ORDER BY game_name;")?;
+        let games = stmt.query_map(params![ device_name ], |row| {
+            row.get(0)
+        })?.filter_map(|row| row.ok()).collect();
+
+        Ok(games)
+    }
+
+    fn get_games_using_samples<S>(&self, sample_set: S) -> Result<Vec<String>> where S: AsRef<str> + rusqlite::ToSql {
+        let mut stmt = self.conn.prepare("SELECT name FROM games WHERE sample_of = ?1 ORDER BY name;")?;
+        let games = stmt.query_map(params![ sample_set ], |row| {
+            row.get(0)
+        })?.filter_map(|row| row.ok()).collect();
+
+        Ok(games)
+    }
+
+    fn get_sample_sets(&self) -> Result<Vec<(String, u32)>> {
+        let mut stmt = self.conn.prepare("SELECT sample_of, COUNT(*) AS games FROM games WHERE sample_of IS NOT NULL
+            GROUP BY sample_of ORDER BY games DESC, sample_of;")?;
+        let sample_sets = stmt.query_map(params![], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?.filter_map(|row| row.ok()).collect();
+
+        Ok(sample_sets)
+    }
+
+    fn get_unreferenced_roms(&self) -> Result<Vec<DbDataEntry<DataFileInfo>>> {
+        let mut stmt = self.conn.prepare("SELECT id, sha1, md5, crc, size FROM roms WHERE id NOT IN (SELECT rom_id FROM game_roms) ORDER BY id;")?;
+        let roms = stmt.query_map(params![], |row| {
+            let mut info = DataFileInfo::new(FileType::Rom);
+            info.sha1 = row.get(1)?;
+            info.md5 = row.get(2)?;
+            info.crc = row.get(3)?;
+            info.size = row.get(4)?;
+            Ok(DbDataEntry::new(row.get(0)?, info))
+        })?.filter_map(|row| row.ok()).collect();
+
+        Ok(roms)
+    }
+
+    fn get_families(&self) -> Result<BTreeMap<String, Vec<String>>> {
+        let mut stmt = self.conn.prepare("SELECT clone_of, name FROM games WHERE clone_of IS NOT NULL ORDER BY clone_of, name;")?;
+        let rows = stmt.query_map(params![], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?.filter_map(|row| row.ok());
+
+        let mut families: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (parent, clone) in rows {
+            families.entry(parent).or_default().push(clone);
+        }
+
+        Ok(families)
+    }
+
+    fn get_near_matches(&self, file: &DataFile) -> Result<Vec<NearMatch>> {
+        let query = "SELECT DISTINCT game_roms.game_name, game_roms.name, roms.sha1, roms.md5, roms.crc, roms.size, game_roms.status
+            FROM game_roms JOIN roms ON game_roms.rom_id = roms.id";
+        let read_row = |row: &Row| -> Result<(String, DataFile), rusqlite::Error> {
+            let mut info = DataFileInfo::new(FileType::Rom);
+            info.sha1 = row.get(2)?;
+            info.md5 = row.get(3)?;
+            info.crc = row.get(4)?;
+            info.size = row.get(5)?;
+            Ok((row.get(0)?, DataFile::new_with_status(row.get::<_, String>(1)?, info, row.get(6)?)))
+        };
+        // Only the roms with a checksum that disagrees with the file are near misses
+        let differs = |rom: &DataFile| {
+            [(&file.info.sha1, &rom.info.sha1), (&file.info.md5, &rom.info.md5), (&file.info.crc, &rom.info.crc)].iter()
+                .any(|(file_check, rom_check)| matches!((file_check, rom_check), (Some(file_check), Some(rom_check)) if !file_check.eq_ignore_ascii_case(rom_check)))
+        };
+
+        let mut near_matches: Vec<NearMatch> = vec![];
+        if let (Some(crc), Some(size)) = (&file.info.crc, file.info.size) {
+            let mut stmt = self.conn.prepare(&format!("{} WHERE roms.crc = ?1 AND roms.size = ?2 ORDER BY game_roms.game_name, game_roms.name;", query))?;
+            let roms = stmt.query_map(params![crc.to_lowercase(), size], read_row)?.filter_map(|row| row.ok());
+            for (game_name, rom) in roms.filter(|(_, rom)| differs(rom)) {
+                near_matches.push(NearMatch { game_name, rom, reason: NearMatchReason::SameCrcAndSize });
+            }
+        }
+
+        let mut stmt = self.conn.prepare(&format!("{} WHERE game_roms.name = ?1 ORDER BY game_roms.game_name;", query))?;
+        let roms = stmt.query_map(params![file.name], read_row)?.filter_map(|row| row.ok()).collect::<Vec<_>>();
+        for (game_name, rom) in roms.into_iter().filter(|(_, rom)| differs(rom)) {
+            if !near_matches.iter().any(|near_match| near_match.game_name == game_name && near_match.rom.name == rom.name) {
+                near_matches.push(NearMatch { game_name, rom, reason: NearMatchReason::SameName });
+            }
+        }
+
+        Ok(near_matches)
+    }
+
+    fn search_roms(&self, filter: &RomFilter) -> Result<Vec<RomNameMatch>> {
+        // The names are matched first, as there are far less names than roms in the games
+        let mut names_stmt = self.conn.prepare("SELECT DISTINCT name FROM game_roms ORDER BY name;")?;
+        let names = names_stmt.query_map(params![], |row| row.get::<_, String>(0))?
+            .filter_map(|row| row.ok())
+            .filter(|name| filter.matches(name))
+            .collect::<Vec<_>>();
+
+        let mut roms_stmt = self.conn.prepare(&self.with_rom_bios("SELECT game_roms.game_name, roms.sha1, roms.md5, roms.crc, roms.size, game_roms.status, game_roms.bios
+            FROM game_roms JOIN roms ON game_roms.rom_id = roms.id WHERE game_roms.name = ?1 ORDER BY game_roms.game_name;")?)?;
+        let mut found: Vec<RomNameMatch> = vec![];
+        for name in names {
+            let roms = roms_stmt.query_map(params![ name ], |row| {
+                let mut info = DataFileInfo::new(FileType::Rom);
+                info.sha1 = row.get(1)?;
+                info.md5 = row.get(2)?;
+                info.crc = row.get(3)?;
+                info.size = row.get(4)?;
+                let mut rom = DataFile::new_with_status(name.as_str(), info, row.get(5)?);
+                rom.bios = row.get(6)?;
+                Ok((row.get::<_, String>(0)?, rom))
+            })?.filter_map(|row| row.ok()).collect::<Vec<_>>();
+
+            // The roms with the same name in several sets are listed once for each content they have
+            let first = found.len();
+            for (game_name, rom) in roms {
+                match found[first..].iter_mut().find(|found_rom| found_rom.rom == rom) {
+                    Some(found_rom) => found_rom.sets.push(game_name),
+                    None => found.push(RomNameMatch { rom, sets: vec![game_name] }),
+                }
+            }
+        }
+
+        Ok(found)
+    }
+
+    fn get_exclusions(&self) -> Result<HashSet<String>> {
+        // Databases created before exclusions existed don't have the table
+        if !self.table_exists("exclusions")? {
+            return Ok(HashSet::new());
+        }
+
+        let mut stmt = self.conn.prepare("SELECT game_name FROM exclusions;")?;
+        let exclusions = stmt.query_map(params![], |row| {
+            row.get(0)
+        })?.filter_map(|row| row.ok()).collect();
+
+        Ok(exclusions)
+    }
+
+    fn get_releases<S>(&self, game_name: S) -> Result<Vec<Release>> where S: AsRef<str> + rusqlite::ToSql {
+        if !self.table_exists("releases")? {
+            return Ok(vec![]);
+        }
+
+        let mut stmt = self.conn.prepare("SELECT name, region, language, date, is_default FROM releases
+            WHERE game_name = ?1 ORDER BY is_default DESC, region, name;")?;
+        let releases = stmt.query_map(params![ game_name ], DBReader::release_from_row)?.collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(releases)
+    }
+
+    fn get_games_with_release(&self, filter: &ReleaseFilter) -> Result<HashSet<String>> {
+        let releases = self.get_all_releases()?;
+        let mut games = releases.iter()
+            .filter(|(_, releases)| releases.iter().any(|release| filter.matches(release)))
+            .map(|(game_name, _)| game_name.to_owned())
+            .collect::<HashSet<_>>();
+        // One of each family, among the ones released in the region and language if any
+        if let Some(priorities) = &filter.prefer {
+            let all_games = self.find_games(&GameFilter::new("*", PatternKind::Glob)?)?;
+            let candidates = all_games.iter().filter(|game| !filter.filters_releases() || games.contains(&game.name));
+            games = priorities.select(candidates, &releases);
+        }
+
+        Ok(games)
+    }
+
+    fn get_rom_shares(&self, rom: &DataFileInfo) -> Result<Option<u32>> {
+        // Databases created before the counts were stored don't have the table, until they are migrated
+        if !self.table_exists("rom_shares")? {
+            return Ok(None);
+        }
+
+        let mut stmt = self.conn.prepare_cached("SELECT rom_shares.sets FROM roms JOIN rom_shares ON rom_shares.rom_id = roms.id
+            WHERE roms.sha1 IS ?1 AND roms.md5 IS ?2 AND roms.crc IS ?3 AND roms.size IS ?4;")?;
+        let sets = stmt.query_row(params![ rom.sha1, rom.md5, rom.crc, rom.size ], |row| row.get(0)).optional()?;
+
+        Ok(sets)
+    }
+
+    fn get_games_with_disks(&self) -> Result<HashSet<String>> {
+        let mut stmt = self.conn.prepare("SELECT DISTINCT game_name FROM game_disks;")?;
+        let games = stmt.query_map(params![], |row| {
+            row.get(0)
+        })?.filter_map(|row| row.ok()).collect();
+
+        Ok(games)
+    }
+
+    fn get_tags(&self) -> Result<HashMap<String, HashSet<String>>> {
+        let mut tags: HashMap<String, HashSet<String>> = HashMap::new();
+        if !self.table_exists("tags")? {
+            return Ok(tags);
+        }
+
+        let mut stmt = self.conn.prepare("SELECT game_name, tag FROM tags;")?;
+        stmt.query_map(params![], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?.filter_map(|row| row.ok()).for_each(|(game_name, tag): (String, String)| {
+            tags.entry(game_name).or_default().insert(tag);
+        });
+
+        Ok(tags)
+    }
+
+    fn get_note<S>(&self, game_name: S) -> Result<Option<String>> where S: AsRef<str> + rusqlite::ToSql {
+        if !self.table_exists("notes")? {
+            return Ok(None);
+        }
+
+        let mut stmt = self.conn.prepare("SELECT note FROM notes WHERE game_name = ?1;")?;
+        match stmt.query_row(params![ game_name ], |row| row.get(0)) {
+            Ok(note) => Ok(Some(note)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into())
+        }
+    }
+
+    fn get_dats(&self) -> Result<Vec<DatInfo>> {
+        if !self.table_exists("info")? {
+            return Ok(vec![]);
+        }
+
+        // The rowid is the dat id, databases created before it existed only have the rowid
+        let mut stmt = self.conn.prepare("SELECT rowid, name, description, version FROM info ORDER BY rowid;")?;
+        let mut dats = stmt.query_map(params![], |row| {
+            Ok(DatInfo {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                description: row.get(2)?,
+                version: row.get(3)?,
+                header: BTreeMap::new(),
+                source: None,
+            })
+        })?.filter_map(|row| row.ok()).collect::<Vec<_>>();
+
+        // Databases created before the import source was recorded don't have the columns
+        if self.column_exists("info", "source")? {
+            let mut source_stmt = self.conn.prepare("SELECT source, source_sha1, imported_at, romst_version FROM info
+                WHERE rowid = ?1 AND source IS NOT NULL;")?;
+            for dat in dats.iter_mut() {
+                dat.source = source_stmt.query_map(params![ dat.id ], |row| {
+                    Ok(ImportSource {
+                        path: row.get(0)?,
+                        sha1: row.get(1)?,
+                        imported_at: row.get(2)?,
+                        romst_version: row.get(3)?,
+                    })
+                })?.filter_map(|row| row.ok()).next();
+            }
+        }
+
+        // Databases created before the headers were kept only have the name, description and version
+        if self.table_exists("dat_header")? {
+            let mut header_stmt = self.conn.prepare("SELECT key, value FROM dat_header WHERE dat_id = ?1;")?;
+            for dat in dats.iter_mut() {
+                dat.header = header_stmt.query_map(params![ dat.id ], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })?.filter_map(|row| row.ok()).collect();
+            }
+        }
+
+        Ok(dats)
+    }
+
+    fn get_report_history(&self, source: Option<&str>) -> Result<Vec<ReportHistoryEntry>> {
+        if !self.table_exists("report_history")? {
+            return Ok(vec![]);
+        }
+
+        let mut stmt = self.conn.prepare("
+            SELECT id, source, date_time, rom_mode, complete, fixable, incomplete, missing FROM report_history
+            WHERE ?1 IS NULL OR source = ?1
+            ORDER BY id;")?;
+        let entries = stmt.query_map(params![ source ], |row| {
+            Ok(ReportHistoryEntry {
+                id: row.get(0)?,
+                source: row.get(1)?,
+                date_time: row.get(2)?,
+                rom_mode: row.get(3)?,
+                complete: row.get(4)?,
+                fixable: row.get(5)?,
+                incomplete: row.get(6)?,
+                missing: row.get(7)?,
+            })
+        })?.filter_map(|row| row.ok()).collect();
+
+        Ok(entries)
+    }
+
+    fn get_game_changes(&self) -> Result<Vec<GameChange>> {
+        if !self.table_exists("game_changes")? {
+            return Ok(vec![]);
+        }
+
+        let mut stmt = self.conn.prepare("SELECT game_name, last_version, removed_in, successor, date_time FROM game_changes
+            ORDER BY date_time, game_name;")?;
+        let changes = stmt.query_map(params![], |row| {
+            Ok(GameChange {
+                game_name: row.get(0)?,
+                last_version: row.get(1)?,
+                removed_in: row.get(2)?,
+                successor: row.get(3)?,
+                date_time: row.get(4)?,
+            })
+        })?.collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(changes)
+    }
+
+    fn get_game_aliases(&self) -> Result<Vec<GameAlias>> {
+        if !self.table_exists("game_aliases")? {
+            return Ok(vec![]);
+        }
+
+        let mut stmt = self.conn.prepare("SELECT alias, game_name FROM game_aliases ORDER BY alias;")?;
+        let aliases = stmt.query_map(params![], |row| {
+            Ok(GameAlias {
+                alias: row.get(0)?,
+                game_name: row.get(1)?,
+            })
+        })?.collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(aliases)
+    }
+
+    fn get_aliased_game(&self, alias: &str) -> Result<Option<String>> {
+        if !self.table_exists("game_aliases")? {
+            return Ok(None);
+        }
+
+        let mut stmt = self.conn.prepare("SELECT games.name FROM game_aliases JOIN games ON games.name = game_aliases.game_name
+            WHERE game_aliases.alias = ?1;")?;
+        match stmt.query_row(params![ alias ], |row| row.get(0)) {
+            Ok(game_name) => Ok(Some(game_name)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn get_game_names(&self, dat_id: Option<u32>) -> Result<Vec<String>> {
+        let game_names = match dat_id {
+            Some(dat_id) => {
+                if !self.column_exists("games", "dat_id")? {
+                    return Ok(vec![]);
+                }
+                let mut stmt = self.conn.prepare("SELECT name FROM games WHERE dat_id = ?1 ORDER BY name;")?;
+                let names = stmt.query_map(params![ dat_id ], |row| {
+                    row.get(0)
+                })?.filter_map(|row| row.ok()).collect();
+                names
+            }
+            None => {
+                let mut stmt = self.conn.prepare("SELECT name FROM games ORDER BY name;")?;
+                let names = stmt.query_map(params![], |row| {
+                    row.get(0)
+                })?.filter_map(|row| row.ok()).collect();
+                names
+            }
+        };
+
+        Ok(game_names)
+    }
+
     fn get_file_checks(&self) -> Result<FileCheckSearch> {
-        let mut stmt = self.conn.prepare("SELECT count(sha1), count(md5), count(crc) FROM roms;")?;
+        // The databases imported before the optional checksums were stored don't have their columns
+        let existing = self.get_column_names("roms")?;
+        let count = |column: &str| if existing.contains(column) { format!("count({})", column) } else { "0".to_string() };
+        let mut stmt = self.conn.prepare(&format!("SELECT count(sha1), count(md5), count(crc), {}, {} FROM roms;", count("sha256"), count("xxh3")))?;
         let result = stmt.query_row(params![], |row| {
             Ok(FileCheckSearch {
                 sha1: row.get(0)?,
                 md5: row.get(1)?,
                 crc: row.get(2)?,
+                sha256: row.get(3)?,
+                xxh3: row.get(4)?,
             })
         })?;
 
@@ -518,11 +1417,48 @@ impl <'d> DataReader for DBReader<'d> {
     }
 }
 
+/// How many statements the SQL has, the semicolons in literals, quoted names and comments don't split them
+fn count_statements(sql: &str) -> usize {
+    let mut statements = 0;
+    let mut has_content = false;
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' | '`' | '[' => {
+                let end = if c == '[' { ']' } else { c };
+                // A doubled quote is an escaped one, it's read as the end and the start of the literal
+                chars.by_ref().find(|next| *next == end);
+                has_content = true;
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                chars.by_ref().find(|next| *next == '\n');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                chars.by_ref().find(|next| std::mem::replace(&mut previous, *next) == '*' && *next == '/');
+            }
+            ';' => {
+                if has_content {
+                    statements += 1;
+                }
+                has_content = false;
+            }
+            c if !c.is_whitespace() => has_content = true,
+            _ => {}
+        }
+    }
+    if has_content {
+        statements += 1;
+    }
+    statements
+}
+
 #[cfg(test)]
 mod tests {
     use std::{io::BufReader, fs::File, path::Path};
     use rusqlite::{Connection, OpenFlags};
-    use crate::data::{importer::DatImporter, models::{disk::GameDiskInfo, file::FileType}, reader::sqlite::DBReader, writer::{sqlite::DBWriter}};
+    use crate::data::{importer::DatImporter, models::{disk::GameDiskInfo, file::FileType, search::PatternKind}, reader::sqlite::DBReader, writer::{sqlite::DBWriter}};
     use super::*;
 
     fn get_db_connection<'a, 'b>(dat_path: &'b impl AsRef<Path>) -> Result<Connection> {
@@ -593,7 +1529,7 @@ mod tests {
         rom4.info.crc = Some("0d46fa2d".to_string());
         roms.push(rom4);
 
-        let rom_ids = DBReader::get_ids_from_files(&conn, roms)?.found;
+        let rom_ids = DBReader::get_ids_from_files(&conn, roms, MatchPolicy::default())?.found;
 
         let roms = rom_ids.len();
         assert_eq!(4, roms);
@@ -667,7 +1603,7 @@ mod tests {
         let mut rom1 = DataFile::new("rom1", DataFileInfo::new(FileType::Rom));
         rom1.info.sha1 = Some("8bb3a81b9fa2de5163f0ffc634a998c455bcca25".to_string());
         roms.push(rom1);
-        let result = DBReader::get_ids_from_files(&conn, roms)?;
+        let result = DBReader::get_ids_from_files(&conn, roms, MatchPolicy::default())?;
         let rom_ids = result.found;
         let not_found = result.not_found;
 
@@ -688,7 +1624,7 @@ mod tests {
         rom1.info.sha1 = Some("802e076afc412be12db3cb8c79523f65d612a6cf".to_string());
         rom1.info.crc = Some("dc20b010".to_string());
         roms.push(rom1);
-        let result = DBReader::get_ids_from_files(&conn, roms)?;
+        let result = DBReader::get_ids_from_files(&conn, roms, MatchPolicy::default())?;
         let rom_ids = result.found;
         let not_found = result.not_found;
 
@@ -710,7 +1646,7 @@ mod tests {
         rom1.info.crc = Some("1b736d41".to_string());
         rom1.info.md5 = Some("0de4e413deb3ae71e9326d70df4d1a27".to_string());
         roms.push(rom1);
-        let result = DBReader::get_ids_from_files(&conn, roms)?;
+        let result = DBReader::get_ids_from_files(&conn, roms, MatchPolicy::default())?;
         let rom_ids = result.found;
         let not_found = result.not_found;
 
@@ -732,7 +1668,7 @@ mod tests {
         rom1.info.crc = Some("1b736d41".to_string());
         rom1.info.size = Some(1024);
         roms.push(rom1);
-        let result = DBReader::get_ids_from_files(&conn, roms)?;
+        let result = DBReader::get_ids_from_files(&conn, roms, MatchPolicy::default())?;
         let rom_ids = result.found;
         let not_found = result.not_found;
 
@@ -754,4 +1690,313 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn get_tags_by_game() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let mut conn = get_db_connection(&path)?;
+        let writer = DBWriter::from_connection(&mut conn, 5);
+        writer.add_tag(&["game1", "game2"], "favorite")?;
+        writer.add_tag(&["game1"], "wanted")?;
+        writer.remove_tag(&["game2"], "favorite")?;
+
+        let data_reader = DBReader::from_connection(&conn);
+        let tags = data_reader.get_tags()?;
+
+        assert_eq!(1, tags.len());
+        let game1_tags = tags.get("game1").unwrap();
+        assert_eq!(2, game1_tags.len());
+        assert!(game1_tags.contains("favorite"));
+        assert!(game1_tags.contains("wanted"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_note_for_game() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let mut conn = get_db_connection(&path)?;
+        let writer = DBWriter::from_connection(&mut conn, 5);
+        writer.set_note("game1", Some("Dumped from my own board"))?;
+        writer.set_note("game2", Some("Bad dump"))?;
+        writer.set_note("game2", None)?;
+
+        let data_reader = DBReader::from_connection(&conn);
+
+        assert_eq!(Some("Dumped from my own board".to_string()), data_reader.get_note("game1")?);
+        assert_eq!(None, data_reader.get_note("game2")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_report_history_by_source() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let mut conn = get_db_connection(&path)?;
+        let writer = DBWriter::from_connection(&mut conn, 5);
+        let entry = |source: &str, complete: u32| ReportHistoryEntry {
+            id: None, source: source.to_string(), date_time: "2021-01-01T00:00:00+00:00".to_string(), rom_mode: "Split".to_string(),
+            complete, fixable: 0, incomplete: 0, missing: 0
+        };
+        writer.add_report_history(&entry("/roms", 3))?;
+        writer.add_report_history(&entry("/other", 1))?;
+        writer.add_report_history(&entry("/roms", 2))?;
+
+        let data_reader = DBReader::from_connection(&conn);
+
+        assert_eq!(data_reader.get_report_history(None)?.len(), 3);
+        let history = data_reader.get_report_history(Some("/roms"))?;
+        assert_eq!(history.iter().map(|entry| entry.complete).collect::<Vec<_>>(), vec![3, 2]);
+        assert_eq!(history[1].id, Some(3));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_game_entry_and_clones() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+
+        let entry = data_reader.get_game_entry("game5")?.unwrap();
+        assert_eq!(3, entry.roms.len());
+        assert_eq!(1, entry.disks.len());
+        assert_eq!("gm5-001.chd", entry.disks[0].name);
+
+        let entry = data_reader.get_game_entry("game1")?.unwrap();
+        assert_eq!(2, entry.device_refs.len());
+        assert!(data_reader.get_game_entry("nogame")?.is_none());
+
+        assert_eq!(vec!["game1a".to_string()], data_reader.get_clones("game1")?);
+        assert!(data_reader.get_clones("game2")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn search_roms_by_name() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+
+        let found = data_reader.search_roms(&RomFilter::new("rom?.trom", PatternKind::Glob)?)?;
+        let names = found.iter().map(|rom| rom.rom.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(vec!["rom1.trom", "rom2.trom", "rom3.trom", "rom4.trom", "rom5.trom"], names);
+        assert_eq!(vec!["game1".to_string(), "game1a".to_string()], found[0].sets);
+        assert_eq!(Some("1d460eee"), found[0].rom.info.crc.as_deref());
+        assert_eq!(vec!["game1a".to_string()], found[3].sets);
+
+        // The same name with other content is another rom
+        let found = data_reader.search_roms(&RomFilter::new(r"^rhum\d", PatternKind::Regex)?)?;
+        assert_eq!(2, found.len());
+        assert!(found.iter().all(|rom| rom.sets == vec!["game4".to_string()]));
+
+        assert!(data_reader.search_roms(&RomFilter::new("*.zip", PatternKind::Glob)?)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn find_games_by_pattern() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+
+        let filter = GameFilter::new("game1*", PatternKind::Glob)?;
+        let games: Vec<String> = data_reader.find_games(&filter)?.into_iter().map(|game| game.name).collect();
+        assert_eq!(vec!["game1".to_string(), "game1a".to_string()], games);
+
+        let mut filter = GameFilter::new("parent", PatternKind::Regex)?;
+        filter.clones = Some(false);
+        let games: Vec<String> = data_reader.find_games(&filter)?.into_iter().map(|game| game.name).collect();
+        assert_eq!(vec!["game1".to_string()], games);
+
+        let mut filter = GameFilter::new("*", PatternKind::Glob)?;
+        filter.devices = Some(true);
+        let games: Vec<String> = data_reader.find_games(&filter)?.into_iter().map(|game| game.name).collect();
+        assert_eq!(vec!["device1".to_string(), "deviceref".to_string(), "screen".to_string()], games);
+
+        let mut filter = GameFilter::new("game*", PatternKind::Glob)?;
+        filter.driver_status = Some("imperfect".to_string());
+        filter.runnable = Some(true);
+        let games: Vec<String> = data_reader.find_games(&filter)?.into_iter().map(|game| game.name).collect();
+        assert_eq!(vec!["game0".to_string(), "game1".to_string(), "game1a".to_string()], games);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_releases_by_region() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+
+        let releases = data_reader.get_releases("game1")?;
+        assert_eq!(releases.len(), 2);
+        assert_eq!(releases[0].region, "USA");
+        assert!(releases[0].default);
+        assert!(data_reader.get_releases("game2")?.is_empty());
+
+        let usa = ReleaseFilter { region: Some("USA".to_string()), ..ReleaseFilter::default() };
+        assert_eq!(data_reader.get_games_with_release(&usa)?, HashSet::from_iter(vec!["game1".to_string()]));
+        let english = ReleaseFilter { language: Some("en".to_string()), ..ReleaseFilter::default() };
+        assert_eq!(data_reader.get_games_with_release(&english)?.len(), 2);
+
+        let mut filter = GameFilter::new("game*", PatternKind::Glob)?;
+        filter.releases = english;
+        let games: Vec<String> = data_reader.find_games(&filter)?.into_iter().map(|game| game.name).collect();
+        assert_eq!(vec!["game1".to_string(), "game3".to_string()], games);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_games_using_device() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+
+        assert_eq!(data_reader.get_games_using_device("device1")?, vec!["game1".to_string(), "game1a".to_string()]);
+        assert_eq!(data_reader.get_games_using_device("deviceref")?, vec!["device1".to_string()]);
+        assert!(data_reader.get_games_using_device("game1")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_games_using_samples() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+
+        assert_eq!(data_reader.get_games_using_samples("game1")?, vec!["game1".to_string(), "game1a".to_string()]);
+        assert!(data_reader.get_games_using_samples("game2")?.is_empty());
+        assert_eq!(data_reader.get_sample_sets()?, vec![("game1".to_string(), 2)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_families() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+
+        let families = data_reader.get_families()?;
+        assert_eq!(families.get("game1"), Some(&vec!["game1a".to_string()]));
+        assert!(!families.contains_key("game2"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_the_dat_header() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+
+        let dats = data_reader.get_dats()?;
+        assert_eq!(dats.len(), 1);
+        assert_eq!(dats[0].name, "MAME");
+        assert_eq!(dats[0].description.as_deref(), Some("MAME Mock"));
+        assert_eq!(dats[0].header.get("date").map(String::as_str), Some("28/10/2020"));
+        assert_eq!(dats[0].header.get("url").map(String::as_str), Some("http://www.example.com/dats/MAME/"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_the_import_source() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let mut conn = Connection::open_in_memory_with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+        let mut writer = DBWriter::from_connection(&mut conn, 5);
+        writer.set_import_source(ImportSource::new("dats/test.dat", Some("fb70f4a3468b66960f8a4e99509c383184d97a80".to_string())));
+        let mut importer = DatImporter::<BufReader<File>, DBWriter>::from_path(&path, writer)?;
+        importer.load_dat()?;
+        let data_reader = DBReader::from_connection(&conn);
+
+        let stats = data_reader.get_stats()?;
+        assert_eq!(stats.sources.len(), 1);
+        let source = stats.sources[0].source.as_ref().unwrap();
+        assert_eq!(source.path, "dats/test.dat");
+        assert_eq!(source.sha1.as_deref(), Some("fb70f4a3468b66960f8a4e99509c383184d97a80"));
+        assert_eq!(source.romst_version, env!("CARGO_PKG_VERSION"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn runs_select_queries() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+
+        let result = data_reader.query_rows("SELECT name, clone_of, info_year FROM games WHERE clone_of = ?1 ORDER BY name;", &["game1".to_string()])?;
+        assert_eq!(result.columns, vec!["name", "clone_of", "info_year"]);
+        assert_eq!(result.rows, vec![serde_json::json!({ "name": "game1a", "clone_of": "game1", "info_year": "1999" })]);
+        let result = data_reader.query_rows("with parents as (select name from games where clone_of is null) select count(*) as total from parents", &[])?;
+        assert!(result.rows[0]["total"].is_u64());
+
+        let games = data_reader.query_rows("SELECT count(*) AS total FROM games", &[])?;
+        assert!(data_reader.query_rows("DELETE FROM games;", &[]).is_err());
+        assert!(data_reader.query_rows("SELECT 1; DELETE FROM games", &[]).is_err());
+        assert!(data_reader.query_rows("SELECT * FROM not_a_table", &[]).is_err());
+        assert!(data_reader.query_rows("WITH old AS (SELECT name FROM games) DELETE FROM games WHERE name IN old", &[]).is_err());
+        assert!(data_reader.query_rows("BEGIN", &[]).is_err());
+        assert_eq!(data_reader.query_rows("SELECT count(*) AS total FROM games", &[])?.rows, games.rows);
+
+        // The semicolons inside literals and comments don't end the statement
+        let result = data_reader.query_rows("SELECT 'a;b' AS \"x;y\" -- the last one;\n; /* ; */", &[])?;
+        assert_eq!(result.rows, vec![serde_json::json!({ "x;y": "a;b" })]);
+        assert_eq!(count_statements("SELECT 'it''s; here'; SELECT 1"), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn iterates_games_in_batches() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+
+        let filter = GameFilter::new("*", PatternKind::Glob)?;
+        let all_games = data_reader.iter_games(&filter).collect::<Result<Vec<_>>>()?;
+        let total: u32 = conn.query_row("SELECT COUNT(*) FROM games;", params![], |row| row.get(0))?;
+        assert_eq!(all_games.len(), total as usize);
+        assert!(all_games.windows(2).all(|games| games[0].name < games[1].name));
+
+        // With batches smaller than the games in the database
+        let batched_games = GameIterator::new(&conn, &filter, 2).collect::<Result<Vec<_>>>()?;
+        assert_eq!(batched_games.iter().map(|game| &game.name).collect::<Vec<_>>(), all_games.iter().map(|game| &game.name).collect::<Vec<_>>());
+
+        let filter = GameFilter::new("game1*", PatternKind::Glob)?;
+        let games = data_reader.iter_games(&filter).map(|game| game.map(|game| game.name)).collect::<Result<Vec<_>>>()?;
+        assert_eq!(vec!["game1".to_string(), "game1a".to_string()], games);
+
+        Ok(())
+    }
+
+    #[test]
+    fn find_rom_locations_by_checksum() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+
+        let mut rom_info = DataFileInfo::new(FileType::Rom);
+        rom_info.crc = Some("1d460eee".to_string());
+
+        let non_merged = data_reader.find_rom_locations(&rom_info, RomsetMode::NonMerged)?;
+        let mut sets: Vec<&String> = non_merged.set_results.keys().collect();
+        sets.sort();
+        assert_eq!(vec!["game1", "game1a"], sets);
+        assert_eq!("rom1.trom", non_merged.set_results["game1"].get_roms_included()[0].file.name);
+
+        let split = data_reader.find_rom_locations(&rom_info, RomsetMode::Split)?;
+        assert_eq!(vec!["game1"], split.set_results.keys().collect::<Vec<_>>());
+
+        rom_info.crc = Some("00000000".to_string());
+        assert!(data_reader.find_rom_locations(&rom_info, RomsetMode::NonMerged)?.set_results.is_empty());
+
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,948 @@
+use std::{collections::{BTreeMap, HashMap, HashSet}, fmt::Display, fs::{self, File}, io::{self, BufReader, Read, Write}, path::{Component, Path, PathBuf}, str::FromStr};
+use anyhow::{Result, anyhow};
+use console::Style;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use zip::{CompressionMethod, ZipArchive, ZipWriter, write::FileOptions};
+
+use crate::{RomsetMode, error::RomstIOError, filesystem::{FileChecks, FileReader, ReadOptions, atomic::{self, AtomicFile}, rar, sevenz, torrentzip::{self, TorrentZipWriter}, volumes::{self, MultiVolumeReader}}, i18n::tr};
+use super::{models::{file::{DataFile, DataFileInfo}, set::SetLayout}, reader::DataReader};
+
+/// What happens with the roms taken from the source
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RebuildMode {
+    /// The source is left as it is
+    #[default]
+    Copy,
+    /// The roms written into the sets are removed from the source, and the files left empty deleted
+    Move,
+}
+
+impl Display for RebuildMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RebuildMode::Copy => write!(f, "{}", tr("Copy")),
+            RebuildMode::Move => write!(f, "{}", tr("Move")),
+        }
+    }
+}
+
+impl FromStr for RebuildMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "copy" => Ok(RebuildMode::Copy),
+            "move" => Ok(RebuildMode::Move),
+            _ => Err(anyhow!("Non valid rebuild mode, can be either `copy` or `move`"))
+        }
+    }
+}
+
+/// How the archives of the sets are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ZipFormat {
+    /// The roms taken from zip archives are copied as they are compressed
+    #[default]
+    Standard,
+    /// Every rom is compressed again in the TorrentZip format, so the same set gives the same archive
+    TorrentZip,
+}
+
+impl Display for ZipFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ZipFormat::Standard => write!(f, "{}", tr("Standard")),
+            ZipFormat::TorrentZip => write!(f, "TorrentZip"),
+        }
+    }
+}
+
+/// Whether a kind of roms is written into the sets
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RomPolicy {
+    /// Written, the sets without them are incomplete
+    #[default]
+    Always,
+    /// Written when found, the sets are complete without them
+    IfAvailable,
+    /// Never written
+    Never,
+}
+
+impl Display for RomPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RomPolicy::Always => write!(f, "{}", tr("Always")),
+            RomPolicy::IfAvailable => write!(f, "{}", tr("If available")),
+            RomPolicy::Never => write!(f, "{}", tr("Never")),
+        }
+    }
+}
+
+impl FromStr for RomPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "always" => Ok(RomPolicy::Always),
+            "if-available" => Ok(RomPolicy::IfAvailable),
+            "never" => Ok(RomPolicy::Never),
+            _ => Err(anyhow!("Non valid rom policy, can be either `always`, `if-available` or `never`"))
+        }
+    }
+}
+
+/// The policies of the roms the DATs flag, the rest of the roms are always written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RomPolicies {
+    /// The roms flagged as optional
+    pub optional: RomPolicy,
+    /// The roms of the BIOS options, the ones with a BIOS
+    pub bios: RomPolicy,
+}
+
+impl RomPolicies {
+    /// The policy of a rom, the BIOS one first
+    pub fn get_policy(&self, rom: &DataFile) -> RomPolicy {
+        if rom.bios.is_some() {
+            self.bios
+        } else if rom.optional {
+            self.optional
+        } else {
+            RomPolicy::Always
+        }
+    }
+}
+
+/// What happens with the source files a move deletes, and with the archives it rewrites without the roms moved
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeletePolicy {
+    /// They are deleted, the archives are rewritten in place
+    #[default]
+    Delete,
+    /// They are moved into this directory, the archives before being rewritten
+    TrashDir(PathBuf),
+    /// They are sent to the trash of the system, the archives before being rewritten
+    SystemTrash,
+}
+
+impl Display for DeletePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeletePolicy::Delete => write!(f, "{}", tr("Deleted")),
+            DeletePolicy::TrashDir(dir) => write!(f, "{}", dir.display()),
+            DeletePolicy::SystemTrash => write!(f, "{}", tr("Trash of the system")),
+        }
+    }
+}
+
+impl FromStr for DeletePolicy {
+    type Err = anyhow::Error;
+
+    /// `system` for the trash of the system, anything else is the directory to move them to
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "" => Err(anyhow!("Non valid trash, can be either a directory or `system`")),
+            "system" => Ok(DeletePolicy::SystemTrash),
+            dir => Ok(DeletePolicy::TrashDir(PathBuf::from(dir))),
+        }
+    }
+}
+
+/// Where a rom is read from, a file in an archive or a loose file
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RomSource {
+    pub path: PathBuf,
+    /// The name in the archive, none for a loose file
+    pub name: Option<String>,
+}
+
+impl Display for RomSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{} {} {}", name, tr("in"), self.path.display()),
+            None => write!(f, "{}", self.path.display()),
+        }
+    }
+}
+
+/// A set archive built from the source, with where each of its roms is taken from
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RebuildSet {
+    pub set_name: String,
+    /// The archive of the set, or its folder with the folder layout
+    pub archive: PathBuf,
+    /// The roms found, by the name they have in the set
+    pub roms: BTreeMap<String, RomSource>,
+    pub missing: Vec<String>,
+    /// Why the set couldn't be written, the set is skipped
+    pub error: Option<String>,
+    /// The bytes of the roms written into the archive, uncompressed
+    #[serde(default)]
+    pub bytes: u64,
+    /// The checksums of the roms in the database, by their name in the set, to verify them once written
+    #[serde(skip)]
+    pub checksums: BTreeMap<String, DataFileInfo>,
+    /// The roms written were read back and matched the database
+    #[serde(default)]
+    pub verified: bool,
+}
+
+impl Display for RebuildSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = if self.missing.is_empty() {
+            Style::new().green().apply_to(&self.set_name)
+        } else {
+            Style::new().yellow().apply_to(&self.set_name)
+        };
+        write!(f, "{}: {} {}", name, self.roms.len(), tr("roms"))?;
+        if !self.missing.is_empty() {
+            write!(f, ", {} {}", self.missing.len(), tr("missing"))?;
+        }
+        if let Some(error) = &self.error {
+            write!(f, " - {}: {}", Style::new().red().apply_to(tr("ERROR")), error)?;
+        }
+        Ok(())
+    }
+}
+
+/// The sets written from a source directory, and what was left of it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RebuildReport {
+    pub date_time: String,
+    pub source: String,
+    pub destination: String,
+    pub rom_mode: RomsetMode,
+    pub mode: RebuildMode,
+    #[serde(default)]
+    pub layout: SetLayout,
+    #[serde(default)]
+    pub zip_format: ZipFormat,
+    /// Nothing was written, the report is what would be done
+    pub dry_run: bool,
+    /// The roms written were read back and hashed before removing any source
+    #[serde(default)]
+    pub verify: bool,
+    #[serde(default)]
+    pub rom_policies: RomPolicies,
+    #[serde(default)]
+    pub delete_policy: DeletePolicy,
+    pub sets: Vec<RebuildSet>,
+    /// The files not matching any rom, they are never moved
+    pub unknowns: Vec<String>,
+    /// The files removed after moving all their roms, deleted or sent to the trash of the delete policy
+    pub removed: Vec<String>,
+}
+
+impl Display for RebuildReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for set in &self.sets {
+            writeln!(f, "{}", set)?;
+        }
+        if !self.unknowns.is_empty() {
+            writeln!(f, "{}:", Style::new().red().apply_to(tr("Unknown files")))?;
+            for unknown in &self.unknowns {
+                writeln!(f, "  - {}", unknown)?;
+            }
+        }
+        let complete = self.sets.iter().filter(|set| set.missing.is_empty() && set.error.is_none()).count();
+        let failed = self.sets.iter().filter(|set| set.error.is_some()).count();
+        let built = if self.dry_run { tr("Sets that would be built") } else { tr("Sets built") };
+        writeln!(f, "{}: {} ({}, {}), {}: {}, {}: {}, {}: {}",
+            built, self.sets.len() - failed, self.rom_mode, self.layout, tr("Complete"), complete, tr("Failed"), failed,
+            tr("Unknown files"), self.unknowns.len())?;
+        if !self.dry_run {
+            writeln!(f, "{}: {}", tr("Bytes of roms written"), self.get_bytes())?;
+        }
+        if self.rom_policies != RomPolicies::default() {
+            writeln!(f, "{}: {}, {}: {}", tr("Optional roms"), self.rom_policies.optional, tr("BIOS roms"), self.rom_policies.bios)?;
+        }
+        if self.verify && !self.dry_run {
+            writeln!(f, "{}: {}", tr("Sets verified"), self.sets.iter().filter(|set| set.verified).count())?;
+        }
+        if !self.removed.is_empty() {
+            writeln!(f, "{}: {}", tr("Files left empty removed"), self.removed.len())?;
+        }
+        if self.mode == RebuildMode::Move && self.delete_policy != DeletePolicy::Delete {
+            writeln!(f, "{}: {}", tr("Files removed sent to"), self.delete_policy)?;
+        }
+        Ok(())
+    }
+}
+
+impl RebuildReport {
+    /// The bytes of the roms written into all the sets
+    pub fn get_bytes(&self) -> u64 {
+        self.sets.iter().map(|set| set.bytes).sum()
+    }
+}
+
+/// Builds the sets of the database out of loose files and archives, named and with the roms of the romset mode
+pub struct Rebuilder<R: DataReader> {
+    data_reader: R,
+    rom_mode: RomsetMode,
+    layout: SetLayout,
+    read_options: ReadOptions,
+    rom_policies: RomPolicies,
+    allowed_sets: Option<HashSet<String>>,
+}
+
+impl<R: DataReader> Rebuilder<R> {
+    pub fn new(data_reader: R, rom_mode: RomsetMode) -> Self {
+        Self { data_reader, rom_mode, layout: SetLayout::default(), read_options: ReadOptions::default(), rom_policies: RomPolicies::default(), allowed_sets: None }
+    }
+
+    /// With the folder layout each set is written as a folder with the roms unzipped
+    pub fn set_layout(&mut self, layout: SetLayout) {
+        self.layout = layout;
+    }
+
+    pub fn set_read_options(&mut self, read_options: ReadOptions) {
+        self.read_options = read_options;
+    }
+
+    /// Which of the optional and BIOS roms are written, and which are missing when not found
+    pub fn set_rom_policies(&mut self, rom_policies: RomPolicies) {
+        self.rom_policies = rom_policies;
+    }
+
+    /// Only these sets are built, like the tagged ones
+    pub fn set_allowed_sets(&mut self, allowed_sets: HashSet<String>) {
+        self.allowed_sets = Some(allowed_sets);
+    }
+
+    fn is_allowed(&self, set_name: &str) -> bool {
+        self.allowed_sets.as_ref().is_none_or(|allowed_sets| allowed_sets.contains(set_name))
+    }
+
+    /// Finds the sets the files of the source can build in the destination, without writing anything. The files are
+    /// hashed only with the checksums the database has, like in the reports, and the excluded sets are never built.
+    /// Returns the sets, with at least a rom found, and the files not matching any rom
+    pub fn plan(&self, source: &Path, destination: &Path) -> Result<(Vec<RebuildSet>, Vec<String>)> {
+        let mut file_reader = FileReader::with_options(self.read_options.clone());
+        let file_checks = self.data_reader.get_file_checks()?.get_file_checks();
+        let mut found: BTreeMap<String, HashMap<u32, RomSource>> = BTreeMap::new();
+        let mut unknowns = vec![];
+        for path in get_source_files(source)? {
+            let (roms, in_archive) = match file_reader.build_game_set(&path, file_checks) {
+                Ok(game_set) => (game_set.roms, true),
+                Err(RomstIOError::NotValidFileError(_, _)) => {
+                    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+                    (vec![DataFile::new(name, file_reader.get_file_info_with_checks(&path, file_checks)?)], false)
+                }
+                Err(e) => {
+                    warn!("Skipping `{}`: {}", path.display(), e);
+                    unknowns.push(path.to_string_lossy().to_string());
+                    continue;
+                }
+            };
+
+            let rom_search = self.data_reader.get_romsets_from_roms(roms, self.rom_mode)?;
+            for unknown in &rom_search.unknowns {
+                let source = RomSource { path: path.clone(), name: if in_archive { Some(unknown.name.clone()) } else { None } };
+                unknowns.push(source.to_string());
+            }
+            for (set_name, set_content) in &rom_search.set_results {
+                let set_roms = found.entry(set_name.clone()).or_default();
+                for rom in set_content.get_roms_included() {
+                    set_roms.entry(rom.id).or_insert_with(|| RomSource {
+                        path: path.clone(),
+                        name: if in_archive { Some(rom.file.name.clone()) } else { None },
+                    });
+                }
+            }
+        }
+
+        let exclusions = self.data_reader.get_exclusions()?;
+        let mut sets = vec![];
+        for (set_name, sources) in found.into_iter().filter(|(set_name, _)| !exclusions.contains(set_name) && self.is_allowed(set_name)) {
+            let (_, db_roms) = self.data_reader.get_romset_roms(&set_name, self.rom_mode)?;
+            let mut roms = BTreeMap::new();
+            let mut checksums = BTreeMap::new();
+            let mut missing = vec![];
+            for db_rom in db_roms {
+                let policy = self.rom_policies.get_policy(&db_rom.file);
+                if policy == RomPolicy::Never || db_rom.file.status.as_ref().is_some_and(|status| status.to_lowercase() == "nodump") {
+                    continue;
+                }
+                match sources.get(&db_rom.id) {
+                    Some(source) => {
+                        checksums.insert(db_rom.file.name.clone(), db_rom.file.info);
+                        roms.insert(db_rom.file.name, source.clone());
+                    }
+                    None if policy == RomPolicy::Always => missing.push(db_rom.file.name),
+                    None => {}
+                }
+            }
+            // Only roms never written were found
+            if roms.is_empty() {
+                continue;
+            }
+            missing.sort();
+            let error = if set_name.contains(['/', '\\']) || set_name == ".." {
+                Some(format!("`{}` can't be a file name", set_name))
+            } else {
+                None
+            };
+            let archive = match self.layout {
+                SetLayout::Archive => destination.join(format!("{}.zip", set_name)),
+                SetLayout::Folder => destination.join(&set_name),
+            };
+            sets.push(RebuildSet { archive, set_name, roms, missing, error, bytes: 0, checksums, verified: false });
+        }
+
+        Ok((sets, unknowns))
+    }
+}
+
+/// Writes the sets planned, the ones that fail get the error. Moving the roms removes them from the sources once
+/// every set using them is written. The passwords of the read options are tried with the encrypted 7z and RAR
+/// sources. With `verify`, the roms of each set are read back and hashed once written, and a set with any not
+/// matching the database fails, so its sources are kept. The files removed, and the archives rewritten without the
+/// roms moved, go where the delete policy says. Returns the files removed, as they were left empty
+pub fn rebuild(sets: &mut [RebuildSet], mode: RebuildMode, layout: SetLayout, zip_format: ZipFormat, read_options: &ReadOptions, verify: bool, delete_policy: &DeletePolicy) -> Vec<String> {
+    let passwords = &read_options.passwords;
+    for set in sets.iter_mut().filter(|set| set.error.is_none()) {
+        let result = match layout {
+            SetLayout::Archive => write_set(set, zip_format, passwords),
+            SetLayout::Folder => write_set_folder(set, passwords),
+        };
+        let result = match result {
+            Ok(bytes) if verify => verify_set(set, layout, read_options).map(|_| bytes),
+            result => result,
+        };
+        match result {
+            Ok(bytes) => {
+                set.bytes = bytes;
+                set.verified = verify;
+            }
+            Err(e) => set.error = Some(e.to_string()),
+        }
+    }
+    if mode == RebuildMode::Copy {
+        return vec![];
+    }
+
+    let failed = sets.iter()
+        .filter(|set| set.error.is_some())
+        .flat_map(|set| set.roms.values().map(|source| source.path.clone()))
+        .collect::<HashSet<_>>();
+    let mut moved: BTreeMap<PathBuf, HashSet<String>> = BTreeMap::new();
+    for set in sets.iter().filter(|set| set.error.is_none()) {
+        for source in set.roms.values() {
+            let names = moved.entry(source.path.clone()).or_default();
+            if let Some(name) = &source.name {
+                names.insert(name.clone());
+            }
+        }
+    }
+
+    let mut removed = vec![];
+    // Split archives are only copied from, their volumes are left as they are
+    for (path, names) in moved.into_iter().filter(|(path, _)| !failed.contains(path) && volumes::get_volume_number(path).is_none()) {
+        let result = if names.is_empty() {
+            discard(&path, delete_policy).map(|_| true)
+        } else {
+            remove_from_archive(&path, &names, passwords, delete_policy)
+        };
+        match result {
+            Ok(true) => removed.push(path.to_string_lossy().to_string()),
+            Ok(false) => {}
+            Err(e) => warn!("Couldn't remove the roms moved from `{}`: {}", path.display(), e),
+        }
+    }
+    removed
+}
+
+/// Reads back the roms of a set written and hashes them again, failing if any doesn't match the database. The roms
+/// without a header in the database are compared without the one found by the skippers
+fn verify_set(set: &RebuildSet, layout: SetLayout, read_options: &ReadOptions) -> Result<()> {
+    let mut file_reader = FileReader::with_options(read_options.clone());
+    let written = match layout {
+        SetLayout::Archive => file_reader.build_game_set(&set.archive, FileChecks::ALL)?,
+        SetLayout::Folder => file_reader.build_game_set_from_dir(&set.archive, FileChecks::ALL)?,
+    };
+    for (name, expected) in &set.checksums {
+        let rom = written.roms.iter().find(|rom| &rom.name == name)
+            .ok_or_else(|| anyhow!("`{}` is not in the set after writing it", name))?;
+        let matches = |info: &DataFileInfo| info.deep_compare(expected, FileChecks::ALL).unwrap_or(false);
+        if !matches(&rom.info) && !rom.headerless.as_ref().is_some_and(matches) {
+            return Err(anyhow!("`{}` doesn't match the database after writing it", name));
+        }
+    }
+    Ok(())
+}
+
+/// The files in the source, in its subdirectories too, without the rest of the volumes of split archives
+fn get_source_files(source: &Path) -> Result<Vec<PathBuf>> {
+    if source.is_file() {
+        return Ok(vec![source.to_path_buf()]);
+    }
+    let mut files = vec![];
+    let mut pending_dirs = vec![source.to_path_buf()];
+    while let Some(current_dir) = pending_dirs.pop() {
+        for entry in current_dir.read_dir()? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending_dirs.push(path);
+            } else if !volumes::is_secondary_volume(&path) && !atomic::is_temp_file(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Writes the archive of the set in a temporary file first, keeping the files it already had that are not replaced.
+/// Returns the bytes of the roms written
+fn write_set(set: &RebuildSet, zip_format: ZipFormat, passwords: &[String]) -> Result<u64> {
+    let mut file = AtomicFile::create(&set.archive)?;
+    let bytes = match zip_format {
+        ZipFormat::Standard => write_set_archive(set, &mut file, passwords)?,
+        ZipFormat::TorrentZip => write_set_torrentzip(set, &mut file, passwords)?,
+    };
+    file.commit()?;
+    Ok(bytes)
+}
+
+fn write_set_archive(set: &RebuildSet, file: &mut AtomicFile, passwords: &[String]) -> Result<u64> {
+    let mut writer = ZipWriter::new(file);
+    if set.archive.exists() {
+        let mut existing = ZipArchive::new(BufReader::new(File::open(&set.archive)?))?;
+        for i in 0..existing.len() {
+            let file = existing.by_index(i)?;
+            if !set.roms.contains_key(file.name()) {
+                writer.raw_copy_file(file)?;
+            }
+        }
+    }
+
+    let decompressed_files = get_decompressed_files(set, passwords)?;
+    let mut archives: HashMap<&Path, ZipArchive<BufReader<MultiVolumeReader>>> = HashMap::new();
+    let mut bytes = 0;
+    for (name, source) in &set.roms {
+        match &source.name {
+            Some(source_name) if decompressed_files.contains_key(source.path.as_path()) => {
+                let data = decompressed_files[source.path.as_path()].get(source_name)
+                    .ok_or_else(|| anyhow!("`{}` not found", source))?;
+                writer.start_file(name, FileOptions::default().compression_method(CompressionMethod::Deflated))?;
+                writer.write_all(data)?;
+                bytes += data.len() as u64;
+            }
+            Some(source_name) => {
+                if !archives.contains_key(source.path.as_path()) {
+                    let archive = ZipArchive::new(BufReader::new(MultiVolumeReader::open(&source.path)?))?;
+                    archives.insert(&source.path, archive);
+                }
+                let archive = archives.get_mut(source.path.as_path()).unwrap();
+                // Copied without decompressing it, the crc is kept as it is
+                let file = archive.by_name(source_name)?;
+                bytes += file.size();
+                writer.raw_copy_file_rename(file, name)?;
+            }
+            None => {
+                writer.start_file(name, FileOptions::default().compression_method(CompressionMethod::Deflated))?;
+                bytes += io::copy(&mut File::open(&source.path)?, &mut writer)?;
+            }
+        }
+    }
+    writer.finish()?;
+
+    Ok(bytes)
+}
+
+/// Writes the archive in the TorrentZip format, the files it already had that are not replaced are compressed again
+/// too. Every file is read whole, as they have to be compressed again in the order of their names
+fn write_set_torrentzip(set: &RebuildSet, file: &mut AtomicFile, passwords: &[String]) -> Result<u64> {
+    let mut existing = if set.archive.exists() {
+        Some(ZipArchive::new(BufReader::new(File::open(&set.archive)?))?)
+    } else {
+        None
+    };
+    let mut names = set.roms.keys().cloned().collect::<Vec<_>>();
+    if let Some(existing) = &existing {
+        names.extend(existing.file_names().filter(|name| !name.ends_with('/') && !set.roms.contains_key(*name)).map(|name| name.to_string()));
+    }
+    names.sort_by(|a, b| torrentzip::compare_names(a, b));
+
+    let decompressed_files = get_decompressed_files(set, passwords)?;
+    let mut archives: HashMap<&Path, ZipArchive<BufReader<MultiVolumeReader>>> = HashMap::new();
+    let mut writer = TorrentZipWriter::new(file);
+    let mut bytes = 0;
+    for name in &names {
+        let mut data = vec![];
+        match (set.roms.get(name), &mut existing) {
+            (Some(source), _) => {
+                match &source.name {
+                    Some(source_name) if decompressed_files.contains_key(source.path.as_path()) => {
+                        data = decompressed_files[source.path.as_path()].get(source_name)
+                            .ok_or_else(|| anyhow!("`{}` not found", source))?.clone();
+                    }
+                    Some(source_name) => {
+                        if !archives.contains_key(source.path.as_path()) {
+                            let archive = ZipArchive::new(BufReader::new(MultiVolumeReader::open(&source.path)?))?;
+                            archives.insert(&source.path, archive);
+                        }
+                        let archive = archives.get_mut(source.path.as_path()).unwrap();
+                        archive.by_name(source_name)?.read_to_end(&mut data)?;
+                    }
+                    None => {
+                        File::open(&source.path)?.read_to_end(&mut data)?;
+                    }
+                }
+                bytes += data.len() as u64;
+            }
+            (None, Some(existing)) => {
+                existing.by_name(name)?.read_to_end(&mut data)?;
+            }
+            (None, None) => continue,
+        }
+        writer.add_file(name, &data)?;
+    }
+    writer.finish()?;
+
+    Ok(bytes)
+}
+
+/// Writes the roms of the set unzipped in its folder, each one in a temporary file first. The files already in the
+/// folder that are not replaced are kept. Returns the bytes of the roms written
+fn write_set_folder(set: &RebuildSet, passwords: &[String]) -> Result<u64> {
+    let decompressed_files = get_decompressed_files(set, passwords)?;
+    let mut archives: HashMap<&Path, ZipArchive<BufReader<MultiVolumeReader>>> = HashMap::new();
+    let mut bytes = 0;
+    for (name, source) in &set.roms {
+        // The roms in subdirectories are written in the same subdirectories of the folder, never out of it
+        if !Path::new(name).components().all(|component| matches!(component, Component::Normal(_))) {
+            return Err(anyhow!("`{}` can't be a file name in the folder of the set", name));
+        }
+        let path = set.archive.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = AtomicFile::create(&path)?;
+        match &source.name {
+            Some(source_name) if decompressed_files.contains_key(source.path.as_path()) => {
+                let data = decompressed_files[source.path.as_path()].get(source_name)
+                    .ok_or_else(|| anyhow!("`{}` not found", source))?;
+                file.write_all(data)?;
+                bytes += data.len() as u64;
+            }
+            Some(source_name) => {
+                if !archives.contains_key(source.path.as_path()) {
+                    let archive = ZipArchive::new(BufReader::new(MultiVolumeReader::open(&source.path)?))?;
+                    archives.insert(&source.path, archive);
+                }
+                let archive = archives.get_mut(source.path.as_path()).unwrap();
+                bytes += io::copy(&mut archive.by_name(source_name)?, &mut file)?;
+            }
+            None => {
+                bytes += io::copy(&mut File::open(&source.path)?, &mut file)?;
+            }
+        }
+        file.commit()?;
+    }
+
+    Ok(bytes)
+}
+
+/// The files in 7z and RAR archives can't be copied as they are, they are decompressed first
+fn get_decompressed_files<'a>(set: &'a RebuildSet, passwords: &[String]) -> Result<HashMap<&'a Path, HashMap<String, Vec<u8>>>> {
+    let mut decompressed_files: HashMap<&Path, HashMap<String, Vec<u8>>> = HashMap::new();
+    for source in set.roms.values().filter(|source| source.name.is_some()) {
+        if decompressed_files.contains_key(source.path.as_path()) {
+            continue;
+        }
+        if sevenz::is_7z_file(&source.path) {
+            decompressed_files.insert(&source.path, read_7z_files(set, &source.path, passwords)?);
+        } else if rar::is_rar_file(&source.path) {
+            decompressed_files.insert(&source.path, read_rar_files(set, &source.path, passwords)?);
+        }
+    }
+    Ok(decompressed_files)
+}
+
+/// The files of a 7z archive taken by the set
+fn read_7z_files(set: &RebuildSet, path: &Path, passwords: &[String]) -> Result<HashMap<String, Vec<u8>>> {
+    let names = set.roms.values()
+        .filter(|source| source.path == path)
+        .filter_map(|source| source.name.as_ref())
+        .collect::<HashSet<_>>();
+    let mut files = HashMap::new();
+    sevenz::for_each_file(&path, passwords, |entry, data| {
+        let name = entry.name().trim().to_string();
+        let mut buffer = vec![];
+        data.read_to_end(&mut buffer)?;
+        if names.contains(&name) {
+            files.insert(name, buffer);
+        }
+        Ok(())
+    })?;
+    Ok(files)
+}
+
+/// The files of a RAR archive taken by the set
+fn read_rar_files(set: &RebuildSet, path: &Path, passwords: &[String]) -> Result<HashMap<String, Vec<u8>>> {
+    let names = set.roms.values()
+        .filter(|source| source.path == path)
+        .filter_map(|source| source.name.as_ref())
+        .collect::<HashSet<_>>();
+    let mut files = HashMap::new();
+    for entry in rar::get_entries(&path)?.entries.into_iter().filter(|entry| names.contains(&entry.name)) {
+        let data = rar::read_file(&path, &entry, passwords)?
+            .ok_or_else(|| anyhow!("`{}` in {} is encrypted", entry.name, path.to_string_lossy()))?;
+        files.insert(entry.name, data);
+    }
+    Ok(files)
+}
+
+/// Deletes a file of the source, or moves it to the trash of the delete policy. A file already in the trash directory
+/// with the same name is kept, the new one gets a number
+fn discard(path: &Path, delete_policy: &DeletePolicy) -> Result<()> {
+    match delete_policy {
+        DeletePolicy::Delete => fs::remove_file(path)?,
+        DeletePolicy::TrashDir(dir) => {
+            fs::create_dir_all(dir)?;
+            let stem = path.file_stem().ok_or_else(|| anyhow!("`{}` is not a file", path.display()))?.to_string_lossy();
+            let extension = path.extension().map(|extension| format!(".{}", extension.to_string_lossy())).unwrap_or_default();
+            let mut target = dir.join(format!("{}{}", stem, extension));
+            let mut number = 1;
+            while target.exists() {
+                target = dir.join(format!("{} ({}){}", stem, number, extension));
+                number += 1;
+            }
+            // The trash can be in another file system, where the file can't be renamed into
+            if fs::rename(path, &target).is_err() {
+                fs::copy(path, &target)?;
+                fs::remove_file(path)?;
+            }
+        }
+        DeletePolicy::SystemTrash => trash::delete(path).map_err(|e| anyhow!("Couldn't send `{}` to the trash: {}", path.display(), e))?,
+    }
+    Ok(())
+}
+
+/// Rewrites the archive without the files moved, or deletes it if there is nothing else. Returns if it was deleted.
+/// 7z and RAR archives are only deleted, they are left as they are while they have other files. Unless the policy
+/// deletes them, the archives rewritten are sent to the trash as they were, so the roms moved can be recovered
+fn remove_from_archive(path: &Path, names: &HashSet<String>, passwords: &[String], delete_policy: &DeletePolicy) -> Result<bool> {
+    let names_in_archive = if sevenz::is_7z_file(&path) {
+        Some(sevenz::get_file_names(&path, passwords)?)
+    } else if rar::is_rar_file(&path) {
+        Some(rar::get_entries(&path)?.entries.into_iter().map(|entry| entry.name).collect())
+    } else {
+        None
+    };
+    if let Some(names_in_archive) = names_in_archive {
+        if names_in_archive.iter().all(|name| names.contains(name)) {
+            discard(path, delete_policy)?;
+            return Ok(true);
+        }
+        return Ok(false);
+    }
+
+    let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+    if archive.file_names().all(|name| names.contains(name)) {
+        drop(archive);
+        discard(path, delete_policy)?;
+        return Ok(true);
+    }
+
+    let mut file = AtomicFile::create(&path)?;
+    let mut writer = ZipWriter::new(&mut file);
+    for i in 0..archive.len() {
+        let archive_file = archive.by_index(i)?;
+        if !names.contains(archive_file.name()) {
+            writer.raw_copy_file(archive_file)?;
+        }
+    }
+    writer.finish()?;
+    drop(writer);
+    drop(archive);
+    if *delete_policy != DeletePolicy::Delete {
+        discard(path, delete_policy)?;
+    }
+    file.commit()?;
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+    use rusqlite::{Connection, OpenFlags};
+    use crate::data::{importer::DatImporter, models::file::FileType, reader::sqlite::DBReader, writer::sqlite::DBWriter};
+    use super::*;
+
+    #[test]
+    fn writes_the_optional_and_bios_roms_by_their_policy() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("romst_rebuild_policies_{}", std::process::id()));
+        let source = dir.join("source");
+        fs::create_dir_all(&source)?;
+        let dat_path = dir.join("policies.dat");
+        fs::write(&dat_path, r#"<?xml version="1.0"?>
+<datafile>
+    <header><name>Policies</name></header>
+    <machine name="game">
+        <rom name="main.bin" size="4" crc="bf28cd64"/>
+        <rom name="extra.bin" size="4" crc="fb8bdd59" optional="yes"/>
+        <rom name="bios.bin" bios="usa" size="4" crc="dc0447d5"/>
+    </machine>
+</datafile>"#)?;
+        fs::write(source.join("main.bin"), "main")?;
+        fs::write(source.join("bios.bin"), "bios")?;
+        let mut conn = Connection::open_in_memory_with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+        DatImporter::<BufReader<File>, DBWriter>::from_path(&dat_path, DBWriter::from_connection(&mut conn, 5))?.load_dat()?;
+
+        let mut rebuilder = Rebuilder::new(DBReader::from_connection(&conn), RomsetMode::NonMerged);
+        let (sets, _) = rebuilder.plan(&source, &dir.join("sets"))?;
+        assert_eq!(sets[0].roms.keys().collect::<Vec<_>>(), vec!["bios.bin", "main.bin"]);
+        assert_eq!(sets[0].missing, vec!["extra.bin".to_string()]);
+
+        rebuilder.set_rom_policies(RomPolicies { optional: RomPolicy::IfAvailable, bios: RomPolicy::Never });
+        let (sets, _) = rebuilder.plan(&source, &dir.join("sets"))?;
+        assert_eq!(sets[0].roms.keys().collect::<Vec<_>>(), vec!["main.bin"]);
+        assert!(sets[0].missing.is_empty());
+
+        // The optional rom is taken once it's found, and the BIOS is never written
+        fs::write(source.join("extra.bin"), "xtra")?;
+        let (mut sets, _) = rebuilder.plan(&source, &dir.join("sets"))?;
+        assert_eq!(sets[0].roms.keys().collect::<Vec<_>>(), vec!["extra.bin", "main.bin"]);
+        fs::create_dir_all(dir.join("sets"))?;
+        rebuild(&mut sets, RebuildMode::Copy, SetLayout::Archive, ZipFormat::Standard, &ReadOptions::default(), true, &DeletePolicy::Delete);
+        assert_eq!(sets[0].error, None);
+        assert!(sets[0].verified);
+        let archive = ZipArchive::new(File::open(dir.join("sets").join("game.zip"))?)?;
+        let mut names = archive.file_names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["extra.bin", "main.bin"]);
+
+        // Without any rom always written, a set with only the optional roms is not built
+        rebuilder.set_rom_policies(RomPolicies { optional: RomPolicy::Never, bios: RomPolicy::Never });
+        fs::remove_file(source.join("main.bin"))?;
+        let (sets, _) = rebuilder.plan(&source, &dir.join("sets"))?;
+        assert!(sets.is_empty());
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_the_sources_of_the_sets_not_verified() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("romst_rebuild_verify_{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let source = dir.join("loose.bin");
+        fs::write(&source, "pacman")?;
+        let mut checksums = BTreeMap::new();
+        let mut expected = DataFileInfo::new(FileType::Rom);
+        expected.sha1 = Some("0000000000000000000000000000000000000000".to_string());
+        checksums.insert("pacman.bin".to_string(), expected.clone());
+        let roms = vec![("pacman.bin".to_string(), RomSource { path: source.clone(), name: None })].into_iter().collect::<BTreeMap<_, _>>();
+        let mut sets = vec![RebuildSet { set_name: "pacman".to_string(), archive: dir.join("pacman.zip"), roms: roms.clone(), missing: vec![],
+            error: None, bytes: 0, checksums, verified: false }];
+
+        let removed = rebuild(&mut sets, RebuildMode::Move, SetLayout::Archive, ZipFormat::Standard, &ReadOptions::default(), true, &DeletePolicy::Delete);
+        assert!(removed.is_empty());
+        assert!(source.exists());
+        assert!(!sets[0].verified);
+        assert!(sets[0].error.as_deref().is_some_and(|error| error.contains("doesn't match the database")));
+
+        // The sha1 of `pacman`
+        expected.sha1 = Some("9cca19308f78053388005d92881437bb0c47b45a".to_string());
+        let checksums = vec![("pacman.bin".to_string(), expected)].into_iter().collect();
+        let mut sets = vec![RebuildSet { set_name: "pacman".to_string(), archive: dir.join("pacman.zip"), roms, missing: vec![],
+            error: None, bytes: 0, checksums, verified: false }];
+        let removed = rebuild(&mut sets, RebuildMode::Move, SetLayout::Archive, ZipFormat::Standard, &ReadOptions::default(), true, &DeletePolicy::Delete);
+        assert!(sets[0].verified);
+        assert_eq!(removed, vec![source.to_string_lossy().to_string()]);
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn sends_the_files_removed_to_the_trash_dir() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("romst_rebuild_trash_{}", std::process::id()));
+        let trash = dir.join("trash");
+        fs::create_dir_all(&trash)?;
+        let loose = dir.join("pacman.bin");
+        fs::write(&loose, "pacman")?;
+        fs::write(trash.join("pacman.bin"), "older")?;
+        let zipped = dir.join("source.zip");
+        let mut writer = ZipWriter::new(File::create(&zipped)?);
+        for (name, data) in [("galaxian.bin", "galaxian"), ("other.bin", "other")] {
+            writer.start_file(name, FileOptions::default())?;
+            writer.write_all(data.as_bytes())?;
+        }
+        writer.finish()?;
+
+        let roms = vec![
+            ("pacman.bin".to_string(), RomSource { path: loose.clone(), name: None }),
+            ("galaxian.bin".to_string(), RomSource { path: zipped.clone(), name: Some("galaxian.bin".to_string()) }),
+        ].into_iter().collect();
+        let mut sets = vec![RebuildSet { set_name: "mixed".to_string(), archive: dir.join("mixed.zip"), roms, missing: vec![],
+            error: None, bytes: 0, checksums: BTreeMap::new(), verified: false }];
+        let removed = rebuild(&mut sets, RebuildMode::Move, SetLayout::Archive, ZipFormat::Standard, &ReadOptions::default(), false,
+            &DeletePolicy::TrashDir(trash.clone()));
+        assert_eq!(sets[0].error, None);
+        assert_eq!(removed, vec![loose.to_string_lossy().to_string()]);
+
+        // The loose file is moved next to the one already there, and the archive is kept as it was before rewriting it
+        assert!(!loose.exists());
+        assert_eq!(fs::read_to_string(trash.join("pacman.bin"))?, "older");
+        assert_eq!(fs::read_to_string(trash.join("pacman (1).bin"))?, "pacman");
+        let trashed = ZipArchive::new(File::open(trash.join("source.zip"))?)?;
+        assert_eq!(trashed.len(), 2);
+        let rewritten = ZipArchive::new(File::open(&zipped)?)?;
+        assert_eq!(rewritten.file_names().collect::<Vec<_>>(), vec!["other.bin"]);
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn reports_the_bytes_written_in_each_set() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("romst_rebuild_bytes_{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let loose = dir.join("loose.bin");
+        fs::write(&loose, "pacman")?;
+        let zipped = dir.join("source.zip");
+        let mut writer = ZipWriter::new(File::create(&zipped)?);
+        writer.start_file("galaxian.bin", FileOptions::default())?;
+        writer.write_all(b"galaxian")?;
+        writer.finish()?;
+
+        let get_set = |set_name: &str, roms: Vec<(&str, RomSource)>| RebuildSet {
+            set_name: set_name.to_string(), archive: dir.join(format!("{}.zip", set_name)), missing: vec![], error: None, bytes: 0,
+            roms: roms.into_iter().map(|(name, source)| (name.to_string(), source)).collect(), checksums: BTreeMap::new(), verified: false,
+        };
+        // A loose file and a file copied from an archive, and a set whose source is gone
+        let mut sets = vec![
+            get_set("mixed", vec![
+                ("pacman.bin", RomSource { path: loose.clone(), name: None }),
+                ("galaxian.bin", RomSource { path: zipped.clone(), name: Some("galaxian.bin".to_string()) }),
+            ]),
+            get_set("gone", vec![("gone.bin", RomSource { path: dir.join("gone.bin"), name: None })]),
+        ];
+        rebuild(&mut sets, RebuildMode::Copy, SetLayout::Archive, ZipFormat::Standard, &ReadOptions::default(), false, &DeletePolicy::Delete);
+        assert_eq!(sets[0].bytes, 14);
+        assert!(sets[1].error.is_some());
+        assert_eq!(sets[1].bytes, 0);
+
+        let report = RebuildReport {
+            date_time: "2024-05-01T00:00:00+00:00".to_string(), source: dir.to_string_lossy().to_string(), destination: dir.to_string_lossy().to_string(),
+            rom_mode: RomsetMode::NonMerged, mode: RebuildMode::Copy, layout: SetLayout::Archive, zip_format: ZipFormat::Standard, dry_run: false,
+            verify: false, rom_policies: RomPolicies::default(), delete_policy: DeletePolicy::Delete, sets, unknowns: vec![], removed: vec![],
+        };
+        assert_eq!(report.get_bytes(), 14);
+        assert!(report.to_string().contains("Bytes of roms written: 14"));
+        let saved: serde_json::Value = serde_json::from_str(&serde_json::to_string(&report)?)?;
+        assert_eq!(saved["date_time"], "2024-05-01T00:00:00+00:00");
+        assert_eq!(saved["sets"][0]["bytes"], 14);
+        assert!(saved["sets"][1]["error"].is_string());
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use std::io::Write;
+use anyhow::Result;
+
+use crate::data::models::file::DataFile;
+use super::{disk_report::DiskStatus, scan_report::{Confidence, RomLocatedAt, ScanReport, SetReport, SetStatus}};
+
+const HEADERS: [&str; 10] = ["set", "set_status", "set_confidence", "file", "type", "status", "size", "crc", "sha1", "location"];
+
+/// Writes the report as CSV, a row per file of each set with its status, to filter the results in a spreadsheet.
+/// The statuses are not translated so the rows can be filtered the same in any language. Returns the rows written
+pub fn write_csv<W: Write>(report: &ScanReport, mut writer: W) -> Result<usize> {
+    write_row(&mut writer, &HEADERS)?;
+
+    let mut set_names = report.sets.keys().collect::<Vec<_>>();
+    set_names.sort();
+    let mut rows = 0;
+    for set_name in set_names {
+        let set_report = &report.sets[set_name];
+        let set_status = get_status_name(&set_report.is_complete());
+        let set_confidence = set_report.confidence.as_ref().map(get_confidence_name).unwrap_or_default();
+        for row in get_set_rows(set_report) {
+            write_row(&mut writer, &[set_name, set_status, set_confidence, &row.file, row.file_type, &row.status, &row.size, &row.crc, &row.sha1, &row.location])?;
+            rows += 1;
+        }
+    }
+    writer.flush()?;
+
+    Ok(rows)
+}
+
+struct CsvRow {
+    file: String,
+    file_type: &'static str,
+    status: String,
+    size: String,
+    crc: String,
+    sha1: String,
+    location: String,
+}
+
+impl CsvRow {
+    fn from_rom<S>(rom: &DataFile, status: &str, location: S) -> Self where S: Into<String> {
+        Self {
+            file: rom.name.to_owned(),
+            file_type: "rom",
+            status: status.to_string(),
+            size: rom.info.size.map(|size| size.to_string()).unwrap_or_default(),
+            crc: rom.info.crc.clone().unwrap_or_default(),
+            sha1: rom.info.sha1.clone().unwrap_or_default(),
+            location: location.into(),
+        }
+    }
+}
+
+/// The roms sorted by name in each status, then the disks
+fn get_set_rows(set_report: &SetReport) -> Vec<CsvRow> {
+    let set_file = set_report.file_name.clone().unwrap_or_default();
+    let mut available = set_report.roms_available.iter().collect::<Vec<_>>();
+    available.sort_by(|a, b| a.0.cmp(b.0));
+    let mut rows = available.into_iter().map(|(rom, located_at)| match located_at {
+        RomLocatedAt::InSet => CsvRow::from_rom(rom, "have", set_file.as_str()),
+        RomLocatedAt::InSetWrongName(name) => CsvRow::from_rom(rom, "rename", format!("{}/{}", set_file, name)),
+        RomLocatedAt::InOthers(locations) => CsvRow::from_rom(rom, "elsewhere",
+            locations.iter().map(|location| format!("{}/{}", location.file, location.with_name)).collect::<Vec<_>>().join(";")),
+    }).collect::<Vec<_>>();
+
+    for (roms, status) in [(&set_report.roms_missing, "missing"), (&set_report.roms_to_spare, "spare"), (&set_report.roms_unneeded, "unneeded")] {
+        let mut roms = roms.iter().collect::<Vec<_>>();
+        roms.sort();
+        rows.extend(roms.into_iter().map(|rom| CsvRow::from_rom(rom, status, if status == "missing" { "" } else { set_file.as_str() })));
+    }
+    rows.extend(set_report.unknown.iter().map(|file| CsvRow::from_rom(file, "unknown", set_file.as_str())));
+
+    rows.extend(set_report.disks.iter().map(|disk| CsvRow {
+        file: disk.name.to_owned(),
+        file_type: "disk",
+        status: get_disk_status_name(&disk.status).to_string(),
+        size: String::new(),
+        crc: String::new(),
+        sha1: String::new(),
+        location: disk.path.clone().unwrap_or_default(),
+    }));
+    rows
+}
+
+fn get_status_name(status: &SetStatus) -> &'static str {
+    match status {
+        SetStatus::COMPLETE => "complete",
+        SetStatus::FIXEABLE => "fixable",
+        SetStatus::INCOMPLETE => "incomplete",
+        SetStatus::MISSING => "missing",
+    }
+}
+
+fn get_confidence_name(confidence: &Confidence) -> &'static str {
+    match confidence {
+        Confidence::NameSize => "name and size",
+        Confidence::CrcSize => "crc and size",
+        Confidence::Md5 => "md5",
+        Confidence::Sha1 => "sha1",
+        Confidence::Sha256 => "sha256",
+    }
+}
+
+fn get_disk_status_name(status: &DiskStatus) -> &'static str {
+    match status {
+        DiskStatus::Ok => "have",
+        DiskStatus::Missing => "missing",
+        DiskStatus::WrongChecksum(_) => "wrong checksum",
+        DiskStatus::ParentMissing(_) => "parent missing",
+        DiskStatus::NotValid => "not valid",
+        DiskStatus::NoDump => "no dump",
+    }
+}
+
+/// RFC 4180: the fields with separators, quotes or line breaks are quoted, doubling their quotes, and rows end in CRLF
+fn write_row<W: Write>(writer: &mut W, fields: &[&str]) -> Result<()> {
+    let fields = fields.iter().map(|field| {
+        if field.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }).collect::<Vec<_>>();
+    write!(writer, "{}\r\n", fields.join(","))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{RomsetMode, data::{models::{file::{DataFileInfo, FileType}, game::Game}, reader::MatchedChecks}};
+    use super::super::scan_report::RomLocation;
+
+    use super::*;
+
+    fn get_rom(name: &str, crc: &str) -> DataFile {
+        let mut info = DataFileInfo::new(FileType::Rom);
+        info.crc = Some(crc.to_string());
+        info.size = Some(1024);
+        DataFile::new(name, info)
+    }
+
+    #[test]
+    fn writes_a_row_per_rom() -> Result<()> {
+        let mut report = ScanReport::new(None, RomsetMode::NonMerged);
+        report.reference_with_game(Game::new("pacman".to_string()));
+        report.set_in_file("pacman.zip");
+        report.add_rom_for_set("pacman", RomLocation::new("pacman.zip", "pacman.6e"), get_rom("pacman.6e", "c1e6ab10"));
+        report.add_rom_for_set("pacman", RomLocation::new("pacman.zip", "wrong, name.bin"), get_rom("pacman.6f", "1a6fb2d4"));
+        report.add_missing_rom_for_set("pacman", get_rom("pacman.6h", "bcdd1beb"));
+        report.add_matched_checks("pacman", "pacman.6e", MatchedChecks { crc: true, size: true, ..Default::default() });
+        report.set_confidences();
+
+        let mut csv = vec![];
+        assert_eq!(3, write_csv(&report, &mut csv)?);
+        let csv = String::from_utf8(csv)?;
+        let lines = csv.split("\r\n").collect::<Vec<_>>();
+        assert_eq!("set,set_status,set_confidence,file,type,status,size,crc,sha1,location", lines[0]);
+        assert_eq!("pacman,incomplete,crc and size,pacman.6e,rom,have,1024,c1e6ab10,,pacman.zip", lines[1]);
+        assert_eq!("pacman,incomplete,crc and size,pacman.6f,rom,rename,1024,1a6fb2d4,,\"pacman.zip/wrong, name.bin\"", lines[2]);
+        assert_eq!("pacman,incomplete,crc and size,pacman.6h,rom,missing,1024,bcdd1beb,,", lines[3]);
+        assert_eq!("", lines[4]);
+
+        Ok(())
+    }
+}
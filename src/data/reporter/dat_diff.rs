@@ -0,0 +1,146 @@
+use std::{collections::BTreeSet, fmt::Display};
+use console::Style;
+use serde::{Deserialize, Serialize};
+use crate::{data::models::history::DatContents, i18n::tr};
+
+/// A set of the old DAT with another name in the new one
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SetRename {
+    pub old_name: String,
+    pub new_name: String,
+}
+
+/// A rom of a set in both DATs that is not the same in the new one. A rom only in the old set was removed, and one only
+/// in the new set was added
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RomChange {
+    pub set_name: String,
+    pub rom_name: String,
+    pub old_checksum: Option<String>,
+    pub new_checksum: Option<String>,
+}
+
+impl Display for RomChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let checksum = |checksum: &Option<String>| checksum.clone().unwrap_or_else(|| "-".to_string());
+        write!(f, "{}: {} {} -> {}", self.set_name, self.rom_name, checksum(&self.old_checksum), checksum(&self.new_checksum))
+    }
+}
+
+/// What changed between two databases or DATs, the sets to acquire again after a new version
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DatDiff {
+    pub old_version: Option<String>,
+    pub new_version: Option<String>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// The sets removed with at least half of their roms in an added one
+    pub renamed: Vec<SetRename>,
+    /// The roms changed in the sets of both, renamed or not
+    pub changed_roms: Vec<RomChange>,
+}
+
+impl DatDiff {
+    pub fn new(old: &DatContents, new: &DatContents) -> Self {
+        let mut diff = DatDiff { old_version: old.version.clone(), new_version: new.version.clone(), ..DatDiff::default() };
+        for change in old.get_snapshot().find_changes(&new.get_snapshot(), "") {
+            match change.successor {
+                Some(new_name) => diff.renamed.push(SetRename { old_name: change.game_name, new_name }),
+                None => diff.removed.push(change.game_name),
+            }
+        }
+        diff.added = new.games.keys()
+            .filter(|game_name| !old.games.contains_key(*game_name) && !diff.renamed.iter().any(|rename| &&rename.new_name == game_name))
+            .cloned().collect();
+        diff.added.sort();
+
+        let mut compared = old.games.keys().filter(|game_name| new.games.contains_key(*game_name))
+            .map(|game_name| (game_name, game_name)).collect::<Vec<_>>();
+        compared.extend(diff.renamed.iter().map(|rename| (&rename.old_name, &rename.new_name)));
+        compared.sort();
+        for (old_name, new_name) in compared {
+            let (old_roms, new_roms) = (&old.games[old_name], &new.games[new_name]);
+            let rom_names = old_roms.keys().chain(new_roms.keys()).collect::<BTreeSet<_>>();
+            for rom_name in rom_names {
+                let (old_checksum, new_checksum) = (old_roms.get(rom_name), new_roms.get(rom_name));
+                if old_checksum != new_checksum {
+                    diff.changed_roms.push(RomChange {
+                        set_name: new_name.to_owned(),
+                        rom_name: rom_name.to_owned(),
+                        old_checksum: old_checksum.cloned(),
+                        new_checksum: new_checksum.cloned(),
+                    });
+                }
+            }
+        }
+
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.renamed.is_empty() && self.changed_roms.is_empty()
+    }
+}
+
+impl Display for DatDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let version = |version: &Option<String>| version.clone().unwrap_or_else(|| "?".to_string());
+        writeln!(f, "{} -> {}", Style::new().bold().apply_to(version(&self.old_version)), Style::new().bold().apply_to(version(&self.new_version)))?;
+        writeln!(f, "{}", Style::new().bold().green().apply_to(format!("== {} ({}) ==", tr("Added"), self.added.len())))?;
+        for set_name in &self.added {
+            writeln!(f, "{}", set_name)?;
+        }
+        writeln!(f, "{}", Style::new().bold().red().apply_to(format!("== {} ({}) ==", tr("Removed"), self.removed.len())))?;
+        for set_name in &self.removed {
+            writeln!(f, "{}", set_name)?;
+        }
+        writeln!(f, "{}", Style::new().bold().yellow().apply_to(format!("== {} ({}) ==", tr("Renamed"), self.renamed.len())))?;
+        for rename in &self.renamed {
+            writeln!(f, "{} -> {}", rename.old_name, rename.new_name)?;
+        }
+        writeln!(f, "{}", Style::new().bold().yellow().apply_to(format!("== {} ({}) ==", tr("Roms changed"), self.changed_roms.len())))?;
+        for rom in &self.changed_roms {
+            writeln!(f, "{}", rom)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents(version: &str, games: Vec<(&str, Vec<(&str, &str)>)>) -> DatContents {
+        DatContents {
+            version: Some(version.to_string()),
+            games: games.into_iter().map(|(name, roms)| {
+                (name.to_string(), roms.into_iter().map(|(rom, checksum)| (rom.to_string(), checksum.to_string())).collect())
+            }).collect(),
+        }
+    }
+
+    #[test]
+    fn finds_the_changed_sets_and_roms() {
+        let old = contents("0.251", vec![
+            ("pacman", vec![("pacman.6e", "aa"), ("pacman.6f", "bb")]),
+            ("galaxian", vec![("galaxian.u", "cc")]),
+            ("puckman", vec![("pm1.bin", "dd"), ("pm2.bin", "ee")]),
+            ("gone", vec![("gone.bin", "ff")]),
+        ]);
+        let new = contents("0.252", vec![
+            ("pacman", vec![("pacman.6e", "aa"), ("pacman.6f", "b2")]),
+            ("galaxian", vec![("galaxian.u", "cc"), ("galaxian.v", "c2")]),
+            ("puckmanj", vec![("pm1.bin", "dd"), ("pm2.bin", "e2")]),
+            ("mspacman", vec![("ms.bin", "11")]),
+        ]);
+
+        let diff = DatDiff::new(&old, &new);
+        assert_eq!(diff.added, vec!["mspacman".to_string()]);
+        assert_eq!(diff.removed, vec!["gone".to_string()]);
+        assert_eq!(diff.renamed, vec![SetRename { old_name: "puckman".to_string(), new_name: "puckmanj".to_string() }]);
+        let changed = diff.changed_roms.iter().map(|rom| format!("{}", rom)).collect::<Vec<_>>();
+        assert_eq!(changed, vec!["galaxian: galaxian.v - -> c2", "pacman: pacman.6f bb -> b2", "puckmanj: pm2.bin ee -> e2"]);
+        assert!(!diff.is_empty());
+        assert!(DatDiff::new(&old, &old).is_empty());
+    }
+}
@@ -0,0 +1,76 @@
+use std::fmt::Display;
+use console::Style;
+use serde::{Deserialize, Serialize};
+use crate::i18n::tr;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiskStatus {
+    Ok,
+    Missing,
+    /// The CHD found has a different sha1 than the expected one
+    WrongChecksum(String),
+    /// The CHD is a delta of a parent CHD that was not found, the sha1 of the parent is kept
+    ParentMissing(String),
+    /// The file is there, but it is not a valid CHD
+    NotValid,
+    /// The disk has no dump, so there is nothing to look for
+    NoDump,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiskResult {
+    pub name: String,
+    /// Where the CHD was found, if it was
+    pub path: Option<String>,
+    pub status: DiskStatus,
+}
+
+impl DiskResult {
+    /// The disk is there, or there is nothing to look for
+    pub fn is_ok(&self) -> bool {
+        self.status == DiskStatus::Ok || self.status == DiskStatus::NoDump
+    }
+}
+
+impl Display for DiskResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.status {
+            DiskStatus::Ok => writeln!(f, "{} {}", Style::new().green().apply_to(tr("OK")), self.name),
+            DiskStatus::Missing => writeln!(f, "{} {}", Style::new().red().apply_to(tr("MISSING")), self.name),
+            DiskStatus::WrongChecksum(sha1) => writeln!(f, "{} {} (sha1: {})", Style::new().red().apply_to(tr("WRONG CHECKSUM")), self.name, sha1),
+            DiskStatus::ParentMissing(parent_sha1) => writeln!(f, "{} {}: {} ({}: {})",
+                Style::new().yellow().apply_to(tr("PARENT MISSING")), self.name, tr("clone CHD present but parent CHD missing"), tr("parent sha1"), parent_sha1),
+            DiskStatus::NotValid => writeln!(f, "{} {}: {}", Style::new().red().apply_to(tr("NOT VALID")), self.name, tr("not a valid CHD")),
+            DiskStatus::NoDump => writeln!(f, "{} {}", Style::new().dim().apply_to(tr("NO DUMP")), self.name),
+        }
+    }
+}
+
+/// The status of the disks of a set, found as CHD files in the set directory or in the directories of its parents
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiskReport {
+    pub set_name: String,
+    pub disks: Vec<DiskResult>,
+}
+
+impl DiskReport {
+    pub fn new<S>(set_name: S) -> Self where S: Into<String> { Self { set_name: set_name.into(), disks: vec![] } }
+
+    pub fn is_complete(&self) -> bool {
+        self.disks.iter().all(DiskResult::is_ok)
+    }
+}
+
+impl Display for DiskReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = if self.is_complete() { Style::new().green().apply_to(tr("COMPLETE")) } else { Style::new().red().apply_to(tr("INCOMPLETE")) };
+        writeln!(f, "{}: {} [{}]", tr("Set"), self.set_name, status)?;
+        if self.disks.is_empty() {
+            return writeln!(f, "{}", tr("The set has no disks"));
+        }
+        for disk in &self.disks {
+            write!(f, "{}", disk)?;
+        }
+        Ok(())
+    }
+}
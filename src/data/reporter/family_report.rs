@@ -0,0 +1,68 @@
+use std::{collections::{BTreeMap, HashSet}, fmt::Display};
+use console::Style;
+use serde::{Deserialize, Serialize};
+use crate::i18n::tr;
+
+/// A parent set with its clones, and which of them are complete in the report
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Family {
+    pub parent: String,
+    pub clones: Vec<String>,
+    pub has_parent: bool,
+    pub clones_owned: Vec<String>,
+}
+
+impl Family {
+    pub fn size(&self) -> usize { self.clones.len() + 1 }
+
+    pub fn owned(&self) -> usize { self.clones_owned.len() + if self.has_parent { 1 } else { 0 } }
+
+    /// Only some clones are complete, the parent is worth looking for
+    pub fn only_clones(&self) -> bool { !self.has_parent && !self.clones_owned.is_empty() }
+}
+
+/// The families of the parents with clones, the ones with more clones first
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FamilyReport {
+    pub families: Vec<Family>,
+}
+
+impl FamilyReport {
+    pub fn new(families: BTreeMap<String, Vec<String>>, complete_sets: &HashSet<String>) -> Self {
+        let mut families = families.into_iter().map(|(parent, clones)| {
+            Family {
+                has_parent: complete_sets.contains(&parent),
+                clones_owned: clones.iter().filter(|clone| complete_sets.contains(*clone)).cloned().collect(),
+                parent,
+                clones,
+            }
+        }).collect::<Vec<_>>();
+        families.sort_by(|a, b| b.clones.len().cmp(&a.clones.len()).then_with(|| a.parent.cmp(&b.parent)));
+
+        Self { families }
+    }
+}
+
+impl Display for FamilyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", Style::new().bold().apply_to(format!("== {} ({}) ==", tr("Families"), self.families.len())))?;
+        if self.families.is_empty() {
+            return writeln!(f, "{}", tr("There are no sets with clones"));
+        }
+        let name_width = self.families.iter().map(|family| family.parent.len()).max().unwrap_or_default();
+        for family in &self.families {
+            let owned = format!("{}/{}", family.owned(), family.size());
+            let owned = if family.owned() == family.size() { Style::new().green().apply_to(owned) } else { Style::new().apply_to(owned) };
+            writeln!(f, "{:width$}  {} {}, {} {}", family.parent, family.clones.len(), tr("clones"), owned, tr("complete"), width = name_width)?;
+        }
+
+        let only_clones = self.families.iter().filter(|family| family.only_clones()).collect::<Vec<_>>();
+        if !only_clones.is_empty() {
+            writeln!(f, "{}", Style::new().bold().yellow().apply_to(format!("== {} ({}) ==", tr("Only clones, parent missing"), only_clones.len())))?;
+            for family in only_clones {
+                writeln!(f, "{}: {}", family.parent, family.clones_owned.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,615 @@
+use std::{collections::HashMap, fmt::Display, path::Path};
+use console::Style;
+use serde::{Deserialize, Serialize};
+
+use crate::{data::models, filesystem::names::{self, WindowsNameIssue}, i18n::tr};
+
+use super::scan_report::{RomLocatedAt, RomLocation, ScanReport, SetReference, SetStatus};
+
+/// A step to fix the sets, the archives are the ones of the report.
+/// The sha1 is the one of the rom in the database, to verify what was written.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FixAction {
+    /// Copies a rom into another archive, the rom is still needed where it is
+    Copy { from_archive: String, from_name: String, to_archive: String, to_name: String, #[serde(default)] sha1: Option<String> },
+    /// Moves a rom not needed where it is into another archive
+    Move { from_archive: String, from_name: String, to_archive: String, to_name: String, #[serde(default)] sha1: Option<String> },
+    /// Renames a rom inside its archive
+    Rename { archive: String, from_name: String, to_name: String, #[serde(default)] sha1: Option<String> },
+    /// Renames an archive that has the roms of a set after the set
+    RenameArchive { from_archive: String, to_archive: String },
+}
+
+impl Display for FixAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (verb, from_archive, from_name, to_archive, to_name) = match self {
+            FixAction::Copy { from_archive, from_name, to_archive, to_name, .. } => (tr("copy"), from_archive, from_name, to_archive, to_name),
+            FixAction::Move { from_archive, from_name, to_archive, to_name, .. } => (tr("move"), from_archive, from_name, to_archive, to_name),
+            FixAction::Rename { archive, from_name, to_name, .. } => {
+                return write!(f, "{} {} {} {} {} {}", tr("rename"), from_name, tr("to"), to_name, tr("in"), archive);
+            }
+            FixAction::RenameArchive { from_archive, to_archive } => {
+                return write!(f, "{} {} {} {}", tr("rename archive"), from_archive, tr("to"), to_archive);
+            }
+        };
+        write!(f, "{} {} {} {} {} {}", verb, from_name, tr("from"), from_archive, tr("into"), to_archive)?;
+        if from_name != to_name {
+            write!(f, " {} {}", tr("as"), to_name)?;
+        }
+        Ok(())
+    }
+}
+
+/// The moves, copies and renames between the scanned archives that complete the fixable sets.
+/// Copies go first, so no rom is moved or renamed before every set needing it has its copy, then the archives are
+/// renamed, before renaming the roms inside them.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FixPlan {
+    /// The directory the archives are in, if the report was from a single one
+    pub root_directory: Option<String>,
+    pub actions: Vec<FixAction>,
+    /// The sets complete once the plan is done
+    pub completed_sets: Vec<String>,
+    /// The fixable sets left out of the plan, their names can't be files on Windows
+    #[serde(default)]
+    pub unrepresentable: Vec<UnrepresentableSet>,
+}
+
+/// A set whose archive or rom names can't be written on Windows
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnrepresentableSet {
+    pub set_name: String,
+    pub issue: WindowsNameIssue,
+}
+
+impl FixPlan {
+    /// With `windows_names` the sets that can't be written on Windows are reported instead of planned
+    pub fn new(report: &ScanReport, windows_names: bool) -> Self {
+        // The scripts only write into archives, the sets kept in folders are left out
+        let mut fixable = report.sets.values()
+            .filter(|set| set.is_complete() == SetStatus::FIXEABLE)
+            .filter(|set| !set.file_name.as_ref().is_some_and(|file_name| file_name.ends_with(models::FOLDER_SUFFIX)))
+            .collect::<Vec<_>>();
+        fixable.sort_by(|a, b| a.reference.get_name().cmp(b.reference.get_name()));
+
+        let mut unrepresentable = vec![];
+        if windows_names {
+            let archives = report.sets.values().map(|set| set.file_name.clone().unwrap_or_else(|| format!("{}.zip", set.reference.get_name()))).collect::<Vec<_>>();
+            fixable.retain(|set| {
+                let set_name = set.reference.get_name();
+                let archive = set.file_name.clone().unwrap_or_else(|| format!("{}.zip", set_name));
+                let rom_names = set.roms_available.keys().map(|rom| rom.name.as_str()).collect::<Vec<_>>();
+                let issue = names::check_windows_name(&archive)
+                    .or_else(|| archives.iter()
+                        .find(|other| **other != archive && other.to_lowercase() == archive.to_lowercase())
+                        .map(|other| WindowsNameIssue::CaseCollision(archive.clone(), other.clone())))
+                    .or_else(|| rom_names.iter().find_map(|name| names::check_windows_name(name)))
+                    .or_else(|| names::find_case_collision(rom_names.iter().copied()));
+                match issue {
+                    Some(issue) => {
+                        unrepresentable.push(UnrepresentableSet { set_name: set_name.to_string(), issue });
+                        false
+                    }
+                    None => true
+                }
+            });
+        }
+
+        // The archives with the roms of a set are renamed instead of taking the roms out of them
+        let archive_renames = report.renames.iter()
+            .filter(|rename| fixable.iter().any(|set| set.reference.get_name() == rename.set_name))
+            .map(|rename| (rename.set_name.as_str(), rename))
+            .collect::<HashMap<_, _>>();
+
+        // Roms taken from other archives, by the set that needs them
+        let mut transfers = vec![];
+        let mut renames = vec![];
+        let mut renamed_archives = vec![];
+        for set in &fixable {
+            let set_name = set.reference.get_name();
+            let mut roms = set.roms_available.iter().collect::<Vec<_>>();
+            roms.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+            if let Some(rename) = archive_renames.get(set_name) {
+                let to_archive = rename.get_new_file_name();
+                renamed_archives.push(FixAction::RenameArchive { from_archive: rename.file_name.clone(), to_archive: to_archive.clone() });
+                for (rom, located_at) in roms {
+                    let name = match located_at {
+                        RomLocatedAt::InOthers(locations) => locations.iter().find(|location| location.file == rename.file_name).map(|location| &location.with_name),
+                        _ => None
+                    };
+                    match name {
+                        Some(name) if *name != rom.name => {
+                            // Copied before renaming the archive if another rom of the set has the name
+                            let still_needed = set.roms_available.keys().any(|other| other.name == *name);
+                            if still_needed {
+                                transfers.push((rename.file_name.clone(), rename.file_name.clone(), name.clone(), rom.name.clone(), rom.info.sha1.clone()));
+                            } else {
+                                renames.push(FixAction::Rename { archive: to_archive.clone(), from_name: name.clone(), to_name: rom.name.clone(), sha1: rom.info.sha1.clone() });
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                continue;
+            }
+            let archive = set.file_name.clone().unwrap_or_else(|| format!("{}.zip", set_name));
+            for (rom, located_at) in roms {
+                match located_at {
+                    RomLocatedAt::InSet => {}
+                    RomLocatedAt::InSetWrongName(name) => {
+                        // The wrong name may be needed as it is by another rom of the set
+                        let still_needed = set.roms_available.iter().any(|(other, other_located_at)| other.name == *name && *other_located_at == RomLocatedAt::InSet);
+                        if still_needed {
+                            transfers.push((archive.clone(), archive.clone(), name.clone(), rom.name.clone(), rom.info.sha1.clone()));
+                        } else {
+                            renames.push(FixAction::Rename { archive: archive.clone(), from_name: name.clone(), to_name: rom.name.clone(), sha1: rom.info.sha1.clone() });
+                        }
+                    }
+                    RomLocatedAt::InOthers(locations) => {
+                        let locations = locations.iter().filter(|location| !location.file.ends_with(models::FOLDER_SUFFIX)).collect::<Vec<_>>();
+                        let source = locations.iter().find(|location| is_spare(report, location)).or_else(|| locations.first());
+                        if let Some(source) = source {
+                            transfers.push((archive.clone(), source.file.clone(), source.with_name.clone(), rom.name.clone(), rom.info.sha1.clone()));
+                        }
+                    }
+                }
+            }
+        }
+
+        // A spare rom is moved by the last set taking it, the rest of them copy it
+        let mut uses: HashMap<(String, String), usize> = HashMap::new();
+        for (_, from_archive, from_name, _, _) in &transfers {
+            *uses.entry((from_archive.clone(), from_name.clone())).or_default() += 1;
+        }
+        let mut copies = vec![];
+        let mut moves = vec![];
+        for (to_archive, from_archive, from_name, to_name, sha1) in transfers {
+            let key = (from_archive.clone(), from_name.clone());
+            let pending = uses.get_mut(&key).map(|pending| { *pending -= 1; *pending }).unwrap_or_default();
+            let location = RomLocation::new(from_archive.clone(), from_name.clone());
+            // The roms of an archive to rename are needed there
+            let renamed = archive_renames.values().any(|rename| rename.file_name == from_archive);
+            if pending == 0 && from_archive != to_archive && !renamed && is_spare(report, &location) {
+                moves.push(FixAction::Move { from_archive, from_name, to_archive, to_name, sha1 });
+            } else {
+                copies.push(FixAction::Copy { from_archive, from_name, to_archive, to_name, sha1 });
+            }
+        }
+
+        let mut actions = copies;
+        actions.append(&mut moves);
+        actions.append(&mut renamed_archives);
+        actions.append(&mut renames);
+
+        Self {
+            root_directory: report.get_root_directory().map(|root_directory| root_directory.to_string()),
+            actions,
+            completed_sets: fixable.iter().map(|set| set.reference.get_name().to_string()).collect(),
+            unrepresentable,
+        }
+    }
+
+    /// A script with the actions of the plan, to review and run it instead of letting romst change the files.
+    /// With `verify`, each rom written is read back and hashed, and the script stops on the first one not matching
+    /// the database, before removing its source. The verifications are logged to `romst_fix.log`.
+    pub fn to_script(&self, shell: ScriptShell, verify: bool) -> String {
+        let mut script = match shell {
+            ScriptShell::Sh => SH_HEADER.to_string(),
+            ScriptShell::PowerShell => POWERSHELL_HEADER.to_string(),
+        };
+        match shell {
+            ScriptShell::Sh => script.push_str(&format!("VERIFY={}\n", if verify { 1 } else { 0 })),
+            ScriptShell::PowerShell => script.push_str(&format!("$Verify = ${}\n", verify)),
+        }
+        if let Some(root_directory) = &self.root_directory {
+            match shell {
+                ScriptShell::Sh => script.push_str(&format!("cd {}\n", shell.quote(root_directory))),
+                ScriptShell::PowerShell => script.push_str(&format!("Set-Location -LiteralPath {}\n", shell.quote(root_directory))),
+            }
+        }
+        script.push('\n');
+
+        for action in &self.actions {
+            // The archives are renamed as they are, there is no rom to verify
+            let (functions, args, sha1) = match action {
+                FixAction::Copy { from_archive, from_name, to_archive, to_name, sha1 } => (("copy_rom", "Copy-Rom"), vec![from_archive, from_name, to_archive, to_name], Some(sha1)),
+                FixAction::Move { from_archive, from_name, to_archive, to_name, sha1 } => (("move_rom", "Move-Rom"), vec![from_archive, from_name, to_archive, to_name], Some(sha1)),
+                FixAction::Rename { archive, from_name, to_name, sha1 } => (("rename_rom", "Rename-Rom"), vec![archive, from_name, to_name], Some(sha1)),
+                FixAction::RenameArchive { from_archive, to_archive } => (("rename_archive", "Rename-Archive"), vec![from_archive, to_archive], None),
+            };
+            let function = match shell {
+                ScriptShell::Sh => functions.0,
+                ScriptShell::PowerShell => functions.1,
+            };
+            let mut args = args.iter().map(|arg| shell.quote(arg)).collect::<Vec<_>>();
+            if let Some(sha1) = sha1 {
+                args.push(shell.quote(sha1.as_deref().unwrap_or_default()));
+            }
+            script.push_str(&format!("{} {}\n", function, args.join(" ")));
+        }
+
+        script
+    }
+}
+
+/// The shell of a fix script
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptShell {
+    Sh,
+    PowerShell,
+}
+
+impl ScriptShell {
+    /// PowerShell for `.ps1` files, sh for `.sh` ones, otherwise the one of the platform
+    pub fn for_file(file: &impl AsRef<Path>) -> Self {
+        match file.as_ref().extension().map(|extension| extension.to_string_lossy().to_lowercase()) {
+            Some(extension) if extension == "ps1" => ScriptShell::PowerShell,
+            Some(extension) if extension == "sh" => ScriptShell::Sh,
+            _ if cfg!(windows) => ScriptShell::PowerShell,
+            _ => ScriptShell::Sh,
+        }
+    }
+
+    fn quote(&self, value: &str) -> String {
+        match self {
+            ScriptShell::Sh => format!("'{}'", value.replace('\'', "'\\''")),
+            ScriptShell::PowerShell => format!("'{}'", value.replace('\'', "''")),
+        }
+    }
+}
+
+// Zip archives are updated with zip, unzip and zipnote, any other source is a directory
+const SH_HEADER: &str = r#"#!/bin/sh
+# Fix plan generated by romst, review it before running it
+set -e
+
+extract_rom() {
+    if [ -d "$1" ]; then cp "$1/$2" "$3"; else unzip -p "$1" "$2" > "$3"; fi
+}
+
+add_rom() {
+    if [ -d "$1" ]; then
+        mkdir -p "$(dirname "$1/$2")"
+        cp "$3" "$1/$2"
+    else
+        archive="$(cd "$(dirname "$1")" && pwd)/$(basename "$1")"
+        tmp_dir=$(mktemp -d)
+        mkdir -p "$(dirname "$tmp_dir/$2")"
+        cp "$3" "$tmp_dir/$2"
+        (cd "$tmp_dir" && zip -q "$archive" "$2")
+        rm -r "$tmp_dir"
+    fi
+}
+
+remove_rom() {
+    if [ -d "$1" ]; then rm "$1/$2"; else zip -q -d "$1" "$2"; fi
+}
+
+sha1_of() {
+    if command -v sha1sum > /dev/null; then sha1sum | cut -d ' ' -f 1; else shasum -a 1 | cut -d ' ' -f 1; fi
+}
+
+# Reads back a rom written, stopping the script if it doesn't have the sha1 of the database
+verify_rom() {
+    if [ "$VERIFY" != 1 ] || [ -z "$3" ]; then return 0; fi
+    if [ -d "$1" ]; then actual=$(sha1_of < "$1/$2"); else actual=$(unzip -p "$1" "$2" | sha1_of); fi
+    if [ "$actual" != "$3" ]; then
+        echo "$(date -u +%Y-%m-%dT%H:%M:%SZ) FAILED $1 $2 expected $3 found $actual" >> romst_fix.log
+        echo "$2 in $1 doesn't match the database, stopping" >&2
+        exit 1
+    fi
+    echo "$(date -u +%Y-%m-%dT%H:%M:%SZ) VERIFIED $1 $2 $3" >> romst_fix.log
+}
+
+copy_rom() {
+    tmp_file=$(mktemp)
+    extract_rom "$1" "$2" "$tmp_file"
+    add_rom "$3" "$4" "$tmp_file"
+    rm "$tmp_file"
+    verify_rom "$3" "$4" "$5"
+}
+
+move_rom() {
+    copy_rom "$1" "$2" "$3" "$4" "$5"
+    remove_rom "$1" "$2"
+}
+
+rename_rom() {
+    if [ -d "$1" ]; then mv "$1/$2" "$1/$3"; else printf '@ %s\n@=%s\n' "$2" "$3" | zipnote -w "$1"; fi
+    verify_rom "$1" "$3" "$4"
+}
+
+rename_archive() {
+    if [ -e "$2" ]; then
+        echo "$2 already exists, stopping" >&2
+        exit 1
+    fi
+    mv "$1" "$2"
+}
+
+"#;
+
+// Zip archives are updated with System.IO.Compression, any other source is a directory.
+// The paths are made absolute, with the \\?\ prefix past the 260 characters Windows allows by default.
+const POWERSHELL_HEADER: &str = r#"# Fix plan generated by romst, review it before running it
+$ErrorActionPreference = 'Stop'
+Add-Type -AssemblyName System.IO.Compression, System.IO.Compression.FileSystem
+
+function Get-FullPath($Path) {
+    $full = [System.IO.Path]::GetFullPath([System.IO.Path]::Combine((Get-Location).ProviderPath, $Path))
+    if ($full.Length -lt 260 -or $full.StartsWith('\\?\')) { return $full }
+    if ($full.StartsWith('\\')) { return '\\?\UNC\' + $full.Substring(2) }
+    '\\?\' + $full
+}
+
+function Get-Rom($Archive, $Name, $Destination) {
+    if (Test-Path -LiteralPath $Archive -PathType Container) {
+        Copy-Item -LiteralPath (Join-Path $Archive $Name) -Destination $Destination -Force
+    } else {
+        $zip = [System.IO.Compression.ZipFile]::OpenRead($Archive)
+        try { [System.IO.Compression.ZipFileExtensions]::ExtractToFile($zip.GetEntry($Name), $Destination, $true) } finally { $zip.Dispose() }
+    }
+}
+
+# Reads back a rom written, stopping the script if it doesn't have the sha1 of the database
+function Confirm-Rom($Archive, $Name, $Sha1) {
+    if (-not $Verify -or -not $Sha1) { return }
+    $temp = New-TemporaryFile
+    Get-Rom $Archive $Name $temp
+    $actual = (Get-FileHash -LiteralPath $temp -Algorithm SHA1).Hash.ToLower()
+    Remove-Item -LiteralPath $temp
+    $date = (Get-Date).ToUniversalTime().ToString('yyyy-MM-ddTHH:mm:ssZ')
+    if ($actual -ne $Sha1.ToLower()) {
+        Add-Content -LiteralPath 'romst_fix.log' -Value "$date FAILED $Archive $Name expected $Sha1 found $actual"
+        throw "$Name in $Archive doesn't match the database, stopping"
+    }
+    Add-Content -LiteralPath 'romst_fix.log' -Value "$date VERIFIED $Archive $Name $Sha1"
+}
+
+function Copy-Rom($FromArchive, $FromName, $ToArchive, $ToName, $Sha1) {
+    $FromArchive = Get-FullPath $FromArchive
+    $ToArchive = Get-FullPath $ToArchive
+    $temp = New-TemporaryFile
+    Get-Rom $FromArchive $FromName $temp
+    if (Test-Path -LiteralPath $ToArchive -PathType Container) {
+        Copy-Item -LiteralPath $temp -Destination (Join-Path $ToArchive $ToName) -Force
+    } else {
+        $zip = [System.IO.Compression.ZipFile]::Open($ToArchive, 'Update')
+        try { [System.IO.Compression.ZipFileExtensions]::CreateEntryFromFile($zip, $temp, $ToName) | Out-Null } finally { $zip.Dispose() }
+    }
+    Remove-Item -LiteralPath $temp
+    Confirm-Rom $ToArchive $ToName $Sha1
+}
+
+function Remove-Rom($Archive, $Name) {
+    $Archive = Get-FullPath $Archive
+    if (Test-Path -LiteralPath $Archive -PathType Container) {
+        Remove-Item -LiteralPath (Join-Path $Archive $Name)
+    } else {
+        $zip = [System.IO.Compression.ZipFile]::Open($Archive, 'Update')
+        try { $zip.GetEntry($Name).Delete() } finally { $zip.Dispose() }
+    }
+}
+
+function Move-Rom($FromArchive, $FromName, $ToArchive, $ToName, $Sha1) {
+    Copy-Rom $FromArchive $FromName $ToArchive $ToName $Sha1
+    Remove-Rom $FromArchive $FromName
+}
+
+function Rename-Rom($Archive, $FromName, $ToName, $Sha1) {
+    Move-Rom $Archive $FromName $Archive $ToName $Sha1
+}
+
+# Fails if there is already a file with the new name
+function Rename-Archive($FromArchive, $ToArchive) {
+    Move-Item -LiteralPath (Get-FullPath $FromArchive) -Destination (Get-FullPath $ToArchive)
+}
+
+"#;
+
+/// The rom is not needed by the set of its archive, or the archive is not a set at all
+fn is_spare(report: &ScanReport, location: &RomLocation) -> bool {
+    let host_name = models::get_set_from_file(&location.file);
+    match report.sets.get(&host_name) {
+        None => true,
+        Some(host) => {
+            if let SetReference::FileName(_) = host.reference {
+                return true;
+            }
+            let needed = host.roms_available.iter().any(|(rom, located_at)| match located_at {
+                RomLocatedAt::InSet => rom.name == location.with_name,
+                RomLocatedAt::InSetWrongName(name) => *name == location.with_name,
+                RomLocatedAt::InOthers(_) => false,
+            });
+            !needed && host.roms_to_spare.iter().any(|rom| rom.name == location.with_name)
+        }
+    }
+}
+
+impl Display for FixPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", Style::new().bold().apply_to(format!("== {} ({} {}, {} {}) ==", tr("Fix plan"), self.actions.len(), tr("actions"), self.completed_sets.len(), tr("sets completed"))))?;
+        if self.actions.is_empty() {
+            writeln!(f, "{}", tr("There is nothing to fix with the files scanned"))?;
+        } else {
+            for action in &self.actions {
+                writeln!(f, "- {}", action)?;
+            }
+            writeln!(f, "{}: {}", tr("Completes"), self.completed_sets.join(", "))?;
+        }
+        for unrepresentable in &self.unrepresentable {
+            writeln!(f, "{} {} can't be written on Windows: {}", Style::new().yellow().apply_to(tr("WARNING")), unrepresentable.set_name, unrepresentable.issue)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{RomsetMode, data::models::{file::{DataFile, DataFileInfo, FileType}, game::Game}, filesystem::names::NameRules};
+
+    use super::*;
+
+    fn get_rom(name: &str, sha1: &str) -> DataFile {
+        let mut info = DataFileInfo::new(FileType::Rom);
+        info.sha1 = Some(sha1.to_string());
+        DataFile::new(name, info)
+    }
+
+    #[test]
+    fn plans_moves_and_copies_between_archives() {
+        let mut report = ScanReport::new(None, RomsetMode::NonMerged);
+        let shared = get_rom("shared.bin", "1234");
+        let used = get_rom("used.bin", "5678");
+
+        report.set_in_file("unsorted.zip");
+        report.add_roms_to_spare(vec![get_rom("unsorted1.bin", "1234")], "unsorted.zip");
+        report.set_in_file("galaxian.zip");
+        report.reference_with_game(Game::new("galaxian".to_string()));
+        report.add_rom_for_set("galaxian", RomLocation::new("galaxian.zip", "used.bin"), used.clone());
+        for set_name in ["mspacman", "pacman"].iter() {
+            report.reference_with_game(Game::new(set_name.to_string()));
+            report.add_rom_for_set(*set_name, RomLocation::new("unsorted.zip", "unsorted1.bin"), shared.clone());
+        }
+        report.set_in_file("pacman.zip");
+        report.add_rom_for_set("pacman", RomLocation::new("galaxian.zip", "used.bin"), used);
+        report.add_rom_for_set("pacman", RomLocation::new("pacman.zip", "wrong.bin"), get_rom("pacman.bin", "9012"));
+
+        let plan = FixPlan::new(&report, false);
+        assert_eq!(plan.completed_sets, vec!["mspacman".to_string(), "pacman".to_string()]);
+        assert_eq!(plan.actions, vec![
+            FixAction::Copy { from_archive: "unsorted.zip".to_string(), from_name: "unsorted1.bin".to_string(), to_archive: "mspacman.zip".to_string(), to_name: "shared.bin".to_string(), sha1: Some("1234".to_string()) },
+            FixAction::Copy { from_archive: "galaxian.zip".to_string(), from_name: "used.bin".to_string(), to_archive: "pacman.zip".to_string(), to_name: "used.bin".to_string(), sha1: Some("5678".to_string()) },
+            FixAction::Move { from_archive: "unsorted.zip".to_string(), from_name: "unsorted1.bin".to_string(), to_archive: "pacman.zip".to_string(), to_name: "shared.bin".to_string(), sha1: Some("1234".to_string()) },
+            FixAction::Rename { archive: "pacman.zip".to_string(), from_name: "wrong.bin".to_string(), to_name: "pacman.bin".to_string(), sha1: Some("9012".to_string()) },
+        ]);
+    }
+
+    #[test]
+    fn renames_the_archives_with_the_roms_of_a_set() {
+        let mut report = ScanReport::new(None, RomsetMode::NonMerged);
+        report.set_in_file("pacman (copy).zip");
+        report.add_roms_to_spare(vec![get_rom("pacman.6e", "1234"), get_rom("wrong.6f", "5678")], "pacman (copy).zip");
+        report.reference_with_game(Game::new("pacman".to_string()));
+        report.add_rom_for_set("pacman", RomLocation::new("pacman (copy).zip", "pacman.6e"), get_rom("pacman.6e", "1234"));
+        report.add_rom_for_set("pacman", RomLocation::new("pacman (copy).zip", "wrong.6f"), get_rom("pacman.6f", "5678"));
+        report.reference_with_game(Game::new("puckman".to_string()));
+        report.add_rom_for_set("puckman", RomLocation::new("pacman (copy).zip", "pacman.6e"), get_rom("puckman.6e", "1234"));
+
+        assert_eq!(report.find_archive_renames(NameRules::ALL), 1);
+        assert_eq!(report.renames[0].get_new_file_name(), "pacman.zip");
+
+        let plan = FixPlan::new(&report, false);
+        assert_eq!(plan.completed_sets, vec!["pacman".to_string(), "puckman".to_string()]);
+        assert_eq!(plan.actions, vec![
+            FixAction::Copy { from_archive: "pacman (copy).zip".to_string(), from_name: "pacman.6e".to_string(), to_archive: "puckman.zip".to_string(), to_name: "puckman.6e".to_string(), sha1: Some("1234".to_string()) },
+            FixAction::RenameArchive { from_archive: "pacman (copy).zip".to_string(), to_archive: "pacman.zip".to_string() },
+            FixAction::Rename { archive: "pacman.zip".to_string(), from_name: "wrong.6f".to_string(), to_name: "pacman.6f".to_string(), sha1: Some("5678".to_string()) },
+        ]);
+        assert!(plan.to_script(ScriptShell::Sh, false).contains("rename_archive 'pacman (copy).zip' 'pacman.zip'\n"));
+        assert!(plan.to_script(ScriptShell::PowerShell, false).contains("Rename-Archive 'pacman (copy).zip' 'pacman.zip'\n"));
+    }
+
+    #[test]
+    fn writes_the_plan_as_a_script() {
+        let plan = FixPlan {
+            root_directory: Some("/roms".to_string()),
+            actions: vec![
+                FixAction::Move { from_archive: "unsorted.zip".to_string(), from_name: "pac'man.bin".to_string(), to_archive: "pacman.zip".to_string(), to_name: "pacman.bin".to_string(), sha1: Some("abcd".to_string()) },
+                FixAction::Rename { archive: "pacman.zip".to_string(), from_name: "wrong.bin".to_string(), to_name: "right.bin".to_string(), sha1: None },
+            ],
+            completed_sets: vec!["pacman".to_string()],
+            unrepresentable: vec![],
+        };
+
+        let script = plan.to_script(ScriptShell::Sh, false);
+        assert!(script.starts_with("#!/bin/sh"));
+        assert!(script.contains("VERIFY=0\n"));
+        assert!(script.contains("cd '/roms'\n"));
+        assert!(script.contains("move_rom 'unsorted.zip' 'pac'\\''man.bin' 'pacman.zip' 'pacman.bin' 'abcd'\n"));
+        assert!(script.contains("rename_rom 'pacman.zip' 'wrong.bin' 'right.bin' ''\n"));
+        assert!(plan.to_script(ScriptShell::Sh, true).contains("VERIFY=1\n"));
+
+        let script = plan.to_script(ScriptShell::PowerShell, true);
+        assert!(script.contains("$Verify = $true\n"));
+        assert!(script.contains("Set-Location -LiteralPath '/roms'\n"));
+        assert!(script.contains("Move-Rom 'unsorted.zip' 'pac''man.bin' 'pacman.zip' 'pacman.bin' 'abcd'\n"));
+        assert!(script.contains("'\\\\?\\UNC\\'"));
+        assert_eq!(ScriptShell::for_file(&"fix.ps1"), ScriptShell::PowerShell);
+        assert_eq!(ScriptShell::for_file(&"fix.sh"), ScriptShell::Sh);
+    }
+
+    #[test]
+    fn writes_the_verification_of_the_roms_in_the_script() {
+        let plan = FixPlan {
+            root_directory: None,
+            actions: vec![
+                FixAction::Copy { from_archive: "unsorted".to_string(), from_name: "pacman.bin".to_string(), to_archive: "pacman".to_string(), to_name: "pacman.bin".to_string(), sha1: Some("abcd".to_string()) },
+            ],
+            completed_sets: vec!["pacman".to_string()],
+            unrepresentable: vec![],
+        };
+
+        let script = plan.to_script(ScriptShell::Sh, true);
+        assert!(script.contains("VERIFY=1\n"));
+        assert!(script.contains("verify_rom() {\n"));
+        assert!(script.contains("    verify_rom \"$3\" \"$4\" \"$5\"\n"));
+        assert!(script.contains("copy_rom 'unsorted' 'pacman.bin' 'pacman' 'pacman.bin' 'abcd'\n"));
+
+        let script = plan.to_script(ScriptShell::PowerShell, true);
+        assert!(script.contains("$Verify = $true\n"));
+        assert!(script.contains("function Confirm-Rom($Archive, $Name, $Sha1) {\n"));
+        assert!(script.contains("    Confirm-Rom $ToArchive $ToName $Sha1\n"));
+        assert!(script.contains("Copy-Rom 'unsorted' 'pacman.bin' 'pacman' 'pacman.bin' 'abcd'\n"));
+        assert!(plan.to_script(ScriptShell::PowerShell, false).contains("$Verify = $false\n"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stops_the_script_when_a_rom_written_does_not_match() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("romst_fix_script_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("unsorted"))?;
+        std::fs::create_dir_all(dir.join("pacman"))?;
+        std::fs::write(dir.join("unsorted").join("pacman.bin"), "pacman")?;
+        let run = |sha1: &str| -> anyhow::Result<bool> {
+            let plan = FixPlan {
+                root_directory: Some(dir.to_string_lossy().to_string()),
+                actions: vec![
+                    FixAction::Copy { from_archive: "unsorted".to_string(), from_name: "pacman.bin".to_string(), to_archive: "pacman".to_string(), to_name: "pacman.bin".to_string(), sha1: Some(sha1.to_string()) },
+                ],
+                completed_sets: vec!["pacman".to_string()],
+                unrepresentable: vec![],
+            };
+            std::fs::write(dir.join("fix.sh"), plan.to_script(ScriptShell::Sh, true))?;
+            Ok(std::process::Command::new("sh").arg(dir.join("fix.sh")).stderr(std::process::Stdio::null()).status()?.success())
+        };
+
+        // The sha1 of `pacman`
+        assert!(run("9cca19308f78053388005d92881437bb0c47b45a")?);
+        assert!(!run("0000000000000000000000000000000000000000")?);
+        let log = std::fs::read_to_string(dir.join("romst_fix.log"))?;
+        assert!(log.contains(" VERIFIED pacman pacman.bin 9cca19308f78053388005d92881437bb0c47b45a\n"));
+        assert!(log.contains(" FAILED pacman pacman.bin expected 0000000000000000000000000000000000000000 found 9cca19308f78053388005d92881437bb0c47b45a\n"));
+        std::fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn leaves_out_sets_not_valid_on_windows() {
+        let mut report = ScanReport::new(None, RomsetMode::NonMerged);
+        report.set_in_file("unsorted.zip");
+        report.add_roms_to_spare(vec![get_rom("unsorted1.bin", "1234"), get_rom("unsorted2.bin", "5678")], "unsorted.zip");
+        report.reference_with_game(Game::new("con".to_string()));
+        report.add_rom_for_set("con", RomLocation::new("unsorted.zip", "unsorted1.bin"), get_rom("con.bin", "1234"));
+        report.reference_with_game(Game::new("pacman".to_string()));
+        report.add_rom_for_set("pacman", RomLocation::new("unsorted.zip", "unsorted2.bin"), get_rom("pacman.bin", "5678"));
+
+        let plan = FixPlan::new(&report, false);
+        assert_eq!(plan.completed_sets, vec!["con".to_string(), "pacman".to_string()]);
+
+        let plan = FixPlan::new(&report, true);
+        assert_eq!(plan.completed_sets, vec!["pacman".to_string()]);
+        assert_eq!(plan.unrepresentable, vec![UnrepresentableSet { set_name: "con".to_string(), issue: WindowsNameIssue::Reserved("con.zip".to_string()) }]);
+    }
+}
@@ -0,0 +1,81 @@
+use std::io::Write;
+use anyhow::Result;
+
+use crate::data::{models::{dat::DatInfo, game::Game}, writer::{DataWriter, dat::DatWriter}};
+use super::scan_report::{ScanReport, SetReference};
+
+/// Writes the roms missing in each set of the report as a Logiqx DAT, to complete the collection with other tools.
+/// Only the sets of the database with missing roms are written, without their parents, so each set lists
+/// exactly the roms it needs. Returns the number of sets written
+pub fn write_fixdat<W: Write>(report: &ScanReport, dat_name: &str, writer: W) -> Result<usize> {
+    let mut dat_info = DatInfo::new(format!("fix_{}", dat_name));
+    dat_info.description = Some(format!("fix_{}", dat_name));
+    dat_info.version = report.get_date_time().ok().map(|date_time| date_time.format("%Y%m%d").to_string());
+    dat_info.header.insert("author".to_string(), "romst".to_string());
+
+    let mut sets = report.sets.iter()
+        .filter(|(_, set_report)| !set_report.roms_missing.is_empty())
+        .filter_map(|(set_name, set_report)| match &set_report.reference {
+            SetReference::Game(game) => Some((set_name, game, &set_report.roms_missing)),
+            SetReference::FileName(_) => None,
+        })
+        .collect::<Vec<_>>();
+    sets.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut dat_writer = DatWriter::new(writer, dat_info.name.to_owned());
+    dat_writer.init()?;
+    dat_writer.on_dat_info(dat_info)?;
+    for (_, game, roms_missing) in &sets {
+        let fix_game = Game {
+            clone_of: None,
+            rom_of: None,
+            sample_of: None,
+            ..(*game).clone()
+        };
+        let mut roms = roms_missing.iter().cloned().collect::<Vec<_>>();
+        roms.sort();
+        dat_writer.on_new_entry(fix_game, roms, vec![], vec![], vec![])?;
+    }
+    dat_writer.finish()?;
+
+    Ok(sets.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{RomsetMode, data::models::file::{DataFile, DataFileInfo, FileType}};
+    use super::super::scan_report::RomLocation;
+
+    use super::*;
+
+    fn get_rom(name: &str, crc: &str) -> DataFile {
+        let mut info = DataFileInfo::new(FileType::Rom);
+        info.crc = Some(crc.to_string());
+        info.size = Some(1024);
+        DataFile::new(name, info)
+    }
+
+    #[test]
+    fn writes_the_missing_roms() -> Result<()> {
+        let mut report = ScanReport::new(None, RomsetMode::Split);
+        let mut clone = Game::new("mspacman".to_string());
+        clone.clone_of = Some("pacman".to_string());
+        for game in [Game::new("pacman".to_string()), clone, Game::new("galaxian".to_string())].iter() {
+            report.reference_with_game(game.clone());
+        }
+        report.set_in_file("pacman.zip");
+        report.add_rom_for_set("pacman", RomLocation::new("pacman.zip", "pacman.6e"), get_rom("pacman.6e", "c1e6ab10"));
+        report.add_missing_roms_for_set("mspacman", vec![get_rom("boot2", "0000000b"), get_rom("boot1", "0000000a")].into_iter());
+        report.set_in_file("unknown.zip");
+        report.add_unknown_files(vec![get_rom("what.bin", "ffffffff")], "unknown.zip");
+
+        let mut output = vec![];
+        assert_eq!(1, write_fixdat(&report, "MAME 0.250", &mut output)?);
+        let dat = String::from_utf8(output)?;
+        assert!(dat.contains("<name>fix_MAME 0.250</name>"));
+        assert!(dat.contains("\t<machine name=\"mspacman\">\n\t\t<rom name=\"boot1\" size=\"1024\" crc=\"0000000a\"/>\n\t\t<rom name=\"boot2\""));
+        assert!(!dat.contains("pacman.6e") && !dat.contains("what.bin") && !dat.contains("galaxian"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use std::fmt::Write;
+use serde::{Deserialize, Serialize};
+use super::scan_report::{ScanReport, SetReference, SetStatus};
+
+/// The sets complete and not complete of a report, to be written as the have and miss lists of clrmamepro:
+/// a line with the counters, an empty one and a set name per line
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HaveMissLists {
+    /// The name of the DATs the sets are from, as the profile name in clrmamepro
+    pub dat_name: String,
+    pub have: Vec<String>,
+    pub miss: Vec<String>,
+}
+
+impl HaveMissLists {
+    /// Only the sets of the database are counted, the files matching no game are left out
+    pub fn new<S>(dat_name: S, report: &ScanReport) -> Self where S: Into<String> {
+        let mut have = vec![];
+        let mut miss = vec![];
+        for (set_name, set_report) in report.sets.iter().filter(|(_, set_report)| matches!(set_report.reference, SetReference::Game(_))) {
+            if set_report.is_complete() == SetStatus::COMPLETE {
+                have.push(set_name.to_owned());
+            } else {
+                miss.push(set_name.to_owned());
+            }
+        }
+        have.sort();
+        miss.sort();
+
+        Self { dat_name: dat_name.into(), have, miss }
+    }
+
+    pub fn known(&self) -> usize { self.have.len() + self.miss.len() }
+
+    pub fn to_have_list(&self) -> String {
+        self.to_list(format!("You have {} of {} known {} sets", self.have.len(), self.known(), self.dat_name), &self.have)
+    }
+
+    pub fn to_miss_list(&self) -> String {
+        self.to_list(format!("You are missing {} of {} known {} sets", self.miss.len(), self.known(), self.dat_name), &self.miss)
+    }
+
+    /// clrmamepro writes CRLF line endings, the tools reading the lists expect them
+    fn to_list(&self, header: String, sets: &[String]) -> String {
+        let mut list = String::new();
+        let _ = write!(list, "{}\r\n\r\n", header);
+        for set in sets {
+            let _ = write!(list, "{}\r\n", set);
+        }
+        list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{RomsetMode, data::models::{file::{DataFile, DataFileInfo, FileType}, game::Game}};
+    use super::super::scan_report::RomLocation;
+
+    use super::*;
+
+    fn get_rom(name: &str) -> DataFile {
+        let mut info = DataFileInfo::new(FileType::Rom);
+        info.sha1 = Some(name.to_string());
+        DataFile::new(name, info)
+    }
+
+    #[test]
+    fn writes_the_have_and_miss_lists() {
+        let mut report = ScanReport::new(None, RomsetMode::NonMerged);
+        for set_name in ["pacman", "galaxian", "mspacman"].iter() {
+            report.reference_with_game(Game::new(set_name.to_string()));
+        }
+        report.set_in_file("pacman.zip");
+        report.add_rom_for_set("pacman", RomLocation::new("pacman.zip", "pacman.bin"), get_rom("pacman.bin"));
+        report.set_in_file("mspacman.zip");
+        report.add_rom_for_set("mspacman", RomLocation::new("mspacman.zip", "mspacman.bin"), get_rom("mspacman.bin"));
+        report.add_missing_rom_for_set("mspacman", get_rom("boot.bin"));
+        report.add_missing_rom_for_set("galaxian", get_rom("galaxian.bin"));
+        report.set_in_file("unknown.zip");
+        report.add_unknown_files(vec![get_rom("what.bin")], "unknown.zip");
+
+        let lists = HaveMissLists::new("MAME 0.250", &report);
+        assert_eq!("You have 1 of 3 known MAME 0.250 sets\r\n\r\npacman\r\n", lists.to_have_list());
+        assert_eq!("You are missing 2 of 3 known MAME 0.250 sets\r\n\r\ngalaxian\r\nmspacman\r\n", lists.to_miss_list());
+    }
+}
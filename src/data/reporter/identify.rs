@@ -0,0 +1,161 @@
+use std::{fmt::Display, path::Path};
+use serde::{Deserialize, Serialize};
+use crate::i18n::tr;
+
+/// The bytes of the beginning of a file read to recognize its header, enough for the Master System one
+pub const HEADER_LENGTH: usize = 0x8000;
+
+/// The logo every Game Boy cartridge has in its header
+const GAME_BOY_LOGO: &[u8] = &[0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B];
+/// The logo of the Game Boy Advance and the Nintendo DS headers
+const GBA_LOGO: &[u8] = &[0x24, 0xFF, 0xAE, 0x51, 0x69, 0x9A, 0xA2, 0x21];
+
+/// The systems recognized by the extension of their files, named like in the No-Intro DATs
+const EXTENSIONS: [(&str, &str); 27] = [
+    ("nes", "Nintendo - Nintendo Entertainment System"),
+    ("fds", "Nintendo - Family Computer Disk System"),
+    ("sfc", "Nintendo - Super Nintendo Entertainment System"),
+    ("smc", "Nintendo - Super Nintendo Entertainment System"),
+    ("gb", "Nintendo - Game Boy"),
+    ("gbc", "Nintendo - Game Boy Color"),
+    ("gba", "Nintendo - Game Boy Advance"),
+    ("nds", "Nintendo - Nintendo DS"),
+    ("n64", "Nintendo - Nintendo 64"),
+    ("z64", "Nintendo - Nintendo 64"),
+    ("v64", "Nintendo - Nintendo 64"),
+    ("vb", "Nintendo - Virtual Boy"),
+    ("md", "Sega - Mega Drive - Genesis"),
+    ("gen", "Sega - Mega Drive - Genesis"),
+    ("smd", "Sega - Mega Drive - Genesis"),
+    ("32x", "Sega - 32X"),
+    ("sms", "Sega - Master System - Mark III"),
+    ("gg", "Sega - Game Gear"),
+    ("pce", "NEC - PC Engine - TurboGrafx-16"),
+    ("lnx", "Atari - Atari Lynx"),
+    ("a26", "Atari - Atari 2600"),
+    ("a78", "Atari - Atari 7800"),
+    ("ws", "Bandai - WonderSwan"),
+    ("wsc", "Bandai - WonderSwan Color"),
+    ("ngp", "SNK - NeoGeo Pocket"),
+    ("ngc", "SNK - NeoGeo Pocket Color"),
+    ("col", "Coleco - ColecoVision"),
+];
+
+/// Why an unknown file is thought to belong to another DAT
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IdentificationClue {
+    /// The checksums match a rom of a game in another database
+    Database { db_file: String, game_name: String },
+    /// The header at the beginning of the file is the one of the system
+    Header,
+    /// Only the extension of the file
+    Extension,
+}
+
+impl Display for IdentificationClue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IdentificationClue::Database { db_file, game_name } => write!(f, "{} {} {} {}", tr("a rom of"), game_name, tr("in"), db_file),
+            IdentificationClue::Header => write!(f, "{}", tr("by its header")),
+            IdentificationClue::Extension => write!(f, "{}", tr("by its extension")),
+        }
+    }
+}
+
+/// An unknown file with the DAT, or the system, it probably belongs to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Identification {
+    /// The archive with the file, none for the files on their own
+    pub archive: Option<String>,
+    pub file_name: String,
+    pub belongs_to: String,
+    pub clue: IdentificationClue,
+}
+
+impl Display for Identification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "- {}", self.file_name)?;
+        if let Some(archive) = &self.archive {
+            write!(f, " {} {}", tr("in"), archive)?;
+        }
+        writeln!(f, ": {} {} ({})", tr("probably belongs to"), self.belongs_to, self.clue)
+    }
+}
+
+/// The system of a file by its header, if it has one of the known ones, or else by its extension
+pub fn guess_system(file_name: &str, header: Option<&[u8]>) -> Option<(&'static str, IdentificationClue)> {
+    if let Some(system) = header.and_then(get_system_from_header) {
+        return Some((system, IdentificationClue::Header));
+    }
+    let extension = Path::new(file_name).extension()?.to_string_lossy().to_lowercase();
+    EXTENSIONS.iter()
+        .find(|(system_extension, _)| *system_extension == extension)
+        .map(|(_, system)| (*system, IdentificationClue::Extension))
+}
+
+/// The DAT of the system, the No-Intro ones are named after the system with the version and flags in parentheses
+pub fn find_dat_for_system<'a>(system: &str, dat_names: &'a [String]) -> Option<&'a String> {
+    let system = system.to_lowercase();
+    dat_names.iter().find(|dat_name| {
+        let dat_name = dat_name.to_lowercase();
+        dat_name.strip_prefix(&system).is_some_and(|rest| rest.is_empty() || rest.starts_with(" ("))
+    })
+}
+
+fn get_system_from_header(data: &[u8]) -> Option<&'static str> {
+    let has_at = |offset: usize, bytes: &[u8]| data.get(offset..offset + bytes.len()) == Some(bytes);
+
+    if has_at(0, b"NES\x1A") {
+        Some("Nintendo - Nintendo Entertainment System")
+    } else if has_at(0, b"FDS\x1A") {
+        Some("Nintendo - Family Computer Disk System")
+    } else if has_at(0, b"LYNX") {
+        Some("Atari - Atari Lynx")
+    } else if has_at(1, b"ATARI7800") {
+        Some("Atari - Atari 7800")
+    // Big endian, byte swapped and little endian dumps
+    } else if has_at(0, &[0x80, 0x37, 0x12, 0x40]) || has_at(0, &[0x37, 0x80, 0x40, 0x12]) || has_at(0, &[0x40, 0x12, 0x37, 0x80]) {
+        Some("Nintendo - Nintendo 64")
+    } else if has_at(0x104, GAME_BOY_LOGO) {
+        // The color flag, the cartridges for both systems are Game Boy Color ones in No-Intro
+        match data.get(0x143) {
+            Some(flag) if flag & 0x80 != 0 => Some("Nintendo - Game Boy Color"),
+            _ => Some("Nintendo - Game Boy"),
+        }
+    } else if has_at(0x04, GBA_LOGO) && data.get(0xB2) == Some(&0x96) {
+        Some("Nintendo - Game Boy Advance")
+    } else if has_at(0xC0, GBA_LOGO) {
+        Some("Nintendo - Nintendo DS")
+    } else if has_at(0x100, b"SEGA") {
+        Some("Sega - Mega Drive - Genesis")
+    } else if has_at(0x7FF0, b"TMR SEGA") {
+        // The region code, the Game Gear ones are 5 to 7
+        match data.get(0x7FFF).map(|region| region >> 4) {
+            Some(5..=7) => Some("Sega - Game Gear"),
+            _ => Some("Sega - Master System - Mark III"),
+        }
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guesses_the_system() {
+        let mut header = vec![0u8; HEADER_LENGTH];
+        header[0x104..0x10C].copy_from_slice(GAME_BOY_LOGO);
+        assert_eq!(guess_system("game.bin", Some(&header)), Some(("Nintendo - Game Boy", IdentificationClue::Header)));
+        header[0x143] = 0x80;
+        assert_eq!(guess_system("game.bin", Some(&header)), Some(("Nintendo - Game Boy Color", IdentificationClue::Header)));
+        assert_eq!(guess_system("game.nes", Some(b"NES\x1A\x02")).map(|guess| guess.1), Some(IdentificationClue::Header));
+        assert_eq!(guess_system("Game (USA).SFC", Some(&[0u8; 16])), Some(("Nintendo - Super Nintendo Entertainment System", IdentificationClue::Extension)));
+        assert_eq!(guess_system("readme.txt", None), None);
+
+        let dat_names = vec!["Nintendo - Game Boy Advance (20240101-000000)".to_string(), "Nintendo - Game Boy (20240101-000000)".to_string()];
+        assert_eq!(find_dat_for_system("Nintendo - Game Boy", &dat_names), Some(&dat_names[1]));
+        assert_eq!(find_dat_for_system("Nintendo - Game Boy Color", &dat_names), None);
+    }
+}
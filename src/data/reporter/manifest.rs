@@ -0,0 +1,153 @@
+use std::{collections::BTreeMap, fs::File, io::BufReader, path::Path};
+use serde::{Deserialize, Serialize};
+use chrono::Utc;
+use anyhow::Result;
+
+use crate::{data::models::file::DataFile, filesystem::{atomic::AtomicFile, checksum_file::ChecksumEntry}};
+use super::scan_report::ConvertedImage;
+
+/// The result of hashing the files of a scan, so they can be checked later without reading them again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanManifest {
+    pub root_directory: Option<String>,
+    pub date_time: String,
+    pub files: Vec<ManifestFile>,
+    pub ignored: Vec<String>,
+    #[serde(default)]
+    pub converted: Vec<ConvertedImage>,
+    /// Corrupt archives, with the files that couldn't be read
+    #[serde(default)]
+    pub corrupt: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub file_name: String,
+    pub path: String,
+    pub size: u64,
+    pub roms: Vec<DataFile>,
+    /// Encrypted files that couldn't be read
+    #[serde(default)]
+    pub locked: Vec<String>,
+    /// Files that couldn't be read, when the rest of a corrupt archive is salvaged
+    #[serde(default)]
+    pub corrupt: Vec<String>,
+}
+
+impl ScanManifest {
+    pub fn new(root_directory: Option<String>) -> Self {
+        Self {
+            root_directory,
+            date_time: Utc::now().to_rfc3339(),
+            files: vec![],
+            ignored: vec![],
+            converted: vec![],
+            corrupt: BTreeMap::new()
+        }
+    }
+
+    /// Adds the files from a checksum file. Files inside a directory belong to the set named like the directory,
+    /// the other ones to the set named like the checksum file. Unzipped sets are added like their zip counterpart.
+    pub fn add_checksum_entries(&mut self, checksum_file: &impl AsRef<Path>, entries: Vec<ChecksumEntry>) {
+        let checksum_file = checksum_file.as_ref();
+        let base_dir = checksum_file.parent().unwrap_or_else(|| Path::new(""));
+        let default_set = checksum_file.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+
+        for entry in entries {
+            let (set_name, rom_name) = match entry.name.split_once('/') {
+                Some((set_name, rom_name)) => (set_name.to_string(), rom_name.to_string()),
+                None => (default_set.clone(), entry.name)
+            };
+            let file_name = format!("{}.zip", set_name);
+            let rom = DataFile::new(rom_name, entry.info);
+
+            match self.files.iter_mut().find(|file| file.file_name == file_name) {
+                Some(file) => {
+                    file.size += rom.info.size.unwrap_or_default() as u64;
+                    file.roms.push(rom);
+                }
+                None => {
+                    self.files.push(ManifestFile {
+                        file_name,
+                        path: base_dir.join(&set_name).to_string_lossy().to_string(),
+                        size: rom.info.size.unwrap_or_default() as u64,
+                        roms: vec![rom],
+                        locked: vec![],
+                        corrupt: vec![]
+                    });
+                }
+            }
+        }
+    }
+
+    /// Saves the manifest as JSON if the file has a `json` extension, in binary format otherwise
+    pub fn save(&self, output_file: &impl AsRef<Path>) -> Result<()> {
+        let mut file = AtomicFile::create(output_file)?;
+        if is_json_file(output_file) {
+            serde_json::to_writer(&mut file, self)?;
+        } else {
+            bincode::serialize_into(&mut file, self)?;
+        }
+        file.commit()?;
+
+        Ok(())
+    }
+
+    pub fn load(input_file: &impl AsRef<Path>) -> Result<Self> {
+        let reader = BufReader::new(File::open(input_file)?);
+        let manifest = if is_json_file(input_file) {
+            serde_json::from_reader(reader)?
+        } else {
+            bincode::deserialize_from(reader)?
+        };
+
+        Ok(manifest)
+    }
+}
+
+fn is_json_file(file: &impl AsRef<Path>) -> bool {
+    file.as_ref().extension().is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::models::file::{DataFileInfo, FileType};
+    use super::*;
+
+    #[test]
+    fn saves_and_loads_manifests() -> Result<()> {
+        let mut manifest = ScanManifest::new(Some("/roms".to_string()));
+        let mut rom = DataFile::new("rom1.trom", DataFileInfo::new(FileType::Rom));
+        rom.info.crc = Some("1d460eee".to_string());
+        manifest.files.push(ManifestFile { file_name: "game1.zip".to_string(), path: "/roms/game1.zip".to_string(), size: 10, roms: vec![rom], locked: vec![], corrupt: vec![] });
+        manifest.ignored.push("readme.txt".to_string());
+
+        let dir = std::env::temp_dir().join(format!("romst_manifest_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        for file_name in ["manifest.json", "manifest.bin"].iter() {
+            let file = dir.join(file_name);
+            manifest.save(&file)?;
+            let loaded = ScanManifest::load(&file)?;
+            assert_eq!(loaded.root_directory, manifest.root_directory);
+            assert_eq!(loaded.files.len(), 1);
+            assert_eq!(loaded.files[0].roms, manifest.files[0].roms);
+            assert_eq!(loaded.ignored, manifest.ignored);
+        }
+        std::fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn adds_checksum_entries_by_set() {
+        let mut manifest = ScanManifest::new(None);
+        let entry = |name: &str| ChecksumEntry { name: name.to_string(), path: Path::new("/roms").join(name), info: DataFileInfo::new(FileType::Rom) };
+        manifest.add_checksum_entries(&Path::new("/roms/game1.sfv"), vec![ entry("rom1.trom"), entry("game2/rom2.trom"), entry("rom3.trom") ]);
+
+        assert_eq!(manifest.files.len(), 2);
+        assert_eq!(manifest.files[0].file_name, "game1.zip");
+        assert_eq!(manifest.files[0].roms.len(), 2);
+        assert_eq!(manifest.files[1].file_name, "game2.zip");
+        assert_eq!(manifest.files[1].roms[0].name, "rom2.trom");
+    }
+}
\ No newline at end of file
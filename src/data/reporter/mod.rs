@@ -1,14 +1,28 @@
 pub mod scan_report;
-
-use std::{fs, path::{Path, PathBuf}};
-use crate::{RomsetMode, err, error::RomstIOError, filesystem::FileReader};
-
-
-use super::{models::{self, set::GameSet}, reader::DataReader};
-use anyhow::Result;
-use crossbeam::sync::WaitGroup;
-
-use scan_report::{RomLocation, ScanReport};
+pub mod manifest;
+pub mod disk_report;
+pub mod family_report;
+pub mod fix_plan;
+pub mod csv_report;
+pub mod fixdat;
+pub mod have_miss;
+pub mod identify;
+pub mod what_if;
+pub mod dat_diff;
+
+use std::{collections::HashSet, fmt::Display, fs, path::{Path, PathBuf}, str::FromStr};
+use crate::{RomsetMode, error::RomstIOError, filesystem::{FileChecks, FileReader, ReadOptions, atomic, chd::ChdHeader, hashing, image::ImageFormat, names::NameRules, remote::RemoteSource, volumes}, i18n::tr};
+
+
+use super::{models::{self, file::DataFile, game::Game, search::{GameFilter, PatternKind}, set::{GameSet, SetLayout}}, reader::{DataReader, MatchPolicy, MatchedChecks, sqlite::DBReader}};
+use rayon::prelude::*;
+use rusqlite::{Connection, OpenFlags};
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use disk_report::{DiskReport, DiskResult, DiskStatus};
+use manifest::{ManifestFile, ScanManifest};
+use scan_report::{ConvertedImage, RomLocation, ScanReport, SetReference, SetReport, Suspect};
 use tokio::sync::mpsc::{Receiver, channel};
 use log::error;
 
@@ -17,9 +31,62 @@ type RR = Option<Box<dyn ReportReporter>>;
     
 pub struct Reporter<R: DataReader> {
     data_reader: R,
-    reporter: RR
+    reporter: RR,
+    scope: ReportScope,
+    /// If set, the only sets checked and reported
+    allowed_sets: Option<HashSet<String>>,
+    scan_level: ScanLevel,
+    layout: SetLayout,
+    read_options: ReadOptions,
+    /// The differences ignored when looking for the archives that only need a rename
+    name_rules: NameRules,
+    /// If set, the files are matched in parallel, each thread reading this database with its own connection
+    parallel_db: Option<PathBuf>,
+}
+
+/// Which sets are included in a report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportScope {
+    /// Only the sets with at least one file found
+    #[default]
+    Present,
+    /// All the sets in the database, the ones without files are reported as missing
+    All
+}
+
+/// How much of the files is read when checking them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScanLevel {
+    /// Every file is hashed and matched by its checksums
+    #[default]
+    Full,
+    /// Nothing is hashed, the files match the roms with the same name and size in the set the archive is named after.
+    /// For quick passes over collections already verified.
+    Trust,
 }
 
+impl Display for ScanLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScanLevel::Full => write!(f, "{}", tr("Full")),
+            ScanLevel::Trust => write!(f, "{}", tr("Trust (matched by name and size, not hashed)")),
+        }
+    }
+}
+
+impl FromStr for ScanLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "full" => Ok(ScanLevel::Full),
+            "trust" => Ok(ScanLevel::Trust),
+            _ => Err(anyhow!("Non valid scan level, can be either `full` or `trust`"))
+        }
+    }
+}
+
+
 pub trait ReportReporter {
     fn set_total_files(&mut self, total_files: usize);
     fn update_report_new_file(&mut self, new_file: &str);
@@ -32,140 +99,589 @@ pub trait ReportReporter {
 
 enum ReportMessageContent {
     GameSetBuilt(GameSet),
-    FoundNotValid,
+    /// Not an archive, it can be a converted disc image
+    FoundNotValid(Option<ImageFormat>),
+    /// An archive with files that failed when reading them, or truncated
+    FoundCorrupt(Vec<String>),
     FoundError,
     Done
 }
 
 struct ReportMessage {
     file_name: String, 
+    file_path: PathBuf,
     content: ReportMessageContent
 }
 
 impl ReportMessage {
-    fn new(file_name: String, content: ReportMessageContent) -> Self { Self { file_name, content } }
+    fn new(file_name: String, file_path: PathBuf, content: ReportMessageContent) -> Self { Self { file_name, file_path, content } }
+}
+
+/// Hashes files without checking them against a database, to create a manifest
+pub struct Scanner {
+    reporter: RR,
+    read_options: ReadOptions,
+}
+
+impl Scanner {
+    pub fn new() -> Self { Self { reporter: None, read_options: ReadOptions::default() } }
+
+    pub fn set_read_options(&mut self, read_options: ReadOptions) {
+        self.read_options = read_options;
+    }
+
+    pub fn add_reporter<P>(&mut self, reporter: P) where P: ReportReporter + 'static {
+        self.reporter = Some(Box::new(reporter));
+    }
+
+    pub async fn scan(&mut self, file_paths: Vec<impl AsRef<Path>>) -> Result<ScanManifest> {
+        let (source_directory, file_paths) = get_files_to_check(file_paths)?;
+        // We don't know yet which database the manifest will be checked against, so we get all checksums
+        let mut rx = send_sets_from_files(&mut self.reporter, file_paths, FileChecks::ALL | FileChecks::HEADERLESS, SetLayout::Archive, self.read_options.clone()).await?;
+
+        let mut manifest = ScanManifest::new(source_directory);
+        while let Some(message) = rx.recv().await {
+            let file_name = message.file_name;
+            if !file_name.is_empty() {
+                if let Some(reporter) = self.reporter.as_mut() {
+                    reporter.update_report_new_file(file_name.as_str());
+                };
+            }
+            match message.content {
+                ReportMessageContent::GameSetBuilt(game_set) => {
+                    let size = fs::metadata(&message.file_path).map(|metadata| metadata.len()).unwrap_or_default();
+                    let path = fs::canonicalize(&message.file_path).unwrap_or(message.file_path);
+                    manifest.files.push(ManifestFile {
+                        file_name,
+                        path: path.to_string_lossy().to_string(),
+                        size,
+                        roms: game_set.roms,
+                        locked: game_set.locked,
+                        corrupt: game_set.corrupt
+                    });
+                    if let Some(reporter) = self.reporter.as_mut() {
+                        reporter.update_report_new_added_file(1);
+                    };
+                }
+                ReportMessageContent::FoundNotValid(image_format) => {
+                    match image_format {
+                        Some(format) => manifest.converted.push(ConvertedImage { file_name, format }),
+                        None => manifest.ignored.push(file_name)
+                    };
+                    if let Some(reporter) = self.reporter.as_mut() {
+                        reporter.update_report_ignored(1);
+                    };
+                }
+                ReportMessageContent::FoundCorrupt(members) => {
+                    manifest.corrupt.insert(file_name, members);
+                    if let Some(reporter) = self.reporter.as_mut() {
+                        reporter.update_report_file_error(1);
+                    };
+                }
+                ReportMessageContent::FoundError => {
+                    if let Some(reporter) = self.reporter.as_mut() {
+                        reporter.update_report_file_error(1);
+                    };
+                },
+                ReportMessageContent::Done => {
+                    break;
+                }
+            }
+        };
+
+        if let Some(reporter) = self.reporter.as_mut() {
+            reporter.finish();
+        }
+        Ok(manifest)
+    }
+
+    /// Like `scan`, but with the files of a directory in a remote machine. Each file is downloaded to a
+    /// temporary file, hashed and removed, so there is never more than one file stored locally.
+    pub fn scan_remote(&mut self, source: &RemoteSource) -> Result<ScanManifest> {
+        let file_names = source.list_files()?;
+        if let Some(reporter) = self.reporter.as_mut() {
+            reporter.set_total_files(file_names.len());
+        }
+
+        let temp_dir = std::env::temp_dir().join(format!("romst_remote_{}", std::process::id()));
+        fs::create_dir_all(&temp_dir)?;
+        let mut manifest = ScanManifest::new(Some(source.get_url("")));
+        let mut file_reader = FileReader::with_options(self.read_options.clone());
+        for file_name in file_names {
+            if let Some(reporter) = self.reporter.as_mut() {
+                reporter.update_report_new_file(file_name.as_str());
+            }
+
+            // The file keeps its name, the set name comes from it
+            let local_file = temp_dir.join(&file_name);
+            let game_set = match source.download_file(&file_name, &local_file) {
+                Ok(_) => file_reader.build_game_set(&local_file, FileChecks::ALL | FileChecks::HEADERLESS),
+                Err(e) => {
+                    error!("{}", e);
+                    Err(RomstIOError::FileNotFound(source.get_url(&file_name)))
+                }
+            };
+            let size = fs::metadata(&local_file).map(|metadata| metadata.len()).unwrap_or_default();
+            let image_format = ImageFormat::detect(&local_file);
+            let _ = fs::remove_file(&local_file);
+
+            match game_set {
+                Ok(game_set) => {
+                    manifest.files.push(ManifestFile {
+                        path: source.get_url(&file_name),
+                        file_name,
+                        size,
+                        roms: game_set.roms,
+                        locked: game_set.locked,
+                        corrupt: game_set.corrupt
+                    });
+                    if let Some(reporter) = self.reporter.as_mut() {
+                        reporter.update_report_new_added_file(1);
+                    };
+                }
+                Err(RomstIOError::NotValidFileError(_, _)) => {
+                    match image_format {
+                        Some(format) => manifest.converted.push(ConvertedImage { file_name, format }),
+                        None => manifest.ignored.push(file_name)
+                    };
+                    if let Some(reporter) = self.reporter.as_mut() {
+                        reporter.update_report_ignored(1);
+                    };
+                }
+                Err(RomstIOError::CorruptArchive(_, members)) => {
+                    manifest.corrupt.insert(file_name, members);
+                    if let Some(reporter) = self.reporter.as_mut() {
+                        reporter.update_report_file_error(1);
+                    };
+                }
+                Err(_) => {
+                    if let Some(reporter) = self.reporter.as_mut() {
+                        reporter.update_report_file_error(1);
+                    };
+                }
+            }
+        }
+        let _ = fs::remove_dir(&temp_dir);
+
+        if let Some(reporter) = self.reporter.as_mut() {
+            reporter.finish();
+        }
+        Ok(manifest)
+    }
+}
+
+impl Default for Scanner {
+    fn default() -> Self {
+        Scanner::new()
+    }
+}
+
+/// If there is only a directory, the files to check are the ones inside
+/// Looks for a CHD with the sha1 in the directories, the parent of a delta CHD is usually a disk of the parent set
+fn has_chd_with_sha1(directories: &[PathBuf], sha1: &str) -> bool {
+    directories.iter()
+        .filter_map(|directory| directory.read_dir().ok())
+        .flat_map(|entries| entries.filter_map(|entry| entry.ok().map(|entry| entry.path())))
+        .filter(|path| path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("chd")))
+        .any(|path| ChdHeader::read(&path).is_ok_and(|header| header.sha1.is_some_and(|chd_sha1| chd_sha1.eq_ignore_ascii_case(sha1))))
+}
+
+fn get_files_to_check(file_paths: Vec<impl AsRef<Path>>) -> Result<(Option<String>, Vec<PathBuf>)> {
+    if file_paths.len() == 1 {
+        if let Some(path) = file_paths.first() {
+            let p = path.as_ref();
+            if p.is_dir() {
+                let contents = p.read_dir()?.filter_map(|dir_entry| {
+                    dir_entry.ok().map(|entry| entry.path())
+                }).collect::<Vec<PathBuf>>();
+                let absolute = fs::canonicalize(p)?;
+                return Ok((Some(absolute.to_string_lossy().to_string()), contents));
+            }
+        }
+    }
+
+    Ok((None, file_paths.iter().map(|path| path.as_ref().to_path_buf()).collect()))
+}
+
+/// What was found in a file, split archives are named after the whole archive and the folders of sets end with a slash
+fn get_report_message(path: &Path, result: Result<GameSet, RomstIOError>) -> ReportMessage {
+    let file_name = match volumes::get_archive_path(&path).file_name() {
+        Some(file) if path.is_dir() => models::get_folder_file_name(&file.to_string_lossy()),
+        Some(file) => {
+            file.to_owned().into_string().unwrap_or_else(|os_string| {
+                os_string.to_string_lossy().to_string()
+            })
+        }
+        None => { "UNKNOWN FILE".to_string() }
+    };
+
+    let p = path.to_path_buf();
+    match result {
+        Ok(game_set) => ReportMessage::new(file_name, p, ReportMessageContent::GameSetBuilt(game_set)),
+        Err(RomstIOError::NotValidFileError(file_name, _file_type)) => {
+            let image_format = ImageFormat::detect(&p);
+            ReportMessage::new(file_name, p, ReportMessageContent::FoundNotValid(image_format))
+        },
+        Err(RomstIOError::CorruptArchive(_, members)) => ReportMessage::new(file_name, p, ReportMessageContent::FoundCorrupt(members)),
+        Err(e) => {
+            error!("ERROR: {}", e);
+            ReportMessage::new(file_name, p, ReportMessageContent::FoundError)
+        }
+    }
+}
+
+/// Returns a Receiver that will receive a message with the file reports. With the folder layout the directories are
+/// sets too, otherwise they are skipped
+async fn send_sets_from_files(reporter: &mut RR, file_paths: Vec<impl AsRef<Path>>, file_checks: FileChecks, layout: SetLayout, read_options: ReadOptions) -> Result<Receiver<ReportMessage>> {
+    // The rest of the volumes of a split archive are read with the first one, and the files being written are skipped
+    let file_paths = file_paths.iter()
+        .filter(|path| !volumes::is_secondary_volume(path) && !atomic::is_temp_file(path))
+        .collect::<Vec<_>>();
+    if let Some(reporter) = reporter.as_mut() {
+        reporter.set_total_files(file_paths.len());
+    }
+
+    let mut files = vec![];
+    for path in file_paths {
+        let path = path.as_ref();
+        if path.is_file() || (layout == SetLayout::Folder && path.is_dir()) {
+            files.push(path.to_path_buf());
+        } else if let Some(reporter) = reporter.as_mut() {
+            reporter.update_report_directory(1);
+        }
+    }
+
+    let (tx, receiver) = channel::<ReportMessage>(files.len() + 1);
+    // The files are hashed in a pool of threads, each set is sent as soon as it's built
+    tokio::task::spawn_blocking(move || {
+        let result = hashing::build_game_sets(&files, file_checks, &read_options, |path, result| {
+            if let Err(error) = tx.blocking_send(get_report_message(path, result)) {
+                error!("ERROR: {}", error);
+            }
+        });
+        if let Err(error) = result {
+            error!("ERROR: {}", error);
+        }
+        if let Err(error) = tx.blocking_send(ReportMessage::new("".to_string(), PathBuf::new(), ReportMessageContent::Done)) {
+            error!("ERROR: {}", error);
+        }
+    });
+
+    Ok(receiver)
 }
 
 impl<R: DataReader> Reporter<R> {
-    pub fn new(data_reader: R) -> Self { Self { data_reader, reporter: None } }
+    pub fn new(data_reader: R) -> Self { Self { data_reader, reporter: None, scope: ReportScope::default(), allowed_sets: None, scan_level: ScanLevel::default(), layout: SetLayout::default(), read_options: ReadOptions::default(), name_rules: NameRules::default(), parallel_db: None } }
+
+    pub fn set_scope(&mut self, scope: ReportScope) {
+        self.scope = scope;
+    }
+
+    pub fn set_scan_level(&mut self, scan_level: ScanLevel) {
+        self.scan_level = scan_level;
+    }
+
+    pub fn set_layout(&mut self, layout: SetLayout) {
+        self.layout = layout;
+    }
+
+    pub fn set_read_options(&mut self, read_options: ReadOptions) {
+        self.read_options = read_options;
+    }
+
+    pub fn set_name_rules(&mut self, name_rules: NameRules) {
+        self.name_rules = name_rules;
+    }
+
+    /// Matches the files against the database in parallel, the database has to be the one the data reader reads
+    pub fn match_in_parallel(&mut self, db_file: impl AsRef<Path>) {
+        self.parallel_db = Some(db_file.as_ref().to_path_buf());
+    }
+
+    pub fn set_allowed_sets(&mut self, allowed_sets: HashSet<String>) {
+        self.allowed_sets = Some(allowed_sets);
+    }
+
+    fn is_allowed(&self, set_name: &str) -> bool {
+        self.allowed_sets.as_ref().is_none_or(|allowed_sets| allowed_sets.contains(set_name))
+    }
 
     pub fn add_reporter<P>(&mut self, reporter: P) where P: ReportReporter + 'static {
         self.reporter = Some(Box::new(reporter));
     }
 
     pub async fn check(&mut self, file_paths: Vec<impl AsRef<Path>>, rom_mode: RomsetMode) -> Result<ScanReport> {
-        if file_paths.len() == 1 {
-            if let Some(path) = file_paths.get(0) {
-                let p = path.as_ref();
-                if p.is_dir() {
-                    return self.check_directory(&p.to_path_buf(), rom_mode).await
+        let (source_directory, file_paths) = get_files_to_check(file_paths)?;
+        self.check_files(source_directory, file_paths, rom_mode).await
+    }
+
+    /// Checks a single archive or directory against the given set, as if it was the file for that set
+    pub async fn verify_set<S>(&mut self, game_name: S, file_path: &impl AsRef<Path>, rom_mode: RomsetMode) -> Result<SetReport> where S: AsRef<str> {
+        // The set may be given by an old name
+        let game_name = &self.data_reader.get_game(game_name.as_ref()).map(|game| game.name).unwrap_or_else(|| game_name.as_ref().to_string());
+        // Fails early if the set doesn't exist
+        let (game, set_roms) = self.data_reader.get_romset_roms(game_name, rom_mode)?;
+
+        let file_checks = self.data_reader.get_file_checks()?.get_file_checks();
+        let mut file_reader = FileReader::with_options(self.read_options.clone());
+        let path = file_path.as_ref();
+        let file_game_set = if path.is_dir() {
+            file_reader.build_game_set_from_dir(&path, file_checks)?
+        } else {
+            file_reader.build_game_set(&path, file_checks)?
+        };
+
+        // We name the file after the set, so the roms found are considered in the right place
+        let file_name = format!("{}.zip", game_name);
+        let mut scan_report = ScanReport::new(None, rom_mode);
+        self.add_set_report(&mut scan_report, file_name, file_game_set, rom_mode, &HashSet::new()).await?;
+
+        let mut set_report = scan_report.sets.remove(game_name).unwrap_or_else(|| SetReport::new(game_name));
+        if let SetReference::FileName(_) = set_report.reference {
+            // None of the roms belong to the set
+            set_report.ref_game(game);
+            set_report.roms_missing.extend(set_roms.into_iter().map(|set_rom| set_rom.file));
+            set_report.device_dependencies.extend(self.data_reader.get_devices_for_game(game_name)?.dependencies);
+        }
+
+        Ok(set_report)
+    }
+
+    /// Looks for near matches of the unknown files in the report, they are added as suspects
+    pub fn find_suspects(&self, scan_report: &mut ScanReport) -> Result<usize> {
+        let mut set_names = scan_report.sets.keys().cloned().collect::<Vec<_>>();
+        set_names.sort();
+        let mut suspects = vec![];
+        for set_name in set_names {
+            for file in &scan_report.sets[&set_name].unknown {
+                let near_matches = self.data_reader.get_near_matches(file)?;
+                if !near_matches.is_empty() {
+                    suspects.push(Suspect { set_name: set_name.clone(), file: file.clone(), near_matches });
                 }
             }
         }
 
-        self.check_files(None::<PathBuf>, file_paths, rom_mode).await
+        let found = suspects.len();
+        scan_report.suspects = suspects;
+        Ok(found)
     }
 
-    async fn check_directory(&mut self, file_path: &impl AsRef<Path>, rom_mode: RomsetMode) -> Result<ScanReport> {
-        let path = file_path.as_ref();
-        if path.is_dir() {
-            let contents = path.read_dir()?.into_iter().filter_map(|dir_entry| {
-                if let Ok(ref entry) = dir_entry {
-                    let path = entry.path();
-                    Some(path)
-                } else {
-                    None
+    /// Checks the CHDs of a set, in a MAME like layout where the disks of each set are in a directory named like the set.
+    /// Clones can use the disks of their parents, and a CHD created as a delta needs its parent CHD to be read.
+    pub fn verify_disks<S>(&self, game_name: S, roms_directory: &impl AsRef<Path>) -> Result<DiskReport> where S: AsRef<str> {
+        let game_name = game_name.as_ref();
+        let entry = match self.data_reader.get_game_entry(game_name)? {
+            Some(entry) => entry,
+            None => return Err(anyhow::anyhow!("Game `{}` not found", game_name))
+        };
+
+        // The set directory first, then the ones of its parents
+        let mut directories = vec![roms_directory.as_ref().join(game_name)];
+        let mut parent = entry.game.clone_of.clone();
+        while let Some(parent_name) = parent {
+            directories.push(roms_directory.as_ref().join(&parent_name));
+            parent = self.data_reader.get_game(&parent_name).and_then(|game| game.clone_of);
+        }
+
+        let mut disk_report = DiskReport::new(game_name);
+        for disk in entry.disks {
+            let file_name = if disk.name.to_lowercase().ends_with(".chd") { disk.name.clone() } else { format!("{}.chd", disk.name) };
+            if disk.info.status.as_ref().is_some_and(|status| status.eq_ignore_ascii_case("nodump")) {
+                disk_report.disks.push(DiskResult { name: disk.name, path: None, status: DiskStatus::NoDump });
+                continue;
+            }
+
+            let path = match directories.iter().map(|directory| directory.join(&file_name)).find(|path| path.is_file()) {
+                Some(path) => path,
+                None => {
+                    disk_report.disks.push(DiskResult { name: disk.name, path: None, status: DiskStatus::Missing });
+                    continue;
                 }
-            }).collect::<Vec<PathBuf>>();
-            self.check_files(Some(file_path), contents, rom_mode).await
-        } else {
-            err!("Path is not a directory")
+            };
+
+            let status = match ChdHeader::read(&path) {
+                Ok(header) => {
+                    match (&header.sha1, &disk.info.sha1, header.parent_sha1) {
+                        (Some(sha1), Some(expected), _) if !sha1.eq_ignore_ascii_case(expected) => DiskStatus::WrongChecksum(sha1.clone()),
+                        (_, _, Some(parent_sha1)) if !has_chd_with_sha1(&directories, &parent_sha1) => DiskStatus::ParentMissing(parent_sha1),
+                        _ => DiskStatus::Ok
+                    }
+                }
+                Err(_) => DiskStatus::NotValid
+            };
+            disk_report.disks.push(DiskResult { name: disk.name, path: Some(path.to_string_lossy().to_string()), status });
         }
+
+        Ok(disk_report)
     }
 
-    /// Returns a Receiver that will receive a message with the file reports.
-    async fn send_sets_from_files(&mut self, file_paths: Vec<impl AsRef<Path>>) -> Result<Receiver<ReportMessage>> {
+    /// Checks the files of a manifest, created by a previous scan
+    pub async fn check_manifest(&mut self, manifest: ScanManifest, rom_mode: RomsetMode) -> Result<ScanReport> {
         if let Some(reporter) = self.reporter.as_mut() {
-            reporter.set_total_files(file_paths.len());
+            reporter.set_total_files(manifest.files.len() + manifest.ignored.len() + manifest.converted.len() + manifest.corrupt.len());
+        }
+
+        let mut scan_report = ScanReport::new(manifest.root_directory, rom_mode);
+        scan_report.match_policy = self.data_reader.get_match_policy();
+        let exclusions = self.data_reader.get_exclusions()?;
+
+        for file in manifest.files {
+            if let Some(reporter) = self.reporter.as_mut() {
+                reporter.update_report_new_file(file.file_name.as_str());
+            };
+            let game = Game::new(models::get_set_from_file(&file.file_name));
+            let mut file_game_set = GameSet::new(game, file.roms, vec![], vec![], vec![]);
+            file_game_set.locked = file.locked;
+            file_game_set.corrupt = file.corrupt;
+            match self.add_set_report(&mut scan_report, file.file_name, file_game_set, rom_mode, &exclusions).await {
+                Ok(_) => {
+                    if let Some(reporter) = self.reporter.as_mut() {
+                        reporter.update_report_new_added_file(1);
+                    };
+                }
+                Err(_) => {
+                    if let Some(reporter) = self.reporter.as_mut() {
+                        reporter.update_report_file_error(1);
+                    };
+                }
+            }
         }
 
-        let (tx, receiver) = channel::<ReportMessage>(file_paths.len());
-        let wg = WaitGroup::new();
+        for file_name in manifest.ignored {
+            scan_report.add_ignored(file_name);
+            if let Some(reporter) = self.reporter.as_mut() {
+                reporter.update_report_ignored(1);
+            };
+        }
+        for converted_image in manifest.converted {
+            scan_report.converted.push(converted_image);
+            if let Some(reporter) = self.reporter.as_mut() {
+                reporter.update_report_ignored(1);
+            };
+        }
+        for (file_name, members) in manifest.corrupt {
+            scan_report.add_corrupt(file_name, members);
+            if let Some(reporter) = self.reporter.as_mut() {
+                reporter.update_report_file_error(1);
+            };
+        }
 
-        let file_checks = self.data_reader.get_file_checks()?.get_file_checks();
+        scan_report.remove_sets(&exclusions);
+        if let Some(allowed_sets) = &self.allowed_sets {
+            scan_report.retain_sets(allowed_sets);
+        }
+        self.add_missing_sets(&mut scan_report, rom_mode, &exclusions)?;
+        self.add_disks(&mut scan_report, rom_mode, &exclusions)?;
+        self.add_rom_shares(&mut scan_report)?;
+        scan_report.find_archive_renames(self.name_rules);
+        scan_report.set_confidences();
 
-        file_paths.into_iter()
-            .for_each(|fp| {
-                let path = fp.as_ref();
-                if path.is_file() {
-                    let sender = tx.clone();
-                    let p = path.to_path_buf();
-                    let wg = wg.clone();
-
-                    tokio::spawn(async move {
-                        let file_name = match p.file_name() {
-                            Some(file) => {
-                                file.to_owned().into_string().unwrap_or_else(|os_string| {
-                                    os_string.to_string_lossy().to_string()
-                                })
-                            }
-                            None => { "UNKNOWN FILE".to_string() }
-                        };
-
-                        let mut file_reader = FileReader::new();
-                        let result = match file_reader.build_game_set(&p, file_checks) {
-                            Ok(game_set) => {
-                                sender.send(ReportMessage::new(file_name,
-                                    ReportMessageContent::GameSetBuilt(game_set))).await
-                            },
-                            Err(RomstIOError::NotValidFileError(file_name, _file_type )) => {
-                                sender.send(ReportMessage::new(file_name,
-                                    ReportMessageContent::FoundNotValid)).await
-                            },
-                            Err(e) => {
-                                error!("ERROR: {}", e);
-                                sender.send(ReportMessage::new(file_name,
-                                    ReportMessageContent::FoundError)).await
-                            }
-                        };
-
-                        if let Err(error) = result {
-                            error!("ERROR: {}", error);
-                        }
-
-                        drop(wg);
-                    });
-                } else if let Some(reporter) = self.reporter.as_mut() {
-                    reporter.update_report_directory(1);
-                };
-            });
-        let sender = tx;
-        tokio::spawn(async move {
-            wg.wait();
-            if let Err(error) = sender.send(ReportMessage::new("".to_string(), 
-            ReportMessageContent::Done)).await {
-                error!("ERROR: {}", error);
+        if let Some(reporter) = self.reporter.as_mut() {
+            reporter.finish();
+        }
+        Ok(scan_report)
+    }
+
+    /// With the `All` scope, adds to the report the sets without any file found
+    fn add_missing_sets(&self, scan_report: &mut ScanReport, rom_mode: RomsetMode, exclusions: &HashSet<String>) -> Result<()> {
+        if self.scope != ReportScope::All {
+            return Ok(());
+        }
+
+        let filter = GameFilter::new("*", PatternKind::Glob)?;
+        for game in self.data_reader.iter_games(&filter) {
+            let game = game?;
+            // In merged mode the clones are inside the parent set
+            if scan_report.sets.contains_key(&game.name) || exclusions.contains(&game.name) || !self.is_allowed(&game.name) ||
+                (matches!(rom_mode, RomsetMode::Merged) && game.clone_of.is_some()) {
+                continue;
             }
-        });
+            // Sets without roms can't be missing
+            if let Ok((_, roms)) = self.data_reader.get_romset_roms(&game.name, rom_mode) {
+                let game_name = game.name.clone();
+                scan_report.reference_with_game(game);
+                scan_report.add_missing_roms_for_set(&game_name, roms.into_iter().map(|rom| rom.file));
+            }
+        }
 
-        Ok(receiver)
+        Ok(())
     }
 
-    async fn check_files(&mut self, source_directory: Option<impl AsRef<Path>>, file_paths: Vec<impl AsRef<Path>>, rom_mode: RomsetMode) -> Result<ScanReport> {
-        let mut rx = self.send_sets_from_files(file_paths).await?;
+    /// Checks the CHDs of the sets with disks, when the roms directory is known. The sets with only their disks found
+    /// are added to the report too.
+    fn add_disks(&self, scan_report: &mut ScanReport, rom_mode: RomsetMode, exclusions: &HashSet<String>) -> Result<()> {
+        let root = match scan_report.get_root_directory() {
+            Some(root) => PathBuf::from(root),
+            None => return Ok(())
+        };
 
-        let source_dir = match source_directory {
-            Some(path) => {
-                let absolute = fs::canonicalize(path.as_ref())?;
-                Some(absolute.to_string_lossy().to_string())
+        let mut game_names = self.data_reader.get_games_with_disks()?.into_iter().collect::<Vec<_>>();
+        game_names.sort();
+        for game_name in game_names {
+            if exclusions.contains(&game_name) || !self.is_allowed(&game_name) {
+                continue;
             }
-            None => {
-                None
+            if let Some(set) = scan_report.sets.get_mut(&game_name) {
+                // The sets only named like a game are not checked
+                if matches!(set.reference, SetReference::Game(_)) {
+                    set.disks = self.verify_disks(&game_name, &root)?.disks;
+                }
+                continue;
             }
-        };
 
-        let mut scan_report = ScanReport::new(source_dir, rom_mode);
+            let game = match self.data_reader.get_game(&game_name) {
+                // In merged mode the clones are inside the parent set
+                Some(game) if !(matches!(rom_mode, RomsetMode::Merged) && game.clone_of.is_some()) => game,
+                _ => continue
+            };
+            let disks = self.verify_disks(&game_name, &root)?.disks;
+            if disks.iter().all(|disk| disk.path.is_none()) {
+                continue;
+            }
+            let roms = self.data_reader.get_romset_roms(&game_name, rom_mode).map(|(_, roms)| roms).unwrap_or_default();
+            scan_report.reference_with_game(game);
+            scan_report.add_missing_roms_for_set(&game_name, roms.into_iter().map(|rom| rom.file));
+            if let Some(set) = scan_report.sets.get_mut(&game_name) {
+                set.disks = disks;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// How many sets use each missing rom, so the ones completing more sets can be looked for first
+    fn add_rom_shares(&self, scan_report: &mut ScanReport) -> Result<()> {
+        for set in scan_report.sets.values_mut().filter(|set| matches!(set.reference, SetReference::Game(_))) {
+            for rom in &set.roms_missing {
+                if let Some(shares) = self.data_reader.get_rom_shares(&rom.info)? {
+                    set.rom_shares.insert(rom.name.clone(), shares);
+                }
+            }
+        }
 
+        Ok(())
+    }
+
+    async fn check_files(&mut self, source_directory: Option<String>, file_paths: Vec<impl AsRef<Path>>, rom_mode: RomsetMode) -> Result<ScanReport> {
+        let scan_level = self.scan_level;
+        let file_checks = match scan_level {
+            // The files are hashed without their header too only when there are skippers to find it
+            ScanLevel::Full => self.data_reader.get_file_checks()?.get_file_checks() | FileChecks::HEADERLESS,
+            ScanLevel::Trust => FileChecks::SIZE,
+        };
+        // Only the directories named like a set, or an old name of one, are read as sets
+        let file_paths = file_paths.into_iter()
+            .filter(|path| self.layout == SetLayout::Archive || !path.as_ref().is_dir() || self.is_set_folder(path.as_ref()))
+            .collect::<Vec<_>>();
+        let mut rx = send_sets_from_files(&mut self.reporter, file_paths, file_checks, self.layout, self.read_options.clone()).await?;
+
+        let mut scan_report = ScanReport::new(source_directory, rom_mode);
+        scan_report.match_policy = self.data_reader.get_match_policy();
+        scan_report.scan_level = scan_level;
+        scan_report.layout = self.layout;
+        let exclusions = self.data_reader.get_exclusions()?;
+
+        let mut game_sets = vec![];
         while let Some(message) = rx.recv().await {
             let file_name = message.file_name;
             if !file_name.eq("") {
@@ -175,25 +691,33 @@ impl<R: DataReader> Reporter<R> {
             }
             match message.content {
                 ReportMessageContent::GameSetBuilt(file_game_set) => {
-                    match self.add_set_report(&mut scan_report, file_name, file_game_set, rom_mode).await {
-                        Ok(_) => {
-                            if let Some(reporter) = self.reporter.as_mut() {
-                                reporter.update_report_new_added_file(1);
-                            };
-                        }
-                        Err(_) => {
-                            if let Some(reporter) = self.reporter.as_mut() {
-                                reporter.update_report_file_error(1);
-                            };
-                        }
+                    // A folder with only CHDs holds the disks of the set, they are checked with the rest of the disks
+                    if file_game_set.roms.is_empty() && file_name.ends_with(models::FOLDER_SUFFIX) {
+                        continue;
+                    }
+                    if self.parallel_db.is_some() {
+                        game_sets.push((file_name, file_game_set));
+                        continue;
                     }
+                    let settings = self.get_match_settings(rom_mode, scan_level, &exclusions);
+                    let result = match_file(&self.data_reader, &settings, file_name, file_game_set).map(|file_match| file_match.apply(&mut scan_report));
+                    self.report_file_result(&result);
                 }
-                ReportMessageContent::FoundNotValid => {
-                    scan_report.add_ignored(file_name);
+                ReportMessageContent::FoundNotValid(image_format) => {
+                    match image_format {
+                        Some(format) => scan_report.converted.push(ConvertedImage { file_name, format }),
+                        None => scan_report.add_ignored(file_name)
+                    };
                     if let Some(reporter) = self.reporter.as_mut() {
                         reporter.update_report_ignored(1);
                     };
                 }
+                ReportMessageContent::FoundCorrupt(members) => {
+                    scan_report.add_corrupt(file_name, members);
+                    if let Some(reporter) = self.reporter.as_mut() {
+                        reporter.update_report_file_error(1);
+                    };
+                }
                 ReportMessageContent::FoundError => {
                     if let Some(reporter) = self.reporter.as_mut() {
                         reporter.update_report_file_error(1);
@@ -205,69 +729,248 @@ impl<R: DataReader> Reporter<R> {
             }
         };
 
+        if let Some(db_file) = &self.parallel_db {
+            let settings = self.get_match_settings(rom_mode, scan_level, &exclusions);
+            let results = match_files_in_parallel(db_file, &settings, game_sets);
+            // Applied in the order the files were read, so the report is the same as matching them one by one
+            for result in results {
+                let result = result.map(|file_match| file_match.apply(&mut scan_report));
+                self.report_file_result(&result);
+            }
+        }
+
+        // Files named after an excluded set still create an entry, we remove them here
+        scan_report.remove_sets(&exclusions);
+        if let Some(allowed_sets) = &self.allowed_sets {
+            scan_report.retain_sets(allowed_sets);
+        }
+        self.add_missing_sets(&mut scan_report, rom_mode, &exclusions)?;
+        self.add_disks(&mut scan_report, rom_mode, &exclusions)?;
+        self.add_rom_shares(&mut scan_report)?;
+        scan_report.find_archive_renames(self.name_rules);
+        scan_report.set_confidences();
+
         if let Some(reporter) = self.reporter.as_mut() {
             reporter.finish();
         }
         Ok(scan_report)
     }
 
-    async fn add_set_report(&mut self, scan_report: &mut ScanReport, file_name: String, file_game_set: GameSet, rom_mode: RomsetMode) -> Result<()> {
-        // We fetch all the sets that can be get from these roms
-        let rom_search = self.data_reader.get_romsets_from_roms(file_game_set.roms, rom_mode)?;
+    async fn add_set_report(&mut self, scan_report: &mut ScanReport, file_name: String, file_game_set: GameSet, rom_mode: RomsetMode, exclusions: &HashSet<String>) -> Result<()> {
+        let settings = self.get_match_settings(rom_mode, ScanLevel::Full, exclusions);
+        match_file(&self.data_reader, &settings, file_name, file_game_set)?.apply(scan_report);
 
-        scan_report.set_in_file(&file_name);
+        Ok(())
+    }
 
-        let mut matched_file_name_with_set = false;
-        for entry in &rom_search.set_results {
-            let set_name = entry.0;
-            let roms = entry.1;
+    fn is_set_folder(&self, path: &Path) -> bool {
+        path.file_name().is_some_and(|name| self.data_reader.get_game(name.to_string_lossy().trim()).is_some())
+    }
 
-            // We fetch all roms for the set we are analyzing
-            let db_game_roms = self.data_reader.get_romset_roms(&set_name, rom_mode)?;
-            let game = db_game_roms.0;
-            let db_roms = db_game_roms.1;
+    fn get_match_settings<'a>(&'a self, rom_mode: RomsetMode, scan_level: ScanLevel, exclusions: &'a HashSet<String>) -> MatchSettings<'a> {
+        MatchSettings { rom_mode, scan_level, match_policy: self.data_reader.get_match_policy(), exclusions, allowed_sets: self.allowed_sets.as_ref() }
+    }
 
-            let deps = self.data_reader.get_devices_for_game(&game.name)?;
-            scan_report.add_dependencies(&game.name, deps.dependencies);
+    fn report_file_result<T>(&mut self, result: &Result<T>) {
+        if let Some(reporter) = self.reporter.as_mut() {
+            match result {
+                Ok(_) => reporter.update_report_new_added_file(1),
+                Err(_) => reporter.update_report_file_error(1),
+            }
+        };
+    }
+}
 
-            scan_report.reference_with_game(game);
+/// What a file adds to a report, kept apart from the report so the files can be matched in parallel
+enum ReportChange {
+    Encrypted(Vec<String>),
+    Corrupt(Vec<String>),
+    InFile,
+    Dependencies(String, Vec<String>),
+    Game(Game),
+    MatchedChecks(String, String, MatchedChecks),
+    RomFound(String, RomLocation, DataFile),
+    RomMissing(String, DataFile),
+    Spare(Vec<DataFile>),
+    Unknown(Vec<DataFile>),
+}
 
-            roms.get_roms_included().into_iter().for_each(|rom| {
-                // We look for coincidences in the database for the roms found for that set
-                db_roms.iter().for_each(|set_rom| {
-                    if rom.id == set_rom.id {
-                        let file_name_c = file_name.clone();
-                        let rom_name = rom.file.name.clone();
-                        let location = RomLocation::new(file_name_c, rom_name);
-                        scan_report.add_rom_for_set(set_name.to_owned(), location, set_rom.file.to_owned());
-                    } else {
-                        scan_report.add_missing_rom_for_set(set_name.to_owned(), set_rom.file.to_owned());
-                    };
-                });
-            });
+struct FileMatch {
+    file_name: String,
+    changes: Vec<ReportChange>,
+}
 
-            if models::does_file_belong_to_set(&file_name, set_name.as_str()) {
-                matched_file_name_with_set = true;
-                scan_report.add_roms_to_spare(rom_search.get_roms_to_spare_for_set(&set_name), &file_name);
+impl FileMatch {
+    /// Applies the changes in the order they were found
+    fn apply(self, scan_report: &mut ScanReport) {
+        let file_name = self.file_name;
+        for change in self.changes {
+            match change {
+                ReportChange::Encrypted(entries) => scan_report.add_encrypted(&file_name, entries),
+                ReportChange::Corrupt(entries) => scan_report.add_corrupt(&file_name, entries),
+                ReportChange::InFile => scan_report.set_in_file(&file_name),
+                ReportChange::Dependencies(set_name, dependencies) => scan_report.add_dependencies(set_name, dependencies),
+                ReportChange::Game(game) => scan_report.reference_with_game(game),
+                ReportChange::MatchedChecks(set_name, rom_name, checks) => scan_report.add_matched_checks(set_name, rom_name, checks),
+                ReportChange::RomFound(set_name, location, rom) => scan_report.add_rom_for_set(set_name, location, rom),
+                ReportChange::RomMissing(set_name, rom) => scan_report.add_missing_rom_for_set(set_name, rom),
+                ReportChange::Spare(roms) => scan_report.add_roms_to_spare(roms, &file_name),
+                ReportChange::Unknown(files) => scan_report.add_unknown_files(files, &file_name),
             }
-        };
+        }
+    }
+}
+
+/// What every file of a check is matched with
+struct MatchSettings<'a> {
+    rom_mode: RomsetMode,
+    scan_level: ScanLevel,
+    /// For the readers of the files matched in parallel
+    match_policy: MatchPolicy,
+    exclusions: &'a HashSet<String>,
+    /// If set, the only sets checked
+    allowed_sets: Option<&'a HashSet<String>>,
+}
+
+impl MatchSettings<'_> {
+    fn is_checked(&self, set_name: &str) -> bool {
+        !self.exclusions.contains(set_name) && self.allowed_sets.is_none_or(|allowed_sets| allowed_sets.contains(set_name))
+    }
+}
+
+/// Matches every file with its own reader, the results are in the same order as the files
+fn match_files_in_parallel(db_file: &Path, settings: &MatchSettings, game_sets: Vec<(String, GameSet)>) -> Vec<Result<FileMatch>> {
+    game_sets.into_par_iter()
+        .map_init(
+            || Connection::open_with_flags(db_file, OpenFlags::SQLITE_OPEN_READ_ONLY),
+            |conn, (file_name, file_game_set)| match conn {
+                Ok(conn) => {
+                    let mut data_reader = DBReader::from_connection(conn);
+                    data_reader.set_match_policy(settings.match_policy);
+                    match_file(&data_reader, settings, file_name, file_game_set)
+                }
+                Err(e) => Err(anyhow!("Couldn't open the database `{}`: {}", db_file.to_string_lossy(), e)),
+            })
+        .collect()
+}
+
+/// Finds the sets the files of an archive belong to, without changing the report
+fn match_file<D: DataReader>(data_reader: &D, settings: &MatchSettings, file_name: String, file_game_set: GameSet) -> Result<FileMatch> {
+    let mut changes = vec![];
+    if !file_game_set.locked.is_empty() {
+        changes.push(ReportChange::Encrypted(file_game_set.locked));
+    }
+    if !file_game_set.corrupt.is_empty() {
+        changes.push(ReportChange::Corrupt(file_game_set.corrupt));
+    }
+    match settings.scan_level {
+        ScanLevel::Full => match_by_checksums(data_reader, settings, &file_name, file_game_set.roms, &mut changes)?,
+        ScanLevel::Trust => match_by_names(data_reader, settings, &file_name, file_game_set.roms, &mut changes)?,
+    }
+
+    Ok(FileMatch { file_name, changes })
+}
+
+fn match_by_checksums<D: DataReader>(data_reader: &D, settings: &MatchSettings, file_name: &str, roms: Vec<DataFile>, changes: &mut Vec<ReportChange>) -> Result<()> {
+    // We fetch all the sets that can be get from these roms
+    let rom_search = data_reader.get_romsets_from_roms(roms, settings.rom_mode)?;
 
-        if !matched_file_name_with_set {
-            let spare = rom_search.get_searched_roms();
-            scan_report.add_roms_to_spare(spare, &file_name);
+    changes.push(ReportChange::InFile);
+
+    // An archive named after an old name of a game belongs to the game
+    let file_set = models::get_set_from_file(file_name);
+    let aliased_set = data_reader.get_game(&file_set).map(|game| game.name).filter(|game_name| *game_name != file_set);
+
+    let mut matched_file_name_with_set = false;
+    for entry in &rom_search.set_results {
+        let set_name = entry.0;
+        let roms = entry.1;
+
+        if !settings.is_checked(set_name) {
+            continue;
         }
 
-        scan_report.add_unknown_files(rom_search.unknowns, file_name);
+        // We fetch all roms for the set we are analyzing
+        let db_game_roms = data_reader.get_romset_roms(set_name, settings.rom_mode)?;
+        let game = db_game_roms.0;
+        let db_roms = db_game_roms.1;
 
-        Ok(())
+        let deps = data_reader.get_devices_for_game(&game.name)?;
+        changes.push(ReportChange::Dependencies(game.name.clone(), deps.dependencies));
+
+        changes.push(ReportChange::Game(game));
+
+        roms.get_roms_included().into_iter().for_each(|rom| {
+            // We look for coincidences in the database for the roms found for that set
+            db_roms.iter().for_each(|set_rom| {
+                if rom.id == set_rom.id {
+                    let location = RomLocation::new(file_name.to_string(), rom.file.name.clone());
+                    if let Some(checks) = rom_search.matched_checks.get(&rom.id) {
+                        changes.push(ReportChange::MatchedChecks(set_name.to_owned(), set_rom.file.name.to_owned(), *checks));
+                    }
+                    changes.push(ReportChange::RomFound(set_name.to_owned(), location, set_rom.file.to_owned()));
+                } else {
+                    changes.push(ReportChange::RomMissing(set_name.to_owned(), set_rom.file.to_owned()));
+                };
+            });
+        });
+
+        if models::does_file_belong_to_set(file_name, set_name.as_str()) || aliased_set.as_ref() == Some(set_name) {
+            matched_file_name_with_set = true;
+            changes.push(ReportChange::Spare(rom_search.get_roms_to_spare_for_set(set_name)));
+        }
+    };
+
+    if !matched_file_name_with_set {
+        changes.push(ReportChange::Spare(rom_search.get_searched_roms()));
     }
+
+    changes.push(ReportChange::Unknown(rom_search.unknowns));
+
+    Ok(())
+}
+
+/// Trusts the name of the archive and the names and sizes of its files, the files are not hashed
+fn match_by_names<D: DataReader>(data_reader: &D, settings: &MatchSettings, file_name: &str, roms: Vec<DataFile>, changes: &mut Vec<ReportChange>) -> Result<()> {
+    changes.push(ReportChange::InFile);
+
+    // The archive may be named after an old name of the game
+    let game = match data_reader.get_game(models::get_set_from_file(file_name)) {
+        Some(game) if settings.is_checked(&game.name) => game,
+        _ => {
+            changes.push(ReportChange::Unknown(roms));
+            return Ok(());
+        }
+    };
+    let set_name = game.name.clone();
+    // A set without roms of its own, like a clone in split mode, has nothing to match
+    let db_roms = data_reader.get_romset_roms(&set_name, settings.rom_mode).map(|(_, roms)| roms).unwrap_or_default();
+    let deps = data_reader.get_devices_for_game(&game.name)?;
+    changes.push(ReportChange::Dependencies(game.name.clone(), deps.dependencies));
+    changes.push(ReportChange::Game(game));
+
+    let mut files = roms;
+    for db_rom in db_roms {
+        let found = files.iter().position(|file| file.name == db_rom.file.name && file.info.size.is_some() && file.info.size == db_rom.file.info.size);
+        match found {
+            Some(index) => {
+                let file = files.remove(index);
+                changes.push(ReportChange::MatchedChecks(set_name.clone(), db_rom.file.name.clone(), MatchedChecks { size: true, name: true, ..MatchedChecks::default() }));
+                changes.push(ReportChange::RomFound(set_name.clone(), RomLocation::new(file_name.to_string(), file.name), db_rom.file));
+            }
+            None => changes.push(ReportChange::RomMissing(set_name.clone(), db_rom.file)),
+        }
+    }
+    changes.push(ReportChange::Unknown(files));
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use std::{cell::RefCell, path::Path, rc::Rc};
     use rusqlite::{Connection, OpenFlags};
-    use crate::data::{importer::DatImporter, reader::sqlite::DBReader, writer::sqlite::DBWriter};
+    use crate::data::{importer::DatImporter, models::file::{DataFile, DataFileInfo, FileType}, rebuilder::{self, DeletePolicy, RebuildMode, Rebuilder, ZipFormat}, reader::{NearMatchReason, sqlite::DBReader}, reporter::scan_report::{Confidence, SetStatus}, writer::sqlite::DBWriter};
     use super::*;
 
     fn get_db_connection<'a, 'b>(dat_path: &'b impl AsRef<Path>) -> Result<Connection> {
@@ -464,4 +1167,418 @@ mod tests {
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn trusts_names_and_sizes() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let mut reporter = Reporter::new(DBReader::from_connection(&conn));
+        reporter.set_scan_level(ScanLevel::Trust);
+
+        let game_path = Path::new("testdata").join("wrong");
+        let report = reporter.check(vec![ &game_path ], RomsetMode::Split).await?;
+
+        assert_eq!(report.scan_level, ScanLevel::Trust);
+        tests::assert_file_report(&report, "game1.zip", "game1", 3, 1, 0, 0, 0, 0);
+        tests::assert_file_report(&report, "game3.zip", "game3", 3, 0, 0, 0, 0, 1);
+        // The rom with the wrong name can't be found without hashing it
+        assert_eq!(report.sets["game2"].roms_missing.len(), 1);
+        assert!(report.sets["game1"].matched_checks.values().all(|checks| checks.is_unhashed() && checks.name));
+        assert_eq!(Some(Confidence::NameSize), report.sets["game1"].confidence);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn matches_in_parallel() -> Result<()> {
+        let db_file = std::env::temp_dir().join(format!("romst_parallel_{}.rst", std::process::id()));
+        let _ = fs::remove_file(&db_file);
+        let mut conn = Connection::open(&db_file)?;
+        DatImporter::from_path(&Path::new("testdata").join("test.dat"), DBWriter::from_connection(&mut conn, 100))?.load_dat()?;
+
+        let game_path = Path::new("testdata").join("wrong");
+        let sequential = Reporter::new(DBReader::from_connection(&conn)).check(vec![ &game_path ], RomsetMode::Split).await?;
+        let mut reporter = Reporter::new(DBReader::from_connection(&conn));
+        reporter.match_in_parallel(&db_file);
+        let parallel = reporter.check(vec![ &game_path ], RomsetMode::Split).await?;
+        drop(conn);
+        fs::remove_file(&db_file)?;
+
+        tests::assert_file_report(&parallel, "game1.zip", "game1", 3, 1, 0, 0, 0, 0);
+        tests::assert_file_report(&parallel, "game2.zip", "game2", 2, 0, 1, 1, 0, 0);
+        tests::assert_file_report(&parallel, "game3.zip", "game3", 3, 0, 0, 0, 0, 1);
+        assert_eq!(parallel.sets.len(), sequential.sets.len());
+        for (set_name, set) in &sequential.sets {
+            let parallel_set = &parallel.sets[set_name];
+            assert_eq!(parallel_set.roms_available, set.roms_available);
+            assert_eq!(parallel_set.roms_missing, set.roms_missing);
+            assert_eq!(parallel_set.roms_to_spare, set.roms_to_spare);
+            assert_eq!(parallel_set.matched_checks, set.matched_checks);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn ignores_excluded_sets() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let mut conn = get_db_connection(&path)?;
+        DBWriter::from_connection(&mut conn, 100).add_exclusions(&["game1", "game2"])?;
+        let data_reader = DBReader::from_connection(&conn);
+
+        let mut reporter = Reporter::new(data_reader);
+
+        let game_path = Path::new("testdata").join("split");
+        let report = reporter.check(vec![ game_path ], RomsetMode::Merged).await?;
+
+        assert_eq!(report.sets.len(), 5);
+        assert!(report.sets.get("game1").is_none());
+        assert!(report.sets.get("game2").is_none());
+        tests::assert_file_report(&report, "game3.zip", "game3", 3, 0, 0, 0, 0, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn verifies_a_single_set() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+        let mut reporter = Reporter::new(data_reader);
+
+        let game_path = Path::new("testdata").join("split").join("game2.zip");
+        let set_report = reporter.verify_set("game2", &game_path, RomsetMode::Split).await?;
+        assert_eq!(set_report.reference.get_name(), "game2");
+        assert!(set_report.in_file);
+        assert_eq!(set_report.roms_available.len(), 3);
+        assert!(set_report.roms_missing.is_empty());
+
+        // The file has nothing to do with the set
+        let set_report = reporter.verify_set("game5", &game_path, RomsetMode::Split).await?;
+        assert_eq!(set_report.reference.get_name(), "game5");
+        assert!(set_report.roms_available.is_empty());
+        assert_eq!(set_report.roms_missing.len(), 3);
+
+        assert!(reporter.verify_set("nogame", &game_path, RomsetMode::Split).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn checks_a_scan_manifest() -> Result<()> {
+        let game_path = Path::new("testdata").join("split");
+        let mut scanner = Scanner::new();
+        let manifest = scanner.scan(vec![ &game_path ]).await?;
+        assert_eq!(manifest.files.len(), 7);
+        assert!(manifest.root_directory.is_some());
+
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+        let mut reporter = Reporter::new(data_reader);
+        let report = reporter.check_manifest(manifest, RomsetMode::Merged).await?;
+
+        assert_eq!(report.sets.len(), 7);
+        tests::assert_file_report(&report, "game1.zip", "game1", 4, 0, 2, 0, 0, 0);
+        tests::assert_file_report(&report, "game2.zip", "game2", 3, 0, 0, 1, 0, 0);
+        tests::assert_file_report(&report, "game5.zip", "game5", 3, 0, 0, 0, 0, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_the_checks_matched() -> Result<()> {
+        let game_path = Path::new("testdata").join("split");
+        let mut scanner = Scanner::new();
+        let mut manifest = scanner.scan(vec![ &game_path ]).await?;
+        // Like the roms of a sfv file, only the crc and the size are known
+        for file in manifest.files.iter_mut().filter(|file| file.file_name == "game2.zip") {
+            for rom in file.roms.iter_mut() {
+                rom.info.sha1 = None;
+                rom.info.md5 = None;
+            }
+        }
+
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+        let mut reporter = Reporter::new(data_reader);
+        let report = reporter.check_manifest(manifest, RomsetMode::Split).await?;
+
+        let game1 = &report.sets["game1"];
+        assert!(!game1.matched_checks.is_empty());
+        assert!(game1.matched_checks.values().all(|checks| checks.sha1 && !checks.is_weak()));
+        assert_eq!(Some(Confidence::Sha1), game1.confidence);
+        let game2 = &report.sets["game2"];
+        assert!(!game2.matched_checks.is_empty());
+        assert!(game2.matched_checks.values().all(|checks| checks.is_weak() && checks.crc && checks.size));
+        assert!(game2.to_string().contains("[weak match: crc, size]"));
+        assert_eq!(Some(Confidence::CrcSize), game2.confidence);
+        assert!(game2.to_string().contains("Verified by: CRC and size"));
+        assert!(report.sets.values().filter(|set| set.roms_available.is_empty()).all(|set| set.confidence.is_none()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_corrupt_archives() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+        let mut reporter = Reporter::new(data_reader);
+
+        let dir = std::env::temp_dir().join(format!("romst_corrupt_report_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        std::fs::copy(Path::new("testdata").join("split").join("game2.zip"), dir.join("game2.zip"))?;
+        let data = std::fs::read(Path::new("testdata").join("split").join("game1.zip"))?;
+        std::fs::write(dir.join("game1.zip"), &data[..data.len() / 2])?;
+
+        let report = reporter.check(vec![ &dir ], RomsetMode::Split).await?;
+        std::fs::remove_dir_all(&dir)?;
+
+        assert_eq!(report.corrupt.len(), 1);
+        assert!(report.corrupt["game1.zip"].contains(&"rom1.trom".to_string()));
+        assert!(report.ignored.is_empty());
+        assert!(!report.sets.contains_key("game1"));
+        assert!(report.sets.contains_key("game2"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn finds_suspect_files() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+        let reporter = Reporter::new(data_reader);
+
+        let mut overdump = DataFileInfo::new(FileType::Rom);
+        overdump.crc = Some("1d460eee".to_string());
+        overdump.size = Some(2048);
+        overdump.sha1 = Some("0000000000000000000000000000000000000000".to_string());
+        let mut bad_dump = DataFileInfo::new(FileType::Rom);
+        bad_dump.crc = Some("00000000".to_string());
+        bad_dump.size = Some(2048);
+        let mut other = DataFileInfo::new(FileType::Rom);
+        other.crc = Some("00000000".to_string());
+        other.size = Some(2048);
+
+        let mut report = ScanReport::new(None, RomsetMode::Split);
+        report.add_unknown_files(vec![
+            DataFile::new("renamed.bin", overdump),
+            DataFile::new("rom4.trom", bad_dump),
+            DataFile::new("nothing.bin", other)
+        ], "mixed.zip");
+        assert_eq!(reporter.find_suspects(&mut report)?, 2);
+
+        let same_crc = report.suspects.iter().find(|suspect| suspect.file.name == "renamed.bin").unwrap();
+        assert_eq!(same_crc.set_name, "mixed");
+        assert!(same_crc.near_matches.iter().any(|near_match| near_match.game_name == "game1" && near_match.rom.name == "rom1.trom" && near_match.reason == NearMatchReason::SameCrcAndSize));
+        let same_name = report.suspects.iter().find(|suspect| suspect.file.name == "rom4.trom").unwrap();
+        assert!(same_name.near_matches.iter().all(|near_match| near_match.rom.name == "rom4.trom" && near_match.reason == NearMatchReason::SameName));
+        assert!(report.into_grouped().to_string().contains("== Suspect (2) =="));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_only_allowed_sets() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+        let mut reporter = Reporter::new(data_reader);
+        reporter.set_scope(ReportScope::All);
+        reporter.set_allowed_sets(vec!["game1".to_string(), "game3".to_string()].into_iter().collect());
+
+        let game_path = Path::new("testdata").join("single");
+        let report = reporter.check(vec![ &game_path ], RomsetMode::Split).await?;
+
+        let mut set_names = report.sets.keys().cloned().collect::<Vec<_>>();
+        set_names.sort();
+        assert_eq!(set_names, vec!["game1".to_string(), "game3".to_string()]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_all_sets_with_all_scope() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+        let mut reporter = Reporter::new(data_reader);
+        reporter.set_scope(ReportScope::All);
+
+        let game_path = Path::new("testdata").join("split").join("game2.zip");
+        let report = reporter.check(vec![ game_path ], RomsetMode::Split).await?;
+
+        assert_eq!(report.sets.len(), 8);
+        tests::assert_file_report(&report, "game2.zip", "game2", 3, 0, 0, 1, 0, 0);
+        tests::assert_file_report(&report, "game5.zip", "game5", 0, 3, 0, 0, 0, 0);
+
+        Ok(())
+    }
+
+    fn write_chd(path: &impl AsRef<Path>, sha1: &str, parent_sha1: Option<&str>) -> Result<()> {
+        let to_bytes = |hex: &str| (0..40).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect::<Vec<_>>();
+        let mut header = vec![0u8; 124];
+        header[..8].copy_from_slice(b"MComprHD");
+        header[8..12].copy_from_slice(&124u32.to_be_bytes());
+        header[12..16].copy_from_slice(&5u32.to_be_bytes());
+        header[84..104].copy_from_slice(&to_bytes(sha1));
+        if let Some(parent_sha1) = parent_sha1 {
+            header[104..124].copy_from_slice(&to_bytes(parent_sha1));
+        }
+        fs::write(path, header)?;
+        Ok(())
+    }
+
+    #[test]
+    fn verifies_disks_with_parent_chds() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+        let reporter = Reporter::new(data_reader);
+
+        let dir = std::env::temp_dir().join(format!("romst_chd_{}", std::process::id()));
+        fs::create_dir_all(dir.join("game5"))?;
+        let parent_sha1 = "1111111111111111111111111111111111111111";
+
+        let report = reporter.verify_disks("game5", &dir)?;
+        assert_eq!(report.disks[0].status, DiskStatus::Missing);
+
+        write_chd(&dir.join("game5").join("gm5-001.chd"), "0f8eb9bb79efdc84dfdb46e2a1c123dd5a7dd221", Some(parent_sha1))?;
+        let report = reporter.verify_disks("game5", &dir)?;
+        assert_eq!(report.disks[0].status, DiskStatus::ParentMissing(parent_sha1.to_string()));
+        assert!(!report.is_complete());
+
+        write_chd(&dir.join("game5").join("parent.chd"), parent_sha1, None)?;
+        let report = reporter.verify_disks("game5", &dir)?;
+        assert_eq!(report.disks[0].status, DiskStatus::Ok);
+        assert!(report.is_complete());
+
+        write_chd(&dir.join("game5").join("gm5-001.chd"), parent_sha1, None)?;
+        let report = reporter.verify_disks("game5", &dir)?;
+        assert_eq!(report.disks[0].status, DiskStatus::WrongChecksum(parent_sha1.to_string()));
+
+        let report = reporter.verify_disks("game0", &dir)?;
+        assert_eq!(report.disks[0].status, DiskStatus::NoDump);
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn counts_the_sets_using_the_missing_roms() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+        let mut reporter = Reporter::new(data_reader);
+        reporter.set_scope(ReportScope::All);
+
+        let dir = std::env::temp_dir().join(format!("romst_rom_shares_{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let report = reporter.check(vec![ &dir ], RomsetMode::NonMerged).await?;
+        fs::remove_dir_all(&dir)?;
+
+        let game1a = &report.sets["game1a"];
+        assert_eq!(game1a.rom_shares["rom1.trom"], 2);
+        assert_eq!(game1a.rom_shares["rom4.trom"], 1);
+        let missing = game1a.get_missing_by_shares();
+        assert_eq!(missing[0].1, Some(2));
+        assert_eq!(missing.last().map(|(_, shares)| *shares), Some(Some(1)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn checks_the_disks_of_the_sets() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let data_reader = DBReader::from_connection(&conn);
+        let mut reporter = Reporter::new(data_reader);
+
+        let dir = std::env::temp_dir().join(format!("romst_chd_report_{}", std::process::id()));
+        fs::create_dir_all(dir.join("game5"))?;
+        std::fs::copy(Path::new("testdata").join("split").join("game2.zip"), dir.join("game2.zip"))?;
+
+        let report = reporter.check(vec![ &dir ], RomsetMode::Split).await?;
+        assert!(!report.sets.contains_key("game5"));
+
+        write_chd(&dir.join("game5").join("gm5-001.chd"), "0f8eb9bb79efdc84dfdb46e2a1c123dd5a7dd221", None)?;
+        let report = reporter.check(vec![ &dir ], RomsetMode::Split).await?;
+        let set = &report.sets["game5"];
+        assert_eq!(set.disks[0].status, DiskStatus::Ok);
+        assert_eq!(set.is_complete(), SetStatus::INCOMPLETE);
+        assert!(report.sets["game2"].disks.is_empty());
+
+        write_chd(&dir.join("game5").join("gm5-001.chd"), "1111111111111111111111111111111111111111", None)?;
+        let report = reporter.check(vec![ &dir ], RomsetMode::Split).await?;
+        assert_eq!(report.sets["game5"].disks[0].status, DiskStatus::WrongChecksum("1111111111111111111111111111111111111111".to_string()));
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn checks_the_sets_in_folders() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let dir = std::env::temp_dir().join(format!("romst_folder_report_{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+
+        let mut rebuilder = Rebuilder::new(DBReader::from_connection(&conn), RomsetMode::Split);
+        rebuilder.set_layout(SetLayout::Folder);
+        let source = Path::new("testdata").join("split").join("game2.zip");
+        let (mut sets, _) = rebuilder.plan(&source, &dir)?;
+        rebuilder::rebuild(&mut sets, RebuildMode::Copy, SetLayout::Folder, ZipFormat::Standard, &ReadOptions::default(), true, &DeletePolicy::Delete);
+        assert!(sets[0].verified);
+        assert_eq!(dir.join("game2"), sets[0].archive);
+        assert!(sets[0].error.is_none());
+        fs::create_dir_all(dir.join("notaset"))?;
+        fs::write(dir.join("notaset").join("file.bin"), "not a rom")?;
+
+        let mut reporter = Reporter::new(DBReader::from_connection(&conn));
+        let report = reporter.check(vec![ &dir ], RomsetMode::Split).await?;
+        assert!(report.sets.is_empty());
+
+        reporter.set_layout(SetLayout::Folder);
+        let report = reporter.check(vec![ &dir ], RomsetMode::Split).await?;
+        assert_eq!(SetLayout::Folder, report.layout);
+        assert_eq!(1, report.sets.len());
+        let set = &report.sets["game2"];
+        assert_eq!(Some("game2/"), set.file_name.as_deref());
+        assert_eq!(SetStatus::COMPLETE, set.is_complete());
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn finds_the_archives_to_rename() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let conn = get_db_connection(&path)?;
+        let dir = std::env::temp_dir().join(format!("romst_archive_renames_{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        fs::copy(Path::new("testdata").join("split").join("game2.zip"), dir.join("game2 (backup).zip"))?;
+
+        let mut reporter = Reporter::new(DBReader::from_connection(&conn));
+        let report = reporter.check(vec![ &dir ], RomsetMode::Split).await?;
+        assert_eq!(1, report.renames.len());
+        assert_eq!("game2 (backup).zip", report.renames[0].file_name);
+        assert_eq!("game2.zip", report.renames[0].get_new_file_name());
+        assert!(!report.renames[0].same_name);
+        assert_eq!(SetStatus::FIXEABLE, report.sets["game2"].is_complete());
+
+        fs::rename(dir.join("game2 (backup).zip"), dir.join("GAME2_.zip"))?;
+        let report = reporter.check(vec![ &dir ], RomsetMode::Split).await?;
+        assert_eq!(1, report.renames.len());
+        assert!(report.renames[0].same_name);
+
+        fs::copy(Path::new("testdata").join("split").join("game2.zip"), dir.join("game2.zip"))?;
+        let report = reporter.check(vec![ &dir ], RomsetMode::Split).await?;
+        assert!(report.renames.is_empty());
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::{HashMap, HashSet, hash_map::Entry}, fmt::Display};
+use std::{collections::{BTreeMap, HashMap, HashSet, hash_map::Entry}, fmt::Display, path::Path, str::FromStr};
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use anyhow::Result;
@@ -6,53 +6,338 @@ use anyhow::anyhow;
 
 use log::debug;
 
-use crate::{RomsetMode, data::models::{self, file::DataFile, game::Game}};
+use crate::{RomsetMode, i18n::tr, data::{models::{self, file::DataFile, game::Game, set::SetLayout}, reader::{MatchPolicy, MatchedChecks, NearMatch}, reporter::{ScanLevel, disk_report::DiskResult, identify::Identification}}, filesystem::{image::ImageFormat, names::NameRules}};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ScanReport {
     root_directory: Option<String>,
     date_time: String,
     rom_mode: RomsetMode,
+    /// The checksums that had to agree for a file to match a rom
+    #[serde(default)]
+    pub match_policy: MatchPolicy,
+    /// With the trust level the files were matched by name and size, without hashing them
+    #[serde(default)]
+    pub scan_level: ScanLevel,
+    /// With the folder layout the directories named like a set were checked as that set
+    #[serde(default)]
+    pub layout: SetLayout,
     pub sets: HashMap<String, SetReport>,
     pub ignored: Vec<String>,
+    pub converted: Vec<ConvertedImage>,
+    /// Files inside encrypted archives that couldn't be verified, by archive
+    pub encrypted: BTreeMap<String, Vec<String>>,
+    /// Archives that failed when reading them, with the files that couldn't be read
+    pub corrupt: BTreeMap<String, Vec<String>>,
+    /// Unknown files that almost match a rom, likely bad dumps or overdumps
+    #[serde(default)]
+    pub suspects: Vec<Suspect>,
+    /// Unknown files with the DAT they probably belong to
+    #[serde(default)]
+    pub identified: Vec<Identification>,
+    /// Archives not named like a set that have exactly the roms of one
+    #[serde(default)]
+    pub renames: Vec<ArchiveRename>,
+}
+
+/// An archive not named like any set, with all the roms of a set and nothing else, it only needs the name of the set
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveRename {
+    pub file_name: String,
+    pub set_name: String,
+    /// The archive is already named like the set but for the differences ignored by the name rules, like the accents
+    #[serde(default)]
+    pub same_name: bool,
+}
+
+impl ArchiveRename {
+    /// The archive named after the set, in the same directory and with the same extension
+    pub fn get_new_file_name(&self) -> String {
+        let path = Path::new(&self.file_name);
+        let new_name = match path.extension() {
+            Some(extension) => format!("{}.{}", self.set_name, extension.to_string_lossy()),
+            None => self.set_name.to_owned(),
+        };
+        path.with_file_name(new_name).to_string_lossy().to_string()
+    }
+}
+
+impl Display for ArchiveRename {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.same_name {
+            true => writeln!(f, "- {} {} {}", self.file_name, tr("only differs in spelling from"), self.get_new_file_name()),
+            false => writeln!(f, "- {} {} {}", self.file_name, tr("should be renamed to"), self.get_new_file_name())
+        }
+    }
+}
+
+/// A file that didn't match any rom, with the roms it almost matches
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Suspect {
+    pub set_name: String,
+    pub file: DataFile,
+    pub near_matches: Vec<NearMatch>,
+}
+
+impl Display for Suspect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "- {} in {}", self.file.name, self.set_name)?;
+        for near_match in &self.near_matches {
+            writeln!(f, "   - {}", near_match)?;
+        }
+        Ok(())
+    }
+}
+
+/// A disc image in a converted or scrubbed format, its checksums can't match the ones of the original dump
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConvertedImage {
+    pub file_name: String,
+    pub format: ImageFormat,
+}
+
+impl Display for ConvertedImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} [{}]: {}", self.file_name, self.format, tr("converted image (cannot verify as-is)"))
+    }
+}
+
+/// How much of a report is printed: only the totals, a line with the counters of each set, or every rom
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    #[default]
+    Verbose,
+}
+
+impl FromStr for Verbosity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "quiet" => Ok(Verbosity::Quiet),
+            "normal" => Ok(Verbosity::Normal),
+            "verbose" => Ok(Verbosity::Verbose),
+            _ => Err(anyhow!("Non valid verbosity, can be either `quiet`, `normal` or `verbose`"))
+        }
+    }
+}
+
+/// A report printed with the detail of a verbosity level, serialized as the report itself
+#[derive(Debug)]
+pub struct ReportView<T> {
+    report: T,
+    verbosity: Verbosity,
+}
+
+impl<T> ReportView<T> {
+    pub fn new(report: T, verbosity: Verbosity) -> Self { Self { report, verbosity } }
+}
+
+impl<T: Serialize> Serialize for ReportView<T> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        self.report.serialize(serializer)
+    }
+}
+
+impl Display for ReportView<ScanReport> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.report.write_text(f, self.verbosity)
+    }
+}
+
+impl Display for ReportView<GroupedScanReport> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.report.write_text(f, self.verbosity)
+    }
+}
+
+/// One line per set with the roms in each state, in aligned columns
+fn write_set_table(f: &mut std::fmt::Formatter<'_>, sets: &[&SetReport]) -> std::fmt::Result {
+    let headers = ["Set", "Status", "Have", "Missing", "Rename", "Elsewhere", "Spare", "Unknown"].iter().map(|header| tr(header)).collect::<Vec<_>>();
+    // Each column is as wide as its header, with room for the longest status and the counts
+    let widths = headers.iter().zip([0, 10, 5, 7, 6, 9, 5, 7].iter()).map(|(header, min)| header.chars().count().max(*min)).collect::<Vec<_>>();
+    let name_width = sets.iter().map(|set| set.reference.get_name().chars().count()).max().unwrap_or_default().max(widths[0]);
+    let status_width = widths[1];
+    writeln!(f, "{:name_width$}  {:status_width$}  {:>w2$}  {:>w3$}  {:>w4$}  {:>w5$}  {:>w6$}  {:>w7$}",
+        headers[0], headers[1], headers[2], headers[3], headers[4], headers[5], headers[6], headers[7],
+        name_width = name_width, status_width = status_width, w2 = widths[2], w3 = widths[3], w4 = widths[4], w5 = widths[5], w6 = widths[6], w7 = widths[7])?;
+    for set in sets {
+        let counters = set.get_counters();
+        writeln!(f, "{:name_width$}  {:status_width$}  {:>w2$}  {:>w3$}  {:>w4$}  {:>w5$}  {:>w6$}  {:>w7$}",
+            set.reference.get_name(), set.is_complete().to_string(), counters.have, counters.missing, counters.rename, counters.elsewhere, counters.spare, counters.unknown,
+            name_width = name_width, status_width = status_width, w2 = widths[2], w3 = widths[3], w4 = widths[4], w5 = widths[5], w6 = widths[6], w7 = widths[7])?;
+    }
+    Ok(())
 }
 
 impl Display for ScanReport {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_text(f, Verbosity::Verbose)
+    }
+}
+
+impl ScanReport {
+    fn write_text(&self, f: &mut std::fmt::Formatter<'_>, verbosity: Verbosity) -> std::fmt::Result {
         if let Some(path) = &self.root_directory {
-            writeln!(f, "Scanned dir: {}", path)?;
+            writeln!(f, "{}: {}", tr("Scanned dir"), path)?;
+        }
+        writeln!(f, "{}: {}", tr("Date of the report"), self.date_time)?;
+        writeln!(f, "{}: {}", tr("Mode"), self.rom_mode)?;
+        writeln!(f, "{}: {}", tr("Match policy"), self.match_policy)?;
+        if self.scan_level == ScanLevel::Trust {
+            writeln!(f, "{}: {}", tr("Scan level"), self.scan_level)?;
+        }
+        if self.layout == SetLayout::Folder {
+            writeln!(f, "{}: {}", tr("Layout"), self.layout)?;
+        }
+        if verbosity == Verbosity::Quiet {
+            let mut statuses = [(SetStatus::COMPLETE, 0), (SetStatus::FIXEABLE, 0), (SetStatus::INCOMPLETE, 0), (SetStatus::MISSING, 0)];
+            for set in self.sets.values() {
+                let status = set.is_complete();
+                statuses.iter_mut().filter(|(counted, _)| *counted == status).for_each(|(_, count)| *count += 1);
+            }
+            let statuses = statuses.iter().map(|(status, count)| format!("{}: {}", status, count)).collect::<Vec<_>>();
+            let others = [("Converted", self.converted.len()), ("Encrypted", self.encrypted.len()), ("Corrupt", self.corrupt.len()), ("Suspect", self.suspects.len()),
+                ("Identified", self.identified.len()), ("Ignored", self.ignored.len())];
+            let others = others.iter().map(|(label, count)| format!("{}: {}", tr(label), count)).collect::<Vec<_>>();
+            return writeln!(f, "{}: {}, {}, {}", tr("Sets"), self.sets.len(), statuses.join(", "), others.join(", "));
         }
-        writeln!(f, "Date of the report: {}", self.date_time)?;
-        writeln!(f, "Mode: {}", self.rom_mode)?;
         writeln!(f)?;
         if !self.ignored.is_empty() {
-            writeln!(f, "Ignored:")?;
+            writeln!(f, "{}:", tr("Ignored"))?;
             for file in &self.ignored {
                 writeln!(f, "- {}", file)?;
             }
             writeln!(f)?;
         }
+        if !self.converted.is_empty() {
+            writeln!(f, "{}:", tr("Converted images"))?;
+            for converted_image in &self.converted {
+                writeln!(f, "- {}", converted_image)?;
+            }
+            writeln!(f)?;
+        }
+        if !self.encrypted.is_empty() {
+            writeln!(f, "{}:", tr("Encrypted, couldn't verify"))?;
+            for (file_name, entries) in &self.encrypted {
+                writeln!(f, "- {}: {}", file_name, entries.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        if !self.corrupt.is_empty() {
+            writeln!(f, "{}:", tr("Corrupt archives"))?;
+            for (file_name, entries) in &self.corrupt {
+                writeln!(f, "- {}: {}", file_name, entries.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        if !self.suspects.is_empty() {
+            writeln!(f, "{}:", tr("Suspect (near matches)"))?;
+            for suspect in &self.suspects {
+                write!(f, "{}", suspect)?;
+            }
+            writeln!(f)?;
+        }
+        if !self.identified.is_empty() {
+            writeln!(f, "{}:", tr("Identified"))?;
+            for identification in &self.identified {
+                write!(f, "{}", identification)?;
+            }
+            writeln!(f)?;
+        }
+        if !self.renames.is_empty() {
+            writeln!(f, "{}:", tr("Archives to rename"))?;
+            for rename in &self.renames {
+                write!(f, "{}", rename)?;
+            }
+            writeln!(f)?;
+        }
 
+        if verbosity == Verbosity::Normal {
+            let mut sets = self.sets.values().collect::<Vec<_>>();
+            sets.sort_by(|a, b| a.reference.get_name().cmp(b.reference.get_name()));
+            return write_set_table(f, &sets);
+        }
         for set in &self.sets {
             let s = set.1; 
             writeln!(f, "{}", s)?;
         }
         Ok(())
     }
-}
 
-impl ScanReport {
+    /// Groups the sets of the report by how complete they are
+    pub fn into_grouped(self) -> GroupedScanReport {
+        let mut grouped = GroupedScanReport {
+            root_directory: self.root_directory,
+            date_time: self.date_time,
+            rom_mode: self.rom_mode,
+            match_policy: self.match_policy,
+            scan_level: self.scan_level,
+            layout: self.layout,
+            complete: vec![],
+            fixable: vec![],
+            incomplete: vec![],
+            missing: vec![],
+            not_in_db: vec![],
+            ignored: self.ignored,
+            converted: self.converted,
+            encrypted: self.encrypted,
+            corrupt: self.corrupt,
+            suspects: self.suspects,
+            identified: self.identified,
+            renames: self.renames
+        };
+
+        for (_, set) in self.sets {
+            if let SetReference::FileName(_) = set.reference {
+                grouped.not_in_db.push(set);
+                continue;
+            }
+            match set.is_complete() {
+                SetStatus::COMPLETE => grouped.complete.push(set),
+                SetStatus::FIXEABLE => grouped.fixable.push(set),
+                SetStatus::INCOMPLETE => grouped.incomplete.push(set),
+                SetStatus::MISSING => grouped.missing.push(set),
+            }
+        }
+        for group in [&mut grouped.complete, &mut grouped.fixable, &mut grouped.incomplete, &mut grouped.missing, &mut grouped.not_in_db].iter_mut() {
+            group.sort_by(|a, b| a.reference.get_name().cmp(b.reference.get_name()));
+        }
+
+        grouped
+    }
+
     pub fn new(root_directory: Option<String>, rom_mode: RomsetMode) -> Self {
         let now = Utc::now();
         
         Self {
             root_directory,
             date_time: now.to_rfc3339(),
-            rom_mode, sets: HashMap::new(),
-            ignored: vec![]
+            rom_mode,
+            match_policy: MatchPolicy::default(),
+            scan_level: ScanLevel::default(),
+            layout: SetLayout::default(),
+            sets: HashMap::new(),
+            ignored: vec![],
+            converted: vec![],
+            encrypted: BTreeMap::new(),
+            corrupt: BTreeMap::new(),
+            suspects: vec![],
+            identified: vec![],
+            renames: vec![]
         }
     }
 
+    pub fn get_root_directory(&self) -> Option<&str> {
+        self.root_directory.as_deref()
+    }
+
+    pub fn get_rom_mode(&self) -> RomsetMode {
+        self.rom_mode
+    }
+
     pub fn get_date_time(&self) -> Result<DateTime<Utc>> {
         DateTime::parse_from_rfc3339(&self.date_time)
             .map_err(|e| anyhow!(e))
@@ -63,6 +348,14 @@ impl ScanReport {
         self.ignored.push(file.into());
     }
 
+    pub fn add_encrypted<S>(&mut self, file: S, entries: Vec<String>) where S: Into<String> {
+        self.encrypted.entry(file.into()).or_default().extend(entries);
+    }
+
+    pub fn add_corrupt<S>(&mut self, file: S, entries: Vec<String>) where S: Into<String> {
+        self.corrupt.entry(file.into()).or_default().extend(entries);
+    }
+
     pub fn add_rom_for_set<S>(&mut self, set_name: S, location: RomLocation, rom: DataFile) where S: AsRef<str> {
         let set = self.sets.entry(set_name.as_ref().to_owned()).or_insert_with(|| SetReport::new(set_name.as_ref()));
         match &rom.status {
@@ -75,6 +368,15 @@ impl ScanReport {
         }
     }
 
+    /// Keeps the weakest checks found for the rom, if it is in more than one file
+    pub fn add_matched_checks<S>(&mut self, set_name: S, rom_name: S, checks: MatchedChecks) where S: AsRef<str> {
+        let set = self.sets.entry(set_name.as_ref().to_owned()).or_insert_with(|| SetReport::new(set_name.as_ref()));
+        match set.matched_checks.get(rom_name.as_ref()) {
+            Some(previous) if previous.is_weak() || !checks.is_weak() => {}
+            _ => { set.matched_checks.insert(rom_name.as_ref().to_owned(), checks); }
+        }
+    }
+
     pub fn add_missing_roms_for_set<I, S>(&mut self, set_name: S, roms: I) where I: IntoIterator<Item = DataFile>, S: AsRef<str> {
         let set = self.sets.entry(set_name.as_ref().to_owned()).or_insert_with(|| SetReport::new(set_name.as_ref()));
         roms.into_iter().for_each(|rom| {
@@ -103,7 +405,9 @@ impl ScanReport {
 
     pub fn set_in_file<S>(&mut self, source_file: S) where S: AsRef<str> {
         let set_name = models::get_set_from_file(source_file.as_ref());
-        self.sets.entry(set_name.clone()).or_insert_with(|| SetReport::new(set_name)).in_file = true;
+        let set = self.sets.entry(set_name.clone()).or_insert_with(|| SetReport::new(set_name));
+        set.in_file = true;
+        set.file_name = Some(source_file.as_ref().to_string());
     }
 
     pub fn add_unknown_files<I, S>(&mut self, files: I, source_file: S) where I: IntoIterator<Item = DataFile>, S: AsRef<str> {
@@ -128,26 +432,267 @@ impl ScanReport {
         set.ref_game(game);
     }
 
+    pub fn remove_sets(&mut self, set_names: &HashSet<String>) {
+        self.sets.retain(|set_name, _| !set_names.contains(set_name));
+    }
+
+    pub fn retain_sets(&mut self, set_names: &HashSet<String>) {
+        self.sets.retain(|set_name, _| set_names.contains(set_name));
+    }
+
     pub fn add_dependencies<S>(&mut self, set_name: S, dependencies: Vec<String>) where S: AsRef<str> {
         let set = self.sets.entry(set_name.as_ref().to_owned()).or_insert_with(|| SetReport::new(set_name.as_ref()));
         set.device_dependencies.extend(dependencies.into_iter());
     }
+
+    /// Sets the confidence of every set, once all the roms found and their matched checks are known
+    pub fn set_confidences(&mut self) {
+        for set in self.sets.values_mut() {
+            set.confidence = set.get_confidence();
+        }
+    }
+
+    /// Looks for the archives not named like a set whose files are exactly the roms of a single set without an
+    /// archive of its own, as renaming them completes the set. Returns how many were found
+    pub fn find_archive_renames(&mut self, name_rules: NameRules) -> usize {
+        let mut renames = vec![];
+        for file_report in self.sets.values() {
+            let file_name = match (&file_report.reference, &file_report.file_name) {
+                (SetReference::FileName(_), Some(file_name)) if !file_name.ends_with(models::FOLDER_SUFFIX) => file_name,
+                _ => continue
+            };
+            // Every file of an archive not named like a set is spare, unless nothing knows it
+            if !file_report.unknown.is_empty() || file_report.roms_to_spare.is_empty() {
+                continue;
+            }
+            let contents = file_report.roms_to_spare.iter().map(|rom| rom.name.as_str()).collect::<HashSet<_>>();
+            let candidates = self.sets.values()
+                .filter(|set| matches!(set.reference, SetReference::Game(_)) && set.file_name.is_none())
+                .filter(|set| set.roms_missing.is_empty() && !set.roms_available.is_empty())
+                .filter(|set| {
+                    let in_archive = set.roms_available.values().map(|located_at| match located_at {
+                        RomLocatedAt::InOthers(locations) => locations.iter().find(|location| location.file == *file_name).map(|location| location.with_name.as_str()),
+                        _ => None
+                    }).collect::<Option<HashSet<_>>>();
+                    in_archive.is_some_and(|in_archive| in_archive == contents)
+                })
+                .map(|set| set.reference.get_name())
+                .collect::<Vec<_>>();
+            // Sets with the same roms, like some clones, can't tell which one the archive is, unless only one has its name
+            let stem = Path::new(file_name).file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+            let same_names = candidates.iter().filter(|set_name| name_rules.same_name(&stem, set_name)).collect::<Vec<_>>();
+            match (&candidates[..], &same_names[..]) {
+                (_, [set_name]) => renames.push(ArchiveRename { file_name: file_name.to_owned(), set_name: set_name.to_string(), same_name: true }),
+                ([set_name], _) => renames.push(ArchiveRename { file_name: file_name.to_owned(), set_name: set_name.to_string(), same_name: false }),
+                _ => {}
+            }
+        }
+        renames.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+        self.renames = renames;
+        self.renames.len()
+    }
+}
+
+/// A scan report with the sets grouped by status: complete, fixable (only renames or roms from other files needed),
+/// incomplete, missing (nothing found) and files that don't match any set
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GroupedScanReport {
+    root_directory: Option<String>,
+    date_time: String,
+    rom_mode: RomsetMode,
+    #[serde(default)]
+    match_policy: MatchPolicy,
+    #[serde(default)]
+    scan_level: ScanLevel,
+    #[serde(default)]
+    layout: SetLayout,
+    pub complete: Vec<SetReport>,
+    pub fixable: Vec<SetReport>,
+    pub incomplete: Vec<SetReport>,
+    pub missing: Vec<SetReport>,
+    pub not_in_db: Vec<SetReport>,
+    pub ignored: Vec<String>,
+    pub converted: Vec<ConvertedImage>,
+    pub encrypted: BTreeMap<String, Vec<String>>,
+    pub corrupt: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub suspects: Vec<Suspect>,
+    #[serde(default)]
+    pub identified: Vec<Identification>,
+    #[serde(default)]
+    pub renames: Vec<ArchiveRename>,
+}
+
+impl Display for GroupedScanReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_text(f, Verbosity::Verbose)
+    }
+}
+
+impl GroupedScanReport {
+    fn write_text(&self, f: &mut std::fmt::Formatter<'_>, verbosity: Verbosity) -> std::fmt::Result {
+        if let Some(path) = &self.root_directory {
+            writeln!(f, "{}: {}", tr("Scanned dir"), path)?;
+        }
+        writeln!(f, "{}: {}", tr("Date of the report"), self.date_time)?;
+        writeln!(f, "{}: {}", tr("Mode"), self.rom_mode)?;
+        writeln!(f, "{}: {}", tr("Match policy"), self.match_policy)?;
+        if self.scan_level == ScanLevel::Trust {
+            writeln!(f, "{}: {}", tr("Scan level"), self.scan_level)?;
+        }
+        if self.layout == SetLayout::Folder {
+            writeln!(f, "{}: {}", tr("Layout"), self.layout)?;
+        }
+        let summary = [
+            ("Complete", self.complete.len()), ("Fixable", self.fixable.len()), ("Incomplete", self.incomplete.len()), ("Missing", self.missing.len()),
+            ("Not in the database", self.not_in_db.len()), ("Converted", self.converted.len()), ("Encrypted", self.encrypted.len()),
+            ("Corrupt", self.corrupt.len()), ("Suspect", self.suspects.len()), ("Identified", self.identified.len()), ("Ignored", self.ignored.len())
+        ];
+        let summary = summary.iter().map(|(label, count)| format!("{}: {}", tr(label), count)).collect::<Vec<_>>();
+        writeln!(f, "{}", summary.join(", "))?;
+
+        if verbosity == Verbosity::Quiet {
+            return Ok(());
+        }
+
+        // The complete and missing sets have nothing to act on, so only the names are listed
+        let name_groups = [("Complete", &self.complete), ("Missing", &self.missing)];
+        let detail_groups = [("Fixable", &self.fixable), ("Incomplete", &self.incomplete)];
+        for (title, sets) in detail_groups.iter() {
+            if !sets.is_empty() {
+                writeln!(f, "\n== {} ({}) ==\n", tr(title), sets.len())?;
+                if verbosity == Verbosity::Normal {
+                    write_set_table(f, &sets.iter().collect::<Vec<_>>())?;
+                    continue;
+                }
+                for set in sets.iter() {
+                    writeln!(f, "{}", set)?;
+                }
+            }
+        }
+        for (title, sets) in name_groups.iter() {
+            if !sets.is_empty() {
+                writeln!(f, "\n== {} ({}) ==", tr(title), sets.len())?;
+                for set in sets.iter() {
+                    writeln!(f, "- {}", set.reference.get_name())?;
+                }
+            }
+        }
+        if !self.not_in_db.is_empty() {
+            writeln!(f, "\n== {} ({}) ==", tr("Not in the database"), self.not_in_db.len())?;
+            for set in &self.not_in_db {
+                writeln!(f, "- {} ({} {})", set.reference.get_name(), set.unknown.len(), tr("unknown files"))?;
+            }
+        }
+        if !self.converted.is_empty() {
+            writeln!(f, "\n== {} ({}) ==", tr("Converted images"), self.converted.len())?;
+            for converted_image in &self.converted {
+                writeln!(f, "- {}", converted_image)?;
+            }
+        }
+        if !self.encrypted.is_empty() {
+            writeln!(f, "\n== {} ({}) ==", tr("Encrypted, couldn't verify"), self.encrypted.len())?;
+            for (file_name, entries) in &self.encrypted {
+                writeln!(f, "- {}: {}", file_name, entries.join(", "))?;
+            }
+        }
+        if !self.corrupt.is_empty() {
+            writeln!(f, "\n== {} ({}) ==", tr("Corrupt"), self.corrupt.len())?;
+            for (file_name, entries) in &self.corrupt {
+                writeln!(f, "- {}: {}", file_name, entries.join(", "))?;
+            }
+        }
+        if !self.suspects.is_empty() {
+            writeln!(f, "\n== {} ({}) ==", tr("Suspect"), self.suspects.len())?;
+            for suspect in &self.suspects {
+                write!(f, "{}", suspect)?;
+            }
+        }
+        if !self.identified.is_empty() {
+            writeln!(f, "\n== {} ({}) ==", tr("Identified"), self.identified.len())?;
+            for identification in &self.identified {
+                write!(f, "{}", identification)?;
+            }
+        }
+        if !self.renames.is_empty() {
+            writeln!(f, "\n== {} ({}) ==", tr("Archives to rename"), self.renames.len())?;
+            for rename in &self.renames {
+                write!(f, "{}", rename)?;
+            }
+        }
+        if !self.ignored.is_empty() {
+            writeln!(f, "\n== {} ({}) ==", tr("Ignored"), self.ignored.len())?;
+            for file in &self.ignored {
+                writeln!(f, "- {}", file)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SetReport {
     pub reference: SetReference,
     pub in_file: bool,
+    /// The archive or directory of the set, if it was found
+    #[serde(default)]
+    pub file_name: Option<String>,
+    #[serde(with = "roms_available")]
     pub roms_available: HashMap<DataFile, RomLocatedAt>,
     pub roms_missing: HashSet<DataFile>,
     pub roms_unneeded: HashSet<DataFile>, // BadDumps
     pub roms_to_spare: HashSet<DataFile>,
     pub device_dependencies: HashSet<String>,
-    pub unknown: Vec<DataFile>
+    pub unknown: Vec<DataFile>,
+    /// The checks that matched for each rom available, by rom name
+    #[serde(default)]
+    pub matched_checks: BTreeMap<String, MatchedChecks>,
+    /// The CHDs of the set, only checked when the roms directory is known
+    #[serde(default)]
+    pub disks: Vec<DiskResult>,
+    /// How many sets use each missing rom, by rom name, the ones needed by more sets are the first to look for
+    #[serde(default)]
+    pub rom_shares: BTreeMap<String, u32>,
+    /// How the roms available were verified, the weakest of them, none if no rom was found
+    #[serde(default)]
+    pub confidence: Option<Confidence>,
+}
+
+/// JSON only has strings as keys of a map, so the roms available are a list with each rom and where it is
+mod roms_available {
+    use std::collections::HashMap;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::data::models::file::DataFile;
+    use super::RomLocatedAt;
+
+    #[derive(Serialize)]
+    struct AvailableRomRef<'a> {
+        rom: &'a DataFile,
+        located_at: &'a RomLocatedAt,
+    }
+
+    #[derive(Deserialize)]
+    struct AvailableRom {
+        rom: DataFile,
+        located_at: RomLocatedAt,
+    }
+
+    pub fn serialize<S>(roms: &HashMap<DataFile, RomLocatedAt>, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        let mut roms = roms.iter().map(|(rom, located_at)| AvailableRomRef { rom, located_at }).collect::<Vec<_>>();
+        roms.sort_by(|a, b| a.rom.name.cmp(&b.rom.name));
+        roms.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<HashMap<DataFile, RomLocatedAt>, D::Error> where D: Deserializer<'de> {
+        let roms = Vec::<AvailableRom>::deserialize(deserializer)?;
+        Ok(roms.into_iter().map(|available| (available.rom, available.located_at)).collect())
+    }
 }
 
 // A set may be associated with a game based on its name, or just contain roms if there are no matches
 #[derive(Debug, Serialize, Deserialize)]
+#[allow(clippy::large_enum_variant)]
 pub enum SetReference {
     FileName(String),
     Game(Game)
@@ -170,7 +715,7 @@ impl Display for SetReference {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             SetReference::FileName(name) => {
-                writeln!(f, "File name: {}", name)
+                writeln!(f, "{}: {}", tr("File name"), name)
             }
             SetReference::Game(game) => {
                 writeln!(f, "{}", game)
@@ -181,56 +726,74 @@ impl Display for SetReference {
 
 impl Display for SetReport {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Set - {}", self.reference)?;
+        write!(f, "{} - {}", tr("Set"), self.reference)?;
         let file_status = if self.in_file {
-            " [in file]"
+            format!(" [{}]", tr("in file"))
         } else {
-            ""
+            "".to_string()
         };
-        writeln!(f, "Status: {}{}", self.is_complete(), file_status)?;
+        writeln!(f, "{}: {}{}", tr("Status"), self.is_complete(), file_status)?;
+        if let Some(confidence) = &self.confidence {
+            writeln!(f, "{}: {}", tr("Verified by"), confidence)?;
+        }
 
         if !self.device_dependencies.is_empty() {
-            writeln!(f, "Depends on:")?;
+            writeln!(f, "{}:", tr("Depends on"))?;
             for dep in &self.device_dependencies {
                 writeln!(f, " - {}", dep)?;
             }
         }
         if !self.roms_available.is_empty() {
-            writeln!(f, "Roms Available")?;
+            writeln!(f, "{}", tr("Roms Available"))?;
             for available in &self.roms_available {
                 let rom = available.0;
                 let location = available.1;
+                let matched = match self.matched_checks.get(&rom.name) {
+                    Some(checks) if checks.is_unhashed() => format!(" [{}: {}]", tr("not hashed"), checks),
+                    Some(checks) if checks.is_weak() => format!(" [{}: {}]", tr("weak match"), checks),
+                    _ => "".to_string()
+                };
+                let rom_name = rom.get_name_with_bios();
                 match location {
-                    RomLocatedAt::InSet => { writeln!(f, " - {}", rom.name)?; }
-                    RomLocatedAt::InSetWrongName(name) => { writeln!(f, " - {} [rename from: {}]", rom.name, name)?; }
+                    RomLocatedAt::InSet => { writeln!(f, " - {}{}", rom_name, matched)?; }
+                    RomLocatedAt::InSetWrongName(name) => { writeln!(f, " - {} [{}: {}]{}", rom_name, tr("rename from"), name, matched)?; }
                     RomLocatedAt::InOthers(locations) => {
                         let mut location_list = vec![];
                         for location in locations {
                             location_list.push(format!("{} as {}", location.file, location.with_name));
                         }
-                        writeln!(f, " - {} [located at: {}]", rom.name, location_list.join(", "))?; 
+                        writeln!(f, " - {} [{}: {}]{}", rom_name, tr("located at"), location_list.join(", "), matched)?; 
                     }
                 }
             }
         }
         if !self.roms_unneeded.is_empty() {
-            writeln!(f, "Roms Unneeded (e.g. Bad Dumps)")?;
+            writeln!(f, "{}", tr("Roms Unneeded (e.g. Bad Dumps)"))?;
             for unneeded in &self.roms_unneeded {
-                writeln!(f, " - {}", unneeded.name)?;
+                writeln!(f, " - {}", unneeded.get_name_with_bios())?;
             }
         }
         if !self.roms_missing.is_empty() {
-            writeln!(f, "Roms Missing")?;
-            for missing in &self.roms_missing {
-                writeln!(f, " - {}", missing.name)?;
+            writeln!(f, "{}", tr("Roms Missing"))?;
+            for (missing, shares) in self.get_missing_by_shares() {
+                match shares {
+                    Some(shares) if shares > 1 => writeln!(f, " - {} [{}: {}]", missing.get_name_with_bios(), tr("used by sets"), shares)?,
+                    _ => writeln!(f, " - {}", missing.get_name_with_bios())?
+                }
             }
         }
         if !self.roms_to_spare.is_empty() {
-            writeln!(f, "Roms to Spare")?;
+            writeln!(f, "{}", tr("Roms to Spare"))?;
             for to_spare in &self.roms_to_spare {
                 writeln!(f, " - {}", to_spare.name)?;
             }
         }
+        if !self.disks.is_empty() {
+            writeln!(f, "{}", tr("Disks"))?;
+            for disk in &self.disks {
+                write!(f, " - {}", disk)?;
+            }
+        }
         Ok(())
     }
 }
@@ -243,47 +806,153 @@ pub enum RomLocatedAt {
 }
 
 #[derive(Debug, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum SetStatus {
     COMPLETE,
     FIXEABLE,
-    INCOMPLETE
+    INCOMPLETE,
+    MISSING
 }
 
 impl Display for SetStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             SetStatus::COMPLETE => {
-                write!(f, "Complete")
+                write!(f, "{}", tr("Complete"))
             }
             SetStatus::FIXEABLE => {
-                write!(f, "Fixeable")
+                write!(f, "{}", tr("Fixable"))
             }
             SetStatus::INCOMPLETE => {
-                write!(f, "Incomplete")
+                write!(f, "{}", tr("Incomplete"))
+            }
+            SetStatus::MISSING => {
+                write!(f, "{}", tr("Missing"))
             }
         }
     }
 }
 
+/// How strongly the roms of a set were verified, from the weakest to the strongest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Confidence {
+    /// Not hashed, matched by the name and the size as in the trust scan level
+    NameSize,
+    /// Only the crc and the size agreed, the database has no stronger checksum
+    CrcSize,
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+impl From<&MatchedChecks> for Confidence {
+    fn from(checks: &MatchedChecks) -> Self {
+        if checks.sha256 {
+            Confidence::Sha256
+        } else if checks.sha1 {
+            Confidence::Sha1
+        } else if checks.md5 {
+            Confidence::Md5
+        } else if checks.crc || checks.xxh3 {
+            Confidence::CrcSize
+        } else {
+            Confidence::NameSize
+        }
+    }
+}
+
+impl Display for Confidence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Confidence::NameSize => write!(f, "{}", tr("name and size")),
+            Confidence::CrcSize => write!(f, "{}", tr("CRC and size")),
+            Confidence::Md5 => write!(f, "MD5"),
+            Confidence::Sha1 => write!(f, "SHA1"),
+            Confidence::Sha256 => write!(f, "SHA256"),
+        }
+    }
+}
+
+/// How many roms of a set are in each state
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SetCounters {
+    pub have: usize,
+    pub missing: usize,
+    pub rename: usize,
+    pub elsewhere: usize,
+    pub spare: usize,
+    pub unknown: usize,
+}
+
 impl SetReport {
+    pub fn get_counters(&self) -> SetCounters {
+        let mut counters = SetCounters {
+            missing: self.roms_missing.len(),
+            spare: self.roms_to_spare.len(),
+            unknown: self.unknown.len(),
+            ..Default::default()
+        };
+        for located_at in self.roms_available.values() {
+            match located_at {
+                RomLocatedAt::InSet => counters.have += 1,
+                RomLocatedAt::InSetWrongName(_) => counters.rename += 1,
+                RomLocatedAt::InOthers(_) => counters.elsewhere += 1,
+            }
+        }
+        counters
+    }
+
     pub fn new<S>(name: S) -> Self where S: Into<String> {
         Self {
             reference: SetReference::FileName(name.into()),
             in_file: false,
+            file_name: None,
             roms_available: HashMap::new(),
             roms_missing: HashSet::new(),
             roms_unneeded: HashSet::new(),
             roms_to_spare: HashSet::new(),
             device_dependencies: HashSet::new(),
-            unknown: vec![]
+            unknown: vec![],
+            matched_checks: BTreeMap::new(),
+            disks: vec![],
+            rom_shares: BTreeMap::new(),
+            confidence: None,
         }
     }
 
+    /// The confidence of the weakest rom available, the roms without matched checks are not taken into account
+    pub fn get_confidence(&self) -> Option<Confidence> {
+        self.roms_available.keys()
+            .filter_map(|rom| self.matched_checks.get(&rom.name))
+            .map(Confidence::from)
+            .min()
+    }
+
+    /// The missing roms with how many sets use them, the ones used by more sets first
+    pub fn get_missing_by_shares(&self) -> Vec<(&DataFile, Option<u32>)> {
+        let mut missing = self.roms_missing.iter().map(|rom| (rom, self.rom_shares.get(&rom.name).copied())).collect::<Vec<_>>();
+        missing.sort_by(|(rom_a, shares_a), (rom_b, shares_b)| shares_b.cmp(shares_a).then_with(|| rom_a.name.cmp(&rom_b.name)));
+        missing
+    }
+
     pub fn ref_game(&mut self, game: Game) {
         self.reference = SetReference::Game(game)
     }
 
     pub fn is_complete(&self) -> SetStatus {
+        let roms_status = self.get_roms_status();
+        // A set is only missing if none of its roms nor its disks were found
+        let disks_found = self.disks.iter().any(|disk| disk.path.is_some());
+        if self.disks.iter().all(DiskResult::is_ok) && (roms_status != SetStatus::MISSING || !disks_found) {
+            roms_status
+        } else if self.roms_available.is_empty() && !disks_found {
+            SetStatus::MISSING
+        } else {
+            SetStatus::INCOMPLETE
+        }
+    }
+
+    fn get_roms_status(&self) -> SetStatus {
         if self.roms_missing.is_empty() {
             let mut available = self.roms_available.len();
 
@@ -298,6 +967,8 @@ impl SetReport {
             }
 
             SetStatus::FIXEABLE
+        } else if self.roms_available.is_empty() {
+            SetStatus::MISSING
         } else {
             SetStatus::INCOMPLETE
         }
@@ -381,8 +1052,8 @@ impl SetReport {
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct RomLocation {
-    file: String,
-    with_name: String,
+    pub file: String,
+    pub with_name: String,
 }
 
 impl RomLocation {
@@ -534,6 +1205,97 @@ mod tests {
         assert_eq!(SetStatus::COMPLETE, completeness);
     }
 
+    #[test]
+    fn groups_sets_by_status() {
+        let mut scan_report = ScanReport::new(None, RomsetMode::Split);
+        scan_report.reference_with_game(Game::new("complete".to_string()));
+        scan_report.add_rom_for_set("complete", RomLocation::new("complete.zip", "file1"),
+            DataFile::new("file1", get_sample_rom("1234")));
+        scan_report.reference_with_game(Game::new("fixable".to_string()));
+        scan_report.add_rom_for_set("fixable", RomLocation::new("other.zip", "file2"),
+            DataFile::new("file2", get_sample_rom("5678")));
+        scan_report.reference_with_game(Game::new("missing".to_string()));
+        scan_report.add_missing_rom_for_set("missing", DataFile::new("file3", get_sample_rom("9012")));
+        scan_report.add_unknown_files(vec![DataFile::new("file4", get_sample_rom("3456"))], "other.zip");
+
+        let grouped = scan_report.into_grouped();
+        assert_eq!(grouped.complete.len(), 1);
+        assert_eq!(grouped.fixable.len(), 1);
+        assert_eq!(grouped.incomplete.len(), 0);
+        assert_eq!(grouped.missing.len(), 1);
+        assert_eq!(grouped.not_in_db.len(), 1);
+        assert_eq!(grouped.missing[0].reference.get_name(), "missing");
+    }
+
+    #[test]
+    fn prints_the_report_with_verbosity() {
+        let mut scan_report = ScanReport::new(None, RomsetMode::Split);
+        scan_report.add_rom_for_set("set1", RomLocation::new("set1.zip", "file1"), DataFile::new("file1", get_sample_rom("1234")));
+        scan_report.add_rom_for_set("set1", RomLocation::new("set1.zip", "wrong"), DataFile::new("file2", get_sample_rom("5678")));
+        scan_report.add_missing_rom_for_set("set1", DataFile::new("file3", get_sample_rom("3456")));
+        scan_report.add_unknown_files(vec![DataFile::new("file4", get_sample_rom("7890"))], "set1.zip");
+
+        let counters = scan_report.sets["set1"].get_counters();
+        assert_eq!(counters, SetCounters { have: 1, missing: 1, rename: 1, elsewhere: 0, spare: 0, unknown: 1 });
+
+        let mut view = ReportView::new(scan_report, Verbosity::Quiet);
+        let quiet = view.to_string();
+        assert!(quiet.contains("Sets: 1, Complete: 0, Fixable: 0, Incomplete: 1, Missing: 0"));
+        assert!(!quiet.contains("set1"));
+        view.verbosity = Verbosity::Normal;
+        let normal = view.to_string();
+        assert!(normal.contains("set1  Incomplete      1        1       1          0      0        1"));
+        assert!(!normal.contains("file1"));
+        view.verbosity = Verbosity::Verbose;
+        assert!(view.to_string().contains("file1"));
+    }
+
+    #[test]
+    fn serializes_the_report_as_json() -> Result<()> {
+        let mut scan_report = ScanReport::new(None, RomsetMode::Split);
+        scan_report.add_rom_for_set("set1", RomLocation::new("set1.zip", "file1"), DataFile::new("file1", get_sample_rom("1234")));
+        scan_report.add_rom_for_set("set1", RomLocation::new("set1.zip", "wrong"), DataFile::new("file2", get_sample_rom("5678")));
+
+        let json = serde_json::to_value(&scan_report)?;
+        let available = &json["sets"]["set1"]["roms_available"];
+        assert_eq!(available[0]["rom"]["name"], "file1");
+        assert_eq!(available[1]["located_at"]["InSetWrongName"], "wrong");
+
+        let deserialized: ScanReport = serde_json::from_value(json)?;
+        assert_eq!(deserialized.sets["set1"].roms_available, scan_report.sets["set1"].roms_available);
+        let deserialized: ScanReport = bincode::deserialize(&bincode::serialize(&scan_report)?)?;
+        assert_eq!(deserialized.sets["set1"].roms_available, scan_report.sets["set1"].roms_available);
+        Ok(())
+    }
+
+    #[test]
+    fn the_confidence_of_a_set_is_the_one_of_its_weakest_rom() -> Result<()> {
+        let mut scan_report = ScanReport::new(None, RomsetMode::Split);
+        scan_report.add_rom_for_set("set1", RomLocation::new("set1.zip", "file1"), DataFile::new("file1", get_sample_rom("1234")));
+        scan_report.add_rom_for_set("set1", RomLocation::new("set1.zip", "file2"), DataFile::new("file2", get_sample_rom("5678")));
+        scan_report.add_rom_for_set("set1", RomLocation::new("set1.zip", "file3"), DataFile::new("file3", get_sample_rom("9012")));
+        scan_report.add_missing_rom_for_set("set2", DataFile::new("file4", get_sample_rom("3456")));
+        scan_report.add_matched_checks("set1", "file1", MatchedChecks { sha256: true, crc: true, size: true, ..Default::default() });
+        scan_report.add_matched_checks("set1", "file2", MatchedChecks { sha1: true, crc: true, size: true, ..Default::default() });
+        // The confidences are only known once every rom is found
+        assert_eq!(None, scan_report.sets["set1"].confidence);
+
+        // The rom without matched checks is left out
+        scan_report.set_confidences();
+        assert_eq!(Some(Confidence::Sha1), scan_report.sets["set1"].confidence);
+        assert_eq!(None, scan_report.sets["set2"].confidence);
+        assert!(scan_report.sets["set1"].to_string().contains("SHA1"));
+
+        scan_report.add_matched_checks("set1", "file3", MatchedChecks { xxh3: true, size: true, ..Default::default() });
+        scan_report.set_confidences();
+        assert_eq!(Some(Confidence::CrcSize), scan_report.sets["set1"].confidence);
+        let json = serde_json::to_value(&scan_report)?;
+        assert_eq!(json["sets"]["set1"]["confidence"], "CrcSize");
+        assert_eq!(Confidence::NameSize, Confidence::from(&MatchedChecks { name: true, size: true, ..Default::default() }));
+
+        Ok(())
+    }
+
     fn get_sample_rom<S>(sha1: S) -> DataFileInfo where S: Into<String>{
         let mut rom = DataFileInfo::new(FileType::Rom);
         rom.sha1 = Some(sha1.into());
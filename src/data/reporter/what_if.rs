@@ -0,0 +1,117 @@
+use std::fmt::Display;
+use console::Style;
+use serde::{Deserialize, Serialize};
+use crate::i18n::tr;
+use super::scan_report::{ScanReport, SetStatus};
+
+/// A set not complete in the collection, with the roms it misses before and after adding the candidate files
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WhatIfSet {
+    pub name: String,
+    pub missing_before: usize,
+    pub missing_after: usize,
+}
+
+impl WhatIfSet {
+    pub fn covered(&self) -> usize { self.missing_before - self.missing_after }
+}
+
+/// What the candidate files would add to the sets of the collection that are not complete, or can't be completed
+/// with the files already there
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct WhatIfReport {
+    /// The sets that could be completed with the candidate files
+    pub completed: Vec<WhatIfSet>,
+    /// The sets that would still miss some roms, but less than now
+    pub improved: Vec<WhatIfSet>,
+}
+
+impl WhatIfReport {
+    /// Compares the report of the collection with the one of the collection and the candidate files together.
+    /// Only the sets in the first report are compared, with `ReportScope::All` they include the missing ones.
+    pub fn new(current: &ScanReport, with_candidates: &ScanReport) -> Self {
+        let mut report = WhatIfReport::default();
+        for (set_name, set_report) in &current.sets {
+            if matches!(set_report.is_complete(), SetStatus::COMPLETE | SetStatus::FIXEABLE) {
+                continue;
+            }
+            let candidate_report = match with_candidates.sets.get(set_name) {
+                Some(candidate_report) => candidate_report,
+                None => continue,
+            };
+            let set = WhatIfSet {
+                name: set_name.to_owned(),
+                missing_before: set_report.roms_missing.len(),
+                missing_after: candidate_report.roms_missing.len().min(set_report.roms_missing.len()),
+            };
+            if matches!(candidate_report.is_complete(), SetStatus::COMPLETE | SetStatus::FIXEABLE) {
+                report.completed.push(set);
+            } else if set.covered() > 0 {
+                report.improved.push(set);
+            }
+        }
+        report.completed.sort_by(|a, b| a.name.cmp(&b.name));
+        report.improved.sort_by(|a, b| b.covered().cmp(&a.covered()).then_with(|| a.name.cmp(&b.name)));
+
+        report
+    }
+
+    /// The missing roms of the collection found in the candidate files
+    pub fn roms_covered(&self) -> usize {
+        self.completed.iter().chain(self.improved.iter()).map(|set| set.covered()).sum()
+    }
+}
+
+impl Display for WhatIfReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", Style::new().bold().green().apply_to(format!("== {} ({}) ==", tr("Would be complete"), self.completed.len())))?;
+        for set in &self.completed {
+            writeln!(f, "{}: {} {}", set.name, set.covered(), tr("roms"))?;
+        }
+        writeln!(f, "{}", Style::new().bold().yellow().apply_to(format!("== {} ({}) ==", tr("Would miss less roms"), self.improved.len())))?;
+        for set in &self.improved {
+            writeln!(f, "{}: {} {} {} {}, {} {}", set.name, set.covered(), tr("of"), set.missing_before, tr("roms"), set.missing_after, tr("still missing"))?;
+        }
+        writeln!(f, "{}: {}", tr("Missing roms covered"), self.roms_covered())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{RomsetMode, data::models::{file::{DataFile, DataFileInfo, FileType}, game::Game}};
+    use super::super::scan_report::RomLocation;
+
+    use super::*;
+
+    fn get_rom(name: &str) -> DataFile {
+        let mut info = DataFileInfo::new(FileType::Rom);
+        info.sha1 = Some(name.to_string());
+        DataFile::new(name, info)
+    }
+
+    fn get_report(found: &[(&str, &str, &str)]) -> ScanReport {
+        let mut report = ScanReport::new(None, RomsetMode::NonMerged);
+        for (set_name, roms) in [("pacman", vec!["pacman1", "pacman2"]), ("galaxian", vec!["galaxian1", "galaxian2", "galaxian3"])].iter() {
+            report.reference_with_game(Game::new(set_name.to_string()));
+            report.add_missing_roms_for_set(*set_name, roms.iter().map(|rom| get_rom(rom)));
+        }
+        for (set_name, file, rom) in found {
+            report.add_rom_for_set(*set_name, RomLocation::new(*file, *rom), get_rom(rom));
+        }
+        report
+    }
+
+    #[test]
+    fn finds_the_sets_the_candidates_complete() {
+        let current = get_report(&[("pacman", "pacman.zip", "pacman1"), ("galaxian", "galaxian.zip", "galaxian1")]);
+        let with_candidates = get_report(&[
+            ("pacman", "pacman.zip", "pacman1"), ("galaxian", "galaxian.zip", "galaxian1"),
+            ("pacman", "friend/pacman.zip", "pacman2"), ("galaxian", "friend/galaxian.zip", "galaxian2"),
+        ]);
+
+        let report = WhatIfReport::new(&current, &with_candidates);
+        assert_eq!(report.completed, vec![WhatIfSet { name: "pacman".to_string(), missing_before: 1, missing_after: 0 }]);
+        assert_eq!(report.improved, vec![WhatIfSet { name: "galaxian".to_string(), missing_before: 2, missing_after: 1 }]);
+        assert_eq!(report.roms_covered(), 2);
+    }
+}
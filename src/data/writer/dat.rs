@@ -0,0 +1,202 @@
+use std::io::Write;
+
+use anyhow::Result;
+use quick_xml::escape::escape;
+
+use crate::data::models::{dat::DatInfo, disk::GameDisk, file::DataFile, game::Game};
+use super::DataWriter;
+
+const DAT_PROLOG: &str = "<?xml version=\"1.0\"?>
+<!DOCTYPE datafile PUBLIC \"-//Logiqx//DTD ROM Management Datafile//EN\" \"http://www.logiqx.com/Dats/datafile.dtd\">
+";
+
+/// The order of the header elements in the Logiqx DTD, the rest go after them
+const HEADER_ORDER: [&str; 10] = ["name", "description", "category", "version", "date", "author", "email", "homepage", "url", "comment"];
+
+/// Writes the entries as a Logiqx XML DAT file
+pub struct DatWriter<W: Write> {
+    writer: W,
+    name: String,
+    header: Option<DatInfo>,
+    started: bool,
+}
+
+impl<W: Write> DatWriter<W> {
+    pub fn new<S>(writer: W, name: S) -> Self where S: Into<String> {
+        Self { writer, name: name.into(), header: None, started: false }
+    }
+
+    fn start(&mut self) -> Result<()> {
+        if !self.started {
+            self.started = true;
+            write!(self.writer, "{}", DAT_PROLOG)?;
+            writeln!(self.writer, "<datafile>")?;
+            writeln!(self.writer, "\t<header>")?;
+            match self.header.take() {
+                Some(dat_info) => self.write_header(dat_info)?,
+                None => {
+                    writeln!(self.writer, "\t\t<name>{}</name>", esc(&self.name))?;
+                    writeln!(self.writer, "\t\t<description>{}</description>", esc(&self.name))?;
+                }
+            }
+            writeln!(self.writer, "\t</header>")?;
+        }
+
+        Ok(())
+    }
+
+    /// Attributes of empty elements in the header, the keys with a dot, are not written
+    fn write_header(&mut self, dat_info: DatInfo) -> Result<()> {
+        let mut header = dat_info.header;
+        header.insert("name".to_string(), dat_info.name);
+        if let Some(description) = dat_info.description {
+            header.insert("description".to_string(), description);
+        }
+        if let Some(version) = dat_info.version {
+            header.insert("version".to_string(), version);
+        }
+
+        let mut keys = HEADER_ORDER.iter().map(|key| key.to_string()).filter(|key| header.contains_key(key)).collect::<Vec<_>>();
+        keys.extend(header.keys().filter(|key| !HEADER_ORDER.contains(&key.as_str()) && !key.contains('.')).cloned());
+        for key in keys {
+            writeln!(self.writer, "\t\t<{}>{}</{}>", key, esc(&header[&key]), key)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn esc(value: &str) -> String {
+    String::from_utf8_lossy(&escape(value.as_bytes())).to_string()
+}
+
+fn attribute(name: &str, value: &Option<impl ToString>) -> String {
+    match value {
+        Some(value) => format!(" {}=\"{}\"", name, esc(&value.to_string())),
+        None => String::new()
+    }
+}
+
+impl<W: Write> DataWriter for DatWriter<W> {
+    fn init(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_dat_info(&mut self, header: DatInfo) -> Result<()> {
+        self.header = Some(header);
+
+        Ok(())
+    }
+
+    fn on_new_entry(&mut self, game: Game, roms: Vec<DataFile>, disks: Vec<GameDisk>, samples: Vec<String>, device_refs: Vec<String>) -> Result<()> {
+        self.start()?;
+
+        writeln!(self.writer, "\t<machine name=\"{}\"{}{}{}{}>",
+            esc(&game.name),
+            attribute("sourcefile", &game.source_file),
+            attribute("cloneof", &game.clone_of),
+            attribute("romof", &game.rom_of),
+            attribute("sampleof", &game.sample_of))?;
+        if let Some(description) = &game.info_description {
+            writeln!(self.writer, "\t\t<description>{}</description>", esc(description))?;
+        }
+        if let Some(year) = &game.info_year {
+            writeln!(self.writer, "\t\t<year>{}</year>", esc(year))?;
+        }
+        if let Some(manufacturer) = &game.info_manufacturer {
+            writeln!(self.writer, "\t\t<manufacturer>{}</manufacturer>", esc(manufacturer))?;
+        }
+        for rom in roms {
+            writeln!(self.writer, "\t\t<rom name=\"{}\"{}{}{}{}{}{}{}{}{}/>",
+                esc(&rom.name),
+                attribute("bios", &rom.bios),
+                attribute("size", &rom.info.size),
+                attribute("crc", &rom.info.crc),
+                attribute("md5", &rom.info.md5),
+                attribute("sha1", &rom.info.sha1),
+                attribute("sha256", &rom.info.sha256),
+                attribute("xxh3", &rom.info.xxh3),
+                attribute("status", &rom.status),
+                attribute("optional", &rom.optional.then_some("yes")))?;
+        }
+        for disk in disks {
+            writeln!(self.writer, "\t\t<disk name=\"{}\"{}{}{}/>",
+                esc(&disk.name),
+                attribute("sha1", &disk.info.sha1),
+                attribute("region", &disk.info.region),
+                attribute("status", &disk.info.status))?;
+        }
+        for sample in samples {
+            writeln!(self.writer, "\t\t<sample name=\"{}\"/>", esc(&sample))?;
+        }
+        for device_ref in device_refs {
+            writeln!(self.writer, "\t\t<device_ref name=\"{}\"/>", esc(&device_ref))?;
+        }
+        writeln!(self.writer, "\t</machine>")?;
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.start()?;
+        writeln!(self.writer, "</datafile>")?;
+        self.writer.flush()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::models::file::{DataFileInfo, FileType};
+
+    use super::*;
+
+    #[test]
+    fn writes_machine_entry() -> Result<()> {
+        let mut output = vec![];
+        let mut writer = DatWriter::new(&mut output, "Test & Co");
+        let mut game = Game::new("game1a".to_string());
+        game.clone_of = Some("game1".to_string());
+        game.info_description = Some("Game <1>".to_string());
+        let mut rom_info = DataFileInfo::new(FileType::Rom);
+        rom_info.crc = Some("1d460eee".to_string());
+        rom_info.size = Some(2048);
+        let roms = vec![DataFile::new("rom1.trom", rom_info)];
+
+        writer.init()?;
+        writer.on_new_entry(game, roms, vec![], vec![], vec!["device1".to_string()])?;
+        writer.finish()?;
+        drop(writer);
+        let dat = String::from_utf8(output)?;
+
+        assert!(dat.contains("<name>Test &amp; Co</name>"));
+        assert!(dat.contains("<machine name=\"game1a\" cloneof=\"game1\">"));
+        assert!(dat.contains("<description>Game &lt;1&gt;</description>"));
+        assert!(dat.contains("<rom name=\"rom1.trom\" size=\"2048\" crc=\"1d460eee\"/>"));
+        assert!(dat.contains("<device_ref name=\"device1\"/>"));
+        assert!(dat.trim_end().ends_with("</datafile>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn writes_dat_header() -> Result<()> {
+        let mut output = vec![];
+        let mut writer = DatWriter::new(&mut output, "Export");
+        let mut dat_info = DatInfo::new("Nintendo - Game Boy");
+        dat_info.version = Some("20201028".to_string());
+        dat_info.header.insert("author".to_string(), "Nico".to_string());
+        dat_info.header.insert("clrmamepro.forcenodump".to_string(), "ignore".to_string());
+
+        writer.init()?;
+        writer.on_dat_info(dat_info)?;
+        writer.finish()?;
+        drop(writer);
+        let dat = String::from_utf8(output)?;
+
+        assert!(dat.contains("<header>\n\t\t<name>Nintendo - Game Boy</name>\n\t\t<version>20201028</version>\n\t\t<author>Nico</author>\n\t</header>"));
+
+        Ok(())
+    }
+}
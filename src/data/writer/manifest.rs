@@ -0,0 +1,49 @@
+use anyhow::Result;
+
+use crate::data::{models::{dat::DatInfo, disk::GameDisk, file::DataFile, game::Game}, reporter::manifest::{ManifestFile, ScanManifest}};
+use super::DataWriter;
+
+/// Turns the games of a DAT into the files of a manifest, an archive per game with its roms, to check them as if they were scanned
+pub struct ManifestWriter {
+    manifest: ScanManifest,
+}
+
+impl ManifestWriter {
+    pub fn new() -> Self {
+        Self { manifest: ScanManifest::new(None) }
+    }
+
+    pub fn into_manifest(self) -> ScanManifest {
+        self.manifest
+    }
+}
+
+impl DataWriter for ManifestWriter {
+    fn init(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_dat_info(&mut self, _header: DatInfo) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_new_entry(&mut self, game: Game, roms: Vec<DataFile>, _disks: Vec<GameDisk>, _samples: Vec<String>, _device_refs: Vec<String>) -> Result<()> {
+        if roms.is_empty() {
+            return Ok(());
+        }
+        self.manifest.files.push(ManifestFile {
+            file_name: format!("{}.zip", game.name),
+            size: roms.iter().map(|rom| rom.info.size.unwrap_or_default() as u64).sum(),
+            path: game.name,
+            roms,
+            locked: vec![],
+            corrupt: vec![],
+        });
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
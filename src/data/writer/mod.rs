@@ -1,11 +1,20 @@
 pub mod sqlite;
+pub mod dat;
+pub mod manifest;
+pub mod update;
 
 use anyhow::Result;
 
-use super::models::{disk::GameDisk, file::*, game::Game};
+use super::models::{dat::DatInfo, disk::GameDisk, file::*, game::Game, release::Release};
 
 pub trait DataWriter {
     fn init(&self) -> Result<()>;
+    /// Called with the header of the DAT before its entries
+    fn on_dat_info(&mut self, header: DatInfo) -> Result<()>;
     fn on_new_entry(&mut self, game: Game, roms: Vec<DataFile>, disks: Vec<GameDisk>, samples: Vec<String>, device_refs: Vec<String>) -> Result<()>;
+    /// Called after the entry of a game with releases, only No-Intro and other Logiqx DATs have them
+    fn on_releases(&mut self, _game_name: &str, _releases: Vec<Release>) -> Result<()> {
+        Ok(())
+    }
     fn finish(&mut self) -> Result<()>;
 }
\ No newline at end of file
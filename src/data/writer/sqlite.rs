@@ -1,10 +1,10 @@
-use std::{collections::{HashMap, HashSet}, iter::FromIterator, rc::Rc};
+use std::{collections::{HashMap, HashSet}, fmt::Display, fs::File, io::{BufWriter, Write}, iter::FromIterator, path::Path, rc::Rc, time::{Duration, Instant}};
 
 use anyhow::Result;
-use log::{debug, error};
+use log::{debug, error, info, warn};
 use rusqlite::{Connection, params};
 
-use crate::{data::{models::{disk::{GameDisk, GameDiskInfo}, file::{DataFile, DataFileInfo}, game::Game}, reader::sqlite::DBReader}};
+use crate::{data::{models::{dat::{DatInfo, ImportProgress, ImportSource}, history::{GameAlias, GameChange, ReportHistoryEntry}, disk::{GameDisk, GameDiskInfo}, file::{DataFile, DataFileInfo}, game::Game, release::Release}, reader::{MatchPolicy, sqlite::DBReader}}};
 use super::DataWriter;
 
 #[derive(Debug)]
@@ -28,11 +28,118 @@ impl IdsCounter {
     }
 }
 
+/// Databases created before the releases were imported don't have the table, it's created when first needed
+const RELEASES_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS releases (
+        game_name   TEXT,
+        name        TEXT,
+        region      TEXT,
+        language    TEXT,
+        date        TEXT,
+        is_default  INTEGER,
+        PRIMARY KEY (game_name, name, region));
+    CREATE INDEX IF NOT EXISTS releases_game ON releases(game_name);
+    CREATE INDEX IF NOT EXISTS releases_region ON releases(region);";
+
+/// Each rom name is stored once, `game_roms` is a view with the names for the queries
+const GAME_ROMS_SCHEMA: &str = "
+    CREATE TABLE rom_names (
+        id          INTEGER PRIMARY KEY,
+        name        TEXT UNIQUE);
+    CREATE TABLE game_rom_refs (
+        game_name   TEXT,
+        rom_id      INTEGER,
+        name_id     INTEGER,
+        status      TEXT,
+        parent      TEXT,
+        bios        TEXT NOT NULL DEFAULT '',
+        optional    INTEGER NOT NULL DEFAULT 0,
+        PRIMARY KEY (game_name, rom_id, name_id, bios));
+    CREATE INDEX game_rom_refs_game ON game_rom_refs(game_name);
+    CREATE INDEX game_rom_refs_rom ON game_rom_refs(rom_id);
+    CREATE INDEX game_rom_refs_name ON game_rom_refs(name_id);
+    CREATE INDEX game_rom_refs_parents ON game_rom_refs(parent);";
+
+const GAME_ROMS_VIEW: &str = "
+    CREATE VIEW game_roms (game_name, rom_id, name, status, parent, bios, optional) AS
+        SELECT game_rom_refs.game_name, game_rom_refs.rom_id, rom_names.name, game_rom_refs.status, game_rom_refs.parent, NULLIF(game_rom_refs.bios, ''),
+            game_rom_refs.optional
+        FROM game_rom_refs JOIN rom_names ON game_rom_refs.name_id = rom_names.id;";
+
+/// How many sets use each rom, computed after every change of the roms of the games
+/// The columns of the games added after the first schema, with their definition for the databases without them
+const MACHINE_INFO_COLUMNS: [(&str, &str); 6] = [
+    ("is_bios", "INTEGER NOT NULL DEFAULT 0"),
+    ("is_device", "INTEGER NOT NULL DEFAULT 0"),
+    ("is_mechanical", "INTEGER NOT NULL DEFAULT 0"),
+    ("runnable", "INTEGER NOT NULL DEFAULT 1"),
+    ("driver_status", "TEXT"),
+    ("software_list", "TEXT"),
+];
+
+/// Where an import is, a single row that is removed once the import finishes
+const IMPORT_PROGRESS_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS import_progress (
+        id          INTEGER PRIMARY KEY,
+        source_sha1 TEXT,
+        dat_id      INTEGER,
+        entries     INTEGER);";
+
+/// The optional checksums of the roms, added after the first schema
+const ROM_CHECKSUM_COLUMNS: [&str; 2] = ["sha256", "xxh3"];
+
+const ROM_CHECKSUM_INDEXES: &str = "
+    CREATE INDEX IF NOT EXISTS roms_sha256 ON roms(sha256);
+    CREATE INDEX IF NOT EXISTS roms_xxh3 ON roms(xxh3);";
+
+const ROM_SHARES_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS rom_shares (
+        rom_id      INTEGER PRIMARY KEY,
+        sets        INTEGER);";
+
+/// A file with everything written during an import, one line per action, to review it afterwards
+#[derive(Debug)]
+pub struct ImportLog {
+    writer: BufWriter<File>,
+}
+
+impl ImportLog {
+    pub fn create(path: &impl AsRef<Path>) -> Result<Self> {
+        Ok(Self { writer: BufWriter::new(File::create(path)?) })
+    }
+
+    fn write(&mut self, line: impl Display) {
+        if let Err(e) = writeln!(self.writer, "{}", line) {
+            warn!("Can't write in the import log: {}", e);
+        }
+    }
+}
+
+fn log_line(import_log: &mut Option<ImportLog>, line: impl Display) {
+    if let Some(import_log) = import_log {
+        import_log.write(line);
+    }
+}
+
 #[derive(Debug)]
 pub struct DBWriter<'d> {
     conn: &'d mut Connection,
     buffer: Buffer,
     buffer_size: u16,
+    current_dat: Option<u32>,
+    /// The file being imported, for the DATs without a source of their own
+    import_source: Option<ImportSource>,
+    /// Ids of the rom names already written
+    rom_name_ids: HashMap<String, u32>,
+    import_log: Option<ImportLog>,
+    /// Recorded with every write of the buffer, when the import can be resumed
+    progress: Option<ImportProgress>,
+    /// The entries of the DAT received, and the ones already in the database of an interrupted import
+    entries: u32,
+    resume_entries: u32,
+    /// The buffer is written at least this often, besides when it's full
+    autosave: Option<Duration>,
+    last_write: Instant,
 }
 
 #[derive(Debug)]
@@ -46,21 +153,25 @@ struct Buffer {
     device_refs: HashMap<String, HashSet<String>>,
     disks: HashMap<GameDiskInfo, u32>,
     game_disks: HashMap<String, Vec<GameFileBufferItem>>,
+    releases: HashMap<String, Vec<Release>>,
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 struct GameFileBufferItem {
     name: String,
     id: u32,
-    status: Option<String>
+    status: Option<String>,
+    /// The region variants of a rom keep their own entry, even with the same name and data
+    bios: Option<String>,
+    optional: bool,
 }
 
 impl GameFileBufferItem {
     fn from_data_file(rom_id: u32, data_file: DataFile) -> Self {
-        Self { name: data_file.name, id: rom_id, status: data_file.status } 
+        Self { name: data_file.name, id: rom_id, status: data_file.status, bios: data_file.bios, optional: data_file.optional }
     }
     fn from_disk_file(disk_id: u32, disk_file: GameDisk) -> Self {
-        Self { name: disk_file.name, id: disk_id, status: disk_file.info.status }
+        Self { name: disk_file.name, id: disk_id, status: disk_file.info.status, bios: None, optional: false }
     }
 }
 
@@ -75,6 +186,7 @@ impl Buffer {
             device_refs: HashMap::new(),
             disks: HashMap::new(),
             game_disks: HashMap::new(),
+            releases: HashMap::new(),
         }
     }
 
@@ -143,7 +255,8 @@ impl Buffer {
 
 impl <'d> DBWriter<'d> {
     pub fn from_connection(conn: &'d mut Connection, buffer_size: u16) -> Self {
-        Self { conn, buffer: Buffer::new(), buffer_size }
+        Self { conn, buffer: Buffer::new(), buffer_size, current_dat: None, import_source: None, rom_name_ids: HashMap::new(), import_log: None,
+            progress: None, entries: 0, resume_entries: 0, autosave: None, last_write: Instant::now() }
     }
 
     fn remove_table_if_exist(&self, table_name: &str) -> Result<()> {
@@ -166,6 +279,7 @@ impl <'d> DBWriter<'d> {
 
     fn create_schema(&self) -> Result<()> {
         self.create_table_info()?;
+        self.create_table_dat_header()?;
         self.create_table_roms()?;
         self.create_table_games()?;
         self.create_table_game_roms()?;
@@ -173,6 +287,12 @@ impl <'d> DBWriter<'d> {
         self.create_table_disks()?;
         self.create_table_game_disks()?;
         self.create_table_samples()?;
+        self.create_table_releases()?;
+        self.create_table_exclusions()?;
+        self.create_table_tags()?;
+        self.create_table_notes()?;
+        self.create_table_game_changes()?;
+        self.create_table_game_aliases()?;
 
         Ok(())
     }
@@ -181,9 +301,27 @@ impl <'d> DBWriter<'d> {
         self.remove_table_if_exist("info")?;
         self.conn.execute(
             "CREATE TABLE info (
+                dat_id      INTEGER PRIMARY KEY,
                 name        TEXT,
                 description TEXT,
-                version     TEXT);", 
+                version     TEXT,
+                source      TEXT,
+                source_sha1 TEXT,
+                imported_at TEXT,
+                romst_version TEXT);",
+            params![])?;
+
+        Ok(())
+    }
+
+    fn create_table_dat_header(&self) -> Result<()> {
+        self.remove_table_if_exist("dat_header")?;
+        self.conn.execute(
+            "CREATE TABLE dat_header (
+                dat_id      INTEGER,
+                key         TEXT,
+                value       TEXT,
+                PRIMARY KEY (dat_id, key));",
             params![])?;
 
         Ok(())
@@ -199,7 +337,9 @@ impl <'d> DBWriter<'d> {
                 sha1    TEXT,
                 md5     TEXT,
                 crc     TEXT,
-                size    INT);", 
+                size    INT,
+                sha256  TEXT,
+                xxh3    TEXT);", 
             params![])?;
         debug!("Creating ROMS indexes");
         // Indexes
@@ -208,6 +348,7 @@ impl <'d> DBWriter<'d> {
         self.conn.execute("CREATE INDEX roms_md5 ON roms(md5);", params![])?;
         self.conn.execute("CREATE INDEX roms_crc ON roms(crc);", params![])?;
         self.conn.execute("CREATE INDEX roms_checks ON roms(sha1, md5, crc);", params![])?;
+        self.conn.execute_batch(ROM_CHECKSUM_INDEXES)?;
 
         Ok(())
     }
@@ -225,35 +366,182 @@ impl <'d> DBWriter<'d> {
                 sample_of   TEXT,
                 info_desc   TEXT,
                 info_year   TEXT,
-                info_manuf  TEXT);",
+                info_manuf  TEXT,
+                dat_id      INTEGER,
+                is_bios     INTEGER NOT NULL DEFAULT 0,
+                is_device   INTEGER NOT NULL DEFAULT 0,
+                is_mechanical INTEGER NOT NULL DEFAULT 0,
+                runnable    INTEGER NOT NULL DEFAULT 1,
+                driver_status TEXT,
+                software_list TEXT);",
             params![])?;
         debug!("Creating Games indexes");
         // Indexes
         self.conn.execute("CREATE INDEX games_parents_roms ON games(rom_of);", params![])?;
         self.conn.execute("CREATE INDEX games_parents_clone ON games(clone_of);", params![])?;
         self.conn.execute("CREATE INDEX games_samples ON games(sample_of);", params![])?;
+        self.conn.execute("CREATE INDEX games_dats ON games(dat_id);", params![])?;
 
         Ok(())
     }
 
     fn create_table_game_roms(&self) -> Result<()> {
         debug!("Creating Games/ROMs table");
+        self.conn.execute("DROP VIEW IF EXISTS game_roms;", params![])?;
+        // Databases created before the rom names were normalized have a game_roms table
         self.remove_table_if_exist("game_roms")?;
-        // Machine/Roms
-        self.conn.execute(
-            "CREATE TABLE game_roms (
-                game_name   TEXT,
-                rom_id      INTEGER,
-                name        TEXT,
-                status      TEXT,
-                parent      TEXT,
-                PRIMARY KEY (game_name, rom_id, name));",
-            params![])?;
-        debug!("Creating Games/ROMs indexes");
-        // Indexes
-        self.conn.execute("CREATE INDEX game_roms_game ON game_roms(game_name);", params![])?;
-        self.conn.execute("CREATE INDEX game_roms_rom ON game_roms(rom_id);", params![])?;
-        self.conn.execute("CREATE INDEX game_roms_parents ON game_roms(parent);", params![])?;
+        self.remove_table_if_exist("game_rom_refs")?;
+        self.remove_table_if_exist("rom_names")?;
+        self.conn.execute_batch(GAME_ROMS_SCHEMA)?;
+        self.conn.execute_batch(GAME_ROMS_VIEW)?;
+
+        Ok(())
+    }
+
+    /// Moves the rom names of a database created before they were normalized to their own table.
+    /// Returns false if the database doesn't need it.
+    pub fn migrate_rom_names(&mut self) -> Result<bool> {
+        let old_table: u32 = self.conn.query_row("SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name = 'game_roms';", params![], |row| {
+            row.get(0)
+        })?;
+        if old_table == 0 {
+            return Ok(false);
+        }
+
+        info!("Moving the rom names to their own table");
+        let tx = self.conn.transaction()?;
+        tx.execute("ALTER TABLE game_roms RENAME TO old_game_roms;", params![])?;
+        tx.execute_batch(GAME_ROMS_SCHEMA)?;
+        tx.execute_batch(GAME_ROMS_VIEW)?;
+        tx.execute("INSERT INTO rom_names (name) SELECT DISTINCT name FROM old_game_roms;", params![])?;
+        tx.execute("INSERT INTO game_rom_refs (game_name, rom_id, name_id, status, parent)
+            SELECT old_game_roms.game_name, old_game_roms.rom_id, rom_names.id, old_game_roms.status, old_game_roms.parent
+            FROM old_game_roms JOIN rom_names ON old_game_roms.name = rom_names.name;", params![])?;
+        tx.execute("DROP TABLE old_game_roms;", params![])?;
+        tx.commit()?;
+        self.conn.execute("VACUUM;", params![])?;
+
+        Ok(true)
+    }
+
+    /// Adds the BIOS of the roms to a database created before it was stored, the roms get no BIOS until the DAT is
+    /// imported again. Returns false if the database doesn't need it.
+    pub fn migrate_rom_bios(&mut self) -> Result<bool> {
+        self.migrate_rom_names()?;
+        let columns: u32 = self.conn.query_row("SELECT COUNT(*) FROM pragma_table_info('game_rom_refs') WHERE name = 'bios';", params![], |row| {
+            row.get(0)
+        })?;
+        if columns > 0 {
+            return Ok(false);
+        }
+
+        info!("Adding the BIOS to the roms of the games");
+        // The key of the roms changes, so the table is created again
+        let tx = self.conn.transaction()?;
+        tx.execute_batch("DROP VIEW game_roms;
+            DROP INDEX game_rom_refs_game; DROP INDEX game_rom_refs_rom; DROP INDEX game_rom_refs_name; DROP INDEX game_rom_refs_parents;
+            ALTER TABLE game_rom_refs RENAME TO old_game_rom_refs;
+            ALTER TABLE rom_names RENAME TO old_rom_names;")?;
+        tx.execute_batch(GAME_ROMS_SCHEMA)?;
+        tx.execute_batch(GAME_ROMS_VIEW)?;
+        tx.execute_batch("INSERT INTO rom_names (id, name) SELECT id, name FROM old_rom_names;
+            INSERT INTO game_rom_refs (game_name, rom_id, name_id, status, parent)
+                SELECT game_name, rom_id, name_id, status, parent FROM old_game_rom_refs;
+            DROP TABLE old_game_rom_refs;
+            DROP TABLE old_rom_names;")?;
+        tx.commit()?;
+
+        Ok(true)
+    }
+
+    /// Adds the optional flag of the roms to a database created before it was stored, no rom is optional until the
+    /// DAT is imported again. Returns false if the database doesn't need it.
+    pub fn migrate_rom_optional(&mut self) -> Result<bool> {
+        self.migrate_rom_bios()?;
+        let columns: u32 = self.conn.query_row("SELECT COUNT(*) FROM pragma_table_info('game_rom_refs') WHERE name = 'optional';", params![], |row| {
+            row.get(0)
+        })?;
+        if columns > 0 {
+            return Ok(false);
+        }
+
+        info!("Adding the optional flag to the roms of the games");
+        let tx = self.conn.transaction()?;
+        tx.execute_batch("DROP VIEW game_roms;
+            ALTER TABLE game_rom_refs ADD COLUMN optional INTEGER NOT NULL DEFAULT 0;")?;
+        tx.execute_batch(GAME_ROMS_VIEW)?;
+        tx.commit()?;
+
+        Ok(true)
+    }
+
+    /// Counts the sets using each rom in a database created before the counts were stored.
+    /// Returns false if the database doesn't need it.
+    pub fn migrate_rom_shares(&mut self) -> Result<bool> {
+        let table: u32 = self.conn.query_row("SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name = 'rom_shares';", params![], |row| {
+            row.get(0)
+        })?;
+        if table > 0 {
+            return Ok(false);
+        }
+
+        info!("Counting the sets using each rom");
+        self.update_rom_shares()?;
+
+        Ok(true)
+    }
+
+    /// Adds the columns of the MAME machine metadata and the software list to the games of a database created before
+    /// they were stored, the games get the values of a plain runnable game until the DAT is imported again.
+    /// Returns false if the database doesn't need it.
+    pub fn migrate_machine_info(&mut self) -> Result<bool> {
+        let tx = self.conn.transaction()?;
+        let mut migrated = false;
+        for (column, definition) in MACHINE_INFO_COLUMNS.iter() {
+            let columns: u32 = tx.query_row("SELECT COUNT(*) FROM pragma_table_info('games') WHERE name = ?1;", params![ column ], |row| {
+                row.get(0)
+            })?;
+            if columns == 0 {
+                info!("Adding the column {} to the games", column);
+                tx.execute(&format!("ALTER TABLE games ADD COLUMN {} {};", column, definition), params![])?;
+                migrated = true;
+            }
+        }
+        tx.commit()?;
+
+        Ok(migrated)
+    }
+
+    /// Adds the columns of the optional checksums to the roms of a database created before they were stored, the roms
+    /// get none until the DAT is imported again. Returns false if the database doesn't need it.
+    pub fn migrate_rom_checksums(&mut self) -> Result<bool> {
+        let tx = self.conn.transaction()?;
+        let mut migrated = false;
+        for column in ROM_CHECKSUM_COLUMNS.iter() {
+            let columns: u32 = tx.query_row("SELECT COUNT(*) FROM pragma_table_info('roms') WHERE name = ?1;", params![ column ], |row| {
+                row.get(0)
+            })?;
+            if columns == 0 {
+                info!("Adding the column {} to the roms", column);
+                tx.execute(&format!("ALTER TABLE roms ADD COLUMN {} TEXT;", column), params![])?;
+                migrated = true;
+            }
+        }
+        if migrated {
+            tx.execute_batch(ROM_CHECKSUM_INDEXES)?;
+        }
+        tx.commit()?;
+
+        Ok(migrated)
+    }
+
+    /// Counts again how many sets use each rom
+    pub fn update_rom_shares(&mut self) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute_batch(ROM_SHARES_SCHEMA)?;
+        tx.execute("DELETE FROM rom_shares;", params![])?;
+        tx.execute("INSERT INTO rom_shares (rom_id, sets) SELECT rom_id, COUNT(DISTINCT game_name) FROM game_rom_refs GROUP BY rom_id;", params![])?;
+        tx.commit()?;
 
         Ok(())
     }
@@ -330,9 +618,504 @@ impl <'d> DBWriter<'d> {
         Ok(())
     }
 
-    fn get_rom_ids(&mut self, roms: Vec<DataFile>) -> Result<Vec<GameFileBufferItem>> {
+    fn create_table_releases(&self) -> Result<()> {
+        debug!("Creating releases table");
+        self.remove_table_if_exist("releases")?;
+        self.conn.execute_batch(RELEASES_SCHEMA)?;
+
+        Ok(())
+    }
+
+    pub fn set_import_source(&mut self, source: ImportSource) {
+        self.import_source = Some(source);
+    }
+
+    /// Logs the games and roms written, the roms reused and the rows that couldn't be inserted
+    pub fn set_import_log(&mut self, import_log: ImportLog) {
+        self.import_log = Some(import_log);
+    }
+
+    /// Registers a new DAT, the games added from now on belong to it. Returns the id of the DAT
+    pub fn add_dat(&mut self, dat: &DatInfo) -> Result<u32> {
+        // The games in the buffer belong to the previous DAT
+        self.write_buffer()?;
+        let source = dat.source.as_ref().or(self.import_source.as_ref());
+        self.conn.execute("INSERT INTO info (name, description, version, source, source_sha1, imported_at, romst_version) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7);",
+            params![ dat.name, dat.description, dat.version,
+                source.map(|source| &source.path), source.and_then(|source| source.sha1.as_ref()),
+                source.map(|source| &source.imported_at), source.map(|source| &source.romst_version) ])?;
+        let dat_id = self.conn.last_insert_rowid() as u32;
+        for (key, value) in &dat.header {
+            self.conn.execute("INSERT OR REPLACE INTO dat_header (dat_id, key, value) VALUES (?1, ?2, ?3);",
+                params![ dat_id, key, value ])?;
+        }
+        self.current_dat = Some(dat_id);
+        log_line(&mut self.import_log, format!("DAT {}: {} {}", dat_id, dat.name, dat.version.as_deref().unwrap_or_default()));
+
+        Ok(dat_id)
+    }
+
+    /// Replaces the header of a DAT already in the database, the games added from now on belong to it
+    pub fn update_dat(&mut self, dat_id: u32, dat: &DatInfo) -> Result<()> {
+        self.write_buffer()?;
+        let source = dat.source.as_ref().or(self.import_source.as_ref());
+        self.conn.execute("UPDATE info SET name = ?2, description = ?3, version = ?4, source = ?5, source_sha1 = ?6, imported_at = ?7, romst_version = ?8
+            WHERE dat_id = ?1;",
+            params![ dat_id, dat.name, dat.description, dat.version,
+                source.map(|source| &source.path), source.and_then(|source| source.sha1.as_ref()),
+                source.map(|source| &source.imported_at), source.map(|source| &source.romst_version) ])?;
+        self.conn.execute("DELETE FROM dat_header WHERE dat_id = ?1;", params![ dat_id ])?;
+        for (key, value) in &dat.header {
+            self.conn.execute("INSERT OR REPLACE INTO dat_header (dat_id, key, value) VALUES (?1, ?2, ?3);",
+                params![ dat_id, key, value ])?;
+        }
+        self.current_dat = Some(dat_id);
+        log_line(&mut self.import_log, format!("DAT {} updated: {} {}", dat_id, dat.name, dat.version.as_deref().unwrap_or_default()));
+
+        Ok(())
+    }
+
+    /// Continues the ids of the roms and disks after the ones in the database, to write more games in it
+    pub fn continue_ids(&mut self) -> Result<()> {
+        let (rom, disk): (u32, u32) = self.conn.query_row("SELECT (SELECT IFNULL(MAX(id) + 1, 0) FROM roms), (SELECT IFNULL(MAX(id) + 1, 0) FROM disks);",
+            params![], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        self.buffer.ids = IdsCounter { rom, disk };
+
+        Ok(())
+    }
+
+    /// Records how far the import went with every write of the buffer, to resume it if it's interrupted
+    pub fn record_progress(&mut self, source_sha1: Option<String>) {
+        self.progress = Some(ImportProgress { source_sha1, dat_id: None, entries: 0 });
+    }
+
+    /// Writes the buffer at least this often, so an interrupted import loses less of it
+    pub fn set_autosave(&mut self, interval: Duration) {
+        self.autosave = Some(interval);
+    }
+
+    /// Goes on with an interrupted import of the same DAT, identified by its sha1, skipping the entries already
+    /// written. Returns how many are skipped, none if the database has no progress of that DAT to resume
+    pub fn resume(&mut self, source_sha1: Option<&str>) -> Result<Option<u32>> {
+        let progress = match self.get_reader().get_import_progress()? {
+            Some(progress) if progress.entries > 0 && source_sha1.is_some() && progress.source_sha1.as_deref() == source_sha1 => progress,
+            _ => return Ok(None),
+        };
+        self.continue_ids()?;
+        self.current_dat = progress.dat_id;
+        self.resume_entries = progress.entries;
+        log_line(&mut self.import_log, format!("Resuming the import after {} entries", progress.entries));
+        let entries = progress.entries;
+        self.progress = Some(progress);
+
+        Ok(Some(entries))
+    }
+
+    fn is_resuming(&self) -> bool {
+        self.resume_entries > 0
+    }
+
+    /// The buffer is full, or it's time to save it
+    fn should_write(&self) -> bool {
+        let len = self.buffer.len();
+        len > 0 && (len >= self.buffer_size as usize || self.autosave.is_some_and(|interval| self.last_write.elapsed() >= interval))
+    }
+
+    /// A reader of the database being written, the games still in the buffer are not there yet
+    pub fn get_reader(&self) -> DBReader<'_> {
+        DBReader::from_connection(&*self.conn)
+    }
+
+    /// Removes the roms and rom names not used by any game. Returns the number of roms removed
+    pub fn prune_unreferenced_roms(&mut self) -> Result<usize> {
+        self.migrate_rom_names()?;
+        self.rom_name_ids.clear();
+        let tx = self.conn.transaction()?;
+        let roms = tx.execute("DELETE FROM roms WHERE id NOT IN (SELECT rom_id FROM game_rom_refs);", params![])?;
+        tx.execute("DELETE FROM rom_names WHERE id NOT IN (SELECT name_id FROM game_rom_refs);", params![])?;
+        tx.commit()?;
+
+        Ok(roms)
+    }
+
+    /// Removes a DAT with all its games, and the roms and disks not used anymore by any other game.
+    /// Returns the number of games removed
+    pub fn remove_dat(&mut self, dat_id: u32) -> Result<usize> {
+        self.migrate_rom_names()?;
+        self.rom_name_ids.clear();
+        let tx = self.conn.transaction()?;
+        let game_condition = "game_name IN (SELECT name FROM games WHERE dat_id = ?1)";
+        tx.execute(&format!("DELETE FROM game_rom_refs WHERE {};", game_condition), params![ dat_id ])?;
+        tx.execute(&format!("DELETE FROM game_disks WHERE {};", game_condition), params![ dat_id ])?;
+        tx.execute(&format!("DELETE FROM devices WHERE {};", game_condition), params![ dat_id ])?;
+        tx.execute_batch(RELEASES_SCHEMA)?;
+        tx.execute(&format!("DELETE FROM releases WHERE {};", game_condition), params![ dat_id ])?;
+        let games = tx.execute("DELETE FROM games WHERE dat_id = ?1;", params![ dat_id ])?;
+        tx.execute("DELETE FROM info WHERE dat_id = ?1;", params![ dat_id ])?;
+        tx.execute("DELETE FROM dat_header WHERE dat_id = ?1;", params![ dat_id ])?;
+
+        // Cleaning up what is not referenced anymore
+        tx.execute("DELETE FROM roms WHERE id NOT IN (SELECT rom_id FROM game_rom_refs);", params![])?;
+        tx.execute("DELETE FROM rom_names WHERE id NOT IN (SELECT name_id FROM game_rom_refs);", params![])?;
+        tx.execute("DELETE FROM disks WHERE id NOT IN (SELECT disk_id FROM game_disks);", params![])?;
+        tx.execute("DELETE FROM samples WHERE sample_set NOT IN (SELECT sample_of FROM games WHERE sample_of IS NOT NULL);", params![])?;
+        tx.commit()?;
+        self.update_rom_shares()?;
+
+        Ok(games)
+    }
+
+    // User data, it is kept when the DAT is imported again
+    fn create_table_exclusions(&self) -> Result<()> {
+        debug!("Creating exclusions table");
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS exclusions (
+                game_name   TEXT PRIMARY KEY);",
+            params![])?;
+
+        Ok(())
+    }
+
+    /// Marks the sets as excluded, so they are ignored in reports
+    pub fn add_exclusions<S>(&self, game_names: &[S]) -> Result<()> where S: AsRef<str> {
+        self.create_table_exclusions()?;
+        for game_name in game_names {
+            self.conn.execute("INSERT OR IGNORE INTO exclusions (game_name) VALUES (?1);", params![ game_name.as_ref() ])?;
+        }
+
+        Ok(())
+    }
+
+    pub fn remove_exclusions<S>(&self, game_names: &[S]) -> Result<()> where S: AsRef<str> {
+        self.create_table_exclusions()?;
+        for game_name in game_names {
+            self.conn.execute("DELETE FROM exclusions WHERE game_name = ?1;", params![ game_name.as_ref() ])?;
+        }
+
+        Ok(())
+    }
+
+    // User data, it is kept when the DAT is imported again
+    fn create_table_tags(&self) -> Result<()> {
+        debug!("Creating tags table");
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS tags (
+                game_name   TEXT,
+                tag         TEXT,
+                PRIMARY KEY (game_name, tag));",
+            params![])?;
+        self.conn.execute("CREATE INDEX IF NOT EXISTS tags_tag ON tags(tag);", params![])?;
+
+        Ok(())
+    }
+
+    pub fn add_tag<S>(&self, game_names: &[S], tag: &str) -> Result<()> where S: AsRef<str> {
+        self.create_table_tags()?;
+        for game_name in game_names {
+            self.conn.execute("INSERT OR IGNORE INTO tags (game_name, tag) VALUES (?1, ?2);", params![ game_name.as_ref(), tag ])?;
+        }
+
+        Ok(())
+    }
+
+    pub fn remove_tag<S>(&self, game_names: &[S], tag: &str) -> Result<()> where S: AsRef<str> {
+        self.create_table_tags()?;
+        for game_name in game_names {
+            self.conn.execute("DELETE FROM tags WHERE game_name = ?1 AND tag = ?2;", params![ game_name.as_ref(), tag ])?;
+        }
+
+        Ok(())
+    }
+
+    // User data, it is kept when the DAT is imported again
+    fn create_table_notes(&self) -> Result<()> {
+        debug!("Creating notes table");
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS notes (
+                game_name   TEXT PRIMARY KEY,
+                note        TEXT);",
+            params![])?;
+
+        Ok(())
+    }
+
+    /// Sets the note for a game, if the note is `None`, the current note is removed
+    pub fn set_note(&self, game_name: &str, note: Option<&str>) -> Result<()> {
+        self.create_table_notes()?;
+        match note {
+            Some(note) => {
+                self.conn.execute("INSERT OR REPLACE INTO notes (game_name, note) VALUES (?1, ?2);", params![ game_name, note ])?;
+            }
+            None => {
+                self.conn.execute("DELETE FROM notes WHERE game_name = ?1;", params![ game_name ])?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn create_table_report_history(&self) -> Result<()> {
+        debug!("Creating report history table");
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS report_history (
+                id          INTEGER PRIMARY KEY,
+                source      TEXT NOT NULL,
+                date_time   TEXT NOT NULL,
+                rom_mode    TEXT NOT NULL,
+                complete    INTEGER NOT NULL,
+                fixable     INTEGER NOT NULL,
+                incomplete  INTEGER NOT NULL,
+                missing     INTEGER NOT NULL);",
+            params![])?;
+
+        Ok(())
+    }
+
+    /// Stores the counts of a report in the history, returns the id of the new entry
+    pub fn add_report_history(&self, entry: &ReportHistoryEntry) -> Result<u32> {
+        self.create_table_report_history()?;
+        self.conn.execute(
+            "INSERT INTO report_history (source, date_time, rom_mode, complete, fixable, incomplete, missing)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7);",
+            params![ entry.source, entry.date_time, entry.rom_mode, entry.complete, entry.fixable, entry.incomplete, entry.missing ])?;
+
+        Ok(self.conn.last_insert_rowid() as u32)
+    }
+
+    // User data, it is kept when the DAT is imported again
+    fn create_table_game_changes(&self) -> Result<()> {
+        debug!("Creating game changes table");
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS game_changes (
+                game_name       TEXT PRIMARY KEY,
+                last_version    TEXT,
+                removed_in      TEXT,
+                successor       TEXT,
+                date_time       TEXT NOT NULL);",
+            params![])?;
+
+        Ok(())
+    }
+
+    /// Records the games removed or renamed by a DAT imported over the database. The games in the DAT lose the
+    /// record they had from an older import, as they are back
+    pub fn update_game_changes<S>(&self, changes: &[GameChange], current_games: &[S]) -> Result<()> where S: AsRef<str> {
+        self.create_table_game_changes()?;
+        for game_name in current_games {
+            self.conn.execute("DELETE FROM game_changes WHERE game_name = ?1;", params![ game_name.as_ref() ])?;
+        }
+        for change in changes {
+            self.conn.execute(
+                "INSERT OR REPLACE INTO game_changes (game_name, last_version, removed_in, successor, date_time) VALUES (?1, ?2, ?3, ?4, ?5);",
+                params![ change.game_name, change.last_version, change.removed_in, change.successor, change.date_time ])?;
+        }
+
+        Ok(())
+    }
+
+    // User data, it is kept when the DAT is imported again
+    fn create_table_game_aliases(&self) -> Result<()> {
+        debug!("Creating game aliases table");
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS game_aliases (
+                alias       TEXT PRIMARY KEY,
+                game_name   TEXT NOT NULL);",
+            params![])?;
+
+        Ok(())
+    }
+
+    /// Adds the old names of the games. The aliases of a game that is renamed again point to the new name
+    pub fn add_game_aliases(&self, aliases: &[GameAlias]) -> Result<()> {
+        self.create_table_game_aliases()?;
+        for alias in aliases {
+            self.conn.execute("UPDATE game_aliases SET game_name = ?2 WHERE game_name = ?1;", params![ alias.alias, alias.game_name ])?;
+            self.conn.execute("INSERT OR REPLACE INTO game_aliases (alias, game_name) VALUES (?1, ?2);", params![ alias.alias, alias.game_name ])?;
+        }
+        // A game renamed back to an old name
+        self.conn.execute("DELETE FROM game_aliases WHERE alias = game_name;", params![])?;
+
+        Ok(())
+    }
+
+    // Editing, to maintain a DAT in the database. The caller checks that the changes are valid
+    /// Adds a game without roms to a DAT
+    pub fn insert_game(&mut self, game: &Game, dat_id: Option<u32>) -> Result<()> {
+        self.migrate_machine_info()?;
+        self.conn.execute("INSERT INTO games (name, clone_of, rom_of, source_file, sample_of, info_desc, info_year, info_manuf, dat_id, is_bios, is_device, is_mechanical, runnable, driver_status, software_list)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15);",
+            params![ game.name, game.clone_of, game.rom_of, game.source_file, game.sample_of, game.info_description, game.info_year, game.info_manufacturer, dat_id,
+                game.is_bios, game.is_device, game.is_mechanical, game.runnable, game.driver_status, game.software_list ])?;
+        self.update_parent_refs(&game.name)?;
+
+        Ok(())
+    }
+
+    /// Updates a game, when renamed everything pointing to the game follows it and the old name becomes an alias
+    pub fn update_game(&mut self, game_name: &str, game: &Game) -> Result<()> {
+        self.migrate_rom_names()?;
+        self.migrate_machine_info()?;
+        let tx = self.conn.transaction()?;
+        tx.execute("UPDATE games SET name = ?2, clone_of = ?3, rom_of = ?4, info_desc = ?5, info_year = ?6, info_manuf = ?7,
+            is_bios = ?8, is_device = ?9, is_mechanical = ?10, runnable = ?11, driver_status = ?12, software_list = ?13 WHERE name = ?1;",
+            params![ game_name, game.name, game.clone_of, game.rom_of, game.info_description, game.info_year, game.info_manufacturer,
+                game.is_bios, game.is_device, game.is_mechanical, game.runnable, game.driver_status, game.software_list ])?;
+        if game_name != game.name {
+            tx.execute_batch(RELEASES_SCHEMA)?;
+            for table in ["game_rom_refs", "game_disks", "devices", "releases"].iter() {
+                tx.execute(&format!("UPDATE {} SET game_name = ?2 WHERE game_name = ?1;", table), params![ game_name, game.name ])?;
+            }
+            tx.execute("UPDATE game_rom_refs SET parent = ?2 WHERE parent = ?1;", params![ game_name, game.name ])?;
+            tx.execute("UPDATE devices SET device_ref = ?2 WHERE device_ref = ?1;", params![ game_name, game.name ])?;
+            for column in ["clone_of", "rom_of"].iter() {
+                tx.execute(&format!("UPDATE games SET {} = ?2 WHERE {} = ?1;", column, column), params![ game_name, game.name ])?;
+            }
+        }
+        tx.commit()?;
+
+        if game_name != game.name {
+            self.create_table_exclusions()?;
+            self.create_table_tags()?;
+            self.create_table_notes()?;
+            for table in ["exclusions", "tags", "notes"].iter() {
+                self.conn.execute(&format!("UPDATE OR REPLACE {} SET game_name = ?2 WHERE game_name = ?1;", table), params![ game_name, game.name ])?;
+            }
+            self.add_game_aliases(&[GameAlias { alias: game_name.to_string(), game_name: game.name.to_owned() }])?;
+        }
+        self.update_parent_refs(&game.name)?;
+
+        Ok(())
+    }
+
+    /// Removes a game, with the roms and disks not used anymore by any other game
+    pub fn delete_game(&mut self, game_name: &str) -> Result<()> {
+        self.delete_game_entries(&[game_name])?;
+        self.rom_name_ids.clear();
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM roms WHERE id NOT IN (SELECT rom_id FROM game_rom_refs);", params![])?;
+        tx.execute("DELETE FROM rom_names WHERE id NOT IN (SELECT name_id FROM game_rom_refs);", params![])?;
+        tx.execute("DELETE FROM disks WHERE id NOT IN (SELECT disk_id FROM game_disks);", params![])?;
+        tx.commit()?;
+        self.update_rom_shares()?;
+
+        Ok(())
+    }
+
+    /// Removes the games with everything they reference, but keeps the roms and disks for the games written next.
+    /// The user data of the games is kept, in case they are written again
+    pub fn delete_game_entries<S>(&mut self, game_names: &[S]) -> Result<()> where S: AsRef<str> {
+        self.migrate_rom_names()?;
+        let tx = self.conn.transaction()?;
+        tx.execute_batch(RELEASES_SCHEMA)?;
+        for game_name in game_names {
+            for table in ["game_rom_refs", "game_disks", "devices", "releases"].iter() {
+                tx.execute(&format!("DELETE FROM {} WHERE game_name = ?1;", table), params![ game_name.as_ref() ])?;
+            }
+            tx.execute("DELETE FROM games WHERE name = ?1;", params![ game_name.as_ref() ])?;
+            log_line(&mut self.import_log, format!("Removed game `{}`", game_name.as_ref()));
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Like `delete_game_entries`, but only the games of a DAT, the ones with the same name in other DATs are kept
+    pub fn delete_dat_game_entries<S>(&mut self, dat_id: u32, game_names: &[S]) -> Result<()> where S: AsRef<str> {
+        let mut dat_games = vec![];
+        for game_name in game_names {
+            let in_dat: bool = self.conn.query_row("SELECT EXISTS (SELECT 1 FROM games WHERE name = ?1 AND dat_id = ?2);",
+                params![ game_name.as_ref(), dat_id ], |row| row.get(0))?;
+            if in_dat {
+                dat_games.push(game_name.as_ref());
+            }
+        }
+        self.delete_game_entries(&dat_games)
+    }
+
+    /// Removes the roms, disks and samples not used anymore by any game, after the games are updated
+    pub fn prune_unreferenced(&mut self) -> Result<()> {
+        self.rom_name_ids.clear();
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM roms WHERE id NOT IN (SELECT rom_id FROM game_rom_refs);", params![])?;
+        tx.execute("DELETE FROM rom_names WHERE id NOT IN (SELECT name_id FROM game_rom_refs);", params![])?;
+        tx.execute("DELETE FROM disks WHERE id NOT IN (SELECT disk_id FROM game_disks);", params![])?;
+        tx.execute("DELETE FROM samples WHERE sample_set NOT IN (SELECT sample_of FROM games WHERE sample_of IS NOT NULL);", params![])?;
+        tx.commit()?;
+        self.update_rom_shares()?;
+
+        Ok(())
+    }
+
+    /// Adds a rom to a game, or replaces the one named `rom_name`. Identical roms are stored once
+    pub fn set_game_rom(&mut self, game_name: &str, rom_name: Option<&str>, rom: &DataFile) -> Result<()> {
+        self.migrate_rom_optional()?;
+        self.migrate_rom_checksums()?;
+        self.rom_name_ids.clear();
+        let tx = self.conn.transaction()?;
+        let info = &rom.info;
+        let rom_id: Option<u32> = tx.query_row("SELECT id FROM roms WHERE sha1 IS ?1 AND md5 IS ?2 AND crc IS ?3 AND size IS ?4;",
+            params![ info.sha1, info.md5, info.crc, info.size ], |row| row.get(0)).ok();
+        let rom_id = match rom_id {
+            Some(rom_id) => rom_id,
+            None => {
+                tx.execute("INSERT INTO roms (sha1, md5, crc, size, sha256, xxh3) VALUES (?1, ?2, ?3, ?4, ?5, ?6);",
+                    params![ info.sha1, info.md5, info.crc, info.size, info.sha256, info.xxh3 ])?;
+                tx.last_insert_rowid() as u32
+            }
+        };
+        tx.execute("INSERT OR IGNORE INTO rom_names (name) VALUES (?1);", params![ rom.name ])?;
+        let name_id: u32 = tx.query_row("SELECT id FROM rom_names WHERE name = ?1;", params![ rom.name ], |row| row.get(0))?;
+        if let Some(rom_name) = rom_name {
+            tx.execute("DELETE FROM game_rom_refs WHERE game_name = ?1 AND name_id IN (SELECT id FROM rom_names WHERE name = ?2);", params![ game_name, rom_name ])?;
+        }
+        tx.execute("INSERT INTO game_rom_refs (game_name, rom_id, name_id, status, bios, optional) VALUES (?1, ?2, ?3, ?4, ?5, ?6);",
+            params![ game_name, rom_id, name_id, rom.status, rom.bios.as_deref().unwrap_or_default(), rom.optional ])?;
+        tx.execute("DELETE FROM roms WHERE id NOT IN (SELECT rom_id FROM game_rom_refs);", params![])?;
+        tx.execute("DELETE FROM rom_names WHERE id NOT IN (SELECT name_id FROM game_rom_refs);", params![])?;
+        tx.commit()?;
+        self.update_parent_refs(game_name)?;
+        self.update_rom_shares()?;
+
+        Ok(())
+    }
+
+    /// Removes a rom from a game, and from the database if no other game uses it. Returns if the game had the rom
+    pub fn remove_game_rom(&mut self, game_name: &str, rom_name: &str) -> Result<bool> {
+        self.migrate_rom_names()?;
+        self.rom_name_ids.clear();
+        let tx = self.conn.transaction()?;
+        let removed = tx.execute("DELETE FROM game_rom_refs WHERE game_name = ?1 AND name_id IN (SELECT id FROM rom_names WHERE name = ?2);", params![ game_name, rom_name ])?;
+        tx.execute("DELETE FROM roms WHERE id NOT IN (SELECT rom_id FROM game_rom_refs);", params![])?;
+        tx.execute("DELETE FROM rom_names WHERE id NOT IN (SELECT name_id FROM game_rom_refs);", params![])?;
+        tx.commit()?;
+        self.update_parent_refs(game_name)?;
+        self.update_rom_shares()?;
+
+        Ok(removed > 0)
+    }
+
+    /// Marks again the roms a game and its clones get from their parent, like at the end of an import
+    fn update_parent_refs(&mut self, game_name: &str) -> Result<()> {
+        self.conn.execute("UPDATE game_rom_refs SET parent = (
+                SELECT games.rom_of FROM games JOIN game_rom_refs AS parent_refs ON parent_refs.game_name = games.rom_of
+                WHERE games.name = game_rom_refs.game_name AND parent_refs.rom_id = game_rom_refs.rom_id LIMIT 1)
+            WHERE game_name = ?1 OR game_name IN (SELECT name FROM games WHERE rom_of = ?1);", params![ game_name ])?;
+
+        Ok(())
+    }
+
+    fn get_rom_ids(&mut self, roms: Vec<DataFile>, game_name: &str) -> Result<Vec<GameFileBufferItem>> {
         // We search the database
-        let rom_ids = DBReader::get_ids_from_files(self.conn, roms)?;
+        // The roms of the DAT are the same ones as in the database with any checksum in common
+        let rom_ids = DBReader::get_ids_from_files(self.conn, roms, MatchPolicy::Any)?;
+        if self.import_log.is_some() {
+            let in_buffer = rom_ids.not_found.iter().chain(rom_ids.ignored.iter())
+                .filter_map(|rom| self.buffer.roms.get(&rom.info).map(|rom_id| (*rom_id, &rom.name)));
+            let reused = rom_ids.found.iter().map(|found| (found.id, &found.file.name)).chain(in_buffer).collect::<Vec<_>>();
+            for (rom_id, rom_name) in reused {
+                log_line(&mut self.import_log, format!("Reused rom {} as `{}` in the game `{}`", rom_id, rom_name, game_name));
+            }
+        }
 
         let mut rom_name_pair: Vec<GameFileBufferItem> = rom_ids.found.into_iter().map(|db_roms|{
             GameFileBufferItem::from_data_file(db_roms.id, db_roms.file)
@@ -389,6 +1172,7 @@ impl <'d> DBWriter<'d> {
         let devices_buffer = &self.buffer.device_refs;
         let disk_buffer = &self.buffer.disks;
         let game_disk_buffer = &self.buffer.game_disks;
+        let release_buffer = &self.buffer.releases;
 
         let values = game_buffer.values();
         for value in values {
@@ -400,13 +1184,23 @@ impl <'d> DBWriter<'d> {
                 game.sample_of,
                 game.info_description,
                 game.info_year,
-                game.info_manufacturer];
-            let result = tx.execute("INSERT INTO games (name, clone_of, rom_of, source_file, sample_of, info_desc, info_year, info_manuf)
-                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8);",
+                game.info_manufacturer,
+                self.current_dat,
+                game.is_bios,
+                game.is_device,
+                game.is_mechanical,
+                game.runnable,
+                game.driver_status,
+                game.software_list];
+            let result = tx.execute("INSERT INTO games (name, clone_of, rom_of, source_file, sample_of, info_desc, info_year, info_manuf, dat_id, is_bios, is_device, is_mechanical, runnable, driver_status, software_list)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15);",
                 p);
             match result {
-                    Ok(_) => {}
-                    Err(e) => { error!("Error inserting row in the games db: {}", e) }
+                    Ok(_) => { log_line(&mut self.import_log, format!("Game `{}`", game.name)) }
+                    Err(e) => {
+                        error!("Error inserting row in the games db: {}", e);
+                        log_line(&mut self.import_log, format!("ERROR inserting the game `{}`: {}", game.name, e));
+                    }
                 }
         }
 
@@ -415,11 +1209,17 @@ impl <'d> DBWriter<'d> {
             let rom = rom_data.0;
 
             let result = tx.execute(
-                "INSERT INTO roms (id, sha1, md5, crc, size) VALUES (?1, ?2, ?3, ?4, ?5);",
-                params![ rom_row_id, rom.sha1, rom.md5, rom.crc, rom.size ]);
+                "INSERT INTO roms (id, sha1, md5, crc, size, sha256, xxh3) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7);",
+                params![ rom_row_id, rom.sha1, rom.md5, rom.crc, rom.size, rom.sha256, rom.xxh3 ]);
             match result {
-                Ok(_n) => { debug!("Inserted rom {} with id {}", rom, rom_row_id) }
-                Err(e) => { error!("Error adding rom `{}` with id `{}`: {}", rom, rom_row_id, e) }
+                Ok(_n) => {
+                    debug!("Inserted rom {} with id {}", rom, rom_row_id);
+                    log_line(&mut self.import_log, format!("Rom {}: {}", rom_row_id, rom));
+                }
+                Err(e) => {
+                    error!("Error adding rom `{}` with id `{}`: {}", rom, rom_row_id, e);
+                    log_line(&mut self.import_log, format!("ERROR inserting the rom {} with id {}: {}", rom, rom_row_id, e));
+                }
             }
         }
 
@@ -427,12 +1227,24 @@ impl <'d> DBWriter<'d> {
             let game_name = game_roms.0;
             let rom_id_names = game_roms.1;
             for rom_id_name in rom_id_names {
+                let name_id = match self.rom_name_ids.get(&rom_id_name.name) {
+                    Some(name_id) => *name_id,
+                    None => {
+                        tx.execute("INSERT OR IGNORE INTO rom_names (name) VALUES (?1);", params![ rom_id_name.name ])?;
+                        let name_id = tx.query_row("SELECT id FROM rom_names WHERE name = ?1;", params![ rom_id_name.name ], |row| row.get(0))?;
+                        self.rom_name_ids.insert(rom_id_name.name.to_owned(), name_id);
+                        name_id
+                    }
+                };
                 let result = tx.execute(
-                    "INSERT INTO game_roms (game_name, rom_id, name, status) VALUES (?1, ?2, ?3, ?4);",
-                    params![ game_name, rom_id_name.id, rom_id_name.name, rom_id_name.status ] );
+                    "INSERT INTO game_rom_refs (game_name, rom_id, name_id, status, bios, optional) VALUES (?1, ?2, ?3, ?4, ?5, ?6);",
+                    params![ game_name, rom_id_name.id, name_id, rom_id_name.status, rom_id_name.bios.as_deref().unwrap_or_default(), rom_id_name.optional ] );
                 match result {
                     Ok(_n) => { debug!("Inserted rom {} with id {} to the game {}", rom_id_name.name, rom_id_name.id, game_name) }
-                    Err(e) => { error!("Error adding rom `{}` to the game {}: {}", rom_id_name.name, "", e) }
+                    Err(e) => {
+                        error!("Error adding rom `{}` to the game {}: {}", rom_id_name.name, game_name, e);
+                        log_line(&mut self.import_log, format!("ERROR adding the rom `{}` with id {} to the game `{}`: {}", rom_id_name.name, rom_id_name.id, game_name, e));
+                    }
                 }
             }
         }
@@ -446,7 +1258,10 @@ impl <'d> DBWriter<'d> {
                     params![sample_set, sample]);
                 match result {
                     Ok(_n) => { debug!("Inserted sample `{}` for sample set `{}`", sample, sample_set) }
-                    Err(e) => { error!("Error inserting sample `{}` for sample set `{}`: {}", sample, sample_set, e) }
+                    Err(e) => {
+                        error!("Error inserting sample `{}` for sample set `{}`: {}", sample, sample_set, e);
+                        log_line(&mut self.import_log, format!("ERROR inserting the sample `{}` for the sample set `{}`: {}", sample, sample_set, e));
+                    }
                 }
             }
         }
@@ -460,7 +1275,10 @@ impl <'d> DBWriter<'d> {
                     params![game_name, device]);
                 match result {
                     Ok(_) => { debug!("Inserted device ref `{}` for game `{}`", device, game_name); }
-                    Err(e) => { error!("Error inserting device ref `{}` for game `{}`: {}", device, game_name, e); }
+                    Err(e) => {
+                        error!("Error inserting device ref `{}` for game `{}`: {}", device, game_name, e);
+                        log_line(&mut self.import_log, format!("ERROR inserting the device ref `{}` for the game `{}`: {}", device, game_name, e));
+                    }
                 }
             }
         }
@@ -474,7 +1292,10 @@ impl <'d> DBWriter<'d> {
                 params![disk_id, disk.sha1, disk.region, disk.status]);
                 match result {
                     Ok(_) => { debug!("Inserted disk `{}` with id `{}`", disk, disk_id); }
-                    Err(e) => { error!("Error inserting disk `{}`: {}", disk, e); }
+                    Err(e) => {
+                        error!("Error inserting disk `{}`: {}", disk, e);
+                        log_line(&mut self.import_log, format!("ERROR inserting the disk {} with id {}: {}", disk, disk_id, e));
+                    }
                 }
         }
 
@@ -487,12 +1308,44 @@ impl <'d> DBWriter<'d> {
                     params![game_name, id.id, id.name]);
                 match result {
                     Ok(_) => { debug!("Inserted disk id `{}` for game `{}`", id.id, game_name); }
-                    Err(e) => { error!("Error inserting disk id `{}` for game `{}`: {}", id.id, game_name, e); }
+                    Err(e) => {
+                        error!("Error inserting disk id `{}` for game `{}`: {}", id.id, game_name, e);
+                        log_line(&mut self.import_log, format!("ERROR adding the disk `{}` with id {} to the game `{}`: {}", id.name, id.id, game_name, e));
+                    }
+                }
+            }
+        }
+
+        if let Some(progress) = self.progress.as_mut() {
+            progress.dat_id = self.current_dat;
+            progress.entries = self.entries;
+            tx.execute_batch(IMPORT_PROGRESS_SCHEMA)?;
+            tx.execute("INSERT OR REPLACE INTO import_progress (id, source_sha1, dat_id, entries) VALUES (0, ?1, ?2, ?3);",
+                params![ progress.source_sha1, progress.dat_id, progress.entries ])?;
+        }
+
+        if !release_buffer.is_empty() {
+            tx.execute_batch(RELEASES_SCHEMA)?;
+        }
+        for (game_name, releases) in release_buffer {
+            for release in releases {
+                let result = tx.execute(
+                    "INSERT OR IGNORE INTO releases (game_name, name, region, language, date, is_default) VALUES (?1, ?2, ?3, ?4, ?5, ?6);",
+                    params![game_name, release.name, release.region, release.language, release.date, release.default]);
+                match result {
+                    Ok(_) => { debug!("Inserted release `{}` for game `{}`", release.name, game_name); }
+                    Err(e) => {
+                        error!("Error inserting release `{}` for game `{}`: {}", release.name, game_name, e);
+                        log_line(&mut self.import_log, format!("ERROR adding the release `{}` to the game `{}`: {}", release.name, game_name, e));
+                    }
                 }
             }
         }
 
         tx.commit()?;
+        if let Some(import_log) = self.import_log.as_mut() {
+            import_log.writer.flush()?;
+        }
         self.buffer.games.clear();
         self.buffer.roms.clear();
         self.buffer.game_roms.clear();
@@ -500,6 +1353,8 @@ impl <'d> DBWriter<'d> {
         self.buffer.device_refs.clear();
         self.buffer.disks.clear();
         self.buffer.game_disks.clear();
+        self.buffer.releases.clear();
+        self.last_write = Instant::now();
 
         Ok(())
     }
@@ -519,15 +1374,11 @@ impl <'d> DBWriter<'d> {
         let game_name = game.name.to_owned();
         self.buffer.add_game(game_name, game); 
 
-        if self.buffer.len() as u16 >= self.buffer_size {
-            self.write_buffer()?;
-        };
-
         Ok(())
     }
 
     fn add_roms_for_game(&mut self, roms: Vec<DataFile>, game_name: &str) -> Result<()> {
-        let rom_list = self.get_rom_ids(roms)?;
+        let rom_list = self.get_rom_ids(roms, game_name)?;
 
         self.buffer.add_roms_for_game(game_name.to_string(), rom_list);
 
@@ -557,10 +1408,31 @@ impl <'d> DBWriter<'d> {
 
 impl <'d> DataWriter for DBWriter<'d> {
     fn init(&self) -> Result<()> {
+        // The schema of a resumed import already has the entries written
+        if self.is_resuming() {
+            return Ok(());
+        }
         self.create_schema()
     }
-    
+
+    fn on_dat_info(&mut self, header: DatInfo) -> Result<()> {
+        if self.is_resuming() && self.current_dat.is_some() {
+            return Ok(());
+        }
+        self.add_dat(&header)?;
+
+        Ok(())
+    }
+
     fn on_new_entry(&mut self, game: Game, roms: Vec<DataFile>, disks: Vec<GameDisk>, samples: Vec<String>, device_refs: Vec<String>) -> Result<()> {
+        // Written before the entry, so the previous one is written with its releases
+        if self.should_write() {
+            self.write_buffer()?;
+        }
+        self.entries += 1;
+        if self.entries <= self.resume_entries {
+            return Ok(());
+        }
         let game_ref = Rc::new(game);
 
         let game_name = &game_ref.name;
@@ -577,6 +1449,15 @@ impl <'d> DataWriter for DBWriter<'d> {
         Ok(())
     }
 
+    fn on_releases(&mut self, game_name: &str, releases: Vec<Release>) -> Result<()> {
+        if self.entries <= self.resume_entries {
+            return Ok(());
+        }
+        self.buffer.releases.entry(game_name.to_string()).or_default().extend(releases);
+
+        Ok(())
+    }
+
     fn finish(&mut self) -> Result<()> {
         self.write_buffer()?;
         let roms_from_parents = self.get_roms_from_parents()?;
@@ -587,13 +1468,17 @@ impl <'d> DataWriter for DBWriter<'d> {
             let rom_id = item.1;
             let parent = item.2;
 
-            let result = tx.execute("UPDATE game_roms SET parent = ?1
-                WHERE game_roms.game_name = ?2 AND game_roms.rom_id = ?3;", params![parent, game_name, rom_id])?;
+            let result = tx.execute("UPDATE game_rom_refs SET parent = ?1
+                WHERE game_rom_refs.game_name = ?2 AND game_rom_refs.rom_id = ?3;", params![parent, game_name, rom_id])?;
             if result > 1 {
                 debug!("Updated {} rows, should be only 1 for game {}, rom_id {}, with parent {}, unless is a 'nodump'", result, game_name, rom_id, parent);
             }
         }
         tx.commit()?;
+        self.update_rom_shares()?;
+        if self.progress.is_some() {
+            self.conn.execute("DROP TABLE IF EXISTS import_progress;", params![])?;
+        }
 
         Ok(())
     }
@@ -601,7 +1486,10 @@ impl <'d> DataWriter for DBWriter<'d> {
 
 #[cfg(test)]
 mod tests {
-    use super::IdsCounter;
+    use std::{fs::File, io::BufReader, path::Path};
+    use rusqlite::OpenFlags;
+    use crate::{RomsetMode, data::{importer::DatImporter, reader::DataReader}, filesystem::FileChecks};
+    use super::*;
 
     #[test]
     fn test_counter() {
@@ -626,4 +1514,230 @@ mod tests {
             assert_eq!(i as u32, nums[i])
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn migrates_rom_names() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let mut conn = Connection::open_in_memory_with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+        let writer = DBWriter::from_connection(&mut conn, 5);
+        DatImporter::<BufReader<File>, DBWriter>::from_path(&path, writer)?.load_dat()?;
+        let get_rom_names = |conn: &Connection| -> Result<Vec<String>> {
+            let mut roms = DBReader::from_connection(conn).get_romset_roms("game1a", RomsetMode::Split)?.1.into_iter()
+                .map(|rom| format!("{} {}", rom.id, rom.file.name)).collect::<Vec<_>>();
+            roms.sort();
+            Ok(roms)
+        };
+        let roms = get_rom_names(&conn)?;
+        let names: u32 = conn.query_row("SELECT COUNT(*) FROM rom_names;", params![], |row| row.get(0))?;
+        let names_in_games: u32 = conn.query_row("SELECT COUNT(DISTINCT name) FROM game_roms;", params![], |row| row.get(0))?;
+        assert_eq!(names, names_in_games);
+
+        // The schema before the names were normalized
+        conn.execute_batch("CREATE TABLE old_roms AS SELECT * FROM game_roms;
+            DROP VIEW game_roms; DROP TABLE game_rom_refs; DROP TABLE rom_names;
+            ALTER TABLE old_roms RENAME TO game_roms;
+            CREATE INDEX game_roms_game ON game_roms(game_name);")?;
+        let mut writer = DBWriter::from_connection(&mut conn, 5);
+        assert!(writer.migrate_rom_names()?);
+        assert!(!writer.migrate_rom_names()?);
+        assert!(!writer.migrate_rom_shares()?);
+        drop(writer);
+        conn.execute("DROP TABLE rom_shares;", params![])?;
+        assert!(DBWriter::from_connection(&mut conn, 5).migrate_rom_shares()?);
+        let shares: u32 = conn.query_row("SELECT sets FROM rom_shares JOIN roms ON rom_shares.rom_id = roms.id WHERE roms.crc = '1d460eee';", params![], |row| row.get(0))?;
+        assert_eq!(shares, 2);
+
+        assert_eq!(get_rom_names(&conn)?, roms);
+
+        Ok(())
+    }
+
+    #[test]
+    fn migrates_machine_info() -> Result<()> {
+        let mut conn = Connection::open_in_memory_with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+        // The games before the machine info was stored
+        conn.execute_batch("CREATE TABLE games (name TEXT PRIMARY KEY, clone_of TEXT, rom_of TEXT, source_file TEXT, sample_of TEXT,
+                info_desc TEXT, info_year TEXT, info_manuf TEXT, dat_id INTEGER);
+            INSERT INTO games (name, info_desc) VALUES ('game1', 'Game 1');")?;
+        let mut writer = DBWriter::from_connection(&mut conn, 5);
+        assert!(writer.migrate_machine_info()?);
+        assert!(!writer.migrate_machine_info()?);
+        drop(writer);
+
+        let game = DBReader::from_connection(&conn).get_game("game1").unwrap();
+        assert!(!game.is_bios && !game.is_device && !game.is_mechanical && game.runnable);
+        assert_eq!(game.driver_status, None);
+        assert_eq!(game.software_list, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn migrates_rom_checksums() -> Result<()> {
+        let mut conn = Connection::open_in_memory_with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+        // The roms before the optional checksums were stored
+        conn.execute_batch("CREATE TABLE roms (id INTEGER PRIMARY KEY, sha1 TEXT, md5 TEXT, crc TEXT, size INT);
+            INSERT INTO roms (sha1, crc, size) VALUES ('1234567812345678123456781234567812345678', '12345678', 4);")?;
+        assert_eq!(DBReader::from_connection(&conn).get_file_checks()?.sha256, 0);
+        let mut writer = DBWriter::from_connection(&mut conn, 5);
+        assert!(writer.migrate_rom_checksums()?);
+        assert!(!writer.migrate_rom_checksums()?);
+        drop(writer);
+
+        conn.execute("UPDATE roms SET sha256 = '65c463a75330fc0f331dad1774dd6e81fb3d1b282a7923255f7921590e10c2cb';", params![])?;
+        let file_checks = DBReader::from_connection(&conn).get_file_checks()?.get_file_checks();
+        assert!(file_checks.contains(FileChecks::SHA256));
+        assert!(!file_checks.contains(FileChecks::XXH3));
+
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_the_region_variants_of_the_bios_roms() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("romst_bios_variants_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let dat_path = dir.join("bios.dat");
+        std::fs::write(&dat_path, r#"<?xml version="1.0"?>
+<datafile>
+    <header><name>BIOS</name></header>
+    <machine name="mbios" isbios="yes">
+        <biosset name="usa" description="USA" default="yes"/><biosset name="japan" description="Japan"/>
+        <rom name="common.bin" bios="usa" size="4" crc="12345678" sha1="1234567812345678123456781234567812345678"/>
+        <rom name="common.bin" bios="japan" size="4" crc="12345678" sha1="1234567812345678123456781234567812345678"/>
+        <rom name="region.bin" bios="usa" size="4" crc="87654321" sha1="8765432187654321876543218765432187654321"/>
+        <rom name="region.bin" bios="japan" size="4" crc="abcdef12" sha1="abcdef12abcdef12abcdef12abcdef12abcdef12"/>
+        <rom name="boot.bin" size="4" crc="12121212" sha1="1212121212121212121212121212121212121212"/>
+    </machine>
+</datafile>"#)?;
+        let mut conn = Connection::open_in_memory_with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+        let writer = DBWriter::from_connection(&mut conn, 5);
+        DatImporter::<BufReader<File>, DBWriter>::from_path(&dat_path, writer)?.load_dat()?;
+        std::fs::remove_dir_all(&dir)?;
+
+        let get_roms = |conn: &Connection| -> Result<Vec<String>> {
+            let mut roms = DBReader::from_connection(conn).get_romset_roms("mbios", RomsetMode::Split)?.1.into_iter()
+                .map(|rom| rom.file.get_name_with_bios()).collect::<Vec<_>>();
+            roms.sort();
+            Ok(roms)
+        };
+        assert_eq!(get_roms(&conn)?, vec!["boot.bin", "common.bin [bios: japan]", "common.bin [bios: usa]",
+            "region.bin [bios: japan]", "region.bin [bios: usa]"]);
+        let entry = DBReader::from_connection(&conn).get_game_entry("mbios")?.unwrap();
+        assert_eq!(entry.roms.iter().filter(|rom| rom.bios.as_deref() == Some("japan")).count(), 2);
+
+        // The roms before their BIOS was stored
+        conn.execute_batch("CREATE TABLE old_refs AS SELECT DISTINCT game_name, rom_id, name_id, status, parent FROM game_rom_refs;
+            DROP VIEW game_roms; DROP TABLE game_rom_refs;
+            ALTER TABLE old_refs RENAME TO game_rom_refs;
+            CREATE INDEX game_rom_refs_game ON game_rom_refs(game_name);
+            CREATE INDEX game_rom_refs_rom ON game_rom_refs(rom_id);
+            CREATE INDEX game_rom_refs_name ON game_rom_refs(name_id);
+            CREATE INDEX game_rom_refs_parents ON game_rom_refs(parent);
+            CREATE VIEW game_roms (game_name, rom_id, name, status, parent) AS
+                SELECT game_rom_refs.game_name, game_rom_refs.rom_id, rom_names.name, game_rom_refs.status, game_rom_refs.parent
+                FROM game_rom_refs JOIN rom_names ON game_rom_refs.name_id = rom_names.id;")?;
+        assert_eq!(get_roms(&conn)?, vec!["boot.bin", "common.bin", "region.bin", "region.bin"]);
+        let mut writer = DBWriter::from_connection(&mut conn, 5);
+        assert!(writer.migrate_rom_bios()?);
+        assert!(!writer.migrate_rom_bios()?);
+        assert!(!writer.migrate_rom_optional()?);
+        drop(writer);
+        assert_eq!(get_roms(&conn)?, vec!["boot.bin", "common.bin", "region.bin", "region.bin"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn logs_the_import() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("romst_import_log_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let dat_path = dir.join("duplicated.dat");
+        let log_path = dir.join("import.log");
+        std::fs::write(&dat_path, r#"<?xml version="1.0"?>
+<datafile>
+    <header><name>Duplicated</name><version>1</version></header>
+    <machine name="game"><rom name="rom1.bin" size="4" crc="12345678" sha1="1234567812345678123456781234567812345678"/></machine>
+    <machine name="other"><rom name="rom2.bin" size="4" crc="12345678" sha1="1234567812345678123456781234567812345678"/></machine>
+    <machine name="game"><rom name="rom1.bin" size="4" crc="12345678" sha1="1234567812345678123456781234567812345678"/></machine>
+</datafile>"#)?;
+
+        let mut conn = Connection::open_in_memory_with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+        let mut writer = DBWriter::from_connection(&mut conn, 1);
+        writer.set_import_log(ImportLog::create(&log_path)?);
+        DatImporter::<BufReader<File>, DBWriter>::from_path(&dat_path, writer)?.load_dat()?;
+        let log = std::fs::read_to_string(&log_path)?;
+        std::fs::remove_dir_all(&dir)?;
+
+        assert!(log.starts_with("DAT 1: Duplicated 1\n"));
+        assert!(log.contains("Game `game`\n"));
+        assert!(log.contains("Reused rom 0 as `rom2.bin` in the game `other`\n"));
+        assert!(log.contains("ERROR inserting the game `game`: UNIQUE constraint failed"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn prunes_unreferenced_roms() -> Result<()> {
+        let path = Path::new("testdata").join("test.dat");
+        let mut conn = Connection::open_in_memory_with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+        let writer = DBWriter::from_connection(&mut conn, 5);
+        DatImporter::<BufReader<File>, DBWriter>::from_path(&path, writer)?.load_dat()?;
+        assert!(DBReader::from_connection(&conn).get_unreferenced_roms()?.is_empty());
+
+        // game5 doesn't share roms with other games
+        conn.execute("DELETE FROM game_rom_refs WHERE game_name = 'game5';", params![])?;
+        let unreferenced = DBReader::from_connection(&conn).get_unreferenced_roms()?;
+        assert!(!unreferenced.is_empty());
+
+        let mut writer = DBWriter::from_connection(&mut conn, 5);
+        assert_eq!(writer.prune_unreferenced_roms()?, unreferenced.len());
+        drop(writer);
+        assert!(DBReader::from_connection(&conn).get_unreferenced_roms()?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn resumes_an_interrupted_import() -> Result<()> {
+        let entry = |name: &str, crc: &str| -> (Game, Vec<DataFile>) {
+            let mut info = DataFileInfo::new(crate::data::models::file::FileType::Rom);
+            info.crc = Some(crc.to_string());
+            info.size = Some(4);
+            (Game::new(name.to_string()), vec![DataFile::new(format!("{}.bin", name), info)])
+        };
+        let games = [entry("game1", "11111111"), entry("game2", "22222222"), entry("game3", "33333333")];
+        let mut conn = Connection::open_in_memory_with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+
+        // Interrupted after the second game, only the first one was written
+        let mut writer = DBWriter::from_connection(&mut conn, 1);
+        writer.record_progress(Some("abcd".to_string()));
+        writer.init()?;
+        writer.on_dat_info(DatInfo::new("Resumed"))?;
+        for (game, roms) in games.iter().take(2).cloned() {
+            writer.on_new_entry(game, roms, vec![], vec![], vec![])?;
+        }
+        drop(writer);
+        let progress = DBReader::from_connection(&conn).get_import_progress()?.unwrap();
+        assert_eq!(progress.entries, 1);
+        assert!(DBWriter::from_connection(&mut conn, 1).resume(Some("other"))?.is_none());
+
+        let mut writer = DBWriter::from_connection(&mut conn, 1);
+        writer.record_progress(Some("abcd".to_string()));
+        assert_eq!(writer.resume(Some("abcd"))?, Some(1));
+        writer.init()?;
+        writer.on_dat_info(DatInfo::new("Resumed"))?;
+        for (game, roms) in games.iter().cloned() {
+            writer.on_new_entry(game, roms, vec![], vec![], vec![])?;
+        }
+        writer.finish()?;
+        drop(writer);
+
+        let reader = DBReader::from_connection(&conn);
+        assert_eq!(reader.get_dats()?.len(), 1);
+        assert_eq!(reader.get_game_names(None)?, vec!["game1".to_string(), "game2".to_string(), "game3".to_string()]);
+        assert_eq!(reader.get_romset_roms("game2", RomsetMode::Split)?.1.len(), 1);
+        assert!(reader.get_import_progress()?.is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,304 @@
+use std::{collections::HashSet, fmt::Display};
+
+use anyhow::Result;
+use console::Style;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{data::{models::{dat::DatInfo, disk::GameDisk, entry::GameEntry, file::DataFile, game::Game, release::Release}, reader::DataReader}, i18n::tr};
+use super::{DataWriter, sqlite::DBWriter};
+
+/// What an update changed in the games of a DAT
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DatUpdate {
+    pub dat: Option<DatInfo>,
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl DatUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl Display for DatUpdate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(dat) = &self.dat {
+            writeln!(f, "{}", Style::new().bold().yellow().apply_to(dat))?;
+        }
+        writeln!(f, "- {}: {}, {}: {}, {}: {}, {}: {}", tr("Added"), self.added.len(), tr("Updated"), self.updated.len(),
+            tr("Removed"), self.removed.len(), tr("Unchanged"), self.unchanged)?;
+        for (title, games, style) in [(tr("Added"), &self.added, Style::new().green()), (tr("Updated"), &self.updated, Style::new().yellow()),
+            (tr("Removed"), &self.removed, Style::new().red())] {
+            if !games.is_empty() {
+                writeln!(f, "  {}:", style.apply_to(title))?;
+                for game in games {
+                    writeln!(f, "   - {}", game)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes a DAT over the database with a previous version of it, only the games that changed are written again.
+/// The DAT updated is the one with the same name, or the only one of the database, otherwise it's added as a new one.
+/// Only the games of that DAT are changed, the games with the same name in other DATs are left as they are
+pub struct DBUpdater<'d> {
+    writer: DBWriter<'d>,
+    dat_id: Option<u32>,
+    /// The games of the DAT in the database before the update
+    previous_games: HashSet<String>,
+    seen: HashSet<String>,
+    /// The games of the DAT skipped because other DATs of the database have them
+    in_other_dats: HashSet<String>,
+    /// The last entry read, written again if only its releases changed
+    last_entry: Option<GameEntry>,
+    update: DatUpdate,
+}
+
+impl <'d> DBUpdater<'d> {
+    pub fn new(mut writer: DBWriter<'d>) -> Result<Self> {
+        writer.migrate_rom_names()?;
+        writer.migrate_rom_shares()?;
+        writer.migrate_machine_info()?;
+        writer.migrate_rom_optional()?;
+        writer.migrate_rom_checksums()?;
+        writer.continue_ids()?;
+        Ok(Self { writer, dat_id: None, previous_games: HashSet::new(), seen: HashSet::new(), in_other_dats: HashSet::new(), last_entry: None, update: DatUpdate::default() })
+    }
+
+    /// What was changed, once the DAT is loaded
+    pub fn into_update(self) -> DatUpdate {
+        self.update
+    }
+
+    fn find_dat(&self, header: &DatInfo) -> Result<Option<u32>> {
+        let dats = self.writer.get_reader().get_dats()?;
+        let dat = match dats.iter().find(|dat| dat.name == header.name) {
+            Some(dat) => Some(dat),
+            None if dats.len() == 1 => dats.first(),
+            None => None,
+        };
+        Ok(dat.and_then(|dat| dat.id))
+    }
+
+    /// The same game, comparing the roms, disks and device refs regardless of their order. The samples are shared by
+    /// the games of the sample set, so only the ones of the entry are looked for
+    fn is_unchanged(stored: &GameEntry, entry: &GameEntry) -> bool {
+        stored.game == entry.game
+            && stored.roms.iter().collect::<HashSet<&DataFile>>() == entry.roms.iter().collect::<HashSet<&DataFile>>()
+            && stored.disks.iter().collect::<HashSet<&GameDisk>>() == entry.disks.iter().collect::<HashSet<&GameDisk>>()
+            && stored.device_refs.iter().collect::<HashSet<_>>() == entry.device_refs.iter().collect::<HashSet<_>>()
+            && entry.samples.iter().all(|sample| stored.samples.contains(sample))
+    }
+
+    fn delete_entries<S>(&mut self, game_names: &[S]) -> Result<()> where S: AsRef<str> {
+        match self.dat_id {
+            Some(dat_id) => self.writer.delete_dat_game_entries(dat_id, game_names),
+            None => Ok(())
+        }
+    }
+
+    fn write_entry(&mut self, entry: GameEntry) -> Result<()> {
+        self.writer.on_new_entry(entry.game, entry.roms, entry.disks, entry.samples, entry.device_refs)
+    }
+}
+
+impl <'d> DataWriter for DBUpdater<'d> {
+    fn init(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_dat_info(&mut self, header: DatInfo) -> Result<()> {
+        match self.find_dat(&header)? {
+            Some(dat_id) => {
+                info!("Updating the DAT {} with {}", dat_id, header);
+                self.previous_games = self.writer.get_reader().get_game_names(Some(dat_id))?.into_iter().collect();
+                self.writer.update_dat(dat_id, &header)?;
+                self.dat_id = Some(dat_id);
+            }
+            None => {
+                info!("The DAT {} is not in the database, adding it", header);
+                self.dat_id = Some(self.writer.add_dat(&header)?);
+            }
+        }
+        self.update.dat = Some(DatInfo { id: self.dat_id, ..header });
+
+        Ok(())
+    }
+
+    fn on_new_entry(&mut self, game: Game, roms: Vec<DataFile>, disks: Vec<GameDisk>, samples: Vec<String>, device_refs: Vec<String>) -> Result<()> {
+        let entry = GameEntry::new(game, roms, disks, samples, device_refs);
+        let game_name = entry.game.name.to_owned();
+        if !self.seen.insert(game_name.to_owned()) {
+            debug!("The game {} is repeated in the DAT, keeping the first one", game_name);
+            return Ok(());
+        }
+        // The game of an old name would be found too
+        let stored = self.writer.get_reader().get_game_entry(&game_name)?.filter(|stored| stored.game.name == game_name);
+        match stored {
+            Some(_) if !self.previous_games.contains(&game_name) => {
+                warn!("The game {} is in another DAT of the database, it's not updated", game_name);
+                self.in_other_dats.insert(game_name);
+                self.last_entry = None;
+            }
+            Some(stored) if DBUpdater::is_unchanged(&stored, &entry) => {
+                self.update.unchanged += 1;
+                self.last_entry = Some(entry);
+            }
+            Some(_) => {
+                self.delete_entries(&[&game_name])?;
+                self.write_entry(entry)?;
+                self.update.updated.push(game_name);
+                self.last_entry = None;
+            }
+            None => {
+                self.write_entry(entry)?;
+                self.update.added.push(game_name);
+                self.last_entry = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn on_releases(&mut self, game_name: &str, releases: Vec<Release>) -> Result<()> {
+        if self.in_other_dats.contains(game_name) {
+            return Ok(());
+        }
+        // The releases of the games written again are written with them
+        let entry = match self.last_entry.take() {
+            Some(entry) if entry.game.name == game_name => entry,
+            _ => return self.writer.on_releases(game_name, releases),
+        };
+        let stored = self.writer.get_reader().get_releases(game_name)?;
+        if stored.len() == releases.len() && releases.iter().all(|release| stored.contains(release)) {
+            return Ok(());
+        }
+        self.delete_entries(&[game_name])?;
+        self.write_entry(entry)?;
+        self.update.unchanged -= 1;
+        self.update.updated.push(game_name.to_string());
+        self.writer.on_releases(game_name, releases)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let mut removed = self.previous_games.difference(&self.seen).cloned().collect::<Vec<_>>();
+        removed.sort();
+        self.delete_entries(&removed)?;
+        self.update.removed = removed;
+        self.writer.finish()?;
+        self.writer.prune_unreferenced()?;
+        self.update.added.sort();
+        self.update.updated.sort();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::BufReader, path::Path};
+    use rusqlite::{Connection, OpenFlags};
+    use crate::{RomsetMode, data::{importer::DatImporter, models::file::{DataFileInfo, FileType}, reader::sqlite::DBReader}};
+    use super::*;
+
+    fn write_dat(path: &Path, version: &str, games: &str) -> Result<()> {
+        std::fs::write(path, format!(r#"<?xml version="1.0"?>
+<datafile>
+    <header><name>Update</name><version>{}</version></header>
+    {}
+</datafile>"#, version, games))?;
+        Ok(())
+    }
+
+    #[test]
+    fn updates_only_the_changed_games() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("romst_dat_update_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let dat_path = dir.join("update.dat");
+        write_dat(&dat_path, "1.0", r#"
+    <machine name="same"><rom name="same.bin" size="4" crc="11111111" sha1="1111111111111111111111111111111111111111"/></machine>
+    <machine name="changed"><rom name="old.bin" size="4" crc="22222222" sha1="2222222222222222222222222222222222222222"/></machine>
+    <machine name="gone"><rom name="gone.bin" size="4" crc="33333333" sha1="3333333333333333333333333333333333333333"/></machine>"#)?;
+        let mut conn = Connection::open_in_memory_with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+        DatImporter::<BufReader<File>, DBWriter>::from_path(&dat_path, DBWriter::from_connection(&mut conn, 5))?.load_dat()?;
+        conn.execute("INSERT INTO tags (game_name, tag) VALUES ('changed', 'favorite');", rusqlite::params![])?;
+
+        write_dat(&dat_path, "1.1", r#"
+    <machine name="same"><rom name="same.bin" size="4" crc="11111111" sha1="1111111111111111111111111111111111111111"/></machine>
+    <machine name="changed"><rom name="new.bin" size="4" crc="44444444" sha1="4444444444444444444444444444444444444444"/></machine>
+    <machine name="added"><rom name="same.bin" size="4" crc="11111111" sha1="1111111111111111111111111111111111111111"/></machine>"#)?;
+        let updater = DBUpdater::new(DBWriter::from_connection(&mut conn, 5))?;
+        let mut importer = DatImporter::<BufReader<File>, DBUpdater>::from_path(&dat_path, updater)?;
+        importer.load_dat()?;
+        let update = importer.into_writer().into_update();
+        std::fs::remove_dir_all(&dir)?;
+
+        assert_eq!(update.added, vec!["added".to_string()]);
+        assert_eq!(update.updated, vec!["changed".to_string()]);
+        assert_eq!(update.removed, vec!["gone".to_string()]);
+        assert_eq!(update.unchanged, 1);
+
+        let reader = DBReader::from_connection(&conn);
+        let dats = reader.get_dats()?;
+        assert_eq!(dats.len(), 1);
+        assert_eq!(dats[0].version.as_deref(), Some("1.1"));
+        let (_, roms) = reader.get_romset_roms("changed", RomsetMode::Split)?;
+        assert_eq!(roms.iter().map(|rom| rom.file.name.as_str()).collect::<Vec<_>>(), vec!["new.bin"]);
+        assert!(reader.get_game("gone").is_none());
+        assert_eq!(reader.get_unreferenced_roms()?.len(), 0);
+        assert!(reader.get_tags()?["changed"].contains("favorite"));
+        let shares = reader.get_rom_shares(&roms[0].file.info)?;
+        assert_eq!(shares, Some(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_the_games_of_other_dats() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("romst_dat_update_others_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let dat_path = dir.join("update.dat");
+        write_dat(&dat_path, "1.0", r#"
+    <machine name="own"><rom name="own.bin" size="4" crc="11111111" sha1="1111111111111111111111111111111111111111"/></machine>"#)?;
+        let mut conn = Connection::open_in_memory_with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+        DatImporter::<BufReader<File>, DBWriter>::from_path(&dat_path, DBWriter::from_connection(&mut conn, 5))?.load_dat()?;
+        let mut writer = DBWriter::from_connection(&mut conn, 5);
+        let other = writer.add_dat(&DatInfo::new("Other"))?;
+        writer.insert_game(&Game::new("shared".to_string()), Some(other))?;
+        let mut rom = DataFileInfo::new(FileType::Rom);
+        rom.size = Some(4);
+        rom.crc = Some("55555555".to_string());
+        rom.sha1 = Some("5555555555555555555555555555555555555555".to_string());
+        writer.set_game_rom("shared", None, &DataFile::new("shared.bin", rom))?;
+        drop(writer);
+
+        // `shared` is in the other DAT with other roms, it's neither replaced nor removed by the update
+        write_dat(&dat_path, "1.1", r#"
+    <machine name="own"><rom name="own.bin" size="4" crc="11111111" sha1="1111111111111111111111111111111111111111"/></machine>
+    <machine name="shared"><rom name="changed.bin" size="4" crc="66666666" sha1="6666666666666666666666666666666666666666"/></machine>"#)?;
+        let updater = DBUpdater::new(DBWriter::from_connection(&mut conn, 5))?;
+        let mut importer = DatImporter::<BufReader<File>, DBUpdater>::from_path(&dat_path, updater)?;
+        importer.load_dat()?;
+        let update = importer.into_writer().into_update();
+        std::fs::remove_dir_all(&dir)?;
+
+        assert!(update.added.is_empty());
+        assert!(update.updated.is_empty());
+        assert!(update.removed.is_empty());
+        assert_eq!(update.unchanged, 1);
+
+        let reader = DBReader::from_connection(&conn);
+        assert_eq!(reader.get_dats()?.len(), 2);
+        assert_eq!(reader.get_game_names(Some(other))?, vec!["shared".to_string()]);
+        let (_, roms) = reader.get_romset_roms("shared", RomsetMode::Split)?;
+        assert_eq!(roms.iter().map(|rom| rom.file.name.as_str()).collect::<Vec<_>>(), vec!["shared.bin"]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,252 @@
+use std::{fmt::Display, fs::{self, OpenOptions}, path::{Path, PathBuf}, process::Command};
+use console::Style;
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+
+use crate::{Romst, ScanProfiles, data::reader::DataReader, i18n::tr};
+
+/// The tables every database has since it's imported, the rest are created when first used
+const REQUIRED_TABLES: [&str; 10] = ["info", "dat_header", "roms", "games", "rom_names", "game_rom_refs", "devices", "disks", "game_disks", "samples"];
+const REQUIRED_INDEXES: [&str; 20] = [
+    "roms_unique", "roms_sha1", "roms_md5", "roms_crc", "roms_checks",
+    "games_parents_roms", "games_parents_clone", "games_samples", "games_dats",
+    "game_rom_refs_game", "game_rom_refs_rom", "game_rom_refs_name", "game_rom_refs_parents",
+    "devices_games", "devices_refs", "disks_sha1", "game_disks_game", "game_disks_disks", "sample_sets",
+    "game_roms_game",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiagnosisStatus {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// The result of a check, with what to do about it when it's not ok
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Diagnosis {
+    pub check: String,
+    pub status: DiagnosisStatus,
+    pub detail: String,
+    pub suggestion: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DoctorReport {
+    pub diagnoses: Vec<Diagnosis>,
+}
+
+impl DoctorReport {
+    pub fn has_errors(&self) -> bool {
+        self.diagnoses.iter().any(|diagnosis| diagnosis.status == DiagnosisStatus::Error)
+    }
+
+    pub fn get(&self, check: &str) -> Option<&Diagnosis> {
+        self.diagnoses.iter().find(|diagnosis| diagnosis.check == check)
+    }
+
+    fn ok(&mut self, check: &str, detail: impl Into<String>) {
+        self.push(check, DiagnosisStatus::Ok, detail, None::<String>);
+    }
+
+    fn warning(&mut self, check: &str, detail: impl Into<String>, suggestion: impl Into<String>) {
+        self.push(check, DiagnosisStatus::Warning, detail, Some(suggestion));
+    }
+
+    fn error(&mut self, check: &str, detail: impl Into<String>, suggestion: impl Into<String>) {
+        self.push(check, DiagnosisStatus::Error, detail, Some(suggestion));
+    }
+
+    fn push(&mut self, check: &str, status: DiagnosisStatus, detail: impl Into<String>, suggestion: Option<impl Into<String>>) {
+        self.diagnoses.push(Diagnosis { check: check.to_string(), status, detail: detail.into(), suggestion: suggestion.map(|suggestion| suggestion.into()) });
+    }
+}
+
+impl Display for DoctorReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for diagnosis in &self.diagnoses {
+            let status = match diagnosis.status {
+                DiagnosisStatus::Ok => Style::new().green().apply_to(tr("OK")),
+                DiagnosisStatus::Warning => Style::new().yellow().apply_to(tr("WARNING")),
+                DiagnosisStatus::Error => Style::new().red().apply_to(tr("ERROR")),
+            };
+            writeln!(f, "{:7} {}: {}", status, Style::new().bold().apply_to(&diagnosis.check), diagnosis.detail)?;
+            if let Some(suggestion) = &diagnosis.suggestion {
+                writeln!(f, "        {}", suggestion)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks the database and the environment romst runs in. A check failing doesn't stop the next ones.
+pub fn examine(db_file: &str, profiles_file: Option<&str>) -> DoctorReport {
+    let mut report = DoctorReport::default();
+    let db_path = Path::new(db_file);
+
+    if let Some(profiles_file) = profiles_file {
+        match ScanProfiles::load(&profiles_file) {
+            Ok(_) => report.ok("Profiles", format!("{} is valid", profiles_file)),
+            Err(e) => report.error("Profiles", format!("{} can't be used: {}", profiles_file, e), "Fix the JSON of the profiles file, `verify-all` won't run with it"),
+        }
+    }
+
+    if !db_path.is_file() {
+        report.error("Database", format!("No database found at {}", db_file), "Create one importing a DAT with `romst import`");
+        return report;
+    }
+    let db_size = fs::metadata(db_path).map(|metadata| metadata.len()).unwrap_or_default();
+    let directory = db_path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or_else(|| Path::new(".")).to_path_buf();
+
+    check_directory(&mut report, &directory);
+    check_lock(&mut report, db_path);
+    check_disk_space(&mut report, &directory, db_size);
+
+    let conn = match Romst::get_r_connection(db_file) {
+        Ok(conn) => conn,
+        Err(e) => {
+            report.error("Database", format!("{} can't be opened: {}", db_file, e), "Check that it's a romst database and the user can read it");
+            return report;
+        }
+    };
+    if let Err(e) = check_database(&mut report, &conn, db_path) {
+        report.error("Database", format!("{} can't be read: {}", db_file, e), "The file may be damaged, import the DATs again into a new database");
+    }
+
+    report
+}
+
+fn check_database(report: &mut DoctorReport, conn: &Connection, db_path: &Path) -> Result<()> {
+    let integrity: String = conn.query_row("PRAGMA quick_check;", params![], |row| row.get(0))?;
+    if integrity == "ok" {
+        report.ok("Integrity", "The database is not damaged");
+    } else {
+        report.error("Integrity", integrity, "Import the DATs again into a new database");
+    }
+
+    let objects = |kind: &str| -> Result<Vec<String>> {
+        let mut stmt = conn.prepare("SELECT name FROM sqlite_master WHERE type = ?1;")?;
+        let names = stmt.query_map(params![ kind ], |row| row.get(0))?.collect::<Result<Vec<String>, _>>()?;
+        Ok(names)
+    };
+    let tables = objects("table")?;
+    let indexes = objects("index")?;
+
+    // Before the rom names were normalized, game_roms was a table instead of a view
+    let old_rom_names = tables.iter().any(|table| table == "game_roms");
+    let missing_tables = REQUIRED_TABLES.iter()
+        .filter(|table| !(old_rom_names && (**table == "rom_names" || **table == "game_rom_refs")))
+        .filter(|table| !tables.iter().any(|name| name == *table))
+        .copied()
+        .collect::<Vec<_>>();
+    if !missing_tables.is_empty() {
+        report.error("Schema", format!("Missing tables: {}", missing_tables.join(", ")), "This is not a romst database or the import didn't finish, import the DAT again");
+        return Ok(());
+    }
+    // Nor were the counts of the sets using each rom stored
+    let without_rom_shares = !tables.iter().any(|table| table == "rom_shares");
+    if old_rom_names || without_rom_shares {
+        report.warning("Schema", "The database is from an older version of romst", "Update it with `romst db migrate`");
+    } else {
+        report.ok("Schema", "All the tables are there");
+    }
+
+    let missing_indexes = REQUIRED_INDEXES.iter()
+        .filter(|index| if old_rom_names { !index.starts_with("game_rom_refs") } else { **index != "game_roms_game" })
+        .filter(|index| !indexes.iter().any(|name| name == *index))
+        .copied()
+        .collect::<Vec<_>>();
+    if missing_indexes.is_empty() {
+        report.ok("Indexes", "All the indexes are there");
+    } else {
+        report.warning("Indexes", format!("Missing indexes: {}", missing_indexes.join(", ")), "Checks will be slow, import the DAT again to create them");
+    }
+
+    check_versions(report, conn)?;
+
+    let journal_mode: String = conn.query_row("PRAGMA journal_mode;", params![], |row| row.get(0))?;
+    let wal_size = fs::metadata(sibling_file(db_path, "-wal")).map(|metadata| metadata.len()).unwrap_or_default();
+    if wal_size > 0 {
+        report.warning("Journal", format!("Mode {}, with {} bytes in the write-ahead log not in the database yet", journal_mode, wal_size),
+            "Another process may be writing it. If not, any write command, like `romst db migrate`, moves them to the database");
+    } else {
+        report.ok("Journal", format!("Mode {}", journal_mode));
+    }
+
+    Ok(())
+}
+
+fn check_versions(report: &mut DoctorReport, conn: &Connection) -> Result<()> {
+    let binary_version = env!("CARGO_PKG_VERSION");
+    let dats = Romst::get_data_reader(conn)?.get_dats()?;
+    let newer = dats.iter()
+        .filter_map(|dat| dat.source.as_ref().map(|source| (dat.name.as_str(), source.romst_version.as_str())))
+        .filter(|(_, version)| parse_version(version) > parse_version(binary_version))
+        .map(|(name, version)| format!("{} ({})", name, version))
+        .collect::<Vec<_>>();
+    if !newer.is_empty() {
+        report.warning("Version", format!("Imported by a newer romst than {}: {}", binary_version, newer.join(", ")), "Update romst, this version may not read everything in the database");
+    } else if dats.iter().any(|dat| dat.source.is_none()) {
+        report.ok("Version", "Some DATs were imported before romst recorded its version");
+    } else {
+        report.ok("Version", format!("Imported by romst {} or older", binary_version));
+    }
+
+    Ok(())
+}
+
+/// The lock files are written next to the database, so the directory has to be writable
+fn check_directory(report: &mut DoctorReport, directory: &Path) {
+    let probe = directory.join(format!(".romst_doctor_{}", std::process::id()));
+    match OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            report.ok("Permissions", format!("{} is writable", directory.to_string_lossy()));
+        }
+        Err(e) => report.error("Permissions", format!("{} is not writable: {}", directory.to_string_lossy(), e),
+            "romst writes a lock file next to the database when changing it, give the user write access to the directory"),
+    }
+}
+
+fn check_lock(report: &mut DoctorReport, db_path: &Path) {
+    let lock_file = sibling_file(db_path, ".lock");
+    if lock_file.exists() {
+        let pid = fs::read_to_string(&lock_file).unwrap_or_default();
+        report.warning("Lock", format!("{} is locked by the process {}", db_path.to_string_lossy(), pid.trim()),
+            format!("If that process is not running anymore, remove {}", lock_file.to_string_lossy()));
+    } else {
+        report.ok("Lock", "The database is not locked");
+    }
+}
+
+/// Migrating, purging or merging can take up to the size of the database again while writing
+fn check_disk_space(report: &mut DoctorReport, directory: &Path, db_size: u64) {
+    match get_available_space(directory) {
+        Some(available) if available < db_size => report.warning("Disk space", format!("{} bytes free, the database takes {}", available, db_size),
+            "Free some space before migrating, purging or merging the database"),
+        Some(available) => report.ok("Disk space", format!("{} bytes free", available)),
+        None => report.ok("Disk space", "Unknown on this platform"),
+    }
+}
+
+/// The bytes free in the file system of the directory, from `df`
+fn get_available_space(directory: &Path) -> Option<u64> {
+    if cfg!(windows) {
+        return None;
+    }
+    let output = Command::new("df").arg("-Pk").arg(directory).output().ok()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    let kilobytes: u64 = output.lines().nth(1)?.split_whitespace().nth(3)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+fn sibling_file(db_path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = db_path.as_os_str().to_owned();
+    file_name.push(suffix);
+    PathBuf::from(file_name)
+}
+
+fn parse_version(version: &str) -> Vec<u32> {
+    version.split('.').map(|part| part.parse().unwrap_or_default()).collect()
+}
@@ -49,4 +49,10 @@ pub enum RomstIOError {
 
     #[error("File not found {0}")]
     FileNotFound(String),
+
+    #[error("Corrupt archive {0}, files that couldn't be read: {}", .1.join(", "))]
+    CorruptArchive(String, Vec<String>),
+
+    #[error("Database {0} is being written by another process, if that's not the case remove the file {1}")]
+    DatabaseLocked(String, String),
 }
\ No newline at end of file
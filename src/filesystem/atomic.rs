@@ -0,0 +1,231 @@
+use std::{fs::{self, File}, io::{self, BufWriter, Seek, SeekFrom, Write}, path::{Path, PathBuf}, time::Duration};
+
+use log::warn;
+
+/// The extension of the temporary files, they are never taken as sets or roms
+const TEMP_EXTENSION: &str = "romst-tmp";
+/// A temporary file not modified for this long is from an operation that was interrupted
+const STALE_TEMP_AGE: Duration = Duration::from_secs(60 * 60);
+/// The extension of the staging copies, they are kept when interrupted so the operation can go on with them
+const STAGING_EXTENSION: &str = "romst-staging";
+
+/// A file written in a temporary file next to the destination, in the same file system, that only replaces the
+/// destination once it's committed. A file dropped without committing it is removed, so an interrupted write never
+/// leaves a half written file with the name of the destination.
+pub struct AtomicFile {
+    path: PathBuf,
+    temp_path: PathBuf,
+    writer: Option<BufWriter<File>>,
+    committed: bool,
+}
+
+impl AtomicFile {
+    pub fn create(path: &impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let temp_path = get_temp_path(&path);
+        let writer = BufWriter::new(File::create(&temp_path)?);
+        Ok(Self { path, temp_path, writer: Some(writer), committed: false })
+    }
+
+    /// Writes everything to the disk and moves the file to its destination
+    pub fn commit(mut self) -> io::Result<()> {
+        if let Some(writer) = self.writer.take() {
+            let file = writer.into_inner().map_err(|e| e.into_error())?;
+            file.sync_all()?;
+        }
+        fs::rename(&self.temp_path, &self.path)?;
+        self.committed = true;
+        // The rename is only kept after a crash once the directory is written too
+        #[cfg(unix)]
+        if let Some(directory) = self.path.parent().filter(|directory| !directory.as_os_str().is_empty()) {
+            File::open(directory)?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    fn writer(&mut self) -> &mut BufWriter<File> {
+        self.writer.as_mut().expect("The file is already committed")
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer().flush()
+    }
+}
+
+impl Seek for AtomicFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.writer().seek(pos)
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            drop(self.writer.take());
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+/// A copy of a file where a long operation writes before replacing the file with it. Unlike the temporary files it's
+/// not removed when the operation is interrupted, so running it again can go on from what was written.
+pub struct StagingFile {
+    path: PathBuf,
+    staging_path: PathBuf,
+}
+
+impl StagingFile {
+    pub fn new(path: &impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let staging_path = get_staging_path(&path);
+        Self { path, staging_path }
+    }
+
+    /// Where the copy is written, the destination is only replaced on `commit`
+    pub fn staging_path(&self) -> &Path {
+        &self.staging_path
+    }
+
+    /// A copy left by an interrupted operation
+    pub fn exists(&self) -> bool {
+        self.staging_path.exists()
+    }
+
+    /// Starts again from the destination, or from an empty file if there is none yet
+    pub fn reset(&self) -> io::Result<()> {
+        self.discard()?;
+        if self.path.exists() {
+            fs::copy(&self.path, &self.staging_path)?;
+        }
+        Ok(())
+    }
+
+    pub fn discard(&self) -> io::Result<()> {
+        match fs::remove_file(&self.staging_path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(())
+        }
+    }
+
+    /// Moves the copy to its destination
+    pub fn commit(self) -> io::Result<()> {
+        File::open(&self.staging_path)?.sync_all()?;
+        fs::rename(&self.staging_path, &self.path)?;
+        #[cfg(unix)]
+        if let Some(directory) = self.path.parent().filter(|directory| !directory.as_os_str().is_empty()) {
+            File::open(directory)?.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the whole file at once, like `fs::write`
+pub fn write(path: &impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut file = AtomicFile::create(path)?;
+    file.write_all(contents.as_ref())?;
+    file.commit()
+}
+
+/// The temporary file is hidden, named after the destination and the process writing it
+fn get_temp_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.{}", file_name, std::process::id(), TEMP_EXTENSION))
+}
+
+/// The staging copy is hidden too, but it's the same for every process, so another one can pick it up
+fn get_staging_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    path.with_file_name(format!(".{}.{}", file_name, STAGING_EXTENSION))
+}
+
+pub fn is_temp_file(path: &impl AsRef<Path>) -> bool {
+    path.as_ref().extension().is_some_and(|extension| extension == TEMP_EXTENSION)
+}
+
+/// Removes the temporary files left in the directory by interrupted operations, returns how many were removed
+pub fn remove_stale_temps(directory: &impl AsRef<Path>) -> io::Result<usize> {
+    let mut removed = 0;
+    for entry in directory.as_ref().read_dir()? {
+        let path = entry?.path();
+        if !is_temp_file(&path) || !path.is_file() {
+            continue;
+        }
+        let age = path.metadata()?.modified()?.elapsed().unwrap_or_default();
+        if age >= STALE_TEMP_AGE {
+            match fs::remove_file(&path) {
+                Ok(()) => removed += 1,
+                Err(e) => warn!("Can't remove the temporary file {}: {}", path.to_string_lossy(), e)
+            }
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, time::SystemTime};
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn only_replaces_the_file_when_committed() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("romst_atomic_{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("file.dat");
+        fs::write(&path, "old")?;
+
+        let mut file = AtomicFile::create(&path)?;
+        file.write_all(b"new")?;
+        assert_eq!("old", fs::read_to_string(&path)?);
+        drop(file);
+        assert_eq!("old", fs::read_to_string(&path)?);
+        assert_eq!(1, dir.read_dir()?.count());
+
+        write(&path, "new")?;
+        assert_eq!("new", fs::read_to_string(&path)?);
+
+        let stale = get_temp_path(&dir.join("set.zip"));
+        fs::write(&stale, "half written")?;
+        assert!(is_temp_file(&stale));
+        assert_eq!(0, remove_stale_temps(&dir)?);
+        File::options().write(true).open(&stale)?.set_modified(SystemTime::now() - STALE_TEMP_AGE)?;
+        assert_eq!(1, remove_stale_temps(&dir)?);
+        assert!(!stale.exists());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_the_staging_copy_until_committed() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("romst_staging_{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("roms.db");
+
+        let staging = StagingFile::new(&path);
+        staging.reset()?;
+        assert!(!staging.exists());
+        fs::write(&path, "old")?;
+        staging.reset()?;
+        assert_eq!("old", fs::read_to_string(staging.staging_path())?);
+        fs::write(staging.staging_path(), "new")?;
+        drop(staging);
+
+        let staging = StagingFile::new(&path);
+        assert!(staging.exists());
+        assert!(!is_temp_file(&staging.staging_path()));
+        staging.commit()?;
+        assert_eq!("new", fs::read_to_string(&path)?);
+        assert_eq!(1, dir.read_dir()?.count());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
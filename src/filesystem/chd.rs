@@ -0,0 +1,96 @@
+use std::{fs::File, io::Read, path::Path};
+use anyhow::Result;
+
+use crate::error::RomstError;
+
+const CHD_TAG: &[u8] = b"MComprHD";
+
+/// The checksums from the header of a CHD file. A CHD with a parent only stores the differences
+/// with it, so it can't be read without the parent CHD.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChdHeader {
+    pub version: u32,
+    /// Checksum of the data and the metadata, the one used in DATs
+    pub sha1: Option<String>,
+    pub parent_sha1: Option<String>,
+}
+
+impl ChdHeader {
+    /// Returns an error if the file is not a CHD
+    pub fn read(file_path: &impl AsRef<Path>) -> Result<Self> {
+        let file_path = file_path.as_ref();
+        let mut header = [0u8; 124];
+        let mut file = File::open(file_path)?;
+        let read = file.read(&mut header)?;
+
+        ChdHeader::parse(&header[..read]).ok_or_else(|| RomstError::ParsingError {
+            message: format!("{} is not a valid CHD file", file_path.to_string_lossy())
+        }.into())
+    }
+
+    fn parse(header: &[u8]) -> Option<Self> {
+        if header.len() < 16 || &header[..8] != CHD_TAG {
+            return None;
+        }
+        let version = u32::from_be_bytes([header[12], header[13], header[14], header[15]]);
+
+        // Offsets of the sha1 and the parent sha1 in each version, older versions only have md5
+        let (sha1_offset, parent_sha1_offset, length) = match version {
+            3 => (80, 100, 120),
+            4 => (48, 68, 108),
+            5 => (84, 104, 124),
+            _ => return Some(Self { version, sha1: None, parent_sha1: None })
+        };
+        if header.len() < length {
+            return None;
+        }
+
+        Some(Self {
+            version,
+            sha1: to_hex(&header[sha1_offset..sha1_offset + 20]),
+            parent_sha1: to_hex(&header[parent_sha1_offset..parent_sha1_offset + 20]),
+        })
+    }
+}
+
+/// Returns `None` if the checksum is empty, as it is in the header when there is no parent
+fn to_hex(bytes: &[u8]) -> Option<String> {
+    if bytes.iter().all(|byte| *byte == 0) {
+        return None;
+    }
+    Some(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_header(version: u32, sha1: u8, parent_sha1: u8) -> Vec<u8> {
+        let (sha1_offset, parent_sha1_offset, length) = match version {
+            4 => (48, 68, 108),
+            _ => (84, 104, 124),
+        };
+        let mut header = vec![0u8; length];
+        header[..8].copy_from_slice(CHD_TAG);
+        header[8..12].copy_from_slice(&(length as u32).to_be_bytes());
+        header[12..16].copy_from_slice(&version.to_be_bytes());
+        header[sha1_offset..sha1_offset + 20].copy_from_slice(&[sha1; 20]);
+        header[parent_sha1_offset..parent_sha1_offset + 20].copy_from_slice(&[parent_sha1; 20]);
+        header
+    }
+
+    #[test]
+    fn parses_chd_headers() {
+        let header = ChdHeader::parse(&build_header(5, 0xab, 0)).unwrap();
+        assert_eq!(header.version, 5);
+        assert_eq!(header.sha1, Some("ab".repeat(20)));
+        assert_eq!(header.parent_sha1, None);
+
+        let header = ChdHeader::parse(&build_header(4, 0x01, 0xff)).unwrap();
+        assert_eq!(header.sha1, Some("01".repeat(20)));
+        assert_eq!(header.parent_sha1, Some("ff".repeat(20)));
+
+        assert_eq!(ChdHeader::parse(b"PK\x03\x04 not a chd file"), None);
+        assert_eq!(ChdHeader::parse(&build_header(5, 0xab, 0)[..100]), None);
+    }
+}
@@ -0,0 +1,214 @@
+use std::io::{self, Read};
+use bitflags::bitflags;
+use md5::Md5;
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use xxhash_rust::xxh3::Xxh3;
+use zip::read::ZipFile;
+
+use crate::data::models::file::DataFileInfo;
+
+/// A running checksum, fed with the data in chunks
+pub trait Checksum {
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self: Box<Self>) -> String;
+}
+
+impl Checksum for Sha1 {
+    fn update(&mut self, data: &[u8]) { Digest::update(self, data); }
+    fn finalize(self: Box<Self>) -> String { format!("{:x}", Digest::finalize(*self)) }
+}
+
+impl Checksum for Md5 {
+    fn update(&mut self, data: &[u8]) { md5::Digest::update(self, data); }
+    fn finalize(self: Box<Self>) -> String { format!("{:x}", md5::Digest::finalize(*self)) }
+}
+
+impl Checksum for Sha256 {
+    fn update(&mut self, data: &[u8]) { sha2::Digest::update(self, data); }
+    fn finalize(self: Box<Self>) -> String { format!("{:x}", sha2::Digest::finalize(*self)) }
+}
+
+impl Checksum for Xxh3 {
+    fn update(&mut self, data: &[u8]) { Xxh3::update(self, data); }
+    fn finalize(self: Box<Self>) -> String { format!("{:016x}", self.digest()) }
+}
+
+impl Checksum for crc32fast::Hasher {
+    fn update(&mut self, data: &[u8]) { crc32fast::Hasher::update(self, data); }
+    fn finalize(self: Box<Self>) -> String { format!("{:08x}", crc32fast::Hasher::finalize(*self)) }
+}
+
+/// An algorithm to hash the roms with, and where its checksum goes in the file info
+pub trait ChecksumEngine: Sync {
+    fn name(&self) -> &'static str;
+    /// The flag requesting this checksum
+    fn check(&self) -> FileChecks;
+    /// The length of the checksum in hex digits, to tell them apart
+    fn hex_len(&self) -> usize;
+    fn new_checksum(&self) -> Box<dyn Checksum>;
+    fn get<'a>(&self, info: &'a DataFileInfo) -> Option<&'a String>;
+    fn set(&self, info: &mut DataFileInfo, checksum: String);
+
+    /// The checksum kept in the archive, so the file doesn't need to be hashed
+    fn zip_checksum(&self, _file: &ZipFile) -> Option<String> { None }
+
+    fn hash(&self, data: &[u8]) -> String {
+        let mut checksum = self.new_checksum();
+        checksum.update(data);
+        checksum.finalize()
+    }
+
+    /// Hashes everything read, without keeping it all in memory
+    fn hash_reader(&self, reader: &mut dyn Read) -> io::Result<String> {
+        let mut checksum = self.new_checksum();
+        let mut buffer = [0u8; 64 * 1024];
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            checksum.update(&buffer[..read]);
+        }
+        Ok(checksum.finalize())
+    }
+}
+
+/// Declares the engines along with their flag, `FileChecks::ALL` includes the size and all of them but the optional
+/// ones, only hashed when the database has their checksums
+macro_rules! checksum_engines {
+    ($($flag:ident = $bits:expr => $engine:ident),+; optional: $($optional_flag:ident = $optional_bits:expr => $optional_engine:ident),+ $(,)?) => {
+        bitflags! {
+            pub struct FileChecks: u32 {
+                const SIZE = 0b00000100;
+                /// Also hashes the files without the header the skippers find, it's not part of `ALL`
+                const HEADERLESS = 0b00010000;
+                $(const $flag = $bits;)+
+                $(const $optional_flag = $optional_bits;)+
+                const ALL = Self::SIZE.bits $(| Self::$flag.bits)+;
+            }
+        }
+
+        /// Every checksum romst knows, the ones of `ALL` first in the order they are preferred to identify a rom
+        pub static ENGINES: &[&dyn ChecksumEngine] = &[$(&$engine),+ $(, &$optional_engine)+];
+    };
+}
+
+checksum_engines! {
+    SHA1 = 0b00000001 => Sha1Engine,
+    MD5 = 0b00000010 => Md5Engine,
+    CRC = 0b00001000 => Crc32Engine;
+    optional:
+    SHA256 = 0b00100000 => Sha256Engine,
+    XXH3 = 0b01000000 => Xxh3Engine,
+}
+
+/// The engines of the requested checks
+pub fn get_engines(file_checks: FileChecks) -> impl Iterator<Item = &'static dyn ChecksumEngine> {
+    ENGINES.iter().copied().filter(move |engine| file_checks.contains(engine.check()))
+}
+
+/// The names of the engines, as a list for the messages: `sha1, md5, crc, sha256 or xxh3`
+pub fn get_engine_names() -> String {
+    let names = ENGINES.iter().map(|engine| engine.name()).collect::<Vec<_>>();
+    match names.split_last() {
+        Some((last, [])) => last.to_string(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
+
+pub struct Sha1Engine;
+
+impl ChecksumEngine for Sha1Engine {
+    fn name(&self) -> &'static str { "sha1" }
+    fn check(&self) -> FileChecks { FileChecks::SHA1 }
+    fn hex_len(&self) -> usize { 40 }
+    fn new_checksum(&self) -> Box<dyn Checksum> { Box::new(Sha1::new()) }
+    fn get<'a>(&self, info: &'a DataFileInfo) -> Option<&'a String> { info.sha1.as_ref() }
+    fn set(&self, info: &mut DataFileInfo, checksum: String) { info.sha1 = Some(checksum); }
+}
+
+pub struct Md5Engine;
+
+impl ChecksumEngine for Md5Engine {
+    fn name(&self) -> &'static str { "md5" }
+    fn check(&self) -> FileChecks { FileChecks::MD5 }
+    fn hex_len(&self) -> usize { 32 }
+    fn new_checksum(&self) -> Box<dyn Checksum> { Box::new(<Md5 as md5::Digest>::new()) }
+    fn get<'a>(&self, info: &'a DataFileInfo) -> Option<&'a String> { info.md5.as_ref() }
+    fn set(&self, info: &mut DataFileInfo, checksum: String) { info.md5 = Some(checksum); }
+}
+
+pub struct Crc32Engine;
+
+impl ChecksumEngine for Crc32Engine {
+    fn name(&self) -> &'static str { "crc" }
+    fn check(&self) -> FileChecks { FileChecks::CRC }
+    fn hex_len(&self) -> usize { 8 }
+    fn new_checksum(&self) -> Box<dyn Checksum> { Box::new(crc32fast::Hasher::new()) }
+    fn get<'a>(&self, info: &'a DataFileInfo) -> Option<&'a String> { info.crc.as_ref() }
+    fn set(&self, info: &mut DataFileInfo, checksum: String) { info.crc = Some(checksum); }
+
+    fn zip_checksum(&self, file: &ZipFile) -> Option<String> { Some(format!("{:08x}", file.crc32())) }
+}
+
+/// Newer Redump and No-Intro DATs have it along with the sha1
+pub struct Sha256Engine;
+
+impl ChecksumEngine for Sha256Engine {
+    fn name(&self) -> &'static str { "sha256" }
+    fn check(&self) -> FileChecks { FileChecks::SHA256 }
+    fn hex_len(&self) -> usize { 64 }
+    fn new_checksum(&self) -> Box<dyn Checksum> { Box::new(<Sha256 as sha2::Digest>::new()) }
+    fn get<'a>(&self, info: &'a DataFileInfo) -> Option<&'a String> { info.sha256.as_ref() }
+    fn set(&self, info: &mut DataFileInfo, checksum: String) { info.sha256 = Some(checksum); }
+}
+
+/// The 64 bits XXH3, much faster than the others but not cryptographic, to match the files of a local collection quickly
+pub struct Xxh3Engine;
+
+impl ChecksumEngine for Xxh3Engine {
+    fn name(&self) -> &'static str { "xxh3" }
+    fn check(&self) -> FileChecks { FileChecks::XXH3 }
+    fn hex_len(&self) -> usize { 16 }
+    fn new_checksum(&self) -> Box<dyn Checksum> { Box::new(Xxh3::new()) }
+    fn get<'a>(&self, info: &'a DataFileInfo) -> Option<&'a String> { info.xxh3.as_ref() }
+    fn set(&self, info: &mut DataFileInfo, checksum: String) { info.xxh3 = Some(checksum); }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::models::file::FileType;
+
+    use super::*;
+
+    #[test]
+    fn hashes_with_every_engine() {
+        let mut info = DataFileInfo::new(FileType::Rom);
+        for engine in get_engines(FileChecks::ALL) {
+            let checksum = engine.hash(b"romst");
+            assert_eq!(checksum.len(), engine.hex_len());
+            assert_eq!(engine.hash_reader(&mut &b"romst"[..]).unwrap(), checksum);
+            engine.set(&mut info, checksum);
+        }
+        assert_eq!(info.crc.as_deref(), Some("35006484"));
+        assert!(info.sha1.is_some() && info.md5.is_some());
+        assert_eq!(get_engines(FileChecks::CRC | FileChecks::SIZE).map(|engine| engine.name()).collect::<Vec<_>>(), vec!["crc"]);
+        assert!(info.sha256.is_none() && info.xxh3.is_none());
+    }
+
+    #[test]
+    fn hashes_with_the_optional_engines() {
+        let mut info = DataFileInfo::new(FileType::Rom);
+        for engine in get_engines(FileChecks::SHA256 | FileChecks::XXH3) {
+            let checksum = engine.hash(b"romst");
+            assert_eq!(checksum.len(), engine.hex_len());
+            assert_eq!(engine.hash_reader(&mut &b"romst"[..]).unwrap(), checksum);
+            engine.set(&mut info, checksum);
+        }
+        assert_eq!(info.sha256.as_deref(), Some("65c463a75330fc0f331dad1774dd6e81fb3d1b282a7923255f7921590e10c2cb"));
+        assert_eq!(info.xxh3.as_deref(), Some("c9f6b1d039e045de"));
+        assert!(info.sha1.is_none() && info.crc.is_none());
+    }
+}
@@ -0,0 +1,230 @@
+use std::{fs, io::Write, path::{Path, PathBuf}, str::FromStr};
+use anyhow::Result;
+
+use crate::{data::models::file::{DataFile, DataFileInfo, FileType}, err, error::RomstError};
+use super::{FileChecks, FileReader, atomic::AtomicFile};
+
+/// A file listed in a checksum file (sfv, md5sum or sha1sum)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecksumEntry {
+    /// The name as it is in the checksum file, relative to it
+    pub name: String,
+    pub path: PathBuf,
+    pub info: DataFileInfo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumFormat {
+    Sfv,
+    Md5,
+    Sha1,
+}
+
+impl ChecksumFormat {
+    /// The format is given by the extension of the file
+    pub fn from_file(file_path: &impl AsRef<Path>) -> Result<Self> {
+        let file_path = file_path.as_ref();
+        match file_path.extension().map(|extension| extension.to_string_lossy().to_lowercase()) {
+            Some(extension) if extension == "sfv" => Ok(ChecksumFormat::Sfv),
+            Some(extension) if extension == "md5" => Ok(ChecksumFormat::Md5),
+            Some(extension) if extension == "sha1" => Ok(ChecksumFormat::Sha1),
+            _ => err!(RomstError::ParsingError {
+                message: format!("{} is not a sfv, md5 or sha1 file", file_path.to_string_lossy())
+            })
+        }
+    }
+
+    pub fn get_extension(&self) -> &str {
+        match self {
+            ChecksumFormat::Sfv => "sfv",
+            ChecksumFormat::Md5 => "md5",
+            ChecksumFormat::Sha1 => "sha1",
+        }
+    }
+
+    pub fn get_file_checks(&self) -> FileChecks {
+        match self {
+            ChecksumFormat::Sfv => FileChecks::CRC,
+            ChecksumFormat::Md5 => FileChecks::MD5,
+            ChecksumFormat::Sha1 => FileChecks::SHA1,
+        }
+    }
+}
+
+impl FromStr for ChecksumFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "sfv" => Ok(ChecksumFormat::Sfv),
+            "md5" => Ok(ChecksumFormat::Md5),
+            "sha1" => Ok(ChecksumFormat::Sha1),
+            _ => Err(anyhow::anyhow!("Non valid checksum file type, can be either `sfv`, `md5` or `sha1`"))
+        }
+    }
+}
+
+pub fn read_checksum_file(file_path: &impl AsRef<Path>) -> Result<Vec<ChecksumEntry>> {
+    let file_path = file_path.as_ref();
+    let format = ChecksumFormat::from_file(&file_path)?;
+    let base_dir = file_path.parent().unwrap_or_else(|| Path::new(""));
+
+    let content = fs::read_to_string(file_path)?;
+    let mut entries = vec![];
+    for (line_number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+
+        let (name, checksum) = match parse_line(line, format) {
+            Some(entry) => entry,
+            None => return err!(RomstError::ParsingError {
+                message: format!("Wrong line {} in {}: {}", line_number + 1, file_path.to_string_lossy(), line)
+            })
+        };
+
+        let mut info = DataFileInfo::new(FileType::Rom);
+        match format {
+            ChecksumFormat::Sfv => info.crc = Some(checksum),
+            ChecksumFormat::Md5 => info.md5 = Some(checksum),
+            ChecksumFormat::Sha1 => info.sha1 = Some(checksum),
+        };
+
+        let name = name.replace('\\', "/");
+        let path = base_dir.join(&name);
+        // The size is cheap to get if the file is there, and it helps to find the right rom
+        info.size = fs::metadata(&path).ok().map(|metadata| metadata.len() as u32);
+
+        entries.push(ChecksumEntry { name, path, info });
+    }
+
+    Ok(entries)
+}
+
+/// Writes a checksum file for the roms, the format is given by the extension of the file.
+/// Roms without the checksum needed for the format are skipped, returns the number of roms written.
+pub fn write_checksum_file(output_file: &impl AsRef<Path>, roms: &[DataFile]) -> Result<usize> {
+    let format = ChecksumFormat::from_file(output_file)?;
+    let mut writer = AtomicFile::create(output_file)?;
+
+    if format == ChecksumFormat::Sfv {
+        writeln!(writer, "; Generated by romst")?;
+    }
+    let mut written = 0;
+    for rom in roms {
+        let checksum = match format {
+            ChecksumFormat::Sfv => &rom.info.crc,
+            ChecksumFormat::Md5 => &rom.info.md5,
+            ChecksumFormat::Sha1 => &rom.info.sha1,
+        };
+        if let Some(checksum) = checksum {
+            match format {
+                ChecksumFormat::Sfv => writeln!(writer, "{} {}", rom.name, checksum)?,
+                ChecksumFormat::Md5 | ChecksumFormat::Sha1 => writeln!(writer, "{} *{}", checksum, rom.name)?,
+            };
+            written += 1;
+        }
+    }
+    writer.commit()?;
+
+    Ok(written)
+}
+
+/// Hashes a sample of the files, evenly spread, and returns the ones that are missing or don't match their checksum
+pub fn spot_check(entries: &[ChecksumEntry], sample_size: usize) -> Vec<String> {
+    if entries.is_empty() || sample_size == 0 {
+        return vec![];
+    }
+
+    let step = (entries.len() / sample_size).max(1);
+    let mut file_reader = FileReader::new();
+    entries.iter().step_by(step).take(sample_size).filter_map(|entry| {
+        match file_reader.get_file_info(&entry.path) {
+            Ok(info) => {
+                let matches = [(&entry.info.crc, &info.crc), (&entry.info.md5, &info.md5), (&entry.info.sha1, &info.sha1)].iter()
+                    .all(|(expected, actual)| expected.is_none() || expected == actual);
+                if matches { None } else { Some(entry.name.clone()) }
+            }
+            Err(_) => Some(entry.name.clone())
+        }
+    }).collect()
+}
+
+/// Returns the file name and the checksum of a line
+fn parse_line(line: &str, format: ChecksumFormat) -> Option<(String, String)> {
+    let (name, checksum) = match format {
+        // file_name crc32
+        ChecksumFormat::Sfv => {
+            let split = line.rfind(char::is_whitespace)?;
+            (line[..split].trim(), line[split..].trim())
+        }
+        // checksum  file_name, or checksum *file_name for binary mode
+        ChecksumFormat::Md5 | ChecksumFormat::Sha1 => {
+            let split = line.find(char::is_whitespace)?;
+            let name = line[split..].trim_start();
+            (name.strip_prefix('*').unwrap_or(name), line[..split].trim())
+        }
+    };
+
+    let checksum_length = match format {
+        ChecksumFormat::Sfv => 8,
+        ChecksumFormat::Md5 => 32,
+        ChecksumFormat::Sha1 => 40,
+    };
+    if name.is_empty() || checksum.len() != checksum_length || !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    Some((name.to_string(), checksum.to_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_checksum_lines() {
+        assert_eq!(parse_line("game1/rom1.trom 1D460EEE", ChecksumFormat::Sfv),
+            Some(("game1/rom1.trom".to_string(), "1d460eee".to_string())));
+        assert_eq!(parse_line("some file.bin   1d460eee", ChecksumFormat::Sfv),
+            Some(("some file.bin".to_string(), "1d460eee".to_string())));
+        assert_eq!(parse_line("aa818fc7769cdd51149f794b0d4fbec9 *rom1.trom", ChecksumFormat::Md5),
+            Some(("rom1.trom".to_string(), "aa818fc7769cdd51149f794b0d4fbec9".to_string())));
+        assert_eq!(parse_line("8bb3a81b9fa2de5163f0ffc634a998c455bcca25  rom 1.trom", ChecksumFormat::Sha1),
+            Some(("rom 1.trom".to_string(), "8bb3a81b9fa2de5163f0ffc634a998c455bcca25".to_string())));
+        assert_eq!(parse_line("rom1.trom 1d460ee", ChecksumFormat::Sfv), None);
+        assert_eq!(parse_line("8bb3a81b9fa2de5163f0ffc634a998c455bcca25", ChecksumFormat::Sha1), None);
+    }
+
+    #[test]
+    fn spot_checks_files() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("romst_sfv_{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("rom1.bin"), b"romst")?;
+        fs::write(dir.join("rom2.bin"), b"other")?;
+        let sfv = dir.join("set.sfv");
+        let mut crc_hasher = crc32fast::Hasher::new();
+        crc_hasher.update(b"romst");
+        let crc = format!("{:08x}", crc_hasher.finalize());
+        fs::write(&sfv, format!("; comment\nrom1.bin {}\nrom2.bin {}\nrom3.bin {}\n", crc, crc, crc))?;
+
+        let entries = read_checksum_file(&sfv)?;
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].info.size, Some(5));
+        assert_eq!(entries[2].info.size, None);
+        assert_eq!(spot_check(&entries, 3), vec!["rom2.bin".to_string(), "rom3.bin".to_string()]);
+        assert!(spot_check(&entries[..1], 3).is_empty());
+
+        let roms = entries.into_iter().map(|entry| DataFile::new(entry.name, entry.info)).collect::<Vec<_>>();
+        let written = dir.join("written.sfv");
+        assert_eq!(write_checksum_file(&written, &roms)?, 3);
+        let entries = read_checksum_file(&written)?;
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].name, "rom2.bin");
+        assert_eq!(entries[1].info.crc, Some(crc));
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,87 @@
+use std::{path::Path, sync::atomic::{AtomicUsize, Ordering}};
+
+use rayon::{ThreadPoolBuilder, prelude::*};
+
+use crate::{data::models::set::GameSet, error::RomstIOError};
+
+use super::{FileChecks, FileReader, ReadOptions};
+
+/// How many files are hashed at the same time, 0 is one for each core
+static HASHING_THREADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Sets how many threads hash the files, 0 uses one for each core
+pub fn set_hashing_threads(threads: usize) {
+    HASHING_THREADS.store(threads, Ordering::Relaxed);
+}
+
+pub fn get_hashing_threads() -> usize {
+    HASHING_THREADS.load(Ordering::Relaxed)
+}
+
+/// Builds the game sets of the files, and of the directories of unzipped sets, in a pool of threads, hashing only the
+/// checks requested, each one with a reader with the options. Each set is given to `on_built` as soon as it's hashed,
+/// so they come in no particular order. Blocks until all the files are done.
+pub fn build_game_sets<P, F>(file_paths: &[P], file_checks: FileChecks, options: &ReadOptions, on_built: F) -> Result<(), RomstIOError>
+    where P: AsRef<Path> + Sync, F: Fn(&Path, Result<GameSet, RomstIOError>) + Sync {
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(get_hashing_threads())
+        .thread_name(|index| format!("romst-hashing-{}", index))
+        .build()
+        .map_err(|e| RomstIOError::Io { source: std::io::Error::other(e) })?;
+
+    pool.install(|| {
+        file_paths.par_iter().for_each(|file_path| {
+            let file_path = file_path.as_ref();
+            let game_set = if file_path.is_dir() {
+                FileReader::with_options(options.clone()).build_game_set_from_dir(&file_path, file_checks)
+            } else {
+                FileReader::with_options(options.clone()).build_game_set(&file_path, file_checks)
+            };
+            on_built(file_path, game_set);
+        });
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn builds_the_same_sets_in_parallel() -> Result<()> {
+        let split = Path::new("testdata").join("split");
+        let mut file_paths = split.read_dir()?.filter_map(|entry| entry.ok().map(|entry| entry.path())).filter(|path| path.is_file()).collect::<Vec<_>>();
+        file_paths.sort();
+
+        let mut sequential = vec![];
+        for file_path in &file_paths {
+            sequential.push(FileReader::new().build_game_set(file_path, FileChecks::ALL)?);
+        }
+
+        let built = Mutex::new(vec![]);
+        build_game_sets(&file_paths, FileChecks::ALL, &ReadOptions::default(), |_, game_set| {
+            built.lock().unwrap().push(game_set.unwrap());
+        })?;
+        let mut parallel = built.into_inner().unwrap();
+        parallel.sort_by(|a, b| a.game.name.cmp(&b.game.name));
+
+        assert_eq!(sequential.len(), parallel.len());
+        for (sequential, parallel) in sequential.iter().zip(&parallel) {
+            assert_eq!(sequential.game.name, parallel.game.name);
+            assert_eq!(sequential.roms, parallel.roms);
+        }
+
+        let built = Mutex::new(vec![]);
+        build_game_sets(&file_paths[..1], FileChecks::SIZE, &ReadOptions::default(), |_, game_set| {
+            built.lock().unwrap().push(game_set.unwrap());
+        })?;
+        let built = built.into_inner().unwrap();
+        assert!(built[0].roms.iter().all(|rom| rom.info.size.is_some() && rom.info.sha1.is_none() && rom.info.crc.is_none()));
+
+        Ok(())
+    }
+}
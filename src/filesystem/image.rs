@@ -0,0 +1,80 @@
+use std::{fmt::Display, fs::File, io::Read, path::Path};
+use serde::{Deserialize, Serialize};
+
+/// Offset of the NKit signature, after the disc header of GameCube and Wii images
+const NKIT_OFFSET: usize = 0x200;
+
+/// Containers and conversions of disc images. The hashes of these files don't match the ones of the
+/// original dumps, so they can't be verified as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImageFormat {
+    Chd,
+    Cso,
+    Zso,
+    NKit,
+    Gcz,
+    Wia,
+    Rvz,
+}
+
+impl Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ImageFormat::Chd => "CHD",
+            ImageFormat::Cso => "CSO",
+            ImageFormat::Zso => "ZSO",
+            ImageFormat::NKit => "NKit",
+            ImageFormat::Gcz => "GCZ",
+            ImageFormat::Wia => "WIA",
+            ImageFormat::Rvz => "RVZ",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl ImageFormat {
+    /// Identifies the format by the signature at the beginning of the file
+    pub fn detect(file_path: &impl AsRef<Path>) -> Option<Self> {
+        let mut header = vec![0u8; NKIT_OFFSET + 4];
+        let read = File::open(file_path).and_then(|mut file| file.read(&mut header)).ok()?;
+        ImageFormat::from_header(&header[..read])
+    }
+
+    fn from_header(header: &[u8]) -> Option<Self> {
+        let signatures: [(&[u8], ImageFormat); 6] = [
+            (b"MComprHD", ImageFormat::Chd),
+            (b"CISO", ImageFormat::Cso),
+            (b"ZISO", ImageFormat::Zso),
+            (&[0x01, 0xc0, 0x0b, 0xb1], ImageFormat::Gcz),
+            (b"WIA\x01", ImageFormat::Wia),
+            (b"RVZ\x01", ImageFormat::Rvz),
+        ];
+        if let Some((_, format)) = signatures.iter().find(|(signature, _)| header.starts_with(signature)) {
+            return Some(*format);
+        }
+
+        match header.get(NKIT_OFFSET..NKIT_OFFSET + 4) {
+            Some(b"NKIT") => Some(ImageFormat::NKit),
+            _ => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_converted_images() {
+        assert_eq!(ImageFormat::from_header(b"CISO\x18\x00\x00\x00"), Some(ImageFormat::Cso));
+        assert_eq!(ImageFormat::from_header(b"MComprHD\x00\x00\x00\x7c"), Some(ImageFormat::Chd));
+        assert_eq!(ImageFormat::from_header(&[0x01, 0xc0, 0x0b, 0xb1, 0x00]), Some(ImageFormat::Gcz));
+
+        let mut nkit = vec![0u8; NKIT_OFFSET + 4];
+        nkit[NKIT_OFFSET..].copy_from_slice(b"NKIT");
+        assert_eq!(ImageFormat::from_header(&nkit), Some(ImageFormat::NKit));
+
+        assert_eq!(ImageFormat::from_header(b"PK\x03\x04"), None);
+        assert_eq!(ImageFormat::from_header(&nkit[..NKIT_OFFSET]), None);
+    }
+}
@@ -0,0 +1,185 @@
+use std::{fs::{self, OpenOptions}, io::{ErrorKind, Write}, path::{Path, PathBuf}, thread, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+use log::{info, error, warn};
+
+use crate::error::RomstIOError;
+
+const LOCK_EXTENSION: &str = "lock";
+const WAIT_INTERVAL: Duration = Duration::from_millis(500);
+/// A lock older than this is taken as left by a process that crashed, when it can't be told if its process is running
+const STALE_LOCK_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Advisory lock for writing a database, it is a file next to the database
+/// that is removed when the lock is dropped
+#[derive(Debug)]
+pub struct DBLock {
+    lock_file: PathBuf,
+}
+
+impl DBLock {
+    /// With `wait`, a lock held by another process is waited for until it is released instead of failing
+    pub fn acquire(db_file: &impl AsRef<Path>, wait: bool) -> Result<Self, RomstIOError> {
+        let lock_file = DBLock::lock_file_for(db_file);
+        let mut waiting = false;
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&lock_file) {
+                Ok(mut file) => {
+                    writeln!(file, "{}", std::process::id())?;
+                    return Ok(Self { lock_file });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    if DBLock::is_stale(&lock_file) && DBLock::reclaim(&lock_file)? {
+                        continue;
+                    }
+                    if !wait {
+                        return Err(RomstIOError::DatabaseLocked(
+                            db_file.as_ref().to_string_lossy().to_string(),
+                            lock_file.to_string_lossy().to_string()));
+                    }
+                    if !waiting {
+                        info!("Waiting for the lock on {}", lock_file.to_string_lossy());
+                        waiting = true;
+                    }
+                    thread::sleep(WAIT_INTERVAL);
+                }
+                Err(e) => return Err(e.into())
+            }
+        }
+    }
+
+    /// Removes a stale lock. Another process may reclaim it or take a new one at the same time, so it's moved
+    /// out of the way first and checked again, and put back if it turns out to be a live lock.
+    /// Returns whether the lock can be taken again
+    fn reclaim(lock_file: &Path) -> Result<bool, RomstIOError> {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_nanos()).unwrap_or_default();
+        let mut file_name = lock_file.as_os_str().to_owned();
+        file_name.push(format!(".stale.{}.{}", std::process::id(), nanos));
+        let stale_file = PathBuf::from(file_name);
+
+        match fs::rename(lock_file, &stale_file) {
+            Ok(()) => {},
+            // Another process reclaimed it first
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(true),
+            Err(e) => return Err(e.into())
+        }
+
+        if DBLock::is_stale(&stale_file) {
+            let pid = fs::read_to_string(&stale_file).unwrap_or_default();
+            warn!("Removing the lock on {} left by the process {}, it's not running anymore", lock_file.to_string_lossy(), pid.trim());
+            fs::remove_file(&stale_file)?;
+            Ok(true)
+        } else {
+            // The lock moved was taken after checking it, a hard link puts it back unless a new one took its place
+            let restored = fs::hard_link(&stale_file, lock_file);
+            fs::remove_file(&stale_file)?;
+            match restored {
+                Ok(()) => Ok(false),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => Ok(false),
+                Err(e) => Err(e.into())
+            }
+        }
+    }
+
+    /// The process that wrote the lock is not running. Where that can't be known, the lock is stale once it's too old
+    fn is_stale(lock_file: &Path) -> bool {
+        let pid = fs::read_to_string(lock_file).ok().and_then(|content| content.trim().parse::<u32>().ok());
+        match pid.and_then(is_process_running) {
+            Some(running) => !running,
+            None => fs::metadata(lock_file).and_then(|metadata| metadata.modified()).ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > STALE_LOCK_AGE),
+        }
+    }
+
+    fn lock_file_for(db_file: &impl AsRef<Path>) -> PathBuf {
+        let mut file_name = db_file.as_ref().as_os_str().to_owned();
+        file_name.push(".");
+        file_name.push(LOCK_EXTENSION);
+        PathBuf::from(file_name)
+    }
+}
+
+/// Whether a process is running, when the platform tells it
+fn is_process_running(pid: u32) -> Option<bool> {
+    if pid == std::process::id() {
+        Some(true)
+    } else if cfg!(target_os = "linux") {
+        Some(Path::new("/proc").join(pid.to_string()).exists())
+    } else {
+        None
+    }
+}
+
+impl Drop for DBLock {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.lock_file) {
+            error!("Error removing the lock file {}: {}", self.lock_file.to_string_lossy(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_is_exclusive_until_dropped() {
+        let db_file = std::env::temp_dir().join(format!("romst_lock_test_{}.rst", std::process::id()));
+        let lock = DBLock::acquire(&db_file, false).unwrap();
+        assert!(matches!(DBLock::acquire(&db_file, false), Err(RomstIOError::DatabaseLocked(_, _))));
+
+        drop(lock);
+        assert!(DBLock::acquire(&db_file, false).is_ok());
+    }
+
+    #[test]
+    fn waits_for_the_lock_when_asked() -> anyhow::Result<()> {
+        let db_file = std::env::temp_dir().join(format!("romst_wait_lock_test_{}.rst", std::process::id()));
+        let lock = DBLock::acquire(&db_file, false)?;
+        let waiting_db_file = db_file.clone();
+        let waiting = thread::spawn(move || DBLock::acquire(&waiting_db_file, true).map(drop));
+
+        thread::sleep(WAIT_INTERVAL);
+        assert!(matches!(DBLock::acquire(&db_file, false), Err(RomstIOError::DatabaseLocked(_, _))));
+        drop(lock);
+        waiting.join().unwrap()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn reclaims_stale_locks() -> anyhow::Result<()> {
+        let db_file = std::env::temp_dir().join(format!("romst_stale_lock_test_{}.rst", std::process::id()));
+        let lock_file = DBLock::lock_file_for(&db_file);
+
+        // Left by a process that crashed long ago, without the pid written
+        fs::write(&lock_file, "")?;
+        let old = std::time::SystemTime::now() - STALE_LOCK_AGE - Duration::from_secs(60);
+        fs::File::options().write(true).open(&lock_file)?.set_modified(old)?;
+        let lock = DBLock::acquire(&db_file, false)?;
+        assert_eq!(fs::read_to_string(&lock_file)?.trim(), std::process::id().to_string());
+        drop(lock);
+
+        // A recent lock of a process that is not running anymore
+        if cfg!(target_os = "linux") {
+            let mut child = std::process::Command::new("true").spawn()?;
+            let pid = child.id();
+            child.wait()?;
+            fs::write(&lock_file, format!("{}\n", pid))?;
+            drop(DBLock::acquire(&db_file, false)?);
+        }
+
+        // Taken again by a running process after it was found stale, it's put back
+        fs::write(&lock_file, format!("{}\n", std::process::id()))?;
+        assert!(!DBLock::reclaim(&lock_file)?);
+        assert_eq!(fs::read_to_string(&lock_file)?.trim(), std::process::id().to_string());
+        fs::remove_file(&lock_file)?;
+
+        // A recent lock without a pid may be being written
+        fs::write(&lock_file, "")?;
+        assert!(matches!(DBLock::acquire(&db_file, false), Err(RomstIOError::DatabaseLocked(_, _))));
+        fs::remove_file(&lock_file)?;
+
+        Ok(())
+    }
+}
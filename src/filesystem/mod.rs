@@ -1,41 +1,145 @@
-mod sha1;
-mod md5;
+pub mod checksum;
+pub mod lock;
+pub mod checksum_file;
+pub mod remote;
+pub mod chd;
+pub mod image;
+pub mod volumes;
+pub mod names;
+pub mod sevenz;
+pub mod rar;
+pub mod atomic;
+pub mod hashing;
+pub mod skipper;
+pub mod torrentzip;
 
 use anyhow::Result;
 use data::models::file::FileType;
-use zip::{ZipArchive, result::ZipError};
-use std::{fs::File, io::BufReader, path::Path};
-use bitflags::bitflags;
+use zip::{ZipArchive, read::ZipFile, result::ZipError};
+use std::{collections::HashSet, fs::File, io::{BufReader, Read}, path::Path};
 
 use crate::{data::{self, models::{file::{DataFile, DataFileInfo}, game::Game, set::GameSet}}, error::RomstIOError};
 
-use self::{md5::MD5Hasher, sha1::SHA1Hasher};
+use rayon::prelude::*;
 
-bitflags! {
-    pub struct FileChecks: u32 {
-        const SHA1 = 0b00000001;
-        const MD5 = 0b00000010;
-        const SIZE = 0b00000100;
-        const CRC = 0b00001000;
-        const ALL = Self::SHA1.bits | Self::MD5.bits | Self::SIZE.bits | Self::CRC.bits;
-    }
+use self::{checksum::{Sha1Engine, ChecksumEngine}, skipper::Skipper, volumes::MultiVolumeReader};
+
+pub use self::checksum::FileChecks;
+
+/// The error of the zip library when reading an encrypted file without password
+const ENCRYPTED_FILE_ERROR: &str = "Password required to decrypt file";
+
+/// Signature at the beginning of each file in a zip archive
+const ZIP_LOCAL_HEADER: &[u8] = b"PK\x03\x04";
+
+/// From this size the checksums of a file are computed at the same time, each one in a thread
+const PARALLEL_HASH_SIZE: usize = 1024 * 1024;
+
+/// How the files are read, given to each reader instead of set for the whole process
+#[derive(Debug, Clone, Default)]
+pub struct ReadOptions {
+    /// The skippers to find the headers with, without any the files are only hashed as they are
+    pub skippers: Vec<Skipper>,
+    /// The passwords to try with encrypted archives, the files that can't be read with any are locked
+    pub passwords: Vec<String>,
+    /// If set, the files that can be read from a corrupt archive are used, instead of discarding the whole archive
+    pub salvage: bool,
 }
 
+#[derive(Default)]
 pub struct FileReader {
-    sha1_hasher: SHA1Hasher,
-    md5_hasher: MD5Hasher,
+    options: ReadOptions,
 }
 
 impl FileReader {
     pub fn new() -> Self {
-        Self { 
-            sha1_hasher: SHA1Hasher::new(),
-            md5_hasher: MD5Hasher::new(),
-        } 
+        Self::default()
+    }
+
+    pub fn with_options(options: ReadOptions) -> Self {
+        Self { options }
+    }
+
+    /// Gets the checksums of a single file, reading it as it is
+    pub fn get_file_info(&mut self, file_path: &impl AsRef<Path>) -> Result<DataFileInfo, RomstIOError> {
+        self.get_file_info_with_checks(file_path, FileChecks::ALL)
+    }
+
+    /// Gets only the checksums of the checks of a single file, reading it as it is
+    pub fn get_file_info_with_checks(&mut self, file_path: &impl AsRef<Path>, file_checks: FileChecks) -> Result<DataFileInfo, RomstIOError> {
+        let data = std::fs::read(file_path)?;
+        Ok(self.build_rom("".to_string(), &data, file_checks).info)
+    }
+
+    /// The sha1 of a file, read in chunks, for big files like DATs
+    pub fn get_file_sha1(&mut self, file_path: &impl AsRef<Path>) -> Result<String, RomstIOError> {
+        let mut file = File::open(file_path)?;
+        Ok(Sha1Engine.hash_reader(&mut file)?)
+    }
+
+    /// Builds a game set from an unzipped set, where every file in the directory is a rom. The CHDs are left out,
+    /// the disks of a set are kept in its directory but they are checked on their own
+    pub fn build_game_set_from_dir(&mut self, dir_path: &impl AsRef<Path>, file_checks: FileChecks) -> Result<GameSet, RomstIOError> {
+        let dir_path = dir_path.as_ref();
+        let game_name = dir_path.file_name().map(|name| name.to_string_lossy().trim().to_string()).unwrap_or_default();
+        let game = Game::new(game_name);
+
+        let mut files = vec![];
+        let mut pending_dirs = vec![dir_path.to_path_buf()];
+        while let Some(current_dir) = pending_dirs.pop() {
+            for entry in current_dir.read_dir()? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    pending_dirs.push(path);
+                    continue;
+                }
+                if atomic::is_temp_file(&path) || path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("chd")) {
+                    continue;
+                }
+
+                // Roms in subdirectories keep the relative path as name, like in zip files
+                let rom_name = path.strip_prefix(dir_path).unwrap_or(&path).components()
+                    .map(|component| component.as_os_str().to_string_lossy().to_string())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.push((rom_name, path));
+            }
+        }
+
+        // Each file is on its own, so they are hashed at the same time
+        let options = &self.options;
+        let mut roms = files.into_par_iter().map(|(rom_name, path)| {
+            let mut file_reader = FileReader::with_options(options.clone());
+            if file_checks == FileChecks::SIZE {
+                let mut rom = file_reader.build_rom(rom_name, &[], file_checks);
+                rom.info.size = Some(path.metadata()?.len() as u32);
+                return Ok(rom);
+            }
+            let data = std::fs::read(&path)?;
+            Ok(file_reader.build_rom(rom_name, &data, file_checks))
+        }).collect::<Result<Vec<_>, RomstIOError>>()?;
+        roms.sort();
+
+        Ok(GameSet::new(game, roms, vec![], vec![], vec![]))
+    }
+
+    fn build_rom(&mut self, name: String, data: &[u8], file_checks: FileChecks) -> DataFile {
+        let mut info = DataFileInfo::new(FileType::Rom);
+        if file_checks.contains(FileChecks::SIZE) {
+            info.size = Some(data.len() as u32);
+        }
+        for (engine, checksum) in hash_with_engines(checksum::get_engines(file_checks).collect(), data) {
+            engine.set(&mut info, checksum);
+        }
+
+        let mut rom = DataFile::new(name, info);
+        rom.headerless = get_headerless_info(data, file_checks, &self.options.skippers);
+        rom
     }
 
     pub fn build_game_set(&mut self, file_path: &impl AsRef<Path>, file_checks: FileChecks) -> Result<GameSet, RomstIOError> {
-        let no_path = Path::new(file_path.as_ref()).with_extension("");
+        // Split archives are named after the whole archive
+        let no_path = volumes::get_archive_path(file_path).with_extension("");
         let base_file_name = no_path.file_name();
 
         let game_name = match base_file_name {
@@ -46,50 +150,84 @@ impl FileReader {
         }.trim();
 
         let game = Game::new(game_name.to_string());
-
-        let use_sha1 = file_checks.contains(FileChecks::SHA1);
-        let use_md5 = file_checks.contains(FileChecks::MD5);
-        let use_crc = file_checks.contains(FileChecks::CRC);
-        let use_size = file_checks.contains(FileChecks::SIZE);
+        if sevenz::is_7z_file(file_path) {
+            return self.build_7z_game_set(file_path, game, file_checks);
+        }
+        if rar::is_rar_file(file_path) {
+            return self.build_rar_game_set(file_path, game, file_checks);
+        }
 
         let mut roms = vec![];
-        let file = File::open(&file_path)?;
-        let reader = BufReader::new(file);
+        let mut locked = vec![];
+        let mut corrupt = vec![];
+        let reader = BufReader::new(MultiVolumeReader::open(file_path)?);
 
         match ZipArchive::new(reader) {
             Ok(mut archive) => {
+                let mut read_names = HashSet::new();
+                let mut encrypted = vec![];
+                let mut unreadable = 0;
                 for i in 0..archive.len() {
-                    let mut f = archive.by_index(i).map_err(|err| { RomstIOError::Io{ source: err.into() } })?;
-                    let mut writer = vec![];
-                    std::io::copy(&mut f, &mut writer)?;
-
-                    let sha1 =  if use_sha1 { Some(self.sha1_hasher.get_hash(&writer)) } else { None };
-                    let md5 =  if use_md5 { Some(self.md5_hasher.get_hash(&writer)) } else { None };
-                    let size = if use_size { Some(f.size() as u32) } else { None };
-                    let crc = if use_crc { 
-                        let crc32 = format!("{:01$x}", f.crc32(), 8);
-                        Some(crc32)
-                    } else { None };
-
-                    let rom = DataFile {
-                        name: f.name().trim().to_string(),
-                        info: DataFileInfo {
-                            file_type: FileType::Rom,
-                            sha1,
-                            md5,
-                            crc,
-                            size,
-                        },
-                        status: None
+                    let mut f = match archive.by_index(i) {
+                        Ok(f) => f,
+                        // Encrypted files are tried later with the passwords
+                        Err(ZipError::UnsupportedArchive(message)) if message == ENCRYPTED_FILE_ERROR => {
+                            encrypted.push(i);
+                            continue;
+                        }
+                        Err(_) => {
+                            unreadable += 1;
+                            continue;
+                        }
                     };
-                    
-                    roms.push(rom);
+                    read_names.insert(f.name().to_string());
+                    // A file with a wrong crc or damaged data fails when reading it
+                    match self.build_zip_rom(&mut f, file_checks) {
+                        Ok(rom) => roms.push(rom),
+                        Err(_) => corrupt.push(f.name().trim().to_string())
+                    }
+                }
+
+                if !encrypted.is_empty() {
+                    let passwords = self.options.passwords.clone();
+                    for i in encrypted {
+                        // A wrong password can pass the header check, but then the crc doesn't match when reading
+                        let rom = passwords.iter().find_map(|password| {
+                            match archive.by_index_decrypt(i, password.as_bytes()) {
+                                Ok(Ok(mut f)) => {
+                                    let name = f.name().to_string();
+                                    self.build_zip_rom(&mut f, file_checks).ok().map(|rom| (name, rom))
+                                }
+                                _ => None
+                            }
+                        });
+                        if let Some((name, rom)) = rom {
+                            read_names.insert(name);
+                            roms.push(rom);
+                        }
+                    }
+                }
+
+                // The names of the files that couldn't be opened are unknown, so they are the ones never read
+                let mut not_read = archive.file_names()
+                    .filter(|name| !read_names.contains(*name))
+                    .map(|name| name.trim().to_string())
+                    .collect::<Vec<_>>();
+                not_read.sort();
+                if unreadable > 0 {
+                    corrupt.extend(not_read);
+                } else {
+                    locked = not_read;
                 }
             },
             Err(ZipError::InvalidArchive(_e)) => {
-                let file_name = file_path.as_ref().to_path_buf().into_os_string().into_string().unwrap_or_else(|ref osstring| {
+                let file_name = volumes::get_archive_path(file_path).into_os_string().into_string().unwrap_or_else(|ref osstring| {
                     osstring.to_string_lossy().to_string()
                 });
+                // A zip file without its central directory at the end is a truncated archive
+                if is_zip_file(file_path) {
+                    return Err(RomstIOError::CorruptArchive(file_name, get_local_file_names(file_path)))
+                }
                 return Err(RomstIOError::NotValidFileError(file_name, FileType::Rom))
             },
             Err(ZipError::FileNotFound) => {
@@ -101,10 +239,227 @@ impl FileReader {
             Err(e) => { return Err(RomstIOError::Io{ source: e.into() }) }
         }
 
-        let game_set = GameSet::new(game, roms, vec![], vec![], vec![]);
+        if !corrupt.is_empty() && !self.options.salvage {
+            let file_name = volumes::get_archive_path(file_path).to_string_lossy().to_string();
+            return Err(RomstIOError::CorruptArchive(file_name, corrupt))
+        }
+
+        let mut game_set = GameSet::new(game, roms, vec![], vec![], vec![]);
+        game_set.locked = locked;
+        game_set.corrupt = corrupt;
+
+        Ok(game_set)
+    }
+
+    /// The files of a 7z archive are all read, so they are hashed as they are decompressed. A file that fails to
+    /// decompress stops the rest of a solid archive, all of them are corrupt then
+    fn build_7z_game_set(&mut self, file_path: &impl AsRef<Path>, game: Game, file_checks: FileChecks) -> Result<GameSet, RomstIOError> {
+        let file_name = volumes::get_archive_path(file_path).to_string_lossy().to_string();
+        let passwords = self.options.passwords.clone();
+
+        let mut roms: Vec<DataFile> = vec![];
+        let result = sevenz::for_each_file(file_path, &passwords, |entry, data| {
+            let mut buffer = vec![];
+            data.read_to_end(&mut buffer)?;
+            // A retry with another password reads again the files not encrypted
+            if !roms.iter().any(|rom| rom.name == entry.name().trim()) {
+                roms.push(self.build_rom(entry.name().trim().to_string(), &buffer, file_checks));
+            }
+            Ok(())
+        });
+
+        let mut locked = vec![];
+        let mut corrupt = vec![];
+        match result {
+            Ok(()) => {}
+            Err(sevenz_rust::Error::PasswordRequired) | Err(sevenz_rust::Error::MaybeBadPassword(_)) => {
+                locked = get_7z_names_not_read(file_path, &roms, &passwords);
+            }
+            Err(sevenz_rust::Error::FileOpen(_, _)) => return Err(RomstIOError::FileNotFound(file_name)),
+            Err(_) => {
+                corrupt = get_7z_names_not_read(file_path, &roms, &passwords);
+                if !self.options.salvage {
+                    return Err(RomstIOError::CorruptArchive(file_name, corrupt))
+                }
+            }
+        }
+        roms.sort();
+
+        let mut game_set = GameSet::new(game, roms, vec![], vec![], vec![]);
+        game_set.locked = locked;
+        game_set.corrupt = corrupt;
 
         Ok(game_set)
     }
+
+    /// The files of a RAR archive are listed from its headers. The stored ones are read as they are, the compressed
+    /// ones are decompressed with unrar
+    fn build_rar_game_set(&mut self, file_path: &impl AsRef<Path>, game: Game, file_checks: FileChecks) -> Result<GameSet, RomstIOError> {
+        let file_name = volumes::get_archive_path(file_path).to_string_lossy().to_string();
+        let archive = rar::get_entries(file_path)?;
+        let passwords = self.options.passwords.clone();
+
+        let mut roms = vec![];
+        let mut locked = vec![];
+        let mut corrupt = vec![];
+        for entry in archive.entries {
+            // The size is in the header, only the checksums need the data
+            if file_checks == FileChecks::SIZE {
+                let mut rom = self.build_rom(entry.name, &[], file_checks);
+                rom.info.size = Some(entry.size as u32);
+                roms.push(rom);
+                continue;
+            }
+            match rar::read_file(file_path, &entry, &passwords) {
+                Ok(Some(data)) => roms.push(self.build_rom(entry.name, &data, file_checks)),
+                Ok(None) => locked.push(entry.name),
+                // Without unrar none of the compressed files can be read
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(e.into()),
+                Err(_) => corrupt.push(entry.name)
+            }
+        }
+
+        if (archive.truncated || !corrupt.is_empty()) && !self.options.salvage {
+            return Err(RomstIOError::CorruptArchive(file_name, corrupt))
+        }
+        roms.sort();
+
+        let mut game_set = GameSet::new(game, roms, vec![], vec![], vec![]);
+        game_set.locked = locked;
+        game_set.corrupt = corrupt;
+
+        Ok(game_set)
+    }
+
+    fn build_zip_rom(&mut self, f: &mut ZipFile, file_checks: FileChecks) -> Result<DataFile, RomstIOError> {
+        // The size is in the header, only the checksums need the data
+        let mut writer = vec![];
+        if file_checks != FileChecks::SIZE {
+            std::io::copy(f, &mut writer)?;
+        }
+
+        let mut info = DataFileInfo::new(FileType::Rom);
+        if file_checks.contains(FileChecks::SIZE) {
+            info.size = Some(f.size() as u32);
+        }
+        let mut to_hash = vec![];
+        for engine in checksum::get_engines(file_checks) {
+            match engine.zip_checksum(f) {
+                Some(checksum) => engine.set(&mut info, checksum),
+                None => to_hash.push(engine)
+            }
+        }
+        for (engine, checksum) in hash_with_engines(to_hash, &writer) {
+            engine.set(&mut info, checksum);
+        }
+
+        let mut rom = DataFile::new(f.name().trim(), info);
+        rom.headerless = get_headerless_info(&writer, file_checks, &self.options.skippers);
+        Ok(rom)
+    }
+}
+
+/// The checksums of the data with each engine, the ones of a big file are computed at the same time
+fn hash_with_engines(engines: Vec<&'static dyn ChecksumEngine>, data: &[u8]) -> Vec<(&'static dyn ChecksumEngine, String)> {
+    if data.len() >= PARALLEL_HASH_SIZE && engines.len() > 1 {
+        engines.into_par_iter().map(|engine| (engine, engine.hash(data))).collect()
+    } else {
+        engines.into_iter().map(|engine| (engine, engine.hash(data))).collect()
+    }
+}
+
+/// The info of the data without its header, when the checks ask for it and a skipper finds a header
+fn get_headerless_info(data: &[u8], file_checks: FileChecks, skippers: &[Skipper]) -> Option<DataFileInfo> {
+    if !file_checks.contains(FileChecks::HEADERLESS) {
+        return None;
+    }
+    let headerless = skipper::strip_header(skippers, data)?;
+    let mut info = DataFileInfo::new(FileType::Rom);
+    if file_checks.contains(FileChecks::SIZE) {
+        info.size = Some(headerless.len() as u32);
+    }
+    for (engine, checksum) in hash_with_engines(checksum::get_engines(file_checks).collect(), &headerless) {
+        engine.set(&mut info, checksum);
+    }
+    Some(info)
+}
+
+/// The first bytes of a file, or of a file inside a zip, 7z or RAR archive, trying the passwords if it's encrypted
+pub fn read_header(file_path: &impl AsRef<Path>, inner_name: Option<&str>, length: usize, passwords: &[String]) -> Result<Vec<u8>> {
+    let mut header = vec![];
+    match inner_name {
+        None => {
+            File::open(file_path)?.take(length as u64).read_to_end(&mut header)?;
+        }
+        Some(inner_name) if sevenz::is_7z_file(file_path) => {
+            sevenz::for_each_file(file_path, passwords, |entry, data| {
+                if entry.name().trim() == inner_name {
+                    data.take(length as u64).read_to_end(&mut header)?;
+                }
+                Ok(())
+            }).map_err(|e| anyhow::anyhow!("{}", e))?;
+        }
+        Some(inner_name) if rar::is_rar_file(file_path) => {
+            let entry = rar::get_entries(file_path)?.entries.into_iter()
+                .find(|entry| entry.name == inner_name)
+                .ok_or_else(|| anyhow::anyhow!("`{}` not found", inner_name))?;
+            header = rar::read_file(file_path, &entry, passwords)?.unwrap_or_default();
+            header.truncate(length);
+        }
+        Some(inner_name) => {
+            let mut archive = ZipArchive::new(BufReader::new(MultiVolumeReader::open(file_path)?))?;
+            archive.by_name(inner_name)?.take(length as u64).read_to_end(&mut header)?;
+        }
+    }
+    Ok(header)
+}
+
+fn is_zip_file(file_path: &impl AsRef<Path>) -> bool {
+    let mut signature = [0u8; 4];
+    match volumes::get_volumes(file_path).first() {
+        Some(volume) => File::open(volume).and_then(|mut file| file.read_exact(&mut signature)).is_ok() && signature == ZIP_LOCAL_HEADER,
+        None => false
+    }
+}
+
+/// The names of the files in a 7z archive that are not in the roms read
+fn get_7z_names_not_read(file_path: &impl AsRef<Path>, roms: &[DataFile], passwords: &[String]) -> Vec<String> {
+    let mut names = sevenz::get_file_names(file_path, passwords).unwrap_or_default().into_iter()
+        .filter(|name| !roms.iter().any(|rom| rom.name == *name))
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+/// The names in the headers that precede each file in a zip archive, used when the central directory
+/// at the end of the archive is missing. It stops at the first header that is not complete.
+fn get_local_file_names(file_path: &impl AsRef<Path>) -> Vec<String> {
+    let mut data = vec![];
+    if MultiVolumeReader::open(file_path).and_then(|mut reader| reader.read_to_end(&mut data)).is_err() {
+        return vec![];
+    }
+
+    let mut names = vec![];
+    let mut offset = 0;
+    while data.get(offset..offset + 4) == Some(ZIP_LOCAL_HEADER) && offset + 30 <= data.len() {
+        let header = &data[offset..offset + 30];
+        let flags = u16::from_le_bytes([header[6], header[7]]);
+        let compressed_size = u32::from_le_bytes([header[18], header[19], header[20], header[21]]) as usize;
+        let name_length = u16::from_le_bytes([header[26], header[27]]) as usize;
+        let extra_length = u16::from_le_bytes([header[28], header[29]]) as usize;
+        let name = match data.get(offset + 30..offset + 30 + name_length) {
+            Some(name) => String::from_utf8_lossy(name).trim().to_string(),
+            None => break
+        };
+        names.push(name);
+
+        // With a data descriptor the size is after the data, so the next header can't be found
+        if flags & 0x08 != 0 {
+            break;
+        }
+        offset += 30 + name_length + extra_length + compressed_size;
+    }
+    names
 }
 
 #[cfg(test)]
@@ -130,4 +485,82 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn gets_7z_info_as_the_zip_one() -> Result<()> {
+        let mut file_reader: FileReader = FileReader::new();
+        let zip_set = file_reader.build_game_set(&Path::new("testdata").join("split").join("game2.zip"), FileChecks::ALL)?;
+        let sevenz_set = file_reader.build_game_set(&Path::new("testdata").join("sevenz").join("game2.7z"), FileChecks::ALL)?;
+
+        assert_eq!(sevenz_set.game.name, "game2");
+        assert_eq!(sevenz_set.roms, zip_set.roms);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reads_encrypted_zips_with_passwords() -> Result<()> {
+        let file_path = Path::new("testdata").join("encrypted").join("game1.zip");
+
+        let mut file_reader = FileReader::with_options(ReadOptions { passwords: vec!["wrong".to_string()], ..ReadOptions::default() });
+        let game_set = file_reader.build_game_set(&file_path, FileChecks::ALL)?;
+        assert_eq!(game_set.roms.len(), 2);
+        assert_eq!(game_set.locked, vec!["rom1.trom".to_string(), "rom2.trom".to_string()]);
+
+        let mut file_reader = FileReader::with_options(ReadOptions { passwords: vec!["wrong".to_string(), "secret".to_string()], ..ReadOptions::default() });
+        let game_set = file_reader.build_game_set(&file_path, FileChecks::ALL)?;
+        assert_eq!(game_set.roms.len(), 4);
+        assert!(game_set.locked.is_empty());
+        assert!(game_set.roms.iter().any(|rom| rom.name == "rom1.trom" && rom.info.crc == Some("1d460eee".to_string())));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reports_corrupt_zips() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("romst_corrupt_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let mut data = std::fs::read(Path::new("testdata").join("split").join("game1.zip"))?;
+
+        // The first rom is stored, so changing its data makes the crc fail
+        let name_position = data.windows(9).position(|window| window == b"rom1.trom").unwrap();
+        let extra_length = u16::from_le_bytes([data[name_position - 2], data[name_position - 1]]) as usize;
+        data[name_position + 9 + extra_length] ^= 0xff;
+        let file_path = dir.join("game1.zip");
+        std::fs::write(&file_path, &data)?;
+
+        let mut file_reader: FileReader = FileReader::new();
+        match file_reader.build_game_set(&file_path, FileChecks::ALL) {
+            Err(RomstIOError::CorruptArchive(_, members)) => assert_eq!(members, vec!["rom1.trom".to_string()]),
+            _ => panic!("The archive should be corrupt")
+        }
+
+        let game_set = FileReader::with_options(ReadOptions { salvage: true, ..ReadOptions::default() }).build_game_set(&file_path, FileChecks::ALL)?;
+        assert_eq!(game_set.roms.len(), 3);
+        assert_eq!(game_set.corrupt, vec!["rom1.trom".to_string()]);
+
+        // Without the central directory the files are taken from the local headers
+        let truncated_path = dir.join("game2.zip");
+        std::fs::write(&truncated_path, &data[..data.len() / 2])?;
+        match file_reader.build_game_set(&truncated_path, FileChecks::ALL) {
+            Err(RomstIOError::CorruptArchive(_, members)) => assert!(members.contains(&"rom1.trom".to_string())),
+            _ => panic!("The archive should be corrupt")
+        }
+        std::fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn gets_file_info() -> Result<()> {
+        let mut file_reader: FileReader = FileReader::new();
+        let file_path = Path::new("testdata").join("split").join("game1.zip");
+        let info = file_reader.get_file_info(&file_path)?;
+
+        assert_eq!(info.size, Some(std::fs::metadata(&file_path)?.len() as u32));
+        assert_eq!(info.sha1.map(|sha1| sha1.len()), Some(40));
+        assert_eq!(info.crc.map(|crc| crc.len()), Some(8));
+
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,185 @@
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+use anyhow::{Result, anyhow};
+use bitflags::bitflags;
+use serde::{Deserialize, Serialize};
+use unicode_normalization::{UnicodeNormalization, char::is_combining_mark};
+
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+const INVALID_CHARACTERS: [char; 7] = ['<', '>', ':', '"', '|', '?', '*'];
+/// The articles moved to the end of the titles in the DATs, like `Legend of Zelda, The`
+const ARTICLES: [&str; 9] = ["The", "A", "An", "Le", "La", "Les", "El", "Los", "Die"];
+
+bitflags! {
+    /// The differences ignored when comparing the names of the files with the names in the DATs, applied in this order
+    pub struct NameRules: u32 {
+        /// `Pokémon` is `Pokemon`
+        const DIACRITICS = 0b0001;
+        /// The underscores and dots are spaces, and the runs of spaces a single one
+        const SEPARATORS = 0b0010;
+        /// `Legend of Zelda, The (USA)` is `The Legend of Zelda (USA)`
+        const ARTICLES = 0b0100;
+        const CASE = 0b1000;
+        const ALL = Self::DIACRITICS.bits | Self::SEPARATORS.bits | Self::ARTICLES.bits | Self::CASE.bits;
+    }
+}
+
+impl FromStr for NameRules {
+    type Err = anyhow::Error;
+
+    /// A comma separated list of rules, or `all` or `none`
+    fn from_str(s: &str) -> Result<Self> {
+        let mut rules = NameRules::empty();
+        for rule in s.split(',').map(|rule| rule.trim()) {
+            rules |= match rule {
+                "all" => NameRules::ALL,
+                "none" => NameRules::empty(),
+                "diacritics" => NameRules::DIACRITICS,
+                "separators" => NameRules::SEPARATORS,
+                "articles" => NameRules::ARTICLES,
+                "case" => NameRules::CASE,
+                _ => return Err(anyhow!("Non valid name rule `{}`, can be `diacritics`, `separators`, `articles`, `case`, `all` or `none`", rule))
+            };
+        }
+        Ok(rules)
+    }
+}
+
+/// Every difference is ignored unless told otherwise
+impl Default for NameRules {
+    fn default() -> Self {
+        NameRules::ALL
+    }
+}
+
+impl NameRules {
+    pub fn normalize(&self, name: &str) -> String {
+        let mut name = name.to_string();
+        if self.contains(NameRules::DIACRITICS) {
+            name = name.nfd().filter(|c| !is_combining_mark(*c)).collect();
+        }
+        if self.contains(NameRules::SEPARATORS) {
+            name = name.replace(['_', '.'], " ").split_whitespace().collect::<Vec<_>>().join(" ");
+        }
+        if self.contains(NameRules::ARTICLES) {
+            name = move_article_to_front(&name);
+        }
+        if self.contains(NameRules::CASE) {
+            name = name.to_lowercase();
+        }
+        name
+    }
+
+    /// Both names are the same once normalized
+    pub fn same_name(&self, name: &str, other: &str) -> bool {
+        name == other || self.normalize(name) == self.normalize(other)
+    }
+}
+
+/// The article at the end of the title, before the tags in parentheses, goes to the front
+fn move_article_to_front(name: &str) -> String {
+    let title_end = name.find(" (").unwrap_or(name.len());
+    let (title, tags) = name.split_at(title_end);
+    for article in ARTICLES.iter() {
+        let suffix_len = article.len() + 2;
+        if title.len() > suffix_len && title.is_char_boundary(title.len() - suffix_len) {
+            let (start, suffix) = title.split_at(title.len() - suffix_len);
+            if suffix.starts_with(", ") && suffix[2..].eq_ignore_ascii_case(article) {
+                return format!("{} {}{}", &suffix[2..], start, tags);
+            }
+        }
+    }
+    name.to_string()
+}
+
+/// Why a name can't be used for a file on Windows
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowsNameIssue {
+    /// A device name, like `CON` or `aux.bin`, with or without extension
+    Reserved(String),
+    /// Windows drops the trailing dots and spaces, so the file would get another name
+    TrailingDotOrSpace(String),
+    InvalidCharacter(String, char),
+    /// Both names are the same file on Windows
+    CaseCollision(String, String),
+}
+
+impl Display for WindowsNameIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WindowsNameIssue::Reserved(name) => write!(f, "`{}` is a reserved name", name),
+            WindowsNameIssue::TrailingDotOrSpace(name) => write!(f, "`{}` ends with a dot or a space", name),
+            WindowsNameIssue::InvalidCharacter(name, c) => write!(f, "`{}` contains the character `{}`", name, c.escape_default()),
+            WindowsNameIssue::CaseCollision(name, other) => write!(f, "`{}` and `{}` only differ in case", name, other),
+        }
+    }
+}
+
+/// Checks every component of a name, the DATs use both `/` and `\` to put files in folders
+pub fn check_windows_name(name: &str) -> Option<WindowsNameIssue> {
+    for component in name.split(['/', '\\']).filter(|component| !component.is_empty()) {
+        if let Some(c) = component.chars().find(|c| INVALID_CHARACTERS.contains(c) || c.is_control()) {
+            return Some(WindowsNameIssue::InvalidCharacter(name.to_string(), c));
+        }
+        if component.ends_with('.') || component.ends_with(' ') {
+            return Some(WindowsNameIssue::TrailingDotOrSpace(name.to_string()));
+        }
+        let stem = component.split('.').next().unwrap_or_default().trim_end();
+        if RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)) {
+            return Some(WindowsNameIssue::Reserved(name.to_string()));
+        }
+    }
+    None
+}
+
+/// The first two names that are the same file on Windows, which ignores the case
+pub fn find_case_collision<'a>(names: impl IntoIterator<Item = &'a str>) -> Option<WindowsNameIssue> {
+    let mut seen: HashMap<String, &str> = HashMap::new();
+    for name in names {
+        let key = name.replace('\\', "/").to_lowercase();
+        match seen.get(&key) {
+            Some(other) if *other != name => return Some(WindowsNameIssue::CaseCollision(other.to_string(), name.to_string())),
+            Some(_) => {}
+            None => { seen.insert(key, name); }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_names_not_valid_on_windows() {
+        assert_eq!(check_windows_name("pacman/rom1.bin"), None);
+        assert_eq!(check_windows_name("con"), Some(WindowsNameIssue::Reserved("con".to_string())));
+        assert_eq!(check_windows_name("disk\\Aux.bin"), Some(WindowsNameIssue::Reserved("disk\\Aux.bin".to_string())));
+        assert_eq!(check_windows_name("console.bin"), None);
+        assert_eq!(check_windows_name("rom1."), Some(WindowsNameIssue::TrailingDotOrSpace("rom1.".to_string())));
+        assert_eq!(check_windows_name("what?.bin"), Some(WindowsNameIssue::InvalidCharacter("what?.bin".to_string(), '?')));
+        assert_eq!(find_case_collision(vec!["a.bin", "b.bin", "a.bin"]), None);
+        assert_eq!(find_case_collision(vec!["rom.bin", "ROM.bin"]), Some(WindowsNameIssue::CaseCollision("rom.bin".to_string(), "ROM.bin".to_string())));
+    }
+
+    #[test]
+    fn normalizes_the_names() -> Result<()> {
+        let rules = NameRules::ALL;
+        assert_eq!(rules.normalize("Pokémon_Snap  (USA)"), "pokemon snap (usa)");
+        assert!(rules.same_name("Legend of Zelda, The (USA)", "the legend of zelda (USA)"));
+        assert!(rules.same_name("Legend_of_Zelda,_The_(USA)", "The Legend of Zelda (USA)"));
+        assert!(!rules.same_name("Legend of Zelda (USA)", "The Legend of Zelda (USA)"));
+        assert_eq!(move_article_to_front("Them (Europe)"), "Them (Europe)");
+
+        let rules = "separators, case".parse::<NameRules>()?;
+        assert!(rules.same_name("Super_Mario_Bros. (World)", "super mario bros (world)"));
+        assert!(!rules.same_name("Pokémon Snap", "Pokemon Snap"));
+        assert!("none".parse::<NameRules>()?.is_empty());
+        assert!("accents".parse::<NameRules>().is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,472 @@
+use std::{io::{self, BufReader, Read, Seek, SeekFrom, Write}, path::Path, process::{Command, Stdio}};
+
+use super::volumes::{self, MultiVolumeReader};
+
+/// Signature at the beginning of the archives of RAR 1.5 to 4
+const RAR4_SIGNATURE: &[u8] = b"Rar!\x1A\x07\x00";
+/// Signature at the beginning of the archives of RAR 5
+const RAR5_SIGNATURE: &[u8] = b"Rar!\x1A\x07\x01\x00";
+/// Decompresses the files that are not just stored, can be replaced with the `ROMST_UNRAR` environment variable
+const DEFAULT_UNRAR_COMMAND: &str = "unrar";
+
+/// A file in a RAR archive, as described in its header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RarEntry {
+    pub name: String,
+    pub size: u64,
+    /// The crc32 of the file, RAR 5 archives can leave it out
+    pub crc: Option<u32>,
+    pub encrypted: bool,
+    /// Stored without compression, so it can be read as it is
+    stored: bool,
+    /// Continues in the next volume of a multivolume archive
+    split: bool,
+    data_offset: u64,
+    packed_size: u64,
+}
+
+/// The files listed in the headers of an archive
+#[derive(Debug)]
+pub struct RarArchive {
+    pub entries: Vec<RarEntry>,
+    /// A header is damaged or the archive ends before its last file, the files after it are unknown
+    pub truncated: bool,
+}
+
+/// Checks the signature, of the first volume for split archives
+pub fn is_rar_file(file_path: &impl AsRef<Path>) -> bool {
+    let mut signature = [0u8; 8];
+    match volumes::get_volumes(file_path).first() {
+        Some(volume) => std::fs::File::open(volume).and_then(|mut file| file.read_exact(&mut signature)).is_ok()
+            && (signature.starts_with(RAR4_SIGNATURE) || signature.starts_with(RAR5_SIGNATURE)),
+        None => false
+    }
+}
+
+/// Lists the files from the headers, without reading them. Archives with encrypted headers can't be listed
+pub fn get_entries(file_path: &impl AsRef<Path>) -> io::Result<RarArchive> {
+    let mut reader = BufReader::new(MultiVolumeReader::open(file_path)?);
+    let length = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+    let mut signature = [0u8; 8];
+    reader.read_exact(&mut signature)?;
+
+    let mut archive = RarArchive { entries: vec![], truncated: false };
+    let result = if signature.starts_with(RAR5_SIGNATURE) {
+        read_rar5_entries(&mut reader, length, &mut archive.entries)
+    } else if signature.starts_with(RAR4_SIGNATURE) {
+        read_rar4_entries(&mut reader, length, &mut archive.entries)
+    } else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Not a RAR archive"));
+    };
+    match result {
+        Ok(truncated) => archive.truncated = truncated,
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof || e.kind() == io::ErrorKind::InvalidData => archive.truncated = true,
+        Err(e) => return Err(e)
+    }
+
+    Ok(archive)
+}
+
+/// The data of a file, `None` if it's encrypted and none of the passwords work. The stored files are read
+/// directly and checked against their crc, the compressed ones need `unrar`
+pub fn read_file(file_path: &impl AsRef<Path>, entry: &RarEntry, passwords: &[String]) -> io::Result<Option<Vec<u8>>> {
+    if entry.stored && !entry.encrypted && !entry.split {
+        let mut reader = MultiVolumeReader::open(file_path)?;
+        reader.seek(SeekFrom::Start(entry.data_offset))?;
+        // The size comes from the header, a damaged one can't make it allocate more than the archive has
+        let mut data = vec![];
+        reader.take(entry.packed_size).read_to_end(&mut data)?;
+        if data.len() as u64 != entry.packed_size {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("{} ends before the end of {}", file_path.as_ref().display(), entry.name)));
+        }
+        if entry.crc.is_some_and(|crc| crc != get_crc(&data)) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Wrong crc for {}", entry.name)));
+        }
+        return Ok(Some(data));
+    }
+
+    if !entry.encrypted {
+        return extract(file_path, entry, None).map(Some);
+    }
+    for password in passwords {
+        match extract(file_path, entry, Some(password)) {
+            Ok(data) => return Ok(Some(data)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(e),
+            Err(_) => continue
+        }
+    }
+    Ok(None)
+}
+
+/// unrar checks the crc itself, with encrypted RAR 5 files the one in the header is mixed with the password
+fn extract(file_path: &impl AsRef<Path>, entry: &RarEntry, password: Option<&str>) -> io::Result<Vec<u8>> {
+    let program = std::env::var("ROMST_UNRAR").unwrap_or_else(|_| DEFAULT_UNRAR_COMMAND.to_string());
+    extract_with(&program, file_path, entry, password)
+}
+
+/// The password is written to the input of unrar, which reads it from there when asked with `-p`, so it's not in
+/// the command line other users can see
+fn extract_with(program: &str, file_path: &impl AsRef<Path>, entry: &RarEntry, password: Option<&str>) -> io::Result<Vec<u8>> {
+    let mut child = Command::new(program)
+        .args(["p", "-inul", "-cfg-", if password.is_some() { "-p" } else { "-p-" }, "--"])
+        .arg(file_path.as_ref())
+        .arg(&entry.name)
+        .stdin(if password.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("`{}` is needed to read compressed RAR files: {}", program, e)))?;
+    if let (Some(mut stdin), Some(password)) = (child.stdin.take(), password) {
+        // unrar may end before reading it, then it fails below anyway
+        let _ = writeln!(stdin, "{}", password);
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() || output.stdout.len() as u64 != entry.size {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Couldn't extract {}: {}", entry.name, output.status)));
+    }
+    Ok(output.stdout)
+}
+
+/// Returns if the archive is truncated. The headers have a type, flags and size, then the data of the block
+fn read_rar4_entries(reader: &mut (impl Read + Seek), length: u64, entries: &mut Vec<RarEntry>) -> io::Result<bool> {
+    let mut position = RAR4_SIGNATURE.len() as u64;
+    while position < length {
+        reader.seek(SeekFrom::Start(position))?;
+        let mut base = [0u8; 7];
+        reader.read_exact(&mut base)?;
+        let header_type = base[2];
+        let flags = u16::from_le_bytes([base[3], base[4]]);
+        let header_size = u16::from_le_bytes([base[5], base[6]]) as u64;
+        if header_size < base.len() as u64 {
+            return Ok(true);
+        }
+        let mut header = vec![0u8; header_size as usize - base.len()];
+        reader.read_exact(&mut header)?;
+        // The header crc is the lower half of the crc32 of the header after it
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&base[2..]);
+        hasher.update(&header);
+        if hasher.finalize() as u16 != u16::from_le_bytes([base[0], base[1]]) {
+            return Ok(true);
+        }
+
+        // Blocks with this flag have data after the header, its size is the first field
+        let mut data_size = if flags & 0x8000 != 0 { HeaderReader::new(&header).u32()? as u64 } else { 0 };
+        match header_type {
+            // The main header, with the flag of the encrypted headers
+            0x73 if flags & 0x0080 != 0 => return Err(encrypted_headers()),
+            0x74 => {
+                let mut fields = HeaderReader::new(&header);
+                let packed_size = fields.u32()? as u64;
+                let size = fields.u32()? as u64;
+                fields.skip(1)?;
+                let crc = fields.u32()?;
+                fields.skip(5)?;
+                let method = fields.u8()?;
+                let name_size = fields.u16()? as usize;
+                fields.skip(4)?;
+                // Files bigger than 4 GiB have the high halves of the sizes
+                let (packed_size, size) = if flags & 0x0100 != 0 {
+                    (packed_size | (fields.u32()? as u64) << 32, size | (fields.u32()? as u64) << 32)
+                } else {
+                    (packed_size, size)
+                };
+                // Unicode names are stored after the plain one, encoded in a way only unrar knows
+                let name = fields.bytes(name_size)?;
+                let name = if flags & 0x0200 != 0 { name.split(|byte| *byte == 0).next().unwrap_or_default() } else { name };
+                data_size = packed_size;
+
+                let data_offset = position + header_size;
+                // A damaged header can have sizes past any archive
+                let data_end = match data_offset.checked_add(packed_size) {
+                    Some(data_end) => data_end,
+                    None => return Ok(true)
+                };
+                let is_directory = flags & 0x00E0 == 0x00E0;
+                // The files continued from the previous volume are read with it
+                if !is_directory && flags & 0x0001 == 0 {
+                    entries.push(RarEntry {
+                        name: String::from_utf8_lossy(name).replace('\\', "/"),
+                        size,
+                        crc: Some(crc),
+                        encrypted: flags & 0x0004 != 0,
+                        stored: method == 0x30,
+                        split: flags & 0x0002 != 0,
+                        data_offset,
+                        packed_size,
+                    });
+                }
+                if data_end > length {
+                    return Ok(true);
+                }
+            }
+            // The end of the archive
+            0x7B => break,
+            _ => {}
+        }
+        position += header_size + data_size;
+    }
+    Ok(false)
+}
+
+/// Returns if the archive is truncated. The headers have a crc32, a size, a type and flags, all of them but the crc as
+/// variable length integers
+fn read_rar5_entries(reader: &mut (impl Read + Seek), length: u64, entries: &mut Vec<RarEntry>) -> io::Result<bool> {
+    let mut position = RAR5_SIGNATURE.len() as u64;
+    while position < length {
+        reader.seek(SeekFrom::Start(position))?;
+        let mut crc = [0u8; 4];
+        reader.read_exact(&mut crc)?;
+        let mut size_bytes = vec![];
+        loop {
+            let mut byte = [0u8; 1];
+            reader.read_exact(&mut byte)?;
+            size_bytes.push(byte[0]);
+            if byte[0] & 0x80 == 0 || size_bytes.len() == 3 {
+                break;
+            }
+        }
+        let header_size = HeaderReader::new(&size_bytes).vint()?;
+        let mut header = vec![0u8; header_size as usize];
+        reader.read_exact(&mut header)?;
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&size_bytes);
+        hasher.update(&header);
+        if hasher.finalize() != u32::from_le_bytes(crc) {
+            return Ok(true);
+        }
+
+        let mut fields = HeaderReader::new(&header);
+        let header_type = fields.vint()?;
+        let flags = fields.vint()?;
+        let extra_size = if flags & 0x0001 != 0 { fields.vint()? } else { 0 };
+        let data_size = if flags & 0x0002 != 0 { fields.vint()? } else { 0 };
+        let data_offset = position + (crc.len() + size_bytes.len()) as u64 + header_size;
+        // A damaged header can have sizes past any archive
+        let data_end = match data_offset.checked_add(data_size) {
+            Some(data_end) => data_end,
+            None => return Ok(true)
+        };
+        match header_type {
+            // The archive encryption header, everything after it is encrypted
+            4 => return Err(encrypted_headers()),
+            2 => {
+                let file_flags = fields.vint()?;
+                let size = fields.vint()?;
+                fields.vint()?;
+                if file_flags & 0x0002 != 0 {
+                    fields.skip(4)?;
+                }
+                let crc = if file_flags & 0x0004 != 0 { Some(fields.u32()?) } else { None };
+                let compression = fields.vint()?;
+                fields.vint()?;
+                let name_size = fields.vint()? as usize;
+                let name = String::from_utf8_lossy(fields.bytes(name_size)?).to_string();
+
+                // The encryption record in the extra area of the file
+                let mut encrypted = false;
+                let mut extra = HeaderReader::new(&header[header.len().saturating_sub(extra_size as usize)..]);
+                while !extra.is_empty() {
+                    let record_size = extra.vint()? as usize;
+                    let record = extra.bytes(record_size)?;
+                    encrypted |= HeaderReader::new(record).vint()? == 1;
+                }
+
+                let is_directory = file_flags & 0x0001 != 0;
+                if !is_directory && flags & 0x0008 == 0 {
+                    entries.push(RarEntry {
+                        name,
+                        size,
+                        crc,
+                        encrypted,
+                        stored: (compression >> 7) & 0x07 == 0,
+                        split: flags & 0x0010 != 0,
+                        data_offset,
+                        packed_size: data_size,
+                    });
+                }
+                if data_end > length {
+                    return Ok(true);
+                }
+            }
+            5 => break,
+            _ => {}
+        }
+        position = data_end;
+    }
+    Ok(false)
+}
+
+fn get_crc(data: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+fn encrypted_headers() -> io::Error {
+    io::Error::other("The file names of the archive are encrypted, it can't be listed")
+}
+
+/// Reads the fields of a header, in little endian
+struct HeaderReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> HeaderReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.data.len()
+    }
+
+    fn bytes(&mut self, length: usize) -> io::Result<&'a [u8]> {
+        let bytes = self.data.get(self.position..self.position + length)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Header too short"))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, length: usize) -> io::Result<()> {
+        self.bytes(length).map(|_| ())
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// 7 bits in each byte, the lower ones first, the high bit is set while more bytes follow
+    fn vint(&mut self) -> io::Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::InvalidData, "Integer too long"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn reads_rar_files() -> Result<()> {
+        for version in ["rar", "rar5"] {
+            let file_path = PathBuf::from("testdata").join(version).join("game2.rar");
+            assert!(is_rar_file(&file_path));
+            assert!(!is_rar_file(&PathBuf::from("testdata").join("sevenz").join("game2.7z")));
+
+            let archive = get_entries(&file_path)?;
+            assert!(!archive.truncated);
+            let mut sizes = vec![];
+            for entry in &archive.entries {
+                let data = read_file(&file_path, entry, &[])?.unwrap();
+                sizes.push((entry.name.clone(), data.len()));
+            }
+            sizes.sort();
+            assert_eq!(sizes, vec![("binary1.bin".to_string(), 4096), ("binary2.bin".to_string(), 4096), ("binary3.bin".to_string(), 4096)]);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn fails_with_sizes_past_the_end_of_the_archive() -> Result<()> {
+        let file_path = PathBuf::from("testdata").join("rar").join("game2.rar");
+        let archive = get_entries(&file_path)?;
+        // Read as a stored file, with the size of a damaged header
+        let damaged = RarEntry { stored: true, encrypted: false, split: false, packed_size: u64::MAX, ..archive.entries[0].clone() };
+        let e = read_file(&file_path, &damaged, &[]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+
+        Ok(())
+    }
+
+    #[test]
+    fn takes_sizes_overflowing_the_offsets_as_truncated() -> Result<()> {
+        // A RAR 4 file header with the high halves of the sizes, the packed size is the largest one
+        let mut header = vec![];
+        header.extend_from_slice(&u32::MAX.to_le_bytes());
+        header.extend_from_slice(&1u32.to_le_bytes());
+        header.extend_from_slice(&[0; 1 + 4 + 4 + 1]);
+        header.push(0x30);
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&[0; 4]);
+        header.extend_from_slice(&u32::MAX.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.push(b'a');
+        let mut block = vec![0x74];
+        block.extend_from_slice(&0x8100u16.to_le_bytes());
+        block.extend_from_slice(&(7 + header.len() as u16).to_le_bytes());
+        block.extend_from_slice(&header);
+        let mut rar4 = RAR4_SIGNATURE.to_vec();
+        rar4.extend_from_slice(&(get_crc(&block) as u16).to_le_bytes());
+        rar4.extend_from_slice(&block);
+        let mut entries = vec![];
+        assert!(read_rar4_entries(&mut io::Cursor::new(&rar4), rar4.len() as u64, &mut entries)?);
+        assert!(entries.is_empty());
+
+        // A RAR 5 file header with a data size of almost 2^64
+        let mut header = vec![2, 0x02];
+        header.extend_from_slice(&[0xFA, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]);
+        header.extend_from_slice(&[0, 1, 0, 0, 0, 1, b'a']);
+        let mut block = vec![header.len() as u8];
+        block.extend_from_slice(&header);
+        let mut rar5 = RAR5_SIGNATURE.to_vec();
+        rar5.extend_from_slice(&get_crc(&block).to_le_bytes());
+        rar5.extend_from_slice(&block);
+        let mut entries = vec![];
+        assert!(read_rar5_entries(&mut io::Cursor::new(&rar5), rar5.len() as u64, &mut entries)?);
+        assert!(entries.is_empty());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn passes_the_password_through_the_input_of_unrar() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Fails if the password is in the arguments, and prints the data only with the one read from the input
+        let dir = std::env::temp_dir().join(format!("romst_unrar_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let unrar = dir.join("unrar.sh");
+        std::fs::write(&unrar, r#"#!/bin/sh
+for arg in "$@"; do
+    case "$arg" in -p?*) [ "$arg" = "-p-" ] || exit 2;; esac
+done
+read -r password
+[ "$password" = "secret" ] && printf data
+"#)?;
+        std::fs::set_permissions(&unrar, std::fs::Permissions::from_mode(0o755))?;
+        let program = unrar.to_string_lossy().to_string();
+        let entry = RarEntry { name: "a.bin".to_string(), size: 4, crc: None, encrypted: true, stored: false, split: false, data_offset: 0, packed_size: 4 };
+
+        assert_eq!(extract_with(&program, &dir.join("a.rar"), &entry, Some("secret"))?, b"data");
+        assert!(extract_with(&program, &dir.join("a.rar"), &entry, Some("wrong")).is_err());
+        assert!(extract_with(&program, &dir.join("a.rar"), &entry, None).is_err());
+        std::fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
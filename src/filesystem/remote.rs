@@ -0,0 +1,388 @@
+use std::{fs::{self, File}, path::Path, process::{Command, Stdio}};
+use anyhow::{Result, anyhow};
+use regex::Regex;
+
+/// The ssh client used to reach ssh sources, can be replaced with the `ROMST_SSH` environment variable
+const DEFAULT_SSH_COMMAND: &str = "ssh";
+/// The http client used to reach http and s3 sources, can be replaced with the `ROMST_CURL` environment variable
+const DEFAULT_CURL_COMMAND: &str = "curl";
+
+/// A directory that is not in the local file system. Its files are listed and downloaded one by one,
+/// romst doesn't have to be installed in the remote machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteSource {
+    Ssh(SshSource),
+    Http(HttpSource),
+}
+
+impl RemoteSource {
+    /// Returns `None` if the source is not a remote url
+    pub fn parse(source: &str) -> Option<Result<Self>> {
+        if let Some(ssh_source) = SshSource::parse(source) {
+            return Some(ssh_source.map(RemoteSource::Ssh));
+        }
+        HttpSource::parse(source).map(|http_source| http_source.map(RemoteSource::Http))
+    }
+
+    /// The url of a file in the remote directory, used as its path in manifests and reports.
+    /// With an empty file name it is the url of the directory.
+    pub fn get_url(&self, file_name: &str) -> String {
+        match self {
+            RemoteSource::Ssh(source) => source.get_url(file_name),
+            RemoteSource::Http(source) => source.get_url(file_name),
+        }
+    }
+
+    /// Lists the names of the files in the remote directory, subdirectories are not included
+    pub fn list_files(&self) -> Result<Vec<String>> {
+        let mut files = match self {
+            RemoteSource::Ssh(source) => source.list_files(),
+            RemoteSource::Http(source) => source.list_files(),
+        }?;
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
+    /// Streams a remote file into a local one
+    pub fn download_file(&self, file_name: &str, destination: &impl AsRef<Path>) -> Result<()> {
+        match self {
+            RemoteSource::Ssh(source) => source.download_file(file_name, destination),
+            RemoteSource::Http(source) => source.download_file(file_name, destination),
+        }
+    }
+
+    /// Downloads all the files of the remote directory into a local one, keeping their names
+    pub fn download_all(&self, directory: &impl AsRef<Path>) -> Result<()> {
+        fs::create_dir_all(directory)?;
+        for file_name in self.list_files()? {
+            self.download_file(&file_name, &directory.as_ref().join(&file_name))?;
+        }
+        Ok(())
+    }
+}
+
+/// A directory in a remote machine reachable through ssh, like `ssh://user@host:2222/roms` or `sftp://host/roms`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshSource {
+    pub destination: String,
+    pub port: Option<u16>,
+    pub directory: String,
+}
+
+impl SshSource {
+    fn parse(source: &str) -> Option<Result<Self>> {
+        let rest = source.strip_prefix("ssh://").or_else(|| source.strip_prefix("sftp://"))?;
+        Some(SshSource::parse_destination(rest).ok_or_else(|| anyhow!("Not a valid remote source: {}", source)))
+    }
+
+    fn parse_destination(rest: &str) -> Option<Self> {
+        let (authority, directory) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, ".")
+        };
+        let (destination, port) = match authority.rsplit_once(':') {
+            Some((destination, port)) => (destination, Some(port.parse::<u16>().ok()?)),
+            None => (authority, None)
+        };
+        if destination.is_empty() {
+            return None;
+        }
+
+        Some(Self { destination: destination.to_string(), port, directory: directory.to_string() })
+    }
+
+    fn get_url(&self, file_name: &str) -> String {
+        let port = self.port.map(|port| format!(":{}", port)).unwrap_or_default();
+        let url = format!("ssh://{}{}/{}", self.destination, port, self.directory.trim_matches('/'));
+        if file_name.is_empty() {
+            url
+        } else {
+            format!("{}/{}", url, file_name)
+        }
+    }
+
+    fn list_files(&self) -> Result<Vec<String>> {
+        let output = self.ssh(&format!("cd {} && find . -maxdepth 1 -type f", quote(&self.directory)))
+            .stderr(Stdio::inherit())
+            .output()?;
+        if !output.status.success() {
+            return Err(anyhow!("Couldn't list the files in {}: {}", self.get_url(""), output.status));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).lines()
+            .map(|line| line.trim_start_matches("./").to_string())
+            .filter(|line| !line.is_empty())
+            .collect())
+    }
+
+    fn download_file(&self, file_name: &str, destination: &impl AsRef<Path>) -> Result<()> {
+        let local_file = File::create(destination)?;
+        let remote_path = format!("{}/{}", self.directory.trim_end_matches('/'), file_name);
+        let status = self.ssh(&format!("cat {}", quote(&remote_path)))
+            .stdout(local_file)
+            .stderr(Stdio::inherit())
+            .status()?;
+        if !status.success() {
+            return Err(anyhow!("Couldn't download {}: {}", self.get_url(file_name), status));
+        }
+
+        Ok(())
+    }
+
+    fn ssh(&self, remote_command: &str) -> Command {
+        let program = std::env::var("ROMST_SSH").unwrap_or_else(|_| DEFAULT_SSH_COMMAND.to_string());
+        let mut command = Command::new(program);
+        if let Some(port) = self.port {
+            command.arg("-p").arg(port.to_string());
+        }
+        command.arg(&self.destination).arg(remote_command).stdin(Stdio::null());
+        command
+    }
+}
+
+/// A directory listing served over http, like the index pages of most web servers, or a public
+/// S3 bucket, given as `s3://bucket/prefix`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpSource {
+    /// The url of the directory, always ending with `/`
+    pub url: String,
+    /// For buckets, the url of the listing of the directory
+    pub listing_url: Option<String>,
+}
+
+impl HttpSource {
+    fn parse(source: &str) -> Option<Result<Self>> {
+        if let Some(rest) = source.strip_prefix("s3://") {
+            let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+            if bucket.is_empty() {
+                return Some(Err(anyhow!("Not a valid remote source: {}", source)));
+            }
+            let prefix = prefix.trim_matches('/');
+            let prefix = if prefix.is_empty() { String::new() } else { format!("{}/", prefix) };
+            let bucket_url = format!("https://{}.s3.amazonaws.com/", bucket);
+            return Some(Ok(Self {
+                url: format!("{}{}", bucket_url, encode(&prefix)),
+                listing_url: Some(format!("{}?list-type=2&delimiter=/&prefix={}", bucket_url, encode(&prefix)))
+            }));
+        }
+
+        if source.starts_with("http://") || source.starts_with("https://") {
+            let url = if source.ends_with('/') { source.to_string() } else { format!("{}/", source) };
+            return Some(Ok(Self { url, listing_url: None }));
+        }
+
+        None
+    }
+
+    fn get_url(&self, file_name: &str) -> String {
+        if file_name.is_empty() {
+            self.url.trim_end_matches('/').to_string()
+        } else {
+            format!("{}{}", self.url, encode(file_name))
+        }
+    }
+
+    fn list_files(&self) -> Result<Vec<String>> {
+        let fetch = |url: &str| {
+            let output = curl().arg(url).output()?;
+            if !output.status.success() {
+                return Err(anyhow!("Couldn't list the files in {}: {}", self.get_url(""), output.status));
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        };
+        match &self.listing_url {
+            Some(listing_url) => list_bucket(listing_url, fetch),
+            None => Ok(parse_index_page(&fetch(&self.url)?)),
+        }
+    }
+
+    fn download_file(&self, file_name: &str, destination: &impl AsRef<Path>) -> Result<()> {
+        let status = curl().arg("-o").arg(destination.as_ref()).arg(self.get_url(file_name)).status()?;
+        if !status.success() {
+            return Err(anyhow!("Couldn't download {}: {}", self.get_url(file_name), status));
+        }
+
+        Ok(())
+    }
+}
+
+fn curl() -> Command {
+    let program = std::env::var("ROMST_CURL").unwrap_or_else(|_| DEFAULT_CURL_COMMAND.to_string());
+    let mut command = Command::new(program);
+    command.args(["--fail", "--silent", "--show-error", "--location"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit());
+    command
+}
+
+/// The files of an index page are the relative links to something that is not a directory
+fn parse_index_page(page: &str) -> Vec<String> {
+    let link = Regex::new(r#"(?i)href\s*=\s*["']([^"'?#]+)["']"#).unwrap();
+    link.captures_iter(page)
+        .map(|captures| captures[1].to_string())
+        .filter(|href| !href.contains("://") && !href.starts_with('.') && !href.contains('/'))
+        .map(|href| decode(&href))
+        .collect()
+}
+
+/// The files of all the pages of a bucket listing, a page has up to 1000 keys and the token to request the next one
+fn list_bucket(listing_url: &str, mut fetch: impl FnMut(&str) -> Result<String>) -> Result<Vec<String>> {
+    let mut files = vec![];
+    let mut url = listing_url.to_string();
+    loop {
+        let listing = fetch(&url)?;
+        files.extend(parse_bucket_listing(&listing));
+        match get_continuation_token(&listing) {
+            Some(token) => url = format!("{}&continuation-token={}", listing_url, encode(&token)),
+            None => return Ok(files),
+        }
+    }
+}
+
+/// Only the keys directly under the prefix are listed, as the listing is requested with a `/` delimiter
+fn parse_bucket_listing(listing: &str) -> Vec<String> {
+    let key = Regex::new(r"<Key>([^<]+)</Key>").unwrap();
+    key.captures_iter(listing)
+        .filter_map(|captures| captures[1].rsplit('/').next().map(decode_xml))
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// The token of the next page, when the listing is truncated
+fn get_continuation_token(listing: &str) -> Option<String> {
+    if !listing.contains("<IsTruncated>true</IsTruncated>") {
+        return None;
+    }
+    let token = Regex::new(r"<NextContinuationToken>([^<]+)</NextContinuationToken>").unwrap();
+    token.captures(listing).map(|captures| decode_xml(&captures[1]))
+}
+
+/// Replaces the predefined entities of XML and the character references, the ones not valid are kept as they are
+fn decode_xml(text: &str) -> String {
+    let entity = Regex::new(r"&(lt|gt|quot|apos|amp|#[0-9]+|#[xX][0-9a-fA-F]+);").unwrap();
+    entity.replace_all(text, |captures: &regex::Captures| {
+        let character = match &captures[1] {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "amp" => Some('&'),
+            reference => match reference[1..].strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => reference[1..].parse::<u32>().ok(),
+            }.and_then(char::from_u32),
+        };
+        character.map(|character| character.to_string()).unwrap_or_else(|| captures[0].to_string())
+    }).to_string()
+}
+
+/// Quotes a path for the remote shell
+fn quote(path: &str) -> String {
+    format!("'{}'", path.replace('\'', r"'\''"))
+}
+
+/// Percent encodes the characters that are not valid in the path of an url
+fn encode(path: &str) -> String {
+    path.bytes().map(|byte| match byte {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => (byte as char).to_string(),
+        _ => format!("%{:02X}", byte)
+    }).collect()
+}
+
+fn decode(path: &str) -> String {
+    let bytes = decode_xml(path).into_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = bytes.get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], byte) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ssh_sources() -> Result<()> {
+        assert!(RemoteSource::parse("roms/").is_none());
+        let source = RemoteSource::parse("ssh://user@nas:2222/mnt/roms").unwrap()?;
+        assert_eq!(source, RemoteSource::Ssh(SshSource { destination: "user@nas".to_string(), port: Some(2222), directory: "/mnt/roms".to_string() }));
+        assert_eq!(source.get_url("game1.zip"), "ssh://user@nas:2222/mnt/roms/game1.zip");
+        assert_eq!(source.get_url(""), "ssh://user@nas:2222/mnt/roms");
+
+        let source = RemoteSource::parse("sftp://seedbox").unwrap()?;
+        assert_eq!(source.get_url("game1.zip"), "ssh://seedbox/./game1.zip");
+        assert!(RemoteSource::parse("ssh://host:port/roms").unwrap().is_err());
+        assert_eq!(quote("it's"), r"'it'\''s'");
+
+        Ok(())
+    }
+
+    #[test]
+    fn parses_http_sources() -> Result<()> {
+        let source = RemoteSource::parse("https://example.com/roms").unwrap()?;
+        assert_eq!(source.get_url("Game (USA).zip"), "https://example.com/roms/Game%20%28USA%29.zip");
+        assert_eq!(source.get_url(""), "https://example.com/roms");
+
+        let source = RemoteSource::parse("s3://my-bucket/mame/roms/").unwrap()?;
+        assert_eq!(source, RemoteSource::Http(HttpSource {
+            url: "https://my-bucket.s3.amazonaws.com/mame/roms/".to_string(),
+            listing_url: Some("https://my-bucket.s3.amazonaws.com/?list-type=2&delimiter=/&prefix=mame/roms/".to_string())
+        }));
+        assert!(RemoteSource::parse("s3://").unwrap().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parses_listings() {
+        let page = r#"<a href="../">Parent</a><a href="sub/">sub/</a><a href="Game%20(USA).zip">Game (USA).zip</a>
+            <A HREF='game2.zip'>game2.zip</A><a href="?C=N;O=D">Name</a><a href="https://example.org/other.zip">x</a>"#;
+        assert_eq!(parse_index_page(page), vec!["Game (USA).zip".to_string(), "game2.zip".to_string()]);
+
+        let listing = "<ListBucketResult><Contents><Key>mame/roms/game1.zip</Key></Contents>\
+            <Contents><Key>mame/roms/Tom &amp; Jerry.zip</Key></Contents></ListBucketResult>";
+        assert_eq!(parse_bucket_listing(listing), vec!["game1.zip".to_string(), "Tom & Jerry.zip".to_string()]);
+
+        let listing = "<Key>roms/&lt;1&gt; &quot;Tom&quot; &apos;n&apos; Jerry &#233;&#xE9; &bogus; &#xD800;.zip</Key>";
+        assert_eq!(parse_bucket_listing(listing), vec![r#"<1> "Tom" 'n' Jerry éé &bogus; &#xD800;.zip"#.to_string()]);
+    }
+
+    #[test]
+    fn lists_all_the_pages_of_a_bucket() -> Result<()> {
+        let listing_url = "https://my-bucket.s3.amazonaws.com/?list-type=2&delimiter=/&prefix=roms/";
+        let mut requested = vec![];
+        let files = list_bucket(listing_url, |url| {
+            requested.push(url.to_string());
+            Ok(match requested.len() {
+                1 => "<IsTruncated>true</IsTruncated><Contents><Key>roms/game1.zip</Key></Contents>\
+                    <NextContinuationToken>1ueGcxLPRx1Tr/XYExHnhbYLgveDs2J/wm36Hy4vbOwM=</NextContinuationToken>",
+                2 => "<IsTruncated>true</IsTruncated><Contents><Key>roms/game2.zip</Key></Contents>\
+                    <NextContinuationToken>a+b&amp;c</NextContinuationToken>",
+                _ => "<IsTruncated>false</IsTruncated><Contents><Key>roms/game3.zip</Key></Contents>",
+            }.to_string())
+        })?;
+        assert_eq!(files, vec!["game1.zip", "game2.zip", "game3.zip"]);
+        assert_eq!(requested, vec![
+            listing_url.to_string(),
+            format!("{}&continuation-token=1ueGcxLPRx1Tr/XYExHnhbYLgveDs2J/wm36Hy4vbOwM%3D", listing_url),
+            format!("{}&continuation-token=a%2Bb%26c", listing_url),
+        ]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+use std::{io::{self, BufReader, Read, Seek, SeekFrom}, path::Path};
+use sevenz_rust::{Error, Password, SevenZArchiveEntry, SevenZReader};
+
+use super::volumes::{self, MultiVolumeReader};
+
+/// Signature at the beginning of a 7z archive
+const SEVEN_Z_SIGNATURE: &[u8] = b"7z\xBC\xAF\x27\x1C";
+
+/// Checks the signature, of the first volume for split archives
+pub fn is_7z_file(file_path: &impl AsRef<Path>) -> bool {
+    let mut signature = [0u8; 6];
+    match volumes::get_volumes(file_path).first() {
+        Some(volume) => std::fs::File::open(volume).and_then(|mut file| file.read_exact(&mut signature)).is_ok() && signature == SEVEN_Z_SIGNATURE,
+        None => false
+    }
+}
+
+/// Reads the files of the archive in order, solid archives can't skip to a file. If the archive is encrypted,
+/// the passwords are tried until one works
+pub fn for_each_file<F>(file_path: &impl AsRef<Path>, passwords: &[String], mut each: F) -> Result<(), Error>
+    where F: FnMut(&SevenZArchiveEntry, &mut dyn Read) -> io::Result<()> {
+    let mut result = read_files(file_path, Password::empty(), &mut each);
+    for password in passwords {
+        match result {
+            Err(Error::PasswordRequired) | Err(Error::MaybeBadPassword(_)) => {
+                result = read_files(file_path, Password::from(password.as_str()), &mut each);
+            }
+            _ => break
+        }
+    }
+    result
+}
+
+/// The names of the files in the archive, without reading them
+pub fn get_file_names(file_path: &impl AsRef<Path>, passwords: &[String]) -> Result<Vec<String>, Error> {
+    let mut result = open(file_path, Password::empty());
+    for password in passwords {
+        match result {
+            Err(Error::PasswordRequired) | Err(Error::MaybeBadPassword(_)) => result = open(file_path, Password::from(password.as_str())),
+            _ => break
+        }
+    }
+    Ok(result?.archive().files.iter()
+        .filter(|entry| !entry.is_directory())
+        .map(|entry| entry.name().trim().to_string())
+        .collect())
+}
+
+fn open(file_path: &impl AsRef<Path>, password: Password) -> Result<SevenZReader<BufReader<MultiVolumeReader>>, Error> {
+    let mut reader = MultiVolumeReader::open(file_path)?;
+    let length = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+    SevenZReader::new(BufReader::new(reader), length, password)
+}
+
+fn read_files<F>(file_path: &impl AsRef<Path>, password: Password, each: &mut F) -> Result<(), Error>
+    where F: FnMut(&SevenZArchiveEntry, &mut dyn Read) -> io::Result<()> {
+    open(file_path, password)?.for_each_entries(|entry, data| {
+        if entry.is_directory() {
+            return Ok(true);
+        }
+        each(entry, data)?;
+        Ok(true)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn reads_7z_files() -> Result<()> {
+        let file_path = PathBuf::from("testdata").join("sevenz").join("game2.7z");
+        assert!(is_7z_file(&file_path));
+        assert!(!is_7z_file(&PathBuf::from("testdata").join("split").join("game2.zip")));
+
+        let mut sizes = vec![];
+        for_each_file(&file_path, &[], |entry, data| {
+            sizes.push((entry.name().to_string(), io::copy(data, &mut io::sink())?));
+            Ok(())
+        })?;
+        sizes.sort();
+        assert_eq!(sizes, vec![("binary1.bin".to_string(), 4096), ("binary2.bin".to_string(), 4096), ("binary3.bin".to_string(), 4096)]);
+        assert_eq!(get_file_names(&file_path, &[])?.len(), 3);
+
+        Ok(())
+    }
+}
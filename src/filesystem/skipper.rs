@@ -0,0 +1,310 @@
+use std::{borrow::Cow, cmp::Ordering, fs, io::BufRead, path::Path, str};
+
+use anyhow::{Result, anyhow};
+use log::debug;
+use quick_xml::{Reader, events::{BytesStart, Event}};
+
+/// The detectors shipped with romst, for the systems No-Intro has headerless DATs of
+const BUILTIN_SKIPPERS: [&str; 5] = [
+    include_str!("skippers/nes.xml"),
+    include_str!("skippers/fds.xml"),
+    include_str!("skippers/lynx.xml"),
+    include_str!("skippers/a7800.xml"),
+    include_str!("skippers/snes.xml"),
+];
+
+/// A header detector, in the skipper XML format of ClrMamePro and No-Intro. The first rule whose tests pass says
+/// where the data without the header is
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skipper {
+    pub name: String,
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    start_offset: usize,
+    /// Up to the end of the file if not set
+    end_offset: Option<usize>,
+    operation: Operation,
+    tests: Vec<Test>,
+}
+
+/// How the data without the header is transformed, for dumps with the bytes in another order
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operation {
+    None,
+    /// Reverses the bits of each byte
+    Bitswap,
+    /// Swaps each pair of bytes
+    Byteswap,
+    /// Reverses each group of four bytes
+    Wordswap,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Test {
+    kind: TestKind,
+    /// What the test has to give for the rule to apply
+    result: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TestKind {
+    /// The bytes at the offset
+    Data { offset: usize, value: Vec<u8> },
+    /// The bytes at the offset, each one with the mask applied, equal to the value
+    And { offset: usize, mask: Vec<u8>, value: Vec<u8> },
+    Or { offset: usize, mask: Vec<u8>, value: Vec<u8> },
+    Xor { offset: usize, mask: Vec<u8>, value: Vec<u8> },
+    /// The size of the file compared to a size, or to being a power of two if not set
+    File { size: Option<usize>, operator: Ordering },
+}
+
+impl Skipper {
+    pub fn from_path(path: &impl AsRef<Path>) -> Result<Self> {
+        let xml = fs::read_to_string(path)?;
+        Self::from_xml(&xml).map_err(|e| anyhow!("{}: {}", path.as_ref().to_string_lossy(), e))
+    }
+
+    pub fn from_xml(xml: &str) -> Result<Self> {
+        let mut reader = Reader::from_str(xml);
+        reader.trim_text(true);
+
+        let mut name = String::new();
+        let mut rules = vec![];
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event(&mut buf)? {
+                Event::Start(ref e) if e.name() == b"name" => name = reader.read_text(b"name", &mut vec![])?.trim().to_string(),
+                Event::Start(ref e) if e.name() == b"rule" => rules.push(read_rule(&mut reader, e, false)?),
+                Event::Empty(ref e) if e.name() == b"rule" => rules.push(read_rule(&mut reader, e, true)?),
+                Event::Eof => break,
+                _ => (),
+            }
+            buf.clear();
+        }
+
+        if rules.is_empty() {
+            return Err(anyhow!("The skipper `{}` has no rules", name));
+        }
+        Ok(Self { name, rules })
+    }
+
+    /// The data without the header, if the skipper finds one
+    pub fn strip<'a>(&self, data: &'a [u8]) -> Option<Cow<'a, [u8]>> {
+        let rule = self.rules.iter().find(|rule| rule.applies(data))?;
+        let end = rule.end_offset.unwrap_or(data.len());
+        let headerless = &data[rule.start_offset..end];
+        Some(match rule.operation {
+            Operation::None => Cow::Borrowed(headerless),
+            Operation::Bitswap => Cow::Owned(headerless.iter().map(|byte| byte.reverse_bits()).collect()),
+            Operation::Byteswap => Cow::Owned(swap_chunks(headerless, 2)),
+            Operation::Wordswap => Cow::Owned(swap_chunks(headerless, 4)),
+        })
+    }
+}
+
+impl Rule {
+    fn applies(&self, data: &[u8]) -> bool {
+        let end = self.end_offset.unwrap_or(data.len());
+        self.start_offset < end && end <= data.len() && self.tests.iter().all(|test| test.passes(data) == test.result)
+    }
+}
+
+impl Test {
+    fn passes(&self, data: &[u8]) -> bool {
+        match &self.kind {
+            TestKind::Data { offset, value } => data.get(*offset..offset + value.len()) == Some(value.as_slice()),
+            TestKind::And { offset, mask, value } => passes_mask(data, *offset, mask, value, |byte, mask| byte & mask),
+            TestKind::Or { offset, mask, value } => passes_mask(data, *offset, mask, value, |byte, mask| byte | mask),
+            TestKind::Xor { offset, mask, value } => passes_mask(data, *offset, mask, value, |byte, mask| byte ^ mask),
+            TestKind::File { size: Some(size), operator } => data.len().cmp(size) == *operator,
+            TestKind::File { size: None, operator } => {
+                let is_power_of_two = data.len().is_power_of_two();
+                match operator {
+                    Ordering::Equal => is_power_of_two,
+                    _ => !is_power_of_two,
+                }
+            }
+        }
+    }
+}
+
+fn passes_mask<F>(data: &[u8], offset: usize, mask: &[u8], value: &[u8], operation: F) -> bool where F: Fn(u8, u8) -> u8 {
+    match data.get(offset..offset + value.len()) {
+        Some(bytes) => bytes.iter().zip(mask).map(|(byte, mask)| operation(*byte, *mask)).eq(value.iter().copied()),
+        None => false
+    }
+}
+
+/// Reverses the bytes of each chunk, a last chunk not complete is left as it is
+fn swap_chunks(data: &[u8], size: usize) -> Vec<u8> {
+    let mut swapped = data.to_vec();
+    for chunk in swapped.chunks_exact_mut(size) {
+        chunk.reverse();
+    }
+    swapped
+}
+
+fn read_rule<B: BufRead>(reader: &mut Reader<B>, start: &BytesStart, is_empty: bool) -> Result<Rule> {
+    let mut rule = Rule { start_offset: 0, end_offset: None, operation: Operation::None, tests: vec![] };
+    for (key, value) in get_attributes(start)? {
+        match key.as_str() {
+            "start_offset" => rule.start_offset = parse_offset(&value)?,
+            "end_offset" if value.eq_ignore_ascii_case("eof") => rule.end_offset = None,
+            "end_offset" => rule.end_offset = Some(parse_offset(&value)?),
+            "operation" => rule.operation = match value.to_lowercase().as_str() {
+                "none" => Operation::None,
+                "bitswap" => Operation::Bitswap,
+                "byteswap" => Operation::Byteswap,
+                "wordswap" => Operation::Wordswap,
+                operation => return Err(anyhow!("Unknown operation `{}`", operation)),
+            },
+            _ => debug!("Unknown rule attribute: {}", key),
+        }
+    }
+    if is_empty {
+        return Ok(rule);
+    }
+
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Empty(ref e) | Event::Start(ref e) => rule.tests.push(read_test(e)?),
+            Event::End(ref e) if e.name() == b"rule" => return Ok(rule),
+            Event::Eof => return Err(anyhow!("Unexpected end of the skipper in a rule")),
+            _ => (),
+        }
+        buf.clear();
+    }
+}
+
+fn read_test(element: &BytesStart) -> Result<Test> {
+    let attributes = get_attributes(element)?;
+    let get = |key: &str| attributes.iter().find(|(k, _)| k == key).map(|(_, value)| value.as_str());
+    let offset = get("offset").map(parse_offset).transpose()?.unwrap_or(0);
+    let value = get("value").map(parse_hex).transpose()?.unwrap_or_default();
+    let mask = get("mask").map(parse_hex).transpose()?.unwrap_or_default();
+    let result = get("result").map(|result| !result.eq_ignore_ascii_case("false")).unwrap_or(true);
+
+    let kind = match element.name() {
+        b"data" => TestKind::Data { offset, value },
+        b"and" => TestKind::And { offset, mask, value },
+        b"or" => TestKind::Or { offset, mask, value },
+        b"xor" => TestKind::Xor { offset, mask, value },
+        b"file" => {
+            let size = match get("size") {
+                Some(size) if size.eq_ignore_ascii_case("po2") => None,
+                Some(size) => Some(parse_offset(size)?),
+                None => return Err(anyhow!("The file test needs a size")),
+            };
+            let operator = match get("operator").unwrap_or("equal").to_lowercase().as_str() {
+                "equal" => Ordering::Equal,
+                "less" => Ordering::Less,
+                "greater" => Ordering::Greater,
+                operator => return Err(anyhow!("Unknown operator `{}`", operator)),
+            };
+            TestKind::File { size, operator }
+        },
+        name => return Err(anyhow!("Unknown test `{}`", String::from_utf8_lossy(name))),
+    };
+    Ok(Test { kind, result })
+}
+
+fn get_attributes(element: &BytesStart) -> Result<Vec<(String, String)>> {
+    element.attributes().map(|attribute| {
+        let attribute = attribute?;
+        Ok((str::from_utf8(attribute.key)?.trim().to_lowercase(), str::from_utf8(&attribute.value)?.trim().to_string()))
+    }).collect()
+}
+
+/// The offsets and sizes are in hexadecimal
+fn parse_offset(value: &str) -> Result<usize> {
+    usize::from_str_radix(value, 16).map_err(|e| anyhow!("`{}` is not a valid offset: {}", value, e))
+}
+
+fn parse_hex(value: &str) -> Result<Vec<u8>> {
+    if !value.len().is_multiple_of(2) || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!("`{}` is not a valid hex value", value));
+    }
+    (0..value.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16).map_err(|e| anyhow!("`{}` is not a valid hex value: {}", value, e)))
+        .collect()
+}
+
+/// The skippers shipped with romst: NES, FDS, Lynx, Atari 7800 and SNES copier headers
+pub fn get_builtin_skippers() -> Vec<Skipper> {
+    BUILTIN_SKIPPERS.iter().map(|xml| Skipper::from_xml(xml).expect("The builtin skippers are valid")).collect()
+}
+
+/// Every skipper in the XML files of a directory
+pub fn load_skippers(dir: &impl AsRef<Path>) -> Result<Vec<Skipper>> {
+    let mut paths = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("xml")))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths.iter().map(Skipper::from_path).collect()
+}
+
+/// The data without the header found by the first skipper that finds one
+pub fn strip_header<'a>(skippers: &[Skipper], data: &'a [u8]) -> Option<Cow<'a, [u8]>> {
+    skippers.iter().find_map(|skipper| skipper.strip(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_nes_rom() -> Vec<u8> {
+        let mut data = b"NES\x1A\x02\x01".to_vec();
+        data.resize(16, 0);
+        data.extend((0..32u8).collect::<Vec<_>>());
+        data
+    }
+
+    #[test]
+    fn strips_the_headers_with_the_builtin_skippers() -> Result<()> {
+        let skippers = get_builtin_skippers();
+        assert_eq!(5, skippers.len());
+        let nes = &skippers[0];
+        assert_eq!("Nintendo Famicom/NES", nes.name);
+
+        let data = get_nes_rom();
+        assert_eq!(Some(&data[16..]), nes.strip(&data).as_deref());
+        assert!(nes.strip(&data[16..]).is_none());
+        assert!(skippers[1..].iter().all(|skipper| skipper.strip(&data).is_none()));
+
+        // The copier header is only there when the size is not a power of two
+        let snes = &skippers[4];
+        let mut data = vec![0u8; 0x200];
+        data[8..11].copy_from_slice(&[0xAA, 0xBB, 0x04]);
+        data.extend(vec![0xFFu8; 0x400]);
+        assert_eq!(Some(0x400), snes.strip(&data).map(|headerless| headerless.len()));
+        data.truncate(0x400);
+        assert!(snes.strip(&data).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn reads_the_tests_and_operations() -> Result<()> {
+        let skipper = Skipper::from_xml(r#"<?xml version="1.0"?>
+<detector>
+    <name>Swapped</name>
+    <rule start_offset="2" end_offset="6" operation="byteswap">
+        <and offset="0" mask="F0" value="A0"/>
+        <file size="8" operator="greater" result="false"/>
+    </rule>
+</detector>"#)?;
+        assert_eq!(Some(&[2u8, 1, 4, 3][..]), skipper.strip(&[0xA5, 0, 1, 2, 3, 4, 5, 6]).as_deref());
+        assert!(skipper.strip(&[0xB5, 0, 1, 2, 3, 4, 5, 6]).is_none());
+        assert!(skipper.strip(&[0xA5, 0, 1, 2, 3, 4, 5, 6, 7]).is_none());
+
+        assert!(Skipper::from_xml("<detector><name>Empty</name></detector>").is_err());
+        assert!(Skipper::from_xml(r#"<detector><rule start_offset="10"><data offset="0" value="4E4"/></rule></detector>"#).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,271 @@
+use std::{cmp::Ordering, convert::TryFrom, io::{self, Write}, ptr};
+
+use libz_sys::{Bytef, uInt, voidpf, z_stream};
+
+/// The comment of the archives, followed by the crc of the central directory
+pub const COMMENT_PREFIX: &str = "TORRENTZIPPED-";
+/// Every file has the same date, 1996-12-24 23:32:00 in MS-DOS format
+const DOS_TIME: u16 = 0xBC00;
+const DOS_DATE: u16 = 0x2198;
+const VERSION_NEEDED: u16 = 20;
+/// Maximum compression
+const FLAGS: u16 = 0x0002;
+/// The name is encoded in UTF-8
+const FLAG_UTF8: u16 = 0x0800;
+const METHOD_DEFLATED: u16 = 8;
+/// The deflate settings of TorrentZip: raw deflate at level 9, with a 32 KB window and the memory level 8
+const DEFLATE_LEVEL: i32 = 9;
+const DEFLATE_WINDOW_BITS: i32 = -15;
+const DEFLATE_MEM_LEVEL: i32 = 8;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+
+/// The order of the files in the archives, by their names with only the ASCII letters in lowercase, like TorrentZip
+pub fn compare_names(a: &str, b: &str) -> Ordering {
+    a.to_ascii_lowercase().cmp(&b.to_ascii_lowercase()).then_with(|| a.cmp(b))
+}
+
+/// A file written, for the central directory
+struct WrittenFile {
+    name: String,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    offset: u32,
+}
+
+impl WrittenFile {
+    fn get_flags(&self) -> u16 {
+        if self.name.is_ascii() { FLAGS } else { FLAGS | FLAG_UTF8 }
+    }
+}
+
+/// Writes zip archives in the TorrentZip format: the files sorted by name, compressed with zlib at the settings of
+/// TorrentZip, with a fixed date and no extra fields, and the crc of the central directory in the comment. The same
+/// files give the same archive as the other tools writing TorrentZip.
+/// The archives are limited to 4 GB and 65535 files, there is no zip64
+pub struct TorrentZipWriter<W: Write> {
+    writer: W,
+    files: Vec<WrittenFile>,
+    offset: u64,
+}
+
+impl<W: Write> TorrentZipWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer, files: vec![], offset: 0 }
+    }
+
+    /// Adds a file, in the order of `compare_names` after the ones already added
+    pub fn add_file(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        if let Some(last) = self.files.last() {
+            if compare_names(&last.name, name) != Ordering::Less {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("`{}` is not sorted after `{}`", name, last.name)));
+            }
+        }
+        let compressed = deflate(data)?;
+
+        let file = WrittenFile {
+            name: name.to_string(),
+            crc: get_crc(data),
+            compressed_size: to_u32(compressed.len())?,
+            size: to_u32(data.len())?,
+            offset: to_u32(self.offset)?,
+        };
+        let mut header = vec![];
+        header.extend_from_slice(&LOCAL_HEADER_SIGNATURE.to_le_bytes());
+        header.extend_from_slice(&VERSION_NEEDED.to_le_bytes());
+        header.extend_from_slice(&file.get_flags().to_le_bytes());
+        header.extend_from_slice(&METHOD_DEFLATED.to_le_bytes());
+        header.extend_from_slice(&DOS_TIME.to_le_bytes());
+        header.extend_from_slice(&DOS_DATE.to_le_bytes());
+        header.extend_from_slice(&file.crc.to_le_bytes());
+        header.extend_from_slice(&file.compressed_size.to_le_bytes());
+        header.extend_from_slice(&file.size.to_le_bytes());
+        header.extend_from_slice(&to_u16(name.len())?.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(name.as_bytes());
+
+        self.writer.write_all(&header)?;
+        self.writer.write_all(&compressed)?;
+        self.offset += (header.len() + compressed.len()) as u64;
+        self.files.push(file);
+
+        Ok(())
+    }
+
+    /// Writes the central directory, returns the writer
+    pub fn finish(mut self) -> io::Result<W> {
+        let mut directory = vec![];
+        for file in &self.files {
+            directory.extend_from_slice(&CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+            // Made by MS-DOS, without file attributes
+            directory.extend_from_slice(&0u16.to_le_bytes());
+            directory.extend_from_slice(&VERSION_NEEDED.to_le_bytes());
+            directory.extend_from_slice(&file.get_flags().to_le_bytes());
+            directory.extend_from_slice(&METHOD_DEFLATED.to_le_bytes());
+            directory.extend_from_slice(&DOS_TIME.to_le_bytes());
+            directory.extend_from_slice(&DOS_DATE.to_le_bytes());
+            directory.extend_from_slice(&file.crc.to_le_bytes());
+            directory.extend_from_slice(&file.compressed_size.to_le_bytes());
+            directory.extend_from_slice(&file.size.to_le_bytes());
+            directory.extend_from_slice(&to_u16(file.name.len())?.to_le_bytes());
+            // Extra field, comment, disk, internal and external attributes
+            directory.extend_from_slice(&[0u8; 12]);
+            directory.extend_from_slice(&file.offset.to_le_bytes());
+            directory.extend_from_slice(file.name.as_bytes());
+        }
+        let comment = format!("{}{:08X}", COMMENT_PREFIX, get_crc(&directory));
+        let files = to_u16(self.files.len())?;
+
+        let mut end = vec![];
+        end.extend_from_slice(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        end.extend_from_slice(&[0u8; 4]);
+        end.extend_from_slice(&files.to_le_bytes());
+        end.extend_from_slice(&files.to_le_bytes());
+        end.extend_from_slice(&to_u32(directory.len())?.to_le_bytes());
+        end.extend_from_slice(&to_u32(self.offset)?.to_le_bytes());
+        end.extend_from_slice(&to_u16(comment.len())?.to_le_bytes());
+        end.extend_from_slice(comment.as_bytes());
+
+        self.writer.write_all(&directory)?;
+        self.writer.write_all(&end)?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Compresses with zlib and the deflate settings of TorrentZip, the same output as the other tools writing it. flate2
+/// always initializes zlib with the memory level 9, which splits the blocks in other places
+fn deflate(data: &[u8]) -> io::Result<Vec<u8>> {
+    unsafe extern "C" fn zalloc(_opaque: voidpf, items: uInt, size: uInt) -> voidpf {
+        libc::calloc(items as usize, size as usize)
+    }
+    unsafe extern "C" fn zfree(_opaque: voidpf, address: voidpf) {
+        libc::free(address)
+    }
+
+    let mut stream = z_stream {
+        next_in: data.as_ptr() as *mut Bytef,
+        avail_in: to_u32(data.len())?,
+        total_in: 0,
+        next_out: ptr::null_mut(),
+        avail_out: 0,
+        total_out: 0,
+        msg: ptr::null_mut(),
+        state: ptr::null_mut(),
+        zalloc,
+        zfree,
+        opaque: ptr::null_mut(),
+        data_type: 0,
+        adler: 0,
+        reserved: 0,
+    };
+    let zlib_error = |call: &str, code: i32| io::Error::other(format!("zlib {} failed with the code {}", call, code));
+    // zlib only reads the input, and writes up to `avail_out` bytes of the output buffer
+    unsafe {
+        let code = libz_sys::deflateInit2_(&mut stream, DEFLATE_LEVEL, libz_sys::Z_DEFLATED, DEFLATE_WINDOW_BITS, DEFLATE_MEM_LEVEL,
+            libz_sys::Z_DEFAULT_STRATEGY, libz_sys::zlibVersion(), std::mem::size_of::<z_stream>() as i32);
+        if code != libz_sys::Z_OK {
+            return Err(zlib_error("deflateInit2", code));
+        }
+        let mut compressed = vec![0u8; libz_sys::deflateBound(&mut stream, data.len() as _) as usize];
+        stream.next_out = compressed.as_mut_ptr();
+        // Past 4 GB zlib stops before the end, and fails below
+        stream.avail_out = uInt::try_from(compressed.len()).unwrap_or(uInt::MAX);
+        let code = libz_sys::deflate(&mut stream, libz_sys::Z_FINISH);
+        let written = stream.total_out as usize;
+        libz_sys::deflateEnd(&mut stream);
+        if code != libz_sys::Z_STREAM_END {
+            return Err(zlib_error("deflate", code));
+        }
+        compressed.truncate(written);
+        Ok(compressed)
+    }
+}
+
+fn get_crc(data: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+fn to_u32<T>(value: T) -> io::Result<u32> where u32: TryFrom<T> {
+    u32::try_from(value).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "The archive is too big for TorrentZip without zip64"))
+}
+
+fn to_u16<T>(value: T) -> io::Result<u16> where u16: TryFrom<T> {
+    u16::try_from(value).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Too many files or a name too long for TorrentZip"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+    use zip::ZipArchive;
+    use super::*;
+
+    fn write_archive(files: &[(&str, &[u8])]) -> io::Result<Vec<u8>> {
+        let mut writer = TorrentZipWriter::new(vec![]);
+        for (name, data) in files {
+            writer.add_file(name, data)?;
+        }
+        writer.finish()
+    }
+
+    #[test]
+    fn writes_the_torrentzip_format() -> anyhow::Result<()> {
+        let files: [(&str, &[u8]); 3] = [("a.bin", b"first rom"), ("B.bin", b"second rom"), ("c.bin", b"")];
+        let archive = write_archive(&files)?;
+        assert_eq!(archive, write_archive(&files)?);
+
+        let mut zip = ZipArchive::new(Cursor::new(archive.clone()))?;
+        assert_eq!(zip.file_names().count(), 3);
+        for (i, (name, data)) in files.iter().enumerate() {
+            let mut file = zip.by_index(i)?;
+            assert_eq!(file.name(), *name);
+            assert_eq!(file.compression(), zip::CompressionMethod::Deflated);
+            let mut content = vec![];
+            file.read_to_end(&mut content)?;
+            assert_eq!(&content, data);
+        }
+
+        // The comment has the crc of the central directory, found from the end of central directory record
+        let end = archive.len() - 22 - 22;
+        let read_u32 = |at: usize| u32::from_le_bytes([archive[at], archive[at + 1], archive[at + 2], archive[at + 3]]) as usize;
+        let (directory_size, directory_offset) = (read_u32(end + 12), read_u32(end + 16));
+        assert_eq!(directory_offset + directory_size, end);
+        let comment = String::from_utf8(archive[end + 22..].to_vec())?;
+        assert_eq!(comment, format!("{}{:08X}", COMMENT_PREFIX, get_crc(&archive[directory_offset..end])));
+
+        let mut writer = TorrentZipWriter::new(vec![]);
+        writer.add_file("b.bin", b"")?;
+        assert!(writer.add_file("A.bin", b"").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn writes_the_same_bytes_as_torrentzip() -> anyhow::Result<()> {
+        // Letters from a linear congruential generator, the blocks of zlib depend on its memory level with them
+        let mut seed = 12345u32;
+        let data = (0..100_000).map(|_| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345) & 0x7fffffff;
+            b"abcdefgh"[(seed >> 16) as usize % 8]
+        }).collect::<Vec<_>>();
+        let files: [(&str, &[u8]); 3] = [("data.bin", &data), ("empty.bin", b""), ("Readme.txt", b"TorrentZip reference\n")];
+        let reference = std::fs::read(std::path::Path::new("testdata").join("torrentzip").join("reference.zip"))?;
+        assert_eq!(write_archive(&files)?, reference);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sorts_the_names_by_their_ascii_lowercase() {
+        assert_eq!(compare_names("Readme.txt", "data.bin"), Ordering::Greater);
+        assert_eq!(compare_names("B.bin", "b.bin"), Ordering::Less);
+        // Only the ASCII letters are in lowercase, `É` is still sorted before `é`
+        assert_eq!(compare_names("Éb.bin", "éa.bin"), Ordering::Less);
+    }
+}
@@ -0,0 +1,145 @@
+use std::{fs::File, io::{self, Read, Seek, SeekFrom}, path::{Path, PathBuf}};
+
+/// Archives split in several files, like `game.7z.001`, `game.7z.002`... have a numeric extension
+pub fn get_volume_number(file_path: &impl AsRef<Path>) -> Option<u32> {
+    let extension = file_path.as_ref().extension()?.to_str()?;
+    if extension.len() == 3 && extension.chars().all(|c| c.is_ascii_digit()) {
+        extension.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Only the first volume of a split archive is read, the rest are read along with it
+pub fn is_secondary_volume(file_path: &impl AsRef<Path>) -> bool {
+    get_volume_number(file_path).is_some_and(|number| number > 1)
+}
+
+/// The path of the whole archive, without the volume extension
+pub fn get_archive_path(file_path: &impl AsRef<Path>) -> PathBuf {
+    let file_path = file_path.as_ref();
+    match get_volume_number(&file_path) {
+        Some(_) => file_path.with_extension(""),
+        None => file_path.to_path_buf()
+    }
+}
+
+/// All the volumes of a split archive, in order, from the first one. Files that are not volumes are a single volume.
+pub fn get_volumes(file_path: &impl AsRef<Path>) -> Vec<PathBuf> {
+    let file_path = file_path.as_ref();
+    if get_volume_number(&file_path) != Some(1) {
+        return vec![file_path.to_path_buf()];
+    }
+
+    let archive_path = get_archive_path(&file_path);
+    let archive_name = archive_path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    (1..).map(|number| archive_path.with_file_name(format!("{}.{:03}", archive_name, number)))
+        .take_while(|volume| volume.is_file())
+        .collect()
+}
+
+/// Reads the volumes of a split archive as if they were a single file
+pub struct MultiVolumeReader {
+    volumes: Vec<File>,
+    /// Where each volume starts in the whole archive
+    offsets: Vec<u64>,
+    length: u64,
+    position: u64,
+}
+
+impl MultiVolumeReader {
+    pub fn open(file_path: &impl AsRef<Path>) -> io::Result<Self> {
+        let mut volumes = vec![];
+        let mut offsets = vec![];
+        let mut length = 0;
+        for volume_path in get_volumes(file_path) {
+            let volume = File::open(volume_path)?;
+            offsets.push(length);
+            length += volume.metadata()?.len();
+            volumes.push(volume);
+        }
+        if volumes.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "No volumes found"));
+        }
+
+        Ok(Self { volumes, offsets, length, position: 0 })
+    }
+
+    /// The last volume starting before the position, so empty volumes are skipped
+    fn current_volume(&self) -> usize {
+        self.offsets.partition_point(|offset| *offset <= self.position) - 1
+    }
+}
+
+impl Read for MultiVolumeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.length {
+            return Ok(0);
+        }
+
+        // A read never goes past the end of the current volume
+        let index = self.current_volume();
+        let volume = &mut self.volumes[index];
+        volume.seek(SeekFrom::Start(self.position - self.offsets[index]))?;
+        let read = volume.read(buf)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for MultiVolumeReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.length.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(io::Error::new(io::ErrorKind::InvalidInput, "Seek before the start of the archive"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identifies_volumes() {
+        assert_eq!(get_volume_number(&"game.7z.001"), Some(1));
+        assert_eq!(get_volume_number(&"game.zip"), None);
+        assert!(is_secondary_volume(&"game.zip.002"));
+        assert!(!is_secondary_volume(&"game.zip.001"));
+        assert_eq!(get_archive_path(&"roms/game.7z.001"), PathBuf::from("roms/game.7z"));
+        assert_eq!(get_archive_path(&"roms/game.7z"), PathBuf::from("roms/game.7z"));
+    }
+
+    #[test]
+    fn reads_volumes_as_a_single_file() -> io::Result<()> {
+        let dir = std::env::temp_dir().join(format!("romst_volumes_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("data.bin.001"), b"0123")?;
+        std::fs::write(dir.join("data.bin.002"), b"")?;
+        std::fs::write(dir.join("data.bin.003"), b"456789")?;
+
+        let mut reader = MultiVolumeReader::open(&dir.join("data.bin.001"))?;
+        assert_eq!(reader.seek(SeekFrom::End(0))?, 10);
+        reader.seek(SeekFrom::Start(0))?;
+        let mut content = String::new();
+        reader.read_to_string(&mut content)?;
+        assert_eq!(content, "0123456789");
+
+        reader.seek(SeekFrom::End(-7))?;
+        let mut buffer = [0u8; 4];
+        reader.read_exact(&mut buffer)?;
+        assert_eq!(&buffer, b"3456");
+        assert!(reader.seek(SeekFrom::Current(-20)).is_err());
+        std::fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
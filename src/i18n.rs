@@ -0,0 +1,327 @@
+use std::{str::FromStr, sync::RwLock};
+use anyhow::{Result, anyhow};
+
+static LANGUAGE: RwLock<Language> = RwLock::new(Language::English);
+
+/// The language of the labels and messages shown to the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    #[default]
+    English,
+    Spanish,
+}
+
+impl FromStr for Language {
+    type Err = anyhow::Error;
+
+    /// Takes a language code or a locale, like `es` or `es_ES.UTF-8`
+    fn from_str(s: &str) -> Result<Self> {
+        let code = s.split(['_', '-', '.']).next().unwrap_or_default().to_lowercase();
+        match code.as_str() {
+            "en" | "c" | "posix" => Ok(Language::English),
+            "es" => Ok(Language::Spanish),
+            _ => Err(anyhow!("Non valid language, can be either `en` or `es`"))
+        }
+    }
+}
+
+impl Language {
+    /// The language set in `ROMST_LANG`, or the one of the locale, English if none is supported
+    pub fn from_env() -> Self {
+        ["ROMST_LANG", "LC_ALL", "LC_MESSAGES", "LANG"].iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default()
+    }
+
+    pub fn current() -> Self {
+        *LANGUAGE.read().unwrap()
+    }
+
+    pub fn set_current(language: Language) {
+        *LANGUAGE.write().unwrap() = language;
+    }
+
+    pub fn translate(&self, text: &'static str) -> &'static str {
+        let catalog = match self {
+            Language::English => return text,
+            Language::Spanish => SPANISH,
+        };
+        catalog.iter().find(|(english, _)| *english == text).map(|(_, translated)| *translated).unwrap_or(text)
+    }
+}
+
+/// Translates a text to the current language, the text stays in English if it has no translation
+pub fn tr(text: &'static str) -> &'static str {
+    Language::current().translate(text)
+}
+
+const SPANISH: &[(&str, &str)] = &[
+    // Messages
+    ("ERROR", "ERROR"),
+    ("SUCCESS", "OK"),
+    ("WARNING", "AVISO"),
+    // Report header
+    ("Scanned dir", "Directorio revisado"),
+    ("Date of the report", "Fecha del informe"),
+    ("Mode", "Modo"),
+    ("Match policy", "Política de coincidencia"),
+    ("Scan level", "Nivel de revisión"),
+    ("Layout", "Organización"),
+    // Set status
+    ("Complete", "Completo"),
+    ("Fixable", "Reparable"),
+    ("Incomplete", "Incompleto"),
+    ("Missing", "Falta"),
+    ("Sets", "Sets"),
+    ("Not in the database", "No están en la base de datos"),
+    ("Converted", "Convertidas"),
+    ("Converted images", "Imágenes convertidas"),
+    ("Encrypted", "Cifrados"),
+    ("Encrypted, couldn't verify", "Cifrados, no se pudieron verificar"),
+    ("Corrupt", "Dañados"),
+    ("Corrupt archives", "Archivos dañados"),
+    ("Suspect", "Sospechosos"),
+    ("Suspect (near matches)", "Sospechosos (casi coinciden)"),
+    ("Ignored", "Ignorados"),
+    ("Identified", "Identificados"),
+    ("Archives to rename", "Archivos a renombrar"),
+    ("should be renamed to", "debería renombrarse a"),
+    ("only differs in spelling from", "solo se escribe distinto que"),
+    ("unknown files", "ficheros desconocidos"),
+    // Sets
+    ("Set", "Set"),
+    ("Status", "Estado"),
+    ("in file", "en fichero"),
+    ("File name", "Nombre del fichero"),
+    ("Depends on", "Depende de"),
+    ("Roms Available", "Roms disponibles"),
+    ("Roms Unneeded (e.g. Bad Dumps)", "Roms innecesarias (p.ej. malos volcados)"),
+    ("Roms Missing", "Roms que faltan"),
+    ("Roms to Spare", "Roms sobrantes"),
+    ("used by sets", "usada en sets"),
+    ("rename from", "renombrar desde"),
+    ("located at", "ubicada en"),
+    ("weak match", "coincidencia débil"),
+    ("not hashed", "sin checksum"),
+    ("Verified by", "Verificado por"),
+    ("name and size", "nombre y tamaño"),
+    ("CRC and size", "CRC y tamaño"),
+    ("Have", "Tiene"),
+    ("Rename", "Renombrar"),
+    ("Elsewhere", "En otro sitio"),
+    ("Spare", "Sobran"),
+    ("Unknown", "Desconocidas"),
+    // Info
+    ("Database info", "Información de la base de datos"),
+    ("Roms", "Roms"),
+    ("Disks", "Discos"),
+    ("Samples", "Samples"),
+    ("Devices", "Dispositivos"),
+    ("Clones", "Clones"),
+    ("Releases", "Lanzamientos"),
+    ("Matching roms", "Roms que coinciden"),
+    ("Used by", "Usada por"),
+    ("Last verification", "Última verificación"),
+    ("Partial", "Parciales"),
+    ("complete", "completo"),
+    ("Unknown files", "Ficheros desconocidos"),
+    // Disks
+    ("COMPLETE", "COMPLETO"),
+    ("INCOMPLETE", "INCOMPLETO"),
+    ("OK", "OK"),
+    ("MISSING", "FALTA"),
+    ("WRONG CHECKSUM", "CHECKSUM ERRÓNEO"),
+    ("PARENT MISSING", "FALTA EL PADRE"),
+    ("NOT VALID", "NO VÁLIDO"),
+    ("NO DUMP", "SIN VOLCADO"),
+    ("REGRESSION", "REGRESIÓN"),
+    // Families and fix plan
+    ("Families", "Familias"),
+    ("Only clones, parent missing", "Solo clones, falta el padre"),
+    ("Fix plan", "Plan de reparación"),
+    ("Completes", "Completa"),
+    // Verify all
+    ("Summary", "Resumen"),
+    ("Total", "Total"),
+    // What if
+    ("Would be complete", "Se completarían"),
+    ("Would miss less roms", "Les faltarían menos roms"),
+    ("Missing roms covered", "Roms que faltan cubiertas"),
+    // DAT update
+    ("Added", "Añadidos"),
+    ("Updated", "Actualizados"),
+    ("Removed", "Eliminados"),
+    ("Unchanged", "Sin cambios"),
+    // Diff
+    ("Renamed", "Renombrados"),
+    ("Roms changed", "Roms cambiadas"),
+    // Pages
+    ("Page", "Página"),
+    // Set lists
+    ("Sets found", "Sets encontrados"),
+    ("Excluded from their families", "Excluidos de sus familias"),
+    ("Excluded sets", "Sets excluidos"),
+    ("No excluded sets", "No hay sets excluidos"),
+    ("Tags", "Etiquetas"),
+    ("No tagged games", "No hay juegos etiquetados"),
+    // Listings
+    ("Dats", "Dats"),
+    ("Games", "Juegos"),
+    ("Roms in Games", "Roms en juegos"),
+    ("Device References", "Referencias a dispositivos"),
+    ("rows", "filas"),
+    ("games", "juegos"),
+    ("roms", "roms"),
+    ("games found", "juegos encontrados"),
+    ("roms found", "roms encontradas"),
+    ("sample sets found", "sets de samples encontrados"),
+    ("No games found", "No se encontraron juegos"),
+    ("No roms found", "No se encontraron roms"),
+    ("No roms found for", "No se encontraron roms para"),
+    ("No sample sets found", "No se encontraron sets de samples"),
+    ("clone of", "clon de"),
+    ("All the roms are used by some game", "Todas las roms las usa algún juego"),
+    ("roms not used by any game removed", "roms que no usa ningún juego eliminadas"),
+    ("roms not used by any game, use --prune to remove them", "roms que no usa ningún juego, usa --prune para eliminarlas"),
+    ("No DATs registered, the database contains a single DAT", "No hay DATs registrados, la base de datos contiene un único DAT"),
+    ("No game aliases", "No hay alias de juegos"),
+    ("previous", "anterior"),
+    ("No directories with a profile found", "No se encontraron directorios con perfil"),
+    ("Directory", "Directorio"),
+    ("profile", "perfil"),
+    ("no directories checked", "ningún directorio revisado"),
+    ("directories", "directorios"),
+    // Games and sets
+    ("Year", "Año"),
+    ("Manufacturer", "Fabricante"),
+    ("Source File", "Fichero fuente"),
+    ("Clone of", "Clon de"),
+    ("ROM of", "ROM de"),
+    ("Sample of", "Samples de"),
+    ("Note", "Nota"),
+    ("Device", "Dispositivo"),
+    ("Mechanical", "Mecánico"),
+    ("Not runnable", "No ejecutable"),
+    ("Driver", "Driver"),
+    ("Software list", "Lista de software"),
+    ("language", "idioma"),
+    ("date", "fecha"),
+    ("Archive per set", "Un archivo por set"),
+    ("Folder per set", "Una carpeta por set"),
+    ("bios", "bios"),
+    ("optional", "opcional"),
+    // Scan options
+    ("Full", "Completo"),
+    ("Trust (matched by name and size, not hashed)", "Confiar (coincide por nombre y tamaño, sin checksum)"),
+    ("Any checksum", "Cualquier checksum"),
+    ("Two of SHA1, MD5 and CRC", "Dos de SHA1, MD5 y CRC"),
+    ("same crc and size, different sha1", "mismo crc y tamaño, distinto sha1"),
+    ("same name, different checksums", "mismo nombre, distintos checksums"),
+    ("converted image (cannot verify as-is)", "imagen convertida (no se puede verificar tal cual)"),
+    // DATs
+    ("imported", "importado"),
+    ("with romst", "con romst"),
+    ("has", "tiene"),
+    ("the same DAT", "el mismo DAT"),
+    ("the same version", "la misma versión"),
+    ("an older version", "una versión anterior"),
+    // History
+    ("Date", "Fecha"),
+    ("partial", "parcial"),
+    ("missing", "falta"),
+    ("since", "desde"),
+    ("No reports in the history", "No hay informes en el historial"),
+    ("No games removed or renamed", "No hay juegos eliminados o renombrados"),
+    ("renamed to", "renombrado a"),
+    ("removed", "eliminado"),
+    ("last in", "último en"),
+    ("on", "el"),
+    // Disks, families and identify
+    ("clone CHD present but parent CHD missing", "está el CHD del clon pero falta el CHD del padre"),
+    ("parent sha1", "sha1 del padre"),
+    ("not a valid CHD", "no es un CHD válido"),
+    ("The set has no disks", "El set no tiene discos"),
+    ("There are no sets with clones", "No hay sets con clones"),
+    ("clones", "clones"),
+    ("a rom of", "una rom de"),
+    ("by its header", "por su cabecera"),
+    ("by its extension", "por su extensión"),
+    ("probably belongs to", "probablemente pertenece a"),
+    ("in", "en"),
+    // Fix plan and what if
+    ("copy", "copiar"),
+    ("move", "mover"),
+    ("rename", "renombrar"),
+    ("rename archive", "renombrar archivo"),
+    ("to", "a"),
+    ("from", "desde"),
+    ("into", "en"),
+    ("as", "como"),
+    ("actions", "acciones"),
+    ("sets completed", "sets completados"),
+    ("There is nothing to fix with the files scanned", "No hay nada que reparar con los ficheros revisados"),
+    ("of", "de"),
+    ("still missing", "aún faltan"),
+    // Rebuild
+    ("Copy", "Copiar"),
+    ("Move", "Mover"),
+    ("Standard", "Estándar"),
+    ("Always", "Siempre"),
+    ("If available", "Si están disponibles"),
+    ("Never", "Nunca"),
+    ("Sets that would be built", "Sets que se construirían"),
+    ("Sets built", "Sets construidos"),
+    ("Failed", "Fallidos"),
+    ("Bytes of roms written", "Bytes de roms escritos"),
+    ("Optional roms", "Roms opcionales"),
+    ("BIOS roms", "Roms de BIOS"),
+    ("Sets verified", "Sets verificados"),
+    ("Files left empty removed", "Ficheros vacíos eliminados"),
+    ("Files removed sent to", "Ficheros eliminados enviados a"),
+    ("Deleted", "Borrados"),
+    ("Trash of the system", "Papelera del sistema"),
+    // Command results
+    ("actions written to", "acciones escritas en"),
+    ("sets have", "sets completos"),
+    ("missing of", "faltan de"),
+    ("report saved", "informe guardado"),
+    ("files saved in the manifest", "ficheros guardados en el manifiesto"),
+    ("checksum files written", "ficheros de checksums escritos"),
+    ("sets excluded", "sets excluidos"),
+    ("sets removed from the exclusions", "sets quitados de las exclusiones"),
+    ("games tagged as", "juegos etiquetados como"),
+    ("tag", "etiqueta"),
+    ("sets written to", "sets escritos en"),
+    ("note removed", "nota eliminada"),
+    ("note saved", "nota guardada"),
+    ("No note for", "No hay nota para"),
+    ("Database updated", "Base de datos actualizada"),
+    ("The database is up to date", "La base de datos está al día"),
+    ("aliases added", "alias añadidos"),
+    ("sets exported to", "sets exportados a"),
+    ("games removed", "juegos eliminados"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translates_with_the_catalog() {
+        assert_eq!(Language::Spanish.translate("Roms Missing"), "Roms que faltan");
+        assert_eq!(Language::English.translate("Roms Missing"), "Roms Missing");
+        assert_eq!(Language::Spanish.translate("Not translated"), "Not translated");
+        assert_eq!("es_ES.UTF-8".parse::<Language>().unwrap(), Language::Spanish);
+        assert_eq!("C".parse::<Language>().unwrap(), Language::English);
+        assert!("fr_FR".parse::<Language>().is_err());
+    }
+
+    #[test]
+    fn has_a_single_translation_per_text() {
+        for (index, (english, _)) in SPANISH.iter().enumerate() {
+            assert!(!SPANISH[index + 1..].iter().any(|(other, _)| other == english), "{} is translated twice", english);
+        }
+    }
+}
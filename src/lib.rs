@@ -1,18 +1,47 @@
 mod data;
+mod doctor;
 mod error;
 mod filesystem;
 mod macros;
+mod notify;
+pub mod i18n;
 pub mod sysout;
 
 use console::Style;
-use data::{importer::{DatImporter, DatImporterReporter}, models::set::GameSet, reader::{DataReader, RomSearch, SetDependencies, sqlite::{DBReader, DBReport}}, reporter::{ReportReporter, Reporter, scan_report::ScanReport}, writer::sqlite::DBWriter};
-use log::{info, error};
+use data::{exporter::{self, Exporter}, rebuilder::{self, Rebuilder}, importer::{self, AnyDatImporter, DatImporterReporter}, models::{dat::{DatInfo, ImportSource}, disk::GameDisk, history::{self, DatContents, GameAlias, GameChange, GamesSnapshot, ReportHistoryEntry, SourceProgress}, file::{DataFile, DataFileInfo, FileType}, game::Game, profile, release::Release, set::GameSet}, reader::{DataReader, DbDataEntry, RomNameMatch, RomSearch, SetDependencies, sqlite::{DBReader, DBReport, QueryRows}}, reporter::{ReportReporter, Reporter, Scanner, disk_report::DiskReport, identify, family_report::FamilyReport, fix_plan::{FixPlan, ScriptShell}, csv_report, dat_diff::DatDiff, fixdat, have_miss::HaveMissLists, manifest::ScanManifest, scan_report::{GroupedScanReport, ScanReport, SetReport, SetStatus}, what_if::WhatIfReport}, writer::{DataWriter, dat::DatWriter, manifest::ManifestWriter, sqlite::{DBWriter, ImportLog}, update::DBUpdater}};
+use error::RomstIOError;
+use i18n::tr;
+use filesystem::{FileReader, ReadOptions, atomic::{self, AtomicFile, StagingFile}, checksum_file, lock::DBLock, remote::RemoteSource, skipper};
+use log::{info, error, warn};
+use rayon::prelude::*;
 use rusqlite::{Connection, OpenFlags};
-use std::{fmt::Display, fs::File, io::Write, path::Path, str::FromStr};
+use std::{collections::{BTreeMap, HashSet}, fmt::Display, ops::{Deref, DerefMut}, path::{Path, PathBuf}, str::FromStr, time::Duration};
 use serde::{Deserialize, Serialize};
 use anyhow::{Result, anyhow};
 
+pub use data::models::dat::{DuplicateDat, DuplicateKind};
+pub use data::models::edit::{GameEdit, RomEdit};
+pub use data::models::set::SetLayout;
+pub use data::rebuilder::{DeletePolicy, RebuildMode, RebuildReport, RomPolicies, RomPolicy, ZipFormat};
+pub use data::writer::update::DatUpdate;
+pub use data::models::profile::{ScanProfile, ScanProfiles};
+pub use data::models::release::RegionPriorities;
+pub use data::models::search::{GameFilter, PatternKind, ReleaseFilter, RomFilter};
+pub use data::models::set_list::{SetList, SetListAction, SetListImport, SetListSource};
+pub use data::reader::MatchPolicy;
+pub use data::reporter::{ReportScope, ScanLevel};
+pub use data::reporter::identify::{Identification, IdentificationClue};
+pub use data::reporter::scan_report::{ReportView, Verbosity};
+pub use doctor::{DiagnosisStatus, DoctorReport};
+pub use notify::{DbChange, DbChangeKind};
+pub use filesystem::checksum_file::ChecksumFormat;
+pub use filesystem::names::NameRules;
+
 pub const DEFAULT_WRITE_BUFFER_SIZE: u16 = 5000;
+/// The imports are saved at least this often, an interrupted one goes on from its last save
+const IMPORT_AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+/// The files bigger than this aren't hashed to look for them in other databases
+const MAX_IDENTIFIED_FILE_SIZE: u64 = 256 * 1024 * 1024;
 
 #[derive(Debug, Serialize, Deserialize, Copy, Clone)]
 pub enum RomsetMode {
@@ -56,10 +85,98 @@ impl FromStr for RomsetMode {
     }
 }
 
+enum ReportSource<P: AsRef<Path>> {
+    Files(Vec<P>),
+    Manifest(ScanManifest)
+}
+
 pub struct Romst {
 
 }
 
+/// How the databases are written. Each call gets its own, so the writes running at the same time can use different ones
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    wait_for_lock: bool,
+}
+
+impl WriteOptions {
+    /// When another process is writing the database, wait for it to finish instead of failing
+    pub fn set_wait_for_lock(&mut self, wait: bool) {
+        self.wait_for_lock = wait;
+    }
+}
+
+/// How the files are read and matched in the checks, scans and rebuilds. Each call gets its own, so the checks
+/// running at the same time can use different ones
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    read: ReadOptions,
+    match_policy: MatchPolicy,
+    scan_level: ScanLevel,
+    layout: SetLayout,
+    name_rules: NameRules,
+}
+
+impl ScanOptions {
+    /// Passwords to try when an archive has encrypted files, the files that can't be read with any are reported as encrypted
+    pub fn set_archive_passwords(&mut self, passwords: Vec<String>) {
+        self.read.passwords = passwords;
+    }
+
+    /// Reads what can be read from corrupt archives, instead of reporting just the archive as corrupt
+    pub fn set_salvage_archives(&mut self, salvage: bool) {
+        self.read.salvage = salvage;
+    }
+
+    /// Which checksums have to agree with the database for a file to match a rom
+    pub fn set_match_policy(&mut self, policy: MatchPolicy) {
+        self.match_policy = policy;
+    }
+
+    /// With the trust level, checks match files by name and size without hashing them
+    pub fn set_scan_level(&mut self, level: ScanLevel) {
+        self.scan_level = level;
+    }
+
+    /// With the folder layout, checks read the directories named like a set as that set, and rebuilds write a folder
+    /// per set instead of an archive
+    pub fn set_layout(&mut self, layout: SetLayout) {
+        self.layout = layout;
+    }
+
+    /// The differences ignored when comparing the names of the files with the names of the sets, like the accents or
+    /// the underscores, so the archives named almost like a set are told apart from the ones with another name
+    pub fn set_name_rules(&mut self, rules: NameRules) {
+        self.name_rules = rules;
+    }
+
+    /// Finds the headers of the files, like the iNES one, to match them with the DATs of headerless dumps. Uses the
+    /// skippers shipped with romst and the ones in the XML files of the directory, returns the names of all of them
+    pub fn set_header_skippers(&mut self, skippers_dir: Option<String>) -> Result<Vec<String>> {
+        let mut skippers = skipper::get_builtin_skippers();
+        if let Some(skippers_dir) = skippers_dir {
+            skippers.extend(skipper::load_skippers(&skippers_dir)?);
+        }
+        let names = skippers.iter().map(|skipper| skipper.name.to_owned()).collect::<Vec<_>>();
+        info!("Header skippers: {}", names.join(", "));
+        self.read.skippers = skippers;
+        Ok(names)
+    }
+
+    /// These options with the match policy and the header skippers of a profile, for the directories using it
+    pub fn with_profile(&self, profile: &ScanProfile) -> Result<ScanOptions> {
+        let mut options = self.clone();
+        if let Some(match_policy) = profile.get_match_policy()? {
+            options.set_match_policy(match_policy);
+        }
+        if profile.skippers.is_some() {
+            options.set_header_skippers(profile.get_skippers_dir().map(str::to_string))?;
+        }
+        Ok(options)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GameSetsInfo {
     pub game_sets: Vec<GameSet>
@@ -79,137 +196,2087 @@ impl Display for GameSetsInfo {
     }
 }
 
-impl Romst {
-    fn get_rw_connection<S>(db_file: S) -> Result<Connection> where S: AsRef<str>{
-        let db_path = Path::new(db_file.as_ref());
-        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
-        Ok(conn)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GameInfo {
+    pub game: Game,
+    pub rom_mode: RomsetMode,
+    pub roms: Vec<DataFile>,
+    pub disks: Vec<GameDisk>,
+    pub samples: Vec<String>,
+    pub device_refs: Vec<String>,
+    pub clones: Vec<String>,
+    #[serde(default)]
+    pub releases: Vec<Release>,
+    pub tags: Vec<String>,
+    pub note: Option<String>,
+}
+
+impl Display for GameInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let title = match &self.game.info_description {
+            Some(description) => format!("[{}] {}", self.game.name, description),
+            None => format!("[{}]", self.game.name)
+        };
+        writeln!(f, "{}", Style::new().bold().green().apply_to(title))?;
+        if let Some(year) = &self.game.info_year {
+            writeln!(f, "{}: {}", tr("Year"), year)?;
+        }
+        if let Some(manufacturer) = &self.game.info_manufacturer {
+            writeln!(f, "{}: {}", tr("Manufacturer"), manufacturer)?;
+        }
+        if let Some(source_file) = &self.game.source_file {
+            writeln!(f, "{}: {}", tr("Source File"), source_file)?;
+        }
+        if let Some(clone_of) = &self.game.clone_of {
+            writeln!(f, "{}: {}", tr("Clone of"), clone_of)?;
+        }
+        if let Some(rom_of) = &self.game.rom_of {
+            writeln!(f, "{}: {}", tr("ROM of"), rom_of)?;
+        }
+        if let Some(sample_of) = &self.game.sample_of {
+            writeln!(f, "{}: {}", tr("Sample of"), sample_of)?;
+        }
+        if !self.releases.is_empty() {
+            writeln!(f, "{}:", tr("Releases"))?;
+            for release in &self.releases {
+                writeln!(f, "    - {}", release)?;
+            }
+        }
+        if !self.tags.is_empty() {
+            writeln!(f, "{}: {}", tr("Tags"), self.tags.join(", "))?;
+        }
+        if let Some(note) = &self.note {
+            writeln!(f, "{}: {}", tr("Note"), note)?;
+        }
+        writeln!(f, "{} ({}):", Style::new().cyan().apply_to(tr("Roms")), self.rom_mode)?;
+        for rom in &self.roms {
+            writeln!(f, "    - {}", rom)?;
+        }
+        if !self.disks.is_empty() {
+            writeln!(f, "{}:", Style::new().cyan().apply_to(tr("Disks")))?;
+            for disk in &self.disks {
+                writeln!(f, "    - {}", disk)?;
+            }
+        }
+        if !self.samples.is_empty() {
+            writeln!(f, "{}:", Style::new().cyan().apply_to(tr("Samples")))?;
+            for sample in &self.samples {
+                writeln!(f, "    - {}", sample)?;
+            }
+        }
+        if !self.device_refs.is_empty() {
+            writeln!(f, "{}:", Style::new().cyan().apply_to(tr("Devices")))?;
+            for device_ref in &self.device_refs {
+                writeln!(f, "    - {}", device_ref)?;
+            }
+        }
+        if !self.clones.is_empty() {
+            writeln!(f, "{}:", Style::new().cyan().apply_to(tr("Clones")))?;
+            for clone in &self.clones {
+                writeln!(f, "    - {}", clone)?;
+            }
+        }
+        Ok(())
     }
+}
 
-    fn get_r_connection<S>(db_file: S) -> Result<Connection> where S: AsRef<str> {
-        let db_path = Path::new(db_file.as_ref());
-        if !db_path.exists() {
-            return Err(anyhow!("No Database found at `{}`", db_file.as_ref()));
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RomSetLocations {
+    pub rom_mode: RomsetMode,
+    pub sets: BTreeMap<String, Vec<String>>
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RomLocations {
+    pub searched: DataFileInfo,
+    pub roms: Vec<DataFileInfo>,
+    pub locations: Vec<RomSetLocations>
+}
+
+impl Display for RomLocations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.roms.is_empty() {
+            return writeln!(f, "{} {}", tr("No roms found for"), self.searched);
         }
-        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
-        Ok(conn)
+        writeln!(f, "{}:", Style::new().cyan().apply_to(tr("Matching roms")))?;
+        for rom in &self.roms {
+            writeln!(f, "    - {}", rom)?;
+        }
+        for location in &self.locations {
+            writeln!(f, "{}:", Style::new().cyan().apply_to(location.rom_mode))?;
+            for (set_name, rom_names) in &location.sets {
+                writeln!(f, "    {}: {}", Style::new().bold().apply_to(set_name), rom_names.join(", "))?;
+            }
+        }
+        Ok(())
     }
+}
 
-    pub fn get_data_reader(conn: &Connection) -> Result<DBReader> {
-        Ok(DBReader::from_connection(conn))
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GameList {
+    pub games: Vec<Game>
+}
+
+impl GameList {
+    pub fn new(games: Vec<Game>) -> Self { Self { games } }
+}
+
+impl Display for GameList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.games.is_empty() {
+            return writeln!(f, "{}", tr("No games found"));
+        }
+        let name_width = self.games.iter().map(|game| game.name.len()).max().unwrap_or_default();
+        for game in &self.games {
+            let parent = match &game.clone_of {
+                Some(parent) => format!(" ({} {})", tr("clone of"), parent),
+                None => "".to_string()
+            };
+            writeln!(f, "{:width$}  {:4}  {:20}  {}{}",
+                Style::new().bold().apply_to(&game.name),
+                game.info_year.as_deref().unwrap_or("????"),
+                game.info_manufacturer.as_deref().unwrap_or(""),
+                game.info_description.as_deref().unwrap_or(""),
+                parent,
+                width = name_width)?;
+        };
+        writeln!(f, "{} {}", self.games.len(), tr("games found"))
     }
+}
 
-    pub fn get_data_writer(conn: &mut Connection) -> Result<DBWriter> {
-        Ok(DBWriter::from_connection(conn, 500))
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RomList {
+    pub roms: Vec<RomNameMatch>
+}
+
+impl Display for RomList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.roms.is_empty() {
+            return writeln!(f, "{}", tr("No roms found"));
+        }
+        for found in &self.roms {
+            writeln!(f, "{}", Style::new().bold().apply_to(&found.rom))?;
+            writeln!(f, "    {}: {}", tr("Used by"), found.sets.join(", "))?;
+        }
+        writeln!(f, "{} {}", self.roms.len(), tr("roms found"))
     }
+}
 
-    pub fn import_dat<R, S>(input: S, output_file: S, overwrite: bool, reporter: Option<R>) -> Result<()> where R: DatImporterReporter + 'static, S: AsRef<str> {
-        println!("Loading file: {}", Style::new().bold().apply_to(input.as_ref()));
-        println!("Output: {}", Style::new().bold().apply_to(output_file.as_ref()));
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UnreferencedRoms {
+    pub roms: Vec<DbDataEntry<DataFileInfo>>,
+    /// The roms were removed from the database
+    pub pruned: bool,
+}
 
-        let db_path = Path::new(output_file.as_ref());
-        if !overwrite && db_path.exists() {
-            return Err(anyhow!("Destination file `{}` already exists, choose another output or rename the file.", output_file.as_ref()));
+impl Display for UnreferencedRoms {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.roms.is_empty() {
+            return writeln!(f, "{}", tr("All the roms are used by some game"));
+        }
+        for rom in &self.roms {
+            writeln!(f, "{}", rom)?;
+        }
+        if self.pruned {
+            writeln!(f, "{} {} {}", Style::new().green().apply_to(tr("SUCCESS")), self.roms.len(), tr("roms not used by any game removed"))
+        } else {
+            writeln!(f, "{} {}", self.roms.len(), tr("roms not used by any game, use --prune to remove them"))
         }
+    }
+}
 
-        let mut conn = Romst::get_rw_connection(output_file)?;
-        let db_writer = DBWriter::from_connection(&mut conn, DEFAULT_WRITE_BUFFER_SIZE);
-        let mut dat_importer = DatImporter::from_path(&input.as_ref().to_string(), db_writer)?;
-        if let Some(r) = reporter {
-            dat_importer.set_reporter(r);
+/// The sample sets with the number of games using each one
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SampleSets {
+    pub sample_sets: Vec<(String, u32)>
+}
+
+impl Display for SampleSets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.sample_sets.is_empty() {
+            return writeln!(f, "{}", tr("No sample sets found"));
+        }
+        let name_width = self.sample_sets.iter().map(|(name, _)| name.len()).max().unwrap_or_default();
+        for (name, games) in &self.sample_sets {
+            writeln!(f, "{:width$}  {} {}", Style::new().bold().apply_to(name), games, tr("games"), width = name_width)?;
         }
+        writeln!(f, "{} {}", self.sample_sets.len(), tr("sample sets found"))
+    }
+}
 
-        match dat_importer.load_dat() {
-            Ok(_) => info!("Parsing complete"),
-            Err(e) => error!("Error parsing file: {}", e)
-        };
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Exclusions {
+    pub game_names: Vec<String>
+}
+
+impl Exclusions {
+    pub fn new(game_names: Vec<String>) -> Self { Self { game_names } }
+}
 
+impl Display for Exclusions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.game_names.is_empty() {
+            return writeln!(f, "{}", tr("No excluded sets"));
+        }
+        writeln!(f, "{}:", tr("Excluded sets"))?;
+        for game_name in &self.game_names {
+            writeln!(f, "- {}", game_name)?;
+        };
         Ok(())
     }
+}
 
-    pub fn get_set_info<S>(db_file: S, game_names: Vec<S>, rom_mode: RomsetMode) -> Result<GameSetsInfo> where S: AsRef<str> {
-        let mut games =  vec![];
-        let conn = Romst::get_r_connection(db_file)?;
-        let reader = Romst::get_data_reader(&conn)?;
-        for game_name in game_names {
-            let roms = reader.get_romset_roms(game_name.as_ref(), rom_mode)?.1.into_iter().map(|db_rom| {
-                db_rom.file
-            }).collect();
-            let device_refs = reader.get_devices_for_game(game_name.as_ref())?;
-            match reader.get_game(game_name.as_ref()) {
-                Some(game) => {
-                    games.push(GameSet::new(game, roms, vec![], vec![], device_refs.dependencies));
-                }
-                None => {
-                    error!("Game {} not found", game_name.as_ref())
-                }
-            }
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GameTags {
+    pub games: BTreeMap<String, Vec<String>>
+}
+
+impl GameTags {
+    pub fn new(games: BTreeMap<String, Vec<String>>) -> Self { Self { games } }
+}
+
+impl Display for GameTags {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.games.is_empty() {
+            return writeln!(f, "{}", tr("No tagged games"));
         }
+        for (game_name, tags) in &self.games {
+            writeln!(f, "{}: {}", Style::new().bold().apply_to(game_name), tags.join(", "))?;
+        };
+        Ok(())
+    }
+}
 
-        Ok(GameSetsInfo::new(games))
+/// Connection to write a database, holds the lock until dropped
+struct WriteConnection {
+    conn: Connection,
+    _lock: DBLock,
+}
+
+impl Deref for WriteConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
     }
+}
 
-    pub fn get_rom_usage<S>(db_file: S, game_name: S, rom_name: S, rom_mode: RomsetMode) -> Result<RomSearch> where S: AsRef<str> {
-        let conn = Romst::get_r_connection(db_file)?;
-        let reader = Romst::get_data_reader(&conn)?;
-        reader.find_rom_usage(game_name.as_ref(), rom_name.as_ref(), rom_mode)
+impl DerefMut for WriteConnection {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.conn
     }
+}
 
-    pub fn get_romset_usage<S>(db_file: S, game_name: S, rom_mode: RomsetMode) -> Result<RomSearch> where S: AsRef<str> {
-        let conn = Romst::get_r_connection(db_file)?;
-        let reader = Romst::get_data_reader(&conn)?;
-        reader.get_romset_shared_roms(game_name.as_ref(), rom_mode)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DatList {
+    pub dats: Vec<DatInfo>
+}
+
+impl DatList {
+    pub fn new(dats: Vec<DatInfo>) -> Self { Self { dats } }
+}
+
+impl Display for DatList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.dats.is_empty() {
+            return writeln!(f, "{}", tr("No DATs registered, the database contains a single DAT"));
+        }
+        for dat in &self.dats {
+            writeln!(f, "{}", dat)?;
+        };
+        Ok(())
     }
+}
 
-    pub fn get_romset_dependencies<S>(db_file: S, game_name: S, rom_mode: RomsetMode) -> Result<SetDependencies> where S: AsRef<str> {
-        let conn = Romst::get_r_connection(db_file)?;
-        let reader = Romst::get_data_reader(&conn)?;
-        let mut result = reader.get_devices_for_game(game_name.as_ref())?;
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportHistory {
+    pub entries: Vec<ReportHistoryEntry>
+}
 
-        // If we are in split mode, we add the parent as a dependency
-        match rom_mode {
-            RomsetMode::Split => {
-                if let Some(game) = reader.get_game(game_name.as_ref()) {
-                    if let Some(clone_of) = game.clone_of {
-                        result.dependencies.push(clone_of);
-                    }
-                }
-            }
-            _ => {}
+impl ReportHistory {
+    pub fn new(entries: Vec<ReportHistoryEntry>) -> Self { Self { entries } }
+}
+
+impl Display for ReportHistory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.entries.is_empty() {
+            return writeln!(f, "{}", tr("No reports in the history"));
         }
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        };
+        Ok(())
+    }
+}
 
-        Ok(result)
+/// The completeness over time of the sources checked against a database
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DatProgress {
+    pub db_file: String,
+    pub dat_name: String,
+    pub sources: Vec<SourceProgress>,
+}
+
+impl Display for DatProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} ({})", Style::new().bold().apply_to(&self.dat_name), self.db_file)?;
+        if self.sources.is_empty() {
+            return writeln!(f, "{}", tr("No reports in the history"));
+        }
+        for source in &self.sources {
+            write!(f, "{}", source)?;
+        };
+        Ok(())
     }
+}
 
-    pub fn get_db_info<S>(db_file: S) -> Result<DBReport> where S: AsRef<str>{
-        let conn = Romst::get_r_connection(db_file)?;
-        let reader = Romst::get_data_reader(&conn)?;
-        reader.get_stats()
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProgressReport {
+    pub dats: Vec<DatProgress>
+}
+
+impl ProgressReport {
+    pub fn new(dats: Vec<DatProgress>) -> Self { Self { dats } }
+}
+
+impl Display for ProgressReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, dat) in self.dats.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", dat)?;
+        };
+        Ok(())
     }
+}
 
-    pub fn get_report<R, S>(db_file: S, file_paths: Vec<impl AsRef<Path>>, rom_mode: RomsetMode, progress_reporter: Option<R>) -> Result<ScanReport> where R: ReportReporter + 'static, S: AsRef<str> {
-        let conn = Romst::get_r_connection(db_file)?;
-        let reader = Romst::get_data_reader(&conn)?;
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GameChanges {
+    pub changes: Vec<GameChange>
+}
 
-        let mut reporter = Reporter::new(reader);
-        if let Some(progress_reporter) = progress_reporter {
-            reporter.add_reporter(progress_reporter);
+impl GameChanges {
+    pub fn new(changes: Vec<GameChange>) -> Self { Self { changes } }
+}
+
+impl Display for GameChanges {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.changes.is_empty() {
+            return writeln!(f, "{}", tr("No games removed or renamed"));
         }
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        };
+        Ok(())
+    }
+}
 
-        let report = tokio::runtime::Builder::new_multi_thread()
-            .enable_all()
-            .build()
-            .unwrap()
-            .block_on(async { 
-                reporter.check(file_paths, rom_mode).await
-             });
-        report
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GameAliases {
+    pub aliases: Vec<GameAlias>
+}
+
+impl GameAliases {
+    pub fn new(aliases: Vec<GameAlias>) -> Self { Self { aliases } }
+}
+
+impl Display for GameAliases {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.aliases.is_empty() {
+            return writeln!(f, "{}", tr("No game aliases"));
+        }
+        for alias in &self.aliases {
+            writeln!(f, "{}", alias)?;
+        };
+        Ok(())
+    }
+}
+
+/// A report stored in the history, along with the previous one from the same source
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportHistoryRecord {
+    pub entry: ReportHistoryEntry,
+    pub previous: Option<ReportHistoryEntry>,
+}
+
+impl ReportHistoryRecord {
+    pub fn is_regression(&self) -> bool {
+        self.previous.as_ref().is_some_and(|previous| self.entry.is_regression_from(previous))
+    }
+}
+
+impl Display for ReportHistoryRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.entry)?;
+        if let Some(previous) = &self.previous {
+            let status = if self.is_regression() {
+                Style::new().red().apply_to(tr("REGRESSION"))
+            } else {
+                Style::new().green().apply_to(tr("OK"))
+            };
+            writeln!(f, "{} {}: {}", status, tr("previous"), previous)?;
+        }
+        Ok(())
+    }
+}
+
+/// The report of a directory, checked with the profile of its subtree
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileReport {
+    pub directory: String,
+    pub profile: String,
+    pub report: GroupedScanReport,
+}
+
+/// A directory that couldn't be checked, the rest of them are checked anyway
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FailedDirectory {
+    pub directory: String,
+    pub profile: String,
+    pub error: String,
+}
+
+/// The set counts of all the directories checked with a database
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SystemSummary {
+    pub db: String,
+    pub directories: Vec<String>,
+    pub complete: usize,
+    pub fixable: usize,
+    pub incomplete: usize,
+    pub missing: usize,
+    pub not_in_db: usize,
+}
+
+impl SystemSummary {
+    fn add(&mut self, directory: &str, report: &GroupedScanReport) {
+        self.directories.push(directory.to_string());
+        self.complete += report.complete.len();
+        self.fixable += report.fixable.len();
+        self.incomplete += report.incomplete.len();
+        self.missing += report.missing.len();
+        self.not_in_db += report.not_in_db.len();
+    }
+}
+
+impl Display for SystemSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}, {}: {}, {}: {}, {}: {}, {}: {}",
+            tr("Complete"), self.complete, tr("Fixable"), self.fixable, tr("Incomplete"), self.incomplete,
+            tr("Missing"), self.missing, tr("Not in the database"), self.not_in_db)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileReports {
+    pub reports: Vec<ProfileReport>,
+    #[serde(default)]
+    pub failed: Vec<FailedDirectory>,
+    /// The counts of each database, including the ones without any directory
+    #[serde(default)]
+    pub systems: Vec<SystemSummary>,
+}
+
+impl ProfileReports {
+    fn new(reports: Vec<ProfileReport>, failed: Vec<FailedDirectory>, profiles: &ScanProfiles) -> Result<Self> {
+        let mut systems = profiles.get_databases()?.into_iter()
+            .map(|db| (db.clone(), SystemSummary { db, ..SystemSummary::default() }))
+            .collect::<BTreeMap<_, _>>();
+        for profile_report in &reports {
+            if let Some(profile) = profiles.profiles.get(&profile_report.profile) {
+                systems.entry(profile.db.clone())
+                    .or_insert_with(|| SystemSummary { db: profile.db.clone(), ..SystemSummary::default() })
+                    .add(&profile_report.directory, &profile_report.report);
+            }
+        }
+
+        Ok(Self { reports, failed, systems: systems.into_values().collect() })
+    }
+
+    pub fn get_total(&self) -> SystemSummary {
+        let mut total = SystemSummary::default();
+        for system in &self.systems {
+            total.directories.extend(system.directories.iter().cloned());
+            total.complete += system.complete;
+            total.fixable += system.fixable;
+            total.incomplete += system.incomplete;
+            total.missing += system.missing;
+            total.not_in_db += system.not_in_db;
+        }
+        total
+    }
+}
+
+impl Display for ProfileReports {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.reports.is_empty() && self.failed.is_empty() {
+            return writeln!(f, "{}", tr("No directories with a profile found"));
+        }
+        for profile_report in &self.reports {
+            writeln!(f, "{} {} ({}: {})", Style::new().bold().apply_to(format!("{}:", tr("Directory"))), profile_report.directory, tr("profile"), profile_report.profile)?;
+            writeln!(f, "{}", profile_report.report)?;
+        }
+        for failed in &self.failed {
+            writeln!(f, "{} {} ({}: {}): {}", Style::new().red().apply_to(tr("ERROR")), failed.directory, tr("profile"), failed.profile, failed.error)?;
+        }
+
+        writeln!(f, "{}", Style::new().bold().apply_to(format!("== {} ==", tr("Summary"))))?;
+        for system in &self.systems {
+            if system.directories.is_empty() {
+                writeln!(f, "{}: {}", system.db, tr("no directories checked"))?;
+            } else {
+                writeln!(f, "{} ({} {}): {}", system.db, system.directories.len(), tr("directories"), system)?;
+            }
+        }
+        writeln!(f, "{}: {}", Style::new().bold().apply_to(tr("Total")), self.get_total())
+    }
+}
+
+impl Romst {
+    fn get_rw_connection<S>(db_file: S, options: &WriteOptions) -> Result<WriteConnection> where S: AsRef<str>{
+        let db_path = Path::new(db_file.as_ref());
+        let lock = DBLock::acquire(&db_path, options.wait_for_lock)?;
+        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+        Ok(WriteConnection { conn, _lock: lock })
+    }
+
+    fn get_r_connection<S>(db_file: S) -> Result<Connection> where S: AsRef<str> {
+        let db_path = Path::new(db_file.as_ref());
+        if !db_path.exists() {
+            return Err(anyhow!("No Database found at `{}`", db_file.as_ref()));
+        }
+        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        Ok(conn)
+    }
+
+    fn get_w_connection<S>(db_file: S, options: &WriteOptions) -> Result<WriteConnection> where S: AsRef<str> {
+        let db_path = Path::new(db_file.as_ref());
+        if !db_path.exists() {
+            return Err(anyhow!("No Database found at `{}`", db_file.as_ref()));
+        }
+        let lock = DBLock::acquire(&db_path, options.wait_for_lock)?;
+        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+        Ok(WriteConnection { conn, _lock: lock })
+    }
+
+    /// How many files are hashed at the same time, 0 hashes one for each core
+    pub fn set_hashing_threads(threads: usize) {
+        filesystem::hashing::set_hashing_threads(threads);
+    }
+
+    /// Receives the changes made to any database by this process: imports, purges, merges, user data...
+    /// For views of a database that have to be refreshed when it changes
+    pub fn subscribe_changes() -> std::sync::mpsc::Receiver<DbChange> {
+        notify::subscribe()
+    }
+
+    pub fn get_data_reader(conn: &Connection) -> Result<DBReader> {
+        Ok(DBReader::from_connection(conn))
+    }
+
+    pub fn get_data_writer(conn: &mut Connection) -> Result<DBWriter> {
+        Ok(DBWriter::from_connection(conn, 500))
+    }
+
+    /// Imports a DAT into a new database. With a `log_file`, everything written is logged there, including the rows that failed
+    pub fn import_dat<R, S>(input: S, output_file: S, overwrite: bool, log_file: Option<S>, options: &WriteOptions, reporter: Option<R>) -> Result<()> where R: DatImporterReporter + 'static, S: AsRef<str> {
+        println!("Loading file: {}", Style::new().bold().apply_to(input.as_ref()));
+        println!("Output: {}", Style::new().bold().apply_to(output_file.as_ref()));
+
+        let db_path = Path::new(output_file.as_ref());
+        if !overwrite && db_path.exists() {
+            return Err(anyhow!("Destination file `{}` already exists, choose another output or rename the file.", output_file.as_ref()));
+        }
+
+        let source_path = std::fs::canonicalize(input.as_ref()).map(|path| path.to_string_lossy().to_string()).unwrap_or_else(|_| input.as_ref().to_string());
+        let source_sha1 = FileReader::new().get_file_sha1(&input.as_ref()).map_err(|e| warn!("Can't get the sha1 of {}: {}", input.as_ref(), e)).ok();
+
+        // The DAT is imported in a staging copy of the database, that replaces it once complete. The copy left by an
+        // interrupted import of the same DAT goes on from its last write, otherwise it starts again from the database
+        let _lock = DBLock::acquire(&db_path, options.wait_for_lock)?;
+        let staging = StagingFile::new(&db_path);
+        let resume = staging.exists() && Connection::open_with_flags(staging.staging_path(), OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(anyhow::Error::from)
+            .and_then(|conn| DBReader::from_connection(&conn).get_import_progress())
+            .map_err(|e| warn!("Can't read the interrupted import: {}", e)).ok().flatten()
+            .is_some_and(|progress| source_sha1.is_some() && progress.source_sha1 == source_sha1);
+        if !resume {
+            staging.reset()?;
+        }
+
+        // Importing over a database, the games that are not in the new DAT are recorded
+        let previous_games = match db_path.exists() {
+            true => Romst::get_data_reader(&Romst::get_r_connection(output_file.as_ref())?)?.get_games_snapshot()
+                .map_err(|e| warn!("Can't read the games before importing: {}", e)).ok(),
+            false => None
+        };
+        let mut conn = Connection::open_with_flags(staging.staging_path(), OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+        let mut db_writer = DBWriter::from_connection(&mut conn, DEFAULT_WRITE_BUFFER_SIZE);
+        db_writer.set_import_source(ImportSource::new(source_path, source_sha1.clone()));
+        if let Some(log_file) = log_file {
+            db_writer.set_import_log(ImportLog::create(&log_file.as_ref())?);
+        }
+        db_writer.record_progress(source_sha1.clone());
+        db_writer.set_autosave(IMPORT_AUTOSAVE_INTERVAL);
+        if resume {
+            if let Some(entries) = db_writer.resume(source_sha1.as_deref())? {
+                println!("Resuming the interrupted import after {} games", Style::new().bold().apply_to(entries));
+            }
+        }
+        let mut dat_importer = AnyDatImporter::from_path(&input.as_ref().to_string(), db_writer)?;
+        if let Some(r) = reporter {
+            dat_importer.set_reporter(r);
+        }
+
+        match dat_importer.load_dat() {
+            Ok(_) => info!("Parsing complete"),
+            Err(e) => {
+                error!("Error parsing file: {}", e);
+                return Err(anyhow!("The import was interrupted: {}. The database `{}` was not modified, importing the same file again goes on from where it stopped.",
+                    e, output_file.as_ref()));
+            }
+        };
+        drop(dat_importer);
+
+        if let Some(previous_games) = previous_games {
+            Romst::record_game_changes(&mut conn, previous_games)?;
+        }
+        drop(conn);
+        staging.commit()?;
+        notify::notify(output_file.as_ref(), DbChangeKind::Imported);
+
+        Ok(())
+    }
+
+    /// Imports a new version of a DAT over the database with the previous one, writing only the games that changed.
+    /// Returns what was added, updated and removed
+    pub fn update_dat<R, S>(input: S, db_file: S, log_file: Option<S>, options: &WriteOptions, reporter: Option<R>) -> Result<DatUpdate> where R: DatImporterReporter + 'static, S: AsRef<str> {
+        let mut conn = Romst::get_w_connection(db_file.as_ref(), options)?;
+        let source_path = std::fs::canonicalize(input.as_ref()).map(|path| path.to_string_lossy().to_string()).unwrap_or_else(|_| input.as_ref().to_string());
+        let source_sha1 = FileReader::new().get_file_sha1(&input.as_ref()).map_err(|e| warn!("Can't get the sha1 of {}: {}", input.as_ref(), e)).ok();
+
+        let previous_games = Romst::get_data_reader(&conn)?.get_games_snapshot()?;
+        let mut db_writer = DBWriter::from_connection(&mut conn, DEFAULT_WRITE_BUFFER_SIZE);
+        db_writer.set_import_source(ImportSource::new(source_path, source_sha1));
+        if let Some(log_file) = log_file {
+            db_writer.set_import_log(ImportLog::create(&log_file.as_ref())?);
+        }
+        let mut dat_importer = AnyDatImporter::from_path(&input.as_ref().to_string(), DBUpdater::new(db_writer)?)?;
+        if let Some(r) = reporter {
+            dat_importer.set_reporter(r);
+        }
+        dat_importer.load_dat()?;
+        let update = dat_importer.into_writer().into_update();
+
+        Romst::record_game_changes(&mut conn, previous_games)?;
+        drop(conn);
+        if !update.is_empty() {
+            notify::notify(db_file.as_ref(), DbChangeKind::Imported);
+        }
+
+        Ok(update)
+    }
+
+    /// Compares two databases, or two DATs, with the sets added, removed and renamed in the new one and the roms changed
+    pub fn diff<S>(old_file: S, new_file: S) -> Result<DatDiff> where S: AsRef<str> {
+        let old = Romst::get_dat_contents(old_file.as_ref())?;
+        let new = Romst::get_dat_contents(new_file.as_ref())?;
+
+        Ok(DatDiff::new(&old, &new))
+    }
+
+    /// The contents of a database, a DAT is imported in memory first
+    fn get_dat_contents(file: &str) -> Result<DatContents> {
+        let path = Path::new(file);
+        if !Romst::is_dat_file(path) {
+            return Romst::get_data_reader(&Romst::get_r_connection(file)?)?.get_dat_contents();
+        }
+        let mut conn = Connection::open_in_memory_with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+        let db_writer = DBWriter::from_connection(&mut conn, DEFAULT_WRITE_BUFFER_SIZE);
+        AnyDatImporter::from_path(&path, db_writer)?.load_dat()?;
+        let contents = Romst::get_data_reader(&conn)?.get_dat_contents()?;
+
+        Ok(contents)
+    }
+
+    /// Records the games removed or renamed since the previous import, the old names become aliases
+    fn record_game_changes(conn: &mut Connection, previous_games: GamesSnapshot) -> Result<()> {
+        let current_games = Romst::get_data_reader(conn)?.get_games_snapshot()?;
+        let changes = previous_games.find_changes(&current_games, &chrono::Utc::now().to_rfc3339());
+        let current_names = current_games.games.into_keys().collect::<Vec<_>>();
+        let writer = Romst::get_data_writer(conn)?;
+        writer.update_game_changes(&changes, &current_names)?;
+        writer.add_game_aliases(&changes.iter().filter_map(GameAlias::from_change).collect::<Vec<_>>())?;
+        if !changes.is_empty() {
+            info!("{} games removed or renamed since the previous import", changes.len());
+        }
+
+        Ok(())
+    }
+
+    /// The other databases in the directory of `output_file` with the same DAT than `input`, or an older version of it,
+    /// by its sha1 or the name and version of its header. Importing the DAT over one of them updates it instead
+    pub fn find_duplicate_dats<S>(input: S, output_file: S) -> Result<Vec<DuplicateDat>> where S: AsRef<str> {
+        let new_dat = match importer::read_dat_info(&input.as_ref())? {
+            Some(dat_info) => dat_info,
+            None => return Ok(vec![])
+        };
+        let new_sha1 = FileReader::new().get_file_sha1(&input.as_ref()).map_err(|e| warn!("Can't get the sha1 of {}: {}", input.as_ref(), e)).ok();
+
+        let output_path = Path::new(output_file.as_ref());
+        let output = std::fs::canonicalize(output_path).ok();
+        let db_directory = output_path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or_else(|| Path::new("."));
+        let mut duplicates = vec![];
+        for db in profile::find_databases(&db_directory)? {
+            if output.is_some() && std::fs::canonicalize(&db).ok() == output {
+                continue;
+            }
+            let dats = match Romst::get_r_connection(db.as_str()).and_then(|conn| Romst::get_data_reader(&conn)?.get_dats()) {
+                Ok(dats) => dats,
+                Err(e) => {
+                    warn!("Skipping the database {}: {}", db, e);
+                    continue;
+                }
+            };
+            for dat in dats {
+                if let Some(kind) = DuplicateDat::find_kind(&dat, &new_dat, new_sha1.as_deref()) {
+                    duplicates.push(DuplicateDat { db: db.clone(), dat, kind });
+                }
+            }
+        }
+
+        Ok(duplicates)
+    }
+
+    /// Creates a new database, or a DAT file if the output has a `dat` or `xml` extension, with the
+    /// games selected by name or tag, plus the sets they depend on. Returns the number of games exported
+    pub fn export_subset<S>(db_file: S, output_file: S, game_names: Vec<String>, tag: Option<S>, overwrite: bool, options: &WriteOptions) -> Result<usize> where S: AsRef<str> {
+        let output_path = Path::new(output_file.as_ref());
+        if !overwrite && output_path.exists() {
+            return Err(anyhow!("Destination file `{}` already exists, choose another output or rename the file.", output_file.as_ref()));
+        }
+
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        let mut selected = game_names;
+        if let Some(tag) = tag {
+            selected.extend(Romst::get_games_with_tag(&reader, tag.as_ref())?);
+        }
+        if selected.is_empty() {
+            return Err(anyhow!("No games selected to export"));
+        }
+
+        if Romst::is_dat_file(output_path) {
+            let mut file = AtomicFile::create(&output_path)?;
+            let name = output_path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+            let mut exporter = Exporter::new(reader, DatWriter::new(&mut file, name));
+            let exported = exporter.export(&selected)?;
+            drop(exporter);
+            file.commit()?;
+            Ok(exported)
+        } else {
+            let mut out_conn = Romst::get_rw_connection(output_file.as_ref(), options)?;
+            let writer = DBWriter::from_connection(&mut out_conn, DEFAULT_WRITE_BUFFER_SIZE);
+            let mut exporter = Exporter::new(reader, writer);
+            let exported = exporter.export(&selected)?;
+            drop(exporter);
+            drop(out_conn);
+            notify::notify(output_file.as_ref(), DbChangeKind::Exported);
+            Ok(exported)
+        }
+    }
+
+    /// Writes every game in the database as a DAT file, with the header of the DAT when there is a single one. The roms
+    /// are the ones of the sets in the romset mode if given, otherwise they are written as they were imported.
+    /// Returns the number of games written
+    pub fn export_dat<S>(db_file: S, output_file: S, rom_mode: Option<RomsetMode>, overwrite: bool) -> Result<usize> where S: AsRef<str> {
+        let output_path = Path::new(output_file.as_ref());
+        if !overwrite && output_path.exists() {
+            return Err(anyhow!("Destination file `{}` already exists, choose another output or rename the file.", output_file.as_ref()));
+        }
+
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        let name = output_path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+        let mut file = AtomicFile::create(&output_path)?;
+        let mut writer = DatWriter::new(&mut file, name);
+        writer.init()?;
+        let mut dats = reader.get_dats()?;
+        if dats.len() == 1 {
+            writer.on_dat_info(dats.remove(0))?;
+        }
+        let game_names = reader.get_game_names(None)?;
+        let exported = match rom_mode {
+            Some(rom_mode) => exporter::write_games_in_mode(&reader, &mut writer, &game_names, rom_mode)?,
+            None => exporter::write_games(&reader, &mut writer, &game_names)?,
+        };
+        writer.finish()?;
+        drop(writer);
+        file.commit()?;
+
+        Ok(exported)
+    }
+
+    /// Combines several databases in a new one, each DAT in the source databases keeps its own
+    /// entry. Identical roms are stored once. Returns the stats of the new database
+    pub fn merge_dbs<S>(db_files: Vec<S>, output_file: S, overwrite: bool, options: &WriteOptions) -> Result<DBReport> where S: AsRef<str> {
+        let output_path = Path::new(output_file.as_ref());
+        if !overwrite && output_path.exists() {
+            return Err(anyhow!("Destination file `{}` already exists, choose another output or rename the file.", output_file.as_ref()));
+        }
+        for db_file in &db_files {
+            if output_path.exists() && Path::new(db_file.as_ref()).canonicalize()? == output_path.canonicalize()? {
+                return Err(anyhow!("The destination can't be one of the databases to merge"));
+            }
+        }
+
+        let mut out_conn = Romst::get_rw_connection(output_file.as_ref(), options)?;
+        let mut writer = DBWriter::from_connection(&mut out_conn, DEFAULT_WRITE_BUFFER_SIZE);
+        writer.init()?;
+        // Game names are unique, the first database with a game wins
+        let mut merged_games = HashSet::new();
+        let mut new_games = |game_names: Vec<String>| -> Vec<String> {
+            game_names.into_iter().filter(|game_name| {
+                let is_new = merged_games.insert(game_name.to_owned());
+                if !is_new {
+                    warn!("Game `{}` is already in the merged database, ignoring it", game_name);
+                }
+                is_new
+            }).collect()
+        };
+        for db_file in &db_files {
+            info!("Merging {}", db_file.as_ref());
+            let conn = Romst::get_r_connection(db_file)?;
+            let reader = Romst::get_data_reader(&conn)?;
+
+            let dats = reader.get_dats()?;
+            if dats.is_empty() {
+                // A database with a single DAT, we use the file name
+                let name = Path::new(db_file.as_ref()).file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+                writer.add_dat(&DatInfo::new(name))?;
+                exporter::write_games(&reader, &mut writer, &new_games(reader.get_game_names(None)?))?;
+            } else {
+                for dat in dats {
+                    writer.add_dat(&dat)?;
+                    exporter::write_games(&reader, &mut writer, &new_games(reader.get_game_names(dat.id)?))?;
+                }
+            }
+
+            // User data
+            writer.add_exclusions(&reader.get_exclusions()?.into_iter().collect::<Vec<_>>())?;
+            for (game_name, tags) in reader.get_tags()? {
+                for tag in tags {
+                    writer.add_tag(&[&game_name], &tag)?;
+                }
+            }
+            for game_name in reader.get_game_names(None)? {
+                if let Some(note) = reader.get_note(&game_name)? {
+                    writer.set_note(&game_name, Some(&note))?;
+                }
+            }
+        }
+        writer.finish()?;
+        drop(writer);
+
+        let stats = Romst::get_data_reader(&out_conn)?.get_stats()?;
+        drop(out_conn);
+        notify::notify(output_file.as_ref(), DbChangeKind::Merged);
+        Ok(stats)
+    }
+
+    pub fn get_dats<S>(db_file: S) -> Result<DatList> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        Ok(DatList::new(reader.get_dats()?))
+    }
+
+    /// Runs a read only query on the database, for anything the other commands don't cover
+    pub fn query<S>(db_file: S, sql: S, params: &[String]) -> Result<QueryRows> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        reader.query_rows(sql.as_ref(), params)
+    }
+
+    /// Removes a DAT and all its data from a database. Returns the number of games removed
+    pub fn purge_dat<S>(db_file: S, dat_id: u32, options: &WriteOptions) -> Result<usize> where S: AsRef<str> {
+        let mut conn = Romst::get_w_connection(db_file.as_ref(), options)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        if !reader.get_dats()?.iter().any(|dat| dat.id == Some(dat_id)) {
+            return Err(anyhow!("No DAT found with id {}", dat_id));
+        }
+        let mut writer = Romst::get_data_writer(&mut conn)?;
+        let games = writer.remove_dat(dat_id)?;
+        drop(conn);
+        notify::notify(db_file.as_ref(), DbChangeKind::Purged);
+        Ok(games)
+    }
+
+    /// The parents with clones, and how many sets of each family are complete in the report
+    pub fn get_family_report<S>(db_file: S, report: &ScanReport) -> Result<FamilyReport> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        let complete_sets = report.sets.iter()
+            .filter(|(_, set_report)| set_report.is_complete() == SetStatus::COMPLETE)
+            .map(|(set_name, _)| set_name.to_owned())
+            .collect::<HashSet<_>>();
+
+        Ok(FamilyReport::new(reader.get_families()?, &complete_sets))
+    }
+
+    /// Writes the sets of the report that are complete, and the ones that are not, as the have and miss lists of clrmamepro.
+    /// The lists are named after the DATs of the database, like the profiles of clrmamepro.
+    pub fn write_have_miss_lists<S>(db_file: S, report: &ScanReport, have_file: Option<S>, miss_file: Option<S>) -> Result<HaveMissLists> where S: AsRef<str> {
+        let lists = HaveMissLists::new(Romst::get_dat_name(db_file)?, report);
+        if let Some(have_file) = have_file {
+            atomic::write(&have_file.as_ref(), lists.to_have_list())?;
+        }
+        if let Some(miss_file) = miss_file {
+            atomic::write(&miss_file.as_ref(), lists.to_miss_list())?;
+        }
+
+        Ok(lists)
+    }
+
+    /// Writes the roms missing in the sets of the report as a DAT file, a fixdat named after the DATs of the database.
+    /// Returns the number of sets written
+    pub fn write_fixdat<S>(db_file: S, report: &ScanReport, output_file: S) -> Result<usize> where S: AsRef<str> {
+        let dat_name = Romst::get_dat_name(db_file)?;
+        let mut file = AtomicFile::create(&output_file.as_ref())?;
+        let written = fixdat::write_fixdat(report, &dat_name, &mut file)?;
+        file.commit()?;
+        Ok(written)
+    }
+
+    /// Writes the report as CSV, a row per rom and disk of each set with its status. Returns the number of rows written
+    pub fn write_report_csv<S>(report: &ScanReport, output_file: S) -> Result<usize> where S: AsRef<str> {
+        let mut file = AtomicFile::create(&output_file.as_ref())?;
+        let written = csv_report::write_csv(report, &mut file)?;
+        file.commit()?;
+        Ok(written)
+    }
+
+    /// Builds the sets with roms in the source, loose or in archives, as zip archives in the destination named after
+    /// the sets of the romset mode. The archives already there keep the files that are not replaced, and with
+    /// TorrentZip all their files are compressed again. With `dry_run` nothing is written, the report has the sets that would be built.
+    /// The excluded sets are never built, and if a tag is specified only the sets with that tag are.
+    /// Moving the roms, the files left empty and the archives rewritten without them go where the delete policy says
+    #[allow(clippy::too_many_arguments)]
+    /// A remote source, like `https://` or `s3://`, is downloaded first and its roms can only be copied
+    pub fn rebuild<S>(db_file: S, source: S, destination: S, rom_mode: RomsetMode, tag: Option<S>, mode: RebuildMode, zip_format: ZipFormat, dry_run: bool, verify: bool, rom_policies: RomPolicies, delete_policy: DeletePolicy, options: &ScanOptions) -> Result<RebuildReport> where S: AsRef<str> {
+        let remote_source = match RemoteSource::parse(source.as_ref()) {
+            Some(remote_source) => remote_source?,
+            None => {
+                let source = std::fs::canonicalize(source.as_ref())?;
+                return Romst::rebuild_local(db_file, source, destination, rom_mode, tag, mode, zip_format, dry_run, verify, rom_policies, delete_policy, options);
+            }
+        };
+        if mode == RebuildMode::Move {
+            return Err(anyhow!("The roms can't be moved from the remote source `{}`, only copied", source.as_ref()));
+        }
+
+        let downloaded = std::env::temp_dir().join(format!("romst_rebuild_remote_{}", std::process::id()));
+        let result = remote_source.download_all(&downloaded)
+            .and_then(|_| Ok(std::fs::canonicalize(&downloaded)?))
+            .and_then(|downloaded| {
+                let report = Romst::rebuild_local(db_file, downloaded.clone(), destination, rom_mode, tag, mode, zip_format, dry_run, verify, rom_policies, delete_policy, options)?;
+                Ok((report, downloaded))
+            });
+        let _ = std::fs::remove_dir_all(&downloaded);
+        let (mut report, downloaded) = result?;
+
+        // The report has the urls of the files, not where they were downloaded
+        report.source = remote_source.get_url("");
+        for rom_source in report.sets.iter_mut().flat_map(|set| set.roms.values_mut()) {
+            if let Ok(file_name) = rom_source.path.strip_prefix(&downloaded) {
+                rom_source.path = PathBuf::from(remote_source.get_url(&file_name.to_string_lossy()));
+            }
+        }
+        let downloaded_prefix = format!("{}{}", downloaded.display(), std::path::MAIN_SEPARATOR);
+        let url_prefix = format!("{}/", report.source);
+        for unknown in report.unknowns.iter_mut() {
+            *unknown = unknown.replacen(&downloaded_prefix, &url_prefix, 1);
+        }
+
+        Ok(report)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn rebuild_local<S>(db_file: S, source: PathBuf, destination: S, rom_mode: RomsetMode, tag: Option<S>, mode: RebuildMode, zip_format: ZipFormat, dry_run: bool, verify: bool, rom_policies: RomPolicies, delete_policy: DeletePolicy, options: &ScanOptions) -> Result<RebuildReport> where S: AsRef<str> {
+        if !dry_run {
+            std::fs::create_dir_all(destination.as_ref())?;
+        }
+        let destination = std::fs::canonicalize(destination.as_ref()).unwrap_or_else(|_| PathBuf::from(destination.as_ref()));
+        // The sets written would be read again as a source
+        if destination.starts_with(&source) || source.starts_with(&destination) {
+            return Err(anyhow!("The destination `{}` can't be in the source `{}`, or the other way around", destination.display(), source.display()));
+        }
+
+        let conn = Romst::get_r_connection(db_file)?;
+        let layout = options.layout;
+        let mut reader = Romst::get_data_reader(&conn)?;
+        reader.set_match_policy(options.match_policy);
+        let tagged_games = match tag {
+            Some(tag) => Some(Romst::get_games_with_tag(&reader, tag.as_ref())?),
+            None => None
+        };
+        let mut rebuilder = Rebuilder::new(reader, rom_mode);
+        rebuilder.set_layout(layout);
+        rebuilder.set_read_options(options.read.clone());
+        rebuilder.set_rom_policies(rom_policies);
+        if let Some(tagged_games) = tagged_games {
+            rebuilder.set_allowed_sets(tagged_games);
+        }
+        let (mut sets, unknowns) = rebuilder.plan(&source, &destination)?;
+        let removed = if dry_run {
+            vec![]
+        } else {
+            for directory in [&source, &destination] {
+                atomic::remove_stale_temps(directory)?;
+            }
+            rebuilder::rebuild(&mut sets, mode, layout, zip_format, &options.read, verify, &delete_policy)
+        };
+
+        Ok(RebuildReport {
+            date_time: chrono::Utc::now().to_rfc3339(),
+            source: source.to_string_lossy().to_string(),
+            destination: destination.to_string_lossy().to_string(),
+            rom_mode, mode, layout, zip_format, dry_run, verify, rom_policies, delete_policy, sets, unknowns, removed
+        })
+    }
+
+    /// Writes the report of a rebuild as JSON, for the scripts keeping track of what was built
+    pub fn save_rebuild_report<S>(output_file: S, report: &RebuildReport) -> Result<()> where S: AsRef<str> {
+        let serialized = serde_json::to_string_pretty(report)?;
+        atomic::write(&output_file.as_ref(), serialized)?;
+
+        Ok(())
+    }
+
+    /// The description, or the name, of each DAT in the database
+    fn get_dat_name<S>(db_file: S) -> Result<String> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let dat_name = Romst::get_data_reader(&conn)?.get_dats()?.into_iter()
+            .map(|dat| dat.description.unwrap_or(dat.name))
+            .collect::<Vec<_>>()
+            .join(" + ");
+        Ok(dat_name)
+    }
+
+    /// The moves, copies and renames between the archives of the report that complete the most sets.
+    /// On Windows the sets with names that can't be files there are left out of the plan.
+    pub fn get_fix_plan(report: &ScanReport) -> FixPlan {
+        FixPlan::new(report, cfg!(windows))
+    }
+
+    /// Writes the fix plan as a sh or PowerShell script, depending on the extension of the file, returns the plan written.
+    /// The sets with names that can't be files on Windows are left out of the PowerShell scripts.
+    /// With `verify`, the script hashes every rom it writes and stops if one doesn't match the database.
+    pub fn write_fix_script<S>(report: &ScanReport, output_file: S, verify: bool) -> Result<FixPlan> where S: AsRef<str> {
+        let shell = ScriptShell::for_file(&output_file.as_ref());
+        let plan = FixPlan::new(report, shell == ScriptShell::PowerShell);
+        let script = plan.to_script(shell, verify);
+        atomic::write(&output_file.as_ref(), script)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(output_file.as_ref(), std::fs::Permissions::from_mode(0o755))?;
+        }
+
+        Ok(plan)
+    }
+
+    /// Adds to the report the unknown files that almost match a rom of the database, returns how many were found
+    pub fn add_suspects<S>(db_file: S, report: &mut ScanReport) -> Result<usize> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        Reporter::new(reader).find_suspects(report)
+    }
+
+    /// Adds to the report the DAT each unknown file probably belongs to, looking for it in the other databases of the
+    /// directory of the database, or else by its header or extension. Returns how many files were identified
+    pub fn identify_unknowns<S>(db_file: S, report: &mut ScanReport, options: &ScanOptions) -> Result<usize> where S: AsRef<str> {
+        let db_path = Path::new(db_file.as_ref());
+        let this_db = std::fs::canonicalize(db_path)?;
+        let db_directory = db_path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or_else(|| Path::new("."));
+        let mut other_dbs = vec![];
+        for other_db in profile::find_databases(&db_directory)? {
+            if std::fs::canonicalize(&other_db).ok().as_ref() == Some(&this_db) {
+                continue;
+            }
+            match Romst::get_dat_name(&other_db) {
+                Ok(dat_name) => other_dbs.push((other_db, dat_name)),
+                Err(e) => warn!("Skipping the database {}: {}", other_db, e)
+            }
+        }
+
+        // The unknown files of the sets, with their archive, and the files ignored in the scan
+        let root = report.get_root_directory().map(PathBuf::from).unwrap_or_default();
+        let mut unknowns = vec![];
+        let mut set_names = report.sets.keys().cloned().collect::<Vec<_>>();
+        set_names.sort();
+        for set_name in set_names {
+            let set = &report.sets[&set_name];
+            let archive = set.file_name.clone().unwrap_or(set_name);
+            unknowns.extend(set.unknown.iter().map(|file| (Some(archive.clone()), file.clone())));
+        }
+        let mut file_reader = FileReader::new();
+        // The ignored files keep the path they were found with
+        for file_name in &report.ignored {
+            let path = Path::new(file_name);
+            let info = match path.metadata() {
+                Ok(metadata) if metadata.is_file() && metadata.len() <= MAX_IDENTIFIED_FILE_SIZE => file_reader.get_file_info(&path).ok(),
+                _ => None
+            };
+            let info = info.unwrap_or_else(|| DataFileInfo::new(FileType::Rom));
+            unknowns.push((None, DataFile::new(file_name.clone(), info)));
+        }
+
+        let mut identified: Vec<Option<Identification>> = vec![None; unknowns.len()];
+        for (other_db, dat_name) in &other_dbs {
+            let conn = Romst::get_r_connection(other_db)?;
+            let mut reader = Romst::get_data_reader(&conn)?;
+            reader.set_match_policy(options.match_policy);
+            for ((archive, file), identification) in unknowns.iter().zip(identified.iter_mut()) {
+                // Only the size is not enough to tell a rom
+                if identification.is_some() || (file.info.crc.is_none() && file.info.sha1.is_none() && file.info.md5.is_none()) {
+                    continue;
+                }
+                let rom_search = reader.get_romsets_from_roms(vec![file.clone()], report.get_rom_mode())?;
+                if let Some(game_name) = rom_search.set_results.keys().min() {
+                    *identification = Some(Identification {
+                        archive: archive.clone(),
+                        file_name: file.name.clone(),
+                        belongs_to: dat_name.clone(),
+                        clue: IdentificationClue::Database { db_file: other_db.clone(), game_name: game_name.clone() }
+                    });
+                }
+            }
+        }
+
+        let dat_names = other_dbs.into_iter().map(|(_, dat_name)| dat_name).collect::<Vec<_>>();
+        for ((archive, file), identification) in unknowns.into_iter().zip(identified.iter_mut()) {
+            if identification.is_some() {
+                continue;
+            }
+            // The files in directory sets are read on their own
+            let passwords = &options.read.passwords;
+            let header = match &archive {
+                Some(archive) if root.join(archive).is_dir() => filesystem::read_header(&root.join(archive).join(&file.name), None, identify::HEADER_LENGTH, passwords),
+                Some(archive) => filesystem::read_header(&root.join(archive), Some(&file.name), identify::HEADER_LENGTH, passwords),
+                None => filesystem::read_header(&file.name, None, identify::HEADER_LENGTH, passwords),
+            };
+            if let Some((system, clue)) = identify::guess_system(&file.name, header.ok().as_deref()) {
+                let belongs_to = identify::find_dat_for_system(system, &dat_names).cloned().unwrap_or_else(|| system.to_string());
+                *identification = Some(Identification { archive, file_name: file.name, belongs_to, clue });
+            }
+        }
+
+        report.identified = identified.into_iter().flatten().collect();
+        Ok(report.identified.len())
+    }
+
+    /// The roms not used by any game, removing them if `prune` is set
+    pub fn get_unreferenced_roms<S>(db_file: S, prune: bool, options: &WriteOptions) -> Result<UnreferencedRoms> where S: AsRef<str> {
+        let roms = {
+            let conn = Romst::get_r_connection(db_file.as_ref())?;
+            let reader = Romst::get_data_reader(&conn)?;
+            reader.get_unreferenced_roms()?
+        };
+        if prune && !roms.is_empty() {
+            let mut conn = Romst::get_w_connection(db_file.as_ref(), options)?;
+            let mut writer = Romst::get_data_writer(&mut conn)?;
+            writer.prune_unreferenced_roms()?;
+            drop(conn);
+            notify::notify(db_file.as_ref(), DbChangeKind::Pruned);
+        }
+
+        Ok(UnreferencedRoms { roms, pruned: prune })
+    }
+
+    /// Updates a database created by an older version of romst. Returns false if it was up to date.
+    pub fn migrate_db<S>(db_file: S, options: &WriteOptions) -> Result<bool> where S: AsRef<str> {
+        let mut conn = Romst::get_w_connection(db_file.as_ref(), options)?;
+        let mut writer = Romst::get_data_writer(&mut conn)?;
+        let migrated = writer.migrate_rom_names()?;
+        let migrated = writer.migrate_rom_shares()? || migrated;
+        let migrated = writer.migrate_machine_info()? || migrated;
+        let migrated = writer.migrate_rom_bios()? || migrated;
+        let migrated = writer.migrate_rom_optional()? || migrated;
+        let migrated = writer.migrate_rom_checksums()? || migrated;
+        drop(conn);
+        if migrated {
+            notify::notify(db_file.as_ref(), DbChangeKind::Migrated);
+        }
+        Ok(migrated)
+    }
+
+    /// Checks the health of the database and the environment, with suggestions for the problems found.
+    /// The profiles file is validated too if given.
+    pub fn doctor<S>(db_file: S, profiles_file: Option<S>) -> DoctorReport where S: AsRef<str> {
+        doctor::examine(db_file.as_ref(), profiles_file.as_ref().map(|profiles_file| profiles_file.as_ref()))
+    }
+
+    fn is_dat_file(path: &Path) -> bool {
+        match path.extension() {
+            Some(extension) => {
+                let extension = extension.to_string_lossy().to_lowercase();
+                extension == "dat" || extension == "xml"
+            }
+            None => false
+        }
+    }
+
+    pub fn get_set_info<S>(db_file: S, game_names: Vec<S>, rom_mode: RomsetMode) -> Result<GameSetsInfo> where S: AsRef<str> {
+        let mut games =  vec![];
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        for game_name in game_names {
+            let game_name = reader.get_game(game_name.as_ref()).map(|game| game.name).unwrap_or_else(|| game_name.as_ref().to_string());
+            let roms = reader.get_romset_roms(game_name.as_str(), rom_mode)?.1.into_iter().map(|db_rom| {
+                db_rom.file
+            }).collect();
+            let device_refs = reader.get_devices_for_game(game_name.as_str())?;
+            match reader.get_game(game_name.as_str()) {
+                Some(game) => {
+                    let mut game_set = GameSet::new(game, roms, vec![], vec![], device_refs.dependencies);
+                    game_set.note = reader.get_note(game_name.as_str())?;
+                    games.push(game_set);
+                }
+                None => {
+                    error!("Game {} not found", game_name.as_str())
+                }
+            }
+        }
+
+        Ok(GameSetsInfo::new(games))
+    }
+
+    /// Gets everything the database knows about a game
+    pub fn get_game_info<S>(db_file: S, game_name: S, rom_mode: RomsetMode) -> Result<GameInfo> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        // The game may be given by an old name
+        let game_name = reader.get_game(game_name.as_ref()).map(|game| game.name).unwrap_or_else(|| game_name.as_ref().to_string());
+        let entry = match reader.get_game_entry(&game_name)? {
+            Some(entry) => entry,
+            None => return Err(anyhow!("Game `{}` not found", game_name))
+        };
+
+        let mut roms = match rom_mode {
+            RomsetMode::NonMerged => entry.roms,
+            _ if entry.roms.is_empty() => entry.roms,
+            _ => reader.get_romset_roms(game_name.as_str(), rom_mode)?.1.into_iter().map(|db_rom| db_rom.file).collect()
+        };
+        roms.sort();
+        let mut tags = reader.get_tags()?.remove(game_name.as_str()).unwrap_or_default().into_iter().collect::<Vec<_>>();
+        tags.sort();
+
+        Ok(GameInfo {
+            game: entry.game,
+            rom_mode,
+            roms,
+            disks: entry.disks,
+            samples: entry.samples,
+            device_refs: entry.device_refs,
+            clones: reader.get_clones(game_name.as_str())?,
+            releases: reader.get_releases(game_name.as_str())?,
+            tags,
+            note: reader.get_note(game_name.as_str())?,
+        })
+    }
+
+    pub fn search_games<S>(db_file: S, filter: &GameFilter) -> Result<GameList> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        Ok(GameList::new(reader.find_games(filter)?))
+    }
+
+    /// Finds the roms by their file name, for when there's no checksum to look for
+    pub fn search_roms<S>(db_file: S, filter: &RomFilter) -> Result<RomList> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        Ok(RomList { roms: reader.search_roms(filter)? })
+    }
+
+    /// Finds in which sets, and under which names, a rom is used. The rom is a checksum (crc, md5 or sha1) or a file to hash
+    pub fn find_rom_locations<S>(db_file: S, rom: S) -> Result<RomLocations> where S: AsRef<str> {
+        let rom_path = Path::new(rom.as_ref());
+        let searched = if rom_path.is_file() {
+            FileReader::new().get_file_info(&rom_path)?
+        } else {
+            DataFileInfo::from_checksum(rom.as_ref())?
+        };
+
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+
+        let mut roms = HashSet::new();
+        let mut locations = vec![];
+        for rom_mode in [RomsetMode::NonMerged, RomsetMode::Split, RomsetMode::Merged].iter() {
+            let rom_search = reader.find_rom_locations(&searched, *rom_mode)?;
+            let mut sets = BTreeMap::new();
+            for (set_name, set_content) in &rom_search.set_results {
+                let mut rom_names = set_content.get_roms_included().into_iter().map(|db_rom| {
+                    roms.insert(db_rom.file.info.clone());
+                    db_rom.file.name.clone()
+                }).collect::<Vec<_>>();
+                rom_names.sort();
+                rom_names.dedup();
+                sets.insert(set_name.to_string(), rom_names);
+            }
+            locations.push(RomSetLocations { rom_mode: *rom_mode, sets });
+        }
+
+        let mut roms = roms.into_iter().collect::<Vec<_>>();
+        roms.sort();
+        Ok(RomLocations { searched, roms, locations })
+    }
+
+    pub fn get_rom_usage<S>(db_file: S, game_name: S, rom_name: S, rom_mode: RomsetMode) -> Result<RomSearch> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        reader.find_rom_usage(game_name.as_ref(), rom_name.as_ref(), rom_mode)
+    }
+
+    pub fn get_romset_usage<S>(db_file: S, game_name: S, rom_mode: RomsetMode) -> Result<RomSearch> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        reader.get_romset_shared_roms(game_name.as_ref(), rom_mode)
+    }
+
+    /// The games that need a device, the inverse of the dependencies of a game
+    pub fn get_games_using_device<S>(db_file: S, device_name: S) -> Result<GameList> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        if reader.get_game(device_name.as_ref()).is_none() {
+            return Err(anyhow!("Device `{}` not found", device_name.as_ref()));
+        }
+        let games = reader.get_games_using_device(device_name.as_ref())?.into_iter()
+            .filter_map(|game_name| reader.get_game(&game_name))
+            .collect();
+
+        Ok(GameList::new(games))
+    }
+
+    /// The games using a sample set
+    pub fn get_games_using_samples<S>(db_file: S, sample_set: S) -> Result<GameList> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        let games = reader.get_games_using_samples(sample_set.as_ref())?.into_iter()
+            .filter_map(|game_name| reader.get_game(&game_name))
+            .collect();
+
+        Ok(GameList::new(games))
+    }
+
+    /// All the sample sets, the ones used by more games first
+    pub fn get_sample_sets<S>(db_file: S) -> Result<SampleSets> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        Ok(SampleSets { sample_sets: reader.get_sample_sets()? })
+    }
+
+    pub fn get_romset_dependencies<S>(db_file: S, game_name: S, rom_mode: RomsetMode) -> Result<SetDependencies> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        let mut result = reader.get_devices_for_game(game_name.as_ref())?;
+
+        // If we are in split mode, we add the parent as a dependency
+        match rom_mode {
+            RomsetMode::Split => {
+                if let Some(game) = reader.get_game(game_name.as_ref()) {
+                    if let Some(clone_of) = game.clone_of {
+                        result.dependencies.push(clone_of);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Ok(result)
+    }
+
+    pub fn get_db_info<S>(db_file: S) -> Result<DBReport> where S: AsRef<str>{
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        reader.get_stats()
+    }
+
+    pub fn add_exclusions<S>(db_file: S, game_names: Vec<S>, options: &WriteOptions) -> Result<()> where S: AsRef<str> {
+        let mut conn = Romst::get_w_connection(db_file.as_ref(), options)?;
+        let writer = Romst::get_data_writer(&mut conn)?;
+        writer.add_exclusions(&game_names)?;
+        drop(conn);
+        notify::notify(db_file.as_ref(), DbChangeKind::UserData);
+        Ok(())
+    }
+
+    pub fn remove_exclusions<S>(db_file: S, game_names: Vec<S>, options: &WriteOptions) -> Result<()> where S: AsRef<str> {
+        let mut conn = Romst::get_w_connection(db_file.as_ref(), options)?;
+        let writer = Romst::get_data_writer(&mut conn)?;
+        writer.remove_exclusions(&game_names)?;
+        drop(conn);
+        notify::notify(db_file.as_ref(), DbChangeKind::UserData);
+        Ok(())
+    }
+
+    pub fn get_exclusions<S>(db_file: S) -> Result<Exclusions> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        let mut game_names = reader.get_exclusions()?.into_iter().collect::<Vec<_>>();
+        game_names.sort();
+        Ok(Exclusions::new(game_names))
+    }
+
+    pub fn add_tag<S>(db_file: S, game_names: Vec<S>, tag: S, options: &WriteOptions) -> Result<()> where S: AsRef<str> {
+        let mut conn = Romst::get_w_connection(db_file.as_ref(), options)?;
+        let writer = Romst::get_data_writer(&mut conn)?;
+        writer.add_tag(&game_names, tag.as_ref())?;
+        drop(conn);
+        notify::notify(db_file.as_ref(), DbChangeKind::UserData);
+        Ok(())
+    }
+
+    pub fn remove_tag<S>(db_file: S, game_names: Vec<S>, tag: S, options: &WriteOptions) -> Result<()> where S: AsRef<str> {
+        let mut conn = Romst::get_w_connection(db_file.as_ref(), options)?;
+        let writer = Romst::get_data_writer(&mut conn)?;
+        writer.remove_tag(&game_names, tag.as_ref())?;
+        drop(conn);
+        notify::notify(db_file.as_ref(), DbChangeKind::UserData);
+        Ok(())
+    }
+
+    /// Gets the tags for all the games, or only the games with the tag if specified
+    pub fn get_tags<S>(db_file: S, tag: Option<S>) -> Result<GameTags> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        let games = reader.get_tags()?.into_iter().filter(|(_game_name, tags)| {
+            match &tag {
+                Some(tag) => tags.contains(tag.as_ref()),
+                None => true
+            }
+        }).map(|(game_name, tags)| {
+            let mut tags = tags.into_iter().collect::<Vec<_>>();
+            tags.sort();
+            (game_name, tags)
+        }).collect();
+
+        Ok(GameTags::new(games))
+    }
+
+    /// Tags or excludes the sets of a list, a set name per line. With a 1G1R list the sets are kept and the other games
+    /// of their families are excluded
+    pub fn import_set_list<S>(db_file: S, list_file: S, action: &SetListAction, options: &ScanOptions, write_options: &WriteOptions) -> Result<SetListImport> where S: AsRef<str> {
+        let list = SetList::parse(&std::fs::read_to_string(list_file.as_ref())?);
+        let mut conn = Romst::get_w_connection(db_file.as_ref(), write_options)?;
+        let writer = Romst::get_data_writer(&mut conn)?;
+        let reader = writer.get_reader();
+        let aliases = reader.get_game_aliases()?.into_iter().map(|alias| (alias.alias, alias.game_name)).collect();
+        let found = list.resolve(&reader.get_game_names(None)?, &aliases, options.name_rules);
+
+        let mut import = SetListImport { sets: found.sets, unknown: found.unknown, ..SetListImport::default() };
+        match action {
+            SetListAction::Tag(tag) => writer.add_tag(&import.sets, tag)?,
+            SetListAction::Exclude => writer.add_exclusions(&import.sets)?,
+            SetListAction::Keep => {
+                import.excluded = SetList::get_others_in_families(&import.sets, &reader.get_families()?);
+                writer.remove_exclusions(&import.sets)?;
+                writer.add_exclusions(&import.excluded)?;
+            }
+        }
+        drop(conn);
+        notify::notify(db_file.as_ref(), DbChangeKind::UserData);
+
+        Ok(import)
+    }
+
+    /// Writes a list of the sets with a tag, the excluded ones or the game kept of each family, a set name per line
+    pub fn export_set_list<S>(db_file: S, list_file: S, source: &SetListSource) -> Result<SetList> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        let mut names = match source {
+            SetListSource::Tag(tag) => reader.get_tags()?.into_iter()
+                .filter(|(_, tags)| tags.contains(tag))
+                .map(|(game_name, _)| game_name)
+                .collect::<Vec<_>>(),
+            SetListSource::Exclusions => reader.get_exclusions()?.into_iter().collect(),
+            SetListSource::Prefer(priorities) => {
+                let filter = ReleaseFilter { prefer: Some(priorities.clone()), ..ReleaseFilter::default() };
+                reader.get_games_with_release(&filter)?.into_iter().collect()
+            }
+        };
+        names.sort();
+
+        let list = SetList::new(names);
+        atomic::write(&list_file.as_ref(), list.to_string())?;
+        Ok(list)
+    }
+
+    pub fn set_note<S>(db_file: S, game_name: S, note: Option<S>, options: &WriteOptions) -> Result<()> where S: AsRef<str> {
+        let mut conn = Romst::get_w_connection(db_file.as_ref(), options)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        if reader.get_game(game_name.as_ref()).is_none() {
+            return Err(anyhow!("Game `{}` not found", game_name.as_ref()));
+        }
+        let writer = Romst::get_data_writer(&mut conn)?;
+        writer.set_note(game_name.as_ref(), note.as_ref().map(|note| note.as_ref()))?;
+        drop(conn);
+        notify::notify(db_file.as_ref(), DbChangeKind::UserData);
+        Ok(())
+    }
+
+    pub fn get_note<S>(db_file: S, game_name: S) -> Result<Option<String>> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        reader.get_note(game_name.as_ref())
+    }
+
+    /// Set names, and glob patterns matching the names of the sets
+    fn get_selected_games(reader: &impl DataReader, sets: &[String]) -> Result<HashSet<String>> {
+        let mut games = HashSet::new();
+        for set in sets {
+            if set.contains(&['*', '?'][..]) {
+                let filter = GameFilter::new(set, PatternKind::Glob)?;
+                games.extend(reader.find_games(&filter)?.into_iter().map(|game| game.name));
+            } else {
+                games.insert(set.to_string());
+            }
+        }
+
+        Ok(games)
+    }
+
+    fn get_games_with_tag(reader: &impl DataReader, tag: &str) -> Result<HashSet<String>> {
+        let games = reader.get_tags()?.into_iter().filter_map(|(game_name, tags)| {
+            if tags.contains(tag) {
+                Some(game_name)
+            } else {
+                None
+            }
+        }).collect();
+
+        Ok(games)
+    }
+
+    /// Checks the files, if a tag is specified, only the sets with that tag are included in the report.
+    /// The same with a list of sets, which can have glob patterns too, and with the releases filter.
+    /// A single `ssh://` or `sftp://` source is scanned in the remote machine first
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_report<R, S>(db_file: S, file_paths: Vec<impl AsRef<Path>>, rom_mode: RomsetMode, scope: ReportScope, tag: Option<S>, sets: Option<Vec<String>>, releases: ReleaseFilter, options: &ScanOptions, progress_reporter: Option<R>) -> Result<ScanReport> where R: ReportReporter + 'static, S: AsRef<str> {
+        if let Some(remote_source) = Romst::get_remote_source(&file_paths) {
+            let mut scanner = Scanner::new();
+            scanner.set_read_options(options.read.clone());
+            if let Some(progress_reporter) = progress_reporter {
+                scanner.add_reporter(progress_reporter);
+            }
+            let manifest = scanner.scan_remote(&remote_source?)?;
+            return Romst::build_report(db_file, ReportSource::<&Path>::Manifest(manifest), rom_mode, scope, tag, sets, releases, options, None::<R>);
+        }
+        Romst::build_report(db_file, ReportSource::Files(file_paths), rom_mode, scope, tag, sets, releases, options, progress_reporter)
+    }
+
+    fn get_remote_source(file_paths: &[impl AsRef<Path>]) -> Option<Result<RemoteSource>> {
+        match file_paths {
+            [file_path] => RemoteSource::parse(&file_path.as_ref().to_string_lossy()),
+            _ => None
+        }
+    }
+
+    /// Checks each directory with a profile, under the root or the ones configured in the profiles if there is no root,
+    /// with a summary of the sets of each database. A directory failing doesn't stop the rest.
+    pub fn verify_all<S>(profiles_file: S, root: Option<S>, parallel: bool, options: &ScanOptions) -> Result<ProfileReports> where S: AsRef<str> {
+        let profiles = ScanProfiles::load(&profiles_file.as_ref())?;
+        let directories = match root {
+            Some(root) => profiles.get_directories(&root.as_ref())?,
+            None => profiles.get_all_directories()?,
+        };
+        let mut checks = vec![];
+        for directory in directories {
+            let (profile_name, profile) = match profiles.get_profile(&directory) {
+                Some(profile) => profile,
+                None => continue
+            };
+            let files = directory.read_dir()?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file() && !profile.is_excluded(path))
+                .collect::<Vec<_>>();
+            if !files.is_empty() {
+                checks.push((directory.to_string_lossy().to_string(), profile_name, profile, files));
+            }
+        }
+
+        let check = |(directory, profile_name, profile, files): (String, &str, &ScanProfile, Vec<PathBuf>)| {
+            profile.get_rom_mode()
+                .and_then(|rom_mode| Ok((rom_mode, options.with_profile(profile)?)))
+                .and_then(|(rom_mode, options)| Romst::get_report(profile.db.as_str(), files, rom_mode, profile.get_scope(), profile.tag.as_deref(), profile.sets.clone(), profile.get_releases(), &options, None::<sysout::ReportReporterSysOut>))
+                .map(|report| ProfileReport { directory: directory.clone(), profile: profile_name.to_string(), report: report.into_grouped() })
+                .map_err(|e| FailedDirectory { directory, profile: profile_name.to_string(), error: e.to_string() })
+        };
+        let results: Vec<_> = if parallel {
+            checks.into_par_iter().map(check).collect()
+        } else {
+            checks.into_iter().map(check).collect()
+        };
+
+        let mut reports = vec![];
+        let mut failed = vec![];
+        for result in results {
+            match result {
+                Ok(report) => reports.push(report),
+                Err(failure) => failed.push(failure),
+            }
+        }
+        ProfileReports::new(reports, failed, &profiles)
+    }
+
+    /// Like `get_report`, but with the files from a manifest instead of scanning them again
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_report_from_manifest<R, S>(db_file: S, manifest_file: S, rom_mode: RomsetMode, scope: ReportScope, tag: Option<S>, sets: Option<Vec<String>>, releases: ReleaseFilter, options: &ScanOptions, progress_reporter: Option<R>) -> Result<ScanReport> where R: ReportReporter + 'static, S: AsRef<str> {
+        let manifest = ScanManifest::load(&manifest_file.as_ref())?;
+        Romst::build_report(db_file, ReportSource::<&Path>::Manifest(manifest), rom_mode, scope, tag, sets, releases, options, progress_reporter)
+    }
+
+    /// Like `get_report`, but with the checksums from sfv, md5 or sha1 files instead of reading the roms.
+    /// If `verify_sample` is more than zero, that number of files from each checksum file are hashed to verify them.
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_report_from_checksum_files<R, S>(db_file: S, checksum_files: Vec<impl AsRef<Path>>, verify_sample: usize, rom_mode: RomsetMode, scope: ReportScope, tag: Option<S>, sets: Option<Vec<String>>, releases: ReleaseFilter, options: &ScanOptions, progress_reporter: Option<R>) -> Result<ScanReport> where R: ReportReporter + 'static, S: AsRef<str> {
+        let root_directory = match checksum_files.as_slice() {
+            [checksum_file] => std::fs::canonicalize(checksum_file.as_ref()).ok()
+                .and_then(|checksum_file| checksum_file.parent().map(|parent| parent.to_path_buf()))
+                .map(|parent| parent.to_string_lossy().to_string()),
+            _ => None
+        };
+
+        let mut manifest = ScanManifest::new(root_directory);
+        for checksum_file in checksum_files {
+            let entries = checksum_file::read_checksum_file(&checksum_file)?;
+            let failed = checksum_file::spot_check(&entries, verify_sample);
+            if !failed.is_empty() {
+                return Err(anyhow!("The checksums in {} don't match the files: {}", checksum_file.as_ref().to_string_lossy(), failed.join(", ")));
+            }
+            manifest.add_checksum_entries(&checksum_file, entries);
+        }
+
+        Romst::build_report(db_file, ReportSource::<&Path>::Manifest(manifest), rom_mode, scope, tag, sets, releases, options, progress_reporter)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn build_report<R, S, P>(db_file: S, source: ReportSource<P>, rom_mode: RomsetMode, scope: ReportScope, tag: Option<S>, sets: Option<Vec<String>>, releases: ReleaseFilter, options: &ScanOptions, progress_reporter: Option<R>) -> Result<ScanReport> where R: ReportReporter + 'static, S: AsRef<str>, P: AsRef<Path> {
+        let conn = Romst::get_r_connection(db_file.as_ref())?;
+        let mut reader = Romst::get_data_reader(&conn)?;
+        reader.set_match_policy(options.match_policy);
+        let mut allowed_sets = match sets {
+            Some(sets) => Some(Romst::get_selected_games(&reader, &sets)?),
+            None => None
+        };
+        if let Some(tag) = tag {
+            let tagged_games = Romst::get_games_with_tag(&reader, tag.as_ref())?;
+            allowed_sets = Some(match allowed_sets {
+                Some(allowed_sets) => allowed_sets.intersection(&tagged_games).cloned().collect(),
+                None => tagged_games
+            });
+        }
+        if !releases.is_empty() {
+            let release_games = reader.get_games_with_release(&releases)?;
+            allowed_sets = Some(match allowed_sets {
+                Some(allowed_sets) => allowed_sets.intersection(&release_games).cloned().collect(),
+                None => release_games
+            });
+        }
+
+        let mut reporter = Reporter::new(reader);
+        reporter.set_scope(scope);
+        reporter.set_scan_level(options.scan_level);
+        reporter.set_layout(options.layout);
+        reporter.set_read_options(options.read.clone());
+        reporter.set_name_rules(options.name_rules);
+        reporter.match_in_parallel(Path::new(db_file.as_ref()));
+        if let Some(allowed_sets) = allowed_sets {
+            reporter.set_allowed_sets(allowed_sets);
+        }
+        if let Some(progress_reporter) = progress_reporter {
+            reporter.add_reporter(progress_reporter);
+        }
+
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(async {
+                match source {
+                    ReportSource::Files(file_paths) => reporter.check(file_paths, rom_mode).await,
+                    ReportSource::Manifest(manifest) => reporter.check_manifest(manifest, rom_mode).await,
+                }
+            })
+    }
+
+    /// What the candidate files, roms that could be acquired, would complete in the collection: the sets not complete
+    /// that could be completed, or would miss less roms. The candidates are a DAT, a manifest created with `scan_files`,
+    /// or a directory to scan. With `ReportScope::All` the sets without any file in the collection are included.
+    pub fn what_if<R, S>(db_file: S, file_paths: Vec<impl AsRef<Path>>, candidates: S, rom_mode: RomsetMode, scope: ReportScope, options: &ScanOptions, progress_reporter: Option<R>) -> Result<WhatIfReport> where R: ReportReporter + 'static, S: AsRef<str> {
+        let candidates_path = Path::new(candidates.as_ref());
+        let candidates = if candidates_path.is_dir() {
+            Romst::scan_sources(vec![candidates_path], options, None::<R>)?
+        } else if Romst::is_dat_file(candidates_path) {
+            let mut dat_importer = AnyDatImporter::from_path(&candidates_path, ManifestWriter::new())?;
+            dat_importer.load_dat()?;
+            dat_importer.into_writer().into_manifest()
+        } else {
+            ScanManifest::load(&candidates_path)?
+        };
+
+        let collection = Romst::scan_sources(file_paths, options, progress_reporter)?;
+        let mut with_candidates = collection.clone();
+        with_candidates.files.extend(candidates.files);
+
+        let current = Romst::build_report(db_file.as_ref(), ReportSource::<&Path>::Manifest(collection), rom_mode, scope, None, None, ReleaseFilter::default(), options, None::<R>)?;
+        let with_candidates = Romst::build_report(db_file.as_ref(), ReportSource::<&Path>::Manifest(with_candidates), rom_mode, scope, None, None, ReleaseFilter::default(), options, None::<R>)?;
+
+        Ok(WhatIfReport::new(&current, &with_candidates))
+    }
+
+    /// Hashes the files without checking them, the manifest can be checked later with `get_report_from_manifest`
+    pub fn scan_files<R, S>(file_paths: Vec<impl AsRef<Path>>, output_file: S, options: &ScanOptions, progress_reporter: Option<R>) -> Result<usize> where R: ReportReporter + 'static, S: AsRef<str> {
+        let manifest = Romst::scan_sources(file_paths, options, progress_reporter)?;
+        manifest.save(&output_file.as_ref())?;
+
+        Ok(manifest.files.len())
+    }
+
+    fn scan_sources<R>(file_paths: Vec<impl AsRef<Path>>, options: &ScanOptions, progress_reporter: Option<R>) -> Result<ScanManifest> where R: ReportReporter + 'static {
+        let mut scanner = Scanner::new();
+        scanner.set_read_options(options.read.clone());
+        if let Some(progress_reporter) = progress_reporter {
+            scanner.add_reporter(progress_reporter);
+        }
+
+        let manifest = match Romst::get_remote_source(&file_paths) {
+            Some(remote_source) => scanner.scan_remote(&remote_source?)?,
+            None => tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(async {
+                    scanner.scan(file_paths).await
+                })?
+        };
+
+        Ok(manifest)
+    }
+
+    /// Writes a checksum file for each set archive, next to it, or a single one with all of them if there is an output file
+    pub fn create_checksum_files(file_paths: Vec<impl AsRef<Path>>, format: ChecksumFormat, output_file: Option<impl AsRef<Path>>, options: &ScanOptions) -> Result<usize> {
+        let mut archives = vec![];
+        for file_path in file_paths {
+            let file_path = file_path.as_ref();
+            if file_path.is_dir() {
+                let mut contents = file_path.read_dir()?.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect::<Vec<_>>();
+                contents.sort();
+                archives.append(&mut contents);
+            } else {
+                archives.push(file_path.to_path_buf());
+            }
+        }
+
+        let mut file_reader = FileReader::with_options(options.read.clone());
+        let mut all_roms = vec![];
+        let mut written = 0;
+        for archive in archives.iter().filter(|archive| archive.is_file()) {
+            let game_set = match file_reader.build_game_set(archive, format.get_file_checks()) {
+                Ok(game_set) => game_set,
+                Err(RomstIOError::NotValidFileError(_, _)) => continue,
+                Err(e) => return Err(e.into())
+            };
+
+            if output_file.is_some() {
+                let game_name = game_set.game.name;
+                all_roms.extend(game_set.roms.into_iter().map(|mut rom| {
+                    rom.name = format!("{}/{}", game_name, rom.name);
+                    rom
+                }));
+            } else {
+                checksum_file::write_checksum_file(&archive.with_extension(format.get_extension()), &game_set.roms)?;
+                written += 1;
+            }
+        }
+
+        if let Some(output_file) = output_file {
+            checksum_file::write_checksum_file(&output_file, &all_roms)?;
+            written += 1;
+        }
+
+        Ok(written)
+    }
+
+    pub fn verify_set<S>(db_file: S, game_name: S, file_path: impl AsRef<Path>, rom_mode: RomsetMode, options: &ScanOptions) -> Result<SetReport> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let mut reader = Romst::get_data_reader(&conn)?;
+        reader.set_match_policy(options.match_policy);
+        let mut reporter = Reporter::new(reader);
+        reporter.set_read_options(options.read.clone());
+
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(async {
+                reporter.verify_set(game_name, &file_path, rom_mode).await
+            })
+    }
+
+    pub fn verify_disks<S>(db_file: S, game_name: S, roms_directory: impl AsRef<Path>) -> Result<DiskReport> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        Reporter::new(reader).verify_disks(game_name, &roms_directory)
+    }
+
+    /// Checks a directory against all the sets in the database and stores the counts in the report history
+    pub fn record_report<R, S>(db_file: S, directory: S, rom_mode: RomsetMode, options: &ScanOptions, write_options: &WriteOptions, progress_reporter: Option<R>) -> Result<ReportHistoryRecord> where R: ReportReporter + 'static, S: AsRef<str> {
+        let source = match RemoteSource::parse(directory.as_ref()) {
+            Some(_) => directory.as_ref().to_string(),
+            None => std::fs::canonicalize(directory.as_ref())?.to_string_lossy().to_string()
+        };
+        let report = Romst::get_report(db_file.as_ref(), vec![&source], rom_mode, ReportScope::All, None, None, ReleaseFilter::default(), options, progress_reporter)?.into_grouped();
+        let mut entry = ReportHistoryEntry {
+            id: None,
+            source,
+            date_time: chrono::Utc::now().to_rfc3339(),
+            rom_mode: rom_mode.to_string(),
+            complete: report.complete.len() as u32,
+            fixable: report.fixable.len() as u32,
+            incomplete: report.incomplete.len() as u32,
+            missing: report.missing.len() as u32,
+        };
+
+        let mut conn = Romst::get_w_connection(db_file.as_ref(), write_options)?;
+        let previous = Romst::get_data_reader(&conn)?.get_report_history(Some(&entry.source))?.pop();
+        let writer = Romst::get_data_writer(&mut conn)?;
+        entry.id = Some(writer.add_report_history(&entry)?);
+        drop(conn);
+        notify::notify(db_file.as_ref(), DbChangeKind::ReportRecorded);
+
+        Ok(ReportHistoryRecord { entry, previous })
+    }
+
+    /// Runs a command through the shell, with the counts of the record as `ROMST_*` environment variables
+    pub fn run_command_for_record<S>(command: S, record: &ReportHistoryRecord) -> Result<()> where S: AsRef<str> {
+        let mut shell = if cfg!(windows) {
+            let mut shell = std::process::Command::new("cmd");
+            shell.arg("/C");
+            shell
+        } else {
+            let mut shell = std::process::Command::new("sh");
+            shell.arg("-c");
+            shell
+        };
+        shell.arg(command.as_ref())
+            .env("ROMST_SOURCE", &record.entry.source)
+            .env("ROMST_DATE_TIME", &record.entry.date_time)
+            .env("ROMST_COMPLETE", record.entry.complete.to_string())
+            .env("ROMST_FIXABLE", record.entry.fixable.to_string())
+            .env("ROMST_INCOMPLETE", record.entry.incomplete.to_string())
+            .env("ROMST_MISSING", record.entry.missing.to_string());
+        if let Some(previous) = &record.previous {
+            shell.env("ROMST_PREVIOUS_COMPLETE", previous.complete.to_string())
+                .env("ROMST_PREVIOUS_FIXABLE", previous.fixable.to_string())
+                .env("ROMST_PREVIOUS_INCOMPLETE", previous.incomplete.to_string())
+                .env("ROMST_PREVIOUS_MISSING", previous.missing.to_string());
+        }
+
+        let status = shell.status()?;
+        if !status.success() {
+            return Err(anyhow!("The command `{}` failed with {}", command.as_ref(), status));
+        }
+
+        Ok(())
+    }
+
+    pub fn get_report_history<S>(db_file: S, source: Option<S>) -> Result<ReportHistory> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        let source = source.map(|source| std::fs::canonicalize(source.as_ref())
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_else(|_| source.as_ref().to_string()));
+        Ok(ReportHistory::new(reader.get_report_history(source.as_deref())?))
+    }
+
+    /// The completeness over time of each database, from the reports in their history, keeping the `last` reports
+    /// of each source (all of them if 0)
+    pub fn get_progress<S>(db_files: &[S], source: Option<S>, last: usize) -> Result<ProgressReport> where S: AsRef<str> {
+        let dats = db_files.iter().map(|db_file| {
+            let history = Romst::get_report_history(db_file.as_ref(), source.as_ref().map(|source| source.as_ref()))?;
+            Ok(DatProgress {
+                db_file: db_file.as_ref().to_string(),
+                dat_name: Romst::get_dat_name(db_file.as_ref())?,
+                sources: SourceProgress::from_history(history.entries, last),
+            })
+        }).collect::<Result<Vec<_>>>()?;
+        Ok(ProgressReport::new(dats))
+    }
+
+    /// The games removed or renamed by the DATs imported over the database, only the ones removed after a DAT version if specified
+    pub fn get_game_changes<S>(db_file: S, since: Option<S>) -> Result<GameChanges> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        let changes = reader.get_game_changes()?.into_iter().filter(|change| match (&since, &change.removed_in) {
+            (Some(since), Some(removed_in)) => history::compare_versions(removed_in, since.as_ref()).is_gt(),
+            (Some(_), None) => false,
+            (None, _) => true
+        }).collect();
+        Ok(GameChanges::new(changes))
+    }
+
+    /// The old names of the games, from the renames found importing DATs and the lists imported
+    pub fn get_game_aliases<S>(db_file: S) -> Result<GameAliases> where S: AsRef<str> {
+        let conn = Romst::get_r_connection(db_file)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        Ok(GameAliases::new(reader.get_game_aliases()?))
+    }
+
+    /// Adds the renames of a list, a line per game with the old and the new name, returns how many were added
+    pub fn import_game_aliases<S>(db_file: S, list_file: S, options: &WriteOptions) -> Result<usize> where S: AsRef<str> {
+        let aliases = GameAlias::parse_list(&std::fs::read_to_string(list_file.as_ref())?)?;
+        let mut conn = Romst::get_w_connection(db_file.as_ref(), options)?;
+        let writer = Romst::get_data_writer(&mut conn)?;
+        writer.add_game_aliases(&aliases)?;
+        drop(conn);
+        notify::notify(db_file.as_ref(), DbChangeKind::UserData);
+
+        Ok(aliases.len())
+    }
+
+    /// Adds a game without roms. With several DATs in the database, `dat_id` chooses the one the game is added to
+    pub fn add_game<S>(db_file: S, game_name: S, edit: &GameEdit, dat_id: Option<u32>, options: &WriteOptions) -> Result<Game> where S: AsRef<str> {
+        let mut conn = Romst::get_w_connection(db_file.as_ref(), options)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        let mut game = Game::new(game_name.as_ref().to_string());
+        GameEdit { name: Some(game.name.to_owned()), ..edit.clone() }.apply(&mut game)?;
+        if reader.get_game(&game.name).filter(|existing| existing.name == game.name).is_some() {
+            return Err(anyhow!("The game `{}` is already in the database", game.name));
+        }
+        Romst::check_parent(&reader, &game)?;
+        let dats = reader.get_dats()?;
+        let dat_id = match dat_id {
+            Some(dat_id) if !dats.iter().any(|dat| dat.id == Some(dat_id)) => return Err(anyhow!("No DAT found with id {}", dat_id)),
+            Some(dat_id) => Some(dat_id),
+            None if dats.len() > 1 => return Err(anyhow!("The database has {} DATs, choose the one to add the game to", dats.len())),
+            None => dats.first().and_then(|dat| dat.id),
+        };
+
+        let mut writer = Romst::get_data_writer(&mut conn)?;
+        writer.insert_game(&game, dat_id)?;
+        drop(conn);
+        notify::notify(db_file.as_ref(), DbChangeKind::Edited);
+        Ok(game)
+    }
+
+    /// Changes the name or the metadata of a game
+    pub fn edit_game<S>(db_file: S, game_name: S, edit: &GameEdit, options: &WriteOptions) -> Result<Game> where S: AsRef<str> {
+        let mut conn = Romst::get_w_connection(db_file.as_ref(), options)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        let mut game = Romst::get_existing_game(&reader, game_name.as_ref())?;
+        let old_name = game.name.to_owned();
+        edit.apply(&mut game)?;
+        if game.name != old_name && reader.get_game(&game.name).filter(|existing| existing.name == game.name).is_some() {
+            return Err(anyhow!("The game `{}` is already in the database", game.name));
+        }
+        Romst::check_parent(&reader, &game)?;
+
+        let mut writer = Romst::get_data_writer(&mut conn)?;
+        writer.update_game(&old_name, &game)?;
+        drop(conn);
+        notify::notify(db_file.as_ref(), DbChangeKind::Edited);
+        Ok(game)
+    }
+
+    /// Removes a game and its roms, as long as no other game depends on it
+    pub fn remove_game<S>(db_file: S, game_name: S, options: &WriteOptions) -> Result<()> where S: AsRef<str> {
+        let mut conn = Romst::get_w_connection(db_file.as_ref(), options)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        let game = Romst::get_existing_game(&reader, game_name.as_ref())?;
+        let mut dependants = reader.get_clones(&game.name)?;
+        dependants.extend(reader.get_games_using_device(&game.name)?);
+        if !dependants.is_empty() {
+            return Err(anyhow!("`{}` can't be removed, these games depend on it: {}", game.name, dependants.join(", ")));
+        }
+
+        let mut writer = Romst::get_data_writer(&mut conn)?;
+        writer.delete_game(&game.name)?;
+        drop(conn);
+        notify::notify(db_file.as_ref(), DbChangeKind::Edited);
+        Ok(())
+    }
+
+    /// Adds a rom to a game, the rom needs at least a checksum
+    pub fn add_rom<S>(db_file: S, game_name: S, edit: &RomEdit, options: &WriteOptions) -> Result<DataFile> where S: AsRef<str> {
+        let mut conn = Romst::get_w_connection(db_file.as_ref(), options)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        let game = Romst::get_existing_game(&reader, game_name.as_ref())?;
+        let rom = edit.to_rom()?;
+        let roms = reader.get_game_entry(&game.name)?.map(|entry| entry.roms).unwrap_or_default();
+        if roms.iter().any(|existing| existing.name == rom.name) {
+            return Err(anyhow!("The game `{}` already has a rom named `{}`", game.name, rom.name));
+        }
+
+        let mut writer = Romst::get_data_writer(&mut conn)?;
+        writer.set_game_rom(&game.name, None, &rom)?;
+        drop(conn);
+        notify::notify(db_file.as_ref(), DbChangeKind::Edited);
+        Ok(rom)
+    }
+
+    /// Changes the name, the size or the checksums of a rom of a game
+    pub fn edit_rom<S>(db_file: S, game_name: S, rom_name: S, edit: &RomEdit, options: &WriteOptions) -> Result<DataFile> where S: AsRef<str> {
+        let mut conn = Romst::get_w_connection(db_file.as_ref(), options)?;
+        let reader = Romst::get_data_reader(&conn)?;
+        let game = Romst::get_existing_game(&reader, game_name.as_ref())?;
+        let roms = reader.get_game_entry(&game.name)?.map(|entry| entry.roms).unwrap_or_default();
+        let mut rom = roms.iter().find(|rom| rom.name == rom_name.as_ref()).cloned()
+            .ok_or_else(|| anyhow!("The game `{}` has no rom named `{}`", game.name, rom_name.as_ref()))?;
+        edit.apply(&mut rom)?;
+        if rom.name != rom_name.as_ref() && roms.iter().any(|existing| existing.name == rom.name) {
+            return Err(anyhow!("The game `{}` already has a rom named `{}`", game.name, rom.name));
+        }
+
+        let mut writer = Romst::get_data_writer(&mut conn)?;
+        writer.set_game_rom(&game.name, Some(rom_name.as_ref()), &rom)?;
+        drop(conn);
+        notify::notify(db_file.as_ref(), DbChangeKind::Edited);
+        Ok(rom)
+    }
+
+    pub fn remove_rom<S>(db_file: S, game_name: S, rom_name: S, options: &WriteOptions) -> Result<()> where S: AsRef<str> {
+        let mut conn = Romst::get_w_connection(db_file.as_ref(), options)?;
+        let game = Romst::get_existing_game(&Romst::get_data_reader(&conn)?, game_name.as_ref())?;
+        let mut writer = Romst::get_data_writer(&mut conn)?;
+        if !writer.remove_game_rom(&game.name, rom_name.as_ref())? {
+            return Err(anyhow!("The game `{}` has no rom named `{}`", game.name, rom_name.as_ref()));
+        }
+        drop(conn);
+        notify::notify(db_file.as_ref(), DbChangeKind::Edited);
+        Ok(())
+    }
+
+    fn get_existing_game(reader: &DBReader, game_name: &str) -> Result<Game> {
+        reader.get_game(game_name).ok_or_else(|| anyhow!("The game `{}` is not in the database", game_name))
+    }
+
+    /// The parent has to be in the database, and not be a clone of the game
+    fn check_parent(reader: &DBReader, game: &Game) -> Result<()> {
+        if let Some(clone_of) = &game.clone_of {
+            let parent = Romst::get_existing_game(reader, clone_of)?;
+            if parent.clone_of.as_ref() == Some(&game.name) {
+                return Err(anyhow!("`{}` is already a clone of `{}`", parent.name, game.name));
+            }
+        }
+
+        Ok(())
     }
 
     pub fn save_report<S>(output_file: S, report: ScanReport) -> Result<()> where S: AsRef<str> {
         let encoded: Vec<u8> = bincode::serialize(&report)?;
-        let mut file = File::create(output_file.as_ref())?;
-        file.write_all(&encoded)?;
+        atomic::write(&output_file.as_ref(), encoded)?;
 
         Ok(())
     }
@@ -0,0 +1,45 @@
+use std::sync::{Mutex, mpsc::{self, Receiver, Sender}};
+use serde::{Deserialize, Serialize};
+
+static SUBSCRIBERS: Mutex<Vec<Sender<DbChange>>> = Mutex::new(Vec::new());
+
+/// What was written to a database
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DbChangeKind {
+    /// A DAT was imported, into a new database or over an existing one
+    Imported,
+    /// A new database was created with a subset of the games of another one
+    Exported,
+    Merged,
+    /// A DAT and its games were removed
+    Purged,
+    /// The roms not used by any game were removed
+    Pruned,
+    Migrated,
+    /// Games or roms were added, changed or removed by hand
+    Edited,
+    /// Exclusions, tags or notes
+    UserData,
+    /// A report was stored in the history
+    ReportRecorded,
+}
+
+/// Sent to the subscribers after a database is written, so views of it can be refreshed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DbChange {
+    /// The database as it was given to the command that changed it
+    pub db_file: String,
+    pub kind: DbChangeKind,
+}
+
+/// Every change made from now on in this process is sent to the receiver. Dropping it unsubscribes
+pub fn subscribe() -> Receiver<DbChange> {
+    let (tx, rx) = mpsc::channel();
+    SUBSCRIBERS.lock().unwrap().push(tx);
+    rx
+}
+
+pub(crate) fn notify(db_file: &str, kind: DbChangeKind) {
+    let change = DbChange { db_file: db_file.to_string(), kind };
+    SUBSCRIBERS.lock().unwrap().retain(|subscriber| subscriber.send(change.clone()).is_ok());
+}
@@ -1,18 +1,133 @@
+pub mod paged;
+
+use std::{cell::RefCell, io::{self, Stderr, Write}, str::FromStr};
+use anyhow::{Result, anyhow};
 use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde::Serialize;
 
 use crate::data::{importer::DatImporterReporter, reporter::ReportReporter};
 
+/// How the progress is shown: as a progress bar, or as JSON events in stderr, one per line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProgressFormat {
+    #[default]
+    Bar,
+    Json,
+}
+
+impl FromStr for ProgressFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "bar" => Ok(ProgressFormat::Bar),
+            "json" => Ok(ProgressFormat::Json),
+            _ => Err(anyhow!("Non valid progress format, can be either `bar` or `json`"))
+        }
+    }
+}
+
+/// When the output is colored: only in a terminal and without `NO_COLOR` set, always or never
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            _ => Err(anyhow!("Non valid color mode, can be either `auto`, `always` or `never`"))
+        }
+    }
+}
+
+impl ColorMode {
+    /// Enables or disables the colors of everything printed, reports and progress bars included
+    pub fn apply(&self) {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|no_color| !no_color.is_empty());
+        console::set_colors_enabled(self.colors_enabled(no_color, console::colors_enabled()));
+        console::set_colors_enabled_stderr(self.colors_enabled(no_color, console::colors_enabled_stderr()));
+    }
+
+    /// In auto mode the terminal decides, `terminal_colors` is false when the output is piped
+    fn colors_enabled(&self, no_color: bool, terminal_colors: bool) -> bool {
+        match self {
+            ColorMode::Auto => !no_color && terminal_colors,
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ImportProgressEvent<'a> {
+    phase: &'a str,
+    bytes: u64,
+    total_bytes: u64,
+    entries: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct ReportProgressEvent<'a> {
+    phase: &'a str,
+    current: &'a str,
+    processed: usize,
+    total: usize,
+    added: usize,
+    directories: usize,
+    ignored: usize,
+    errors: usize,
+}
+
+fn write_event(output: &RefCell<impl Write>, event: &impl Serialize) {
+    if let Ok(line) = serde_json::to_string(event) {
+        let _ = writeln!(output.borrow_mut(), "{}", line);
+    }
+}
+
+/// The JSON events are written to `W`, stderr unless set with `with_output`
 #[derive(Debug)]
-pub struct DatImporterReporterSysOut {
+pub struct DatImporterReporterSysOut<W: Write = Stderr> {
     progress_bar: ProgressBar,
     entries: u32,
+    format: ProgressFormat,
+    bytes: u64,
+    total_bytes: u64,
+    output: RefCell<W>,
 }
 
 impl DatImporterReporterSysOut {
     pub fn new() -> Self { 
-        let progress_bar = ProgressBar::new_spinner();
-        progress_bar.set_draw_target(ProgressDrawTarget::stdout());
-        Self { progress_bar, entries: 0 }
+        DatImporterReporterSysOut::with_format(ProgressFormat::Bar)
+    }
+
+    pub fn with_format(format: ProgressFormat) -> Self {
+        DatImporterReporterSysOut::with_output(format, io::stderr())
+    }
+}
+
+impl<W: Write> DatImporterReporterSysOut<W> {
+    pub fn with_output(format: ProgressFormat, output: W) -> Self {
+        let progress_bar = match format {
+            ProgressFormat::Bar => ProgressBar::new_spinner(),
+            ProgressFormat::Json => ProgressBar::hidden()
+        };
+        if format == ProgressFormat::Bar {
+            progress_bar.set_draw_target(ProgressDrawTarget::stdout());
+        }
+        Self { progress_bar, entries: 0, format, bytes: 0, total_bytes: 0, output: RefCell::new(output) }
+    }
+
+    fn write_event(&self, phase: &str) {
+        write_event(&self.output, &ImportProgressEvent { phase, bytes: self.bytes, total_bytes: self.total_bytes, entries: self.entries });
     }
 }
 
@@ -22,8 +137,13 @@ impl Default for DatImporterReporterSysOut {
     }
 }
 
-impl DatImporterReporter for DatImporterReporterSysOut {
+impl<W: Write> DatImporterReporter for DatImporterReporterSysOut<W> {
     fn set_total_bytes(&mut self, total_bytes: u64) {
+        self.total_bytes = total_bytes;
+        if self.format == ProgressFormat::Json {
+            self.write_event("start");
+            return;
+        }
         self.progress_bar.set_length(total_bytes);
         self.progress_bar.set_style(ProgressStyle::default_bar()
             .template("{spinner:.green} [{elapsed_precise}] [{bar:40.green/blue}] {bytes}/{total_bytes} ({eta}) | {msg}")
@@ -32,23 +152,40 @@ impl DatImporterReporter for DatImporterReporterSysOut {
 
     fn update_position(&mut self, current_bytes: u64, new_entries: u32) {
         self.entries += new_entries;
+        if self.format == ProgressFormat::Json {
+            // There is an update for each entry, so there is only an event for each percent read
+            let percent = |bytes: u64| (bytes * 100).checked_div(self.total_bytes).unwrap_or_default();
+            let changed = percent(current_bytes) != percent(self.bytes);
+            self.bytes = current_bytes;
+            if changed {
+                self.write_event("progress");
+            }
+            return;
+        }
 
         self.progress_bar.set_position(current_bytes);
         self.progress_bar.set_message(&format!("Entries: #{}", self.entries));
     }
 
     fn start_finish(&self) {
+        if self.format == ProgressFormat::Json {
+            return self.write_event("finishing");
+        }
         self.progress_bar.finish_at_current_pos();
         self.progress_bar.set_message("Finishing, hold on...");
     }
 
     fn finish(&self) {
+        if self.format == ProgressFormat::Json {
+            return self.write_event("finished");
+        }
         self.progress_bar.set_message(&format!("Entries: #{}", self.entries));
         self.progress_bar.finish_with_message(&format!("Total Entries #{}", self.entries));
     }
 }
 
-pub struct ReportReporterSysOut {
+/// The JSON events are written to `W`, stderr unless set with `with_output`
+pub struct ReportReporterSysOut<W: Write = Stderr> {
     progress_bar: ProgressBar,
     total_files: usize,
     current_files: usize,
@@ -57,19 +194,50 @@ pub struct ReportReporterSysOut {
     ignored: usize,
     error: usize,
     current_file: String,
+    format: ProgressFormat,
+    output: RefCell<W>,
 }
 
 impl ReportReporterSysOut {
     pub fn new() -> Self {
-        let progress_bar = ProgressBar::new(!0);
+        ReportReporterSysOut::with_format(ProgressFormat::Bar)
+    }
+
+    pub fn with_format(format: ProgressFormat) -> Self {
+        ReportReporterSysOut::with_output(format, io::stderr())
+    }
+}
+
+impl<W: Write> ReportReporterSysOut<W> {
+    pub fn with_output(format: ProgressFormat, output: W) -> Self {
+        let progress_bar = match format {
+            ProgressFormat::Bar => ProgressBar::new(!0),
+            ProgressFormat::Json => ProgressBar::hidden()
+        };
         progress_bar.set_style(ProgressStyle::default_bar()
             .template("{prefix}\n{spinner:.green} [{elapsed_precise}] [{bar:40.green/blue}] {pos}% ({eta}) | {msg}")
             .progress_chars("#>-"));
         progress_bar.set_prefix("P: Processed / D: Directories / I: Ignored");
-        Self { progress_bar, total_files: !0, current_files: 0, new_files: 0, directories: 0, ignored: 0, error: 0, current_file: String::new() }
+        Self { progress_bar, total_files: !0, current_files: 0, new_files: 0, directories: 0, ignored: 0, error: 0, current_file: String::new(), format, output: RefCell::new(output) }
+    }
+
+    fn write_event(&self, phase: &str) {
+        write_event(&self.output, &ReportProgressEvent {
+            phase,
+            current: &self.current_file,
+            processed: self.current_files,
+            total: self.total_files,
+            added: self.new_files,
+            directories: self.directories,
+            ignored: self.ignored,
+            errors: self.error
+        });
     }
 
     fn update_info_numbers(&mut self) {
+        if self.format == ProgressFormat::Json {
+            return self.write_event("progress");
+        }
         self.progress_bar.set_prefix(&format!("P: Processed / D: Directories / I: Ignored | {}", self.current_file));
         self.progress_bar.set_message(&format!("P: {} / D: {} / I: {} / E: {}", self.new_files, self.directories, self.ignored, self.error));
     }
@@ -81,15 +249,21 @@ impl Default for ReportReporterSysOut {
     }
 }
 
-impl ReportReporter for ReportReporterSysOut {
+impl<W: Write> ReportReporter for ReportReporterSysOut<W> {
     fn set_total_files(&mut self, total_files: usize) {
         self.total_files = total_files;
+        if self.format == ProgressFormat::Json {
+            return self.write_event("start");
+        }
         self.progress_bar.set_length(100);
     }
 
     fn update_report_new_file(&mut self, new_file: &str) {
         self.current_file = new_file.to_string();
         self.current_files += 1;
+        if self.format == ProgressFormat::Json {
+            return self.write_event("file");
+        }
         let current_progress = (self.current_files * 100) / self.total_files;
         self.progress_bar.set_position(current_progress as u64);
     }
@@ -115,7 +289,71 @@ impl ReportReporter for ReportReporterSysOut {
     }
 
     fn finish(&mut self) {
+        if self.format == ProgressFormat::Json {
+            return self.write_event("finished");
+        }
         self.progress_bar.set_prefix("P: Processed / D: Directories / I: Ignored / E: Errors | FINISHED");
         self.progress_bar.finish_with_message(&format!("P: {} / D: {} / I: {} / E: {}", self.new_files, self.directories, self.ignored, self.error));
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_color_disables_the_auto_mode() {
+        assert!(ColorMode::Auto.colors_enabled(false, true));
+        assert!(!ColorMode::Auto.colors_enabled(true, true));
+        assert!(!ColorMode::Auto.colors_enabled(false, false));
+        assert!(ColorMode::Always.colors_enabled(true, false));
+        assert!(!ColorMode::Never.colors_enabled(false, true));
+    }
+
+    fn get_events(output: &RefCell<Vec<u8>>) -> Vec<serde_json::Value> {
+        output.borrow().split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn writes_the_import_progress_as_json_lines() {
+        let mut reporter = DatImporterReporterSysOut::with_output(ProgressFormat::Json, vec![]);
+        reporter.set_total_bytes(200);
+        reporter.update_position(2, 1);
+        reporter.update_position(3, 1);
+        reporter.update_position(200, 3);
+        reporter.start_finish();
+        reporter.finish();
+
+        // The second update is in the same percent as the first one, without an event
+        let events = get_events(&reporter.output);
+        let phases = events.iter().map(|event| event["phase"].as_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(phases, ["start", "progress", "progress", "finishing", "finished"]);
+        assert_eq!(events[0], serde_json::json!({ "phase": "start", "bytes": 0, "total_bytes": 200, "entries": 0 }));
+        assert_eq!(events[2], serde_json::json!({ "phase": "progress", "bytes": 200, "total_bytes": 200, "entries": 5 }));
+        assert_eq!(events[4], serde_json::json!({ "phase": "finished", "bytes": 200, "total_bytes": 200, "entries": 5 }));
+    }
+
+    #[test]
+    fn writes_the_report_progress_as_json_lines() {
+        let mut reporter = ReportReporterSysOut::with_output(ProgressFormat::Json, vec![]);
+        reporter.set_total_files(2);
+        reporter.update_report_new_file("pacman.zip");
+        reporter.update_report_new_added_file(1);
+        reporter.update_report_new_file("notes.txt");
+        reporter.update_report_ignored(1);
+        reporter.finish();
+
+        let events = get_events(&reporter.output);
+        let phases = events.iter().map(|event| event["phase"].as_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(phases, ["start", "file", "progress", "file", "progress", "finished"]);
+        assert_eq!(events[2], serde_json::json!({
+            "phase": "progress", "current": "pacman.zip", "processed": 1, "total": 2, "added": 1, "directories": 0, "ignored": 0, "errors": 0
+        }));
+        assert_eq!(events[5], serde_json::json!({
+            "phase": "finished", "current": "notes.txt", "processed": 2, "total": 2, "added": 1, "directories": 0, "ignored": 1, "errors": 0
+        }));
+    }
+}
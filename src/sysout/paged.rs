@@ -0,0 +1,53 @@
+use std::io::{self, Write};
+
+/// A range of the lines of a result set, the first page is 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    pub number: usize,
+    pub size: usize,
+}
+
+impl Page {
+    pub fn new(number: usize, size: usize) -> Self {
+        Self { number: number.max(1), size: size.max(1) }
+    }
+
+    /// The first line of the page
+    pub fn start(&self) -> usize {
+        (self.number - 1) * self.size
+    }
+
+    /// How many pages the lines take
+    pub fn count(&self, lines: usize) -> usize {
+        lines.div_ceil(self.size).max(1)
+    }
+}
+
+/// A result set that can be too big to format at once. Only the lines written are formatted, so a page of tens of
+/// thousands of entries can be shown, or the lines can be written as they are needed while scrolling
+pub trait PagedDisplay {
+    /// How many lines it takes, without formatting them
+    fn line_count(&self) -> usize;
+
+    /// Writes `count` lines from `start`, or until the last one. Returns how many were written
+    fn write_lines(&self, out: &mut dyn Write, start: usize, count: usize) -> io::Result<usize>;
+
+    fn write_page(&self, out: &mut dyn Write, page: Page) -> io::Result<usize> {
+        self.write_lines(out, page.start(), page.size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_the_pages() {
+        let page = Page::new(3, 10);
+        assert_eq!(20, page.start());
+        assert_eq!(3, page.count(21));
+        assert_eq!(2, page.count(20));
+        assert_eq!(1, page.count(0));
+        assert_eq!(Page::new(1, 1), Page::new(0, 0));
+    }
+}
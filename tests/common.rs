@@ -0,0 +1,21 @@
+#![allow(dead_code)]
+
+use std::{fs, path::PathBuf};
+
+use anyhow::Result;
+use romst::{Romst, WriteOptions, sysout::DatImporterReporterSysOut};
+
+/// Path for a file in a temporary directory for this test run
+pub fn temp_file(name: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("romst_test_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path: PathBuf = dir.join(name);
+    path.to_string_lossy().to_string()
+}
+
+/// Imports the test DAT into a new database, returns the database path
+pub fn import_test_dat(name: &str) -> Result<String> {
+    let db = temp_file(name);
+    Romst::import_dat("testdata/test.dat".to_string(), db.clone(), true, None, &WriteOptions::default(), None::<DatImporterReporterSysOut>)?;
+    Ok(db)
+}
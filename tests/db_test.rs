@@ -0,0 +1,244 @@
+use std::fs;
+
+use anyhow::Result;
+use romst::{DbChangeKind, DiagnosisStatus, DuplicateKind, GameEdit, GameFilter, PatternKind, RomEdit, Romst, RomsetMode, ScanOptions, WriteOptions, sysout::DatImporterReporterSysOut};
+
+mod common;
+
+#[test]
+fn merges_databases() -> Result<()> {
+    let db = common::import_test_dat("merge_source.rst")?;
+    let subset = common::temp_file("merge_subset.rst");
+    Romst::export_subset(db.clone(), subset.clone(), vec!["game4".to_string()], None, true, &WriteOptions::default())?;
+    let merged = common::temp_file("merge_dest.rst");
+
+    let source_info = Romst::get_db_info(db.clone())?;
+    let merged_info = Romst::merge_dbs(vec![subset, db], merged, true, &WriteOptions::default())?;
+
+    assert_eq!(2, merged_info.dats);
+    assert_eq!(source_info.games, merged_info.games);
+    assert_eq!(source_info.roms, merged_info.roms);
+
+    Ok(())
+}
+
+#[test]
+fn purges_a_dat() -> Result<()> {
+    let db = common::import_test_dat("purge_source.rst")?;
+    let subset1 = common::temp_file("purge_subset1.rst");
+    let subset2 = common::temp_file("purge_subset2.rst");
+    Romst::export_subset(db.clone(), subset1.clone(), vec!["game2".to_string()], None, true, &WriteOptions::default())?;
+    Romst::export_subset(db, subset2.clone(), vec!["game4".to_string()], None, true, &WriteOptions::default())?;
+    let merged = common::temp_file("purge_merged.rst");
+    Romst::merge_dbs(vec![subset1, subset2.clone()], merged.clone(), true, &WriteOptions::default())?;
+
+    let dats = Romst::get_dats(merged.clone())?.dats;
+    assert_eq!(2, dats.len());
+    let removed = Romst::purge_dat(merged.clone(), dats[0].id.unwrap(), &WriteOptions::default())?;
+
+    assert_eq!(1, removed);
+    let purged_info = Romst::get_db_info(merged)?;
+    let subset2_info = Romst::get_db_info(subset2)?;
+    assert_eq!(1, purged_info.dats);
+    assert_eq!(subset2_info.games, purged_info.games);
+    assert_eq!(subset2_info.roms, purged_info.roms);
+
+    Ok(())
+}
+
+#[test]
+fn diagnoses_a_database() -> Result<()> {
+    let db = common::import_test_dat("doctor.rst")?;
+    let report = Romst::doctor(db.clone(), None);
+    assert!(!report.has_errors());
+    assert_eq!(report.get("Indexes").unwrap().status, DiagnosisStatus::Ok);
+
+    rusqlite::Connection::open(&db)?.execute_batch("DROP INDEX roms_sha1;")?;
+    let report = Romst::doctor(db, None);
+    let indexes = report.get("Indexes").unwrap();
+    assert_eq!(indexes.status, DiagnosisStatus::Warning);
+    assert!(indexes.detail.contains("roms_sha1"));
+
+    let report = Romst::doctor(common::temp_file("doctor_missing.rst"), None);
+    assert!(report.has_errors());
+
+    Ok(())
+}
+
+#[test]
+fn records_removed_and_renamed_games() -> Result<()> {
+    let db = common::import_test_dat("changes.rst")?;
+    let dat = fs::read_to_string("testdata/test.dat")?;
+    let game3_start = dat.find("<machine name=\"game3\"").unwrap();
+    let game3_end = game3_start + dat[game3_start..].find("</machine>").unwrap() + "</machine>".len();
+    let updated_dat = format!("{}{}", &dat[..game3_start], &dat[game3_end..])
+        .replace("<version>0.1</version>", "<version>0.2</version>")
+        .replace("<machine name=\"game2\">", "<machine name=\"game2r\">");
+    let updated_file = common::temp_file("changes.dat");
+    fs::write(&updated_file, updated_dat)?;
+    Romst::import_dat(updated_file, db.clone(), true, None, &WriteOptions::default(), None::<DatImporterReporterSysOut>)?;
+
+    let changes = Romst::get_game_changes(db.clone(), None)?.changes;
+    assert_eq!(2, changes.len());
+    assert_eq!("game2", changes[0].game_name);
+    assert_eq!(Some("game2r".to_string()), changes[0].successor);
+    assert_eq!(Some("0.1".to_string()), changes[0].last_version);
+    assert_eq!(Some("0.2".to_string()), changes[0].removed_in);
+    assert_eq!("game3", changes[1].game_name);
+    assert_eq!(None, changes[1].successor);
+    assert_eq!(2, Romst::get_game_changes(db.clone(), Some("0.1".to_string()))?.changes.len());
+    assert!(Romst::get_game_changes(db, Some("0.2".to_string()))?.changes.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn finds_the_dat_in_other_databases() -> Result<()> {
+    let db = common::import_test_dat("duplicates.rst")?;
+    let new_db = common::temp_file("duplicates_new.rst");
+    let duplicates = Romst::find_duplicate_dats("testdata/test.dat".to_string(), new_db.clone())?;
+    let duplicate = duplicates.iter().find(|duplicate| duplicate.db == db).unwrap();
+    assert_eq!(DuplicateKind::Identical, duplicate.kind);
+    assert!(Romst::find_duplicate_dats("testdata/test.dat".to_string(), db.clone())?.iter().all(|duplicate| duplicate.db != db));
+
+    let dat = fs::read_to_string("testdata/test.dat")?;
+    let newer_file = common::temp_file("duplicates_newer.dat");
+    fs::write(&newer_file, dat.replace("<version>0.1</version>", "<version>0.2</version>"))?;
+    let duplicates = Romst::find_duplicate_dats(newer_file, new_db.clone())?;
+    assert_eq!(DuplicateKind::OlderVersion, duplicates.iter().find(|duplicate| duplicate.db == db).unwrap().kind);
+
+    let older_file = common::temp_file("duplicates_older.dat");
+    fs::write(&older_file, dat.replace("<version>0.1</version>", "<version>0.0.1</version>"))?;
+    assert!(Romst::find_duplicate_dats(older_file, new_db)?.iter().all(|duplicate| duplicate.db != db));
+
+    Ok(())
+}
+
+#[test]
+fn notifies_the_changes() -> Result<()> {
+    let changes = Romst::subscribe_changes();
+    let db = common::import_test_dat("notify_source.rst")?;
+    let subset = common::temp_file("notify_subset.rst");
+    Romst::export_subset(db.clone(), subset.clone(), vec!["game2".to_string()], None, true, &WriteOptions::default())?;
+    Romst::add_tag(db.clone(), vec!["game1".to_string()], "favourite".to_string(), &WriteOptions::default())?;
+    let dat_id = Romst::get_dats(db.clone())?.dats[0].id.unwrap();
+    Romst::purge_dat(db.clone(), dat_id, &WriteOptions::default())?;
+
+    // Other tests change their own databases at the same time
+    let received = changes.try_iter()
+        .filter(|change| change.db_file == db || change.db_file == subset)
+        .map(|change| (change.db_file, change.kind))
+        .collect::<Vec<_>>();
+    assert_eq!(vec![
+        (db.clone(), DbChangeKind::Imported),
+        (subset.clone(), DbChangeKind::Exported),
+        (db.clone(), DbChangeKind::UserData),
+        (db, DbChangeKind::Purged),
+    ], received);
+
+    Ok(())
+}
+
+#[test]
+fn resolves_old_game_names() -> Result<()> {
+    let db = common::import_test_dat("aliases.rst")?;
+    let updated_dat = fs::read_to_string("testdata/test.dat")?.replace("<machine name=\"game2\">", "<machine name=\"game2r\">");
+    let updated_file = common::temp_file("aliases.dat");
+    fs::write(&updated_file, updated_dat)?;
+    Romst::import_dat(updated_file, db.clone(), true, None, &WriteOptions::default(), None::<DatImporterReporterSysOut>)?;
+
+    let aliases = Romst::get_game_aliases(db.clone())?.aliases;
+    assert_eq!(1, aliases.len());
+    assert_eq!(("game2", "game2r"), (aliases[0].alias.as_str(), aliases[0].game_name.as_str()));
+    assert_eq!("game2r", Romst::get_game_info(db.clone(), "game2".to_string(), RomsetMode::Split)?.game.name);
+    let found = Romst::search_games(db.clone(), &GameFilter::new("game2", PatternKind::Glob)?)?.games;
+    assert_eq!(vec!["game2r".to_string()], found.into_iter().map(|game| game.name).collect::<Vec<_>>());
+    let set_report = Romst::verify_set(db.clone(), "game2".to_string(), "testdata/split/game2.zip", RomsetMode::Split, &ScanOptions::default())?;
+    assert_eq!("game2r", set_report.reference.get_name());
+    assert!(set_report.roms_missing.is_empty());
+
+    let list_file = common::temp_file("aliases.txt");
+    fs::write(&list_file, "# old new\nold1 game1\n")?;
+    assert_eq!(1, Romst::import_game_aliases(db.clone(), list_file, &WriteOptions::default())?);
+    assert_eq!("game1", Romst::get_game_info(db, "old1".to_string(), RomsetMode::Split)?.game.name);
+
+    Ok(())
+}
+
+#[test]
+fn verifies_all_the_systems() -> Result<()> {
+    let db = common::import_test_dat("verify_all.rst")?;
+    let roms = common::temp_file("verify_all_roms");
+    fs::create_dir_all(&roms)?;
+    fs::copy("testdata/split/game1.zip", format!("{}/game1.zip", roms))?;
+    let strict_roms = common::temp_file("verify_all_strict_roms");
+    fs::create_dir_all(&strict_roms)?;
+    fs::copy("testdata/split/game1.zip", format!("{}/game1.zip", strict_roms))?;
+    let profiles_file = common::temp_file("verify_all.json");
+    fs::write(&profiles_file, format!(r#"{{
+        "profiles": {{
+            "arcade": {{ "db": "{}", "mode": "split", "checks": "any", "skippers": "builtin" }},
+            "strict": {{ "db": "{}", "mode": "split", "checks": "sha1", "skippers": "verify_all_missing_skippers" }},
+            "broken": {{ "db": "missing.rst" }}
+        }},
+        "directories": {{ "verify_all_roms": "arcade", "verify_all_strict_roms": "strict" }}
+    }}"#, db, db))?;
+
+    // Each directory is checked with the options of its profile, the skippers of `strict` can't be loaded
+    let reports = Romst::verify_all(profiles_file, None, true, &ScanOptions::default())?;
+    assert_eq!(1, reports.reports.len());
+    assert_eq!(1, reports.failed.len());
+    assert_eq!("strict", reports.failed[0].profile);
+    assert_eq!(2, reports.systems.len());
+    let arcade = reports.systems.iter().find(|system| system.db == db).unwrap();
+    assert_eq!(1, arcade.directories.len());
+    assert_eq!(1, reports.get_total().complete + reports.get_total().fixable);
+
+    Ok(())
+}
+
+#[test]
+fn edits_games_and_exports_them() -> Result<()> {
+    let db = common::import_test_dat("edit.rst")?;
+    let edit = GameEdit { description: Some("New Game".to_string()), ..Default::default() };
+    Romst::add_game(db.clone(), "newgame".to_string(), &edit, None, &WriteOptions::default())?;
+    let rom = RomEdit { name: Some("new.rom".to_string()), size: Some(4), crc: Some("1234abcd".to_string()), ..Default::default() };
+    Romst::add_rom(db.clone(), "newgame".to_string(), &rom, &WriteOptions::default())?;
+    assert!(Romst::add_rom(db.clone(), "newgame".to_string(), &rom, &WriteOptions::default()).is_err());
+    assert!(Romst::remove_game(db.clone(), "game1".to_string(), &WriteOptions::default()).is_err());
+
+    let rename = GameEdit { name: Some("renamed".to_string()), ..Default::default() };
+    Romst::edit_game(db.clone(), "newgame".to_string(), &rename, &WriteOptions::default())?;
+    Romst::edit_rom(db.clone(), "renamed".to_string(), "new.rom".to_string(), &RomEdit { md5: Some("0123456789abcdef0123456789abcdef".to_string()), ..Default::default() }, &WriteOptions::default())?;
+    Romst::remove_rom(db.clone(), "game2".to_string(), "binary1.bin".to_string(), &WriteOptions::default())?;
+    assert_eq!("renamed", Romst::get_game_info(db.clone(), "newgame".to_string(), RomsetMode::Split)?.game.name);
+
+    let dat = common::temp_file("edit.dat");
+    let exported = Romst::export_dat(db.clone(), dat.clone(), None, true)?;
+    let reimported = common::temp_file("edit_reimported.rst");
+    Romst::import_dat(dat, reimported.clone(), true, None, &WriteOptions::default(), None::<DatImporterReporterSysOut>)?;
+    let info = Romst::get_game_info(reimported.clone(), "renamed".to_string(), RomsetMode::Split)?;
+    assert_eq!(Some("New Game".to_string()), info.game.info_description);
+    assert_eq!(Some("0123456789abcdef0123456789abcdef".to_string()), info.roms[0].info.md5);
+    assert_eq!(3, Romst::get_game_info(reimported.clone(), "game2".to_string(), RomsetMode::Split)?.roms.len());
+    assert_eq!(exported, Romst::get_db_info(reimported)?.games as usize);
+
+    Ok(())
+}
+
+#[test]
+fn exports_the_dat_with_the_clones_merged() -> Result<()> {
+    let db = common::import_test_dat("export_merged.rst")?;
+    let dat = common::temp_file("export_merged.dat");
+    Romst::export_dat(db, dat.clone(), Some(RomsetMode::Merged), true)?;
+    let reimported = common::temp_file("export_merged_reimported.rst");
+    Romst::import_dat(dat, reimported.clone(), true, None, &WriteOptions::default(), None::<DatImporterReporterSysOut>)?;
+
+    assert!(Romst::get_game_info(reimported.clone(), "game1a".to_string(), RomsetMode::Split).is_err());
+    let game1 = Romst::get_game_info(reimported, "game1".to_string(), RomsetMode::Split)?;
+    let mut rom_names = game1.roms.iter().map(|rom| rom.name.as_str()).collect::<Vec<_>>();
+    rom_names.sort_unstable();
+    assert_eq!(vec!["binfil1.bin", "rom1.trom", "rom2.trom", "rom3.trom", "rom4.trom", "rom5.trom"], rom_names);
+
+    Ok(())
+}
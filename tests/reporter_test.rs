@@ -1,4 +1,7 @@
+use std::{fs::{self, File}, io::Write, path::Path};
+
 use anyhow::Result;
+use romst::{DeletePolicy, RebuildMode, ReleaseFilter, ReportScope, RomPolicies, Romst, RomsetMode, ScanOptions, WriteOptions, ZipFormat, sysout::{DatImporterReporterSysOut, ReportReporterSysOut}};
 
 mod common;
 
@@ -6,4 +9,218 @@ mod common;
 fn empty_test() -> Result<()> {
 
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn rebuilds_sets_with_the_names_of_the_database() -> Result<()> {
+    let db = common::import_test_dat("rebuild.rst")?;
+    let output = common::temp_file("rebuilt");
+
+    let report = Romst::rebuild(db.clone(), "testdata/wrong".to_string(), output.clone(), RomsetMode::Split, None, RebuildMode::Copy, ZipFormat::Standard, true, false, RomPolicies::default(), DeletePolicy::Delete, &ScanOptions::default())?;
+    assert_eq!(vec!["game1", "game2", "game3"], report.sets.iter().map(|set| set.set_name.as_str()).collect::<Vec<_>>());
+    assert!(!Path::new(&output).join("game2.zip").exists());
+
+    let report = Romst::rebuild(db, "testdata/wrong".to_string(), output.clone(), RomsetMode::Split, None, RebuildMode::Copy, ZipFormat::Standard, false, true, RomPolicies::default(), DeletePolicy::Delete, &ScanOptions::default())?;
+    assert_eq!(vec!["rom2.trom".to_string()], report.sets[0].missing);
+    assert!(report.sets.iter().all(|set| set.error.is_none()));
+    assert_eq!(2, report.unknowns.len());
+    assert!(report.sets.iter().all(|set| set.bytes > 0));
+    assert!(report.verify);
+    assert!(report.sets.iter().all(|set| set.verified));
+
+    let report_file = common::temp_file("rebuilt.json");
+    Romst::save_rebuild_report(&report_file, &report)?;
+    let saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(&report_file)?)?;
+    assert_eq!(saved["sets"].as_array().map(|sets| sets.len()), Some(report.sets.len()));
+    assert_eq!(saved["sets"][0]["bytes"], report.sets[0].bytes);
+    assert_eq!(saved["sets"][0]["verified"], true);
+    fs::remove_file(&report_file)?;
+
+    let game2 = zip::ZipArchive::new(File::open(Path::new(&output).join("game2.zip"))?)?;
+    let mut names = game2.file_names().collect::<Vec<_>>();
+    names.sort_unstable();
+    assert_eq!(vec!["binary1.bin", "binary2.bin", "binary3.bin"], names);
+    assert!(Path::new("testdata/wrong/game2.zip").exists());
+
+    Ok(())
+}
+
+#[test]
+fn rebuilds_only_the_sets_not_excluded_and_with_the_tag() -> Result<()> {
+    let db = common::import_test_dat("rebuild_selection.rst")?;
+    let output = common::temp_file("rebuilt_selection");
+    Romst::add_exclusions(db.clone(), vec!["game1".to_string()], &WriteOptions::default())?;
+    Romst::add_tag(db.clone(), vec!["game2".to_string(), "game1".to_string()], "favorites".to_string(), &WriteOptions::default())?;
+
+    let report = Romst::rebuild(db.clone(), "testdata/wrong".to_string(), output.clone(), RomsetMode::Split, None, RebuildMode::Copy, ZipFormat::Standard, false, false, RomPolicies::default(), DeletePolicy::Delete, &ScanOptions::default())?;
+    assert_eq!(vec!["game2", "game3"], report.sets.iter().map(|set| set.set_name.as_str()).collect::<Vec<_>>());
+    assert!(!Path::new(&output).join("game1.zip").exists());
+    assert!(Path::new(&output).join("game3.zip").exists());
+
+    // The excluded set is left out even with its tag
+    let tagged = common::temp_file("rebuilt_tagged");
+    let report = Romst::rebuild(db, "testdata/wrong".to_string(), tagged.clone(), RomsetMode::Split, Some("favorites".to_string()), RebuildMode::Copy, ZipFormat::Standard, false, false, RomPolicies::default(), DeletePolicy::Delete, &ScanOptions::default())?;
+    assert_eq!(vec!["game2"], report.sets.iter().map(|set| set.set_name.as_str()).collect::<Vec<_>>());
+    assert!(!Path::new(&tagged).join("game1.zip").exists());
+    assert!(!Path::new(&tagged).join("game3.zip").exists());
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn rebuilds_sets_from_a_remote_source() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Serves `testdata/wrong` as an index page, and its files
+    let curl = common::temp_file("fake_curl.sh");
+    fs::write(&curl, r#"#!/bin/sh
+while [ $# -gt 0 ]; do
+    case "$1" in
+        -o) output="$2"; shift 2;;
+        --*) shift;;
+        *) url="$1"; shift;;
+    esac
+done
+name="${url#https://roms.example.com/wrong/}"
+if [ -z "$name" ]; then
+    for file in testdata/wrong/*; do echo "<a href=\"${file##*/}\">${file##*/}</a>"; done
+else
+    cp "testdata/wrong/$name" "$output"
+fi
+"#)?;
+    fs::set_permissions(&curl, fs::Permissions::from_mode(0o755))?;
+    std::env::set_var("ROMST_CURL", &curl);
+
+    let db = common::import_test_dat("rebuild_remote.rst")?;
+    let output = common::temp_file("rebuilt_remote");
+    let source = "https://roms.example.com/wrong".to_string();
+    assert!(Romst::rebuild(db.clone(), source.clone(), output.clone(), RomsetMode::Split, None, RebuildMode::Move, ZipFormat::Standard, false, false, RomPolicies::default(), DeletePolicy::Delete, &ScanOptions::default()).is_err());
+
+    let report = Romst::rebuild(db, source.clone(), output.clone(), RomsetMode::Split, None, RebuildMode::Copy, ZipFormat::Standard, false, true, RomPolicies::default(), DeletePolicy::Delete, &ScanOptions::default())?;
+    assert_eq!(source, report.source);
+    assert_eq!(vec!["game1", "game2", "game3"], report.sets.iter().map(|set| set.set_name.as_str()).collect::<Vec<_>>());
+    assert!(report.sets.iter().all(|set| set.error.is_none() && set.verified));
+    assert!(report.sets.iter().flat_map(|set| set.roms.values()).all(|rom| rom.path.starts_with("https://roms.example.com/wrong/")));
+    assert_eq!(2, report.unknowns.len());
+    assert!(report.unknowns.iter().all(|unknown| unknown.contains("https://roms.example.com/wrong/")));
+    assert!(Path::new(&output).join("game2.zip").exists());
+
+    Ok(())
+}
+
+#[test]
+fn rebuilds_sets_in_the_torrentzip_format() -> Result<()> {
+    let db = common::import_test_dat("torrentzip.rst")?;
+    let output = common::temp_file("torrentzipped");
+    let again = common::temp_file("torrentzipped_again");
+
+    let report = Romst::rebuild(db.clone(), "testdata/wrong".to_string(), output.clone(), RomsetMode::Split, None, RebuildMode::Copy, ZipFormat::TorrentZip, false, false, RomPolicies::default(), DeletePolicy::Delete, &ScanOptions::default())?;
+    assert!(report.sets.iter().all(|set| set.error.is_none()));
+    assert_eq!(ZipFormat::TorrentZip, report.zip_format);
+    Romst::rebuild(db, "testdata/wrong".to_string(), again.clone(), RomsetMode::Split, None, RebuildMode::Copy, ZipFormat::TorrentZip, false, false, RomPolicies::default(), DeletePolicy::Delete, &ScanOptions::default())?;
+
+    let game2_path = Path::new(&output).join("game2.zip");
+    let mut game2 = zip::ZipArchive::new(File::open(&game2_path)?)?;
+    let comment = String::from_utf8(game2.comment().to_vec())?;
+    assert!(comment.starts_with("TORRENTZIPPED-"));
+    assert_eq!(22, comment.len());
+    let mut names = vec![];
+    for i in 0..game2.len() {
+        names.push(game2.by_index(i)?.name().to_string());
+    }
+    assert_eq!(vec!["binary1.bin", "binary2.bin", "binary3.bin"], names);
+    assert_eq!(fs::read(&game2_path)?, fs::read(Path::new(&again).join("game2.zip"))?);
+
+    Ok(())
+}
+
+#[test]
+fn identifies_the_unknown_files_with_other_databases() -> Result<()> {
+    let dir = Path::new(&common::temp_file("identify")).to_path_buf();
+    fs::create_dir_all(dir.join("dbs"))?;
+    fs::create_dir_all(dir.join("roms"))?;
+    let db = dir.join("dbs").join("test.rst").to_string_lossy().to_string();
+    Romst::import_dat("testdata/test.dat".to_string(), db.clone(), true, None, &WriteOptions::default(), None::<DatImporterReporterSysOut>)?;
+    let other_dat = dir.join("other.dat");
+    fs::write(&other_dat, r#"<?xml version="1.0"?>
+<datafile>
+    <header><name>Other</name><description>Nintendo - Game Boy (20240101-000000)</description></header>
+    <game name="Other Game"><description>Other Game</description><rom name="other.gb" size="152" crc="7351def4" sha1="d08bd944b164a124d193cc0b8b9ddbc98f4257fa"/></game>
+</datafile>"#)?;
+    let other_db = dir.join("dbs").join("other.rst").to_string_lossy().to_string();
+    Romst::import_dat(other_dat.to_string_lossy().to_string(), other_db.clone(), true, None, &WriteOptions::default(), None::<DatImporterReporterSysOut>)?;
+
+    let mut archive = zip::ZipWriter::new(File::create(dir.join("roms").join("unknown.zip"))?);
+    archive.start_file("other.bin", zip::write::FileOptions::default())?;
+    archive.write_all(&b"romst identify test".repeat(8))?;
+    archive.start_file("game.nes", zip::write::FileOptions::default())?;
+    archive.write_all(b"NES\x1A\x02\x01")?;
+    archive.start_file("readme.txt", zip::write::FileOptions::default())?;
+    archive.write_all(b"nothing to see")?;
+    archive.finish()?;
+
+    let mut report = Romst::get_report(db.clone(), vec![dir.join("roms")], RomsetMode::NonMerged, ReportScope::Present, None, None, ReleaseFilter::default(), &ScanOptions::default(), None::<ReportReporterSysOut>)?;
+    assert_eq!(2, Romst::identify_unknowns(db, &mut report, &ScanOptions::default())?);
+    let mut identified = report.identified.iter()
+        .map(|identification| (identification.file_name.as_str(), identification.belongs_to.as_str()))
+        .collect::<Vec<_>>();
+    identified.sort_unstable();
+    assert_eq!(vec![("game.nes", "Nintendo - Nintendo Entertainment System"), ("other.bin", "Nintendo - Game Boy (20240101-000000)")], identified);
+
+    Ok(())
+}
+
+#[test]
+fn checks_the_sets_of_a_software_list() -> Result<()> {
+    let dir = Path::new(&common::temp_file("softlist")).to_path_buf();
+    fs::create_dir_all(dir.join("mockcart"))?;
+    fs::copy("testdata/split/game2.zip", dir.join("mockcart").join("game2.zip"))?;
+    let db = dir.join("mockcart.rst").to_string_lossy().to_string();
+    Romst::import_dat("testdata/softlist.xml".to_string(), db.clone(), true, None, &WriteOptions::default(), None::<DatImporterReporterSysOut>)?;
+
+    let report = Romst::get_report(db, vec![dir.join("mockcart")], RomsetMode::Split, ReportScope::Present, None, None, ReleaseFilter::default(), &ScanOptions::default(), None::<ReportReporterSysOut>)?;
+    assert_eq!(vec!["game2"], report.sets.keys().map(String::as_str).collect::<Vec<_>>());
+    assert!(report.sets["game2"].roms_missing.is_empty());
+    assert_eq!(3, report.sets["game2"].roms_available.len());
+
+    Ok(())
+}
+
+#[test]
+fn matches_headered_dumps_with_the_skippers() -> Result<()> {
+    let dir = Path::new(&common::temp_file("skippers")).to_path_buf();
+    fs::create_dir_all(dir.join("roms"))?;
+    let dat = dir.join("headerless.dat");
+    fs::write(&dat, r#"<?xml version="1.0"?>
+<datafile>
+    <header><name>Nintendo - Nintendo Entertainment System (Headerless)</name><description>Headerless</description></header>
+    <game name="Mock Game (World)"><description>Mock Game (World)</description><rom name="Mock Game (World).nes" size="256" crc="db9a29be" sha1="3be64d4a2ed16f97e8fbd886578fe4da174fa52e"/></game>
+</datafile>"#)?;
+    let db = dir.join("headerless.rst").to_string_lossy().to_string();
+    Romst::import_dat(dat.to_string_lossy().to_string(), db.clone(), true, None, &WriteOptions::default(), None::<DatImporterReporterSysOut>)?;
+
+    let mut rom = b"NES\x1A\x02\x01".to_vec();
+    rom.resize(16, 0);
+    rom.extend((0..32u8).cycle().take(256));
+    let mut archive = zip::ZipWriter::new(File::create(dir.join("roms").join("Mock Game (World).zip"))?);
+    archive.start_file("Mock Game (World).nes", zip::write::FileOptions::default())?;
+    archive.write_all(&rom)?;
+    archive.finish()?;
+
+    let report = Romst::get_report(db.clone(), vec![dir.join("roms")], RomsetMode::Split, ReportScope::Present, None, None, ReleaseFilter::default(), &ScanOptions::default(), None::<ReportReporterSysOut>)?;
+    assert!(report.sets["Mock Game (World)"].roms_available.is_empty());
+
+    let mut options = ScanOptions::default();
+    assert!(options.set_header_skippers(None)?.contains(&"Nintendo Famicom/NES".to_string()));
+    let report = Romst::get_report(db.clone(), vec![dir.join("roms")], RomsetMode::Split, ReportScope::Present, None, None, ReleaseFilter::default(), &options, None::<ReportReporterSysOut>)?;
+    let set = &report.sets["Mock Game (World)"];
+    assert!(set.roms_missing.is_empty());
+    assert_eq!(1, set.roms_available.len());
+    assert!(set.unknown.is_empty());
+
+    // The skippers are only used by the checks they are given to
+    let report = Romst::get_report(db, vec![dir.join("roms")], RomsetMode::Split, ReportScope::Present, None, None, ReleaseFilter::default(), &ScanOptions::default(), None::<ReportReporterSysOut>)?;
+    assert!(report.sets["Mock Game (World)"].roms_available.is_empty());
+
+    Ok(())
+}